@@ -0,0 +1,70 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+
+/**
+ * @title RngVerifier
+ * @dev Checks operator attestations in the layout of the operator's `abi`
+ * module: the operator signs
+ *
+ *     keccak256(abi.encode(requestId, randomness, salt, expiry, operator))
+ *
+ * for `ecrecover`, as `r || s || v` with `v` 27 or 28. `requestId` is the
+ * task ID when it is a uint256, and the keccak256 of the task ID otherwise.
+ * `expiry` is in Unix seconds; 0 means the attestation never expires.
+ * `GET /conformance/abi` on any operator serves a fixed example to check an
+ * integration against.
+ */
+contract RngVerifier {
+    /**
+     * @dev The digest the operator signs
+     */
+    function digest(
+        uint256 requestId,
+        bytes calldata randomness,
+        bytes32 salt,
+        uint64 expiry,
+        address operator
+    ) public pure returns (bytes32) {
+        return keccak256(abi.encode(requestId, randomness, salt, expiry, operator));
+    }
+
+    /**
+     * @dev Returns the address that signed the attestation, or the zero
+     * address if the signature is malformed
+     */
+    function recover(
+        uint256 requestId,
+        bytes calldata randomness,
+        bytes32 salt,
+        uint64 expiry,
+        address operator,
+        bytes calldata signature
+    ) public pure returns (address) {
+        if (signature.length != 65) {
+            return address(0);
+        }
+        bytes32 r = bytes32(signature[0:32]);
+        bytes32 s = bytes32(signature[32:64]);
+        uint8 v = uint8(signature[64]);
+        return ecrecover(digest(requestId, randomness, salt, expiry, operator), v, r, s);
+    }
+
+    /**
+     * @dev Reverts unless `operator` signed the attestation and it has not
+     * expired
+     */
+    function verify(
+        uint256 requestId,
+        bytes calldata randomness,
+        bytes32 salt,
+        uint64 expiry,
+        address operator,
+        bytes calldata signature
+    ) external view returns (bool) {
+        require(expiry == 0 || block.timestamp <= expiry, "Attestation expired");
+        address signer = recover(requestId, randomness, salt, expiry, operator, signature);
+        require(signer != address(0) && signer == operator, "Invalid operator signature");
+        return true;
+    }
+}
@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+import {Test} from "../lib/forge-std/src/Test.sol";
+import {RngVerifier} from "../src/RngVerifier.sol";
+
+/**
+ * @title RngVerifierTest
+ * @dev Checks the verifier against the fixture served at
+ * `GET /conformance/abi`, which the operator's `abi` module pins too
+ */
+contract RngVerifierTest is Test {
+    uint256 constant PRIVATE_KEY = 0x6a0e12dc38b0cf55558fdda6ba5cf3bd1da9a64ba59f3407eb180fa7e4f82fa9;
+    address constant OPERATOR = 0x4C14B4a1D3A456bd93dF7deC0e3626Fe7Dc45FA4;
+    bytes32 constant DIGEST = 0xe62d31ce30cd5597a905979db7df55e94403ca74cfd574ff071ea9ec45c9ff5c;
+
+    bytes constant ENCODED =
+        hex"000000000000000000000000000000000000000000000000000000000000002a"
+        hex"00000000000000000000000000000000000000000000000000000000000000a0"
+        hex"af0266ebd204ece1c6d90e0208c0b098b72b9f739a1a6451fa3a7bb83837cede"
+        hex"000000000000000000000000000000000000000000000000000000006553f100"
+        hex"0000000000000000000000004c14b4a1d3a456bd93df7dec0e3626fe7dc45fa4"
+        hex"0000000000000000000000000000000000000000000000000000000000000020"
+        hex"0445349e8d2aeccbbe6ee06ce0ff65c2e80ed9c20d0da8e2f3e5c9b7d75232e4";
+
+    bytes constant SIGNATURE =
+        hex"cb25afaed188c49cc7aa3527bf5e0376759be82085c167b42b219097f091c0ad"
+        hex"1f11e9501a65463f2662a08848e65ada25cadcd94887a3f9dbc20567ffba210a1b";
+
+    RngVerifier verifier;
+
+    function setUp() public {
+        verifier = new RngVerifier();
+    }
+
+    function decoded()
+        internal
+        pure
+        returns (uint256 requestId, bytes memory randomness, bytes32 salt, uint64 expiry, address operator)
+    {
+        return abi.decode(ENCODED, (uint256, bytes, bytes32, uint64, address));
+    }
+
+    function test_FixtureDecodes() public pure {
+        (uint256 requestId, bytes memory randomness, bytes32 salt, uint64 expiry, address operator) = decoded();
+        assertEq(requestId, 42);
+        assertEq(randomness.length, 32);
+        assertEq(salt, 0xaf0266ebd204ece1c6d90e0208c0b098b72b9f739a1a6451fa3a7bb83837cede);
+        assertEq(expiry, 1_700_000_000);
+        assertEq(operator, OPERATOR);
+        assertEq(abi.encode(requestId, randomness, salt, expiry, operator), ENCODED);
+        assertEq(keccak256(ENCODED), DIGEST);
+    }
+
+    function test_FixtureKeyIsTheOperator() public pure {
+        assertEq(vm.addr(PRIVATE_KEY), OPERATOR);
+    }
+
+    function test_FixtureVerifies() public {
+        (uint256 requestId, bytes memory randomness, bytes32 salt, uint64 expiry, address operator) = decoded();
+        vm.warp(expiry);
+        assertEq(verifier.digest(requestId, randomness, salt, expiry, operator), DIGEST);
+        assertEq(verifier.recover(requestId, randomness, salt, expiry, operator, SIGNATURE), OPERATOR);
+        assertTrue(verifier.verify(requestId, randomness, salt, expiry, operator, SIGNATURE));
+    }
+
+    function test_RevertWhen_RandomnessIsTampered() public {
+        (uint256 requestId, bytes memory randomness, bytes32 salt, uint64 expiry, address operator) = decoded();
+        vm.warp(expiry);
+        randomness[0] ^= 0x01;
+        vm.expectRevert("Invalid operator signature");
+        verifier.verify(requestId, randomness, salt, expiry, operator, SIGNATURE);
+    }
+
+    function test_RevertWhen_Expired() public {
+        (uint256 requestId, bytes memory randomness, bytes32 salt, uint64 expiry, address operator) = decoded();
+        vm.warp(uint256(expiry) + 1);
+        vm.expectRevert("Attestation expired");
+        verifier.verify(requestId, randomness, salt, expiry, operator, SIGNATURE);
+    }
+}
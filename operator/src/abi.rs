@@ -0,0 +1,347 @@
+// src/abi.rs
+
+//! Attestations in the ABI layout of the companion Solidity verifier.
+//!
+//! `contracts/src/RngVerifier.sol` checks a value with
+//!
+//! ```text
+//! keccak256(abi.encode(requestId, randomness, salt, expiry, operator))
+//! ```
+//!
+//! signed for `ecrecover` by `operator`, as `(uint256, bytes, bytes32,
+//! uint64, address)`. [`AbiAttestation`] produces exactly those bytes from an
+//! attestation, so integrators need not rebuild the layout themselves. The
+//! request ID is the task ID when it is a decimal or `0x`-prefixed uint256,
+//! and the Keccak-256 of the task ID otherwise. `expiry` is the end of the
+//! validity window in Unix seconds, or 0 for an attestation without one.
+//!
+//! With `signing.abi` enabled every outcome carries the encoding, its digest
+//! and the signature in [`AbiOutcome`], always hex like Ethereum addresses.
+//! [`fixture`] is a fixed example, signed with a fixed key, for checking an
+//! integration against; `GET /conformance/abi` serves it.
+
+use k256::ecdsa::SigningKey as Secp256k1Key;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+
+use crate::attester::{self, AttestationPayload};
+use crate::chain;
+use crate::tasks::TaskOutcome;
+
+/// Types of the encoded tuple, in order.
+pub const TYPES: &str = "(uint256,bytes,bytes32,uint64,address)";
+/// Words before `randomness`, which is the only dynamic member.
+const HEAD_WORDS: usize = 5;
+
+const FIXTURE_KEY_DOMAIN: &[u8] = b"othentic-rng/abi/v1/key";
+
+/// The members of the encoded tuple.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbiAttestation {
+    pub request_id: [u8; 32],
+    pub randomness: Vec<u8>,
+    pub salt: [u8; 32],
+    /// Unix seconds; 0 when the attestation never expires.
+    pub expiry: u64,
+    pub operator: [u8; 20],
+}
+
+impl AbiAttestation {
+    /// The tuple for `payload`, attested as task `task_id` by the Ethereum
+    /// address `operator`.
+    pub fn from_payload(
+        task_id: &str,
+        payload: &AttestationPayload,
+        operator: [u8; 20],
+    ) -> Result<Self, String> {
+        let salt = payload
+            .salt
+            .as_slice()
+            .try_into()
+            .map_err(|_| "the ABI layout needs a 32-byte salt".to_string())?;
+        Ok(AbiAttestation {
+            request_id: request_id(task_id),
+            randomness: payload.random_number.clone(),
+            salt,
+            expiry: payload.validity.map_or(0, |v| v.expires_at / 1000),
+            operator,
+        })
+    }
+
+    /// The tuple of a served outcome, whose secp256k1 address is the operator.
+    pub fn from_outcome(outcome: &TaskOutcome) -> Result<Self, String> {
+        let address = outcome
+            .secp256k1_address
+            .as_deref()
+            .ok_or("the ABI layout needs a secp256k1 address")?;
+        let operator = hex::decode(address.trim_start_matches("0x"))
+            .ok()
+            .and_then(|bytes| <[u8; 20]>::try_from(bytes).ok())
+            .ok_or_else(|| format!("Invalid secp256k1 address {}", address))?;
+        let attestation = outcome.to_attestation()?;
+        Self::from_payload(&outcome.task_id, &attestation.payload, operator)
+    }
+
+    /// `abi.encode(requestId, randomness, salt, expiry, operator)`.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(32 * (HEAD_WORDS + 1) + self.randomness.len() + 31);
+        data.extend_from_slice(&self.request_id);
+        data.extend_from_slice(&word((32 * HEAD_WORDS) as u64));
+        data.extend_from_slice(&self.salt);
+        data.extend_from_slice(&word(self.expiry));
+        data.extend_from_slice(&[0u8; 12]);
+        data.extend_from_slice(&self.operator);
+        data.extend_from_slice(&word(self.randomness.len() as u64));
+        data.extend_from_slice(&self.randomness);
+        data.resize(data.len() + (32 - self.randomness.len() % 32) % 32, 0);
+        data
+    }
+
+    /// Parses what [`AbiAttestation::encode`] produces, refusing any other
+    /// layout: a different offset, dirty padding or trailing bytes.
+    pub fn decode(data: &[u8]) -> Result<Self, String> {
+        let word_at = |n: usize| -> Result<&[u8], String> {
+            data.get(32 * n..32 * (n + 1))
+                .ok_or_else(|| "ABI data is truncated".to_string())
+        };
+        let small = |n: usize, what: &str| -> Result<u64, String> {
+            let w = word_at(n)?;
+            if w[..24].iter().any(|&b| b != 0) {
+                return Err(format!("ABI {} does not fit in 64 bits", what));
+            }
+            Ok(u64::from_be_bytes(w[24..].try_into().expect("8 bytes")))
+        };
+        if small(1, "offset")? != (32 * HEAD_WORDS) as u64 {
+            return Err("ABI randomness is not where the layout puts it".to_string());
+        }
+        let operator = word_at(4)?;
+        if operator[..12].iter().any(|&b| b != 0) {
+            return Err("ABI operator is not a 20-byte address".to_string());
+        }
+        let length = small(HEAD_WORDS, "randomness length")? as usize;
+        let start = 32 * (HEAD_WORDS + 1);
+        let padded = length.div_ceil(32) * 32;
+        if data.len() != start + padded {
+            return Err(format!(
+                "ABI data is {} bytes, not the {} its randomness length implies",
+                data.len(),
+                start + padded
+            ));
+        }
+        if data[start + length..].iter().any(|&b| b != 0) {
+            return Err("ABI randomness padding is not zero".to_string());
+        }
+        Ok(AbiAttestation {
+            request_id: word_at(0)?.try_into().expect("32 bytes"),
+            randomness: data[start..start + length].to_vec(),
+            salt: word_at(2)?.try_into().expect("32 bytes"),
+            expiry: small(3, "expiry")?,
+            operator: operator[12..].try_into().expect("20 bytes"),
+        })
+    }
+
+    /// Keccak-256 of the encoding; what the operator signs.
+    pub fn digest(&self) -> [u8; 32] {
+        Keccak256::digest(self.encode()).into()
+    }
+
+    /// Checks that `signature` over the digest recovers to the operator.
+    pub fn verify(&self, signature: &[u8; 65]) -> Result<(), String> {
+        if attester::recover_address(&self.digest(), signature)? != self.operator {
+            return Err("ABI signature recovers to a different address".to_string());
+        }
+        Ok(())
+    }
+
+    /// The hex form attached to outcomes, with `signature` over the digest.
+    pub fn to_outcome(&self, signature: &[u8; 65]) -> AbiOutcome {
+        AbiOutcome {
+            encoded: hex::encode(self.encode()),
+            digest: hex::encode(self.digest()),
+            signature: hex::encode(signature),
+        }
+    }
+}
+
+/// The ABI form of an outcome; see [`AbiAttestation`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbiOutcome {
+    /// Hex `abi.encode(...)` of the tuple.
+    pub encoded: String,
+    /// Hex Keccak-256 of `encoded`.
+    pub digest: String,
+    /// Hex `r || s || v` over `digest`, `v` being 27 or 28.
+    pub signature: String,
+}
+
+impl AbiOutcome {
+    /// Checks that this is the ABI form of `outcome`, signed by its
+    /// secp256k1 address.
+    pub fn verify(&self, outcome: &TaskOutcome) -> Result<(), String> {
+        let expected = AbiAttestation::from_outcome(outcome)?;
+        let encoded = hex::decode(&self.encoded).map_err(|e| format!("Invalid ABI hex: {}", e))?;
+        if AbiAttestation::decode(&encoded)? != expected {
+            return Err("ABI encoding does not match the attestation".to_string());
+        }
+        if self.digest != hex::encode(expected.digest()) {
+            return Err("ABI digest is not the Keccak-256 of the encoding".to_string());
+        }
+        let signature: [u8; 65] = hex::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or("ABI signature must be 65 hex bytes")?;
+        expected.verify(&signature)
+    }
+}
+
+/// `requestId` of task `task_id`.
+pub fn request_id(task_id: &str) -> [u8; 32] {
+    chain::parse_uint256(task_id).unwrap_or_else(|_| Keccak256::digest(task_id).into())
+}
+
+/// The body of `GET /conformance/abi`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fixture {
+    pub types: String,
+    /// Hex secp256k1 private key the fixture is signed with; it signs
+    /// nothing else.
+    pub private_key: String,
+    pub task_id: String,
+    pub request_id: String,
+    pub randomness: String,
+    pub salt: String,
+    pub expiry: u64,
+    pub operator: String,
+    #[serde(flatten)]
+    pub abi: AbiOutcome,
+}
+
+/// A fixed tuple and its encoding, digest and signature. RFC 6979 makes the
+/// signature deterministic, so the fixture is the same on every operator.
+pub fn fixture() -> Fixture {
+    let key_bytes: [u8; 32] = Sha256::digest(FIXTURE_KEY_DOMAIN).into();
+    let key = Secp256k1Key::from_slice(&key_bytes).expect("fixture key is a valid scalar");
+    let task_id = "42";
+    let tuple = AbiAttestation {
+        request_id: request_id(task_id),
+        randomness: Sha256::digest(b"othentic-rng/abi/v1/randomness").to_vec(),
+        salt: Sha256::digest(b"othentic-rng/abi/v1/salt").into(),
+        expiry: 1_700_000_000,
+        operator: attester::ethereum_address(key.verifying_key()),
+    };
+    let signature = attester::sign_recoverable(&key, &tuple.digest()).expect("fixture signs");
+    Fixture {
+        types: TYPES.to_string(),
+        private_key: hex::encode(key_bytes),
+        task_id: task_id.to_string(),
+        request_id: hex::encode(tuple.request_id),
+        randomness: hex::encode(&tuple.randomness),
+        salt: hex::encode(tuple.salt),
+        expiry: tuple.expiry,
+        operator: format!("0x{}", hex::encode(tuple.operator)),
+        abi: tuple.to_outcome(&signature),
+    }
+}
+
+/// `value` right-aligned in a 32-byte word.
+fn word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `abi.encode` of the fixture, as `contracts/test/RngVerifier.t.sol`
+    /// decodes it.
+    const FIXTURE_ENCODED: &str = concat!(
+        "000000000000000000000000000000000000000000000000000000000000002a",
+        "00000000000000000000000000000000000000000000000000000000000000a0",
+        "af0266ebd204ece1c6d90e0208c0b098b72b9f739a1a6451fa3a7bb83837cede",
+        "000000000000000000000000000000000000000000000000000000006553f100",
+        "0000000000000000000000004c14b4a1d3a456bd93df7dec0e3626fe7dc45fa4",
+        "0000000000000000000000000000000000000000000000000000000000000020",
+        "0445349e8d2aeccbbe6ee06ce0ff65c2e80ed9c20d0da8e2f3e5c9b7d75232e4",
+    );
+    const FIXTURE_SIGNATURE: &str = concat!(
+        "cb25afaed188c49cc7aa3527bf5e0376759be82085c167b42b219097f091c0ad",
+        "1f11e9501a65463f2662a08848e65ada25cadcd94887a3f9dbc20567ffba210a1b",
+    );
+
+    fn tuple(randomness: Vec<u8>) -> AbiAttestation {
+        AbiAttestation {
+            request_id: request_id("42"),
+            randomness,
+            salt: [7; 32],
+            expiry: 1_700_000_000,
+            operator: [9; 20],
+        }
+    }
+
+    #[test]
+    fn fixture_matches_the_pinned_encoding() {
+        let fixture = fixture();
+        assert_eq!(fixture.abi.encoded, FIXTURE_ENCODED);
+        assert_eq!(fixture.abi.signature, FIXTURE_SIGNATURE);
+        assert_eq!(
+            fixture.abi.digest,
+            "e62d31ce30cd5597a905979db7df55e94403ca74cfd574ff071ea9ec45c9ff5c"
+        );
+        assert_eq!(
+            fixture.operator,
+            "0x4c14b4a1d3a456bd93df7dec0e3626fe7dc45fa4"
+        );
+
+        let decoded = AbiAttestation::decode(&hex::decode(FIXTURE_ENCODED).unwrap()).unwrap();
+        assert_eq!(hex::encode(decoded.encode()), FIXTURE_ENCODED);
+        assert_eq!(hex::encode(decoded.request_id), fixture.request_id);
+        assert_eq!(decoded.expiry, fixture.expiry);
+        let signature: [u8; 65] = hex::decode(FIXTURE_SIGNATURE).unwrap().try_into().unwrap();
+        decoded.verify(&signature).unwrap();
+
+        let mut tampered = decoded;
+        tampered.randomness[0] ^= 1;
+        assert!(tampered.verify(&signature).is_err());
+    }
+
+    #[test]
+    fn request_ids() {
+        assert_eq!(request_id("42")[31], 42);
+        assert_eq!(request_id("0x2a"), request_id("42"));
+        assert_eq!(
+            request_id("task-1"),
+            <[u8; 32]>::from(Keccak256::digest("task-1"))
+        );
+    }
+
+    #[test]
+    fn encoding_round_trips_at_every_padding() {
+        for length in [0, 1, 31, 32, 33, 64, 100] {
+            let tuple = tuple(vec![0xab; length]);
+            let encoded = tuple.encode();
+            assert_eq!(encoded.len() % 32, 0);
+            assert_eq!(AbiAttestation::decode(&encoded).unwrap(), tuple);
+        }
+    }
+
+    #[test]
+    fn other_layouts_are_refused() {
+        let encoded = tuple(vec![0xab; 5]).encode();
+        let refused = |edit: &dyn Fn(&mut Vec<u8>)| {
+            let mut data = encoded.clone();
+            edit(&mut data);
+            AbiAttestation::decode(&data).is_err()
+        };
+        assert!(refused(&|d| d[63] = 0xc0));
+        assert!(refused(&|d| d[100] = 1));
+        assert!(refused(&|d| d[130] = 1));
+        assert!(refused(&|d| d[32 * 6 + 5] = 1));
+        assert!(refused(&|d| d.extend_from_slice(&[0; 32])));
+        assert!(refused(&|d| d.truncate(32 * 6)));
+    }
+}
@@ -3,50 +3,1045 @@ use ed25519_dalek::{
     SigningKey, VerifyingKey,   
 };
 
+use k256::ecdsa::{
+    RecoveryId, Signature as EcdsaSignature,
+    SigningKey as Secp256k1Key, VerifyingKey as Secp256k1PublicKey,
+};
+use k256::schnorr::{
+    Signature as SchnorrSignature, SigningKey as SchnorrKey, VerifyingKey as SchnorrPublicKey,
+};
+use log::error;
 use rand::rngs::OsRng; 
 use rand::RngCore; 
-use sha2::{Sha256, Digest}; 
+use sha2::{Sha256, Sha512, Digest}; 
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha3::Keccak256;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::drand::{self, DrandBeacon};
+use crate::epochs::EpochCertificate;
+use crate::performer::RngPerformer;
+use crate::storage::Storage;
+use crate::vdf::{self, VdfProof};
+use crate::vrf::{self, VrfProof};
+
+/// Domain tag prefixed to the encoding of extended (v2) payloads.
+pub const PAYLOAD_DOMAIN: &[u8] = b"othentic-rng/attestation/v2";
+
+/// Field identifiers used in the v2 payload encoding.
+const FIELD_RANDOM_NUMBER: u8 = 0x01;
+const FIELD_SALT: u8 = 0x02;
+const FIELD_CLIENT_ENTROPY: u8 = 0x03;
+const FIELD_OPERATOR_ENTROPY: u8 = 0x04;
+const FIELD_CHAIN: u8 = 0x05;
+const FIELD_KIND: u8 = 0x06;
+const FIELD_VDF: u8 = 0x07;
+const FIELD_SHARE: u8 = 0x08;
+const FIELD_DRAND: u8 = 0x09;
+const FIELD_VRF: u8 = 0x0a;
+const FIELD_VALIDITY: u8 = 0x0b;
+const FIELD_COUNTER: u8 = 0x0c;
+const FIELD_METADATA: u8 = 0x0d;
+const FIELD_SLOT: u8 = 0x0e;
+const FIELD_DOMAIN: u8 = 0x0f;
+const FIELD_EPOCH: u8 = 0x10;
+const FIELD_ENCLAVE: u8 = 0x11;
+const FIELD_DEFINITION: u8 = 0x12;
+const FIELD_HASH: u8 = 0x13;
+const FIELD_POSTPROCESS: u8 = 0x14;
+
+/// Domain tag of the report data an enclave quote carries.
+pub const ENCLAVE_REPORT_DOMAIN: &[u8] = b"othentic-rng/enclave-key/v1";
+
+/// Collection recording every salt signed by an attester with a nonce store.
+pub const USED_SALTS: &str = "used_salts";
+/// Collection holding the next attestation counter value.
+pub const NONCE_STATE: &str = "nonce_state";
+
+/// Position of a payload within a chained stream of attested chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainLink {
+    /// Zero-based chunk index.
+    pub index: u64,
+    /// Total number of bytes in the stream this chunk belongs to.
+    pub total_len: u64,
+    /// Payload digest of the previous chunk (a fixed genesis value for chunk 0).
+    pub previous: [u8; 32],
+}
+
+impl ChainLink {
+    fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(48);
+        data.extend_from_slice(&self.index.to_be_bytes());
+        data.extend_from_slice(&self.total_len.to_be_bytes());
+        data.extend_from_slice(&self.previous);
+        data
+    }
+}
+
+/// Identifies a payload as one Shamir share of a split secret.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShareInfo {
+    /// Evaluation point of the share, between 1 and `total`.
+    pub index: u8,
+    /// Number of shares needed to reconstruct the secret.
+    pub threshold: u8,
+    /// Number of shares the secret was split into.
+    pub total: u8,
+    /// Blinded hash commitment to the secret, shared by every share of the set.
+    pub commitment: [u8; 32],
+}
+
+impl ShareInfo {
+    fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(35);
+        data.push(self.index);
+        data.push(self.threshold);
+        data.push(self.total);
+        data.extend_from_slice(&self.commitment);
+        data
+    }
+}
+
+/// Window, in Unix milliseconds, during which an attestation may be consumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Validity {
+    pub not_before: u64,
+    pub expires_at: u64,
+}
+
+impl Validity {
+    fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(16);
+        data.extend_from_slice(&self.not_before.to_be_bytes());
+        data.extend_from_slice(&self.expires_at.to_be_bytes());
+        data
+    }
+
+    /// Checks that `now` falls inside the window, allowing `skew` of clock
+    /// drift on either side.
+    pub fn check(&self, now: u64, skew: Duration) -> Result<(), String> {
+        let skew = skew.as_millis() as u64;
+        if now.saturating_add(skew) < self.not_before {
+            return Err(format!("Attestation is not valid before {}", self.not_before));
+        }
+        if now > self.expires_at.saturating_add(skew) {
+            return Err(format!("Attestation expired at {}", self.expires_at));
+        }
+        Ok(())
+    }
+}
+
+/// Clock drift tolerated by default when checking a validity window.
+pub const DEFAULT_CLOCK_SKEW: Duration = Duration::from_secs(30);
+
+/// Source of the current time for validity checks.
+pub trait Clock {
+    /// Current Unix time in milliseconds.
+    fn now_millis(&self) -> u64;
+}
+
+/// Hash function a payload is digested with before signing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlg {
+    #[default]
+    Sha256,
+    /// For consumers that check the secp256k1 signature with `ecrecover`
+    /// over a Keccak-256 digest.
+    Keccak256,
+}
+
+impl HashAlg {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HashAlg::Sha256 => "sha256",
+            HashAlg::Keccak256 => "keccak256",
+        }
+    }
+}
+
+/// The system wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// How and by what software a value was produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorMetadata {
+    /// Version of the operator software.
+    pub version: String,
+    /// Signatures the attestation carries, e.g. `"ed25519+secp256k1"`.
+    pub signing_scheme: String,
+    /// Where the randomness came from, in derivation order (`"os"`, `"vrf"`,
+    /// `"client"`, `"drand"`).
+    pub entropy_sources: Vec<String>,
+    /// Digest of the operator's running configuration, secrets masked.
+    pub config_hash: [u8; 32],
+    pub chain_id: u64,
+}
+
+impl OperatorMetadata {
+    fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        push_field(&mut data, 0x01, self.version.as_bytes());
+        push_field(&mut data, 0x02, self.signing_scheme.as_bytes());
+        for source in &self.entropy_sources {
+            push_field(&mut data, 0x03, source.as_bytes());
+        }
+        push_field(&mut data, 0x04, &self.config_hash);
+        push_field(&mut data, 0x05, &self.chain_id.to_be_bytes());
+        data
+    }
+}
+
+/// A drand round mixed into the random number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrandRound {
+    /// Hash identifying the drand chain the round belongs to.
+    pub chain_hash: [u8; 32],
+    pub beacon: DrandBeacon,
+    /// The operator's contribution before mixing (may be empty).
+    pub local: Vec<u8>,
+}
+
+impl DrandRound {
+    fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        push_field(&mut data, 0x01, &self.chain_hash);
+        push_field(&mut data, 0x02, &self.beacon.round.to_be_bytes());
+        push_field(&mut data, 0x03, &self.beacon.signature);
+        push_field(&mut data, 0x04, &self.beacon.previous_signature);
+        push_field(&mut data, 0x05, &self.local);
+        data
+    }
+}
+
+/// Everything covered by an attestation signature.
+///
+/// A payload carrying only `random_number` and `salt` is digested exactly as
+/// `attest` always has (`SHA-256(random_number || salt)`), so plain
+/// attestations stay verifiable by existing consumers. As soon as an extension
+/// field is present the payload switches to the v2 encoding: a domain tag
+/// followed by `(field id, u32 length, bytes)` records in field-id order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttestationPayload {
+    pub random_number: Vec<u8>,
+    pub salt: Vec<u8>,
+    /// Caller-supplied entropy mixed into `random_number`.
+    pub client_entropy: Option<Vec<u8>>,
+    /// The operator's contribution before mixing; present with `client_entropy`.
+    pub operator_entropy: Option<Vec<u8>>,
+    /// Set when the payload is one chunk of a chained stream.
+    pub chain: Option<ChainLink>,
+    /// How `random_number` is meant to be interpreted (e.g. `"uuid-v4"`).
+    pub kind: Option<String>,
+    /// Delay-function evaluation `random_number` was derived from.
+    pub vdf: Option<VdfProof>,
+    /// Set when `random_number` is one share of a secret-shared value.
+    pub share: Option<ShareInfo>,
+    /// drand round `random_number` was mixed with.
+    pub drand: Option<DrandRound>,
+    /// VRF evaluation `random_number` was derived from, when the output is
+    /// deterministic in the request.
+    pub vrf: Option<VrfProof>,
+    /// When the attestation may be consumed; outside it the value is stale.
+    pub validity: Option<Validity>,
+    /// Position in the attester's monotonic sequence of signatures.
+    pub counter: Option<u64>,
+    /// Describes the operator that produced the value.
+    pub metadata: Option<OperatorMetadata>,
+    /// Pool slot a pre-generated value was reserved for; such a value is only
+    /// usable together with a binding signature naming the task it served
+    /// (see [`crate::pool`]).
+    pub slot: Option<u64>,
+    /// Signing context of the tenant the value was produced for, so that it
+    /// cannot be replayed to another tenant (see [`crate::tenants`]).
+    pub domain: Option<String>,
+    /// Key epoch of the signer (see [`crate::epochs`]); set by the attester.
+    pub epoch: Option<u64>,
+    /// SHA-256 of the quote of the enclave holding the signing key (see
+    /// [`SigningBackend::quote`]); set by the attester.
+    pub enclave: Option<[u8; 32]>,
+    /// Task definition the value was produced under (see [`crate::definitions`]).
+    pub definition: Option<String>,
+    /// Digest algorithm, when not SHA-256.
+    pub hash: Option<HashAlg>,
+    /// IDs of the post-processing stages applied to the operator's entropy,
+    /// in order (see [`crate::postprocess`]).
+    pub postprocess: Vec<String>,
+}
+
+impl AttestationPayload {
+    /// Starts a payload for `random_number`; the attester fills in the salt.
+    pub fn new(random_number: Vec<u8>) -> Self {
+        AttestationPayload {
+            random_number,
+            ..Default::default()
+        }
+    }
+
+    /// Mixes `client_entropy` into the random number, recording both inputs.
+    pub fn with_client_entropy(mut self, client_entropy: Vec<u8>) -> Self {
+        let operator_entropy = std::mem::take(&mut self.random_number);
+        self.random_number = RngPerformer::mix_client_entropy(&operator_entropy, &client_entropy);
+        self.operator_entropy = Some(operator_entropy);
+        self.client_entropy = Some(client_entropy);
+        self
+    }
+
+    /// Marks the payload as chunk `link.index` of a chained stream.
+    pub fn with_chain(mut self, link: ChainLink) -> Self {
+        self.chain = Some(link);
+        self
+    }
+
+    /// Runs the current random number through the VDF and replaces it with
+    /// randomness derived from the VDF output; the proof is kept in the payload.
+    pub fn with_vdf(mut self, iterations: u64) -> Result<Self, String> {
+        let proof = vdf::evaluate(&self.random_number, iterations)?;
+        self.random_number = proof.randomness(self.random_number.len());
+        self.vdf = Some(proof);
+        Ok(self)
+    }
+
+    /// Mixes the randomness of a verified drand round into the random number,
+    /// recording the round and the local contribution.
+    pub fn with_drand(mut self, chain_hash: [u8; 32], beacon: DrandBeacon) -> Self {
+        let local = std::mem::take(&mut self.random_number);
+        self.random_number = drand::mix(&beacon.randomness(), &local);
+        self.drand = Some(DrandRound {
+            chain_hash,
+            beacon,
+            local,
+        });
+        self
+    }
+
+    /// Marks the payload as share `share.index` of a split secret.
+    pub fn with_share(mut self, share: ShareInfo) -> Self {
+        self.share = Some(share);
+        self
+    }
+
+    /// Limits the window during which the attestation may be consumed.
+    pub fn with_validity(mut self, not_before: u64, expires_at: u64) -> Self {
+        self.validity = Some(Validity { not_before, expires_at });
+        self
+    }
+
+    /// Records how and by which operator the value was produced.
+    pub fn with_metadata(mut self, metadata: OperatorMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Reserves the payload for pool slot `slot`.
+    pub fn with_slot(mut self, slot: u64) -> Self {
+        self.slot = Some(slot);
+        self
+    }
+
+    /// Binds the payload to the signing context `domain`.
+    pub fn with_domain(mut self, domain: &str) -> Self {
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    /// Tags the payload with the output format its random number follows.
+    pub fn with_kind(mut self, kind: &str) -> Self {
+        self.kind = Some(kind.to_string());
+        self
+    }
+
+    /// Records the task definition the payload was produced under.
+    pub fn with_definition(mut self, id: &str) -> Self {
+        self.definition = Some(id.to_string());
+        self
+    }
+
+    /// Digests the payload with `hash`; SHA-256 is the default and is not
+    /// recorded.
+    pub fn with_hash(mut self, hash: HashAlg) -> Self {
+        self.hash = (hash != HashAlg::Sha256).then_some(hash);
+        self
+    }
+
+    /// Records the post-processing stages the operator's entropy went through.
+    pub fn with_postprocess(mut self, stages: Vec<String>) -> Self {
+        self.postprocess = stages;
+        self
+    }
+
+    fn is_extended(&self) -> bool {
+        self.client_entropy.is_some()
+            || self.operator_entropy.is_some()
+            || self.chain.is_some()
+            || self.kind.is_some()
+            || self.vdf.is_some()
+            || self.share.is_some()
+            || self.drand.is_some()
+            || self.vrf.is_some()
+            || self.validity.is_some()
+            || self.counter.is_some()
+            || self.metadata.is_some()
+            || self.slot.is_some()
+            || self.domain.is_some()
+            || self.epoch.is_some()
+            || self.enclave.is_some()
+            || self.definition.is_some()
+            || self.hash.is_some()
+            || !self.postprocess.is_empty()
+    }
+
+    /// Returns the bytes that are hashed and signed.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.random_number.len() + 128);
+        self.write_to(&mut data);
+        data
+    }
+
+    /// SHA-256 (or the payload's [`HashAlg`]) of [`AttestationPayload::encode`],
+    /// hashed as it is encoded so the random number is never copied.
+    pub fn digest(&self) -> [u8; 32] {
+        match self.hash.unwrap_or_default() {
+            HashAlg::Sha256 => {
+                let mut hasher = Sha256::new();
+                self.write_to(&mut hasher);
+                hasher.finalize().into()
+            }
+            HashAlg::Keccak256 => {
+                let mut hasher = Keccak256::new();
+                self.write_to(&mut hasher);
+                hasher.finalize().into()
+            }
+        }
+    }
+
+    fn write_to(&self, data: &mut impl Sink) {
+        if !self.is_extended() {
+            data.put(&self.random_number);
+            data.put(&self.salt);
+            return;
+        }
+
+        data.put(PAYLOAD_DOMAIN);
+        push_field(data, FIELD_RANDOM_NUMBER, &self.random_number);
+        push_field(data, FIELD_SALT, &self.salt);
+        if let Some(client) = &self.client_entropy {
+            push_field(data, FIELD_CLIENT_ENTROPY, client);
+        }
+        if let Some(operator) = &self.operator_entropy {
+            push_field(data, FIELD_OPERATOR_ENTROPY, operator);
+        }
+        if let Some(chain) = &self.chain {
+            push_field(data, FIELD_CHAIN, &chain.encode());
+        }
+        if let Some(kind) = &self.kind {
+            push_field(data, FIELD_KIND, kind.as_bytes());
+        }
+        if let Some(proof) = &self.vdf {
+            let mut record = Vec::new();
+            push_field(&mut record, 0x01, &proof.seed);
+            push_field(&mut record, 0x02, &proof.iterations.to_be_bytes());
+            push_field(&mut record, 0x03, &proof.output);
+            push_field(&mut record, 0x04, &proof.proof);
+            push_field(data, FIELD_VDF, &record);
+        }
+        if let Some(share) = &self.share {
+            push_field(data, FIELD_SHARE, &share.encode());
+        }
+        if let Some(round) = &self.drand {
+            push_field(data, FIELD_DRAND, &round.encode());
+        }
+        if let Some(proof) = &self.vrf {
+            let mut record = Vec::new();
+            push_field(&mut record, 0x01, &proof.input);
+            push_field(&mut record, 0x02, &proof.proof);
+            push_field(data, FIELD_VRF, &record);
+        }
+        if let Some(validity) = &self.validity {
+            push_field(data, FIELD_VALIDITY, &validity.encode());
+        }
+        if let Some(counter) = self.counter {
+            push_field(data, FIELD_COUNTER, &counter.to_be_bytes());
+        }
+        if let Some(metadata) = &self.metadata {
+            push_field(data, FIELD_METADATA, &metadata.encode());
+        }
+        if let Some(slot) = self.slot {
+            push_field(data, FIELD_SLOT, &slot.to_be_bytes());
+        }
+        if let Some(domain) = &self.domain {
+            push_field(data, FIELD_DOMAIN, domain.as_bytes());
+        }
+        if let Some(epoch) = self.epoch {
+            push_field(data, FIELD_EPOCH, &epoch.to_be_bytes());
+        }
+        if let Some(quote) = &self.enclave {
+            push_field(data, FIELD_ENCLAVE, quote);
+        }
+        if let Some(definition) = &self.definition {
+            push_field(data, FIELD_DEFINITION, definition.as_bytes());
+        }
+        if let Some(hash) = self.hash {
+            push_field(data, FIELD_HASH, hash.as_str().as_bytes());
+        }
+        if !self.postprocess.is_empty() {
+            let mut record = Vec::new();
+            for stage in &self.postprocess {
+                push_field(&mut record, 0x01, stage.as_bytes());
+            }
+            push_field(data, FIELD_POSTPROCESS, &record);
+        }
+    }
+
+}
+
+/// Destination of encoded payload bytes: a buffer, or a hasher fed directly.
+trait Sink {
+    fn put(&mut self, bytes: &[u8]);
+}
+
+impl Sink for Vec<u8> {
+    fn put(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+}
+
+impl Sink for Sha256 {
+    fn put(&mut self, bytes: &[u8]) {
+        Digest::update(self, bytes);
+    }
+}
+
+impl Sink for Keccak256 {
+    fn put(&mut self, bytes: &[u8]) {
+        Digest::update(self, bytes);
+    }
+}
+
+fn push_field(data: &mut impl Sink, id: u8, value: &[u8]) {
+    data.put(&[id]);
+    data.put(&(value.len() as u32).to_be_bytes());
+    data.put(value);
+}
+
+/// A signed [`AttestationPayload`].
+#[derive(Debug, Clone)]
+pub struct Attestation {
+    pub payload: AttestationPayload,
+    pub signature: Signature,
+    /// Recoverable secp256k1 signature over the same digest, `r || s || v`
+    /// with `v` = 27 or 28, as accepted by `ecrecover`.
+    pub secp256k1_signature: Option<[u8; 65]>,
+    /// BIP-340 Schnorr signature over the same digest, checked against the
+    /// signer's x-only public key.
+    pub schnorr_signature: Option<[u8; 64]>,
+}
+
+/// A legacy (v1) attestation that borrows the caller's random number
+/// instead of copying it; see [`RngAttester::attest_borrowed`].
+#[derive(Debug, Clone, Copy)]
+pub struct BorrowedAttestation<'a> {
+    pub random_number: &'a [u8],
+    pub salt: [u8; 32],
+    pub signature: Signature,
+}
+
+/// Names the signatures an attestation carries besides ed25519.
+pub fn signing_scheme(secp256k1: bool, schnorr: bool) -> &'static str {
+    match (secp256k1, schnorr) {
+        (false, false) => "ed25519",
+        (true, false) => "ed25519+secp256k1",
+        (false, true) => "ed25519+schnorr",
+        (true, true) => "ed25519+secp256k1+schnorr",
+    }
+}
+
+/// Ethereum address (last 20 bytes of the Keccak-256 of the public key).
+pub fn ethereum_address(public_key: &Secp256k1PublicKey) -> [u8; 20] {
+    let point = public_key.to_encoded_point(false);
+    let hash = Keccak256::digest(&point.as_bytes()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+/// Signs `digest` for `ecrecover`: `r || s || v`, with `v` 27 or 28.
+pub fn sign_recoverable(key: &Secp256k1Key, digest: &[u8; 32]) -> Result<[u8; 65], String> {
+    let (ecdsa, recovery) = key.sign_prehash_recoverable(digest)
+        .map_err(|e| format!("secp256k1 signing failed: {}", e))?;
+    let mut bytes = [0u8; 65];
+    bytes[..64].copy_from_slice(&ecdsa.to_bytes());
+    bytes[64] = 27 + recovery.to_byte();
+    Ok(bytes)
+}
+
+/// Recovers the Ethereum address that produced `signature` over `digest`.
+pub fn recover_address(digest: &[u8; 32], signature: &[u8; 65]) -> Result<[u8; 20], String> {
+    let ecdsa = EcdsaSignature::from_slice(&signature[..64])
+        .map_err(|e| format!("Invalid secp256k1 signature: {}", e))?;
+    let recovery = signature[64]
+        .checked_sub(27)
+        .and_then(RecoveryId::from_byte)
+        .ok_or("Invalid secp256k1 recovery id")?;
+    let key = Secp256k1PublicKey::recover_from_prehash(digest, &ecdsa, recovery)
+        .map_err(|e| format!("secp256k1 recovery failed: {}", e))?;
+    Ok(ethereum_address(&key))
+}
+
+/// Persistent record of the salts and counter values an attester has used.
+///
+/// Both are written before the signature is produced, so a crash can skip a
+/// counter value but never hand one out twice.
+struct NonceStore {
+    storage: Arc<dyn Storage>,
+    /// Next counter value; the lock also serialises salt checks.
+    next: Mutex<u64>,
+}
+
+impl NonceStore {
+    fn open(storage: Arc<dyn Storage>) -> Result<Self, String> {
+        let next = storage.get(NONCE_STATE, "counter")?
+            .and_then(|state| state["next"].as_u64())
+            .unwrap_or(0);
+        Ok(NonceStore { storage, next: Mutex::new(next) })
+    }
+
+    /// Records `salts` and reserves consecutive counter values for them,
+    /// refusing the lot if any salt has been signed before. One storage flush
+    /// covers the whole batch.
+    fn reserve(&self, salts: &[Vec<u8>]) -> Result<Vec<u64>, String> {
+        let mut next = self.next.lock().expect("nonce lock poisoned");
+        let keys: Vec<String> = salts.iter().map(hex::encode).collect();
+        for (i, key) in keys.iter().enumerate() {
+            if keys[..i].contains(key) || self.storage.get(USED_SALTS, key)?.is_some() {
+                error!("Refusing to reuse salt {}: the attester state may have been rolled back", key);
+                return Err("Salt has already been used; refusing to sign".to_string());
+            }
+        }
+        let first = *next;
+        let end = first + keys.len() as u64;
+        self.storage.put(NONCE_STATE, "counter", json!({ "next": end }))?;
+        for (counter, key) in (first..end).zip(&keys) {
+            self.storage.put(USED_SALTS, key, json!({ "counter": counter }))?;
+        }
+        self.storage.flush()?;
+        *next = end;
+        Ok((first..end).collect())
+    }
+}
+
+/// Hardware evidence that a signing key was generated in, and never leaves,
+/// an enclave: a quote whose report data is [`enclave_report_data`] of the key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnclaveQuote {
+    /// `"sgx"` or `"nitro"`.
+    pub platform: String,
+    /// DCAP quote (SGX) or COSE-signed attestation document (Nitro).
+    pub quote: Vec<u8>,
+}
+
+impl EnclaveQuote {
+    pub fn digest(&self) -> [u8; 32] {
+        Sha256::digest(&self.quote).into()
+    }
+
+    /// Whether the quote carries the report data of `public_key`. The
+    /// vendor's signature on the quote is not checked here.
+    pub fn binds(&self, public_key: &VerifyingKey) -> bool {
+        let expected = enclave_report_data(public_key);
+        self.quote.windows(expected.len()).any(|w| w == expected)
+    }
+}
+
+/// The 64 bytes of report data a quote for `public_key` carries.
+pub fn enclave_report_data(public_key: &VerifyingKey) -> [u8; 64] {
+    Sha512::new()
+        .chain_update(ENCLAVE_REPORT_DOMAIN)
+        .chain_update(public_key.as_bytes())
+        .finalize()
+        .into()
+}
 
+/// Holds the ed25519 attestation key and signs with it.
+///
+/// [`SoftwareKey`] keeps the key in process memory. With the `enclave`
+/// feature, [`crate::enclave::EnclaveKey`] generates and uses it inside an
+/// SGX or Nitro enclave and vouches for it with a quote.
+pub trait SigningBackend: Send + Sync {
+    fn verifying_key(&self) -> VerifyingKey;
+
+    /// Plain Ed25519 signature over `message`.
+    fn sign(&self, message: &[u8]) -> Result<Signature, String>;
+
+    /// Ed25519ph signature over the SHA-512 state `prehash` under `context`.
+    fn sign_prehashed(&self, prehash: Sha512, context: &[u8]) -> Result<Signature, String>;
+
+    /// Evaluates the VRF of [`crate::vrf`] with the key at `input`.
+    fn vrf_prove(&self, input: &[u8]) -> (VrfProof, [u8; 64]);
+
+    /// A new key held the same way.
+    fn rotated(&self) -> Result<Arc<dyn SigningBackend>, String>;
+
+    /// Evidence that the key is held in an enclave, if it is.
+    fn quote(&self) -> Option<&EnclaveQuote> {
+        None
+    }
+}
+
+/// An attestation key in process memory.
+pub struct SoftwareKey(SigningKey);
+
+impl SoftwareKey {
+    pub fn generate() -> Self {
+        SoftwareKey(SigningKey::generate(&mut OsRng))
+    }
+}
+
+impl From<SigningKey> for SoftwareKey {
+    fn from(key: SigningKey) -> Self {
+        SoftwareKey(key)
+    }
+}
+
+impl SigningBackend for SoftwareKey {
+    fn verifying_key(&self) -> VerifyingKey {
+        self.0.verifying_key()
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Signature, String> {
+        Ok(self.0.sign(message))
+    }
+
+    fn sign_prehashed(&self, prehash: Sha512, context: &[u8]) -> Result<Signature, String> {
+        self.0.sign_prehashed(prehash, Some(context))
+            .map_err(|e| format!("Signing failed: {}", e))
+    }
+
+    fn vrf_prove(&self, input: &[u8]) -> (VrfProof, [u8; 64]) {
+        vrf::prove(&self.0.to_bytes(), input)
+    }
+
+    fn rotated(&self) -> Result<Arc<dyn SigningBackend>, String> {
+        Ok(Arc::new(SoftwareKey::generate()))
+    }
+}
 
 pub struct RngAttester {
-    signing_key: SigningKey, 
+    key: Arc<dyn SigningBackend>,
     verifying_key: VerifyingKey, 
+    secp256k1_key: Option<Secp256k1Key>,
+    schnorr_key: Option<SchnorrKey>,
+    certificate: Option<Arc<EpochCertificate>>,
+    nonces: Option<Arc<NonceStore>>,
 }
 
 impl RngAttester {
 
     pub fn new() -> Result<Self, String> {
-        let mut csprng = OsRng; 
+        Ok(Self::with_backend(Arc::new(SoftwareKey::generate())))
+    }
 
-    
-        let signing_key = SigningKey::generate(&mut csprng);
-        let verifying_key = (&signing_key).verifying_key(); 
+    /// An attester signing with the key held by `backend`.
+    pub fn with_backend(backend: Arc<dyn SigningBackend>) -> Self {
+        RngAttester {
+            verifying_key: backend.verifying_key(),
+            key: backend,
+            secp256k1_key: None,
+            schnorr_key: None,
+            certificate: None,
+            nonces: None,
+        }
+    }
+
+    /// Also signs every attestation with the hex-encoded secp256k1 key
+    /// (optionally `0x`-prefixed), typically the operator's Ethereum key.
+    pub fn with_secp256k1_key(mut self, key_hex: &str) -> Result<Self, String> {
+        let bytes = hex::decode(key_hex.trim_start_matches("0x"))
+            .map_err(|e| format!("Invalid secp256k1 key: {}", e))?;
+        let key = Secp256k1Key::from_slice(&bytes)
+            .map_err(|e| format!("Invalid secp256k1 key: {}", e))?;
+        self.secp256k1_key = Some(key);
+        Ok(self)
+    }
+
+    /// Also signs every attestation with BIP-340 Schnorr under the
+    /// hex-encoded secp256k1 key (optionally `0x`-prefixed), for consumers
+    /// that verify against an x-only public key (Taproot, Nostr).
+    pub fn with_schnorr_key(mut self, key_hex: &str) -> Result<Self, String> {
+        let bytes = hex::decode(key_hex.trim_start_matches("0x"))
+            .map_err(|e| format!("Invalid Schnorr key: {}", e))?;
+        let key = SchnorrKey::from_bytes(&bytes)
+            .map_err(|e| format!("Invalid Schnorr key: {}", e))?;
+        self.schnorr_key = Some(key);
+        Ok(self)
+    }
+
+    /// Names the signatures this attester puts on every attestation.
+    pub fn signing_scheme(&self) -> &'static str {
+        signing_scheme(self.secp256k1_key.is_some(), self.schnorr_key.is_some())
+    }
+
+    /// Tracks used salts and a monotonic counter in `storage`: every
+    /// attestation then carries the next counter value, and a salt that was
+    /// signed before is refused, as happens after a state rollback.
+    pub fn with_nonce_store(mut self, storage: Arc<dyn Storage>) -> Result<Self, String> {
+        self.nonces = Some(Arc::new(NonceStore::open(storage)?));
+        Ok(self)
+    }
+
+    /// Returns a fresh ed25519 attester, held by the same kind of backend,
+    /// that keeps this attester's secp256k1 and Schnorr keys, whose address is
+    /// the operator's on-chain identity, and its nonce store, so the counter
+    /// keeps increasing across rotations.
+    pub fn rotated(&self) -> Result<Self, String> {
+        let mut fresh = Self::with_backend(self.key.rotated()?);
+        fresh.secp256k1_key = self.secp256k1_key.clone();
+        fresh.schnorr_key = self.schnorr_key.clone();
+        fresh.nonces = self.nonces.clone();
+        Ok(fresh)
+    }
+
+    /// Like [`RngAttester::rotated`], but signs with the epoch key `key`
+    /// and stamps its epoch on every payload.
+    pub fn for_epoch(&self, key: SigningKey, certificate: EpochCertificate) -> Self {
+        RngAttester {
+            verifying_key: key.verifying_key(),
+            key: Arc::new(SoftwareKey::from(key)),
+            secp256k1_key: self.secp256k1_key.clone(),
+            schnorr_key: self.schnorr_key.clone(),
+            certificate: Some(Arc::new(certificate)),
+            nonces: self.nonces.clone(),
+        }
+    }
 
-        Ok(RngAttester {
-            signing_key,
-            verifying_key,
-        })
+    /// Quote of the enclave holding the key, if it is held in one.
+    pub fn enclave_quote(&self) -> Option<&EnclaveQuote> {
+        self.key.quote()
     }
 
+    /// Certificate of the epoch key this attester signs with, if any.
+    pub fn epoch_certificate(&self) -> Option<&EpochCertificate> {
+        self.certificate.as_deref()
+    }
+
+    /// Ethereum address of the secp256k1 key, if one is configured.
+    pub fn secp256k1_address(&self) -> Option<[u8; 20]> {
+        self.secp256k1_key.as_ref().map(|key| ethereum_address(key.verifying_key()))
+    }
+
+    /// BIP-340 x-only public key of the Schnorr key, if one is configured.
+    pub fn schnorr_public_key(&self) -> Option<[u8; 32]> {
+        self.schnorr_key.as_ref().map(|key| key.verifying_key().to_bytes().into())
+    }
+
+    /// Owned form of [`RngAttester::attest_borrowed`]; copies the random
+    /// number and salt into the returned tuple.
     pub fn attest(
         &self,
         random_number: &[u8],
     ) -> Result<(Vec<u8>, Vec<u8>, Signature), String> {
+        let attestation = self.attest_borrowed(random_number)?;
+        Ok((attestation.random_number.to_vec(), attestation.salt.to_vec(), attestation.signature))
+    }
 
-        let mut salt = vec![0u8; 32];
-        let mut csprng = OsRng;
-        csprng.fill_bytes(&mut salt); 
+    /// Salts and signs `random_number` without copying it: the hash is
+    /// computed incrementally over the caller's buffer and the result borrows it.
+    pub fn attest_borrowed<'a>(&self, random_number: &'a [u8]) -> Result<BorrowedAttestation<'a>, String> {
+        let mut salt = [0u8; 32];
+        OsRng.fill_bytes(&mut salt);
 
+        let hashed_data = Sha256::new()
+            .chain_update(random_number)
+            .chain_update(salt)
+            .finalize();
+        let signature = self.key.sign(&hashed_data)?;
 
-        let mut data_to_hash = Vec::with_capacity(random_number.len() + salt.len());
-        data_to_hash.extend_from_slice(random_number);
-        data_to_hash.extend_from_slice(&salt);
+        Ok(BorrowedAttestation { random_number, salt, signature })
+    }
+
+    /// Starts a payload whose `len`-byte random number is the VRF output for
+    /// `input` under this attester's key, so it is the same on every call.
+    pub fn vrf_payload(&self, input: &[u8], len: usize) -> AttestationPayload {
+        let (proof, output) = self.key.vrf_prove(input);
+        AttestationPayload {
+            random_number: vrf::randomness(&output, len),
+            vrf: Some(proof),
+            ..Default::default()
+        }
+    }
 
-        let hashed_data = Sha256::digest(&data_to_hash);
+    /// Salts and signs `payload`, overwriting any salt (and counter) it
+    /// already carries.
+    pub fn attest_payload(&self, payload: AttestationPayload) -> Result<Attestation, String> {
+        let payload = self.prepare_payloads(vec![payload])?.remove(0);
+        let digest = payload.digest();
+        self.sign_prepared(payload, &digest)
+    }
 
-        let signature = self.signing_key.sign(&hashed_data);
+    /// Salts `payloads` and reserves their counter values; the first half of
+    /// [`RngAttester::attest_payload`].
+    pub(crate) fn prepare_payloads(&self, mut payloads: Vec<AttestationPayload>) -> Result<Vec<AttestationPayload>, String> {
+        let salts: Vec<Vec<u8>> = payloads.iter().map(|_| {
+            let mut salt = vec![0u8; 32];
+            OsRng.fill_bytes(&mut salt);
+            salt
+        }).collect();
+        if let Some(nonces) = &self.nonces {
+            for (payload, counter) in payloads.iter_mut().zip(nonces.reserve(&salts)?) {
+                payload.counter = Some(counter);
+            }
+        }
+        for (payload, salt) in payloads.iter_mut().zip(salts) {
+            payload.salt = salt;
+            payload.epoch = self.certificate.as_ref().map(|c| c.epoch);
+            payload.enclave = self.key.quote().map(EnclaveQuote::digest);
+        }
+        Ok(payloads)
+    }
 
-        Ok((random_number.to_vec(), salt, signature))
+    /// Signs a prepared payload; `digest` must be `payload.digest()`.
+    pub(crate) fn sign_prepared(&self, payload: AttestationPayload, digest: &[u8; 32]) -> Result<Attestation, String> {
+        let signature = self.key.sign(digest)?;
+        let secp256k1_signature = self.sign_ethereum(digest)?;
+        let schnorr_signature = match &self.schnorr_key {
+            Some(key) => {
+                let mut aux = [0u8; 32];
+                OsRng.fill_bytes(&mut aux);
+                let schnorr = key.sign_prehash_with_aux_rand(digest, &aux)
+                    .map_err(|e| format!("Schnorr signing failed: {}", e))?;
+                Some(schnorr.to_bytes())
+            }
+            None => None,
+        };
+        Ok(Attestation { payload, signature, secp256k1_signature, schnorr_signature })
+    }
+
+    /// Signs an arbitrary `digest` with the secp256k1 key, if there is one;
+    /// see [`sign_recoverable`].
+    pub fn sign_ethereum(&self, digest: &[u8; 32]) -> Result<Option<[u8; 65]>, String> {
+        self.secp256k1_key.as_ref().map(|key| sign_recoverable(key, digest)).transpose()
+    }
+
+    /// Checks that the secp256k1 signature on `attestation` recovers to `address`.
+    pub fn verify_secp256k1(address: &[u8; 20], attestation: &Attestation) -> Result<(), String> {
+        let signature = attestation.secp256k1_signature.as_ref()
+            .ok_or("Attestation carries no secp256k1 signature")?;
+        if &recover_address(&attestation.payload.digest(), signature)? != address {
+            return Err("secp256k1 signature recovers to a different address".to_string());
+        }
+        Ok(())
+    }
+
+    /// Checks the BIP-340 signature on `attestation` against the x-only
+    /// `public_key`.
+    pub fn verify_schnorr(public_key: &[u8; 32], attestation: &Attestation) -> Result<(), String> {
+        let signature = attestation.schnorr_signature.as_ref()
+            .ok_or("Attestation carries no Schnorr signature")?;
+        let key = SchnorrPublicKey::from_bytes(public_key)
+            .map_err(|e| format!("Invalid x-only public key: {}", e))?;
+        let signature = SchnorrSignature::try_from(&signature[..])
+            .map_err(|e| format!("Invalid Schnorr signature: {}", e))?;
+        key.verify_raw(&attestation.payload.digest(), &signature)
+            .map_err(|_| "Schnorr signature verification failed".to_string())
+    }
+
+    /// Checks the signature on `attestation` and that every recorded
+    /// derivation step (VDF, drand mixing, client mixing, VRF) really produced
+    /// the random number.
+    ///
+    /// A recorded drand round is only checked for consistency here; use
+    /// [`drand::verify_attestation`] to also check its BLS signature.
+    ///
+    /// This does not check expiry: an attestation outside its signed validity
+    /// window still verifies, so that old draws stay auditable. Consumers
+    /// deciding whether to act on a value must call
+    /// [`RngAttester::verify_with_clock`] instead.
+    pub fn verify(public_key: &VerifyingKey, attestation: &Attestation) -> Result<(), String> {
+        let payload = &attestation.payload;
+
+        // Walk the derivation backwards: VDF output first, then drand, then mixing, then the VRF.
+        let mut source = &payload.random_number;
+        if let Some(proof) = &payload.vdf {
+            if proof.randomness(payload.random_number.len()) != payload.random_number {
+                return Err("Random number does not match the VDF output".to_string());
+            }
+            vdf::verify(proof)?;
+            source = &proof.seed;
+        }
+        if let Some(round) = &payload.drand {
+            if &drand::mix(&round.beacon.randomness(), &round.local) != source {
+                return Err("Random number does not match the drand round".to_string());
+            }
+            source = &round.local;
+        }
+
+        match (&payload.operator_entropy, &payload.client_entropy) {
+            (Some(operator), Some(client)) => {
+                if &RngPerformer::mix_client_entropy(operator, client) != source {
+                    return Err("Random number does not match the mixed contributions".to_string());
+                }
+                source = operator;
+            }
+            (None, None) => {}
+            _ => return Err("Operator and client entropy must be recorded together".to_string()),
+        }
+
+        if let Some(proof) = &payload.vrf {
+            let output = vrf::verify(public_key.as_bytes(), proof)?;
+            if &vrf::randomness(&output, source.len()) != source {
+                return Err("Random number does not match the VRF output".to_string());
+            }
+        }
+
+        public_key.verify(&payload.digest(), &attestation.signature)
+            .map_err(|e| format!("Signature verification failed: {}", e))
+    }
+
+    /// Like [`RngAttester::verify`], and also rejects an attestation whose
+    /// validity window does not contain the current time of `clock`, give or
+    /// take `skew`. Attestations without a window are accepted as before.
+    pub fn verify_with_clock(
+        public_key: &VerifyingKey,
+        attestation: &Attestation,
+        clock: &dyn Clock,
+        skew: Duration,
+    ) -> Result<(), String> {
+        Self::verify(public_key, attestation)?;
+        match &attestation.payload.validity {
+            Some(validity) => validity.check(clock.now_millis(), skew),
+            None => Ok(()),
+        }
+    }
+
+    /// Signs `message` with Ed25519ph under `context`. Such a signature never
+    /// verifies as an attestation signature, so the key can vouch for other
+    /// messages (heartbeats, ...) without them passing as attestations.
+    pub fn sign_with_context(&self, context: &[u8], message: &[u8]) -> Result<Signature, String> {
+        self.key.sign_prehashed(Sha512::new().chain_update(message), context)
+    }
+
+    /// Checks a signature made with [`RngAttester::sign_with_context`].
+    pub fn verify_with_context(
+        public_key: &VerifyingKey,
+        context: &[u8],
+        message: &[u8],
+        signature: &Signature,
+    ) -> Result<(), String> {
+        public_key.verify_prehashed(Sha512::new().chain_update(message), Some(context), signature)
+            .map_err(|e| format!("Signature verification failed: {}", e))
     }
 
     pub fn get_public_key(&self) -> &VerifyingKey {
@@ -60,12 +1055,10 @@ impl RngAttester {
         salt: &[u8],
         signature: &Signature,
     ) -> Result<(), String> {
-    
-        let mut data_to_verify_hash = Vec::with_capacity(random_number.len() + salt.len());
-        data_to_verify_hash.extend_from_slice(random_number);
-        data_to_verify_hash.extend_from_slice(salt);
-
-        let hashed_data_to_verify = Sha256::digest(&data_to_verify_hash);
+        let hashed_data_to_verify = Sha256::new()
+            .chain_update(random_number)
+            .chain_update(salt)
+            .finalize();
 
         public_key.verify(&hashed_data_to_verify, signature)
             .map_err(|e| format!("Signature verification failed: {}", e))
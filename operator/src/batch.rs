@@ -0,0 +1,180 @@
+// src/batch.rs
+
+//! Batched requests: many values under one Merkle-rooted attestation.
+//!
+//! `POST /task/execute_batch` takes up to `server.max_batch` request contexts
+//! and fulfils them as a single task. One entropy draw is split between the
+//! items, each item's client entropy is mixed into its share, and the items
+//! are hashed into a Merkle tree (see [`crate::merkle`]) whose root is the
+//! random number of one attestation of kind [`BATCH_ROOT_KIND`]. That
+//! attestation is signed, checked and submitted like any other, and every
+//! item comes back with its inclusion proof: a rollup aggregating many user
+//! draws pays for one signature and one submission, and each user can check
+//! their own draw against the signed root with [`BatchItem::verify`].
+//!
+//! Byte fields are hex here, as in an outcome re-encoded with
+//! [`Encoding::Hex`](crate::encoding::Encoding::Hex).
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::merkle;
+use crate::performer::RngPerformer;
+use crate::tasks::TaskOutcome;
+
+/// Payload kind of a batch root attestation.
+pub const BATCH_ROOT_KIND: &str = "merkle-root";
+
+const LEAF_DOMAIN: &[u8] = b"othentic-rng/batch-leaf/v1";
+
+/// One request context of a batch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchEntry {
+    pub task_id: String,
+    pub length: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_entropy: Option<String>,
+}
+
+/// One fulfilled request of a batch, with its proof against the root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchItem {
+    pub task_id: String,
+    /// Position of the item's leaf in the tree.
+    pub index: u64,
+    pub random_number: String,
+    /// The item's share of the operator's draw; present with `client_entropy`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator_entropy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_entropy: Option<String>,
+    /// Audit path from the item's leaf up to the root.
+    pub proof: Vec<String>,
+}
+
+impl BatchItem {
+    /// The item's Merkle leaf.
+    pub fn leaf(&self) -> Result<[u8; 32], String> {
+        let mut data = LEAF_DOMAIN.to_vec();
+        data.extend_from_slice(&self.index.to_be_bytes());
+        let fields = [
+            Some(self.task_id.as_bytes().to_vec()),
+            Some(decode("randomNumber", &self.random_number)?),
+            self.operator_entropy
+                .as_deref()
+                .map(|e| decode("operatorEntropy", e))
+                .transpose()?,
+            self.client_entropy
+                .as_deref()
+                .map(|e| decode("clientEntropy", e))
+                .transpose()?,
+        ];
+        for field in fields {
+            match field {
+                Some(bytes) => {
+                    data.push(1);
+                    data.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+                    data.extend_from_slice(&bytes);
+                }
+                None => data.push(0),
+            }
+        }
+        Ok(merkle::leaf_hash(&data))
+    }
+
+    /// Checks that the item is leaf `index` of the `size`-item tree with
+    /// `root`, and that its client entropy was mixed into its value.
+    pub fn verify(&self, root: &[u8; 32], size: u64) -> Result<(), String> {
+        match (&self.operator_entropy, &self.client_entropy) {
+            (Some(operator), Some(client)) => {
+                let mixed = RngPerformer::mix_client_entropy(
+                    &decode("operatorEntropy", operator)?,
+                    &decode("clientEntropy", client)?,
+                );
+                if hex::encode(mixed) != self.random_number {
+                    return Err(format!(
+                        "batch item {} does not mix in its client entropy",
+                        self.task_id
+                    ));
+                }
+            }
+            (None, None) => {}
+            _ => {
+                return Err(format!(
+                    "batch item {}: operatorEntropy and clientEntropy must be given together",
+                    self.task_id
+                ))
+            }
+        }
+        let proof = self
+            .proof
+            .iter()
+            .map(|node| {
+                decode("proof", node)?
+                    .try_into()
+                    .map_err(|_| "proof nodes must be 32 bytes".to_string())
+            })
+            .collect::<Result<Vec<[u8; 32]>, String>>()?;
+        if !merkle::verify_inclusion(&self.leaf()?, self.index, size, &proof, root) {
+            return Err(format!(
+                "batch item {} is not included at index {} of the signed root",
+                self.task_id, self.index
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Numbers `items` in order, fills in their proofs and returns the root.
+pub fn commit(items: &mut [BatchItem]) -> Result<[u8; 32], String> {
+    for (index, item) in items.iter_mut().enumerate() {
+        item.index = index as u64;
+    }
+    let leaves = items
+        .iter()
+        .map(BatchItem::leaf)
+        .collect::<Result<Vec<_>, _>>()?;
+    for (index, item) in items.iter_mut().enumerate() {
+        item.proof = merkle::inclusion_proof(&leaves, index)
+            .iter()
+            .map(hex::encode)
+            .collect();
+    }
+    Ok(merkle::root(&leaves))
+}
+
+/// Checks every item of a batch outcome against its root and returns how
+/// many there are. The root's own attestation is not checked here.
+pub fn verify_outcome(outcome: &TaskOutcome) -> Result<usize, String> {
+    let items = outcome
+        .batch
+        .as_deref()
+        .ok_or_else(|| format!("task {} is not a batch", outcome.task_id))?;
+    if outcome.kind.as_deref() != Some(BATCH_ROOT_KIND) {
+        return Err(format!(
+            "batch {} is not attested as a {}",
+            outcome.task_id, BATCH_ROOT_KIND
+        ));
+    }
+    let root: [u8; 32] = decode("randomNumber", &outcome.random_number)?
+        .try_into()
+        .map_err(|_| "a batch root must be 32 bytes".to_string())?;
+    if items.is_empty() {
+        return Err(format!("batch {} has no items", outcome.task_id));
+    }
+    let mut seen = BTreeSet::new();
+    for item in items {
+        if !seen.insert(item.task_id.as_str()) {
+            return Err(format!("batch lists task {} twice", item.task_id));
+        }
+        item.verify(&root, items.len() as u64)?;
+    }
+    Ok(items.len())
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>, String> {
+    hex::decode(value).map_err(|e| format!("Invalid hex in {}: {}", field, e))
+}
@@ -0,0 +1,1162 @@
+// src/beacon.rs
+
+//! Multi-party randomness beacon built on [`crate::pvss`].
+//!
+//! Rounds start on wall-clock boundaries (`round = unix_ms / period`), so
+//! every configured operator runs the same round without coordination. Each
+//! round has three phases of `phase_timeout` each:
+//!
+//! 1. **Deal** – every operator broadcasts a signed PVSS dealing.
+//! 2. **Complain** – dealings are publicly checked; an invalid dealing, or two
+//!    different dealings signed by the same dealer, is answered with a
+//!    [`Complaint`] carrying the signed evidence. Anybody can re-check a
+//!    complaint, so honest dealers cannot be framed, and a justified
+//!    complaint disqualifies the dealer everywhere.
+//! 3. **Decrypt** – every operator fixes the qualified dealings, broadcasts
+//!    them signed ([`QualifiedSet`]), then decrypts its share of each and
+//!    broadcasts it with a proof.
+//!
+//! The output combines exactly the qualified dealings; a round in which any
+//! of them is left without `threshold` verified decryptions fails rather
+//! than drop it, so withholding decryptions cannot steer the value. Dealings
+//! are fixed before any secret is revealed and recovery only needs
+//! `threshold` honest operators, so the beacon value is unbiasable as long
+//! as that many operators are honest. The output is stored with the full
+//! transcript ([`BeaconProof`]) and the signed qualified set, so it can be
+//! re-verified offline with [`pvss::verify_beacon`].
+//!
+//! Stored rounds form a hash chain: each [`RoundRecord`] carries the link
+//! hash of the round completed before it. A node signs the latest link with
+//! its PVSS key ([`SignedHead`]), so a consumer that joins late fetches any
+//! past round with the links up to that head ([`RoundChain`]) and checks it
+//! with [`verify_round_chain`] against the committee roster alone.
+//!
+//! The committee channel also carries operators' [`Heartbeat`]s; the latest
+//! one seen from each member is kept for liveness monitoring.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::config::{self, BeaconConfig};
+use crate::epochs;
+use crate::heartbeat::Heartbeat;
+use crate::metrics::Metrics;
+use crate::p2p::{Envelope, HttpTransport, P2pNode, Peer};
+use crate::publish::{self, EventPublisher};
+use crate::pvss::{
+    self, BeaconProof, Bytes32, Dealing, DecryptedShare, KeyPair, Params, Participant,
+    SchnorrSignature,
+};
+use crate::reputation::Reputation;
+use crate::resilience::Resilience;
+use crate::storage::Storage;
+use crate::tasks::unix_millis;
+
+/// Storage collection holding completed rounds, keyed by zero-padded round.
+pub const BEACON_ROUNDS: &str = "beacon_rounds";
+
+const CHAIN_DOMAIN: &[u8] = b"othentic-rng/beacon-chain/v1";
+const HEAD_DOMAIN: &[u8] = b"othentic-rng/beacon-head/v1";
+const QUALIFIED_DOMAIN: &[u8] = b"othentic-rng/beacon-qualified/v1";
+
+/// `previous` of the first round in the chain.
+pub const GENESIS_LINK: Bytes32 = Bytes32([0; 32]);
+
+/// A completed round as stored and served.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundRecord {
+    pub round: u64,
+    pub value: Bytes32,
+    pub params: Params,
+    pub proof: BeaconProof,
+    /// Link hash of the round stored before this one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<Bytes32>,
+    /// This round's link hash; absent on rounds stored before the chain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<Bytes32>,
+    /// The dealings the round was fixed to combine, as signed before the
+    /// decryption phase; absent on rounds stored before it was signed, which
+    /// no longer verify.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qualified: Option<QualifiedSet>,
+}
+
+impl RoundRecord {
+    /// Re-runs the transcript; `params` pins the committee to expect.
+    pub fn verify(&self, params: Option<&Params>) -> Result<(), String> {
+        if params.is_some_and(|expected| *expected != self.params) {
+            return Err(format!("Round {} was run by another committee", self.round));
+        }
+        if self.proof.round != self.round {
+            return Err(format!(
+                "Round {} carries the transcript of round {}",
+                self.round, self.proof.round
+            ));
+        }
+        if pvss::verify_beacon(&self.params, &self.proof)? != self.value {
+            return Err(format!(
+                "Round {} value does not follow from its transcript",
+                self.round
+            ));
+        }
+        let qualified = self
+            .qualified
+            .as_ref()
+            .ok_or_else(|| format!("Round {} carries no signed qualified set", self.round))?;
+        qualified.verify(&self.params)?;
+        let combined: Vec<QualifiedDealing> = self
+            .proof
+            .dealings
+            .iter()
+            .map(QualifiedDealing::of)
+            .collect();
+        if qualified.round != self.round || qualified.dealings != combined {
+            return Err(format!(
+                "Round {} did not combine the dealings it qualified",
+                self.round
+            ));
+        }
+        Ok(())
+    }
+
+    /// This round's entry in the chain; `None` before the chain existed.
+    pub fn link(&self) -> Option<ChainLink> {
+        Some(ChainLink {
+            round: self.round,
+            value: self.value,
+            previous: self.previous?,
+        })
+    }
+}
+
+/// One round's entry in the hash chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainLink {
+    pub round: u64,
+    pub value: Bytes32,
+    pub previous: Bytes32,
+}
+
+impl ChainLink {
+    /// SHA-256 over the round, its value and the previous link hash.
+    pub fn hash(&self) -> Bytes32 {
+        let digest = Sha256::new()
+            .chain_update(CHAIN_DOMAIN)
+            .chain_update(self.round.to_be_bytes())
+            .chain_update(self.value.0)
+            .chain_update(self.previous.0)
+            .finalize();
+        Bytes32(digest.into())
+    }
+}
+
+/// The latest link hash, signed by a committee member's PVSS key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedHead {
+    pub node: u32,
+    pub round: u64,
+    pub hash: Bytes32,
+    pub issued_at: u64,
+    pub signature: SchnorrSignature,
+}
+
+impl SignedHead {
+    /// Checks the signature against the key `params` lists for the node.
+    pub fn verify(&self, params: &Params) -> Result<(), String> {
+        let index = params
+            .position(self.node)
+            .ok_or_else(|| format!("Head signer {} is not a participant", self.node))?;
+        pvss::verify_signature(
+            &params.participants[index].public_key,
+            &head_message(self.node, self.round, &self.hash, self.issued_at),
+            &self.signature,
+        )
+        .map_err(|e| format!("Head signed by {}: {}", self.node, e))
+    }
+}
+
+/// The bytes a head's signature covers.
+fn head_message(node: u32, round: u64, hash: &Bytes32, issued_at: u64) -> Vec<u8> {
+    let mut data = HEAD_DOMAIN.to_vec();
+    data.extend_from_slice(&node.to_be_bytes());
+    data.extend_from_slice(&round.to_be_bytes());
+    data.extend_from_slice(&hash.0);
+    data.extend_from_slice(&issued_at.to_be_bytes());
+    data
+}
+
+/// A dealing a round is fixed to combine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QualifiedDealing {
+    pub dealer: u32,
+    /// [`Dealing::digest`], so that an equivocating dealer cannot swap it.
+    pub digest: Bytes32,
+}
+
+impl QualifiedDealing {
+    fn of(dealing: &Dealing) -> Self {
+        QualifiedDealing {
+            dealer: dealing.dealer,
+            digest: dealing.digest(),
+        }
+    }
+}
+
+/// The dealings a node fixed for a round once complaints were in, signed
+/// with its PVSS key before it decrypted any of them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QualifiedSet {
+    pub node: u32,
+    pub round: u64,
+    /// Ordered by dealer.
+    pub dealings: Vec<QualifiedDealing>,
+    pub signature: SchnorrSignature,
+}
+
+impl QualifiedSet {
+    fn sign(key: &KeyPair, node: u32, round: u64, dealings: Vec<QualifiedDealing>) -> Self {
+        let signature = key.sign(&qualified_message(node, round, &dealings));
+        QualifiedSet {
+            node,
+            round,
+            dealings,
+            signature,
+        }
+    }
+
+    /// Checks the signature against the key `params` lists for the node.
+    pub fn verify(&self, params: &Params) -> Result<(), String> {
+        let index = params
+            .position(self.node)
+            .ok_or_else(|| format!("Qualified set signer {} is not a participant", self.node))?;
+        pvss::verify_signature(
+            &params.participants[index].public_key,
+            &qualified_message(self.node, self.round, &self.dealings),
+            &self.signature,
+        )
+        .map_err(|e| format!("Qualified set signed by {}: {}", self.node, e))
+    }
+}
+
+/// The bytes a qualified set's signature covers.
+fn qualified_message(node: u32, round: u64, dealings: &[QualifiedDealing]) -> Vec<u8> {
+    let mut data = QUALIFIED_DOMAIN.to_vec();
+    data.extend_from_slice(&node.to_be_bytes());
+    data.extend_from_slice(&round.to_be_bytes());
+    data.extend_from_slice(&(dealings.len() as u32).to_be_bytes());
+    for dealing in dealings {
+        data.extend_from_slice(&dealing.dealer.to_be_bytes());
+        data.extend_from_slice(&dealing.digest.0);
+    }
+    data
+}
+
+/// A past round with the chain linking it to a signed head.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundChain {
+    pub record: RoundRecord,
+    /// The links of every round stored after `record`, oldest first.
+    pub links: Vec<ChainLink>,
+    pub head: SignedHead,
+}
+
+/// Checks that `chain` ties its round to a head signed by a member of
+/// `params`, and re-runs the round's transcript.
+///
+/// The head is one member's word, so the round must have qualified at least
+/// `threshold` dealers: a member serving a chain cannot have made its value
+/// up alone. The rounds after it are vouched for by the head only; fetch
+/// them to check their transcripts too.
+pub fn verify_round_chain(params: &Params, chain: &RoundChain) -> Result<(), String> {
+    let record = &chain.record;
+    let dealers = record.qualified.as_ref().map_or(0, |q| q.dealings.len());
+    if dealers < params.threshold {
+        return Err(format!(
+            "Round {} qualified {} dealer(s), {} required",
+            record.round, dealers, params.threshold
+        ));
+    }
+    record.verify(Some(params))?;
+    let link = record
+        .link()
+        .ok_or_else(|| format!("Round {} predates the hash chain", record.round))?;
+    let mut hash = link.hash();
+    if record.hash != Some(hash) {
+        return Err(format!(
+            "Round {} does not match its link hash",
+            record.round
+        ));
+    }
+    let mut round = record.round;
+    for link in &chain.links {
+        if link.round <= round {
+            return Err(format!(
+                "Link for round {} follows round {}",
+                link.round, round
+            ));
+        }
+        if link.previous != hash {
+            return Err(format!(
+                "Link for round {} does not extend round {}",
+                link.round, round
+            ));
+        }
+        hash = link.hash();
+        round = link.round;
+    }
+    chain.head.verify(params)?;
+    if chain.head.round != round || chain.head.hash != hash {
+        return Err(format!(
+            "Chain ends at round {}, head is round {}",
+            round, chain.head.round
+        ));
+    }
+    Ok(())
+}
+
+/// Messages exchanged during a round.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BeaconMessage {
+    Dealing(Dealing),
+    Complaint(Complaint),
+    /// The sender's qualified dealings, broadcast before its decryptions.
+    Qualified(QualifiedSet),
+    Decryptions {
+        round: u64,
+        shares: Vec<DecryptedShare>,
+    },
+    /// Liveness heartbeat; recorded on arrival, whatever the round.
+    Heartbeat(Box<Heartbeat>),
+}
+
+impl BeaconMessage {
+    fn round(&self) -> u64 {
+        match self {
+            BeaconMessage::Dealing(dealing) => dealing.round,
+            BeaconMessage::Complaint(complaint) => complaint.round(),
+            BeaconMessage::Qualified(set) => set.round,
+            BeaconMessage::Decryptions { round, .. } => *round,
+            BeaconMessage::Heartbeat(heartbeat) => heartbeat.round.unwrap_or_default(),
+        }
+    }
+}
+
+/// Publicly checkable accusation against a dealer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Complaint {
+    /// The dealer signed a dealing that fails [`pvss::verify_dealing`].
+    InvalidDealing {
+        round: u64,
+        dealer: u32,
+        evidence: Envelope,
+    },
+    /// The dealer signed two different dealings for the same round.
+    Equivocation {
+        round: u64,
+        dealer: u32,
+        first: Envelope,
+        second: Envelope,
+    },
+}
+
+impl Complaint {
+    fn round(&self) -> u64 {
+        match self {
+            Complaint::InvalidDealing { round, .. } | Complaint::Equivocation { round, .. } => {
+                *round
+            }
+        }
+    }
+
+    fn dealer(&self) -> u32 {
+        match self {
+            Complaint::InvalidDealing { dealer, .. } | Complaint::Equivocation { dealer, .. } => {
+                *dealer
+            }
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Complaint::InvalidDealing { .. } => "invalid_dealing",
+            Complaint::Equivocation { .. } => "equivocation",
+        }
+    }
+
+    /// Returns the accused dealer if the evidence holds up.
+    pub fn check(&self, params: &Params) -> Result<u32, String> {
+        let round = self.round();
+        let dealer = self.dealer();
+        match self {
+            Complaint::InvalidDealing { evidence, .. } => {
+                let dealing = signed_dealing(params, evidence, round, dealer)?;
+                match pvss::verify_dealing(params, &dealing) {
+                    Ok(()) => Err(format!("Dealing from {} is valid", dealer)),
+                    Err(_) => Ok(dealer),
+                }
+            }
+            Complaint::Equivocation { first, second, .. } => {
+                let first = signed_dealing(params, first, round, dealer)?;
+                let second = signed_dealing(params, second, round, dealer)?;
+                if first.digest() == second.digest() {
+                    return Err(format!("Both dealings from {} are identical", dealer));
+                }
+                Ok(dealer)
+            }
+        }
+    }
+}
+
+/// Extracts the dealing `dealer` signed for `round` from `envelope`.
+fn signed_dealing(
+    params: &Params,
+    envelope: &Envelope,
+    round: u64,
+    dealer: u32,
+) -> Result<Dealing, String> {
+    if envelope.sender != dealer {
+        return Err(format!("Evidence was not sent by dealer {}", dealer));
+    }
+    let index = params
+        .position(dealer)
+        .ok_or_else(|| format!("Dealer {} is not a participant", dealer))?;
+    envelope.verify(&params.participants[index].public_key)?;
+    match serde_json::from_str(&envelope.body) {
+        Ok(BeaconMessage::Dealing(dealing))
+            if dealing.round == round && dealing.dealer == dealer =>
+        {
+            Ok(dealing)
+        }
+        _ => Err(format!(
+            "Evidence is not a round {} dealing from {}",
+            round, dealer
+        )),
+    }
+}
+
+/// Per-round bookkeeping.
+struct RoundState {
+    round: u64,
+    accepting_dealings: bool,
+    dealings: BTreeMap<u32, (Envelope, Dealing)>,
+    disqualified: BTreeSet<u32>,
+    /// Qualified sets the other members signed, by member.
+    qualified: BTreeMap<u32, QualifiedSet>,
+    decryptions: BTreeMap<u32, BTreeMap<u32, DecryptedShare>>,
+}
+
+/// Runs beacon rounds with the other operators of the committee.
+pub struct BeaconNode {
+    params: Params,
+    p2p: Arc<P2pNode>,
+    storage: Arc<dyn Storage>,
+    metrics: Arc<Metrics>,
+    period: Duration,
+    phase_timeout: Duration,
+    /// Messages that arrived for a later round than the one running.
+    stash: Mutex<Vec<(Envelope, BeaconMessage)>>,
+    /// Latest verified heartbeat from each committee member.
+    heartbeats: Mutex<BTreeMap<u32, Heartbeat>>,
+    publisher: Option<Arc<EventPublisher>>,
+    reputation: Option<Arc<Reputation>>,
+}
+
+impl BeaconNode {
+    /// Builds a node from the `beacon` config section.
+    ///
+    /// The PVSS key is read from `key_file`; if the file does not exist a new
+    /// key is generated and written there, and its public key is logged so it
+    /// can be added to the other operators' committee lists.
+    pub fn from_config(
+        config: &BeaconConfig,
+        resilience: Arc<Resilience>,
+        storage: Arc<dyn Storage>,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self, String> {
+        let key = load_or_create_key(Path::new(&config.key_file))?;
+        let mut participants = Vec::with_capacity(config.participants.len());
+        let mut peers = Vec::with_capacity(config.participants.len());
+        for entry in &config.participants {
+            let public_key = Bytes32::from_hex(&entry.public_key)
+                .map_err(|e| format!("beacon participant {}: {}", entry.id, e))?;
+            participants.push(Participant {
+                id: entry.id,
+                public_key,
+            });
+            peers.push(Peer {
+                id: entry.id,
+                url: entry.url.clone(),
+                public_key,
+            });
+        }
+        let params = Params::new(config.threshold, participants)?;
+        match params.position(config.node_id) {
+            Some(i) if params.participants[i].public_key == key.public() => {}
+            Some(_) => {
+                return Err(format!(
+                    "Key in {} does not match the public key listed for node {}",
+                    config.key_file, config.node_id
+                ))
+            }
+            None => {
+                return Err(format!(
+                    "beacon.node_id {} is not a participant",
+                    config.node_id
+                ))
+            }
+        }
+
+        let transport = Arc::new(HttpTransport::new(resilience));
+        let p2p = Arc::new(P2pNode::new(config.node_id, key, peers, transport));
+        Ok(BeaconNode {
+            params,
+            p2p,
+            storage,
+            metrics,
+            period: config::parse_duration(&config.period)?,
+            phase_timeout: config::parse_duration(&config.phase_timeout)?,
+            stash: Mutex::new(Vec::new()),
+            heartbeats: Mutex::new(BTreeMap::new()),
+            publisher: None,
+            reputation: None,
+        })
+    }
+
+    /// Publishes every completed round with `publisher`.
+    pub fn with_publisher(mut self, publisher: Arc<EventPublisher>) -> Self {
+        self.publisher = Some(publisher);
+        self
+    }
+
+    /// Scores committee members by the heartbeats they broadcast.
+    pub fn with_reputation(mut self, reputation: Arc<Reputation>) -> Self {
+        self.reputation = Some(reputation);
+        self
+    }
+
+    /// Returns the peer-to-peer endpoint inbound messages are delivered to.
+    pub fn p2p(&self) -> &P2pNode {
+        &self.p2p
+    }
+
+    /// Returns the stored record of the most recent completed round.
+    pub fn latest(&self) -> Result<Option<Value>, String> {
+        Ok(self
+            .storage
+            .scan(BEACON_ROUNDS)?
+            .pop()
+            .map(|(_, record)| record))
+    }
+
+    /// Returns the number of the most recent completed round.
+    pub fn latest_round(&self) -> Result<Option<u64>, String> {
+        Ok(self.latest()?.and_then(|record| record["round"].as_u64()))
+    }
+
+    /// Returns the latest heartbeat received from each committee member.
+    pub fn peer_heartbeats(&self) -> BTreeMap<u32, Heartbeat> {
+        self.heartbeats
+            .lock()
+            .expect("beacon heartbeat lock poisoned")
+            .clone()
+    }
+
+    /// Sends `heartbeat` to the rest of the committee.
+    pub fn broadcast_heartbeat(&self, heartbeat: &Heartbeat) -> Result<(), String> {
+        self.broadcast(&BeaconMessage::Heartbeat(Box::new(heartbeat.clone())))
+    }
+
+    /// Returns the stored record of `round`, if it completed.
+    pub fn round(&self, round: u64) -> Result<Option<Value>, String> {
+        self.storage.get(BEACON_ROUNDS, &round_key(round))
+    }
+
+    /// Signs the link hash of the most recent completed round.
+    pub fn head(&self) -> Result<Option<SignedHead>, String> {
+        match self.latest()? {
+            Some(record) => self.sign_head(&parse_record(record)?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns `round` with the links of every later round and a signed head,
+    /// if it completed.
+    pub fn round_chain(&self, round: u64) -> Result<Option<RoundChain>, String> {
+        let key = round_key(round);
+        let rounds = self.storage.scan(BEACON_ROUNDS)?;
+        let Some(start) = rounds.iter().position(|(k, _)| *k == key) else {
+            return Ok(None);
+        };
+        let mut later = rounds.into_iter().skip(start).map(|(_, r)| parse_record(r));
+        let record = later.next().expect("position is in range")?;
+        let mut last = record.clone();
+        let mut links = Vec::new();
+        for next in later {
+            last = next?;
+            links.push(
+                last.link()
+                    .ok_or_else(|| format!("Round {} predates the hash chain", last.round))?,
+            );
+        }
+        let head = self.sign_head(&last)?;
+        Ok(Some(RoundChain {
+            record,
+            links,
+            head,
+        }))
+    }
+
+    fn sign_head(&self, record: &RoundRecord) -> Result<SignedHead, String> {
+        let hash = record
+            .hash
+            .ok_or_else(|| format!("Round {} predates the hash chain", record.round))?;
+        let node = self.p2p.id();
+        let issued_at = unix_millis();
+        Ok(SignedHead {
+            node,
+            round: record.round,
+            hash,
+            issued_at,
+            signature: self
+                .p2p
+                .key()
+                .sign(&head_message(node, record.round, &hash, issued_at)),
+        })
+    }
+
+    /// Runs a round at every period boundary, forever.
+    pub fn run(&self) {
+        let period_ms = self.period.as_millis().max(1) as u64;
+        info!(
+            "Beacon node {} running with {} participant(s), threshold {}",
+            self.p2p.id(),
+            self.params.participants.len(),
+            self.params.threshold
+        );
+        loop {
+            let now = unix_millis();
+            let round = now / period_ms + 1;
+            thread::sleep(Duration::from_millis(round * period_ms - now));
+
+            match self.run_round(round, Instant::now()) {
+                Ok(proof) => {
+                    info!(
+                        "Beacon round {} complete with {} dealing(s): {}",
+                        round,
+                        proof.dealings.len(),
+                        proof.value
+                    );
+                    self.metrics.inc_counter(
+                        "rng_beacon_rounds_total",
+                        &[("outcome", "success")],
+                        1,
+                    );
+                }
+                Err(e) => {
+                    warn!("Beacon round {} failed: {}", round, e);
+                    self.metrics.inc_counter(
+                        "rng_beacon_rounds_total",
+                        &[("outcome", "failed")],
+                        1,
+                    );
+                }
+            }
+        }
+    }
+
+    fn run_round(&self, round: u64, start: Instant) -> Result<BeaconProof, String> {
+        let me = self.p2p.id();
+        let mut state = RoundState {
+            round,
+            accepting_dealings: true,
+            dealings: BTreeMap::new(),
+            disqualified: BTreeSet::new(),
+            qualified: BTreeMap::new(),
+            decryptions: BTreeMap::new(),
+        };
+
+        // Phase 1: deal.
+        let dealing = pvss::deal(&self.params, round, me)?;
+        self.broadcast(&BeaconMessage::Dealing(dealing))?;
+        self.collect(&mut state, start + self.phase_timeout);
+
+        // Phase 2: complain about invalid dealings.
+        state.accepting_dealings = false;
+        let mut complaints = Vec::new();
+        for (dealer, (envelope, dealing)) in &state.dealings {
+            if state.disqualified.contains(dealer) {
+                continue;
+            }
+            if let Err(e) = pvss::verify_dealing(&self.params, dealing) {
+                warn!("Round {}: rejecting dealing from {}: {}", round, dealer, e);
+                complaints.push(Complaint::InvalidDealing {
+                    round,
+                    dealer: *dealer,
+                    evidence: envelope.clone(),
+                });
+            }
+        }
+        for complaint in complaints {
+            state.disqualified.insert(complaint.dealer());
+            self.broadcast(&BeaconMessage::Complaint(complaint))?;
+        }
+        self.collect(&mut state, start + self.phase_timeout * 2);
+
+        // Phase 3: fix the qualified dealings and sign them, then decrypt
+        // our share of each. Later complaints no longer change the set.
+        let dealings: Vec<Dealing> = state
+            .dealings
+            .iter()
+            .filter(|(dealer, _)| !state.disqualified.contains(dealer))
+            .map(|(_, (_, dealing))| dealing.clone())
+            .collect();
+        if dealings.len() < self.params.threshold {
+            return Err(format!(
+                "Round {} has {} qualified dealing(s), {} required",
+                round,
+                dealings.len(),
+                self.params.threshold
+            ));
+        }
+        let qualified = QualifiedSet::sign(
+            self.p2p.key(),
+            me,
+            round,
+            dealings.iter().map(QualifiedDealing::of).collect(),
+        );
+        self.broadcast(&BeaconMessage::Qualified(qualified.clone()))?;
+        self.metrics
+            .set_gauge("rng_beacon_qualified_dealers", &[], dealings.len() as f64);
+        let mut shares = Vec::new();
+        for dealing in &dealings {
+            shares.push(pvss::decrypt_share(
+                &self.params,
+                self.p2p.key(),
+                me,
+                dealing,
+            )?);
+        }
+        self.broadcast(&BeaconMessage::Decryptions { round, shares })?;
+        self.collect(&mut state, start + self.phase_timeout * 3);
+
+        for (node, theirs) in &state.qualified {
+            if theirs.dealings != qualified.dealings {
+                warn!(
+                    "Round {}: node {} qualified other dealings than this node",
+                    round, node
+                );
+            }
+        }
+        // Combine exactly the qualified dealings: one without enough
+        // decryptions fails the round instead of being dropped from it.
+        let mut combined = Vec::with_capacity(dealings.len());
+        for dealing in dealings {
+            let shares: Vec<DecryptedShare> = state
+                .decryptions
+                .remove(&dealing.dealer)
+                .map(|by_participant| by_participant.into_values().collect())
+                .unwrap_or_default();
+            if shares.len() < self.params.threshold {
+                return Err(format!(
+                    "Qualified dealing from {} has only {} of {} decryption(s)",
+                    dealing.dealer,
+                    shares.len(),
+                    self.params.threshold
+                ));
+            }
+            combined.push((dealing, shares));
+        }
+        let proof = pvss::combine(&self.params, round, combined)?;
+        // Rounds stored before the chain existed restart it.
+        let previous = match self.latest()? {
+            Some(latest) => parse_record(latest)?.hash.unwrap_or(GENESIS_LINK),
+            None => GENESIS_LINK,
+        };
+        let link = ChainLink {
+            round,
+            value: proof.value,
+            previous,
+        };
+        let record = RoundRecord {
+            round,
+            value: proof.value,
+            params: self.params.clone(),
+            proof: proof.clone(),
+            previous: Some(previous),
+            hash: Some(link.hash()),
+            qualified: Some(qualified),
+        };
+        let record = serde_json::to_value(&record)
+            .map_err(|e| format!("Failed to encode round {}: {}", round, e))?;
+        self.storage
+            .put(BEACON_ROUNDS, &round_key(round), record.clone())?;
+        if let Some(publisher) = &self.publisher {
+            publisher.publish(publish::BEACON_ROUND_SCHEMA, &round.to_string(), record);
+        }
+        Ok(proof)
+    }
+
+    fn broadcast(&self, message: &BeaconMessage) -> Result<(), String> {
+        let body = serde_json::to_string(message)
+            .map_err(|e| format!("Failed to encode beacon message: {}", e))?;
+        self.p2p.broadcast(body);
+        Ok(())
+    }
+
+    /// Processes incoming messages for `state.round` until `deadline`.
+    fn collect(&self, state: &mut RoundState, deadline: Instant) {
+        let stashed = {
+            let mut stash = self.stash.lock().expect("beacon stash lock poisoned");
+            let (current, later): (Vec<_>, Vec<_>) = stash
+                .drain(..)
+                .filter(|(_, message)| message.round() >= state.round)
+                .partition(|(_, message)| message.round() == state.round);
+            *stash = later;
+            current
+        };
+        for (envelope, message) in stashed {
+            self.handle(state, envelope, message);
+        }
+
+        while Instant::now() < deadline {
+            for envelope in self.p2p.wait(deadline) {
+                let message: BeaconMessage = match serde_json::from_str(&envelope.body) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        warn!("Ignoring malformed message from {}: {}", envelope.sender, e);
+                        continue;
+                    }
+                };
+                if let BeaconMessage::Heartbeat(heartbeat) = message {
+                    self.record_heartbeat(envelope.sender, *heartbeat);
+                    continue;
+                }
+                let round = message.round();
+                if round == state.round {
+                    self.handle(state, envelope, message);
+                } else if round > state.round && round <= state.round + 2 {
+                    self.stash
+                        .lock()
+                        .expect("beacon stash lock poisoned")
+                        .push((envelope, message));
+                }
+            }
+        }
+    }
+
+    fn handle(&self, state: &mut RoundState, envelope: Envelope, message: BeaconMessage) {
+        let sender = envelope.sender;
+        match message {
+            BeaconMessage::Dealing(dealing) => {
+                if !state.accepting_dealings || dealing.dealer != sender {
+                    return;
+                }
+                let equivocation = match state.dealings.get(&sender) {
+                    None => {
+                        state.dealings.insert(sender, (envelope, dealing));
+                        None
+                    }
+                    Some((first, existing)) if existing.digest() != dealing.digest() => {
+                        Some(Complaint::Equivocation {
+                            round: state.round,
+                            dealer: sender,
+                            first: first.clone(),
+                            second: envelope,
+                        })
+                    }
+                    Some(_) => None,
+                };
+                if let Some(complaint) = equivocation {
+                    if state.disqualified.insert(sender) {
+                        warn!("Round {}: dealer {} equivocated", state.round, sender);
+                        self.record_complaint(&complaint);
+                        if let Err(e) = self.broadcast(&BeaconMessage::Complaint(complaint)) {
+                            warn!("Failed to broadcast complaint: {}", e);
+                        }
+                    }
+                }
+            }
+            BeaconMessage::Complaint(complaint) => match complaint.check(&self.params) {
+                Ok(dealer) => {
+                    if state.disqualified.insert(dealer) {
+                        info!(
+                            "Round {}: dealer {} disqualified on complaint from {}",
+                            state.round, dealer, sender
+                        );
+                        self.record_complaint(&complaint);
+                    }
+                }
+                Err(e) => warn!(
+                    "Round {}: ignoring unjustified complaint from {}: {}",
+                    state.round, sender, e
+                ),
+            },
+            BeaconMessage::Heartbeat(heartbeat) => self.record_heartbeat(sender, *heartbeat),
+            BeaconMessage::Qualified(set) => {
+                if set.node != sender {
+                    return;
+                }
+                match set.verify(&self.params) {
+                    Ok(()) => {
+                        state.qualified.entry(sender).or_insert(set);
+                    }
+                    Err(e) => warn!("Round {}: {}", state.round, e),
+                }
+            }
+            BeaconMessage::Decryptions { shares, .. } => {
+                for share in shares {
+                    if share.participant != sender {
+                        continue;
+                    }
+                    let Some((_, dealing)) = state.dealings.get(&share.dealer) else {
+                        continue;
+                    };
+                    match pvss::verify_decryption(&self.params, dealing, &share) {
+                        Ok(()) => {
+                            state
+                                .decryptions
+                                .entry(share.dealer)
+                                .or_default()
+                                .insert(sender, share);
+                        }
+                        Err(e) => warn!("Round {}: {}", state.round, e),
+                    }
+                }
+            }
+        }
+    }
+
+    fn record_heartbeat(&self, sender: u32, heartbeat: Heartbeat) {
+        if let Err(e) = heartbeat.verify() {
+            warn!("Ignoring heartbeat from {}: {}", sender, e);
+            return;
+        }
+        self.metrics.set_gauge(
+            "rng_peer_last_heartbeat_ms",
+            &[("peer", &sender.to_string())],
+            heartbeat.timestamp as f64,
+        );
+        if let Some(reputation) = &self.reputation {
+            if let Err(e) = reputation.observe_heartbeat(&heartbeat) {
+                warn!("Failed to score heartbeat from {}: {}", sender, e);
+            }
+        }
+        self.heartbeats
+            .lock()
+            .expect("beacon heartbeat lock poisoned")
+            .insert(sender, heartbeat);
+    }
+
+    fn record_complaint(&self, complaint: &Complaint) {
+        self.metrics.inc_counter(
+            "rng_beacon_complaints_total",
+            &[("kind", complaint.kind())],
+            1,
+        );
+    }
+}
+
+fn round_key(round: u64) -> String {
+    format!("{:020}", round)
+}
+
+fn parse_record(record: Value) -> Result<RoundRecord, String> {
+    serde_json::from_value(record).map_err(|e| format!("Invalid beacon round record: {}", e))
+}
+
+/// Reads the hex-encoded PVSS secret at `path`, generating it if missing.
+fn load_or_create_key(path: &Path) -> Result<KeyPair, String> {
+    match std::fs::read_to_string(path) {
+        Ok(raw) => KeyPair::from_secret(&Bytes32::from_hex(raw.trim())?)
+            .map_err(|e| format!("Invalid beacon key in {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let key = KeyPair::generate();
+            epochs::write_private(path, format!("{}\n", key.secret()).as_bytes())
+                .map_err(|e| format!("Failed to write beacon key {}: {}", path.display(), e))?;
+            info!(
+                "Generated beacon key {} with public key {}",
+                path.display(),
+                key.public()
+            );
+            Ok(key)
+        }
+        Err(e) => Err(format!(
+            "Failed to read beacon key {}: {}",
+            path.display(),
+            e
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> (RoundRecord, Vec<KeyPair>) {
+        let keys: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate()).collect();
+        let participants = keys
+            .iter()
+            .zip(1..)
+            .map(|(key, id)| Participant {
+                id,
+                public_key: key.public(),
+            })
+            .collect();
+        let params = Params::new(2, participants).unwrap();
+        let dealings: Vec<Dealing> = (1..=3)
+            .map(|d| pvss::deal(&params, 9, d).unwrap())
+            .collect();
+        let combined = dealings
+            .iter()
+            .map(|dealing| {
+                let shares = keys
+                    .iter()
+                    .zip(1..)
+                    .map(|(key, id)| pvss::decrypt_share(&params, key, id, dealing).unwrap())
+                    .collect();
+                (dealing.clone(), shares)
+            })
+            .collect();
+        let proof = pvss::combine(&params, 9, combined).unwrap();
+        let qualified = QualifiedSet::sign(
+            &keys[0],
+            1,
+            9,
+            dealings.iter().map(QualifiedDealing::of).collect(),
+        );
+        let record = RoundRecord {
+            round: 9,
+            value: proof.value,
+            params,
+            proof,
+            previous: None,
+            hash: None,
+            qualified: Some(qualified),
+        };
+        (record, keys)
+    }
+
+    #[test]
+    fn round_combining_its_qualified_set_verifies() {
+        let (record, _) = record();
+        record.verify(None).unwrap();
+
+        let mut unsigned = record.clone();
+        unsigned.qualified = None;
+        assert!(unsigned.verify(None).is_err());
+    }
+
+    #[test]
+    fn qualified_set_must_match_the_transcript() {
+        let (record, keys) = record();
+
+        // A dealer the transcript dropped after the set was fixed.
+        let mut dropped = record.clone();
+        let mut dealings = record.qualified.clone().unwrap().dealings;
+        dealings.push(QualifiedDealing {
+            dealer: 4,
+            digest: Bytes32([7; 32]),
+        });
+        dropped.qualified = Some(QualifiedSet::sign(&keys[0], 1, 9, dealings));
+        assert!(dropped.verify(None).is_err());
+
+        let mut other_round = record.clone();
+        let dealings = record.qualified.clone().unwrap().dealings;
+        other_round.qualified = Some(QualifiedSet::sign(&keys[0], 1, 8, dealings));
+        assert!(other_round.verify(None).is_err());
+    }
+
+    #[test]
+    fn qualified_set_signature_is_checked() {
+        let (record, keys) = record();
+        let set = record.qualified.clone().unwrap();
+        set.verify(&record.params).unwrap();
+
+        let mut reordered = set.clone();
+        reordered.dealings.reverse();
+        assert!(reordered.verify(&record.params).is_err());
+
+        // Signed by node 2, claimed for node 1.
+        let forged = QualifiedSet::sign(&keys[1], 1, 9, set.dealings.clone());
+        assert!(forged.verify(&record.params).is_err());
+
+        let mut outsider = set;
+        outsider.node = 9;
+        assert!(outsider.verify(&record.params).is_err());
+    }
+    fn chain(record: RoundRecord, key: &KeyPair) -> RoundChain {
+        let mut record = record;
+        let link = ChainLink {
+            round: record.round,
+            value: record.value,
+            previous: GENESIS_LINK,
+        };
+        record.previous = Some(GENESIS_LINK);
+        record.hash = Some(link.hash());
+        let hash = link.hash();
+        RoundChain {
+            head: SignedHead {
+                node: 1,
+                round: record.round,
+                hash,
+                issued_at: 1,
+                signature: key.sign(&head_message(1, record.round, &hash, 1)),
+            },
+            record,
+            links: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn round_chain_needs_threshold_dealers() {
+        let (record, keys) = record();
+        let params = record.params.clone();
+        verify_round_chain(&params, &chain(record.clone(), &keys[0])).unwrap();
+
+        // One member alone dealing and signing the head.
+        let dealing = pvss::deal(&params, 9, 1).unwrap();
+        let shares = keys
+            .iter()
+            .zip(1..)
+            .map(|(key, id)| pvss::decrypt_share(&params, key, id, &dealing).unwrap())
+            .collect();
+        let proof = pvss::combine(&params, 9, vec![(dealing.clone(), shares)]).unwrap();
+        let alone = RoundRecord {
+            value: proof.value,
+            proof,
+            qualified: Some(QualifiedSet::sign(
+                &keys[0],
+                1,
+                9,
+                vec![QualifiedDealing::of(&dealing)],
+            )),
+            ..record
+        };
+        let error = verify_round_chain(&params, &chain(alone, &keys[0])).unwrap_err();
+        assert!(error.contains("1 dealer(s), 2 required"), "{}", error);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn generated_key_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = std::env::temp_dir().join(format!("beacon-key-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let key = load_or_create_key(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(load_or_create_key(&path).unwrap().public(), key.public());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
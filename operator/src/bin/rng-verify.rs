@@ -0,0 +1,1229 @@
+// src/bin/rng-verify.rs
+
+//! Standalone verifier for operator attestations.
+//!
+//! ```text
+//! rng-verify [--output text|json] [--encoding NAME] [--public-key HEX]...
+//!            [--master-key HEX]... [--address HEX]... [--schnorr-key HEX]... [--quorum N]
+//!            [--drand-info FILE] [--at TIME] [--skew DURATION] [--domain TAG]
+//!            [--task-definition ID] [--crl FILE] [--deck FILE]... [--prime FILE]... FILE...
+//! rng-verify [--output text|json] [--encoding NAME] --public-key HEX
+//!            --random-number HEX --salt HEX --signature HEX
+//!            [--secp256k1-signature HEX] [--schnorr-signature HEX]
+//! ```
+//!
+//! `FILE` (or `-` for stdin) holds a `/task/execute` response, or JSONL as
+//! written by `operator export` (event rows are skipped), or a released
+//! timelock (`GET /timelock/{task}`), whose outcome is decrypted and checked
+//! against its commitment first (see [`operator::timelock`]). Every attestation is
+//! checked for a valid signature and for every recorded derivation step (VDF,
+//! drand mixing, client mixing, VRF). `--public-key` lists the operator keys
+//! to trust; without it the key embedded in each attestation is used, which only
+//! proves internal consistency. `--master-key` trusts the epoch keys an
+//! operator's master key certified (see [`operator::epochs`]): an attestation
+//! of epoch `E` must carry the certificate of its key for `E`, and must have
+//! been signed within that epoch. A secp256k1 signature, when present, is checked
+//! by recovering its signer, which must match the attested address and, given
+//! `--address`, one of the trusted Ethereum addresses. A BIP-340 Schnorr
+//! signature is checked against the attested x-only key, which must be one of
+//! `--schnorr-key` if given. `--drand-info` takes the chain info JSON of the
+//! drand network so wrapped rounds can be checked as well. `--quorum N`
+//! additionally requires N distinct trusted operators to attest the same value.
+//! An attestation with a validity window must be valid now, or at `--at`
+//! (Unix ms or a UTC date) when auditing a past draw, within `--skew`. A
+//! pre-generated (pooled) value must carry a binding to its task ID, and every
+//! request of a batch its inclusion proof against the signed root (see
+//! [`operator::batch`]). `--domain`
+//! requires the signed domain tag of the tenant the value was made for, and
+//! `--task-definition` the signed ID of the task definition (see
+//! [`operator::definitions`]).
+//! `--crl` takes the operator's revocation list (`GET /revocations`) and fails
+//! every attestation it revokes; statements count when signed by a trusted key
+//! or, without `--public-key`, by the attestation's own key. `--deck` takes a
+//! card shuffle proof (or a JSON array of them, see [`operator::card_deck`])
+//! and checks that its deck order follows from the attestation it names.
+//! `--prime` takes a prime trail (see [`operator::primes::PrimeTrail`]) whose
+//! seed must be an attested random number, and replays its search.
+//! An attestation signed in an enclave carries the enclave's quote, which
+//! must be bound to the signing key; the vendor's signature on the quote is
+//! not checked here. An ABI form (see [`operator::abi`]) must encode the
+//! attestation and be signed by its secp256k1 address. A batch response, or
+//! an `/attestations/export` response, carrying a signed receipt (see
+//! [`operator::receipts`]) is checked to cover exactly the values in the
+//! file, signed by a trusted key when keys are given.
+//!
+//! `--encoding` (hex, base64, base58 or bech32; see [`operator::encoding`])
+//! is how the keys and fields given as `HEX` are encoded; Ethereum addresses
+//! are always hex. Attestations in files are read in the encoding they name.
+//!
+//! Prints a verdict per check and exits with 0 on PASS, 1 on FAIL and 2 on
+//! usage errors. `--output json` prints one object instead, with every check
+//! grouped per attestation and the status fields of [`operator::status`].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::process;
+use std::time::Duration;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde_json::{json, Map, Value};
+
+use operator::attester::{
+    self, Attestation, AttestationPayload, Clock, EnclaveQuote, RngAttester, SystemClock,
+    DEFAULT_CLOCK_SKEW,
+};
+use operator::batch::{self, BATCH_ROOT_KIND};
+use operator::card_deck::{self, DeckProof};
+use operator::config;
+use operator::drand::{self, ChainInfo};
+use operator::encoding::Encoding;
+use operator::epochs::EpochCertificate;
+use operator::export;
+use operator::pool;
+use operator::primes::PrimeTrail;
+use operator::receipts::Receipt;
+use operator::revocation::{self, Revocation, RevocationList};
+use operator::status::{self, Failure, FailureClass, OutputMode};
+use operator::tasks::TaskOutcome;
+use operator::timelock::TimelockStatus;
+
+const USAGE: &str = "usage: rng-verify [--output text|json] [--encoding NAME] [--public-key HEX]...
+                  [--master-key HEX]... [--address HEX]... [--schnorr-key HEX]... [--quorum N]
+                  [--drand-info FILE] [--at TIME] [--skew DURATION] [--domain TAG]
+                  [--task-definition ID] [--crl FILE] [--deck FILE]... [--prime FILE]... FILE...
+       rng-verify [--output text|json] [--encoding NAME] --public-key HEX
+                  --random-number HEX --salt HEX --signature HEX
+                  [--secp256k1-signature HEX] [--schnorr-signature HEX]";
+
+struct Options {
+    trusted: Vec<VerifyingKey>,
+    /// Master keys whose certified epoch keys are trusted.
+    masters: Vec<VerifyingKey>,
+    addresses: Vec<[u8; 20]>,
+    /// Trusted BIP-340 x-only public keys.
+    schnorr_keys: Vec<[u8; 32]>,
+    quorum: Option<usize>,
+    drand: Option<ChainInfo>,
+    at: Option<u64>,
+    skew: Duration,
+    domain: Option<String>,
+    definition: Option<String>,
+    crl: Option<Vec<Revocation>>,
+    decks: Vec<DeckProof>,
+    primes: Vec<PrimeTrail>,
+    files: Vec<String>,
+    hex_fields: BTreeMap<&'static str, String>,
+    /// Encoding of the key and field flags.
+    encoding: Encoding,
+}
+
+/// One attestation to check, with the key it claims to be signed by.
+struct Candidate {
+    label: String,
+    attestation: Attestation,
+    public_key: VerifyingKey,
+    /// Ethereum address the attestation claims for its secp256k1 signature.
+    address: Option<[u8; 20]>,
+    /// x-only key the attestation claims for its Schnorr signature.
+    schnorr_key: Option<[u8; 32]>,
+    /// Certificate of the epoch key that signed.
+    certificate: Option<EpochCertificate>,
+    /// Quote of the enclave the key was generated in.
+    enclave_quote: Option<EnclaveQuote>,
+    task_id: Option<String>,
+    /// Signature binding a pooled value to `task_id`.
+    binding: Option<Signature>,
+    /// For a batch root, how many requests were checked against it.
+    batch: Option<Result<usize, String>>,
+    /// Check of the ABI form, when the outcome carries one.
+    abi: Option<Result<(), String>>,
+    /// Check of the receipt the attestation was served with: the values it
+    /// covers.
+    receipt: Option<Result<u64, String>>,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    // Known up front, so that even usage errors are reported as asked.
+    let output = args
+        .iter()
+        .position(|a| a == "--output")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|value| OutputMode::parse(value).ok())
+        .unwrap_or_default();
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(e) => usage_error(output, &format!("{}\n{}", e, USAGE), &e),
+    };
+    let candidates = match load(&options) {
+        Ok(candidates) if candidates.is_empty() => {
+            let e = "no attestations found in the input";
+            usage_error(output, e, e)
+        }
+        Ok(candidates) => candidates,
+        Err(e) => usage_error(output, &e, &e),
+    };
+
+    let mut report = Report::new(output);
+    if options.trusted.is_empty() && options.masters.is_empty() {
+        report.warn("no --public-key given; trusting the key embedded in each attestation");
+    }
+    let revocations = options
+        .crl
+        .as_deref()
+        .map(|crl| screen_revocations(crl, &options, &mut report));
+    let mut passed = true;
+    let mut agreeing: BTreeMap<Vec<u8>, BTreeSet<[u8; 32]>> = BTreeMap::new();
+    for candidate in &candidates {
+        report.section(&candidate.label);
+        let mut ok = check(candidate, &options, &mut report);
+        if let Some(revocations) = &revocations {
+            ok &= check_revocation(candidate, revocations, &options, &mut report);
+        }
+        if ok {
+            agreeing
+                .entry(candidate.attestation.payload.random_number.clone())
+                .or_default()
+                .insert(candidate.public_key.to_bytes());
+        }
+        passed &= ok;
+    }
+
+    for proof in &options.decks {
+        passed &= check_deck(proof, &candidates, &mut report);
+    }
+    for trail in &options.primes {
+        passed &= check_prime(trail, &candidates, &mut report);
+    }
+    if let Some(required) = options.quorum {
+        passed &= check_quorum(&agreeing, required, &mut report);
+    }
+    report.finish(passed)
+}
+
+/// Reports an argument or input error: `text` as before, or a `usage` status.
+fn usage_error(output: OutputMode, text: &str, message: &str) -> ! {
+    let failure = Failure::new(FailureClass::Usage, message);
+    match output {
+        OutputMode::Text => eprintln!("rng-verify: {}", text),
+        OutputMode::Json => println!("{}", status::status_json(&Err(failure.clone()))),
+    }
+    process::exit(failure.class.exit_code());
+}
+
+/// Where check results go: printed as they come in text mode, collected
+/// into one object in JSON mode.
+struct Report {
+    output: OutputMode,
+    warnings: Vec<String>,
+    sections: Vec<(String, Vec<Value>)>,
+}
+
+impl Report {
+    fn new(output: OutputMode) -> Self {
+        Report {
+            output,
+            warnings: Vec::new(),
+            sections: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, message: &str) {
+        match self.output {
+            OutputMode::Text => println!("WARN  {}", message),
+            OutputMode::Json => self.warnings.push(message.to_string()),
+        }
+    }
+
+    /// Starts the checks of `label`.
+    fn section(&mut self, label: &str) {
+        match self.output {
+            OutputMode::Text => println!("{}:", label),
+            OutputMode::Json => self.sections.push((label.to_string(), Vec::new())),
+        }
+    }
+
+    fn check(&mut self, ok: bool, message: &str) {
+        self.line(if ok { "PASS" } else { "FAIL" }, message);
+    }
+
+    /// One line of the current section; `kind` is PASS, FAIL, INFO or SKIP.
+    fn line(&mut self, kind: &str, message: &str) {
+        match self.output {
+            OutputMode::Text => println!("  {}  {}", kind, message),
+            OutputMode::Json => {
+                if let Some((_, checks)) = self.sections.last_mut() {
+                    checks.push(json!({
+                        "result": kind.to_lowercase(),
+                        "message": message,
+                    }));
+                }
+            }
+        }
+    }
+
+    /// Prints the verdict and exits with its code.
+    fn finish(self, passed: bool) -> ! {
+        let verdict = if passed { "PASS" } else { "FAIL" };
+        let result = if passed {
+            Ok(json!({}))
+        } else {
+            Err(Failure::new(
+                FailureClass::Verification,
+                "at least one check failed",
+            ))
+        };
+        match self.output {
+            OutputMode::Text => println!("VERDICT: {}", verdict),
+            OutputMode::Json => {
+                let mut object = status::status_json(&result);
+                object["verdict"] = json!(verdict);
+                object["warnings"] = json!(self.warnings);
+                object["attestations"] = self
+                    .sections
+                    .into_iter()
+                    .map(|(label, checks)| json!({ "label": label, "checks": checks }))
+                    .collect();
+                println!("{}", object);
+            }
+        }
+        process::exit(status::exit_code(&result));
+    }
+}
+
+fn check(candidate: &Candidate, options: &Options, report: &mut Report) -> bool {
+    let key = hex::encode(candidate.public_key.as_bytes());
+    let payload = &candidate.attestation.payload;
+    let mut ok = true;
+
+    // An epoch key is trusted when a trusted master certified it.
+    let mut certified_by = None;
+    if let Some(epoch) = payload.epoch {
+        match check_epoch(candidate, epoch, options) {
+            Ok(master) => {
+                report.check(
+                    true,
+                    &format!(
+                        "epoch {} key certified by master {}",
+                        epoch,
+                        hex::encode(master.as_bytes())
+                    ),
+                );
+                certified_by = Some(master);
+            }
+            Err(e) => {
+                report.check(false, &e);
+                ok = false;
+            }
+        }
+    }
+    if !options.trusted.is_empty() || !options.masters.is_empty() {
+        if options.trusted.contains(&candidate.public_key) {
+            report.check(true, &format!("signed by trusted key {}", key));
+        } else if certified_by.is_some_and(|master| options.masters.contains(&master)) {
+            report.check(true, &format!("signed by certified epoch key {}", key));
+        } else {
+            report.check(false, &format!("signed by untrusted key {}", key));
+            ok = false;
+        }
+    }
+
+    let mut steps = Vec::new();
+    if payload.vrf.is_some() {
+        steps.push("VRF");
+    }
+    if payload.client_entropy.is_some() {
+        steps.push("client mixing");
+    }
+    if payload.drand.is_some() {
+        steps.push("drand mixing");
+    }
+    if payload.vdf.is_some() {
+        steps.push("VDF");
+    }
+    match RngAttester::verify(&candidate.public_key, &candidate.attestation) {
+        Ok(()) if steps.is_empty() => report.check(true, "signature is valid"),
+        Ok(()) => report.check(
+            true,
+            &format!("signature and derivation ({}) are valid", steps.join(", ")),
+        ),
+        Err(e) => {
+            report.check(false, &e);
+            ok = false;
+        }
+    }
+
+    // The signature covers the quote's digest; the quote must name the key.
+    if let Some(quote) = &candidate.enclave_quote {
+        if quote.binds(&candidate.public_key) {
+            report.check(
+                true,
+                &format!("enclave quote ({}) binds the signing key", quote.platform),
+            );
+            report.line(
+                "SKIP",
+                "enclave quote signature not checked; verify it with the vendor",
+            );
+        } else {
+            report.check(
+                false,
+                &format!(
+                    "enclave quote ({}) does not bind the signing key",
+                    quote.platform
+                ),
+            );
+            ok = false;
+        }
+    }
+
+    if let Some(validity) = &payload.validity {
+        let now = options.at.unwrap_or_else(|| SystemClock.now_millis());
+        match validity.check(now, options.skew) {
+            Ok(()) => report.check(
+                true,
+                &format!(
+                    "valid at {} (window {} to {})",
+                    now, validity.not_before, validity.expires_at
+                ),
+            ),
+            Err(e) => {
+                report.check(false, &e);
+                ok = false;
+            }
+        }
+    }
+
+    match (&options.domain, &payload.domain) {
+        (Some(expected), Some(domain)) if expected == domain => {
+            report.check(true, &format!("bound to domain {}", domain))
+        }
+        (Some(expected), Some(domain)) => {
+            report.check(
+                false,
+                &format!("bound to domain {}, not {}", domain, expected),
+            );
+            ok = false;
+        }
+        (Some(expected), None) => {
+            report.check(false, &format!("not bound to domain {}", expected));
+            ok = false;
+        }
+        (None, Some(domain)) => report.line("INFO", &format!("bound to domain {}", domain)),
+        (None, None) => {}
+    }
+
+    match (&options.definition, &payload.definition) {
+        (Some(expected), Some(definition)) if expected == definition => {
+            report.check(true, &format!("made under task definition {}", definition))
+        }
+        (Some(expected), Some(definition)) => {
+            report.check(
+                false,
+                &format!(
+                    "made under task definition {}, not {}",
+                    definition, expected
+                ),
+            );
+            ok = false;
+        }
+        (Some(expected), None) => {
+            report.check(
+                false,
+                &format!("not made under task definition {}", expected),
+            );
+            ok = false;
+        }
+        (None, Some(definition)) => report.line(
+            "INFO",
+            &format!("made under task definition {}", definition),
+        ),
+        (None, None) => {}
+    }
+    if let Some(hash) = payload.hash {
+        report.line("INFO", &format!("signed over a {} digest", hash.as_str()));
+    }
+    if !payload.postprocess.is_empty() {
+        report.line(
+            "INFO",
+            &format!("post-processed by {}", payload.postprocess.join(", ")),
+        );
+    }
+
+    if let Some(slot) = payload.slot {
+        let bound = match (&candidate.task_id, &candidate.binding) {
+            (Some(task_id), Some(binding)) => pool::verify_binding(
+                &candidate.public_key,
+                task_id,
+                &candidate.attestation,
+                binding,
+            )
+            .map(|()| format!("pool slot {} is bound to task {}", slot, task_id)),
+            _ => Err(format!(
+                "pooled value (slot {}) carries no task binding",
+                slot
+            )),
+        };
+        match bound {
+            Ok(message) => report.check(true, &message),
+            Err(e) => {
+                report.check(false, &e);
+                ok = false;
+            }
+        }
+    }
+
+    match &candidate.batch {
+        Some(Ok(count)) => report.check(
+            true,
+            &format!(
+                "{} batched request(s) are included in the signed root",
+                count
+            ),
+        ),
+        Some(Err(e)) => {
+            report.check(false, e);
+            ok = false;
+        }
+        None => {}
+    }
+
+    match &candidate.abi {
+        Some(Ok(())) => report.check(
+            true,
+            "ABI encoding matches the attestation and its signature recovers to the attested address",
+        ),
+        Some(Err(e)) => {
+            report.check(false, e);
+            ok = false;
+        }
+        None => {}
+    }
+
+    match &candidate.receipt {
+        Some(Ok(count)) => report.check(
+            true,
+            &format!("served under a signed receipt for {} value(s)", count),
+        ),
+        Some(Err(e)) => {
+            report.check(false, e);
+            ok = false;
+        }
+        None => {}
+    }
+
+    if let Some(metadata) = &payload.metadata {
+        report.line(
+            "INFO",
+            &format!(
+                "produced by operator {} on chain {} from {} (config {})",
+                metadata.version,
+                metadata.chain_id,
+                metadata.entropy_sources.join(" + "),
+                hex::encode(metadata.config_hash)
+            ),
+        );
+        if metadata.signing_scheme.contains("secp256k1")
+            && candidate.attestation.secp256k1_signature.is_none()
+        {
+            report.check(
+                false,
+                &format!(
+                    "signing scheme {} but no secp256k1 signature",
+                    metadata.signing_scheme
+                ),
+            );
+            ok = false;
+        }
+        if metadata.signing_scheme.contains("schnorr")
+            && candidate.attestation.schnorr_signature.is_none()
+        {
+            report.check(
+                false,
+                &format!(
+                    "signing scheme {} but no Schnorr signature",
+                    metadata.signing_scheme
+                ),
+            );
+            ok = false;
+        }
+    }
+
+    ok &= check_secp256k1(candidate, options, report);
+    ok &= check_schnorr(candidate, options, report);
+
+    if let Some(round) = &payload.drand {
+        match &options.drand {
+            Some(chain) => {
+                match drand::verify_attestation(
+                    &candidate.public_key,
+                    &candidate.attestation,
+                    chain,
+                ) {
+                    Ok(()) => report.check(
+                        true,
+                        &format!(
+                            "drand round {} carries a valid group signature",
+                            round.beacon.round
+                        ),
+                    ),
+                    Err(e) => {
+                        report.check(false, &e);
+                        ok = false;
+                    }
+                }
+            }
+            None => report.line(
+                "SKIP",
+                &format!(
+                    "drand round {} signature not checked; pass --drand-info",
+                    round.beacon.round
+                ),
+            ),
+        }
+    }
+    ok
+}
+
+/// The statements of a revocation list that verify, with their signers.
+/// Gaps in the serials and statements by untrusted keys are warned about.
+fn screen_revocations(
+    crl: &[Revocation],
+    options: &Options,
+    report: &mut Report,
+) -> Vec<(Revocation, VerifyingKey)> {
+    let mut screened = Vec::new();
+    for (expected, revocation) in (1..).zip(crl) {
+        if revocation.serial != expected {
+            report.warn(&format!(
+                "revocation list skips from serial {} to {}; statements may be missing",
+                expected, revocation.serial
+            ));
+        }
+        match revocation.verify() {
+            Ok(key) if options.trusted.is_empty() || options.trusted.contains(&key) => {
+                screened.push((revocation.clone(), key))
+            }
+            Ok(key) => report.warn(&format!(
+                "ignoring revocation #{} by untrusted key {}",
+                revocation.serial,
+                hex::encode(key.as_bytes())
+            )),
+            Err(e) => report.warn(&format!(
+                "ignoring revocation #{}: {}",
+                revocation.serial, e
+            )),
+        }
+    }
+    screened
+}
+
+fn check_revocation(
+    candidate: &Candidate,
+    revocations: &[(Revocation, VerifyingKey)],
+    options: &Options,
+    report: &mut Report,
+) -> bool {
+    let payload = &candidate.attestation.payload;
+    // Without trusted keys, only the attesting key can revoke its own work.
+    let applicable: Vec<Revocation> = revocations
+        .iter()
+        .filter(|(_, issuer)| !options.trusted.is_empty() || *issuer == candidate.public_key)
+        .map(|(r, _)| r.clone())
+        .collect();
+    let signed_at = payload.validity.as_ref().map(|v| v.not_before);
+    match revocation::find(
+        &applicable,
+        &payload.digest(),
+        &candidate.public_key,
+        signed_at,
+    ) {
+        Some(revocation) => {
+            report.check(
+                false,
+                &format!(
+                    "revoked by statement #{}: {}",
+                    revocation.serial, revocation.reason
+                ),
+            );
+            false
+        }
+        None => {
+            report.check(true, "not revoked");
+            true
+        }
+    }
+}
+
+/// Follows the signing key of an epoch-`epoch` attestation to its master.
+fn check_epoch(
+    candidate: &Candidate,
+    epoch: u64,
+    options: &Options,
+) -> Result<VerifyingKey, String> {
+    let certificate = candidate
+        .certificate
+        .as_ref()
+        .ok_or_else(|| format!("epoch {} attestation carries no key certificate", epoch))?;
+    let (master, key) = certificate.verify()?;
+    if key != candidate.public_key {
+        return Err(format!(
+            "epoch certificate is for key {}, not the signing key",
+            certificate.public_key
+        ));
+    }
+    if certificate.epoch != epoch {
+        return Err(format!(
+            "key is certified for epoch {}, not {}",
+            certificate.epoch, epoch
+        ));
+    }
+    if let Some(validity) = &candidate.attestation.payload.validity {
+        if !certificate.covers(validity.not_before, options.skew.as_millis() as u64) {
+            return Err(format!(
+                "signed at {}, outside epoch {} ({} to {})",
+                validity.not_before, epoch, certificate.not_before, certificate.not_after
+            ));
+        }
+    }
+    Ok(master)
+}
+
+fn check_secp256k1(candidate: &Candidate, options: &Options, report: &mut Report) -> bool {
+    let Some(signature) = &candidate.attestation.secp256k1_signature else {
+        if options.addresses.is_empty() {
+            return true;
+        }
+        report.check(false, "no secp256k1 signature, but --address was given");
+        return false;
+    };
+    let recovered =
+        match attester::recover_address(&candidate.attestation.payload.digest(), signature) {
+            Ok(address) => address,
+            Err(e) => {
+                report.check(false, &e);
+                return false;
+            }
+        };
+    let shown = format!("0x{}", hex::encode(recovered));
+    if candidate
+        .address
+        .is_some_and(|claimed| claimed != recovered)
+    {
+        report.check(
+            false,
+            &format!(
+                "secp256k1 signer {} differs from the attested address",
+                shown
+            ),
+        );
+        return false;
+    }
+    if !options.addresses.is_empty() && !options.addresses.contains(&recovered) {
+        report.check(false, &format!("secp256k1 signer {} is not trusted", shown));
+        return false;
+    }
+    report.check(true, &format!("secp256k1 signature recovers to {}", shown));
+    true
+}
+
+fn check_schnorr(candidate: &Candidate, options: &Options, report: &mut Report) -> bool {
+    if candidate.attestation.schnorr_signature.is_none() {
+        if options.schnorr_keys.is_empty() {
+            return true;
+        }
+        report.check(false, "no Schnorr signature, but --schnorr-key was given");
+        return false;
+    }
+    // Hex fields carry no key of their own; try the trusted ones.
+    let keys = match candidate.schnorr_key {
+        Some(claimed) => vec![claimed],
+        None => options.schnorr_keys.clone(),
+    };
+    let Some(key) = keys
+        .iter()
+        .find(|key| RngAttester::verify_schnorr(key, &candidate.attestation).is_ok())
+    else {
+        let e = match keys.as_slice() {
+            [] => "Schnorr signature present, but no x-only key to check it; pass --schnorr-key"
+                .to_string(),
+            [key] => RngAttester::verify_schnorr(key, &candidate.attestation)
+                .expect_err("verification failed above"),
+            _ => "Schnorr signature matches none of the --schnorr-key keys".to_string(),
+        };
+        report.check(false, &e);
+        return false;
+    };
+    let shown = hex::encode(key);
+    if !options.schnorr_keys.is_empty() && !options.schnorr_keys.contains(key) {
+        report.check(false, &format!("Schnorr signer {} is not trusted", shown));
+        return false;
+    }
+    report.check(
+        true,
+        &format!("Schnorr signature is valid for x-only key {}", shown),
+    );
+    true
+}
+
+fn check_deck(proof: &DeckProof, candidates: &[Candidate], report: &mut Report) -> bool {
+    report.section(&format!("deck hand {}", proof.hand));
+    let Some(candidate) = candidates
+        .iter()
+        .find(|c| hex::encode(c.attestation.payload.digest()) == proof.attestation)
+    else {
+        report.check(
+            false,
+            &format!("attestation {} is not among the inputs", proof.attestation),
+        );
+        return false;
+    };
+    match card_deck::verify(proof, &candidate.public_key, &candidate.attestation) {
+        Ok(()) => {
+            report.check(
+                true,
+                &format!(
+                    "{}-card order follows from {}",
+                    proof.deck_size, candidate.label
+                ),
+            );
+            true
+        }
+        Err(e) => {
+            report.check(false, &e);
+            false
+        }
+    }
+}
+
+fn check_prime(trail: &PrimeTrail, candidates: &[Candidate], report: &mut Report) -> bool {
+    report.section(&format!("{}-bit prime", trail.bits));
+    let Some(candidate) = candidates
+        .iter()
+        .find(|c| hex::encode(&c.attestation.payload.random_number) == trail.seed)
+    else {
+        report.check(false, "seed is not the random number of any input");
+        return false;
+    };
+    match trail.verify() {
+        Ok(_) => {
+            report.check(
+                true,
+                &format!(
+                    "found from {} after {} rejected candidate(s)",
+                    candidate.label, trail.rejected
+                ),
+            );
+            true
+        }
+        Err(e) => {
+            report.check(false, &e);
+            false
+        }
+    }
+}
+
+fn check_quorum(
+    agreeing: &BTreeMap<Vec<u8>, BTreeSet<[u8; 32]>>,
+    required: usize,
+    report: &mut Report,
+) -> bool {
+    report.section("quorum");
+    if agreeing.len() > 1 {
+        report.check(
+            false,
+            &format!(
+                "valid attestations disagree on {} different values",
+                agreeing.len()
+            ),
+        );
+        return false;
+    }
+    let (value, operators) = match agreeing.iter().next() {
+        Some(entry) => entry,
+        None => {
+            report.check(false, "no valid attestations to form a quorum");
+            return false;
+        }
+    };
+    let ok = operators.len() >= required;
+    report.check(
+        ok,
+        &format!(
+            "{} of {} required operators attest {}",
+            operators.len(),
+            required,
+            hex::encode(value)
+        ),
+    );
+    ok
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    // Known up front, so that it applies to keys given before it too.
+    let encoding = match args.iter().position(|a| a == "--encoding") {
+        Some(i) => Encoding::parse(args.get(i + 1).ok_or("--encoding needs a value")?)?,
+        None => Encoding::Hex,
+    };
+    let mut options = Options {
+        trusted: Vec::new(),
+        masters: Vec::new(),
+        addresses: Vec::new(),
+        schnorr_keys: Vec::new(),
+        quorum: None,
+        drand: None,
+        at: None,
+        skew: DEFAULT_CLOCK_SKEW,
+        domain: None,
+        definition: None,
+        crl: None,
+        decks: Vec::new(),
+        primes: Vec::new(),
+        files: Vec::new(),
+        hex_fields: BTreeMap::new(),
+        encoding,
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--public-key" => options.trusted.push(parse_key(&value()?, encoding)?),
+            "--master-key" => options.masters.push(parse_key(&value()?, encoding)?),
+            "--address" => options.addresses.push(parse_address(&value()?)?),
+            "--schnorr-key" => options
+                .schnorr_keys
+                .push(parse_x_only(&value()?, encoding)?),
+            "--quorum" => {
+                let n = value()?
+                    .parse::<usize>()
+                    .map_err(|_| "--quorum needs a positive integer".to_string())?;
+                if n == 0 {
+                    return Err("--quorum needs a positive integer".to_string());
+                }
+                options.quorum = Some(n);
+            }
+            "--encoding" => {
+                value()?;
+            }
+            // Already applied by `main`; only validated here.
+            "--output" => {
+                OutputMode::parse(&value()?).map_err(|f| f.message)?;
+            }
+            "--drand-info" => options.drand = Some(ChainInfo::from_json(&read_input(&value()?)?)?),
+            "--at" => options.at = Some(export::parse_time(&value()?)?),
+            "--skew" => options.skew = config::parse_duration(&value()?)?,
+            "--domain" => options.domain = Some(value()?),
+            "--task-definition" => options.definition = Some(value()?),
+            "--crl" => {
+                let list: RevocationList = serde_json::from_str(&read_input(&value()?)?)
+                    .map_err(|e| format!("invalid revocation list: {}", e))?;
+                options.crl = Some(list.revocations);
+            }
+            "--deck" => {
+                let raw = read_input(&value()?)?;
+                let proofs = match serde_json::from_str::<Vec<DeckProof>>(&raw) {
+                    Ok(proofs) => proofs,
+                    Err(_) => vec![serde_json::from_str(&raw)
+                        .map_err(|e| format!("invalid deck proof: {}", e))?],
+                };
+                options.decks.extend(proofs);
+            }
+            "--prime" => {
+                let raw = read_input(&value()?)?;
+                options.primes.push(
+                    serde_json::from_str(&raw)
+                        .map_err(|e| format!("invalid prime trail: {}", e))?,
+                );
+            }
+            "--random-number" => {
+                options.hex_fields.insert("random-number", value()?);
+            }
+            "--salt" => {
+                options.hex_fields.insert("salt", value()?);
+            }
+            "--signature" => {
+                options.hex_fields.insert("signature", value()?);
+            }
+            "--secp256k1-signature" => {
+                options.hex_fields.insert("secp256k1-signature", value()?);
+            }
+            "--schnorr-signature" => {
+                options.hex_fields.insert("schnorr-signature", value()?);
+            }
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            _ => options.files.push(arg),
+        }
+    }
+    if options.quorum.is_some() && options.trusted.is_empty() && options.masters.is_empty() {
+        return Err(
+            "--quorum requires the operator set via --public-key or --master-key".to_string(),
+        );
+    }
+    if !options.hex_fields.is_empty() && !options.files.is_empty() {
+        return Err("pass either attestation files or hex fields, not both".to_string());
+    }
+    if options.hex_fields.is_empty() && options.files.is_empty() {
+        return Err("no attestation given".to_string());
+    }
+    Ok(options)
+}
+
+fn load(options: &Options) -> Result<Vec<Candidate>, String> {
+    if !options.hex_fields.is_empty() {
+        return Ok(vec![from_hex_fields(options)?]);
+    }
+    let mut candidates = Vec::new();
+    for file in &options.files {
+        let raw = read_input(file)?;
+        let documents: Vec<Value> = match serde_json::from_str(&raw) {
+            Ok(value) => vec![value],
+            Err(_) => raw
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .enumerate()
+                .map(|(n, line)| {
+                    serde_json::from_str(line)
+                        .map_err(|e| format!("{} line {}: invalid JSON: {}", file, n + 1, e))
+                })
+                .collect::<Result<_, _>>()?,
+        };
+        for (n, document) in documents.into_iter().enumerate() {
+            let document = if document.get("commitment").is_some() {
+                open_timelock(document).map_err(|e| format!("{} record {}: {}", file, n + 1, e))?
+            } else {
+                document
+            };
+            let (documents, receipt) =
+                split_receipt(document).map_err(|e| format!("{} record {}: {}", file, n + 1, e))?;
+            let mut outcomes = Vec::new();
+            for document in documents {
+                let Some(outcome) = to_outcome(document) else {
+                    continue;
+                };
+                let outcome: TaskOutcome = serde_json::from_value(outcome)
+                    .map_err(|e| format!("{} record {}: {}", file, n + 1, e))?;
+                outcomes.push(outcome.encoded(Encoding::Hex)?);
+            }
+            let receipt = receipt.map(|receipt| check_receipt(&receipt, &outcomes, options));
+            for outcome in outcomes {
+                let attestation = outcome.to_attestation()?;
+                let binding = match &outcome.binding {
+                    Some(binding) => {
+                        let bytes: [u8; 64] = hex::decode(binding)
+                            .map_err(|e| format!("invalid binding: {}", e))?
+                            .try_into()
+                            .map_err(|_| "binding must be 64 bytes".to_string())?;
+                        Some(Signature::from_bytes(&bytes))
+                    }
+                    None => None,
+                };
+                candidates.push(Candidate {
+                    label: format!("{} task {}", file, outcome.task_id),
+                    attestation,
+                    public_key: parse_key(&outcome.public_key, Encoding::Hex)?,
+                    address: outcome
+                        .secp256k1_address
+                        .as_deref()
+                        .map(parse_address)
+                        .transpose()?,
+                    schnorr_key: outcome
+                        .schnorr_public_key
+                        .as_deref()
+                        .map(|key| parse_x_only(key, Encoding::Hex))
+                        .transpose()?,
+                    certificate: outcome.epoch_certificate.clone(),
+                    enclave_quote: outcome.enclave_quote()?,
+                    batch: (outcome.batch.is_some()
+                        || outcome.kind.as_deref() == Some(BATCH_ROOT_KIND))
+                    .then(|| batch::verify_outcome(&outcome)),
+                    abi: outcome.abi.as_ref().map(|abi| abi.verify(&outcome)),
+                    receipt: receipt.clone(),
+                    task_id: Some(outcome.task_id),
+                    binding,
+                });
+            }
+        }
+    }
+    Ok(candidates)
+}
+
+/// Splits a document served with a receipt into the outcomes it covers and
+/// the receipt: an `/attestations/export` response, or a batch response.
+fn split_receipt(document: Value) -> Result<(Vec<Value>, Option<Receipt>), String> {
+    let Value::Object(mut fields) = document else {
+        return Ok((vec![document], None));
+    };
+    let Some(receipt) = fields.remove("receipt") else {
+        return Ok((vec![Value::Object(fields)], None));
+    };
+    let receipt: Receipt =
+        serde_json::from_value(receipt).map_err(|e| format!("invalid receipt: {}", e))?;
+    match fields.remove("attestations") {
+        Some(Value::Array(outcomes)) => Ok((outcomes, Some(receipt))),
+        Some(_) => Err("attestations must be an array".to_string()),
+        None => Ok((vec![Value::Object(fields)], Some(receipt))),
+    }
+}
+
+/// Checks that `receipt` covers exactly `outcomes` and, given keys to trust,
+/// was signed by one; returns the values it covers.
+fn check_receipt(
+    receipt: &Receipt,
+    outcomes: &[TaskOutcome],
+    options: &Options,
+) -> Result<u64, String> {
+    let key = receipt.check(outcomes)?;
+    if !options.trusted.is_empty() || !options.masters.is_empty() {
+        let certified = match &receipt.epoch_certificate {
+            Some(certificate) => options.masters.contains(&certificate.verify()?.0),
+            None => false,
+        };
+        if !options.trusted.contains(&key) && !certified {
+            return Err(format!(
+                "receipt signed by untrusted key {}",
+                hex::encode(key.as_bytes())
+            ));
+        }
+    }
+    Ok(receipt.count)
+}
+
+/// Decrypts the outcome of a released timelock (`GET /timelock/{task}`),
+/// checking that the commitment was signed by the key that attested it.
+fn open_timelock(document: Value) -> Result<Value, String> {
+    let status: TimelockStatus = serde_json::from_value(document).map_err(|e| e.to_string())?;
+    let (outcome, sealed_by) = status.open()?;
+    if parse_key(&outcome.public_key, Encoding::Hex)? != sealed_by {
+        return Err(format!(
+            "timelock of task {} was sealed by another key than attested it",
+            outcome.task_id
+        ));
+    }
+    serde_json::to_value(outcome).map_err(|e| e.to_string())
+}
+
+/// Maps a `/task/execute` response or an export row to a `TaskOutcome` value.
+/// Returns `None` for rows that carry no attestation.
+fn to_outcome(document: Value) -> Option<Value> {
+    let Value::Object(mut fields) = document else {
+        return None;
+    };
+    match fields.get("record").and_then(Value::as_str) {
+        None => Some(Value::Object(fields)),
+        Some("attestation") => {
+            let mut outcome = match fields.remove("extensions") {
+                Some(Value::Object(extensions)) => extensions,
+                _ => Map::new(),
+            };
+            for (from, to) in [
+                ("task_id", "taskId"),
+                ("random_number", "randomNumber"),
+                ("salt", "salt"),
+                ("signature", "signature"),
+                ("public_key", "publicKey"),
+            ] {
+                if let Some(value) = fields.remove(from) {
+                    outcome.insert(to.to_string(), value);
+                }
+            }
+            Some(Value::Object(outcome))
+        }
+        Some(_) => None,
+    }
+}
+
+fn from_hex_fields(options: &Options) -> Result<Candidate, String> {
+    let field = |name: &str| {
+        let value = options
+            .hex_fields
+            .get(name)
+            .ok_or_else(|| format!("--{} is required with hex fields", name))?;
+        options
+            .encoding
+            .decode(value)
+            .map_err(|e| format!("--{}: {}", name, e))
+    };
+    let [public_key] = options.trusted[..] else {
+        return Err("hex fields need exactly one --public-key".to_string());
+    };
+    let signature: [u8; 64] = field("signature")?
+        .try_into()
+        .map_err(|_| "--signature must be 64 bytes".to_string())?;
+    let secp256k1_signature = if options.hex_fields.contains_key("secp256k1-signature") {
+        Some(
+            field("secp256k1-signature")?
+                .try_into()
+                .map_err(|_| "--secp256k1-signature must be 65 bytes".to_string())?,
+        )
+    } else {
+        None
+    };
+    let schnorr_signature = if options.hex_fields.contains_key("schnorr-signature") {
+        Some(
+            field("schnorr-signature")?
+                .try_into()
+                .map_err(|_| "--schnorr-signature must be 64 bytes".to_string())?,
+        )
+    } else {
+        None
+    };
+    let mut payload = AttestationPayload::new(field("random-number")?);
+    payload.salt = field("salt")?;
+    Ok(Candidate {
+        label: "attestation".to_string(),
+        attestation: Attestation {
+            payload,
+            signature: Signature::from_bytes(&signature),
+            secp256k1_signature,
+            schnorr_signature,
+        },
+        public_key,
+        address: None,
+        schnorr_key: None,
+        certificate: None,
+        enclave_quote: None,
+        task_id: None,
+        binding: None,
+        batch: None,
+        abi: None,
+        receipt: None,
+    })
+}
+
+fn parse_key(value: &str, encoding: Encoding) -> Result<VerifyingKey, String> {
+    let bytes: [u8; 32] = encoding
+        .decode(value)
+        .map_err(|e| format!("invalid public key {}: {}", value, e))?
+        .try_into()
+        .map_err(|_| format!("public key {} must be 32 bytes", value))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("invalid public key {}: {}", value, e))
+}
+
+fn parse_address(value: &str) -> Result<[u8; 20], String> {
+    hex::decode(value.trim_start_matches("0x"))
+        .map_err(|e| format!("invalid address {}: {}", value, e))?
+        .try_into()
+        .map_err(|_| format!("address {} must be 20 bytes", value))
+}
+
+fn parse_x_only(value: &str, encoding: Encoding) -> Result<[u8; 32], String> {
+    encoding
+        .decode(value)
+        .map_err(|e| format!("invalid x-only key {}: {}", value, e))?
+        .try_into()
+        .map_err(|_| format!("x-only key {} must be 32 bytes", value))
+}
+
+fn read_input(path: &str) -> Result<String, String> {
+    if path == "-" {
+        let mut raw = String::new();
+        std::io::stdin()
+            .read_to_string(&mut raw)
+            .map_err(|e| format!("failed to read stdin: {}", e))?;
+        return Ok(raw);
+    }
+    std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))
+}
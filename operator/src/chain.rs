@@ -0,0 +1,821 @@
+// src/chain.rs
+
+//! On-chain fulfillment through `RNGTaskManager.submitResult`.
+//!
+//! A [`ChainSubmitter`] signs an EIP-1559 transaction with the operator key
+//! for every attested outcome and sends it to `network.rpc_url`. Fees follow
+//! the [`GasPolicy`] of the configured chain:
+//!
+//! - the priority fee is either fixed, the node's `eth_maxPriorityFeePerGas`,
+//!   or a percentile of recent tips (`p50`, `p90`, ...) from `eth_feeHistory`,
+//!   never above `priority_fee_gwei` for the latter two;
+//! - while the base fee plus priority fee exceeds `max_fee_gwei`, or the
+//!   expected cost would overrun the budget of the current window, the
+//!   submission is deferred and retried every `chain.defer_poll`, for at most
+//!   `chain.max_deferral` and never past the outcome's `expires_at`. A task
+//!   that is still deferred then fails to submit and keeps its pending record.
+//!
+//! Spend is charged at submission as estimated gas times the effective gas
+//! price and persisted per chain, so budgets survive restarts.
+//!
+//! `submitResult` receives the task ID (a decimal or `0x` hex uint256), the
+//! random value as big-endian 32-byte words, the secp256k1 signature when the
+//! attester dual-signs (the Ed25519 signature otherwise) and the operator
+//! address as the only attester.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use k256::ecdsa::SigningKey;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+
+use crate::attester::ethereum_address;
+use crate::config::{self, ChainConfig, Config, GasPolicy};
+use crate::metrics::Metrics;
+use crate::resilience::{CallError, Resilience};
+use crate::storage::Storage;
+use crate::tasks::{unix_millis, Submitter, TaskOutcome};
+
+/// Storage collection holding each chain's spend in the current window.
+const GAS_SPEND: &str = "gas_spend";
+
+/// Storage collection recording the transaction sent for each task, keyed by
+/// [`crate::tenants::task_key`], with the `traceparent` of the submission
+/// when it was traced.
+pub const CHAIN_SUBMISSIONS: &str = "chain_submissions";
+
+const WEI_PER_GWEI: f64 = 1e9;
+
+/// Blocks of fee history a percentile priority fee is taken over.
+const FEE_HISTORY_BLOCKS: u64 = 20;
+
+/// How the priority fee (tip) of a transaction is chosen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriorityFee {
+    Fixed,
+    Network,
+    Percentile(u8),
+}
+
+impl PriorityFee {
+    /// Parses `fixed`, `network` or `pNN` with `NN` between 1 and 99.
+    pub fn parse(strategy: &str) -> Result<Self, String> {
+        match strategy {
+            "fixed" => Ok(PriorityFee::Fixed),
+            "network" => Ok(PriorityFee::Network),
+            _ => strategy
+                .strip_prefix('p')
+                .and_then(|p| p.parse::<u8>().ok())
+                .filter(|p| (1..=99).contains(p))
+                .map(PriorityFee::Percentile)
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown priority fee strategy '{}': expected fixed, network or pNN",
+                        strategy
+                    )
+                }),
+        }
+    }
+}
+
+/// A [`GasPolicy`] with its amounts converted to wei.
+struct Policy {
+    max_fee: u128,
+    priority_fee: PriorityFee,
+    priority_cap: u128,
+    budget: Option<u128>,
+    window: Duration,
+}
+
+impl Policy {
+    fn from_config(policy: &GasPolicy) -> Result<Self, String> {
+        Ok(Policy {
+            max_fee: gwei_to_wei(policy.max_fee_gwei),
+            priority_fee: PriorityFee::parse(&policy.priority_fee)?,
+            priority_cap: gwei_to_wei(policy.priority_fee_gwei),
+            budget: policy.budget_gwei.map(gwei_to_wei),
+            window: config::parse_duration(&policy.budget_window)?,
+        })
+    }
+}
+
+/// Spend of one chain in its current budget window.
+struct Spend {
+    window_start: u64,
+    spent: u128,
+}
+
+/// Where a transaction recorded in [`CHAIN_SUBMISSIONS`] stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmissionStatus {
+    /// Sent but not in a canonical block.
+    Pending,
+    /// In a canonical block, short of the required confirmations.
+    Included,
+    /// Buried under enough blocks to be treated as final.
+    Confirmed,
+    /// Mined, but the contract rejected it.
+    Reverted,
+    /// Dropped more often than `chain.max_resubmissions` allows.
+    Abandoned,
+}
+
+/// The parts of a transaction receipt fulfillment tracking needs.
+pub(crate) struct Receipt {
+    pub block_number: u64,
+    pub block_hash: String,
+    pub succeeded: bool,
+    /// Gas used times the effective gas price, in wei.
+    pub cost: u128,
+}
+
+/// Fees quoted for the next block.
+struct Quote {
+    base_fee: u128,
+    priority_fee: u128,
+}
+
+/// Submits outcomes to the task manager contract on `network.chain_id`.
+pub struct ChainSubmitter {
+    rpc_url: String,
+    chain_id: u64,
+    contract: [u8; 20],
+    key: SigningKey,
+    address: [u8; 20],
+    policy: Policy,
+    gas_multiplier: f64,
+    max_deferral: Duration,
+    defer_poll: Duration,
+    storage: Arc<dyn Storage>,
+    spend: Mutex<Spend>,
+    sending: Mutex<()>,
+    agent: ureq::Agent,
+    resilience: Arc<Resilience>,
+    metrics: Arc<Metrics>,
+}
+
+impl ChainSubmitter {
+    /// Configured by the `chain` section, with the gas policy of
+    /// `network.chain_id` and the spend recorded for it in `storage`.
+    pub fn new(
+        settings: &Config,
+        storage: Arc<dyn Storage>,
+        resilience: Arc<Resilience>,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self, String> {
+        let chain: &ChainConfig = &settings.chain;
+        let chain_id = settings.network.chain_id;
+        let policy = Policy::from_config(chain.policy(chain_id))?;
+        let key_bytes = hex::decode(settings.operator.private_key.trim_start_matches("0x"))
+            .map_err(|e| format!("Invalid operator.private_key: {}", e))?;
+        let key = SigningKey::from_slice(&key_bytes)
+            .map_err(|e| format!("Invalid operator.private_key: {}", e))?;
+        let address = ethereum_address(key.verifying_key());
+        let spend = match storage.get(GAS_SPEND, &chain_id.to_string())? {
+            Some(state) => Spend {
+                window_start: state
+                    .get("windowStart")
+                    .and_then(Value::as_u64)
+                    .ok_or("Corrupt gas spend: missing windowStart")?,
+                spent: state
+                    .get("spentWei")
+                    .and_then(Value::as_str)
+                    .and_then(|s| s.parse().ok())
+                    .ok_or("Corrupt gas spend: missing spentWei")?,
+            },
+            None => Spend {
+                window_start: unix_millis(),
+                spent: 0,
+            },
+        };
+        Ok(ChainSubmitter {
+            rpc_url: settings.network.rpc_url.clone(),
+            chain_id,
+            contract: parse_address(&settings.contracts.task_manager)?,
+            key,
+            address,
+            policy,
+            gas_multiplier: chain.gas_multiplier,
+            max_deferral: config::parse_duration(&chain.max_deferral)?,
+            defer_poll: config::parse_duration(&chain.defer_poll)?,
+            storage,
+            spend: Mutex::new(spend),
+            sending: Mutex::new(()),
+            agent: ureq::AgentBuilder::new().build(),
+            resilience,
+            metrics,
+        })
+    }
+
+    /// Address transactions are sent from.
+    pub fn address(&self) -> [u8; 20] {
+        self.address
+    }
+
+    /// Waits until fees and budget allow sending `calldata`, returning the
+    /// quote and the gas estimate, or why the submission is still deferred
+    /// once it gives up.
+    fn wait_for_gas(&self, calldata: &[u8], outcome: &TaskOutcome) -> Result<(Quote, u64), String> {
+        let started = Instant::now();
+        loop {
+            let quote = self.quote()?;
+            let estimate = self.estimate_gas(calldata)?;
+            let reason = match self.check(&quote, estimate) {
+                Ok(()) => return Ok((quote, estimate)),
+                Err(reason) => reason,
+            };
+            let retry_at = unix_millis() + self.defer_poll.as_millis() as u64;
+            if started.elapsed() + self.defer_poll > self.max_deferral
+                || outcome.expires_at.is_some_and(|e| retry_at >= e)
+            {
+                self.metrics.inc_counter(
+                    "rng_chain_submissions_total",
+                    &[("outcome", "deferred")],
+                    1,
+                );
+                return Err(format!(
+                    "Submission of task {} deferred: {}",
+                    outcome.task_id, reason.1
+                ));
+            }
+            warn!(
+                "Deferring submission of task {} for {:?}: {}",
+                outcome.task_id, self.defer_poll, reason.1
+            );
+            self.metrics
+                .inc_counter("rng_chain_deferrals_total", &[("reason", reason.0)], 1);
+            thread::sleep(self.defer_poll);
+        }
+    }
+
+    /// Checks the fee cap and the budget for `gas` at `quote`; on failure
+    /// returns the metric label and a description.
+    fn check(&self, quote: &Quote, gas: u64) -> Result<(), (&'static str, String)> {
+        let price = quote.base_fee + quote.priority_fee;
+        if price > self.policy.max_fee {
+            return Err((
+                "fee_cap",
+                format!(
+                    "gas price {:.3} gwei exceeds the cap of {:.3} gwei",
+                    wei_to_gwei(price),
+                    wei_to_gwei(self.policy.max_fee)
+                ),
+            ));
+        }
+        let Some(budget) = self.policy.budget else {
+            return Ok(());
+        };
+        let mut spend = self.spend.lock().expect("spend lock poisoned");
+        self.roll_window(&mut spend);
+        let cost = gas as u128 * price;
+        let remaining = budget.saturating_sub(spend.spent);
+        self.metrics.set_gauge(
+            "rng_chain_budget_remaining_gwei",
+            &[("chain_id", &self.chain_id.to_string())],
+            wei_to_gwei(remaining),
+        );
+        if cost > remaining {
+            return Err((
+                "budget",
+                format!(
+                    "expected cost {:.0} gwei exceeds the {:.0} gwei left in the budget window",
+                    wei_to_gwei(cost),
+                    wei_to_gwei(remaining)
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Starts a new budget window once the current one has elapsed.
+    fn roll_window(&self, spend: &mut Spend) {
+        let window = self.policy.window.as_millis() as u64;
+        let now = unix_millis();
+        if now >= spend.window_start + window {
+            spend.window_start = now - (now - spend.window_start) % window.max(1);
+            spend.spent = 0;
+        }
+    }
+
+    /// Adds `cost` to the chain's spend and persists it.
+    fn charge(&self, cost: u128) -> Result<(), String> {
+        let mut spend = self.spend.lock().expect("spend lock poisoned");
+        self.roll_window(&mut spend);
+        spend.spent += cost;
+        self.metrics.inc_counter(
+            "rng_chain_gas_spent_gwei_total",
+            &[("chain_id", &self.chain_id.to_string())],
+            wei_to_gwei(cost).round() as u64,
+        );
+        self.save_spend(&spend)
+    }
+
+    /// Replaces the `charged` amount in the chain's spend by the `actual`
+    /// cost, e.g. from a receipt, or 0 for a transaction that did not land.
+    pub(crate) fn settle(&self, charged: u128, actual: u128) -> Result<(), String> {
+        let mut spend = self.spend.lock().expect("spend lock poisoned");
+        spend.spent = spend.spent.saturating_sub(charged) + actual;
+        self.save_spend(&spend)
+    }
+
+    fn save_spend(&self, spend: &Spend) -> Result<(), String> {
+        let chain_id = self.chain_id.to_string();
+        self.storage.put(
+            GAS_SPEND,
+            &chain_id,
+            json!({ "windowStart": spend.window_start, "spentWei": spend.spent.to_string() }),
+        )?;
+        if let Some(budget) = self.policy.budget {
+            self.metrics.set_gauge(
+                "rng_chain_budget_remaining_gwei",
+                &[("chain_id", &chain_id)],
+                wei_to_gwei(budget.saturating_sub(spend.spent)),
+            );
+        }
+        Ok(())
+    }
+
+    /// Counts the final cost of a confirmed fulfillment.
+    pub(crate) fn record_gas_used(&self, cost: u128) {
+        self.metrics.inc_counter(
+            "rng_chain_gas_used_gwei_total",
+            &[("chain_id", &self.chain_id.to_string())],
+            wei_to_gwei(cost).round() as u64,
+        );
+    }
+
+    pub(crate) fn block_number(&self) -> Result<u64, String> {
+        let number = self.rpc("eth_blockNumber", json!([]))?;
+        parse_u64(&number, "eth_blockNumber")
+    }
+
+    /// Hash of the canonical block at `number`, if the node has one.
+    pub(crate) fn block_hash(&self, number: u64) -> Result<Option<String>, String> {
+        let block = self.rpc(
+            "eth_getBlockByNumber",
+            json!([format!("0x{:x}", number), false]),
+        )?;
+        Ok(block
+            .get("hash")
+            .and_then(Value::as_str)
+            .map(str::to_string))
+    }
+
+    /// Receipt of `hash` if the transaction is in a canonical block.
+    pub(crate) fn receipt(&self, hash: &str) -> Result<Option<Receipt>, String> {
+        let receipt = self.rpc("eth_getTransactionReceipt", json!([hash]))?;
+        if receipt.is_null() {
+            return Ok(None);
+        }
+        let quantity = |field: &str| {
+            receipt
+                .get(field)
+                .and_then(Value::as_str)
+                .ok_or_else(|| format!("Receipt of {} has no {}", hash, field))
+                .and_then(parse_quantity)
+        };
+        Ok(Some(Receipt {
+            block_number: quantity("blockNumber")? as u64,
+            block_hash: receipt
+                .get("blockHash")
+                .and_then(Value::as_str)
+                .ok_or_else(|| format!("Receipt of {} has no blockHash", hash))?
+                .to_string(),
+            succeeded: quantity("status")? == 1,
+            cost: quantity("gasUsed")? * quantity("effectiveGasPrice")?,
+        }))
+    }
+
+    /// Whether the node still knows transaction `hash`, mined or pending.
+    pub(crate) fn is_known(&self, hash: &str) -> Result<bool, String> {
+        Ok(!self
+            .rpc("eth_getTransactionByHash", json!([hash]))?
+            .is_null())
+    }
+
+    /// The transaction to send `calldata` with at `quote`, using the next
+    /// pending nonce.
+    fn transaction(
+        &self,
+        calldata: Vec<u8>,
+        quote: &Quote,
+        estimate: u64,
+    ) -> Result<Transaction, String> {
+        Ok(Transaction {
+            chain_id: self.chain_id,
+            nonce: self.nonce()?,
+            max_priority_fee: quote.priority_fee,
+            max_fee: (2 * quote.base_fee + quote.priority_fee).min(self.policy.max_fee),
+            gas: (estimate as f64 * self.gas_multiplier).ceil() as u64,
+            to: self.contract,
+            data: calldata,
+        })
+    }
+
+    fn quote(&self) -> Result<Quote, String> {
+        let block = self.rpc("eth_getBlockByNumber", json!(["latest", false]))?;
+        let base_fee = block
+            .get("baseFeePerGas")
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                "Latest block has no baseFeePerGas: the chain does not support EIP-1559".to_string()
+            })
+            .and_then(parse_quantity)?;
+        let priority_fee = match self.policy.priority_fee {
+            PriorityFee::Fixed => self.policy.priority_cap,
+            PriorityFee::Network => {
+                let tip = self.rpc("eth_maxPriorityFeePerGas", json!([]))?;
+                parse_quantity(
+                    tip.as_str()
+                        .ok_or("Invalid eth_maxPriorityFeePerGas result")?,
+                )?
+                .min(self.policy.priority_cap)
+            }
+            PriorityFee::Percentile(percentile) => {
+                let history = self.rpc(
+                    "eth_feeHistory",
+                    json!([
+                        format!("0x{:x}", FEE_HISTORY_BLOCKS),
+                        "latest",
+                        [percentile]
+                    ]),
+                )?;
+                let tips = history
+                    .get("reward")
+                    .and_then(Value::as_array)
+                    .ok_or("eth_feeHistory returned no rewards")?
+                    .iter()
+                    .filter_map(|block| block.get(0).and_then(Value::as_str))
+                    .map(parse_quantity)
+                    .collect::<Result<Vec<u128>, String>>()?;
+                let mean = if tips.is_empty() {
+                    0
+                } else {
+                    tips.iter().sum::<u128>() / tips.len() as u128
+                };
+                mean.min(self.policy.priority_cap)
+            }
+        };
+        let chain_id = self.chain_id.to_string();
+        self.metrics.set_gauge(
+            "rng_chain_base_fee_gwei",
+            &[("chain_id", &chain_id)],
+            wei_to_gwei(base_fee),
+        );
+        self.metrics.set_gauge(
+            "rng_chain_priority_fee_gwei",
+            &[("chain_id", &chain_id)],
+            wei_to_gwei(priority_fee),
+        );
+        Ok(Quote {
+            base_fee,
+            priority_fee,
+        })
+    }
+
+    fn estimate_gas(&self, calldata: &[u8]) -> Result<u64, String> {
+        let estimate = self.rpc(
+            "eth_estimateGas",
+            json!([{
+                "from": format!("0x{}", hex::encode(self.address)),
+                "to": format!("0x{}", hex::encode(self.contract)),
+                "data": format!("0x{}", hex::encode(calldata)),
+            }]),
+        )?;
+        parse_u64(&estimate, "eth_estimateGas")
+    }
+
+    fn nonce(&self) -> Result<u64, String> {
+        let count = self.rpc(
+            "eth_getTransactionCount",
+            json!([format!("0x{}", hex::encode(self.address)), "pending"]),
+        )?;
+        parse_u64(&count, "eth_getTransactionCount")
+    }
+
+    /// Calls `method` on the node; errors it answers with are permanent.
+    fn rpc(&self, method: &str, params: Value) -> Result<Value, String> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response = self.resilience.call("chain", |remaining| {
+            match self
+                .agent
+                .post(&self.rpc_url)
+                .timeout(remaining)
+                .set("Content-Type", "application/json")
+                .send_string(&body.to_string())
+            {
+                Ok(response) => response
+                    .into_string()
+                    .map_err(|e| CallError::Transient(e.to_string()))
+                    .and_then(|text| {
+                        serde_json::from_str::<Value>(&text)
+                            .map_err(|e| CallError::Transient(e.to_string()))
+                    }),
+                Err(ureq::Error::Status(code, _)) if code < 500 && code != 429 => {
+                    Err(CallError::Permanent(format!("HTTP {}", code)))
+                }
+                Err(e) => Err(CallError::Transient(e.to_string())),
+            }
+        })?;
+        if let Some(error) = response.get("error") {
+            return Err(format!(
+                "{} failed: {}",
+                method,
+                error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error")
+            ));
+        }
+        response
+            .get("result")
+            .cloned()
+            .ok_or_else(|| format!("{} returned no result", method))
+    }
+}
+
+impl Submitter for ChainSubmitter {
+    fn chain_height(&self) -> Result<Option<u64>, String> {
+        self.block_number().map(Some)
+    }
+
+    fn submit(&self, outcome: &TaskOutcome) -> Result<(), String> {
+        let calldata = submit_result_calldata(outcome, &self.address)?;
+        // One submission at a time, so pending nonces are not reused.
+        let _sending = self.sending.lock().expect("chain submitter lock poisoned");
+        let (quote, estimate) = self.wait_for_gas(&calldata, outcome)?;
+        let transaction = self.transaction(calldata, &quote, estimate)?;
+        let gas = transaction.gas;
+        let raw = transaction.sign(&self.key)?;
+        let hash = match self.rpc(
+            "eth_sendRawTransaction",
+            json!([format!("0x{}", hex::encode(&raw))]),
+        ) {
+            Ok(hash) => hash.as_str().unwrap_or_default().to_string(),
+            Err(e) => {
+                self.metrics.inc_counter(
+                    "rng_chain_submissions_total",
+                    &[("outcome", "failed")],
+                    1,
+                );
+                return Err(e);
+            }
+        };
+        // Charged at the price expected for the next block: the base fee
+        // can only rise by 12.5% per block, so this is close to what it costs.
+        let charged = estimate as u128 * (quote.base_fee + quote.priority_fee);
+        self.charge(charged)?;
+        // A task submitted before was dropped or reorged out; keep count.
+        let key = outcome.key();
+        let previous = self.storage.get(CHAIN_SUBMISSIONS, &key)?;
+        let resubmissions = match &previous {
+            Some(previous) => previous["resubmissions"].as_u64().unwrap_or(0) + 1,
+            None => 0,
+        };
+        // A resubmission stays in the trace of the first submission.
+        let traceparent = match outcome.trace {
+            Some(trace) => json!(trace.traceparent()),
+            None => previous.map_or(Value::Null, |p| p["traceparent"].clone()),
+        };
+        self.storage.put(
+            CHAIN_SUBMISSIONS,
+            &key,
+            json!({
+                "txHash": hash,
+                "chainId": self.chain_id,
+                "nonce": transaction.nonce,
+                "gasLimit": gas,
+                "maxFeePerGas": transaction.max_fee.to_string(),
+                "maxPriorityFeePerGas": transaction.max_priority_fee.to_string(),
+                "chargedWei": charged.to_string(),
+                "submittedAt": unix_millis(),
+                "status": SubmissionStatus::Pending,
+                "resubmissions": resubmissions,
+                "traceparent": traceparent,
+            }),
+        )?;
+        self.metrics.inc_counter(
+            "rng_chain_submissions_total",
+            &[("outcome", "submitted")],
+            1,
+        );
+        info!(
+            "Task {} submitted in transaction {} (nonce {}, {:.3} gwei)",
+            key,
+            hash,
+            transaction.nonce,
+            wei_to_gwei(quote.base_fee + quote.priority_fee)
+        );
+        Ok(())
+    }
+
+    /// Quotes fees and signs the transaction without sending it or waiting
+    /// out a deferral; `deferral` says why `submit` would wait.
+    fn dry_run(&self, outcome: &TaskOutcome) -> Result<Value, String> {
+        let calldata = submit_result_calldata(outcome, &self.address)?;
+        let quote = self.quote()?;
+        let estimate = self.estimate_gas(&calldata)?;
+        let deferral = self.check(&quote, estimate).err().map(|(_, reason)| reason);
+        let transaction = self.transaction(calldata, &quote, estimate)?;
+        let raw = transaction.sign(&self.key)?;
+        Ok(json!({
+            "chainId": self.chain_id,
+            "from": format!("0x{}", hex::encode(self.address)),
+            "to": format!("0x{}", hex::encode(transaction.to)),
+            "nonce": transaction.nonce,
+            "gasLimit": transaction.gas,
+            "maxFeePerGas": transaction.max_fee.to_string(),
+            "maxPriorityFeePerGas": transaction.max_priority_fee.to_string(),
+            "data": format!("0x{}", hex::encode(&transaction.data)),
+            "rawTransaction": format!("0x{}", hex::encode(raw)),
+            "deferral": deferral,
+        }))
+    }
+}
+
+/// ABI-encoded `submitResult(uint256,uint256[],bytes,address[])` call.
+fn submit_result_calldata(outcome: &TaskOutcome, operator: &[u8; 20]) -> Result<Vec<u8>, String> {
+    let task_id = parse_uint256(&outcome.task_id)?;
+    let random = hex::decode(&outcome.random_number)
+        .map_err(|e| format!("Invalid random number hex: {}", e))?;
+    let signature = hex::decode(
+        outcome
+            .secp256k1_signature
+            .as_deref()
+            .unwrap_or(&outcome.signature),
+    )
+    .map_err(|e| format!("Invalid signature hex: {}", e))?;
+    let words: Vec<[u8; 32]> = random.chunks(32).map(left_pad).collect();
+
+    let numbers_offset = 4 * 32;
+    let signature_offset = numbers_offset + 32 * (1 + words.len());
+    let attesters_offset = signature_offset + 32 * (1 + signature.len().div_ceil(32));
+
+    let selector = Keccak256::digest(b"submitResult(uint256,uint256[],bytes,address[])");
+    let mut data = selector[..4].to_vec();
+    data.extend_from_slice(&task_id);
+    data.extend_from_slice(&left_pad(&(numbers_offset as u64).to_be_bytes()));
+    data.extend_from_slice(&left_pad(&(signature_offset as u64).to_be_bytes()));
+    data.extend_from_slice(&left_pad(&(attesters_offset as u64).to_be_bytes()));
+    data.extend_from_slice(&left_pad(&(words.len() as u64).to_be_bytes()));
+    for word in &words {
+        data.extend_from_slice(word);
+    }
+    data.extend_from_slice(&left_pad(&(signature.len() as u64).to_be_bytes()));
+    data.extend_from_slice(&signature);
+    data.resize(data.len() + (32 - signature.len() % 32) % 32, 0);
+    data.extend_from_slice(&left_pad(&1u64.to_be_bytes()));
+    data.extend_from_slice(&left_pad(operator));
+    Ok(data)
+}
+
+/// `bytes` right-aligned in a 32-byte word.
+fn left_pad(bytes: &[u8]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[32 - bytes.len()..].copy_from_slice(bytes);
+    word
+}
+
+/// Parses a decimal or `0x`-prefixed hex uint256 into a big-endian word.
+pub(crate) fn parse_uint256(value: &str) -> Result<[u8; 32], String> {
+    let invalid = || format!("Task ID {} is not a uint256", value);
+    if let Some(digits) = value.strip_prefix("0x") {
+        if digits.is_empty() || digits.len() > 64 {
+            return Err(invalid());
+        }
+        let padded = format!("{:0>64}", digits);
+        let bytes = hex::decode(padded).map_err(|_| invalid())?;
+        return Ok(left_pad(&bytes));
+    }
+    if value.is_empty() {
+        return Err(invalid());
+    }
+    let mut word = [0u8; 32];
+    for digit in value.chars() {
+        let mut carry = digit.to_digit(10).ok_or_else(invalid)?;
+        for byte in word.iter_mut().rev() {
+            let product = *byte as u32 * 10 + carry;
+            *byte = product as u8;
+            carry = product >> 8;
+        }
+        if carry != 0 {
+            return Err(invalid());
+        }
+    }
+    Ok(word)
+}
+
+fn parse_address(value: &str) -> Result<[u8; 20], String> {
+    hex::decode(value.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| <[u8; 20]>::try_from(bytes).ok())
+        .ok_or_else(|| format!("Invalid contract address {}", value))
+}
+
+fn parse_u64(value: &Value, method: &str) -> Result<u64, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("Invalid {} result", method))
+        .and_then(parse_quantity)
+        .and_then(|n| u64::try_from(n).map_err(|_| format!("{} result out of range", method)))
+}
+
+fn parse_quantity(value: &str) -> Result<u128, String> {
+    value
+        .strip_prefix("0x")
+        .and_then(|digits| u128::from_str_radix(digits, 16).ok())
+        .ok_or_else(|| format!("Invalid quantity {}", value))
+}
+
+fn gwei_to_wei(gwei: f64) -> u128 {
+    (gwei * WEI_PER_GWEI).round() as u128
+}
+
+fn wei_to_gwei(wei: u128) -> f64 {
+    wei as f64 / WEI_PER_GWEI
+}
+
+/// Unsigned EIP-1559 (type 2) transaction without an access list.
+struct Transaction {
+    chain_id: u64,
+    nonce: u64,
+    max_priority_fee: u128,
+    max_fee: u128,
+    gas: u64,
+    to: [u8; 20],
+    data: Vec<u8>,
+}
+
+impl Transaction {
+    fn fields(&self) -> Vec<Vec<u8>> {
+        vec![
+            rlp_uint(self.chain_id as u128),
+            rlp_uint(self.nonce as u128),
+            rlp_uint(self.max_priority_fee),
+            rlp_uint(self.max_fee),
+            rlp_uint(self.gas as u128),
+            rlp_bytes(&self.to),
+            rlp_uint(0),
+            rlp_bytes(&self.data),
+            rlp_list(&[]),
+        ]
+    }
+
+    /// Signed raw transaction, `0x02 || rlp([...fields, yParity, r, s])`.
+    fn sign(&self, key: &SigningKey) -> Result<Vec<u8>, String> {
+        let mut fields = self.fields();
+        let mut unsigned = vec![0x02];
+        unsigned.extend_from_slice(&rlp_list(&fields));
+        let digest = Keccak256::digest(&unsigned);
+        let (signature, recovery) = key
+            .sign_prehash_recoverable(&digest)
+            .map_err(|e| format!("Failed to sign transaction: {}", e))?;
+        let bytes = signature.to_bytes();
+        fields.push(rlp_uint(recovery.is_y_odd() as u128));
+        fields.push(rlp_bytes(trim_zeros(&bytes[..32])));
+        fields.push(rlp_bytes(trim_zeros(&bytes[32..])));
+        let mut raw = vec![0x02];
+        raw.extend_from_slice(&rlp_list(&fields));
+        Ok(raw)
+    }
+}
+
+fn trim_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+fn rlp_uint(value: u128) -> Vec<u8> {
+    rlp_bytes(trim_zeros(&value.to_be_bytes()))
+}
+
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut encoded = rlp_length(bytes.len(), 0x80);
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload: Vec<u8> = items.concat();
+    let mut encoded = rlp_length(payload.len(), 0xc0);
+    encoded.extend_from_slice(&payload);
+    encoded
+}
+
+fn rlp_length(length: usize, offset: u8) -> Vec<u8> {
+    if length < 56 {
+        return vec![offset + length as u8];
+    }
+    let length_bytes = trim_zeros(&length.to_be_bytes()).to_vec();
+    let mut encoded = vec![offset + 55 + length_bytes.len() as u8];
+    encoded.extend_from_slice(&length_bytes);
+    encoded
+}
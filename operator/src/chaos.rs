@@ -0,0 +1,186 @@
+// src/chaos.rs
+
+//! Fault injection, for testing consumers against a misbehaving operator.
+//!
+//! Built only with the `chaos` feature. The admin API switches faults on and
+//! off at runtime (`GET` and `POST /admin/faults`); all are off at start:
+//!
+//! - `dropSubmissions`: outcomes are acknowledged but never handed to the
+//!   aggregator or chain ([`ChaosSubmitter`]).
+//! - `signingDelay`: every signature waits this long first, e.g. `"5s"`.
+//! - `corruptSalts`: the salt of every outcome has a bit flipped after
+//!   signing, so its signatures no longer verify.
+//! - `staleRounds`: `GET /beacon/latest` serves the round this many before
+//!   the latest.
+//!
+//! `rate` is the share of eligible events each fault hits, from 0 to 1.
+//! Every injected fault is logged and counted in `rng_faults_injected_total`.
+
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use log::warn;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config;
+use crate::metrics::Metrics;
+use crate::tasks::{Submitter, TaskOutcome};
+
+/// The faults to inject, as set through the admin API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FaultSettings {
+    pub drop_submissions: bool,
+    pub signing_delay: Option<String>,
+    pub corrupt_salts: bool,
+    pub stale_rounds: u64,
+    pub rate: f64,
+}
+
+impl Default for FaultSettings {
+    fn default() -> Self {
+        FaultSettings {
+            drop_submissions: false,
+            signing_delay: None,
+            corrupt_salts: false,
+            stale_rounds: 0,
+            rate: 1.0,
+        }
+    }
+}
+
+impl FaultSettings {
+    /// Whether any fault is on.
+    pub fn is_active(&self) -> bool {
+        self.drop_submissions
+            || self.signing_delay.is_some()
+            || self.corrupt_salts
+            || self.stale_rounds > 0
+    }
+}
+
+/// The live fault settings, shared by everything that injects them.
+pub struct Faults {
+    state: RwLock<(FaultSettings, Option<Duration>)>,
+    metrics: Arc<Metrics>,
+}
+
+impl Faults {
+    /// Injects nothing until [`Faults::set`] is called.
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        Faults {
+            state: RwLock::new((FaultSettings::default(), None)),
+            metrics,
+        }
+    }
+
+    pub fn settings(&self) -> FaultSettings {
+        self.state.read().expect("fault lock poisoned").0.clone()
+    }
+
+    /// Replaces the settings.
+    pub fn set(&self, settings: FaultSettings) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&settings.rate) {
+            return Err("rate must be between 0 and 1".to_string());
+        }
+        let delay = settings
+            .signing_delay
+            .as_deref()
+            .map(config::parse_duration)
+            .transpose()?;
+        if settings.is_active() {
+            warn!(
+                "Fault injection on: {}",
+                serde_json::to_string(&settings).unwrap_or_default()
+            );
+        } else {
+            warn!("Fault injection off");
+        }
+        *self.state.write().expect("fault lock poisoned") = (settings, delay);
+        Ok(())
+    }
+
+    /// Whether the next submission is to be dropped.
+    pub fn drop_submission(&self) -> bool {
+        let on = self.settings().drop_submissions;
+        on && self.hit("drop_submission")
+    }
+
+    /// Waits out the signing delay, if one is set and hits.
+    pub fn delay_signing(&self) {
+        let delay = self.state.read().expect("fault lock poisoned").1;
+        if let Some(delay) = delay {
+            if self.hit("signing_delay") {
+                thread::sleep(delay);
+            }
+        }
+    }
+
+    /// Flips a bit of the salt of `outcome`, if salts are to be corrupted.
+    pub fn corrupt_salt(&self, outcome: &mut TaskOutcome) {
+        if !self.settings().corrupt_salts || !self.hit("corrupt_salt") {
+            return;
+        }
+        let Ok(mut salt) = hex::decode(&outcome.salt) else {
+            return;
+        };
+        if let Some(first) = salt.first_mut() {
+            *first ^= 0x01;
+        }
+        outcome.salt = hex::encode(salt);
+        warn!("Corrupted the salt of task {}", outcome.task_id);
+    }
+
+    /// The round to serve instead of `latest`, if rounds are to be stale.
+    pub fn stale_round(&self, latest: u64) -> Option<u64> {
+        let back = self.settings().stale_rounds;
+        if back == 0 || !self.hit("stale_round") {
+            return None;
+        }
+        Some(latest.saturating_sub(back))
+    }
+
+    fn hit(&self, fault: &str) -> bool {
+        let rate = self.settings().rate;
+        let hit = rate >= 1.0 || rand::thread_rng().gen_bool(rate);
+        if hit {
+            self.metrics
+                .inc_counter("rng_faults_injected_total", &[("fault", fault)], 1);
+        }
+        hit
+    }
+}
+
+/// Submitter that drops outcomes while [`FaultSettings::drop_submissions`]
+/// is on, and hands them to `inner` otherwise.
+pub struct ChaosSubmitter {
+    inner: Arc<dyn Submitter>,
+    faults: Arc<Faults>,
+}
+
+impl ChaosSubmitter {
+    pub fn new(inner: Arc<dyn Submitter>, faults: Arc<Faults>) -> Self {
+        ChaosSubmitter { inner, faults }
+    }
+}
+
+impl Submitter for ChaosSubmitter {
+    fn submit(&self, outcome: &TaskOutcome) -> Result<(), String> {
+        if self.faults.drop_submission() {
+            warn!("Dropped the submission of task {}", outcome.task_id);
+            return Ok(());
+        }
+        self.inner.submit(outcome)
+    }
+
+    fn chain_height(&self) -> Result<Option<u64>, String> {
+        self.inner.chain_height()
+    }
+
+    fn dry_run(&self, outcome: &TaskOutcome) -> Result<Value, String> {
+        self.inner.dry_run(outcome)
+    }
+}
@@ -0,0 +1,813 @@
+// src/client.rs
+
+//! Typed client for the operator's HTTP API (`client` feature).
+//!
+//! Rust backends consuming randomness use [`OperatorClient`] instead of
+//! hand-written requests and JSON structs: [`OperatorClient::request`] has a
+//! value generated and attested, [`OperatorClient::verify`] checks the result
+//! locally with the same code as `rng-verify`, and
+//! [`OperatorClient::beacon_rounds`] follows the committee beacon, checking
+//! each round's transcript. A consumer that joins late catches up with
+//! [`OperatorClient::beacon_round_chain`], which ties a past round to the
+//! signed head of the round hash chain. Heartbeats, build statements and the
+//! revocation list are fetched with their signatures checked. Time-locked
+//! values are requested with [`OperatorClient::seal`] and read back with
+//! [`OperatorClient::timelock`] once released, and rollups aggregating many
+//! draws have them fulfilled under one attestation with
+//! [`OperatorClient::request_batch`]. Values too large for one attestation
+//! arrive chunk by chunk from [`OperatorClient::request_stream`].
+//!
+//! The client is async, on reqwest, and needs a Tokio runtime; every call
+//! is a future, and [`BeaconRounds::next`] sleeps between polls with
+//! `tokio::time::sleep`.
+
+use std::fmt;
+use std::time::Duration;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::attester::{Attestation, Clock, RngAttester, SystemClock, DEFAULT_CLOCK_SKEW};
+use crate::batch::{self, BATCH_ROOT_KIND};
+use crate::beacon::{self, RoundChain, RoundRecord, SignedHead};
+use crate::encoding::Encoding;
+use crate::heartbeat::Heartbeat;
+use crate::pool;
+use crate::provenance::ProvenanceStatement;
+use crate::pvss::Params;
+use crate::queue::Priority;
+use crate::revocation::RevocationList;
+use crate::stream::StreamVerifier;
+use crate::tasks::{TaskOutcome, TaskStage};
+use crate::timelock::{Commitment, Release, TimelockStatus};
+
+/// Why a call failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientError {
+    /// The operator could not be reached.
+    Transport(String),
+    /// The operator answered with an error status.
+    Api { status: u16, message: String },
+    /// The response is not what the API documents.
+    Decode(String),
+    /// A response did not verify.
+    Verification(String),
+}
+
+impl ClientError {
+    /// Whether retrying the same call later may succeed: the operator was
+    /// unreachable, busy, paused or still running a request with the key.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Transport(_) => true,
+            ClientError::Api { status, .. } => matches!(status, 409 | 429 | 503),
+            ClientError::Decode(_) | ClientError::Verification(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Transport(e) => write!(f, "operator unreachable: {}", e),
+            ClientError::Api { status, message } => {
+                write!(f, "operator answered {}: {}", status, message)
+            }
+            ClientError::Decode(e) => write!(f, "unexpected response: {}", e),
+            ClientError::Verification(e) => write!(f, "verification failed: {}", e),
+        }
+    }
+}
+
+/// A request for randomness; see `POST /task/execute`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RandomnessRequest {
+    /// Generated by the operator when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    /// Bytes of randomness; the operator's default when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// Unix time in milliseconds after which the task must not be fulfilled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<u64>,
+    /// Caller entropy mixed into the output, in `encoding`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_entropy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dry_run: Option<bool>,
+    /// Encoding of the entropy and of the outcome's byte fields; the
+    /// operator's `server.encoding` when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<Encoding>,
+    /// Task definition to serve the request under; `length` must then be
+    /// unset or within its range.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_definition_id: Option<String>,
+    /// Seals the value until the release; see [`OperatorClient::seal`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timelock: Option<Release>,
+    /// Sent as `Idempotency-Key`; retries with it return the first result.
+    #[serde(skip)]
+    pub idempotency_key: Option<String>,
+}
+
+impl RandomnessRequest {
+    /// A request for `length` bytes.
+    pub fn new(length: usize) -> Self {
+        RandomnessRequest {
+            length: Some(length),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_task_id(mut self, task_id: &str) -> Self {
+        self.task_id = Some(task_id.to_string());
+        self
+    }
+
+    pub fn with_client_entropy(mut self, entropy: &[u8]) -> Self {
+        self.client_entropy = Some(self.encoding.unwrap_or_default().encode(entropy));
+        self
+    }
+
+    /// Asks for the outcome in `encoding`, re-encoding any entropy set.
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        let from = self.encoding.unwrap_or_default();
+        if let Some(entropy) = &self.client_entropy {
+            if let Ok(bytes) = from.decode(entropy) {
+                self.client_entropy = Some(encoding.encode(&bytes));
+            }
+        }
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Serves the request under the operator's task definition `id`, which
+    /// sets the length.
+    pub fn with_task_definition(mut self, id: &str) -> Self {
+        self.task_definition_id = Some(id.to_string());
+        self.length = None;
+        self
+    }
+
+    /// Seals the value until `release`.
+    pub fn with_timelock(mut self, release: Release) -> Self {
+        self.timelock = Some(release);
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: u64) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn with_idempotency_key(mut self, key: &str) -> Self {
+        self.idempotency_key = Some(key.to_string());
+        self
+    }
+}
+
+/// Requests fulfilled under one Merkle-rooted attestation; see
+/// `POST /task/execute_batch` and [`crate::batch`].
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchRequest {
+    /// Task ID of the batch root; generated by the operator when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
+    /// Of each, only the task ID, length, task definition and client entropy
+    /// are sent.
+    #[serde(skip)]
+    pub requests: Vec<RandomnessRequest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dry_run: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<Encoding>,
+    /// Sent as `Idempotency-Key`; retries with it return the first result.
+    #[serde(skip)]
+    pub idempotency_key: Option<String>,
+}
+
+impl BatchRequest {
+    pub fn new(requests: Vec<RandomnessRequest>) -> Self {
+        BatchRequest {
+            requests,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_batch_id(mut self, batch_id: &str) -> Self {
+        self.batch_id = Some(batch_id.to_string());
+        self
+    }
+
+    pub fn with_idempotency_key(mut self, key: &str) -> Self {
+        self.idempotency_key = Some(key.to_string());
+        self
+    }
+}
+
+/// The operator's answer to a [`RandomnessRequest`].
+#[derive(Debug, Clone)]
+pub struct Randomness {
+    pub outcome: TaskOutcome,
+    /// The outcome was recorded for an earlier request with the same key.
+    pub replayed: bool,
+}
+
+/// A task being processed, as listed by `GET /admin/tasks`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskStatus {
+    pub task_id: String,
+    pub stage: TaskStage,
+}
+
+/// The body of `GET /admin/tasks`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskList {
+    pub paused: bool,
+    pub queued: usize,
+    pub tasks: Vec<TaskStatus>,
+}
+
+/// Client for one operator.
+pub struct OperatorClient {
+    base_url: String,
+    http: reqwest::Client,
+    token: Option<String>,
+    admin_token: Option<String>,
+    trusted: Vec<VerifyingKey>,
+    masters: Vec<VerifyingKey>,
+    committee: Option<Params>,
+}
+
+impl OperatorClient {
+    /// A client for the operator at `base_url`, e.g. `http://localhost:8080`.
+    pub fn new(base_url: &str) -> Self {
+        OperatorClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http: http_client(Duration::from_secs(30)),
+            token: None,
+            admin_token: None,
+            trusted: Vec::new(),
+            masters: Vec::new(),
+            committee: None,
+        }
+    }
+
+    /// Gives up on calls after `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.http = http_client(timeout);
+        self
+    }
+
+    /// Requests randomness as the tenant with bearer `token`.
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    /// Enables the admin calls with `admin.token`.
+    pub fn with_admin_token(mut self, token: &str) -> Self {
+        self.admin_token = Some(token.to_string());
+        self
+    }
+
+    /// Only accepts attestations by `key`. Without trusted keys the key each
+    /// attestation names is used, which only proves internal consistency.
+    pub fn with_trusted_key(mut self, key: VerifyingKey) -> Self {
+        self.trusted.push(key);
+        self
+    }
+
+    /// Accepts attestations by epoch keys `master` certified.
+    pub fn with_master_key(mut self, master: VerifyingKey) -> Self {
+        self.masters.push(master);
+        self
+    }
+
+    /// Only accepts beacon rounds run by `committee`.
+    pub fn with_committee(mut self, committee: Params) -> Self {
+        self.committee = Some(committee);
+        self
+    }
+
+    /// Has a value generated and attested; see [`OperatorClient::verify`].
+    pub async fn request(&self, request: &RandomnessRequest) -> Result<Randomness, ClientError> {
+        let response = self.execute(request).await?;
+        let replayed = replayed(&response);
+        Ok(Randomness {
+            outcome: decode(response).await?,
+            replayed,
+        })
+    }
+
+    /// Has a value sealed until the request's release and returns the
+    /// commitment to it, with its signature checked; the value itself is
+    /// read with [`OperatorClient::timelock`] once released.
+    pub async fn seal(&self, request: &RandomnessRequest) -> Result<Commitment, ClientError> {
+        if request.timelock.is_none() {
+            return Err(ClientError::Decode(
+                "a sealed request needs a timelock".to_string(),
+            ));
+        }
+        let commitment: Commitment = decode(self.execute(request).await?).await?;
+        commitment.verify().map_err(ClientError::Verification)?;
+        Ok(commitment)
+    }
+
+    /// The timelock of task `task`, looked up as the tenant of
+    /// [`OperatorClient::with_token`], with the commitment's signature
+    /// checked, or `None` if no such task was sealed. Once released,
+    /// [`TimelockStatus::open`] decrypts the outcome, which
+    /// [`OperatorClient::verify`] then checks.
+    pub async fn timelock(&self, task: &str) -> Result<Option<TimelockStatus>, ClientError> {
+        let path = format!("/timelock/{}", task);
+        match self.get::<TimelockStatus>(&path, false).await {
+            Ok(status) => {
+                status
+                    .commitment
+                    .verify()
+                    .map_err(ClientError::Verification)?;
+                Ok(Some(status))
+            }
+            Err(ClientError::Api { status: 404, .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Has every request of `batch` fulfilled under one Merkle-rooted
+    /// attestation. The outcome's `batch` holds the requests' values, each
+    /// with its inclusion proof; [`OperatorClient::verify`] checks them all.
+    pub async fn request_batch(&self, batch: &BatchRequest) -> Result<Randomness, ClientError> {
+        let encoding = batch.encoding.unwrap_or_default();
+        let mut body =
+            serde_json::to_value(batch).map_err(|e| ClientError::Decode(e.to_string()))?;
+        let mut requests = Vec::with_capacity(batch.requests.len());
+        for request in &batch.requests {
+            let client_entropy = match &request.client_entropy {
+                Some(entropy) => Some(
+                    encoding.encode(
+                        &request
+                            .encoding
+                            .unwrap_or_default()
+                            .decode(entropy)
+                            .map_err(ClientError::Decode)?,
+                    ),
+                ),
+                None => None,
+            };
+            requests.push(serde_json::json!({
+                "taskId": request.task_id,
+                "length": request.length,
+                "taskDefinitionId": request.task_definition_id,
+                "clientEntropy": client_entropy,
+            }));
+        }
+        body["requests"] = Value::Array(requests);
+        body["encoding"] = serde_json::json!(encoding);
+        let response = self
+            .post(
+                "/task/execute_batch",
+                body.to_string(),
+                batch.idempotency_key.as_deref(),
+            )
+            .await?;
+        let replayed = replayed(&response);
+        Ok(Randomness {
+            outcome: decode(response).await?,
+            replayed,
+        })
+    }
+
+    /// Has `length` bytes generated as a stream of attested chunks of
+    /// `chunk_size` bytes and hands each chunk's bytes to `sink` once it
+    /// verified: like [`OperatorClient::verify`], signed by the key of the
+    /// first chunk, and linked to the chunk before it. Returns the number of
+    /// bytes received, after checking that none are missing.
+    pub async fn request_stream(
+        &self,
+        length: usize,
+        chunk_size: usize,
+        mut sink: impl FnMut(&[u8]),
+    ) -> Result<u64, ClientError> {
+        let body = serde_json::json!({
+            "length": length,
+            "chunkSize": chunk_size,
+            "encoding": Encoding::Hex,
+        });
+        let response = self.post("/task/stream", body.to_string(), None).await?;
+        let mut lines = Lines::new(response);
+
+        let first = lines
+            .next_outcome()
+            .await?
+            .ok_or_else(|| ClientError::Decode("empty stream".to_string()))?;
+        let first_key = first.public_key.clone();
+        let public_key = parse_key(&first_key).map_err(ClientError::Verification)?;
+        let mut chain = StreamVerifier::new(&public_key, chunk_size);
+        let mut outcome = Some(first);
+        while let Some(chunk) = outcome {
+            let attestation = self.verify(&chunk)?;
+            if chunk.public_key != first_key {
+                return Err(ClientError::Verification(
+                    "stream chunks are signed by different keys".to_string(),
+                ));
+            }
+            chain
+                .push(&attestation)
+                .map_err(ClientError::Verification)?;
+            sink(&attestation.payload.random_number);
+            outcome = lines.next_outcome().await?;
+        }
+        chain.finish().map_err(ClientError::Verification)
+    }
+
+    async fn execute(&self, request: &RandomnessRequest) -> Result<reqwest::Response, ClientError> {
+        let body =
+            serde_json::to_string(request).map_err(|e| ClientError::Decode(e.to_string()))?;
+        self.post("/task/execute", body, request.idempotency_key.as_deref())
+            .await
+    }
+
+    async fn post(
+        &self,
+        path: &str,
+        body: String,
+        idempotency_key: Option<&str>,
+    ) -> Result<reqwest::Response, ClientError> {
+        let mut call = self.http.post(self.url(path));
+        if let Some(token) = &self.token {
+            call = call.bearer_auth(token);
+        }
+        if let Some(key) = idempotency_key {
+            call = call.header("Idempotency-Key", key);
+        }
+        send(call.header("Content-Type", "application/json").body(body)).await
+    }
+
+    /// Requests randomness and verifies it before returning it.
+    pub async fn request_verified(
+        &self,
+        request: &RandomnessRequest,
+    ) -> Result<Randomness, ClientError> {
+        let randomness = self.request(request).await?;
+        self.verify(&randomness.outcome)?;
+        Ok(randomness)
+    }
+
+    /// Checks an outcome locally: its signature and derivation steps, the
+    /// signing key against the trusted and master keys, the validity window
+    /// and, for a pre-generated value, its binding to the task or, for a
+    /// batch, every request's inclusion in the root.
+    pub fn verify(&self, outcome: &TaskOutcome) -> Result<Attestation, ClientError> {
+        let fail = ClientError::Verification;
+        let outcome = &outcome.encoded(Encoding::Hex).map_err(fail)?;
+        let attestation = outcome.to_attestation().map_err(fail)?;
+        let public_key = parse_key(&outcome.public_key).map_err(fail)?;
+        RngAttester::verify(&public_key, &attestation).map_err(fail)?;
+
+        let certified_by = match (&outcome.epoch_certificate, attestation.payload.epoch) {
+            (Some(certificate), Some(epoch)) => {
+                let (master, key) = certificate.verify().map_err(fail)?;
+                if key != public_key || certificate.epoch != epoch {
+                    return Err(fail(format!(
+                        "epoch certificate does not cover the signing key in epoch {}",
+                        epoch
+                    )));
+                }
+                Some(master)
+            }
+            (None, Some(epoch)) => {
+                return Err(fail(format!(
+                    "epoch {} attestation carries no key certificate",
+                    epoch
+                )))
+            }
+            _ => None,
+        };
+        let pinned = !self.trusted.is_empty() || !self.masters.is_empty();
+        if pinned
+            && !self.trusted.contains(&public_key)
+            && !certified_by.is_some_and(|master| self.masters.contains(&master))
+        {
+            return Err(fail(format!(
+                "signed by untrusted key {}",
+                outcome.public_key
+            )));
+        }
+
+        if let Some(validity) = &attestation.payload.validity {
+            validity
+                .check(SystemClock.now_millis(), DEFAULT_CLOCK_SKEW)
+                .map_err(fail)?;
+        }
+        if let Some(slot) = attestation.payload.slot {
+            let binding: [u8; 64] = outcome
+                .binding
+                .as_deref()
+                .and_then(|b| hex::decode(b).ok())
+                .and_then(|b| b.try_into().ok())
+                .ok_or_else(|| {
+                    fail(format!(
+                        "pooled value (slot {}) carries no task binding",
+                        slot
+                    ))
+                })?;
+            pool::verify_binding(
+                &public_key,
+                &outcome.task_id,
+                &attestation,
+                &Signature::from_bytes(&binding),
+            )
+            .map_err(fail)?;
+        }
+        if outcome.batch.is_some() || outcome.kind.as_deref() == Some(BATCH_ROOT_KIND) {
+            batch::verify_outcome(outcome).map_err(fail)?;
+        }
+        Ok(attestation)
+    }
+
+    /// The tasks being processed; needs the admin token.
+    pub async fn tasks(&self) -> Result<TaskList, ClientError> {
+        self.get("/admin/tasks", true).await
+    }
+
+    /// The stage of task `task_id`, while it is being processed.
+    pub async fn task_stage(&self, task_id: &str) -> Result<Option<TaskStage>, ClientError> {
+        Ok(self
+            .tasks()
+            .await?
+            .tasks
+            .into_iter()
+            .find(|t| t.task_id == task_id)
+            .map(|t| t.stage))
+    }
+
+    /// The operator's latest heartbeat, with its signature checked.
+    pub async fn heartbeat(&self) -> Result<Heartbeat, ClientError> {
+        let heartbeat: Heartbeat = self.get("/heartbeat", false).await?;
+        heartbeat.verify().map_err(ClientError::Verification)?;
+        Ok(heartbeat)
+    }
+
+    /// A signed statement of the operator's build, with its signature checked.
+    pub async fn identity(&self) -> Result<ProvenanceStatement, ClientError> {
+        let statement: ProvenanceStatement = self.get("/identity", false).await?;
+        statement.verify().map_err(ClientError::Verification)?;
+        Ok(statement)
+    }
+
+    /// The revocation list; statements that do not verify are dropped.
+    pub async fn revocations(&self) -> Result<RevocationList, ClientError> {
+        let mut list: RevocationList = self.get("/revocations", false).await?;
+        list.revocations.retain(|r| r.verify().is_ok());
+        Ok(list)
+    }
+
+    /// The latest beacon round, verified.
+    pub async fn beacon_latest(&self) -> Result<RoundRecord, ClientError> {
+        let round: RoundRecord = self.get("/beacon/latest", false).await?;
+        round
+            .verify(self.committee.as_ref())
+            .map_err(ClientError::Verification)?;
+        Ok(round)
+    }
+
+    /// Beacon round `round`, verified, or `None` if it did not complete.
+    pub async fn beacon_round(&self, round: u64) -> Result<Option<RoundRecord>, ClientError> {
+        let path = format!("/beacon/rounds/{}", round);
+        match self.get::<RoundRecord>(&path, false).await {
+            Ok(record) => {
+                record
+                    .verify(self.committee.as_ref())
+                    .map_err(ClientError::Verification)?;
+                Ok(Some(record))
+            }
+            Err(ClientError::Api { status: 404, .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The operator's signed head of the round hash chain.
+    ///
+    /// The signature is checked against the committee pinned with
+    /// [`OperatorClient::with_committee`]; without one it is not checked.
+    pub async fn beacon_head(&self) -> Result<SignedHead, ClientError> {
+        let head: SignedHead = self.get("/beacon/head", false).await?;
+        if let Some(committee) = &self.committee {
+            head.verify(committee).map_err(ClientError::Verification)?;
+        }
+        Ok(head)
+    }
+
+    /// Beacon round `round` with the chain linking it to the operator's
+    /// signed head, checked with [`beacon::verify_round_chain`], or `None`
+    /// if it did not complete.
+    ///
+    /// Without a pinned committee the one named in the record is trusted.
+    pub async fn beacon_round_chain(&self, round: u64) -> Result<Option<RoundChain>, ClientError> {
+        let path = format!("/beacon/rounds/{}/proof", round);
+        match self.get::<RoundChain>(&path, false).await {
+            Ok(chain) => {
+                let params = self.committee.as_ref().unwrap_or(&chain.record.params);
+                beacon::verify_round_chain(params, &chain).map_err(ClientError::Verification)?;
+                Ok(Some(chain))
+            }
+            Err(ClientError::Api { status: 404, .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Every beacon round completed from now on, polling every `poll`.
+    pub fn beacon_rounds(&self, poll: Duration) -> BeaconRounds<'_> {
+        BeaconRounds {
+            client: self,
+            poll,
+            last: None,
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, admin: bool) -> Result<T, ClientError> {
+        let mut call = self.http.get(self.url(path));
+        if admin {
+            let token = self.admin_token.as_ref().ok_or_else(|| ClientError::Api {
+                status: 401,
+                message: "no admin token configured".to_string(),
+            })?;
+            call = call.bearer_auth(token);
+        } else if let Some(token) = &self.token {
+            call = call.bearer_auth(token);
+        }
+        decode(send(call).await?).await
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+}
+
+/// A subscription to the beacon; see [`OperatorClient::beacon_rounds`].
+///
+/// [`BeaconRounds::next`] resolves to the next verified round; until one
+/// completes, and while the beacon is not enabled, it keeps polling. Rounds
+/// missed between two polls are skipped; [`OperatorClient::beacon_round`] and
+/// [`OperatorClient::beacon_round_chain`] fetch them.
+pub struct BeaconRounds<'a> {
+    client: &'a OperatorClient,
+    poll: Duration,
+    last: Option<u64>,
+}
+
+impl BeaconRounds<'_> {
+    /// The next round completed since the last one returned.
+    pub async fn next(&mut self) -> Result<RoundRecord, ClientError> {
+        loop {
+            match self.client.beacon_latest().await {
+                Ok(round) if self.last.is_none_or(|last| round.round > last) => {
+                    self.last = Some(round.round);
+                    return Ok(round);
+                }
+                Ok(_) | Err(ClientError::Api { status: 404, .. }) => {
+                    tokio::time::sleep(self.poll).await
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// The lines of a newline-delimited JSON response, read as they arrive.
+struct Lines {
+    response: reqwest::Response,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl Lines {
+    fn new(response: reqwest::Response) -> Self {
+        Lines {
+            response,
+            buffer: Vec::new(),
+            done: false,
+        }
+    }
+
+    async fn next_line(&mut self) -> Result<Option<String>, ClientError> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                return utf8(&line[..end]).map(Some);
+            }
+            if self.done {
+                if self.buffer.is_empty() {
+                    return Ok(None);
+                }
+                let line = std::mem::take(&mut self.buffer);
+                return utf8(&line).map(Some);
+            }
+            match self
+                .response
+                .chunk()
+                .await
+                .map_err(|e| ClientError::Transport(e.to_string()))?
+            {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk),
+                None => self.done = true,
+            }
+        }
+    }
+
+    /// The next chunk of a stream, or `None` once it ended.
+    async fn next_outcome(&mut self) -> Result<Option<TaskOutcome>, ClientError> {
+        let Some(line) = self.next_line().await? else {
+            return Ok(None);
+        };
+        let line: Value =
+            serde_json::from_str(&line).map_err(|e| ClientError::Decode(e.to_string()))?;
+        if let Some(message) = line["error"].as_str() {
+            // The operator failed after answering 200.
+            return Err(ClientError::Api {
+                status: 500,
+                message: message.to_string(),
+            });
+        }
+        serde_json::from_value(line)
+            .map(Some)
+            .map_err(|e| ClientError::Decode(e.to_string()))
+    }
+}
+
+fn utf8(line: &[u8]) -> Result<String, ClientError> {
+    let line = std::str::from_utf8(line).map_err(|e| ClientError::Decode(e.to_string()))?;
+    Ok(line.strip_suffix('\r').unwrap_or(line).to_string())
+}
+
+fn http_client(timeout: Duration) -> reqwest::Client {
+    // Building only fails if the TLS backend cannot initialize, as in
+    // `reqwest::Client::new`.
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .expect("failed to initialize the HTTP client")
+}
+
+/// Sends `call`, turning an error status into [`ClientError::Api`] with the
+/// operator's message.
+async fn send(call: reqwest::RequestBuilder) -> Result<reqwest::Response, ClientError> {
+    let response = call
+        .send()
+        .await
+        .map_err(|e| ClientError::Transport(e.to_string()))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = response
+        .text()
+        .await
+        .ok()
+        .and_then(|body| serde_json::from_str::<Value>(&body).ok())
+        .and_then(|body| body["error"].as_str().map(str::to_string))
+        .unwrap_or_else(|| "no error message".to_string());
+    Err(ClientError::Api {
+        status: status.as_u16(),
+        message,
+    })
+}
+
+fn replayed(response: &reqwest::Response) -> bool {
+    response
+        .headers()
+        .get("Idempotent-Replayed")
+        .and_then(|value| value.to_str().ok())
+        == Some("true")
+}
+
+async fn decode<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, ClientError> {
+    let body = response
+        .text()
+        .await
+        .map_err(|e| ClientError::Transport(e.to_string()))?;
+    serde_json::from_str(&body).map_err(|e| ClientError::Decode(e.to_string()))
+}
+
+fn parse_key(value: &str) -> Result<VerifyingKey, String> {
+    let bytes: [u8; 32] = hex::decode(value)
+        .map_err(|e| format!("invalid public key {}: {}", value, e))?
+        .try_into()
+        .map_err(|_| format!("public key {} must be 32 bytes", value))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("invalid public key {}: {}", value, e))
+}
@@ -0,0 +1,1569 @@
+// src/config.rs
+
+//! Operator configuration loaded from `config/config.yaml`.
+//!
+//! Settings are split into two groups. Critical settings (keys, chain and
+//! contract addresses, listen address, outbound resilience) are fixed for the
+//! lifetime of the process. Non-critical settings (log level, rate limits,
+//! webhook targets, entropy sources, admin token) can be swapped at runtime
+//! through [`ConfigHandle::reload`], triggered by `SIGHUP` or `POST /admin/reload`.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::attester::HashAlg;
+use crate::dedup::OnDuplicate;
+use crate::definitions::Derivation;
+use crate::encoding::Encoding;
+use crate::performer::LengthPolicy;
+use crate::postprocess::Pipeline;
+use crate::resilience::{BreakerConfig, ResilienceConfig, RetryPolicy};
+use crate::roles::Role;
+use crate::stream::MAX_STREAM_CHUNKS;
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Config {
+    pub operator: OperatorConfig,
+    pub network: NetworkConfig,
+    pub contracts: ContractsConfig,
+    #[serde(default)]
+    pub performance: PerformanceConfig,
+    #[serde(default)]
+    pub entropy: EntropyConfig,
+    /// Post-processing stage IDs, applied in order to fresh entropy; see
+    /// [`crate::postprocess`].
+    #[serde(default)]
+    pub postprocess: Vec<String>,
+    #[serde(default)]
+    pub role: RoleConfig,
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub lengths: LengthConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub queue: QueueConfig,
+    #[serde(default)]
+    pub dedup: DedupConfig,
+    #[serde(default)]
+    pub signing: SigningConfig,
+    #[serde(default)]
+    pub epochs: EpochConfig,
+    #[serde(default)]
+    pub provenance: ProvenanceConfig,
+    #[serde(default)]
+    pub vdf: VdfConfig,
+    #[serde(default)]
+    pub drand: DrandConfig,
+    #[serde(default)]
+    pub vrf: VrfConfig,
+    #[serde(default)]
+    pub beacon: BeaconConfig,
+    #[serde(default)]
+    pub heartbeat: HeartbeatConfig,
+    #[serde(default)]
+    pub reputation: ReputationConfig,
+    #[serde(default)]
+    pub tracing: TracingConfig,
+    #[serde(default)]
+    pub pool: PoolConfig,
+    #[serde(default)]
+    pub publish: PublishConfig,
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+    #[serde(default)]
+    pub reverify: ReverifyConfig,
+    #[serde(default)]
+    pub timelock: TimelockConfig,
+    #[serde(default)]
+    pub chain: ChainConfig,
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
+    #[serde(default)]
+    pub task_definitions: Vec<TaskDefinitionConfig>,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub rate_limits: RateLimitConfig,
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+    #[serde(default)]
+    pub resilience: ResilienceSettings,
+    #[serde(default)]
+    pub admin: AdminConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OperatorConfig {
+    pub address: String,
+    pub private_key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct NetworkConfig {
+    pub rpc_url: String,
+    pub chain_id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ContractsConfig {
+    pub task_manager: String,
+    pub registry: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PerformanceConfig {
+    pub task_interval: String,
+    pub batch_size: usize,
+    /// Target time from admitting a task to its outcome; `None` sets none.
+    pub latency_slo: Option<String>,
+}
+
+impl Default for PerformanceConfig {
+    fn default() -> Self {
+        PerformanceConfig {
+            task_interval: "30s".to_string(),
+            batch_size: 10,
+            latency_slo: None,
+        }
+    }
+}
+
+/// Sources raced for operator entropy; see [`crate::entropy`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EntropyConfig {
+    /// Any of [`crate::entropy::SOURCES`]; OS entropy alone when empty.
+    pub sources: Vec<String>,
+    /// Fewest sources that must answer for a value to be produced.
+    pub min_sources: usize,
+    /// How long a draw waits for the sources.
+    pub timeout: String,
+    /// Device the `hardware` source reads.
+    pub device: String,
+    /// URL the `atmospheric` source fetches raw bytes from; `{n}` is
+    /// replaced with the number of bytes.
+    pub atmospheric_url: String,
+}
+
+impl Default for EntropyConfig {
+    fn default() -> Self {
+        EntropyConfig {
+            sources: Vec::new(),
+            min_sources: 1,
+            timeout: "500ms".to_string(),
+            device: "/dev/hwrng".to_string(),
+            atmospheric_url: "https://www.random.org/cgi-bin/randbyte?nbytes={n}&format=f"
+                .to_string(),
+        }
+    }
+}
+
+/// What the process does in the AVS; see [`crate::roles`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RoleConfig {
+    pub mode: Role,
+    /// Performer: the attester nodes every outcome is validated by.
+    pub attesters: Vec<AttesterConfig>,
+    /// Performer: approvals an outcome needs before it is submitted.
+    pub quorum: usize,
+    /// Attester: hex ed25519 keys of the performers whose outputs pass;
+    /// any performer's when empty.
+    pub trusted_performers: Vec<String>,
+}
+
+impl Default for RoleConfig {
+    fn default() -> Self {
+        RoleConfig {
+            mode: Role::Full,
+            attesters: Vec::new(),
+            quorum: 1,
+            trusted_performers: Vec::new(),
+        }
+    }
+}
+
+/// An attester node a performer sends its outcomes to.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AttesterConfig {
+    /// Base URL of the attester's HTTP server.
+    pub url: String,
+    /// Hex ed25519 key the attester signs its verdicts with, or the master
+    /// key certifying its epoch keys.
+    pub public_key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub listen: String,
+    /// Number of threads serving HTTP requests.
+    pub workers: usize,
+    /// How long shutdown waits for in-flight tasks before persisting them.
+    pub drain_timeout: String,
+    /// How long `/task/execute` answers a repeated idempotency key with the
+    /// original outcome; `None` disables idempotency keys.
+    pub idempotency_ttl: Option<String>,
+    /// Encoding of byte fields in `/task/execute` responses and of
+    /// `clientEntropy`, unless a request names its own.
+    pub encoding: Encoding,
+    /// Most requests one `/task/execute_batch` call may carry.
+    pub max_batch: usize,
+    /// Largest request body read; longer ones are answered with 413.
+    pub max_body_bytes: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            listen: "0.0.0.0:4003".to_string(),
+            workers: 4,
+            drain_timeout: "30s".to_string(),
+            idempotency_ttl: Some("24h".to_string()),
+            encoding: Encoding::Hex,
+            max_batch: 256,
+            max_body_bytes: 1024 * 1024,
+        }
+    }
+}
+
+/// Bounds on requested lengths; see [`crate::performer::LengthPolicy`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LengthConfig {
+    /// Bytes per task when a request sets no length.
+    pub default: usize,
+    pub min: usize,
+    /// Most bytes of one task; anything longer is only served as a stream.
+    pub max: usize,
+    /// Most bytes of one `/task/stream`.
+    pub stream_max: usize,
+    /// Bytes per attested chunk of a stream, unless the request picks a size.
+    pub stream_chunk: usize,
+}
+
+impl Default for LengthConfig {
+    fn default() -> Self {
+        LengthConfig {
+            default: 32,
+            min: 1,
+            max: 64 * 1024,
+            stream_max: 16 * 1024 * 1024,
+            stream_chunk: 4096,
+        }
+    }
+}
+
+impl LengthConfig {
+    /// The policy task lengths are checked against.
+    pub fn policy(&self) -> Result<LengthPolicy, String> {
+        LengthPolicy::new(self.min, self.max)
+    }
+}
+
+/// Where operator state is kept. Without a `path`, state lives in memory only.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub path: Option<String>,
+}
+
+/// Sizing of the task queue and the worker pool draining it.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct QueueConfig {
+    pub workers: usize,
+    pub capacity: usize,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        QueueConfig {
+            workers: 2,
+            capacity: 1024,
+        }
+    }
+}
+
+/// Persistent deduplication of fulfilled tasks; see [`crate::dedup`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DedupConfig {
+    pub enabled: bool,
+    /// How long a fulfilled task is remembered.
+    pub ttl: String,
+    pub on_duplicate: OnDuplicate,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        DedupConfig {
+            enabled: true,
+            ttl: "168h".to_string(),
+            on_duplicate: OnDuplicate::Replay,
+        }
+    }
+}
+
+/// Signature schemes applied to every attestation besides ed25519.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SigningConfig {
+    /// Also sign with `operator.private_key` (secp256k1) for `ecrecover`.
+    pub secp256k1: bool,
+    /// Also sign with `operator.private_key` as a BIP-340 Schnorr key.
+    pub schnorr: bool,
+    /// Attach the attestation in the layout of the Solidity verifier,
+    /// signed with the secp256k1 key; see [`crate::abi`].
+    pub abi: bool,
+    /// How long each attestation may be consumed after it is signed (e.g.
+    /// `"5m"`). Unset, attestations carry no validity window.
+    pub validity: Option<String>,
+    pub batching: BatchingConfig,
+    /// Generate and hold the attestation key inside an enclave, `"sgx"` or
+    /// `"nitro"`; needs the `enclave` feature. Unset, the key is in memory.
+    pub enclave: Option<String>,
+}
+
+/// Batched signing on a dedicated thread, trading a little latency for
+/// throughput under load.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BatchingConfig {
+    pub enabled: bool,
+    /// Most attestations signed together.
+    pub max_batch: usize,
+    /// How long the first request of a batch waits for others to join.
+    pub max_wait: String,
+    /// Threads hashing payloads in parallel; 0 uses one per CPU.
+    pub digest_threads: usize,
+}
+
+impl Default for BatchingConfig {
+    fn default() -> Self {
+        BatchingConfig {
+            enabled: false,
+            max_batch: 256,
+            max_wait: "2ms".to_string(),
+            digest_threads: 0,
+        }
+    }
+}
+
+/// Per-epoch attestation keys certified by a master key.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EpochConfig {
+    pub enabled: bool,
+    /// How long each epoch key signs, e.g. `"24h"`.
+    pub length: String,
+    /// File holding the hex-encoded ed25519 master secret key.
+    pub master_key_file: String,
+    /// File holding the ratchet state; rewritten as each epoch begins.
+    pub state_file: String,
+}
+
+impl Default for EpochConfig {
+    fn default() -> Self {
+        EpochConfig {
+            enabled: false,
+            length: "24h".to_string(),
+            master_key_file: "epoch-master.key".to_string(),
+            state_file: "epoch.state".to_string(),
+        }
+    }
+}
+
+/// Where the build provenance statement gets its hardware backing.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProvenanceConfig {
+    /// `"sgx"` (Gramine) or `"tsm"` (configfs-tsm: SEV-SNP, TDX) to attach a
+    /// TEE quote; `None` for a signed statement without one.
+    pub tee: Option<String>,
+}
+
+/// Optional VDF post-processing of every generated seed.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct VdfConfig {
+    pub enabled: bool,
+    /// Sequential squarings per evaluation; sets the delay.
+    pub iterations: u64,
+}
+
+impl Default for VdfConfig {
+    fn default() -> Self {
+        VdfConfig {
+            enabled: false,
+            iterations: 100_000,
+        }
+    }
+}
+
+/// Deterministic per-task output derived from a VRF over the task ID.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct VrfConfig {
+    pub enabled: bool,
+}
+
+/// Mixing of verified drand rounds into every generated value.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DrandConfig {
+    pub enabled: bool,
+    /// Base URL of a drand HTTP relay.
+    pub url: String,
+    /// Hex-encoded hash of the chain to follow; pins its group key.
+    pub chain_hash: String,
+}
+
+impl Default for DrandConfig {
+    fn default() -> Self {
+        DrandConfig {
+            enabled: false,
+            url: "https://api.drand.sh".to_string(),
+            // League of Entropy "quicknet".
+            chain_hash: "52db9ba70e0cc0f6eaf7803dd07447a1f5477735fd3f661792ba94600c84e971"
+                .to_string(),
+        }
+    }
+}
+
+/// Committee settings for the multi-party PVSS beacon.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BeaconConfig {
+    pub enabled: bool,
+    /// This operator's participant id.
+    pub node_id: u32,
+    /// File holding this operator's hex-encoded PVSS secret key.
+    pub key_file: String,
+    /// Decrypted shares needed to recover each dealing.
+    pub threshold: usize,
+    /// Time between rounds; must fit the three protocol phases.
+    pub period: String,
+    /// Length of each of the deal, complain and decrypt phases.
+    pub phase_timeout: String,
+    /// Every committee member, this operator included.
+    pub participants: Vec<BeaconParticipantConfig>,
+}
+
+impl Default for BeaconConfig {
+    fn default() -> Self {
+        BeaconConfig {
+            enabled: false,
+            node_id: 1,
+            key_file: "beacon.key".to_string(),
+            threshold: 1,
+            period: "60s".to_string(),
+            phase_timeout: "5s".to_string(),
+            participants: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BeaconParticipantConfig {
+    pub id: u32,
+    pub url: String,
+    /// Hex-encoded PVSS public key.
+    pub public_key: String,
+}
+
+/// Periodic signed liveness heartbeats.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HeartbeatConfig {
+    pub enabled: bool,
+    pub interval: String,
+    /// URLs each heartbeat is POSTed to, typically the aggregator's.
+    pub targets: Vec<String>,
+    /// Sent as a bearer token to the targets, e.g. their `reputation.token`.
+    pub token: Option<String>,
+}
+
+/// OTLP export of request traces.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TracingConfig {
+    pub enabled: bool,
+    /// OTLP/HTTP traces endpoint of the collector.
+    pub endpoint: String,
+    /// `service.name` reported with every span.
+    pub service_name: String,
+    /// How often buffered spans are sent.
+    pub export_interval: String,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        TracingConfig {
+            enabled: false,
+            endpoint: "http://localhost:4318/v1/traces".to_string(),
+            service_name: "othentic-rng-operator".to_string(),
+            export_interval: "5s".to_string(),
+        }
+    }
+}
+
+/// Outputs generated and attested ahead of demand.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PoolConfig {
+    pub enabled: bool,
+    /// Outputs kept ready.
+    pub size: usize,
+    /// Byte length of pooled outputs; requests for other lengths bypass it.
+    pub length: usize,
+    /// How often the pool is topped up.
+    pub refill_interval: String,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            enabled: false,
+            size: 64,
+            length: 32,
+            refill_interval: "250ms".to_string(),
+        }
+    }
+}
+
+/// Message-bus feed of attestations and beacon rounds.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PublishConfig {
+    pub enabled: bool,
+    /// `nats`, or `kafka` through a Kafka REST proxy.
+    pub backend: String,
+    /// `nats://host:4222` (optionally with `user:pass@` or `token@`), or the
+    /// REST proxy's base URL.
+    pub url: String,
+    /// NATS subject or Kafka topic every event is published to.
+    pub topic: String,
+    /// Events buffered while the bus is slow; further events are dropped.
+    pub capacity: usize,
+}
+
+impl Default for PublishConfig {
+    fn default() -> Self {
+        PublishConfig {
+            enabled: false,
+            backend: "nats".to_string(),
+            url: "nats://localhost:4222".to_string(),
+            topic: "othentic.rng".to_string(),
+            capacity: 1024,
+        }
+    }
+}
+
+/// Upload of attestations to object storage for long-term retention.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ArchiveConfig {
+    pub enabled: bool,
+    /// `s3` or `gcs`.
+    pub provider: String,
+    /// Overrides the provider's endpoint, e.g. for MinIO.
+    pub endpoint: Option<String>,
+    /// S3 region; ignored for GCS.
+    pub region: String,
+    pub bucket: String,
+    /// Prepended to every object name.
+    pub prefix: String,
+    /// HMAC credentials (for GCS, a service account HMAC key).
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// How often pending attestations are uploaded.
+    pub interval: String,
+    /// Most attestations per object.
+    pub max_records: usize,
+    /// Delete archived attestations from the local store.
+    pub prune: bool,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        ArchiveConfig {
+            enabled: false,
+            provider: "s3".to_string(),
+            endpoint: None,
+            region: "us-east-1".to_string(),
+            bucket: String::new(),
+            prefix: "attestations/".to_string(),
+            access_key_id: String::new(),
+            secret_access_key: String::new(),
+            interval: "1h".to_string(),
+            max_records: 10_000,
+            prune: false,
+        }
+    }
+}
+
+/// Periodic re-verification of stored data; see [`crate::reverify`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReverifyConfig {
+    pub enabled: bool,
+    pub interval: String,
+    /// Hex ed25519 keys, operator or epoch master keys, attestations must be
+    /// signed or certified by. Empty, each attestation's own key is used.
+    pub trusted_keys: Vec<String>,
+}
+
+impl Default for ReverifyConfig {
+    fn default() -> Self {
+        ReverifyConfig {
+            enabled: false,
+            interval: "24h".to_string(),
+            trusted_keys: Vec::new(),
+        }
+    }
+}
+
+/// Time-locked delivery; see [`crate::timelock`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TimelockConfig {
+    pub enabled: bool,
+    /// How often sealed tasks are checked for release.
+    pub poll_interval: String,
+    /// Furthest in the future a release time may be.
+    pub max_delay: String,
+}
+
+impl Default for TimelockConfig {
+    fn default() -> Self {
+        TimelockConfig {
+            enabled: false,
+            poll_interval: "1s".to_string(),
+            max_delay: "168h".to_string(),
+        }
+    }
+}
+
+/// On-chain submission of attested outcomes; see [`crate::chain`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ChainConfig {
+    pub enabled: bool,
+    /// Applied to `eth_estimateGas` to get the gas limit.
+    pub gas_multiplier: f64,
+    /// Longest a submission waits for fees or budget before failing.
+    pub max_deferral: String,
+    /// How often a deferred submission re-checks fees.
+    pub defer_poll: String,
+    /// Blocks on top of a fulfillment before it is considered final.
+    pub confirmations: u64,
+    /// How often submitted transactions are checked for inclusion and reorgs.
+    pub confirm_poll: String,
+    /// Times a dropped or reorged-out fulfillment is sent again.
+    pub max_resubmissions: u64,
+    /// Policy used for chains without an entry in `chains`.
+    pub gas: GasPolicy,
+    /// Policies by chain ID, replacing `gas` as a whole.
+    pub chains: BTreeMap<u64, GasPolicy>,
+}
+
+impl ChainConfig {
+    /// Gas policy applying to `chain_id`.
+    pub fn policy(&self, chain_id: u64) -> &GasPolicy {
+        self.chains.get(&chain_id).unwrap_or(&self.gas)
+    }
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        ChainConfig {
+            enabled: false,
+            gas_multiplier: 1.2,
+            max_deferral: "10m".to_string(),
+            defer_poll: "15s".to_string(),
+            confirmations: 12,
+            confirm_poll: "15s".to_string(),
+            max_resubmissions: 3,
+            gas: GasPolicy::default(),
+            chains: BTreeMap::new(),
+        }
+    }
+}
+
+/// Fee limits and spend budget for one chain.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GasPolicy {
+    /// Highest base fee plus priority fee paid; above it submissions wait.
+    pub max_fee_gwei: f64,
+    /// `fixed`, `network` (`eth_maxPriorityFeePerGas`) or a percentile of
+    /// recent tips such as `p50`.
+    pub priority_fee: String,
+    /// The tip for `fixed`; the ceiling on the tip otherwise.
+    pub priority_fee_gwei: f64,
+    /// Most gwei spent per `budget_window`; unlimited when unset.
+    pub budget_gwei: Option<f64>,
+    pub budget_window: String,
+}
+
+impl Default for GasPolicy {
+    fn default() -> Self {
+        GasPolicy {
+            max_fee_gwei: 100.0,
+            priority_fee: "network".to_string(),
+            priority_fee_gwei: 2.0,
+            budget_gwei: None,
+            budget_window: "24h".to_string(),
+        }
+    }
+}
+
+/// A consumer served in its own namespace; see [`crate::tenants`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TenantConfig {
+    /// Letters, digits, `-` and `_`; names the tenant's storage partitions.
+    pub id: String,
+    /// Bearer token the tenant calls `/task/execute` with.
+    pub token: String,
+    /// Signed into the tenant's attestations; defaults to `id`.
+    #[serde(default)]
+    pub domain: Option<String>,
+    /// Most tasks of the tenant in flight at once; unlimited when unset.
+    #[serde(default)]
+    pub max_in_flight: Option<usize>,
+    /// Most tasks admitted per `quota_window`; unlimited when unset.
+    #[serde(default)]
+    pub quota: Option<u64>,
+    #[serde(default = "default_quota_window")]
+    pub quota_window: String,
+}
+
+fn default_quota_window() -> String {
+    "24h".to_string()
+}
+
+/// A task type the operator serves; see [`crate::definitions`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TaskDefinitionConfig {
+    /// Letters, digits, `-` and `_`; requests name it as `taskDefinitionId`.
+    pub id: String,
+    /// Bytes of randomness per task when a request sets no length.
+    pub length: usize,
+    /// Shortest length a request may ask for; `length` when unset.
+    #[serde(default)]
+    pub min_length: Option<usize>,
+    /// Longest length a request may ask for; `length` when unset.
+    #[serde(default)]
+    pub max_length: Option<usize>,
+    /// `random` or `vrf`; follows `vrf.enabled` when unset.
+    #[serde(default)]
+    pub derivation: Option<Derivation>,
+    /// Any of `ed25519`, `secp256k1` and `schnorr`; ed25519 is always made.
+    /// Every enabled scheme when unset.
+    #[serde(default)]
+    pub signing: Option<Vec<String>>,
+    /// `sha256` or `keccak256`.
+    #[serde(default)]
+    pub hash: HashAlg,
+    /// Validity window of the attestations; `signing.validity` when unset.
+    #[serde(default)]
+    pub expiry: Option<String>,
+}
+
+impl TaskDefinitionConfig {
+    /// The lengths a request under the definition may ask for.
+    pub fn policy(&self) -> Result<LengthPolicy, String> {
+        LengthPolicy::new(
+            self.min_length.unwrap_or(self.length),
+            self.max_length.unwrap_or(self.length),
+        )
+    }
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        HeartbeatConfig {
+            enabled: false,
+            interval: "30s".to_string(),
+            targets: Vec::new(),
+            token: None,
+        }
+    }
+}
+
+/// Scoring of other operators from gossiped outcomes and heartbeats; see
+/// [`crate::reputation`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReputationConfig {
+    pub enabled: bool,
+    /// Median latency at or below which an operator loses no score.
+    pub latency_target: String,
+    /// Operators scoring below this are reported as excluded.
+    pub min_score: f64,
+    /// Latencies and task values remembered per operator.
+    pub window: usize,
+    /// Operator keys tracked at most; others are refused.
+    pub max_operators: usize,
+    /// Bearer token gossipers present on `POST /reputation/*`; without one
+    /// those routes are refused.
+    pub token: Option<String>,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        ReputationConfig {
+            enabled: false,
+            latency_target: "2s".to_string(),
+            min_score: 0.5,
+            window: 1024,
+            max_operators: 1024,
+            token: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub level: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            level: "info".to_string(),
+        }
+    }
+}
+
+/// Token-bucket limits applied to incoming API requests.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            requests_per_second: 20.0,
+            burst: 40,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub targets: Vec<String>,
+}
+
+/// Access to the `/admin/*` endpoints.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AdminConfig {
+    /// Bearer token required by the admin API; without one the API is disabled.
+    pub token: Option<String>,
+}
+
+/// YAML form of [`ResilienceConfig`]; durations are written as `"200ms"`, `"5s"`, ...
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ResilienceSettings {
+    pub max_attempts: u32,
+    pub base_delay: String,
+    pub max_delay: String,
+    pub failure_threshold: u32,
+    pub open_for: String,
+    pub timeout_budget: String,
+}
+
+impl Default for ResilienceSettings {
+    fn default() -> Self {
+        ResilienceSettings {
+            max_attempts: 4,
+            base_delay: "200ms".to_string(),
+            max_delay: "5s".to_string(),
+            failure_threshold: 5,
+            open_for: "30s".to_string(),
+            timeout_budget: "15s".to_string(),
+        }
+    }
+}
+
+impl ResilienceSettings {
+    /// Converts the YAML settings into the runtime [`ResilienceConfig`].
+    pub fn to_config(&self) -> Result<ResilienceConfig, String> {
+        Ok(ResilienceConfig {
+            retry: RetryPolicy {
+                max_attempts: self.max_attempts,
+                base_delay: parse_duration(&self.base_delay)?,
+                max_delay: parse_duration(&self.max_delay)?,
+            },
+            breaker: BreakerConfig {
+                failure_threshold: self.failure_threshold,
+                open_for: parse_duration(&self.open_for)?,
+            },
+            timeout_budget: parse_duration(&self.timeout_budget)?,
+        })
+    }
+}
+
+impl Config {
+    /// Reads and validates the configuration file at `path`.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+        let config: Config = serde_yaml::from_str(&raw)
+            .map_err(|e| format!("Failed to parse config {}: {}", path.display(), e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Returns a copy with secrets masked, safe to show over the admin API.
+    pub fn redacted(&self) -> Config {
+        let mut copy = self.clone();
+        copy.operator.private_key = REDACTED.to_string();
+        for token in [
+            &mut copy.admin.token,
+            &mut copy.reputation.token,
+            &mut copy.heartbeat.token,
+        ] {
+            if token.is_some() {
+                *token = Some(REDACTED.to_string());
+            }
+        }
+        if !copy.archive.secret_access_key.is_empty() {
+            copy.archive.secret_access_key = REDACTED.to_string();
+        }
+        for tenant in &mut copy.tenants {
+            tenant.token = REDACTED.to_string();
+        }
+        copy
+    }
+
+    /// SHA-256 of the redacted config as compact JSON with sorted keys, i.e.
+    /// of the `GET /admin/config` body. Identifies the settings a value was
+    /// produced under without exposing (or allowing guesses at) the secrets.
+    pub fn digest(&self) -> [u8; 32] {
+        let json = serde_json::to_value(self.redacted()).expect("config serializes to JSON");
+        Sha256::digest(json.to_string()).into()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        parse_duration(&self.performance.task_interval)?;
+        if let Some(slo) = &self.performance.latency_slo {
+            if parse_duration(slo)?.is_zero() {
+                return Err("performance.latency_slo must be positive".to_string());
+            }
+        }
+        let mut sources = std::collections::BTreeSet::new();
+        for source in &self.entropy.sources {
+            if !crate::entropy::SOURCES.contains(&source.as_str()) {
+                return Err(format!(
+                    "unknown entropy source '{}' (expected one of {})",
+                    source,
+                    crate::entropy::SOURCES.join(", ")
+                ));
+            }
+            if !sources.insert(source) {
+                return Err(format!("entropy source {} is listed twice", source));
+            }
+        }
+        if self.entropy.min_sources == 0 || self.entropy.min_sources > sources.len().max(1) {
+            return Err(
+                "entropy.min_sources must be between 1 and the number of sources".to_string(),
+            );
+        }
+        if parse_duration(&self.entropy.timeout)?.is_zero() {
+            return Err("entropy.timeout must be positive".to_string());
+        }
+        parse_duration(&self.server.drain_timeout)?;
+        if self.server.workers == 0 {
+            return Err("server.workers must be at least 1".to_string());
+        }
+        if self.server.max_batch == 0 {
+            return Err("server.max_batch must be at least 1".to_string());
+        }
+        if self.server.max_body_bytes == 0 {
+            return Err("server.max_body_bytes must be at least 1".to_string());
+        }
+        match self.role.mode {
+            Role::Performer => {
+                if self.role.quorum == 0 || self.role.quorum > self.role.attesters.len() {
+                    return Err("role.quorum must be 1 to the number of role.attesters".to_string());
+                }
+            }
+            Role::Attester => {
+                if self.pool.enabled || self.timelock.enabled || self.chain.enabled {
+                    return Err(
+                        "an attester generates nothing; disable pool, timelock and chain"
+                            .to_string(),
+                    );
+                }
+            }
+            Role::Full => {}
+        }
+        let lengths = self
+            .lengths
+            .policy()
+            .map_err(|_| "lengths.min must be at least 1 and at most lengths.max".to_string())?;
+        lengths
+            .check(self.lengths.default)
+            .map_err(|e| format!("lengths.default: {}", e))?;
+        if self.lengths.stream_max < self.lengths.max {
+            return Err("lengths.stream_max must be at least lengths.max".to_string());
+        }
+        if self.lengths.stream_chunk < self.lengths.min
+            || self.lengths.stream_chunk > self.lengths.max
+        {
+            return Err("lengths.stream_chunk must be lengths.min to lengths.max".to_string());
+        }
+        if self.lengths.stream_max.div_ceil(self.lengths.stream_chunk) > MAX_STREAM_CHUNKS {
+            return Err(format!(
+                "lengths.stream_max splits into more than {} chunks of lengths.stream_chunk",
+                MAX_STREAM_CHUNKS
+            ));
+        }
+        let pipeline =
+            Pipeline::parse(&self.postprocess).map_err(|e| format!("postprocess: {}", e))?;
+        if let Some(max) = pipeline.max_length() {
+            if self.lengths.max > max {
+                return Err(format!(
+                    "lengths.max must be at most {} with an hkdf post-processing stage",
+                    max
+                ));
+            }
+        }
+        if let Some(ttl) = &self.server.idempotency_ttl {
+            if parse_duration(ttl)?.is_zero() {
+                return Err("server.idempotency_ttl must be positive".to_string());
+            }
+        }
+        if self.queue.workers == 0 || self.queue.capacity == 0 {
+            return Err("queue.workers and queue.capacity must be at least 1".to_string());
+        }
+        self.resilience.to_config()?;
+        for (enabled, scheme) in [
+            (self.signing.secp256k1, "secp256k1"),
+            (self.signing.schnorr, "schnorr"),
+        ] {
+            let key = hex::decode(self.operator.private_key.trim_start_matches("0x"));
+            if enabled && key.map_or(true, |k| k.len() != 32) {
+                return Err(format!(
+                    "signing.{} needs operator.private_key as 32 hex-encoded bytes",
+                    scheme
+                ));
+            }
+        }
+        if self.signing.abi && !self.signing.secp256k1 {
+            return Err("signing.abi needs signing.secp256k1".to_string());
+        }
+        if let Some(validity) = &self.signing.validity {
+            if parse_duration(validity)?.is_zero() {
+                return Err("signing.validity must be positive".to_string());
+            }
+        }
+        if self.signing.batching.enabled {
+            parse_duration(&self.signing.batching.max_wait)?;
+            if self.signing.batching.max_batch == 0 {
+                return Err("signing.batching.max_batch must be at least 1".to_string());
+            }
+        }
+        if self.vdf.enabled
+            && (self.vdf.iterations == 0 || self.vdf.iterations > crate::vdf::MAX_ITERATIONS)
+        {
+            return Err(format!(
+                "vdf.iterations must be between 1 and {}",
+                crate::vdf::MAX_ITERATIONS
+            ));
+        }
+        if self.drand.enabled && hex::decode(&self.drand.chain_hash).map_or(true, |h| h.len() != 32)
+        {
+            return Err("drand.chain_hash must be 32 hex-encoded bytes".to_string());
+        }
+        if self.vrf.enabled && self.drand.enabled {
+            return Err(
+                "vrf and drand cannot both be enabled: drand rounds change over time".to_string(),
+            );
+        }
+        if self.beacon.enabled {
+            let period = parse_duration(&self.beacon.period)?;
+            let phase = parse_duration(&self.beacon.phase_timeout)?;
+            if phase.is_zero() || phase * 3 >= period {
+                return Err("beacon.period must exceed three beacon.phase_timeout".to_string());
+            }
+            if !self
+                .beacon
+                .participants
+                .iter()
+                .any(|p| p.id == self.beacon.node_id)
+            {
+                return Err(format!(
+                    "beacon.node_id {} is not listed in beacon.participants",
+                    self.beacon.node_id
+                ));
+            }
+            if self.beacon.threshold == 0 || self.beacon.threshold > self.beacon.participants.len()
+            {
+                return Err(
+                    "beacon.threshold must be between 1 and the participant count".to_string(),
+                );
+            }
+        }
+        if self.epochs.enabled && parse_duration(&self.epochs.length)? < Duration::from_secs(1) {
+            return Err("epochs.length must be at least 1s".to_string());
+        }
+        if let Some(enclave) = &self.signing.enclave {
+            if enclave != "sgx" && enclave != "nitro" {
+                return Err(format!(
+                    "signing.enclave must be sgx or nitro, not {}",
+                    enclave
+                ));
+            }
+            if !cfg!(feature = "enclave") {
+                return Err(
+                    "signing.enclave needs an operator built with the enclave feature".to_string(),
+                );
+            }
+            if self.epochs.enabled {
+                return Err("signing.enclave cannot be combined with epochs".to_string());
+            }
+        }
+        if let Some(tee) = &self.provenance.tee {
+            if tee != "sgx" && tee != "tsm" {
+                return Err(format!("provenance.tee must be sgx or tsm, not {}", tee));
+            }
+        }
+        if self.reverify.enabled && parse_duration(&self.reverify.interval)?.is_zero() {
+            return Err("reverify.interval must be positive".to_string());
+        }
+        if let Some(key) = self
+            .reverify
+            .trusted_keys
+            .iter()
+            .find(|key| hex::decode(key).map_or(true, |k| k.len() != 32))
+        {
+            return Err(format!(
+                "reverify.trusted_keys entry {} is not a 32-byte hex key",
+                key
+            ));
+        }
+        if self.dedup.enabled && parse_duration(&self.dedup.ttl)?.is_zero() {
+            return Err("dedup.ttl must be positive".to_string());
+        }
+        if self.timelock.enabled
+            && (parse_duration(&self.timelock.poll_interval)?.is_zero()
+                || parse_duration(&self.timelock.max_delay)?.is_zero())
+        {
+            return Err(
+                "timelock.poll_interval and timelock.max_delay must be positive".to_string(),
+            );
+        }
+        if self.reputation.enabled {
+            if parse_duration(&self.reputation.latency_target)?.is_zero() {
+                return Err("reputation.latency_target must be positive".to_string());
+            }
+            if !(0.0..=1.0).contains(&self.reputation.min_score) {
+                return Err("reputation.min_score must be between 0 and 1".to_string());
+            }
+            if self.reputation.window == 0 || self.reputation.max_operators == 0 {
+                return Err(
+                    "reputation.window and reputation.max_operators must be at least 1".to_string(),
+                );
+            }
+        }
+        if self.heartbeat.enabled && parse_duration(&self.heartbeat.interval)?.is_zero() {
+            return Err("heartbeat.interval must be positive".to_string());
+        }
+        if self.tracing.enabled && parse_duration(&self.tracing.export_interval)?.is_zero() {
+            return Err("tracing.export_interval must be positive".to_string());
+        }
+        if self.pool.enabled {
+            if self.pool.size == 0 || self.pool.length == 0 {
+                return Err("pool.size and pool.length must be at least 1".to_string());
+            }
+            lengths
+                .check(self.pool.length)
+                .map_err(|e| format!("pool.length: {}", e))?;
+            if parse_duration(&self.pool.refill_interval)?.is_zero() {
+                return Err("pool.refill_interval must be positive".to_string());
+            }
+        }
+        if self.archive.enabled {
+            if !matches!(self.archive.provider.as_str(), "s3" | "gcs") {
+                return Err("archive.provider must be s3 or gcs".to_string());
+            }
+            if self.archive.bucket.is_empty() || self.archive.max_records == 0 {
+                return Err(
+                    "archive.bucket must be set and archive.max_records at least 1".to_string(),
+                );
+            }
+            if parse_duration(&self.archive.interval)?.is_zero() {
+                return Err("archive.interval must be positive".to_string());
+            }
+        }
+        if self.publish.enabled {
+            if !matches!(self.publish.backend.as_str(), "nats" | "kafka") {
+                return Err("publish.backend must be nats or kafka".to_string());
+            }
+            if self.publish.topic.is_empty() || self.publish.capacity == 0 {
+                return Err("publish.topic must be set and publish.capacity at least 1".to_string());
+            }
+        }
+        if self.chain.enabled {
+            let key = hex::decode(self.operator.private_key.trim_start_matches("0x"));
+            if key.map_or(true, |k| k.len() != 32) {
+                return Err("chain needs operator.private_key as 32 hex-encoded bytes".to_string());
+            }
+            if self.chain.gas_multiplier < 1.0 {
+                return Err("chain.gas_multiplier must be at least 1".to_string());
+            }
+            parse_duration(&self.chain.max_deferral)?;
+            if parse_duration(&self.chain.defer_poll)?.is_zero()
+                || parse_duration(&self.chain.confirm_poll)?.is_zero()
+            {
+                return Err("chain.defer_poll and chain.confirm_poll must be positive".to_string());
+            }
+            if self.chain.confirmations == 0 {
+                return Err("chain.confirmations must be at least 1".to_string());
+            }
+            for policy in std::iter::once(&self.chain.gas).chain(self.chain.chains.values()) {
+                crate::chain::PriorityFee::parse(&policy.priority_fee)?;
+                if policy.max_fee_gwei <= 0.0 || policy.priority_fee_gwei < 0.0 {
+                    return Err(
+                        "chain max_fee_gwei must be positive and priority_fee_gwei not negative"
+                            .to_string(),
+                    );
+                }
+                if policy.budget_gwei.is_some_and(|b| b <= 0.0)
+                    || parse_duration(&policy.budget_window)?.is_zero()
+                {
+                    return Err("chain budget_gwei and budget_window must be positive".to_string());
+                }
+            }
+        }
+        let mut ids = std::collections::BTreeSet::new();
+        let mut tokens = std::collections::BTreeSet::new();
+        for tenant in &self.tenants {
+            if tenant.id.is_empty()
+                || !tenant
+                    .id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!(
+                    "tenant ID '{}' must be letters, digits, - and _",
+                    tenant.id
+                ));
+            }
+            if !ids.insert(&tenant.id) {
+                return Err(format!("tenant {} is listed twice", tenant.id));
+            }
+            if tenant.token.trim().is_empty() || !tokens.insert(&tenant.token) {
+                return Err(format!("tenant {} needs a token of its own", tenant.id));
+            }
+            if tenant.domain.as_deref().is_some_and(str::is_empty)
+                || tenant.max_in_flight == Some(0)
+                || parse_duration(&tenant.quota_window)?.is_zero()
+            {
+                return Err(format!(
+                    "tenant {} needs a non-empty domain, max_in_flight at least 1 and a positive quota_window",
+                    tenant.id
+                ));
+            }
+        }
+        let mut definitions = std::collections::BTreeSet::new();
+        for definition in &self.task_definitions {
+            if definition.id.is_empty()
+                || !definition
+                    .id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!(
+                    "task definition ID '{}' must be letters, digits, - and _",
+                    definition.id
+                ));
+            }
+            if !definitions.insert(&definition.id) {
+                return Err(format!("task definition {} is listed twice", definition.id));
+            }
+            let policy = definition.policy().map_err(|_| {
+                format!(
+                    "task definition {} needs 1 <= min_length <= length <= max_length",
+                    definition.id
+                )
+            })?;
+            if policy.check(definition.length).is_err() {
+                return Err(format!(
+                    "task definition {} needs 1 <= min_length <= length <= max_length",
+                    definition.id
+                ));
+            }
+            if !lengths.contains(&policy) {
+                return Err(format!(
+                    "task definition {} allows lengths outside lengths.min..=lengths.max",
+                    definition.id
+                ));
+            }
+            for scheme in definition.signing.iter().flatten() {
+                let enabled = match scheme.as_str() {
+                    "ed25519" => true,
+                    "secp256k1" => self.signing.secp256k1,
+                    "schnorr" => self.signing.schnorr,
+                    other => {
+                        return Err(format!(
+                            "task definition {}: unknown signing scheme '{}' (expected ed25519, secp256k1 or schnorr)",
+                            definition.id, other
+                        ))
+                    }
+                };
+                if !enabled {
+                    return Err(format!(
+                        "task definition {} signs with {}, which needs signing.{}",
+                        definition.id, scheme, scheme
+                    ));
+                }
+            }
+            if let Some(expiry) = &definition.expiry {
+                if parse_duration(expiry)?.is_zero() {
+                    return Err(format!(
+                        "task definition {} needs a positive expiry",
+                        definition.id
+                    ));
+                }
+            }
+        }
+        if self.rate_limits.requests_per_second <= 0.0 {
+            return Err("rate_limits.requests_per_second must be positive".to_string());
+        }
+        if self
+            .admin
+            .token
+            .as_deref()
+            .is_some_and(|t| t.trim().is_empty())
+        {
+            return Err("admin.token must not be empty".to_string());
+        }
+        crate::logging::parse_level(&self.logging.level)?;
+        Ok(())
+    }
+
+    /// Lists the critical sections that differ between `self` and `other`.
+    fn critical_changes(&self, other: &Config) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.operator != other.operator {
+            changed.push("operator");
+        }
+        if self.network != other.network {
+            changed.push("network");
+        }
+        if self.contracts != other.contracts {
+            changed.push("contracts");
+        }
+        if self.performance != other.performance {
+            changed.push("performance");
+        }
+        if self.role != other.role {
+            changed.push("role");
+        }
+        if self.server != other.server {
+            changed.push("server");
+        }
+        if self.lengths != other.lengths {
+            changed.push("lengths");
+        }
+        if self.storage != other.storage {
+            changed.push("storage");
+        }
+        if self.queue != other.queue {
+            changed.push("queue");
+        }
+        if self.dedup != other.dedup {
+            changed.push("dedup");
+        }
+        if self.signing != other.signing {
+            changed.push("signing");
+        }
+        if self.epochs != other.epochs {
+            changed.push("epochs");
+        }
+        if self.provenance != other.provenance {
+            changed.push("provenance");
+        }
+        if self.vdf != other.vdf {
+            changed.push("vdf");
+        }
+        if self.drand != other.drand {
+            changed.push("drand");
+        }
+        if self.vrf != other.vrf {
+            changed.push("vrf");
+        }
+        if self.postprocess != other.postprocess {
+            changed.push("postprocess");
+        }
+        if self.beacon != other.beacon {
+            changed.push("beacon");
+        }
+        if self.reputation != other.reputation {
+            changed.push("reputation");
+        }
+        if self.heartbeat != other.heartbeat {
+            changed.push("heartbeat");
+        }
+        if self.tracing != other.tracing {
+            changed.push("tracing");
+        }
+        if self.pool != other.pool {
+            changed.push("pool");
+        }
+        if self.publish != other.publish {
+            changed.push("publish");
+        }
+        if self.archive != other.archive {
+            changed.push("archive");
+        }
+        if self.reverify != other.reverify {
+            changed.push("reverify");
+        }
+        if self.timelock != other.timelock {
+            changed.push("timelock");
+        }
+        if self.chain != other.chain {
+            changed.push("chain");
+        }
+        if self.tenants != other.tenants {
+            changed.push("tenants");
+        }
+        if self.task_definitions != other.task_definitions {
+            changed.push("task_definitions");
+        }
+        if self.resilience != other.resilience {
+            changed.push("resilience");
+        }
+        changed
+    }
+
+    /// Lists the reloadable sections that differ between `self` and `other`.
+    fn reloadable_changes(&self, other: &Config) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.logging != other.logging {
+            changed.push("logging");
+        }
+        if self.rate_limits != other.rate_limits {
+            changed.push("rate_limits");
+        }
+        if self.webhooks != other.webhooks {
+            changed.push("webhooks");
+        }
+        if self.entropy != other.entropy {
+            changed.push("entropy");
+        }
+        if self.admin != other.admin {
+            changed.push("admin");
+        }
+        changed
+    }
+}
+
+/// Parses durations of the form `"250ms"`, `"30s"`, `"5m"` or `"1h"`.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (digits, unit) = value.split_at(split);
+    let amount: u64 = digits
+        .parse()
+        .map_err(|_| format!("Invalid duration '{}'", value))?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(amount)),
+        "s" | "" => Ok(Duration::from_secs(amount)),
+        "m" => Ok(Duration::from_secs(amount * 60)),
+        "h" => Ok(Duration::from_secs(amount * 3600)),
+        _ => Err(format!("Invalid duration unit in '{}'", value)),
+    }
+}
+
+/// Summary of a reload, reported back to the caller and logged.
+#[derive(Debug, Clone, Default)]
+pub struct ReloadOutcome {
+    /// Reloadable sections whose new values are now live.
+    pub applied: Vec<&'static str>,
+    /// Critical sections that changed on disk but await a restart.
+    pub requires_restart: Vec<&'static str>,
+}
+
+/// Shared, swappable view of the configuration.
+///
+/// Readers take an `Arc<Config>` snapshot via [`ConfigHandle::current`], so a
+/// reload never mutates settings underneath a task that is already running;
+/// in-flight work finishes with the snapshot it started with.
+pub struct ConfigHandle {
+    path: PathBuf,
+    current: RwLock<Arc<Config>>,
+}
+
+impl ConfigHandle {
+    /// Loads the configuration at `path` and wraps it in a handle.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let config = Config::from_file(&path)?;
+        Ok(ConfigHandle {
+            path,
+            current: RwLock::new(Arc::new(config)),
+        })
+    }
+
+    /// Returns the path the configuration is loaded from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns a snapshot of the configuration currently in effect.
+    pub fn current(&self) -> Arc<Config> {
+        self.current.read().expect("config lock poisoned").clone()
+    }
+
+    /// Re-reads the file and applies the non-critical settings.
+    ///
+    /// Critical sections keep their running values; any differences are
+    /// reported in [`ReloadOutcome::requires_restart`]. An unreadable or
+    /// invalid file leaves the running configuration untouched.
+    pub fn reload(&self) -> Result<ReloadOutcome, String> {
+        let fresh = Config::from_file(&self.path)?;
+        let mut guard = self.current.write().expect("config lock poisoned");
+        let running = guard.as_ref();
+
+        let outcome = ReloadOutcome {
+            applied: running.reloadable_changes(&fresh),
+            requires_restart: running.critical_changes(&fresh),
+        };
+
+        let mut next = running.clone();
+        next.logging = fresh.logging;
+        next.rate_limits = fresh.rate_limits;
+        next.webhooks = fresh.webhooks;
+        next.entropy = fresh.entropy;
+        next.admin = fresh.admin;
+
+        crate::logging::set_level(&next.logging.level)?;
+        *guard = Arc::new(next);
+        Ok(outcome)
+    }
+}
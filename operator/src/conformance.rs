@@ -0,0 +1,433 @@
+// src/conformance.rs
+
+//! Conformance suite for attestation verifiers in other languages.
+//!
+//! Consumers check attestations in Solidity, Go, TypeScript and so on, and
+//! each of those verifiers has to agree with this crate on every input, not
+//! just on well-formed ones. [`suite`] is a fixed set of [`Vector`]s, each an
+//! attestation in the [`TaskOutcome`] wire form that `/task/execute` serves:
+//! a few valid ones and many edge cases, such as flipped bits, salts of the
+//! wrong length, truncated or malleated signatures and payloads of another
+//! version. The expected verdict of every vector is the one of [`reference`],
+//! the Rust verifier.
+//!
+//! The suite is derived from a fixed key and fixed inputs, so it is
+//! byte-for-byte the same on every operator and can be checked into a
+//! verifier's own test fixtures. `GET /conformance/vectors` serves it;
+//! `POST /conformance/report` takes an implementation's verdicts and returns,
+//! via [`evaluate`], where it diverges from the reference.
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::attester::{AttestationPayload, RngAttester, PAYLOAD_DOMAIN};
+use crate::tasks::TaskOutcome;
+
+/// Identifies the suite, so vectors can be added without silently changing
+/// what an earlier certification covered.
+pub const SUITE: &str = "othentic-rng/conformance/v1";
+/// The only payload version a verifier may accept.
+pub const PAYLOAD_VERSION: u32 = 2;
+/// Length every attestation salt must have.
+pub const SALT_LEN: usize = 32;
+
+const KEY_DOMAIN: &[u8] = b"othentic-rng/conformance/v1/key";
+const STRANGER_DOMAIN: &[u8] = b"othentic-rng/conformance/v1/stranger";
+/// The domain tag of a payload version that does not exist.
+const FUTURE_DOMAIN: &[u8] = b"othentic-rng/attestation/v3";
+/// The ed25519 group order, little-endian.
+const GROUP_ORDER: [u8; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10,
+];
+
+/// One attestation of the suite and the verdict the reference reaches on it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Vector {
+    pub id: String,
+    pub description: String,
+    /// Payload version the attestation claims; see [`PAYLOAD_VERSION`].
+    pub version: u32,
+    pub outcome: TaskOutcome,
+    /// Whether a verifier must accept the attestation.
+    pub valid: bool,
+    /// Why the reference rejects it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// The body of `GET /conformance/vectors`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suite {
+    pub suite: String,
+    /// Hex key the valid vectors are signed with.
+    pub public_key: String,
+    pub vectors: Vec<Vector>,
+}
+
+/// What an implementation decided on one vector.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Verdict {
+    pub id: String,
+    pub valid: bool,
+}
+
+/// The body of `POST /conformance/report`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Submission {
+    /// Name and version of the verifier under test.
+    pub implementation: String,
+    pub results: Vec<Verdict>,
+}
+
+/// A vector on which the implementation disagrees with the reference.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Divergence {
+    pub id: String,
+    pub description: String,
+    pub expected: bool,
+    pub reported: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// How a [`Submission`] compares with the reference.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConformanceReport {
+    pub implementation: String,
+    pub suite: String,
+    pub vectors: usize,
+    pub agreed: usize,
+    pub diverged: Vec<Divergence>,
+    /// Vectors the submission gave no verdict for.
+    pub missing: Vec<String>,
+    /// Verdicts for IDs that are not in the suite.
+    pub unknown: Vec<String>,
+    /// Whether the implementation agrees with the reference on every vector.
+    pub conformant: bool,
+}
+
+/// The reference verdict: the attestation claims [`PAYLOAD_VERSION`], its
+/// salt is [`SALT_LEN`] bytes, the key and signature are well-formed and
+/// [`RngAttester::verify`] accepts it.
+pub fn reference(vector: &Vector) -> Result<(), String> {
+    if vector.version != PAYLOAD_VERSION {
+        return Err(format!("Unsupported payload version {}", vector.version));
+    }
+    let outcome = &vector.outcome;
+    let key: [u8; 32] = hex::decode(&outcome.public_key)
+        .map_err(|e| format!("Invalid public key: {}", e))?
+        .try_into()
+        .map_err(|_| "Public key must be 32 bytes".to_string())?;
+    let key = VerifyingKey::from_bytes(&key).map_err(|e| format!("Invalid public key: {}", e))?;
+    let attestation = outcome.to_attestation()?;
+    if attestation.payload.salt.len() != SALT_LEN {
+        return Err(format!(
+            "Salt is {} bytes, not {}",
+            attestation.payload.salt.len(),
+            SALT_LEN
+        ));
+    }
+    RngAttester::verify(&key, &attestation)
+}
+
+/// The suite, built on first use.
+pub fn suite() -> &'static Suite {
+    static SUITE_VECTORS: OnceLock<Suite> = OnceLock::new();
+    SUITE_VECTORS.get_or_init(build)
+}
+
+/// Compares `submission` with the reference verdicts.
+pub fn evaluate(submission: &Submission) -> Result<ConformanceReport, String> {
+    if submission.implementation.trim().is_empty() {
+        return Err("Name the implementation under test".to_string());
+    }
+    let suite = suite();
+    let mut seen = HashSet::new();
+    let mut unknown = Vec::new();
+    let mut diverged = Vec::new();
+    let mut agreed = 0;
+    for verdict in &submission.results {
+        if !seen.insert(verdict.id.as_str()) {
+            return Err(format!("Vector {} is reported more than once", verdict.id));
+        }
+        let Some(vector) = suite.vectors.iter().find(|v| v.id == verdict.id) else {
+            unknown.push(verdict.id.clone());
+            continue;
+        };
+        if vector.valid == verdict.valid {
+            agreed += 1;
+        } else {
+            diverged.push(Divergence {
+                id: vector.id.clone(),
+                description: vector.description.clone(),
+                expected: vector.valid,
+                reported: verdict.valid,
+                reason: vector.reason.clone(),
+            });
+        }
+    }
+    let missing: Vec<String> = suite
+        .vectors
+        .iter()
+        .filter(|v| !seen.contains(v.id.as_str()))
+        .map(|v| v.id.clone())
+        .collect();
+    Ok(ConformanceReport {
+        implementation: submission.implementation.clone(),
+        suite: suite.suite.clone(),
+        vectors: suite.vectors.len(),
+        agreed,
+        conformant: diverged.is_empty() && missing.is_empty(),
+        diverged,
+        missing,
+        unknown,
+    })
+}
+
+fn build() -> Suite {
+    let key = fixed_key(KEY_DOMAIN);
+    let stranger = fixed_key(STRANGER_DOMAIN);
+    let plain = AttestationPayload {
+        random_number: fixed_bytes(b"random-number", 32),
+        salt: fixed_bytes(b"salt", SALT_LEN),
+        ..Default::default()
+    };
+    let extended = AttestationPayload {
+        counter: Some(7),
+        domain: Some("conformance".to_string()),
+        ..plain.clone()
+    };
+    let with_salt = |len: usize| AttestationPayload {
+        salt: fixed_bytes(b"salt", len),
+        ..extended.clone()
+    };
+    let signed = |payload: &AttestationPayload| sign(&key, &payload.digest());
+
+    let mut cases = Vec::new();
+    let mut case = |id: &str, description: &str, outcome: TaskOutcome| {
+        cases.push((
+            id.to_string(),
+            description.to_string(),
+            PAYLOAD_VERSION,
+            outcome,
+        ));
+    };
+
+    case(
+        "valid-plain",
+        "Random number and salt only, signed over SHA-256(randomNumber || salt)",
+        outcome(&key, &plain, &signed(&plain)),
+    );
+    case(
+        "valid-extended",
+        "Carries a counter and a domain, so the v2 field encoding is signed",
+        outcome(&key, &extended, &signed(&extended)),
+    );
+    let mut flipped = outcome(&key, &plain, &signed(&plain));
+    flipped.random_number = flip_bit(&flipped.random_number, 0);
+    case(
+        "flipped-random-number",
+        "First bit of the random number flipped after signing",
+        flipped,
+    );
+    let mut flipped = outcome(&key, &plain, &signed(&plain));
+    flipped.salt = flip_bit(&flipped.salt, 255);
+    case(
+        "flipped-salt",
+        "Last bit of the salt flipped after signing",
+        flipped,
+    );
+    let mut flipped = outcome(&key, &extended, &signed(&extended));
+    flipped.counter = Some(6);
+    case(
+        "flipped-counter",
+        "Counter changed after signing; only caught by encoding the v2 fields",
+        flipped,
+    );
+    let mut flipped = outcome(&key, &extended, &signed(&extended));
+    flipped.domain = Some("conformancf".to_string());
+    case(
+        "flipped-domain",
+        "One character of the domain changed after signing",
+        flipped,
+    );
+    let mut flipped = outcome(&key, &plain, &signed(&plain));
+    flipped.signature = flip_bit(&flipped.signature, 0);
+    case(
+        "flipped-signature-r",
+        "First bit of the signature's R flipped",
+        flipped,
+    );
+    let mut flipped = outcome(&key, &plain, &signed(&plain));
+    flipped.signature = flip_bit(&flipped.signature, 256);
+    case(
+        "flipped-signature-s",
+        "First bit of the signature's s flipped",
+        flipped,
+    );
+    let mut flipped = outcome(&key, &plain, &signed(&plain));
+    flipped.public_key = flip_bit(&flipped.public_key, 0);
+    case(
+        "flipped-public-key",
+        "First bit of the public key flipped",
+        flipped,
+    );
+    let mut foreign = outcome(&stranger, &plain, &sign(&stranger, &plain.digest()));
+    foreign.public_key = hex::encode(key.verifying_key().as_bytes());
+    case(
+        "wrong-key",
+        "Well-formed signature by a different key",
+        foreign,
+    );
+    for (id, description, len) in [
+        ("salt-empty", "Empty salt, correctly signed", 0),
+        ("salt-short", "31-byte salt, correctly signed", SALT_LEN - 1),
+        ("salt-long", "33-byte salt, correctly signed", SALT_LEN + 1),
+    ] {
+        let payload = with_salt(len);
+        case(id, description, outcome(&key, &payload, &signed(&payload)));
+    }
+    let mut truncated = outcome(&key, &plain, &signed(&plain));
+    truncated.signature.truncate(126);
+    case(
+        "signature-truncated",
+        "Signature cut to 63 bytes",
+        truncated,
+    );
+    let mut truncated = outcome(&key, &plain, &signed(&plain));
+    truncated.signature.clear();
+    case("signature-empty", "Empty signature", truncated);
+    let mut padded = outcome(&key, &plain, &signed(&plain));
+    padded.signature.push_str("00");
+    case(
+        "signature-padded",
+        "Valid signature followed by a zero byte",
+        padded,
+    );
+    let mut malleated = outcome(&key, &plain, &signed(&plain));
+    malleated.signature = hex::encode(add_group_order(&signed(&plain)));
+    case(
+        "signature-malleated",
+        "Valid signature with the group order added to s, which must be rejected as non-canonical",
+        malleated,
+    );
+    let mut downgraded = outcome(&key, &extended, &signed(&plain));
+    downgraded.counter = extended.counter;
+    case(
+        "downgraded-encoding",
+        "Carries a counter but is signed over the plain encoding without it",
+        downgraded,
+    );
+    let mut future = extended.encode();
+    future.splice(..PAYLOAD_DOMAIN.len(), FUTURE_DOMAIN.iter().copied());
+    case(
+        "future-domain",
+        "Fields encoded under the v3 domain tag and signed",
+        outcome(
+            &key,
+            &extended,
+            &sign(&key, &Sha256::digest(&future).into()),
+        ),
+    );
+
+    for (id, version) in [("version-1", 1), ("version-3", 3)] {
+        cases.push((
+            id.to_string(),
+            format!("Valid v2 attestation labelled payload version {}", version),
+            version,
+            outcome(&key, &extended, &signed(&extended)),
+        ));
+    }
+
+    let vectors = cases
+        .into_iter()
+        .map(|(id, description, version, outcome)| {
+            let mut vector = Vector {
+                id,
+                description,
+                version,
+                outcome,
+                valid: false,
+                reason: None,
+            };
+            match reference(&vector) {
+                Ok(()) => vector.valid = true,
+                Err(e) => vector.reason = Some(e),
+            }
+            vector
+        })
+        .collect();
+    Suite {
+        suite: SUITE.to_string(),
+        public_key: hex::encode(key.verifying_key().as_bytes()),
+        vectors,
+    }
+}
+
+fn outcome(key: &SigningKey, payload: &AttestationPayload, signature: &[u8; 64]) -> TaskOutcome {
+    TaskOutcome {
+        task_id: "conformance".to_string(),
+        random_number: hex::encode(&payload.random_number),
+        salt: hex::encode(&payload.salt),
+        signature: hex::encode(signature),
+        public_key: hex::encode(key.verifying_key().as_bytes()),
+        counter: payload.counter,
+        domain: payload.domain.clone(),
+        ..Default::default()
+    }
+}
+
+fn sign(key: &SigningKey, digest: &[u8; 32]) -> [u8; 64] {
+    key.sign(digest).to_bytes()
+}
+
+fn fixed_key(domain: &[u8]) -> SigningKey {
+    SigningKey::from_bytes(&Sha256::digest(domain).into())
+}
+
+/// `len` bytes that depend only on `label`.
+fn fixed_bytes(label: &[u8], len: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(len);
+    let mut counter = 0u32;
+    while bytes.len() < len {
+        bytes.extend_from_slice(
+            &Sha256::new()
+                .chain_update(SUITE.as_bytes())
+                .chain_update(label)
+                .chain_update(counter.to_be_bytes())
+                .finalize(),
+        );
+        counter += 1;
+    }
+    bytes.truncate(len);
+    bytes
+}
+
+/// Flips bit `bit` (most significant first) of hex `value`.
+fn flip_bit(value: &str, bit: usize) -> String {
+    let mut bytes = hex::decode(value).expect("suite values are hex");
+    bytes[bit / 8] ^= 0x80 >> (bit % 8);
+    hex::encode(bytes)
+}
+
+/// `signature` with `L` added to its `s` half: the same point equation holds,
+/// but `s` is no longer reduced.
+fn add_group_order(signature: &[u8; 64]) -> [u8; 64] {
+    let mut malleated = *signature;
+    let mut carry = 0u16;
+    for (byte, order) in malleated[32..].iter_mut().zip(GROUP_ORDER) {
+        let sum = u16::from(*byte) + u16::from(order) + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+    malleated
+}
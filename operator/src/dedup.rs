@@ -0,0 +1,215 @@
+// src/dedup.rs
+
+//! Persistent deduplication of fulfilled tasks.
+//!
+//! The in-flight set of the [`crate::tasks::TaskRunner`] only lives as long
+//! as the process, and idempotency keys (see [`crate::idempotency`]) are only
+//! recorded once the HTTP response is ready. A request that arrives again
+//! after a crash could therefore be fulfilled a second time, with a different
+//! value. The runner records every fulfilled task key in [`FULFILLED_TASKS`]
+//! before forgetting the pending task, and consults it before admitting a new
+//! one. A repeated task is answered with the stored outcome or refused,
+//! depending on [`OnDuplicate`]. Records are pruned once they are older than
+//! the configured TTL.
+//!
+//! The entries of a batch are recorded too, pointing at the batch. They are
+//! always refused, since no single outcome answers them.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Storage;
+use crate::tasks::{unix_millis, TaskOutcome};
+
+/// Collection of the fulfilled tasks, keyed by [`crate::tenants::task_key`].
+pub const FULFILLED_TASKS: &str = "fulfilled_tasks";
+
+/// Expired records are looked for at most this often.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How a request for an already fulfilled task is answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnDuplicate {
+    /// With the outcome it was fulfilled with.
+    #[default]
+    Replay,
+    /// With an error.
+    Reject,
+}
+
+impl OnDuplicate {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OnDuplicate::Replay => "replay",
+            OnDuplicate::Reject => "reject",
+        }
+    }
+}
+
+/// What is stored per fulfilled task.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Record {
+    fulfilled_at: u64,
+    length: usize,
+    /// Absent for the entries of a batch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    outcome: Option<TaskOutcome>,
+    /// Key of the batch a batched task was fulfilled in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    batch: Option<String>,
+}
+
+/// Result of [`FulfilledTasks::check`] for a task fulfilled before.
+pub enum Duplicate {
+    /// Answer with this outcome.
+    Replay(Box<TaskOutcome>),
+    /// Refuse the request, for this reason.
+    Reject(String),
+}
+
+/// The tasks fulfilled within the TTL.
+pub struct FulfilledTasks {
+    storage: Arc<dyn Storage>,
+    ttl: Duration,
+    on_duplicate: OnDuplicate,
+    last_purge: Mutex<Instant>,
+}
+
+impl FulfilledTasks {
+    pub fn new(storage: Arc<dyn Storage>, ttl: Duration, on_duplicate: OnDuplicate) -> Self {
+        FulfilledTasks {
+            storage,
+            ttl,
+            on_duplicate,
+            last_purge: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Looks up a request for `length` bytes under task key `key`, and the
+    /// keys of the tasks it batches; `None` if none was fulfilled before.
+    pub fn check(
+        &self,
+        key: &str,
+        length: usize,
+        entries: &[String],
+    ) -> Result<Option<Duplicate>, String> {
+        self.purge_if_due();
+        if let Some(record) = self.lookup(key)? {
+            return Ok(Some(self.duplicate(key, length, record)));
+        }
+        for entry in entries {
+            if let Some(record) = self.lookup(entry)? {
+                return Ok(Some(Duplicate::Reject(format!(
+                    "batched task {} was already fulfilled{}",
+                    entry,
+                    record
+                        .batch
+                        .map(|batch| format!(" in batch {}", batch))
+                        .unwrap_or_default()
+                ))));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Records that task `key` was fulfilled with `outcome`, as were the
+    /// batched tasks `entries` (key and length) under it, and flushes.
+    pub fn record(
+        &self,
+        key: &str,
+        outcome: &TaskOutcome,
+        length: usize,
+        entries: &[(String, usize)],
+    ) -> Result<(), String> {
+        let fulfilled_at = unix_millis();
+        for (entry, length) in entries {
+            let record = Record {
+                fulfilled_at,
+                length: *length,
+                outcome: None,
+                batch: Some(key.to_string()),
+            };
+            let value = serde_json::to_value(&record).map_err(|e| e.to_string())?;
+            self.storage.put(FULFILLED_TASKS, entry, value)?;
+        }
+        let record = Record {
+            fulfilled_at,
+            length,
+            outcome: Some(outcome.clone()),
+            batch: None,
+        };
+        let value = serde_json::to_value(&record).map_err(|e| e.to_string())?;
+        self.storage.put(FULFILLED_TASKS, key, value)?;
+        self.storage.flush()
+    }
+
+    /// Deletes the records older than the TTL; returns how many.
+    pub fn purge_expired(&self) -> Result<usize, String> {
+        let mut purged = 0;
+        for (key, value) in self.storage.scan(FULFILLED_TASKS)? {
+            let expired = serde_json::from_value::<Record>(value)
+                .map_or(true, |record| self.expired(&record));
+            if expired {
+                self.storage.delete(FULFILLED_TASKS, &key)?;
+                purged += 1;
+            }
+        }
+        if purged > 0 {
+            self.storage.compact(FULFILLED_TASKS)?;
+        }
+        Ok(purged)
+    }
+
+    fn lookup(&self, key: &str) -> Result<Option<Record>, String> {
+        Ok(self
+            .storage
+            .get(FULFILLED_TASKS, key)?
+            .and_then(|value| serde_json::from_value::<Record>(value).ok())
+            .filter(|record| !self.expired(record)))
+    }
+
+    fn duplicate(&self, key: &str, length: usize, record: Record) -> Duplicate {
+        let Some(outcome) = record.outcome else {
+            return Duplicate::Reject(format!(
+                "task {} was already fulfilled in batch {}",
+                key,
+                record.batch.unwrap_or_default()
+            ));
+        };
+        if record.length != length {
+            return Duplicate::Reject(format!(
+                "task {} was already fulfilled with {} bytes, not {}",
+                key, record.length, length
+            ));
+        }
+        match self.on_duplicate {
+            OnDuplicate::Replay => Duplicate::Replay(Box::new(outcome)),
+            OnDuplicate::Reject => Duplicate::Reject(format!(
+                "task {} was already fulfilled at {}",
+                key, record.fulfilled_at
+            )),
+        }
+    }
+
+    fn expired(&self, record: &Record) -> bool {
+        unix_millis().saturating_sub(record.fulfilled_at) >= self.ttl.as_millis() as u64
+    }
+
+    fn purge_if_due(&self) {
+        {
+            let mut last = self.last_purge.lock().expect("dedup lock poisoned");
+            if last.elapsed() < PURGE_INTERVAL {
+                return;
+            }
+            *last = Instant::now();
+        }
+        if let Err(e) = self.purge_expired() {
+            warn!("Failed to purge expired fulfilled tasks: {}", e);
+        }
+    }
+}
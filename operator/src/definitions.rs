@@ -0,0 +1,165 @@
+// src/definitions.rs
+
+//! Task definitions: named parameter sets one operator serves side by side.
+//!
+//! An Othentic AVS can run several task types against the same operator, say
+//! a 32-byte seed, a dice roll and a card shuffle. Each is configured under
+//! `task_definitions` with the output length (and the range a request may
+//! pick from, by default that length alone), how the value is derived (fresh
+//! entropy or a VRF over the task ID), which signatures it carries, the hash
+//! its payload is digested with and how long it stays valid. A request names
+//! its definition with `taskDefinitionId`; the ID is signed into the
+//! attestation, so a value made for one task type does not pass as another's.
+//! Settings a definition leaves unset follow the operator's own config.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::attester::HashAlg;
+use crate::config::{self, TaskDefinitionConfig};
+use crate::performer::LengthPolicy;
+
+/// How a definition's values are derived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Derivation {
+    /// Fresh operator entropy.
+    Random,
+    /// A VRF over the task ID, so every fulfilment yields the same value.
+    Vrf,
+}
+
+/// The signatures a definition's attestations carry besides ed25519.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SigningSchemes {
+    pub secp256k1: bool,
+    pub schnorr: bool,
+}
+
+/// A task definition as configured.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskDefinition {
+    pub id: String,
+    /// Bytes of randomness per task when a request sets no length.
+    pub length: usize,
+    pub min_length: usize,
+    pub max_length: usize,
+    /// The operator's derivation when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub derivation: Option<Derivation>,
+    /// Every signature the operator makes when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signing: Option<SigningSchemes>,
+    pub hash: HashAlg,
+    /// Validity window; the operator's `signing.validity` when unset.
+    #[serde(skip_serializing_if = "Option::is_none", with = "millis")]
+    pub expiry: Option<Duration>,
+}
+
+mod millis {
+    use std::time::Duration;
+
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(value: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(d) => s.serialize_u64(d.as_millis() as u64),
+            None => s.serialize_none(),
+        }
+    }
+}
+
+impl TaskDefinition {
+    /// The lengths a request under the definition may ask for.
+    pub fn policy(&self) -> LengthPolicy {
+        LengthPolicy {
+            min: self.min_length,
+            max: self.max_length,
+        }
+    }
+
+    fn from_config(entry: &TaskDefinitionConfig) -> Result<Self, String> {
+        let signing = match &entry.signing {
+            Some(schemes) => {
+                let mut signing = SigningSchemes {
+                    secp256k1: false,
+                    schnorr: false,
+                };
+                for scheme in schemes {
+                    match scheme.as_str() {
+                        "ed25519" => {}
+                        "secp256k1" => signing.secp256k1 = true,
+                        "schnorr" => signing.schnorr = true,
+                        other => {
+                            return Err(format!(
+                                "task definition {}: unknown signing scheme {}",
+                                entry.id, other
+                            ))
+                        }
+                    }
+                }
+                Some(signing)
+            }
+            None => None,
+        };
+        let policy = entry.policy()?;
+        Ok(TaskDefinition {
+            id: entry.id.clone(),
+            length: entry.length,
+            min_length: policy.min,
+            max_length: policy.max,
+            derivation: entry.derivation,
+            signing,
+            hash: entry.hash,
+            expiry: entry
+                .expiry
+                .as_deref()
+                .map(config::parse_duration)
+                .transpose()?,
+        })
+    }
+}
+
+/// The configured task definitions, by ID.
+#[derive(Debug, Clone, Default)]
+pub struct DefinitionRegistry {
+    definitions: BTreeMap<String, Arc<TaskDefinition>>,
+}
+
+impl DefinitionRegistry {
+    /// Builds the registry from the `task_definitions` config section.
+    pub fn from_config(entries: &[TaskDefinitionConfig]) -> Result<Self, String> {
+        let mut definitions = BTreeMap::new();
+        for entry in entries {
+            let definition = TaskDefinition::from_config(entry)?;
+            if definitions
+                .insert(definition.id.clone(), Arc::new(definition))
+                .is_some()
+            {
+                return Err(format!("task definition {} is listed twice", entry.id));
+            }
+        }
+        Ok(DefinitionRegistry { definitions })
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<TaskDefinition>> {
+        self.definitions.get(id).cloned()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.definitions.is_empty()
+    }
+
+    pub fn ids(&self) -> Vec<&str> {
+        self.definitions.keys().map(String::as_str).collect()
+    }
+
+    /// Every definition, ordered by ID.
+    pub fn list(&self) -> Vec<&TaskDefinition> {
+        self.definitions.values().map(Arc::as_ref).collect()
+    }
+}
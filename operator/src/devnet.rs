@@ -0,0 +1,594 @@
+// src/devnet.rs
+
+//! In-process devnet of simulated operators.
+//!
+//! `operator devnet` runs a small AVS in one process, so integrators can
+//! develop against multi-operator behaviour on a laptop, without a chain:
+//!
+//! - `operators` nodes, each with its own attestation and PVSS keys and its
+//!   own [`TaskRunner`], joined by a [`MemoryBus`] that carries the signed
+//!   [`Envelope`]s of [`crate::p2p`] between them and drops a `loss` share;
+//! - the nodes take turns as the performer of a request: it generates and
+//!   attests the value and gossips the outcome, and every other node checks
+//!   it as `POST /task/validate` does and gossips its signed [`Verdict`];
+//! - a mock aggregator on the bus pins every node's key, accepts an outcome
+//!   once `quorum` other nodes approved it, and checks what it accepted the
+//!   way a consumer does, against the performer's pinned key.
+//!
+//! The last `faulty` nodes misbehave: as performers they flip a bit of the
+//! salt after signing, and as attesters they approve anything unchecked.
+//! Every request is reported with its verdicts, its latency and whether the
+//! aggregator accepted and verified it.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ed25519_dalek::VerifyingKey;
+use log::warn;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::attester::RngAttester;
+use crate::config::RoleConfig;
+use crate::metrics::Metrics;
+use crate::p2p::{Envelope, P2pNode, Peer, Transport};
+use crate::performer::RngPerformer;
+use crate::pvss::KeyPair;
+use crate::queue::Priority;
+use crate::reverify;
+use crate::roles::{Validator, Verdict};
+use crate::storage::MemoryStorage;
+use crate::tasks::{Submitter, TaskOutcome, TaskRequest, TaskRunner};
+
+/// Bus ID of the mock aggregator; the operators are 1 to `operators`.
+const AGGREGATOR_ID: u32 = 0;
+/// How often listeners look at the stop flag.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Tasks each node's runner queues.
+const QUEUE_CAPACITY: usize = 64;
+
+/// Shape of the devnet, from the `devnet` command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DevnetConfig {
+    pub operators: usize,
+    /// Approvals from nodes other than the performer an outcome needs.
+    pub quorum: usize,
+    pub requests: usize,
+    /// Bytes per random number.
+    pub length: usize,
+    pub faulty: usize,
+    /// Share of envelopes the bus drops, from 0 to 1.
+    pub loss: f64,
+    /// How long the aggregator waits for a quorum on each request.
+    pub timeout: Duration,
+}
+
+impl Default for DevnetConfig {
+    fn default() -> Self {
+        DevnetConfig {
+            operators: 4,
+            quorum: 2,
+            requests: 10,
+            length: 32,
+            faulty: 0,
+            loss: 0.0,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl DevnetConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.operators < 2 {
+            return Err("a devnet needs at least 2 operators".to_string());
+        }
+        if self.quorum == 0 || self.quorum >= self.operators {
+            return Err(format!(
+                "quorum must be 1 to {}, the operators other than the performer",
+                self.operators - 1
+            ));
+        }
+        if self.faulty > self.operators {
+            return Err("faulty must be at most the number of operators".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.loss) {
+            return Err("loss must be between 0 and 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Delivers envelopes between the nodes of one process.
+pub struct MemoryBus {
+    nodes: RwLock<HashMap<u32, Arc<P2pNode>>>,
+    loss: f64,
+}
+
+impl MemoryBus {
+    pub fn new(loss: f64) -> Self {
+        MemoryBus {
+            nodes: RwLock::new(HashMap::new()),
+            loss,
+        }
+    }
+
+    /// Makes `node` reachable under its ID.
+    pub fn join(&self, node: Arc<P2pNode>) {
+        self.nodes
+            .write()
+            .expect("bus lock poisoned")
+            .insert(node.id(), node);
+    }
+}
+
+impl Transport for MemoryBus {
+    fn send(&self, peer: &Peer, envelope: &Envelope) -> Result<(), String> {
+        if self.loss > 0.0 && rand::thread_rng().gen_bool(self.loss) {
+            return Err("dropped by the devnet bus".to_string());
+        }
+        let node = self
+            .nodes
+            .read()
+            .expect("bus lock poisoned")
+            .get(&peer.id)
+            .cloned()
+            .ok_or_else(|| format!("Peer {} is not on the bus", peer.id))?;
+        node.receive(envelope.clone())
+    }
+}
+
+/// What the nodes gossip.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Message {
+    Outcome { outcome: Box<TaskOutcome> },
+    Verdict { verdict: Box<Verdict> },
+}
+
+impl Message {
+    fn broadcast(&self, node: &Arc<P2pNode>) -> Result<(), String> {
+        let body =
+            serde_json::to_string(self).map_err(|e| format!("Failed to encode message: {}", e))?;
+        node.broadcast(body);
+        Ok(())
+    }
+}
+
+/// Submitter of a devnet node: gossips every outcome on the bus.
+struct GossipSubmitter {
+    node: Arc<P2pNode>,
+    faulty: bool,
+}
+
+impl Submitter for GossipSubmitter {
+    fn submit(&self, outcome: &TaskOutcome) -> Result<(), String> {
+        let mut outcome = outcome.clone();
+        if self.faulty {
+            let mut salt = hex::decode(&outcome.salt).map_err(|e| e.to_string())?;
+            if let Some(first) = salt.first_mut() {
+                *first ^= 0x01;
+            }
+            outcome.salt = hex::encode(salt);
+        }
+        Message::Outcome {
+            outcome: Box::new(outcome),
+        }
+        .broadcast(&self.node)
+    }
+}
+
+/// A node of the devnet, as reported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatorInfo {
+    pub id: u32,
+    /// Hex ed25519 attestation key.
+    pub public_key: String,
+    pub faulty: bool,
+}
+
+/// How one request went.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestReport {
+    pub task_id: String,
+    pub performer: u32,
+    /// Nodes whose approval the aggregator counted.
+    pub approvals: Vec<u32>,
+    /// Rejections and unusable verdicts, by node.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rejections: Vec<String>,
+    pub accepted: bool,
+    /// Why the performer produced nothing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Why the accepted outcome fails a consumer's checks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification_error: Option<String>,
+    /// From the request to the aggregator's decision.
+    pub latency_ms: u64,
+    /// The outcome the aggregator accepted.
+    #[serde(skip)]
+    pub outcome: Option<TaskOutcome>,
+}
+
+/// Everything a devnet run did.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevnetReport {
+    pub operators: Vec<OperatorInfo>,
+    pub quorum: usize,
+    pub requests: Vec<RequestReport>,
+}
+
+impl DevnetReport {
+    pub fn accepted(&self) -> usize {
+        self.requests.iter().filter(|r| r.accepted).count()
+    }
+
+    /// Accepted outcomes that fail verification: a quorum agreed on a bad
+    /// value.
+    pub fn invalid(&self) -> usize {
+        self.requests
+            .iter()
+            .filter(|r| r.accepted && r.verification_error.is_some())
+            .count()
+    }
+
+    /// Requests of honest performers that were not accepted.
+    pub fn stalled(&self) -> usize {
+        self.requests
+            .iter()
+            .filter(|r| !r.accepted && !self.is_faulty(r.performer))
+            .count()
+    }
+
+    fn is_faulty(&self, id: u32) -> bool {
+        self.operators.iter().any(|o| o.id == id && o.faulty)
+    }
+}
+
+/// What the aggregator has heard about one task.
+#[derive(Default)]
+struct Round {
+    outcome: Option<(u32, TaskOutcome)>,
+    verdicts: Vec<(u32, Verdict)>,
+}
+
+/// Mock aggregator: collects outcomes and verdicts from the bus.
+struct Aggregator {
+    node: Arc<P2pNode>,
+    keys: HashMap<u32, VerifyingKey>,
+    rounds: Mutex<HashMap<String, Round>>,
+    changed: Condvar,
+}
+
+impl Aggregator {
+    fn listen(&self, stop: &AtomicBool) {
+        while !stop.load(Ordering::Relaxed) {
+            for envelope in self.node.wait(Instant::now() + POLL_INTERVAL) {
+                let Ok(message) = serde_json::from_str::<Message>(&envelope.body) else {
+                    continue;
+                };
+                let mut rounds = self.rounds.lock().expect("aggregator lock poisoned");
+                match message {
+                    Message::Outcome { outcome } => {
+                        let round = rounds.entry(outcome.task_id.clone()).or_default();
+                        round.outcome.get_or_insert((envelope.sender, *outcome));
+                    }
+                    Message::Verdict { verdict } => rounds
+                        .entry(verdict.task_id.clone())
+                        .or_default()
+                        .verdicts
+                        .push((envelope.sender, *verdict)),
+                }
+                self.changed.notify_all();
+            }
+        }
+    }
+
+    /// Waits until `task_id` has `quorum` approvals, every other node has
+    /// answered or `deadline` passes; returns the outcome with the approvals
+    /// and rejections counted.
+    fn decide(
+        &self,
+        task_id: &str,
+        quorum: usize,
+        deadline: Instant,
+    ) -> (Option<(u32, TaskOutcome)>, Vec<u32>, Vec<String>) {
+        let attesters = self.keys.len() - 1;
+        let mut rounds = self.rounds.lock().expect("aggregator lock poisoned");
+        loop {
+            let round = rounds.entry(task_id.to_string()).or_default();
+            let (approvals, rejections) = self.tally(round);
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if approvals.len() >= quorum
+                || approvals.len() + rejections.len() >= attesters
+                || remaining == Duration::ZERO
+            {
+                let outcome = rounds.remove(task_id).and_then(|round| round.outcome);
+                return (outcome, approvals, rejections);
+            }
+            rounds = self
+                .changed
+                .wait_timeout(rounds, remaining)
+                .expect("aggregator lock poisoned")
+                .0;
+        }
+    }
+
+    /// Approving nodes, and rejections, among the verdicts on the outcome
+    /// of `round`; one per node, from its pinned key.
+    fn tally(&self, round: &Round) -> (Vec<u32>, Vec<String>) {
+        let mut approvals = Vec::new();
+        let mut rejections = Vec::new();
+        let Some((performer, outcome)) = &round.outcome else {
+            return (approvals, rejections);
+        };
+        let digest = match outcome.to_attestation() {
+            Ok(attestation) => hex::encode(attestation.payload.digest()),
+            Err(_) => return (approvals, rejections),
+        };
+        let mut answered = Vec::new();
+        for (sender, verdict) in &round.verdicts {
+            if sender == performer || answered.contains(sender) {
+                continue;
+            }
+            answered.push(*sender);
+            let pinned = self
+                .keys
+                .get(sender)
+                .is_some_and(|key| verdict.is_from(key).unwrap_or(false));
+            if !pinned {
+                rejections.push(format!(
+                    "operator {}: verdict is not signed by its pinned key",
+                    sender
+                ));
+            } else if verdict.digest != digest {
+                rejections.push(format!(
+                    "operator {}: verdict is about a different attestation",
+                    sender
+                ));
+            } else if verdict.approved {
+                approvals.push(*sender);
+            } else {
+                rejections.push(format!(
+                    "operator {}: {}",
+                    sender,
+                    verdict.reason.clone().unwrap_or_default()
+                ));
+            }
+        }
+        (approvals, rejections)
+    }
+}
+
+/// One simulated operator.
+struct Node {
+    id: u32,
+    runner: Arc<TaskRunner>,
+    faulty: bool,
+}
+
+/// A running devnet; its listeners stop when it is dropped.
+pub struct Devnet {
+    config: DevnetConfig,
+    nodes: Vec<Node>,
+    aggregator: Arc<Aggregator>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Devnet {
+    /// Generates the keys of every node and starts them on a shared bus.
+    pub fn start(config: DevnetConfig) -> Result<Self, String> {
+        config.validate()?;
+        let metrics = Arc::new(Metrics::new());
+        let bus = Arc::new(MemoryBus::new(config.loss));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let ids: Vec<u32> = (AGGREGATOR_ID..=config.operators as u32).collect();
+        let bus_keys: Vec<KeyPair> = ids.iter().map(|_| KeyPair::generate()).collect();
+        let peers: Vec<Peer> = ids
+            .iter()
+            .zip(&bus_keys)
+            .map(|(id, key)| Peer {
+                id: *id,
+                url: format!("memory://node-{}", id),
+                public_key: key.public(),
+            })
+            .collect();
+        let mut p2p = Vec::new();
+        for (id, key) in ids.iter().zip(bus_keys) {
+            let transport: Arc<dyn Transport> = bus.clone();
+            let node = Arc::new(P2pNode::new(*id, key, peers.clone(), transport));
+            bus.join(Arc::clone(&node));
+            p2p.push(node);
+        }
+
+        let mut nodes = Vec::new();
+        let mut keys = HashMap::new();
+        for node in &p2p[1..] {
+            let id = node.id();
+            let faulty = id as usize > config.operators - config.faulty;
+            let attester = RngAttester::new()?;
+            keys.insert(id, *attester.get_public_key());
+            let submitter = Arc::new(GossipSubmitter {
+                node: Arc::clone(node),
+                faulty,
+            });
+            let runner = Arc::new(TaskRunner::new(
+                RngPerformer::new(),
+                attester,
+                Arc::new(MemoryStorage::new()),
+                submitter,
+                Arc::clone(&metrics),
+                QUEUE_CAPACITY,
+            ));
+            runner.start_workers(1);
+            nodes.push(Node { id, runner, faulty });
+        }
+
+        let role = RoleConfig {
+            trusted_performers: nodes
+                .iter()
+                .map(|n| hex::encode(keys[&n.id].as_bytes()))
+                .collect(),
+            ..RoleConfig::default()
+        };
+        for (node, p2p) in nodes.iter().zip(&p2p[1..]) {
+            let validator = Validator::from_config(&role, Arc::clone(&metrics))?;
+            let attester = node.runner.attester();
+            let p2p = Arc::clone(p2p);
+            let stop = Arc::clone(&stop);
+            let faulty = node.faulty;
+            thread::spawn(move || attest(&p2p, &attester, &validator, faulty, &stop));
+        }
+
+        let aggregator = Arc::new(Aggregator {
+            node: Arc::clone(&p2p[0]),
+            keys,
+            rounds: Mutex::new(HashMap::new()),
+            changed: Condvar::new(),
+        });
+        {
+            let aggregator = Arc::clone(&aggregator);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || aggregator.listen(&stop));
+        }
+        Ok(Devnet {
+            config,
+            nodes,
+            aggregator,
+            stop,
+        })
+    }
+
+    pub fn operators(&self) -> Vec<OperatorInfo> {
+        self.nodes
+            .iter()
+            .map(|node| OperatorInfo {
+                id: node.id,
+                public_key: hex::encode(node.runner.attester().get_public_key().as_bytes()),
+                faulty: node.faulty,
+            })
+            .collect()
+    }
+
+    /// Runs the configured number of requests, one after the other.
+    pub fn run(&self) -> DevnetReport {
+        DevnetReport {
+            operators: self.operators(),
+            quorum: self.config.quorum,
+            requests: (0..self.config.requests).map(|n| self.request(n)).collect(),
+        }
+    }
+
+    /// Runs request `n`, performed by node `n % operators + 1`.
+    pub fn request(&self, n: usize) -> RequestReport {
+        let node = &self.nodes[n % self.nodes.len()];
+        let task_id = format!("devnet-{}", n + 1);
+        let started = Instant::now();
+        let mut report = RequestReport {
+            task_id: task_id.clone(),
+            performer: node.id,
+            approvals: Vec::new(),
+            rejections: Vec::new(),
+            accepted: false,
+            error: None,
+            verification_error: None,
+            latency_ms: 0,
+            outcome: None,
+        };
+        let executed = node.runner.execute(TaskRequest {
+            task_id: task_id.clone(),
+            length: self.config.length,
+            priority: Priority::default(),
+            deadline: None,
+            client_entropy: None,
+            trace: None,
+            dry_run: false,
+            tenant: None,
+            domain: None,
+            definition: None,
+            timelock: None,
+            batch: None,
+        });
+        if let Err(e) = executed {
+            report.error = Some(e.to_string());
+            report.latency_ms = started.elapsed().as_millis() as u64;
+            return report;
+        }
+
+        let deadline = started + self.config.timeout;
+        let (outcome, approvals, rejections) =
+            self.aggregator
+                .decide(&task_id, self.config.quorum, deadline);
+        report.latency_ms = started.elapsed().as_millis() as u64;
+        report.accepted = outcome.is_some() && approvals.len() >= self.config.quorum;
+        report.approvals = approvals;
+        report.rejections = rejections;
+        match outcome {
+            Some((performer, outcome)) if report.accepted => {
+                let pinned = [self.aggregator.keys[&performer]];
+                report.verification_error = reverify::check_outcome(&outcome, &pinned)
+                    .err()
+                    .map(|(kind, detail)| format!("{}: {}", kind.as_str(), detail));
+                report.outcome = Some(outcome);
+            }
+            Some(_) => {}
+            None => report.error = Some("the aggregator never received the outcome".to_string()),
+        }
+        report
+    }
+}
+
+impl Drop for Devnet {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Checks every outcome gossiped by another node and gossips the verdict.
+fn attest(
+    node: &Arc<P2pNode>,
+    attester: &RngAttester,
+    validator: &Validator,
+    faulty: bool,
+    stop: &AtomicBool,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        for envelope in node.wait(Instant::now() + POLL_INTERVAL) {
+            if envelope.sender == node.id() {
+                continue;
+            }
+            let Ok(Message::Outcome { outcome }) = serde_json::from_str(&envelope.body) else {
+                continue;
+            };
+            let verdict = if faulty {
+                outcome.to_attestation().and_then(|attestation| {
+                    let digest = hex::encode(attestation.payload.digest());
+                    Verdict::sign(attester, &outcome.task_id, &digest, None)
+                })
+            } else {
+                validator.validate(attester, &outcome)
+            };
+            let sent = verdict.and_then(|verdict| {
+                Message::Verdict {
+                    verdict: Box::new(verdict),
+                }
+                .broadcast(node)
+            });
+            if let Err(e) = sent {
+                warn!(
+                    "Node {} gave no verdict on task {}: {}",
+                    node.id(),
+                    outcome.task_id,
+                    e
+                );
+            }
+        }
+    }
+}
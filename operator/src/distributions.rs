@@ -0,0 +1,145 @@
+// src/distributions.rs
+
+//! Verifiable sampling from common distributions.
+//!
+//! A [`Sampler`] expands an attested seed into a deterministic sequence of
+//! 64-bit words (SHA-256 in counter mode), and maps those words onto uniform,
+//! normal, exponential and categorical draws. Anyone holding the attestation
+//! can rebuild the same sampler and reproduce every drawn value.
+//!
+//! Uniform and categorical draws use only exact IEEE-754 operations and are
+//! bit-for-bit reproducible. Normal and exponential draws go through `f64::ln`
+//! (and `cos` for the normal), whose last-bit rounding can vary between libm
+//! implementations; verifiers on other platforms should compare those with a
+//! small tolerance.
+
+use ed25519_dalek::VerifyingKey;
+use sha2::{Digest, Sha256};
+
+use crate::attester::{Attestation, RngAttester};
+
+/// Domain tag for deriving sampler words from a seed.
+pub const SAMPLER_DOMAIN: &[u8] = b"othentic-rng/sampler/v1";
+
+/// Deterministic source of draws derived from a seed.
+pub struct Sampler {
+    key: [u8; 32],
+    counter: u64,
+    block: [u8; 32],
+    offset: usize,
+}
+
+impl Sampler {
+    /// Builds a sampler from a verified attestation.
+    ///
+    /// The signature is checked first, so draws can only ever come from an
+    /// authentic random number. `label` separates independent sample streams
+    /// taken from the same attestation (e.g. `b"prize-tier"`, `b"winner"`).
+    pub fn from_attestation(
+        public_key: &VerifyingKey,
+        attestation: &Attestation,
+        label: &[u8],
+    ) -> Result<Self, String> {
+        RngAttester::verify(public_key, attestation)?;
+        Ok(Self::from_seed(&attestation.payload.random_number, label))
+    }
+
+    /// Builds a sampler directly from seed bytes, without any verification.
+    pub fn from_seed(seed: &[u8], label: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(SAMPLER_DOMAIN);
+        hasher.update((seed.len() as u64).to_be_bytes());
+        hasher.update(seed);
+        hasher.update((label.len() as u64).to_be_bytes());
+        hasher.update(label);
+        Sampler {
+            key: hasher.finalize().into(),
+            counter: 0,
+            block: [0u8; 32],
+            offset: 32,
+        }
+    }
+
+    /// Returns the next 64 uniformly distributed bits.
+    pub fn next_u64(&mut self) -> u64 {
+        if self.offset + 8 > self.block.len() {
+            let mut hasher = Sha256::new();
+            hasher.update(self.key);
+            hasher.update(self.counter.to_be_bytes());
+            self.block = hasher.finalize().into();
+            self.counter += 1;
+            self.offset = 0;
+        }
+        let mut word = [0u8; 8];
+        word.copy_from_slice(&self.block[self.offset..self.offset + 8]);
+        self.offset += 8;
+        u64::from_be_bytes(word)
+    }
+
+    /// Uniform `f64` in `[0, 1)` with the full 53 bits of mantissa precision.
+    pub fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Uniform integer in `[0, bound)` without modulo bias.
+    pub fn uniform_below(&mut self, bound: u64) -> Result<u64, String> {
+        if bound == 0 {
+            return Err("Bound must be a positive integer.".to_string());
+        }
+        // Reject the top partial range so every residue is equally likely.
+        let zone = u64::MAX - (u64::MAX % bound);
+        loop {
+            let word = self.next_u64();
+            if word < zone {
+                return Ok(word % bound);
+            }
+        }
+    }
+
+    /// Normal draw with the given mean and standard deviation (Box–Muller).
+    pub fn normal(&mut self, mean: f64, std_dev: f64) -> Result<f64, String> {
+        if !(std_dev.is_finite() && std_dev >= 0.0 && mean.is_finite()) {
+            return Err("Mean must be finite and standard deviation non-negative.".to_string());
+        }
+        // 1 - u lies in (0, 1], keeping ln away from zero.
+        let u1 = 1.0 - self.uniform();
+        let u2 = self.uniform();
+        let z = (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+        Ok(mean + std_dev * z)
+    }
+
+    /// Exponential draw with rate `lambda` (inverse transform).
+    pub fn exponential(&mut self, lambda: f64) -> Result<f64, String> {
+        if !(lambda.is_finite() && lambda > 0.0) {
+            return Err("Rate must be a positive, finite number.".to_string());
+        }
+        let u = 1.0 - self.uniform();
+        Ok(-u.ln() / lambda)
+    }
+
+    /// Draws an index with probability proportional to `weights[index]`.
+    pub fn categorical(&mut self, weights: &[f64]) -> Result<usize, String> {
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return Err("Weights must be finite and non-negative.".to_string());
+        }
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return Err("At least one weight must be positive.".to_string());
+        }
+
+        let target = self.uniform() * total;
+        let mut cumulative = 0.0;
+        for (index, weight) in weights.iter().enumerate() {
+            cumulative += weight;
+            if target < cumulative {
+                return Ok(index);
+            }
+        }
+        // Rounding can leave `target` just past the final sum; fall back to
+        // the last category that can actually be drawn.
+        Ok(weights
+            .iter()
+            .rposition(|w| *w > 0.0)
+            .expect("a positive weight exists"))
+    }
+}
@@ -0,0 +1,350 @@
+// src/drand.rs
+
+//! drand interoperability: fetch, verify and re-attest drand beacon rounds.
+//!
+//! A [`DrandClient`] is pinned to one chain by its chain hash. The chain info
+//! served by the relay is only accepted if it hashes to that value, which fixes
+//! the group public key; every fetched round is then checked against that key
+//! with the BLS scheme the chain uses. Verified rounds are mixed into
+//! operator-local entropy with [`AttestationPayload::with_drand`], which keeps
+//! the round, its signature and the local contribution in the signed payload, so
+//! consumers can verify both the drand round and the operator's attestation from
+//! a single envelope with [`verify_attestation`].
+//!
+//! [`AttestationPayload::with_drand`]: crate::attester::AttestationPayload::with_drand
+
+use std::sync::{Arc, Mutex};
+
+use drand_verify::{G1Pubkey, G2PubkeyFastnet, G2PubkeyRfc, Pubkey};
+use ed25519_dalek::VerifyingKey;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::attester::{Attestation, RngAttester};
+use crate::resilience::{CallError, Resilience};
+
+const MIX_DOMAIN: &[u8] = b"othentic-rng/drand-mix/v1";
+
+/// Signature schemes used by drand networks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    /// League of Entropy mainnet: G1 keys, G2 signatures over the previous signature.
+    PedersenBlsChained,
+    PedersenBlsUnchained,
+    /// The retired "fastnet" (G2 keys, G1 signatures, non-standard DST).
+    BlsUnchainedOnG1,
+    /// "quicknet": G2 keys, G1 signatures per RFC 9380.
+    BlsUnchainedG1Rfc9380,
+}
+
+impl Scheme {
+    /// Parses a drand `schemeID`.
+    pub fn parse(id: &str) -> Result<Self, String> {
+        match id {
+            "pedersen-bls-chained" => Ok(Scheme::PedersenBlsChained),
+            "pedersen-bls-unchained" => Ok(Scheme::PedersenBlsUnchained),
+            "bls-unchained-on-g1" => Ok(Scheme::BlsUnchainedOnG1),
+            "bls-unchained-g1-rfc9380" => Ok(Scheme::BlsUnchainedG1Rfc9380),
+            _ => Err(format!("Unsupported drand scheme '{}'", id)),
+        }
+    }
+
+    /// Whether each round signs over the previous round's signature.
+    pub fn is_chained(self) -> bool {
+        self == Scheme::PedersenBlsChained
+    }
+}
+
+/// Parameters of a drand chain, as served by `GET /{chain_hash}/info`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainInfo {
+    pub public_key: Vec<u8>,
+    /// Seconds between rounds.
+    pub period: u32,
+    /// Unix time (seconds) of round 1.
+    pub genesis_time: i64,
+    pub hash: [u8; 32],
+    pub group_hash: Vec<u8>,
+    pub scheme: Scheme,
+    pub beacon_id: String,
+}
+
+#[derive(Deserialize)]
+struct ChainInfoJson {
+    public_key: String,
+    period: u32,
+    genesis_time: i64,
+    hash: String,
+    #[serde(rename = "groupHash")]
+    group_hash: String,
+    #[serde(rename = "schemeID", default = "default_scheme")]
+    scheme_id: String,
+    #[serde(default)]
+    metadata: Option<ChainMetadataJson>,
+}
+
+#[derive(Deserialize)]
+struct ChainMetadataJson {
+    #[serde(rename = "beaconID", default)]
+    beacon_id: String,
+}
+
+fn default_scheme() -> String {
+    "pedersen-bls-chained".to_string()
+}
+
+impl ChainInfo {
+    /// Parses chain info JSON and checks that it hashes to its `hash` field.
+    pub fn from_json(raw: &str) -> Result<Self, String> {
+        let json: ChainInfoJson =
+            serde_json::from_str(raw).map_err(|e| format!("Invalid drand chain info: {}", e))?;
+        let info = ChainInfo {
+            public_key: decode_hex("public_key", &json.public_key)?,
+            period: json.period,
+            genesis_time: json.genesis_time,
+            hash: decode_hex("hash", &json.hash)?
+                .try_into()
+                .map_err(|_| "drand chain hash must be 32 bytes".to_string())?,
+            group_hash: decode_hex("groupHash", &json.group_hash)?,
+            scheme: Scheme::parse(&json.scheme_id)?,
+            beacon_id: json.metadata.map(|m| m.beacon_id).unwrap_or_default(),
+        };
+        if info.compute_hash() != info.hash {
+            return Err("drand chain info does not match its chain hash".to_string());
+        }
+        Ok(info)
+    }
+
+    /// Recomputes the chain hash the way drand does.
+    ///
+    /// The scheme is not part of the hash; it is taken from the chain info.
+    pub fn compute_hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.period.to_be_bytes());
+        hasher.update(self.genesis_time.to_be_bytes());
+        hasher.update(&self.public_key);
+        hasher.update(&self.group_hash);
+        if !self.beacon_id.is_empty() && self.beacon_id != "default" {
+            hasher.update(self.beacon_id.as_bytes());
+        }
+        hasher.finalize().into()
+    }
+
+    /// Checks the BLS signature of `beacon` against the chain's group key.
+    pub fn verify(&self, beacon: &DrandBeacon) -> Result<(), String> {
+        let previous: &[u8] = if self.scheme.is_chained() {
+            if beacon.previous_signature.is_empty() {
+                return Err(format!(
+                    "drand round {} lacks the previous signature its chain requires",
+                    beacon.round
+                ));
+            }
+            &beacon.previous_signature
+        } else {
+            b""
+        };
+        let invalid_key = |e| format!("Invalid drand public key: {}", e);
+        let valid = match self.scheme {
+            Scheme::PedersenBlsChained | Scheme::PedersenBlsUnchained => {
+                G1Pubkey::from_variable(&self.public_key)
+                    .map_err(invalid_key)?
+                    .verify(beacon.round, previous, &beacon.signature)
+            }
+            Scheme::BlsUnchainedOnG1 => G2PubkeyFastnet::from_variable(&self.public_key)
+                .map_err(invalid_key)?
+                .verify(beacon.round, previous, &beacon.signature),
+            Scheme::BlsUnchainedG1Rfc9380 => G2PubkeyRfc::from_variable(&self.public_key)
+                .map_err(invalid_key)?
+                .verify(beacon.round, previous, &beacon.signature),
+        }
+        .map_err(|e| format!("drand round {}: {}", beacon.round, e))?;
+        if !valid {
+            return Err(format!(
+                "drand round {} has an invalid signature",
+                beacon.round
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// One drand round, as served by `GET /{chain_hash}/public/{round}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrandBeacon {
+    pub round: u64,
+    pub signature: Vec<u8>,
+    /// Empty for unchained schemes.
+    pub previous_signature: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct DrandBeaconJson {
+    round: u64,
+    randomness: String,
+    signature: String,
+    #[serde(default)]
+    previous_signature: String,
+}
+
+impl DrandBeacon {
+    /// Parses round JSON and checks its `randomness` against the signature.
+    pub fn from_json(raw: &str) -> Result<Self, String> {
+        let json: DrandBeaconJson =
+            serde_json::from_str(raw).map_err(|e| format!("Invalid drand round: {}", e))?;
+        let beacon = DrandBeacon {
+            round: json.round,
+            signature: decode_hex("signature", &json.signature)?,
+            previous_signature: decode_hex("previous_signature", &json.previous_signature)?,
+        };
+        if decode_hex("randomness", &json.randomness)? != beacon.randomness() {
+            return Err(format!(
+                "drand round {} randomness does not match its signature",
+                beacon.round
+            ));
+        }
+        Ok(beacon)
+    }
+
+    /// drand's randomness for the round: SHA-256 of the signature.
+    pub fn randomness(&self) -> [u8; 32] {
+        Sha256::digest(&self.signature).into()
+    }
+}
+
+/// Combines drand randomness with operator-local entropy.
+///
+/// With no local entropy the drand randomness is used as is; otherwise the
+/// output has the length of `local`.
+pub fn mix(randomness: &[u8; 32], local: &[u8]) -> Vec<u8> {
+    if local.is_empty() {
+        return randomness.to_vec();
+    }
+    let mut out = Vec::with_capacity(local.len());
+    let mut counter: u32 = 0;
+    while out.len() < local.len() {
+        let mut hasher = Sha256::new();
+        hasher.update(MIX_DOMAIN);
+        hasher.update(counter.to_be_bytes());
+        hasher.update(randomness);
+        hasher.update(local);
+        let block = hasher.finalize();
+        let take = (local.len() - out.len()).min(block.len());
+        out.extend_from_slice(&block[..take]);
+        counter += 1;
+    }
+    out
+}
+
+/// Verifies an attestation that re-wraps a drand round.
+///
+/// Checks that the recorded round belongs to `chain` and carries a valid
+/// group signature, then runs [`RngAttester::verify`], which confirms the
+/// random number was derived from that round.
+pub fn verify_attestation(
+    public_key: &VerifyingKey,
+    attestation: &Attestation,
+    chain: &ChainInfo,
+) -> Result<(), String> {
+    let record = attestation
+        .payload
+        .drand
+        .as_ref()
+        .ok_or_else(|| "Attestation does not carry a drand round".to_string())?;
+    if record.chain_hash != chain.hash {
+        return Err("Attestation wraps a round from a different drand chain".to_string());
+    }
+    chain.verify(&record.beacon)?;
+    RngAttester::verify(public_key, attestation)
+}
+
+/// Fetches and verifies rounds from a drand HTTP relay.
+pub struct DrandClient {
+    base_url: String,
+    chain_hash: [u8; 32],
+    agent: ureq::Agent,
+    resilience: Arc<Resilience>,
+    info: Mutex<Option<ChainInfo>>,
+}
+
+impl DrandClient {
+    /// Creates a client for the chain identified by `chain_hash` (hex) on `base_url`.
+    pub fn new(
+        base_url: &str,
+        chain_hash: &str,
+        resilience: Arc<Resilience>,
+    ) -> Result<Self, String> {
+        let chain_hash = decode_hex("chain_hash", chain_hash)?
+            .try_into()
+            .map_err(|_| "drand chain hash must be 32 bytes".to_string())?;
+        Ok(DrandClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            chain_hash,
+            agent: ureq::AgentBuilder::new().build(),
+            resilience,
+            info: Mutex::new(None),
+        })
+    }
+
+    /// Returns the pinned chain's info, fetching and checking it on first use.
+    pub fn chain_info(&self) -> Result<ChainInfo, String> {
+        let mut cached = self.info.lock().expect("drand info lock poisoned");
+        if let Some(info) = cached.as_ref() {
+            return Ok(info.clone());
+        }
+        let info = ChainInfo::from_json(&self.get("info")?)?;
+        if info.hash != self.chain_hash {
+            return Err("drand relay served info for a different chain".to_string());
+        }
+        *cached = Some(info.clone());
+        Ok(info)
+    }
+
+    /// Fetches `round` (or the latest round) and verifies its signature.
+    pub fn fetch(&self, round: Option<u64>) -> Result<DrandBeacon, String> {
+        let info = self.chain_info()?;
+        let path = match round {
+            Some(round) => format!("public/{}", round),
+            None => "public/latest".to_string(),
+        };
+        let beacon = DrandBeacon::from_json(&self.get(&path)?)?;
+        if let Some(round) = round {
+            if beacon.round != round {
+                return Err(format!(
+                    "drand relay returned round {} instead of {}",
+                    beacon.round, round
+                ));
+            }
+        }
+        info.verify(&beacon)?;
+        Ok(beacon)
+    }
+
+    /// Returns the pinned chain hash.
+    pub fn chain_hash(&self) -> [u8; 32] {
+        self.chain_hash
+    }
+
+    fn get(&self, path: &str) -> Result<String, String> {
+        let url = format!(
+            "{}/{}/{}",
+            self.base_url,
+            hex::encode(self.chain_hash),
+            path
+        );
+        self.resilience.call("drand", |remaining| {
+            match self.agent.get(&url).timeout(remaining).call() {
+                Ok(response) => response
+                    .into_string()
+                    .map_err(|e| CallError::Transient(e.to_string())),
+                Err(ureq::Error::Status(code, _)) if code < 500 && code != 429 => {
+                    Err(CallError::Permanent(format!("HTTP {}", code)))
+                }
+                Err(e) => Err(CallError::Transient(e.to_string())),
+            }
+        })
+    }
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, String> {
+    hex::decode(value).map_err(|e| format!("Invalid hex in drand field {}: {}", field, e))
+}
@@ -0,0 +1,264 @@
+// src/enclave.rs
+
+//! Attestation keys that only exist inside an enclave (`enclave` feature).
+//!
+//! In this mode the operator itself runs in the enclave: under Gramine on
+//! Intel SGX, or as the enclave image on AWS Nitro (reaching the network over
+//! vsock proxies). [`EnclaveKey`] generates the ed25519 key from the
+//! enclave's randomness and keeps it in enclave memory only; it is never
+//! written out, so a restart or rotation yields a new key. Alongside it the
+//! platform produces a quote whose report data is
+//! [`enclave_report_data`] of the public key: a DCAP quote through Gramine's
+//! `/dev/attestation`, or an attestation document from the Nitro Security
+//! Module. Every attestation signs the quote's digest and carries the quote,
+//! so a consumer who checks it with Intel's or AWS's verification service
+//! knows each value was signed inside the measured enclave.
+//!
+//! [`enclave_report_data`]: crate::attester::enclave_report_data
+
+use std::fs::OpenOptions;
+use std::os::unix::io::AsRawFd;
+use std::sync::Arc;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use sha2::Sha512;
+
+use crate::attester::{enclave_report_data, EnclaveQuote, SigningBackend, SoftwareKey};
+use crate::provenance;
+use crate::vrf::VrfProof;
+
+const NSM_DEVICE: &str = "/dev/nsm";
+/// `_IOWR(0x0A, 0, struct nsm_message)` of the Nitro Security Module driver.
+const NSM_IOCTL_REQUEST: u64 = 0xC020_0A00;
+/// Largest response the Nitro Security Module returns.
+const NSM_RESPONSE_MAX: usize = 0x3000;
+
+/// Enclave technology the operator runs in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Sgx,
+    Nitro,
+}
+
+impl Platform {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "sgx" => Ok(Platform::Sgx),
+            "nitro" => Ok(Platform::Nitro),
+            other => Err(format!("Unknown enclave platform {}", other)),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Platform::Sgx => "sgx",
+            Platform::Nitro => "nitro",
+        }
+    }
+}
+
+/// An attestation key generated in the enclave, with the quote vouching for it.
+pub struct EnclaveKey {
+    platform: Platform,
+    key: SoftwareKey,
+    quote: EnclaveQuote,
+}
+
+impl EnclaveKey {
+    /// Generates a key and has the platform quote its public key.
+    pub fn generate(platform: Platform) -> Result<Self, String> {
+        let key = SoftwareKey::generate();
+        let data = enclave_report_data(&key.verifying_key());
+        let quote = match platform {
+            Platform::Sgx => provenance::sgx_quote(&data)?,
+            Platform::Nitro => nitro_document(&data)?,
+        };
+        Ok(EnclaveKey {
+            platform,
+            key,
+            quote: EnclaveQuote {
+                platform: platform.as_str().to_string(),
+                quote,
+            },
+        })
+    }
+}
+
+impl SigningBackend for EnclaveKey {
+    fn verifying_key(&self) -> VerifyingKey {
+        self.key.verifying_key()
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Signature, String> {
+        self.key.sign(message)
+    }
+
+    fn sign_prehashed(&self, prehash: Sha512, context: &[u8]) -> Result<Signature, String> {
+        self.key.sign_prehashed(prehash, context)
+    }
+
+    fn vrf_prove(&self, input: &[u8]) -> (VrfProof, [u8; 64]) {
+        self.key.vrf_prove(input)
+    }
+
+    fn rotated(&self) -> Result<Arc<dyn SigningBackend>, String> {
+        Ok(Arc::new(EnclaveKey::generate(self.platform)?))
+    }
+
+    fn quote(&self) -> Option<&EnclaveQuote> {
+        Some(&self.quote)
+    }
+}
+
+/// `struct nsm_message` of the driver: request and response buffers.
+#[repr(C)]
+struct NsmMessage {
+    request: libc::iovec,
+    response: libc::iovec,
+}
+
+/// Requests an attestation document carrying `user_data` from the Nitro
+/// Security Module.
+fn nitro_document(user_data: &[u8; 64]) -> Result<Vec<u8>, String> {
+    let nsm = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(NSM_DEVICE)
+        .map_err(|e| format!("Failed to open {}: {}", NSM_DEVICE, e))?;
+    // {"Attestation": {"user_data": h'..', "nonce": null, "public_key": null}}
+    let mut request = Vec::new();
+    cbor_header(&mut request, 5, 1);
+    cbor_text(&mut request, "Attestation");
+    cbor_header(&mut request, 5, 3);
+    cbor_text(&mut request, "user_data");
+    cbor_header(&mut request, 2, user_data.len() as u64);
+    request.extend_from_slice(user_data);
+    for field in ["nonce", "public_key"] {
+        cbor_text(&mut request, field);
+        request.push(0xf6);
+    }
+    let mut response = vec![0u8; NSM_RESPONSE_MAX];
+    let mut message = NsmMessage {
+        request: libc::iovec {
+            iov_base: request.as_mut_ptr().cast(),
+            iov_len: request.len(),
+        },
+        response: libc::iovec {
+            iov_base: response.as_mut_ptr().cast(),
+            iov_len: response.len(),
+        },
+    };
+    // SAFETY: both buffers outlive the call and their lengths are correct;
+    // the driver shrinks `response.iov_len` to what it wrote.
+    let rc = unsafe { libc::ioctl(nsm.as_raw_fd(), NSM_IOCTL_REQUEST as _, &mut message) };
+    if rc < 0 {
+        return Err(format!(
+            "Nitro attestation request failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    response.truncate(message.response.iov_len);
+    parse_document(&response)
+}
+
+/// Extracts the document from `{"Attestation": {"document": ..}}`, or the
+/// message of `{"Error": ..}`.
+fn parse_document(response: &[u8]) -> Result<Vec<u8>, String> {
+    let mut cbor = Cbor {
+        data: response,
+        pos: 0,
+    };
+    let malformed = || "Malformed Nitro attestation response".to_string();
+    if cbor.header()? != (5, 1) {
+        return Err(malformed());
+    }
+    match cbor.text()?.as_str() {
+        "Attestation" => {
+            if cbor.header()? != (5, 1) || cbor.text()? != "document" {
+                return Err(malformed());
+            }
+            cbor.bytes()
+        }
+        "Error" => Err(format!(
+            "Nitro attestation request refused: {}",
+            cbor.text()?
+        )),
+        _ => Err(malformed()),
+    }
+}
+
+fn cbor_header(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => out.push(major | value as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, value as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 26);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+    }
+}
+
+fn cbor_text(out: &mut Vec<u8>, text: &str) {
+    cbor_header(out, 3, text.len() as u64);
+    out.extend_from_slice(text.as_bytes());
+}
+
+/// Just enough of a CBOR reader for NSM responses.
+struct Cbor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Cbor<'_> {
+    /// Reads an item header: major type and argument.
+    fn header(&mut self) -> Result<(u8, u64), String> {
+        let initial = self.take(1)?[0];
+        let value = match initial & 0x1f {
+            n @ 0..=23 => u64::from(n),
+            24 => u64::from(self.take(1)?[0]),
+            25 => u64::from(u16::from_be_bytes(self.take(2)?.try_into().unwrap())),
+            26 => u64::from(u32::from_be_bytes(self.take(4)?.try_into().unwrap())),
+            27 => u64::from_be_bytes(self.take(8)?.try_into().unwrap()),
+            _ => return Err("Unsupported CBOR item in Nitro response".to_string()),
+        };
+        Ok((initial >> 5, value))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or("Truncated Nitro attestation response")?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn text(&mut self) -> Result<String, String> {
+        match self.header()? {
+            (3, len) => String::from_utf8(self.take(len as usize)?.to_vec())
+                .map_err(|_| "Invalid text in Nitro response".to_string()),
+            _ => Err("Expected text in Nitro response".to_string()),
+        }
+    }
+
+    /// A byte string, or an array of small integers as some encoders emit.
+    fn bytes(&mut self) -> Result<Vec<u8>, String> {
+        match self.header()? {
+            (2, len) => Ok(self.take(len as usize)?.to_vec()),
+            (4, len) => (0..len)
+                .map(|_| match self.header()? {
+                    (0, byte) if byte <= 0xff => Ok(byte as u8),
+                    _ => Err("Expected bytes in Nitro response".to_string()),
+                })
+                .collect(),
+            _ => Err("Expected bytes in Nitro response".to_string()),
+        }
+    }
+}
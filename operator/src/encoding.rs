@@ -0,0 +1,241 @@
+// src/encoding.rs
+
+//! Text encodings for byte fields of task outcomes.
+//!
+//! Outcomes are stored and signed over raw bytes, with hex as the canonical
+//! text form. A response can instead carry its randomness, salt, signatures
+//! and keys as standard base64 (padded), base58 (Bitcoin alphabet) or bech32
+//! (BIP-173 checksum under [`BECH32_HRP`], without BIP-173's 90-character
+//! limit, since signatures do not fit in it). Decoding is strict: base64 must
+//! be canonically padded with zero trailing bits, base58 may only use its
+//! alphabet, and bech32 must be single-case, carry the expected prefix and
+//! checksum and have zero padding bits.
+
+use std::fmt;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// Human-readable prefix of bech32 output.
+pub const BECH32_HRP: &str = "rng";
+
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Text encoding of a byte field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    #[default]
+    Hex,
+    Base64,
+    Base58,
+    Bech32,
+}
+
+impl Encoding {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "hex" => Ok(Encoding::Hex),
+            "base64" => Ok(Encoding::Base64),
+            "base58" => Ok(Encoding::Base58),
+            "bech32" => Ok(Encoding::Bech32),
+            other => Err(format!(
+                "Unknown encoding '{}' (expected hex, base64, base58 or bech32)",
+                other
+            )),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Hex => "hex",
+            Encoding::Base64 => "base64",
+            Encoding::Base58 => "base58",
+            Encoding::Bech32 => "bech32",
+        }
+    }
+
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Hex => hex::encode(bytes),
+            Encoding::Base64 => STANDARD.encode(bytes),
+            Encoding::Base58 => base58_encode(bytes),
+            Encoding::Bech32 => bech32_encode(bytes),
+        }
+    }
+
+    pub fn decode(self, text: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Encoding::Hex => hex::decode(text).map_err(|e| e.to_string()),
+            Encoding::Base64 => STANDARD.decode(text).map_err(|e| e.to_string()),
+            Encoding::Base58 => base58_decode(text),
+            Encoding::Bech32 => bech32_decode(text),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn base58_encode(bytes: &[u8]) -> String {
+    let zeros = bytes.iter().take_while(|&&b| b == 0).count();
+    // Base-58 digits, least significant first.
+    let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 138 / 100 + 1);
+    for &byte in &bytes[zeros..] {
+        let mut carry = u32::from(byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut text = "1".repeat(zeros);
+    text.extend(
+        digits
+            .iter()
+            .rev()
+            .map(|&d| BASE58_ALPHABET[d as usize] as char),
+    );
+    text
+}
+
+fn base58_decode(text: &str) -> Result<Vec<u8>, String> {
+    let zeros = text.bytes().take_while(|&c| c == b'1').count();
+    // Bytes, least significant first.
+    let mut bytes: Vec<u8> = Vec::with_capacity(text.len());
+    for c in text.bytes().skip(zeros) {
+        let mut carry = BASE58_ALPHABET
+            .iter()
+            .position(|&a| a == c)
+            .ok_or_else(|| format!("Invalid base58 character {:?}", c as char))?
+            as u32;
+        for byte in bytes.iter_mut() {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let mut decoded = vec![0u8; zeros];
+    decoded.extend(bytes.iter().rev());
+    Ok(decoded)
+}
+
+fn bech32_polymod(values: impl IntoIterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+    let mut checksum = 1u32;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x01ff_ffff) << 5) ^ u32::from(value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// The prefix expanded as BIP-173 feeds it to the checksum.
+fn bech32_hrp_values() -> Vec<u8> {
+    let hrp = BECH32_HRP.as_bytes();
+    let mut values: Vec<u8> = hrp.iter().map(|c| c >> 5).collect();
+    values.push(0);
+    values.extend(hrp.iter().map(|c| c & 0x1f));
+    values
+}
+
+fn bech32_encode(bytes: &[u8]) -> String {
+    let mut data = Vec::with_capacity(bytes.len() * 8 / 5 + 7);
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for &byte in bytes {
+        acc = ((acc << 8) | u32::from(byte)) & 0xfff;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            data.push(((acc >> bits) & 0x1f) as u8);
+        }
+    }
+    if bits > 0 {
+        data.push(((acc << (5 - bits)) & 0x1f) as u8);
+    }
+    let values = bech32_hrp_values()
+        .into_iter()
+        .chain(data.iter().copied())
+        .chain([0; 6]);
+    let checksum = bech32_polymod(values) ^ 1;
+    data.extend((0..6).map(|i| ((checksum >> (5 * (5 - i))) & 0x1f) as u8));
+
+    let mut text = format!("{}1", BECH32_HRP);
+    text.extend(data.iter().map(|&d| BECH32_CHARSET[d as usize] as char));
+    text
+}
+
+fn bech32_decode(text: &str) -> Result<Vec<u8>, String> {
+    if text.bytes().any(|c| c.is_ascii_lowercase()) && text.bytes().any(|c| c.is_ascii_uppercase())
+    {
+        return Err("Mixed-case bech32".to_string());
+    }
+    let text = text.to_ascii_lowercase();
+    let (hrp, data) = text.rsplit_once('1').ok_or("Missing bech32 separator")?;
+    if hrp != BECH32_HRP {
+        return Err(format!(
+            "Expected bech32 prefix '{}', found '{}'",
+            BECH32_HRP, hrp
+        ));
+    }
+    if data.len() < 6 {
+        return Err("Bech32 data is shorter than its checksum".to_string());
+    }
+    let values = data
+        .bytes()
+        .map(|c| {
+            BECH32_CHARSET
+                .iter()
+                .position(|&a| a == c)
+                .map(|v| v as u8)
+                .ok_or_else(|| format!("Invalid bech32 character {:?}", c as char))
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    if bech32_polymod(
+        bech32_hrp_values()
+            .into_iter()
+            .chain(values.iter().copied()),
+    ) != 1
+    {
+        return Err("Invalid bech32 checksum".to_string());
+    }
+
+    let mut bytes = Vec::with_capacity(values.len() * 5 / 8);
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for &value in &values[..values.len() - 6] {
+        acc = ((acc << 5) | u32::from(value)) & 0xfff;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+        }
+    }
+    if bits >= 5 || acc & ((1 << bits) - 1) != 0 {
+        return Err("Invalid bech32 padding".to_string());
+    }
+    Ok(bytes)
+}
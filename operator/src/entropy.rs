@@ -0,0 +1,279 @@
+// src/entropy.rs
+
+//! Operator entropy drawn from several sources at once.
+//!
+//! Every source listed under `entropy.sources` is read on a thread of its
+//! own, and whatever has answered within `entropy.timeout` is mixed into the
+//! output; a source that hangs (a blocking hardware RNG, an unreachable
+//! service) is left behind instead of stalling the task, and is not asked
+//! again until its outstanding read returns. A value is only produced once
+//! at least `entropy.min_sources` sources contributed, and the names of those
+//! that did are signed into its attestation as the `entropy_sources` of its
+//! [`OperatorMetadata`].
+//!
+//! The output is SHA-256 in counter mode over every contribution, so it is
+//! unpredictable as long as one contributing source is. Settings are read
+//! from the live config on each draw, so a reload takes effect immediately.
+//!
+//! [`OperatorMetadata`]: crate::attester::OperatorMetadata
+
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::Read;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::warn;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::config::{self, ConfigHandle, EntropyConfig};
+use crate::metrics::Metrics;
+
+/// Domain separation tag of the mix.
+pub const MIX_DOMAIN: &[u8] = b"othentic-rng/entropy-mix/v1";
+
+/// Names of the sources `entropy.sources` may list.
+pub const SOURCES: [&str; 4] = ["os", "hardware", "atmospheric", "blockchain"];
+
+/// Entropy mixed from the sources that answered in time.
+#[derive(Debug, Clone)]
+pub struct Gathered {
+    pub bytes: Vec<u8>,
+    /// Contributing sources, in configured order.
+    pub sources: Vec<String>,
+}
+
+/// Races the configured entropy sources for every value.
+pub struct EntropyMixer {
+    config: Arc<ConfigHandle>,
+    metrics: Arc<Metrics>,
+    agent: ureq::Agent,
+    /// Sources with a read still outstanding.
+    busy: Arc<Mutex<BTreeSet<String>>>,
+}
+
+impl EntropyMixer {
+    pub fn new(config: Arc<ConfigHandle>, metrics: Arc<Metrics>) -> Self {
+        EntropyMixer {
+            config,
+            metrics,
+            agent: ureq::AgentBuilder::new().build(),
+            busy: Arc::new(Mutex::new(BTreeSet::new())),
+        }
+    }
+
+    /// Draws `length` bytes from the sources configured now; OS entropy
+    /// alone when none are.
+    pub fn gather(&self, length: usize) -> Result<Gathered, String> {
+        if length == 0 {
+            return Err("Length must be a positive integer.".to_string());
+        }
+        let settings = self.config.current();
+        let entropy = &settings.entropy;
+        let names = if entropy.sources.is_empty() {
+            vec!["os".to_string()]
+        } else {
+            entropy.sources.clone()
+        };
+        let timeout = config::parse_duration(&entropy.timeout)?;
+        let deadline = Instant::now() + timeout;
+
+        let (sender, answers) = mpsc::channel();
+        let mut pending = BTreeSet::new();
+        for (index, name) in names.iter().enumerate() {
+            if !self
+                .busy
+                .lock()
+                .expect("entropy lock poisoned")
+                .insert(name.clone())
+            {
+                self.count(name, "busy");
+                continue;
+            }
+            let request = SourceRequest {
+                name: name.clone(),
+                length,
+                entropy: entropy.clone(),
+                rpc_url: settings.network.rpc_url.clone(),
+                agent: self.agent.clone(),
+                timeout,
+            };
+            let busy = Arc::clone(&self.busy);
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let result = request.read();
+                busy.lock()
+                    .expect("entropy lock poisoned")
+                    .remove(&request.name);
+                // The draw may have moved on without this source.
+                let _ = sender.send((index, result));
+            });
+            pending.insert(index);
+        }
+
+        let mut contributions = Vec::new();
+        while !pending.is_empty() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Ok((index, result)) = answers.recv_timeout(remaining) else {
+                break;
+            };
+            pending.remove(&index);
+            let name = &names[index];
+            match result {
+                Ok(bytes) => {
+                    self.count(name, "ok");
+                    contributions.push((index, bytes));
+                }
+                Err(e) => {
+                    self.count(name, "error");
+                    warn!("Entropy source {} failed: {}", name, e);
+                }
+            }
+        }
+        for index in pending {
+            self.count(&names[index], "timeout");
+            warn!(
+                "Entropy source {} did not answer within {:?}",
+                names[index], timeout
+            );
+        }
+
+        if contributions.len() < entropy.min_sources {
+            return Err(format!(
+                "Only {} of {} entropy source(s) answered; {} required",
+                contributions.len(),
+                names.len(),
+                entropy.min_sources
+            ));
+        }
+        contributions.sort_by_key(|(index, _)| *index);
+        let sources: Vec<String> = contributions
+            .iter()
+            .map(|(index, _)| names[*index].clone())
+            .collect();
+        let bytes = mix(
+            length,
+            contributions
+                .iter()
+                .map(|(index, bytes)| (names[*index].as_str(), bytes.as_slice())),
+        );
+        Ok(Gathered { bytes, sources })
+    }
+
+    fn count(&self, source: &str, outcome: &str) {
+        self.metrics.inc_counter(
+            "rng_entropy_reads_total",
+            &[("source", source), ("outcome", outcome)],
+            1,
+        );
+    }
+}
+
+/// Mixes `contributions` into `length` bytes.
+pub fn mix<'a>(length: usize, contributions: impl Iterator<Item = (&'a str, &'a [u8])>) -> Vec<u8> {
+    let mut prefix = Sha256::new();
+    prefix.update(MIX_DOMAIN);
+    prefix.update((length as u64).to_be_bytes());
+    for (name, bytes) in contributions {
+        prefix.update((name.len() as u64).to_be_bytes());
+        prefix.update(name.as_bytes());
+        prefix.update((bytes.len() as u64).to_be_bytes());
+        prefix.update(bytes);
+    }
+    let mut output = Vec::with_capacity(length);
+    let mut counter: u32 = 0;
+    while output.len() < length {
+        let block = prefix
+            .clone()
+            .chain_update(counter.to_be_bytes())
+            .finalize();
+        let take = (length - output.len()).min(block.len());
+        output.extend_from_slice(&block[..take]);
+        counter += 1;
+    }
+    output
+}
+
+/// One read of one source, moved onto its thread.
+struct SourceRequest {
+    name: String,
+    length: usize,
+    entropy: EntropyConfig,
+    rpc_url: String,
+    agent: ureq::Agent,
+    timeout: Duration,
+}
+
+impl SourceRequest {
+    fn read(&self) -> Result<Vec<u8>, String> {
+        match self.name.as_str() {
+            "os" => {
+                let mut bytes = vec![0u8; self.length];
+                OsRng
+                    .try_fill_bytes(&mut bytes)
+                    .map_err(|e| e.to_string())?;
+                Ok(bytes)
+            }
+            "hardware" => {
+                let mut bytes = vec![0u8; self.length];
+                File::open(&self.entropy.device)
+                    .and_then(|mut device| device.read_exact(&mut bytes))
+                    .map_err(|e| format!("{}: {}", self.entropy.device, e))?;
+                Ok(bytes)
+            }
+            "atmospheric" => {
+                let url = self
+                    .entropy
+                    .atmospheric_url
+                    .replace("{n}", &self.length.to_string());
+                let response = self
+                    .agent
+                    .get(&url)
+                    .timeout(self.timeout)
+                    .call()
+                    .map_err(|e| e.to_string())?;
+                let mut bytes = Vec::with_capacity(self.length);
+                response
+                    .into_reader()
+                    .take(self.length as u64)
+                    .read_to_end(&mut bytes)
+                    .map_err(|e| e.to_string())?;
+                if bytes.len() != self.length {
+                    return Err(format!("served {} of {} bytes", bytes.len(), self.length));
+                }
+                Ok(bytes)
+            }
+            // Public and only as unpredictable as the next block, so it
+            // strengthens the mix without ever being enough on its own.
+            "blockchain" => {
+                let body = json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "eth_getBlockByNumber",
+                    "params": ["latest", false],
+                });
+                let text = self
+                    .agent
+                    .post(&self.rpc_url)
+                    .timeout(self.timeout)
+                    .set("Content-Type", "application/json")
+                    .send_string(&body.to_string())
+                    .map_err(|e| e.to_string())?
+                    .into_string()
+                    .map_err(|e| e.to_string())?;
+                let response: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+                let hash = response
+                    .pointer("/result/hash")
+                    .and_then(Value::as_str)
+                    .ok_or("eth_getBlockByNumber returned no block hash")?;
+                hex::decode(hash.trim_start_matches("0x")).map_err(|e| e.to_string())
+            }
+            other => Err(format!("Unknown entropy source {}", other)),
+        }
+    }
+}
@@ -0,0 +1,402 @@
+// src/epochs.rs
+
+//! Forward-secure attestation keys, one per epoch.
+//!
+//! With `epochs` enabled, time is cut into epochs of `epochs.length` counted
+//! from when the ratchet was created, and each epoch signs with its own
+//! ed25519 key. Keys come from a hash ratchet: the state of epoch `e + 1` is a
+//! one-way hash of the state of epoch `e`, and entering an epoch rewrites the
+//! state file, erasing every earlier key. Whoever steals today's key or state
+//! can therefore derive today's and later keys, but not those of past epochs.
+//!
+//! A separate master key, whose public half consumers pin, signs an
+//! [`EpochCertificate`] for each epoch key as the epoch begins, binding it to
+//! the epoch number and its time window. Attestations carry their epoch
+//! (signed) and its certificate, so a verifier can follow the chain back to
+//! the master and reject a stolen key passing off attestations as older
+//! ones. The ratchet is seeded at random, not from the master, so the master
+//! key alone does not reveal any epoch key either.
+//!
+//! The master key stays online to certify each epoch as it begins, so a
+//! thief holding it could certify a key of their own for a past epoch too.
+//! The operator never certifies a second key for an epoch, and a verifier
+//! must pin the first certificate it sees for each epoch (as served by
+//! `GET /epochs`) and reject any other one under the same master. Both key
+//! files are written readable by their owner only.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use log::info;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::{self, EpochConfig};
+use crate::storage::Storage;
+use crate::tasks::unix_millis;
+
+/// Domain tag of the master signature on a certificate.
+pub const CERTIFICATE_DOMAIN: &[u8] = b"othentic-rng/epoch-certificate/v1";
+/// Collection holding every certificate issued, keyed by zero-padded epoch.
+pub const EPOCH_CERTIFICATES: &str = "epoch_certificates";
+
+const RATCHET_DOMAIN: &[u8] = b"othentic-rng/epoch-ratchet/v1";
+const KEY_DOMAIN: &[u8] = b"othentic-rng/epoch-key/v1";
+
+/// The master key's statement that `public_key` signs for `epoch`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochCertificate {
+    pub epoch: u64,
+    /// Hex ed25519 key of the epoch.
+    pub public_key: String,
+    /// Unix ms the epoch starts at.
+    pub not_before: u64,
+    /// Unix ms the next epoch starts at.
+    pub not_after: u64,
+    pub master_public_key: String,
+    pub signature: String,
+}
+
+impl EpochCertificate {
+    fn issue(master: &SigningKey, epoch: u64, key: &VerifyingKey, window: (u64, u64)) -> Self {
+        let mut certificate = EpochCertificate {
+            epoch,
+            public_key: hex::encode(key.as_bytes()),
+            not_before: window.0,
+            not_after: window.1,
+            master_public_key: hex::encode(master.verifying_key().as_bytes()),
+            signature: String::new(),
+        };
+        let signature = master.sign(&certificate.signed_bytes(key, &master.verifying_key()));
+        certificate.signature = hex::encode(signature.to_bytes());
+        certificate
+    }
+
+    /// Checks the master signature; returns the master and epoch keys.
+    pub fn verify(&self) -> Result<(VerifyingKey, VerifyingKey), String> {
+        let master = parse_key(&self.master_public_key)?;
+        let key = parse_key(&self.public_key)?;
+        let signature: [u8; 64] = hex::decode(&self.signature)
+            .map_err(|e| format!("Invalid certificate signature: {}", e))?
+            .try_into()
+            .map_err(|_| "Certificate signature must be 64 bytes".to_string())?;
+        master
+            .verify(
+                &self.signed_bytes(&key, &master),
+                &Signature::from_bytes(&signature),
+            )
+            .map_err(|e| format!("Epoch certificate verification failed: {}", e))?;
+        Ok((master, key))
+    }
+
+    /// Whether Unix ms `at` falls in the epoch, give or take `skew` ms.
+    pub fn covers(&self, at: u64, skew: u64) -> bool {
+        at.saturating_add(skew) >= self.not_before && at < self.not_after.saturating_add(skew)
+    }
+
+    fn signed_bytes(&self, key: &VerifyingKey, master: &VerifyingKey) -> Vec<u8> {
+        let mut data = CERTIFICATE_DOMAIN.to_vec();
+        data.extend_from_slice(&self.epoch.to_be_bytes());
+        data.extend_from_slice(key.as_bytes());
+        data.extend_from_slice(&self.not_before.to_be_bytes());
+        data.extend_from_slice(&self.not_after.to_be_bytes());
+        data.extend_from_slice(master.as_bytes());
+        data
+    }
+}
+
+/// Contents of the state file.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RatchetState {
+    /// Unix ms epoch 0 started at.
+    genesis: u64,
+    /// Epoch length in ms; fixed for the life of the ratchet.
+    length: u64,
+    epoch: u64,
+    /// Hex ratchet state of `epoch`.
+    chain_key: String,
+}
+
+struct Position {
+    epoch: u64,
+    chain_key: [u8; 32],
+}
+
+/// The ratchet and master key of one operator.
+pub struct EpochKeys {
+    master: SigningKey,
+    state_file: PathBuf,
+    genesis: u64,
+    length: u64,
+    storage: Arc<dyn Storage>,
+    position: Mutex<Position>,
+}
+
+impl EpochKeys {
+    /// Opens the master key and ratchet state, creating either if missing.
+    pub fn open(config: &EpochConfig, storage: Arc<dyn Storage>) -> Result<Self, String> {
+        let length = config::parse_duration(&config.length)?.as_millis() as u64;
+        let master = load_or_create_master(Path::new(&config.master_key_file))?;
+        let state_file = PathBuf::from(&config.state_file);
+        let state = match std::fs::read_to_string(&state_file) {
+            Ok(raw) => serde_json::from_str::<RatchetState>(&raw)
+                .map_err(|e| format!("Invalid epoch state in {}: {}", state_file.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut chain_key = [0u8; 32];
+                OsRng.fill_bytes(&mut chain_key);
+                let state = RatchetState {
+                    genesis: unix_millis(),
+                    length,
+                    epoch: 0,
+                    chain_key: hex::encode(chain_key),
+                };
+                write_state(&state_file, &state)?;
+                info!("Started epoch ratchet {}", state_file.display());
+                state
+            }
+            Err(e) => {
+                return Err(format!(
+                    "Failed to read epoch state {}: {}",
+                    state_file.display(),
+                    e
+                ))
+            }
+        };
+        if state.length != length {
+            return Err(format!(
+                "epochs.length differs from the {} ms the ratchet in {} was started with",
+                state.length,
+                state_file.display()
+            ));
+        }
+        let chain_key = hex::decode(&state.chain_key)
+            .ok()
+            .and_then(|key| <[u8; 32]>::try_from(key).ok())
+            .ok_or_else(|| format!("Invalid chain key in {}", state_file.display()))?;
+        Ok(EpochKeys {
+            master,
+            state_file,
+            genesis: state.genesis,
+            length,
+            storage,
+            position: Mutex::new(Position {
+                epoch: state.epoch,
+                chain_key,
+            }),
+        })
+    }
+
+    pub fn master_public_key(&self) -> VerifyingKey {
+        self.master.verifying_key()
+    }
+
+    /// The epoch Unix ms `at` falls in.
+    pub fn epoch_at(&self, at: u64) -> u64 {
+        at.saturating_sub(self.genesis) / self.length
+    }
+
+    /// Ratchets forward to `epoch`, erasing the keys of earlier epochs, and
+    /// returns its signing key and certificate.
+    pub fn enter(&self, epoch: u64) -> Result<(SigningKey, EpochCertificate), String> {
+        let mut position = self.position.lock().expect("epoch lock poisoned");
+        if epoch < position.epoch {
+            return Err(format!("Epoch {} has ended and its key was erased", epoch));
+        }
+        if epoch > position.epoch {
+            let mut chain_key = position.chain_key;
+            for _ in position.epoch..epoch {
+                chain_key = Sha256::new()
+                    .chain_update(RATCHET_DOMAIN)
+                    .chain_update(chain_key)
+                    .finalize()
+                    .into();
+            }
+            // Persist first: once the file moves on, earlier keys are gone.
+            write_state(
+                &self.state_file,
+                &RatchetState {
+                    genesis: self.genesis,
+                    length: self.length,
+                    epoch,
+                    chain_key: hex::encode(chain_key),
+                },
+            )?;
+            *position = Position { epoch, chain_key };
+        }
+        let seed: [u8; 32] = Sha256::new()
+            .chain_update(KEY_DOMAIN)
+            .chain_update(position.chain_key)
+            .finalize()
+            .into();
+        let key = SigningKey::from_bytes(&seed);
+        let certificate = self.certificate(epoch, &key.verifying_key())?;
+        Ok((key, certificate))
+    }
+
+    /// Every certificate issued so far, oldest first.
+    pub fn certificates(&self) -> Result<Vec<EpochCertificate>, String> {
+        self.storage
+            .scan(EPOCH_CERTIFICATES)?
+            .into_iter()
+            .map(|(key, value)| {
+                serde_json::from_value(value)
+                    .map_err(|e| format!("Corrupt epoch certificate {}: {}", key, e))
+            })
+            .collect()
+    }
+
+    /// The stored certificate of `epoch`, issued now if there is none yet.
+    /// An epoch certified for another key is an error, never reissued.
+    fn certificate(&self, epoch: u64, key: &VerifyingKey) -> Result<EpochCertificate, String> {
+        let id = format!("{:012}", epoch);
+        if let Some(stored) = self.storage.get(EPOCH_CERTIFICATES, &id)? {
+            let certificate: EpochCertificate = serde_json::from_value(stored)
+                .map_err(|e| format!("Corrupt epoch certificate {}: {}", id, e))?;
+            if certificate.public_key != hex::encode(key.as_bytes()) {
+                return Err(format!(
+                    "Epoch {} is already certified for key {}, not {}; refusing to certify \
+                     a second key",
+                    epoch,
+                    certificate.public_key,
+                    hex::encode(key.as_bytes())
+                ));
+            }
+            return Ok(certificate);
+        }
+        let start = self.genesis + epoch * self.length;
+        let certificate =
+            EpochCertificate::issue(&self.master, epoch, key, (start, start + self.length));
+        let value = serde_json::to_value(&certificate).map_err(|e| e.to_string())?;
+        self.storage.put(EPOCH_CERTIFICATES, &id, value)?;
+        self.storage.flush()?;
+        info!(
+            "Entered epoch {} with key {}",
+            epoch, certificate.public_key
+        );
+        Ok(certificate)
+    }
+}
+
+fn parse_key(value: &str) -> Result<VerifyingKey, String> {
+    let bytes: [u8; 32] = hex::decode(value)
+        .map_err(|e| format!("Invalid public key {}: {}", value, e))?
+        .try_into()
+        .map_err(|_| format!("Public key {} must be 32 bytes", value))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("Invalid public key {}: {}", value, e))
+}
+
+/// Replaces the state file in one rename, so a crash leaves the old or the
+/// new state but never a torn one.
+fn write_state(path: &Path, state: &RatchetState) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    let raw = serde_json::to_string(state).map_err(|e| e.to_string())?;
+    write_private(&tmp, raw.as_bytes())
+        .and_then(|()| std::fs::rename(&tmp, path))
+        .map_err(|e| format!("Failed to write epoch state {}: {}", path.display(), e))
+}
+
+fn load_or_create_master(path: &Path) -> Result<SigningKey, String> {
+    match std::fs::read_to_string(path) {
+        Ok(raw) => hex::decode(raw.trim())
+            .ok()
+            .and_then(|key| <[u8; 32]>::try_from(key).ok())
+            .map(|key| SigningKey::from_bytes(&key))
+            .ok_or_else(|| format!("Invalid epoch master key in {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let key = SigningKey::generate(&mut OsRng);
+            let contents = format!("{}\n", hex::encode(key.to_bytes()));
+            write_private(path, contents.as_bytes()).map_err(|e| {
+                format!("Failed to write epoch master key {}: {}", path.display(), e)
+            })?;
+            info!(
+                "Generated epoch master key {} with public key {}",
+                path.display(),
+                hex::encode(key.verifying_key().as_bytes())
+            );
+            Ok(key)
+        }
+        Err(e) => Err(format!(
+            "Failed to read epoch master key {}: {}",
+            path.display(),
+            e
+        )),
+    }
+}
+
+/// Writes a new file only its owner may read (mode 0600 on Unix).
+pub(crate) fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn open(dir: &Path, storage: Arc<dyn Storage>) -> EpochKeys {
+        let config = EpochConfig {
+            enabled: true,
+            length: "1h".to_string(),
+            master_key_file: dir.join("master.key").display().to_string(),
+            state_file: dir.join("epoch.state").display().to_string(),
+        };
+        EpochKeys::open(&config, storage).unwrap()
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("epochs-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn certificates_verify_and_are_not_reissued() {
+        let dir = scratch("reissue");
+        let storage: Arc<dyn Storage> = Arc::new(MemoryStorage::new());
+        let keys = open(&dir, Arc::clone(&storage));
+        let (key, certificate) = keys.enter(1).unwrap();
+        let (master, certified) = certificate.verify().unwrap();
+        assert_eq!(master, keys.master_public_key());
+        assert_eq!(certified, key.verifying_key());
+        assert_eq!(keys.enter(1).unwrap().1, certificate);
+        assert!(keys.enter(0).is_err());
+
+        // A fresh ratchet under the same master must not re-certify epoch 1.
+        std::fs::remove_file(dir.join("epoch.state")).unwrap();
+        let restarted = open(&dir, storage);
+        assert!(restarted.enter(1).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn key_files_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = scratch("modes");
+        let keys = open(&dir, Arc::new(MemoryStorage::new()));
+        keys.enter(2).unwrap();
+        for file in ["master.key", "epoch.state"] {
+            let mode = std::fs::metadata(dir.join(file))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600, "{}", file);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,296 @@
+// src/export.rs
+
+//! Audit export of stored attestations and task lifecycle events.
+//!
+//! Both formats share one flat schema, [`COLUMNS`], with one row per record:
+//! `record` is `"event"` for an entry of the task lifecycle log and
+//! `"attestation"` for the outcome of a completed task. Columns that do not
+//! apply to a record are null in JSONL and empty in CSV. Attestation fields
+//! beyond the core signature (client entropy, VDF, drand, VRF, validity
+//! window) are carried as one JSON object in `extensions`. Rows are ordered by
+//! `at`.
+
+use std::io::Write;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::storage::Storage;
+use crate::tasks::{TaskOutcome, ATTESTATIONS, TASK_EVENTS};
+use crate::tenants;
+
+/// Column order of the export schema.
+pub const COLUMNS: [&str; 10] = [
+    "record",
+    "at",
+    "task_id",
+    "stage",
+    "detail",
+    "random_number",
+    "salt",
+    "signature",
+    "public_key",
+    "extensions",
+];
+
+/// Output format of an export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Jsonl,
+    Csv,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "jsonl" => Ok(Format::Jsonl),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!(
+                "Unknown export format '{}' (expected jsonl or csv)",
+                value
+            )),
+        }
+    }
+}
+
+/// One exported row.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportRecord {
+    /// Storage key the row was read from; a stream chunk's is
+    /// `{task_id}#{index}`. Not exported.
+    #[serde(skip)]
+    pub key: String,
+    pub record: &'static str,
+    /// Unix time in milliseconds: event time, or completion time of an attestation.
+    pub at: u64,
+    pub task_id: String,
+    pub stage: Option<String>,
+    pub detail: Option<String>,
+    pub random_number: Option<String>,
+    pub salt: Option<String>,
+    pub signature: Option<String>,
+    pub public_key: Option<String>,
+    pub extensions: Option<Value>,
+}
+
+impl ExportRecord {
+    fn fields(&self) -> [String; 10] {
+        let text = |v: &Option<String>| v.clone().unwrap_or_default();
+        [
+            self.record.to_string(),
+            self.at.to_string(),
+            self.task_id.clone(),
+            text(&self.stage),
+            text(&self.detail),
+            text(&self.random_number),
+            text(&self.salt),
+            text(&self.signature),
+            text(&self.public_key),
+            self.extensions
+                .as_ref()
+                .map(Value::to_string)
+                .unwrap_or_default(),
+        ]
+    }
+}
+
+/// Reads every record with `from <= at < to` of the namespace of `tenant`
+/// (the default one for `None`) from `storage`, ordered by time.
+pub fn collect(
+    storage: &dyn Storage,
+    tenant: Option<&str>,
+    from: Option<u64>,
+    to: Option<u64>,
+) -> Result<Vec<ExportRecord>, String> {
+    let in_range = |at: u64| from.is_none_or(|f| at >= f) && to.is_none_or(|t| at < t);
+    let mut records = Vec::new();
+
+    for (key, event) in storage.scan(&tenants::collection(TASK_EVENTS, tenant))? {
+        let at = event["at"]
+            .as_u64()
+            .ok_or_else(|| format!("Task event {} has no timestamp", key))?;
+        if !in_range(at) {
+            continue;
+        }
+        records.push(ExportRecord {
+            key,
+            record: "event",
+            at,
+            task_id: string_field(&event, "task_id").unwrap_or_default(),
+            stage: string_field(&event, "stage"),
+            detail: string_field(&event, "detail"),
+            random_number: None,
+            salt: None,
+            signature: None,
+            public_key: None,
+            extensions: None,
+        });
+    }
+
+    for (key, stored) in storage.scan(&tenants::collection(ATTESTATIONS, tenant))? {
+        let at = stored["completed_at"]
+            .as_u64()
+            .ok_or_else(|| format!("Attestation {} has no completion time", key))?;
+        if !in_range(at) {
+            continue;
+        }
+        let mut outcome = match stored.get("outcome") {
+            Some(Value::Object(outcome)) => outcome.clone(),
+            _ => return Err(format!("Attestation {} has no outcome", key)),
+        };
+        let mut take = |name: &str| match outcome.remove(name) {
+            Some(Value::String(s)) => Some(s),
+            _ => None,
+        };
+        let task_id = take("taskId").unwrap_or_else(|| key.clone());
+        let random_number = take("randomNumber");
+        let salt = take("salt");
+        let signature = take("signature");
+        let public_key = take("publicKey");
+        records.push(ExportRecord {
+            key,
+            record: "attestation",
+            at,
+            task_id,
+            stage: None,
+            detail: None,
+            random_number,
+            salt,
+            signature,
+            public_key,
+            extensions: (!outcome.is_empty()).then_some(Value::Object(outcome)),
+        });
+    }
+
+    records.sort_by_key(|r| r.at);
+    Ok(records)
+}
+
+/// Reads the outcome of every attestation completed at `from <= at < to` in
+/// the namespace of `tenant`, with its completion time, ordered by time.
+pub fn collect_outcomes(
+    storage: &dyn Storage,
+    tenant: Option<&str>,
+    from: Option<u64>,
+    to: Option<u64>,
+) -> Result<Vec<(u64, TaskOutcome)>, String> {
+    let mut outcomes = Vec::new();
+    for (key, stored) in storage.scan(&tenants::collection(ATTESTATIONS, tenant))? {
+        let at = stored["completed_at"]
+            .as_u64()
+            .ok_or_else(|| format!("Attestation {} has no completion time", key))?;
+        if from.is_some_and(|f| at < f) || to.is_some_and(|t| at >= t) {
+            continue;
+        }
+        let outcome = serde_json::from_value(stored["outcome"].clone())
+            .map_err(|e| format!("Attestation {} has no readable outcome: {}", key, e))?;
+        outcomes.push((at, outcome));
+    }
+    outcomes.sort_by_key(|(at, _)| *at);
+    Ok(outcomes)
+}
+
+/// Writes `records` to `out` in `format`.
+pub fn write(records: &[ExportRecord], format: Format, out: &mut dyn Write) -> Result<(), String> {
+    let io = |e: std::io::Error| format!("Failed to write export: {}", e);
+    match format {
+        Format::Jsonl => {
+            for record in records {
+                let line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+                writeln!(out, "{}", line).map_err(io)?;
+            }
+        }
+        Format::Csv => {
+            writeln!(out, "{}", COLUMNS.join(",")).map_err(io)?;
+            for record in records {
+                let row: Vec<String> = record.fields().iter().map(|f| csv_field(f)).collect();
+                writeln!(out, "{}", row.join(",")).map_err(io)?;
+            }
+        }
+    }
+    out.flush().map_err(io)
+}
+
+/// Parses a time bound: Unix milliseconds, or a UTC date `YYYY-MM-DD` with
+/// an optional `THH:MM:SS` and trailing `Z`.
+pub fn parse_time(value: &str) -> Result<u64, String> {
+    if let Ok(ms) = value.parse::<u64>() {
+        return Ok(ms);
+    }
+    let invalid = || format!("Invalid time '{}'", value);
+    let trimmed = value.strip_suffix('Z').unwrap_or(value);
+    let (date, time) = trimmed.split_once('T').unwrap_or((trimmed, "00:00:00"));
+
+    let date: Vec<u32> = date
+        .split('-')
+        .map(|p| p.parse().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    let time: Vec<u32> = time
+        .split(':')
+        .map(|p| p.parse().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    let (&[year, month, day], &[hour, minute, second]) = (date.as_slice(), time.as_slice()) else {
+        return Err(invalid());
+    };
+    if year < 1970 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+    if hour > 23 || minute > 59 || second > 59 {
+        return Err(invalid());
+    }
+
+    // Days since the epoch for a proleptic Gregorian date (H. Hinnant's algorithm).
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = u64::from(era) * 146_097 + u64::from(doe) - 719_468;
+
+    let seconds = days * 86_400 + u64::from(hour * 3600 + minute * 60 + second);
+    Ok(seconds * 1000)
+}
+
+/// Formats Unix milliseconds as a UTC date, `YYYY-MM-DDTHH:MM:SSZ`; the
+/// inverse of [`parse_time`] up to the dropped milliseconds.
+pub fn format_time(ms: u64) -> String {
+    let seconds = ms / 1000;
+    let (days, of_day) = (seconds / 86_400, seconds % 86_400);
+
+    // Civil date from days since the epoch (H. Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        of_day / 3600,
+        of_day % 3600 / 60,
+        of_day % 60
+    )
+}
+
+fn string_field(value: &Value, name: &str) -> Option<String> {
+    value.get(name).and_then(Value::as_str).map(str::to_string)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
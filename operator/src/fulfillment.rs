@@ -0,0 +1,335 @@
+// src/fulfillment.rs
+
+//! Tracking of fulfillment transactions after they were sent.
+//!
+//! A [`FulfillmentTracker`] polls the receipt of every transaction recorded in
+//! [`CHAIN_SUBMISSIONS`] (keyed by [`tenants::task_key`]) until it is
+//! `chain.confirmations` blocks deep, and logs what happens to it as task
+//! events:
+//!
+//! - a receipt whose block is no longer canonical, or that moved to another
+//!   block, is a reorg ([`TaskStage::Reorged`]); the transaction is tracked
+//!   again from inclusion;
+//! - a transaction the node no longer knows was dropped, e.g. by a reorg that
+//!   did not return it to the mempool, and the attested outcome is submitted
+//!   again, at most `chain.max_resubmissions` times;
+//! - a reverted transaction is final ([`TaskStage::Reverted`]): resubmitting
+//!   it would revert again.
+//!
+//! Once a transaction lands, the budget charge made at submission is replaced
+//! by the cost on its receipt; a transaction that leaves the chain or is
+//! dropped is refunded until it lands again.
+//!
+//! A confirmation is traced as an `rng.confirm` span, in the trace the
+//! transaction was submitted in.
+
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use log::{info, warn};
+use serde_json::{json, Value};
+
+use crate::chain::{ChainSubmitter, Receipt, SubmissionStatus, CHAIN_SUBMISSIONS};
+use crate::config::{self, ChainConfig};
+use crate::metrics::Metrics;
+use crate::storage::Storage;
+use crate::tasks::{Submitter, TaskOutcome, TaskRunner, TaskStage, ATTESTATIONS};
+use crate::telemetry::{SpanContext, SpanKind, Tracer};
+use crate::tenants;
+
+/// Follows submitted transactions until they are final.
+pub struct FulfillmentTracker {
+    chain: Arc<ChainSubmitter>,
+    runner: Arc<TaskRunner>,
+    storage: Arc<dyn Storage>,
+    confirmations: u64,
+    poll: Duration,
+    max_resubmissions: u64,
+    metrics: Arc<Metrics>,
+    tracer: Arc<Tracer>,
+}
+
+impl FulfillmentTracker {
+    /// Configured by the `chain` section; events are recorded through `runner`.
+    pub fn new(
+        config: &ChainConfig,
+        chain: Arc<ChainSubmitter>,
+        runner: Arc<TaskRunner>,
+        storage: Arc<dyn Storage>,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self, String> {
+        Ok(FulfillmentTracker {
+            chain,
+            runner,
+            storage,
+            confirmations: config.confirmations,
+            poll: config::parse_duration(&config.confirm_poll)?,
+            max_resubmissions: config.max_resubmissions,
+            metrics,
+            tracer: Arc::new(Tracer::disabled()),
+        })
+    }
+
+    /// Records confirmations as spans with `tracer`.
+    pub fn with_tracer(mut self, tracer: Arc<Tracer>) -> Self {
+        self.tracer = tracer;
+        self
+    }
+
+    /// Checks submissions every `chain.confirm_poll`; never returns.
+    pub fn run(&self) {
+        loop {
+            if let Err(e) = self.check_once() {
+                warn!("Failed to check fulfillments: {}", e);
+            }
+            thread::sleep(self.poll);
+        }
+    }
+
+    /// Checks every submission that is not final yet, returning how many
+    /// still are not.
+    pub fn check_once(&self) -> Result<usize, String> {
+        let head = self.chain.block_number()?;
+        let mut unconfirmed = 0;
+        for (task_id, record) in self.storage.scan(CHAIN_SUBMISSIONS)? {
+            let status = record
+                .get("status")
+                .and_then(|s| serde_json::from_value(s.clone()).ok())
+                .unwrap_or(SubmissionStatus::Pending);
+            if !matches!(
+                status,
+                SubmissionStatus::Pending | SubmissionStatus::Included
+            ) {
+                continue;
+            }
+            match self.check(&task_id, record, status, head) {
+                Ok(tracked) => unconfirmed += tracked as usize,
+                Err(e) => {
+                    warn!("Failed to check the fulfillment of task {}: {}", task_id, e);
+                    unconfirmed += 1;
+                }
+            }
+        }
+        self.metrics
+            .set_gauge("rng_chain_unconfirmed", &[], unconfirmed as f64);
+        Ok(unconfirmed)
+    }
+
+    /// Advances one submission; returns whether it still needs tracking.
+    fn check(
+        &self,
+        task_id: &str,
+        mut record: Value,
+        status: SubmissionStatus,
+        head: u64,
+    ) -> Result<bool, String> {
+        let hash = record
+            .get("txHash")
+            .and_then(Value::as_str)
+            .ok_or("Submission has no txHash")?
+            .to_string();
+        let receipt = match self.chain.receipt(&hash)? {
+            // A receipt from a block that is no longer canonical is stale.
+            Some(receipt)
+                if self.chain.block_hash(receipt.block_number)?.as_deref()
+                    == Some(receipt.block_hash.as_str()) =>
+            {
+                Some(receipt)
+            }
+            _ => None,
+        };
+        let previous_block = record
+            .get("blockHash")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        let Some(receipt) = receipt else {
+            if status == SubmissionStatus::Included {
+                let detail = format!(
+                    "transaction {} left block {}",
+                    hash,
+                    previous_block.as_deref().unwrap_or("?")
+                );
+                self.reorged(task_id, &detail);
+                self.refund(&mut record)?;
+                record["status"] = json!(SubmissionStatus::Pending);
+                record["blockHash"] = Value::Null;
+                record["blockNumber"] = Value::Null;
+                record["confirmations"] = json!(0);
+                self.storage
+                    .put(CHAIN_SUBMISSIONS, task_id, record.clone())?;
+            }
+            // Reorged-out transactions usually return to the mempool.
+            if self.chain.is_known(&hash)? {
+                return Ok(true);
+            }
+            self.refund(&mut record)?;
+            return self.resubmit(task_id, record);
+        };
+
+        if previous_block.as_deref() != Some(receipt.block_hash.as_str()) {
+            if let Some(previous) = &previous_block {
+                self.reorged(
+                    task_id,
+                    &format!(
+                        "transaction {} moved from block {} to {}",
+                        hash, previous, receipt.block_hash
+                    ),
+                );
+            }
+            self.settle(&mut record, &receipt)?;
+        }
+        if !receipt.succeeded {
+            warn!(
+                "Fulfillment of task {} reverted in block {}",
+                task_id, receipt.block_number
+            );
+            record["status"] = json!(SubmissionStatus::Reverted);
+            self.storage.put(CHAIN_SUBMISSIONS, task_id, record)?;
+            self.runner.record_event(
+                task_id,
+                TaskStage::Reverted,
+                Some(&format!("transaction {} reverted", hash)),
+            );
+            self.count("reverted");
+            return Ok(false);
+        }
+
+        let depth = head.saturating_sub(receipt.block_number) + 1;
+        record["confirmations"] = json!(depth);
+        if depth < self.confirmations {
+            record["status"] = json!(SubmissionStatus::Included);
+            self.storage.put(CHAIN_SUBMISSIONS, task_id, record)?;
+            return Ok(true);
+        }
+        record["status"] = json!(SubmissionStatus::Confirmed);
+        self.trace_confirmation(task_id, &record, &hash, &receipt, depth);
+        self.storage.put(CHAIN_SUBMISSIONS, task_id, record)?;
+        info!(
+            "Fulfillment of task {} confirmed in block {}",
+            task_id, receipt.block_number
+        );
+        self.chain.record_gas_used(receipt.cost);
+        self.runner.record_event(
+            task_id,
+            TaskStage::Confirmed,
+            Some(&format!(
+                "transaction {} in block {}",
+                hash, receipt.block_number
+            )),
+        );
+        self.count("confirmed");
+        Ok(false)
+    }
+
+    /// Records an `rng.confirm` span under the submission's trace, if it was
+    /// traced.
+    fn trace_confirmation(
+        &self,
+        task_id: &str,
+        record: &Value,
+        hash: &str,
+        receipt: &Receipt,
+        depth: u64,
+    ) {
+        let Some(parent) = record["traceparent"]
+            .as_str()
+            .and_then(SpanContext::from_traceparent)
+        else {
+            return;
+        };
+        let mut span = self
+            .tracer
+            .start("rng.confirm", SpanKind::Internal, Some(&parent));
+        span.set_attribute("rng.task_id", task_id);
+        span.set_attribute("rng.tx_hash", hash);
+        span.set_int_attribute("rng.block_number", receipt.block_number as i64);
+        span.set_int_attribute("rng.confirmations", depth as i64);
+        self.tracer.end(span);
+    }
+
+    /// Records the block `receipt` is in and settles its cost.
+    fn settle(&self, record: &mut Value, receipt: &Receipt) -> Result<(), String> {
+        self.chain.settle(charged(record), receipt.cost)?;
+        record["chargedWei"] = json!(receipt.cost.to_string());
+        record["blockHash"] = json!(receipt.block_hash);
+        record["blockNumber"] = json!(receipt.block_number);
+        Ok(())
+    }
+
+    /// Takes back what was charged for a transaction that did not land.
+    fn refund(&self, record: &mut Value) -> Result<(), String> {
+        self.chain.settle(charged(record), 0)?;
+        record["chargedWei"] = json!("0");
+        Ok(())
+    }
+
+    fn reorged(&self, task_id: &str, detail: &str) {
+        warn!(
+            "Reorg affected the fulfillment of task {}: {}",
+            task_id, detail
+        );
+        self.runner
+            .record_event(task_id, TaskStage::Reorged, Some(detail));
+        self.count("reorged");
+    }
+
+    /// Sends the attested outcome of `task_id` again after its transaction
+    /// was dropped; returns whether the task is still tracked.
+    fn resubmit(&self, task_id: &str, mut record: Value) -> Result<bool, String> {
+        let resubmissions = record["resubmissions"].as_u64().unwrap_or(0);
+        if resubmissions >= self.max_resubmissions {
+            warn!(
+                "Giving up on task {}: its fulfillment was dropped {} time(s)",
+                task_id,
+                resubmissions + 1
+            );
+            record["status"] = json!(SubmissionStatus::Abandoned);
+            self.storage.put(CHAIN_SUBMISSIONS, task_id, record)?;
+            self.runner.record_event(
+                task_id,
+                TaskStage::Failed,
+                Some("fulfillment dropped; resubmissions exhausted"),
+            );
+            self.count("abandoned");
+            return Ok(false);
+        }
+        let (tenant, id) = tenants::split_key(task_id);
+        let outcome: TaskOutcome = self
+            .storage
+            .get(&tenants::collection(ATTESTATIONS, tenant), id)?
+            .and_then(|r| serde_json::from_value(r["outcome"].clone()).ok())
+            .ok_or_else(|| format!("No attested outcome stored for task {}", task_id))?;
+        warn!("Fulfillment of task {} was dropped; resubmitting", task_id);
+        self.count("resubmitted");
+        if let Err(e) = self.chain.submit(&outcome) {
+            // Counts as an attempt, so a task that can no longer be
+            // fulfilled is eventually abandoned.
+            record["resubmissions"] = json!(resubmissions + 1);
+            record["lastError"] = json!(e);
+            self.storage.put(CHAIN_SUBMISSIONS, task_id, record)?;
+            return Err(e);
+        }
+        self.runner.record_event(
+            task_id,
+            TaskStage::Submitting,
+            Some("resubmitted after the previous transaction was dropped"),
+        );
+        Ok(true)
+    }
+
+    fn count(&self, outcome: &str) {
+        self.metrics
+            .inc_counter("rng_chain_fulfillments_total", &[("outcome", outcome)], 1);
+    }
+}
+
+/// Wei currently charged to the budget for the transaction of `record`.
+fn charged(record: &Value) -> u128 {
+    record
+        .get("chargedWei")
+        .and_then(Value::as_str)
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(0)
+}
@@ -0,0 +1,296 @@
+// src/heartbeat.rs
+
+//! Signed liveness heartbeats.
+//!
+//! Every `heartbeat.interval` the operator signs a [`Heartbeat`] with its
+//! current attestation key, posts it to each `heartbeat.targets` URL (the
+//! aggregator), broadcasts it to the beacon committee when the beacon runs,
+//! and keeps the latest one for `GET /heartbeat`. The AVS can then measure
+//! liveness without waiting for randomness tasks.
+//!
+//! Heartbeats are signed with Ed25519ph under [`CONTEXT`], so a heartbeat
+//! signature can never be replayed as an attestation signature. Each process
+//! draws a random boot ID and numbers its heartbeats from 0 under it, so a
+//! receiver tells a restart from an old heartbeat replayed. With epoch keys,
+//! heartbeats carry the certificate of the key that signed them. With a
+//! [`Provenance`] they also carry the operator's build statement, signed by
+//! the same key, so committee members and the AVS learn what each runs.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use log::warn;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::attester::RngAttester;
+use crate::beacon::BeaconNode;
+use crate::config::{self, HeartbeatConfig};
+use crate::epochs::EpochCertificate;
+use crate::metrics::Metrics;
+use crate::provenance::{Provenance, ProvenanceStatement};
+use crate::resilience::{CallError, Resilience};
+use crate::tasks::{unix_millis, TaskRunner};
+
+/// Ed25519ph context heartbeats are signed under.
+pub const CONTEXT: &[u8] = b"othentic-rng/heartbeat/v1";
+
+/// A signed statement that the operator is alive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Heartbeat {
+    /// The operator's on-chain address (`operator.address`).
+    pub operator: String,
+    /// Software version of the operator binary.
+    pub version: String,
+    /// Latest completed beacon round, when the beacon runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub round: Option<u64>,
+    /// Hex ID drawn at random when the process started.
+    pub boot_id: String,
+    /// Increases by one with every heartbeat of a process.
+    pub sequence: u64,
+    /// Unix time in milliseconds.
+    pub timestamp: u64,
+    pub public_key: String,
+    pub signature: String,
+    /// Build statement by the same key; not covered by `signature`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<ProvenanceStatement>,
+    /// Master certificate of the signing key, when the operator runs key
+    /// epochs; not covered by `signature`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch_certificate: Option<EpochCertificate>,
+}
+
+impl Heartbeat {
+    /// Builds and signs a heartbeat with the attester's key.
+    pub fn sign(
+        attester: &RngAttester,
+        operator: &str,
+        round: Option<u64>,
+        boot_id: &str,
+        sequence: u64,
+        timestamp: u64,
+    ) -> Result<Self, String> {
+        let mut heartbeat = Heartbeat {
+            operator: operator.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            round,
+            boot_id: boot_id.to_string(),
+            sequence,
+            timestamp,
+            public_key: hex::encode(attester.get_public_key().as_bytes()),
+            signature: String::new(),
+            provenance: None,
+            epoch_certificate: attester.epoch_certificate().cloned(),
+        };
+        let signature = attester.sign_with_context(CONTEXT, &heartbeat.signed_bytes())?;
+        heartbeat.signature = hex::encode(signature.to_bytes());
+        Ok(heartbeat)
+    }
+
+    /// Checks the signature, and those of the build statement and epoch
+    /// certificate if any, and returns the key that made them; callers
+    /// compare it with the key registered for `operator`.
+    pub fn verify(&self) -> Result<VerifyingKey, String> {
+        let key: [u8; 32] = hex::decode(&self.public_key)
+            .map_err(|e| format!("Invalid heartbeat public key: {}", e))?
+            .try_into()
+            .map_err(|_| "Heartbeat public key must be 32 bytes".to_string())?;
+        let key = VerifyingKey::from_bytes(&key)
+            .map_err(|e| format!("Invalid heartbeat public key: {}", e))?;
+        let signature: [u8; 64] = hex::decode(&self.signature)
+            .map_err(|e| format!("Invalid heartbeat signature: {}", e))?
+            .try_into()
+            .map_err(|_| "Heartbeat signature must be 64 bytes".to_string())?;
+        RngAttester::verify_with_context(
+            &key,
+            CONTEXT,
+            &self.signed_bytes(),
+            &Signature::from_bytes(&signature),
+        )?;
+        if let Some(provenance) = &self.provenance {
+            if provenance.verify()? != key {
+                return Err("Build statement is signed by a different key".to_string());
+            }
+        }
+        if let Some(certificate) = &self.epoch_certificate {
+            if certificate.verify()?.1 != key {
+                return Err("Epoch certificate is for a different key".to_string());
+            }
+        }
+        Ok(key)
+    }
+
+    /// Hex key the operator is known by: the master key of a certified
+    /// epoch key, otherwise the signing key. Call after [`Heartbeat::verify`].
+    pub fn operator_key(&self) -> String {
+        match &self.epoch_certificate {
+            Some(certificate) => certificate.master_public_key.to_lowercase(),
+            None => self.public_key.to_lowercase(),
+        }
+    }
+
+    /// Length-prefixed encoding of every field but the signature.
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        for field in [
+            self.operator.as_bytes(),
+            self.version.as_bytes(),
+            self.boot_id.as_bytes(),
+        ] {
+            data.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            data.extend_from_slice(field);
+        }
+        match self.round {
+            Some(round) => {
+                data.push(1);
+                data.extend_from_slice(&round.to_be_bytes());
+            }
+            None => data.push(0),
+        }
+        data.extend_from_slice(&self.sequence.to_be_bytes());
+        data.extend_from_slice(&self.timestamp.to_be_bytes());
+        data.extend_from_slice(self.public_key.as_bytes());
+        data
+    }
+}
+
+/// Emits heartbeats on a fixed interval.
+pub struct HeartbeatEmitter {
+    operator: String,
+    interval: Duration,
+    targets: Vec<String>,
+    token: Option<String>,
+    runner: Arc<TaskRunner>,
+    beacon: Option<Arc<BeaconNode>>,
+    provenance: Option<Arc<Provenance>>,
+    resilience: Arc<Resilience>,
+    metrics: Arc<Metrics>,
+    agent: ureq::Agent,
+    boot_id: String,
+    sequence: AtomicU64,
+    latest: Mutex<Option<Heartbeat>>,
+}
+
+impl HeartbeatEmitter {
+    /// Builds an emitter from the `heartbeat` config section; heartbeats are
+    /// signed with whatever key `runner` currently attests with.
+    pub fn new(
+        config: &HeartbeatConfig,
+        operator: &str,
+        runner: Arc<TaskRunner>,
+        resilience: Arc<Resilience>,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self, String> {
+        let mut boot_id = [0u8; 16];
+        OsRng.fill_bytes(&mut boot_id);
+        Ok(HeartbeatEmitter {
+            operator: operator.to_string(),
+            interval: config::parse_duration(&config.interval)?,
+            targets: config.targets.clone(),
+            token: config.token.clone(),
+            runner,
+            beacon: None,
+            provenance: None,
+            resilience,
+            metrics,
+            agent: ureq::AgentBuilder::new().build(),
+            boot_id: hex::encode(boot_id),
+            sequence: AtomicU64::new(0),
+            latest: Mutex::new(None),
+        })
+    }
+
+    /// Also broadcasts heartbeats to the beacon committee and reports its
+    /// latest round.
+    pub fn with_beacon(mut self, beacon: Arc<BeaconNode>) -> Self {
+        self.beacon = Some(beacon);
+        self
+    }
+
+    /// Attaches a build statement from `provenance` to every heartbeat.
+    pub fn with_provenance(mut self, provenance: Arc<Provenance>) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Returns the most recent heartbeat, if one was emitted yet.
+    pub fn latest(&self) -> Option<Heartbeat> {
+        self.latest.lock().expect("heartbeat lock poisoned").clone()
+    }
+
+    /// Signs and delivers one heartbeat.
+    pub fn beat(&self) -> Result<Heartbeat, String> {
+        let round = match &self.beacon {
+            Some(beacon) => beacon.latest_round()?,
+            None => None,
+        };
+        let attester = self.runner.attester();
+        let mut heartbeat = Heartbeat::sign(
+            &attester,
+            &self.operator,
+            round,
+            &self.boot_id,
+            self.sequence.fetch_add(1, Ordering::Relaxed),
+            unix_millis(),
+        )?;
+        if let Some(provenance) = &self.provenance {
+            heartbeat.provenance = Some(provenance.statement(&attester)?);
+        }
+        *self.latest.lock().expect("heartbeat lock poisoned") = Some(heartbeat.clone());
+
+        for (index, target) in self.targets.iter().enumerate() {
+            let outcome = match self.post(index, target, &heartbeat) {
+                Ok(()) => "delivered",
+                Err(e) => {
+                    warn!("Failed to deliver heartbeat to {}: {}", target, e);
+                    "failed"
+                }
+            };
+            self.metrics
+                .inc_counter("rng_heartbeats_total", &[("outcome", outcome)], 1);
+        }
+        if let Some(beacon) = &self.beacon {
+            beacon.broadcast_heartbeat(&heartbeat)?;
+        }
+        Ok(heartbeat)
+    }
+
+    /// Emits a heartbeat every interval, forever.
+    pub fn run(&self) {
+        loop {
+            if let Err(e) = self.beat() {
+                warn!("Failed to emit heartbeat: {}", e);
+            }
+            thread::sleep(self.interval);
+        }
+    }
+
+    fn post(&self, index: usize, target: &str, heartbeat: &Heartbeat) -> Result<(), String> {
+        let body = serde_json::to_string(heartbeat)
+            .map_err(|e| format!("Failed to encode heartbeat: {}", e))?;
+        self.resilience
+            .call(&format!("heartbeat-{}", index), |remaining| {
+                let mut call = self.agent.post(target).timeout(remaining);
+                if let Some(token) = &self.token {
+                    call = call.set("Authorization", &format!("Bearer {}", token));
+                }
+                match call
+                    .set("Content-Type", "application/json")
+                    .send_string(&body)
+                {
+                    Ok(_) => Ok(()),
+                    Err(ureq::Error::Status(code, _)) if code < 500 && code != 429 => {
+                        Err(CallError::Permanent(format!("HTTP {}", code)))
+                    }
+                    Err(e) => Err(CallError::Transient(e.to_string())),
+                }
+            })
+    }
+}
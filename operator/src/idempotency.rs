@@ -0,0 +1,233 @@
+// src/idempotency.rs
+
+//! Idempotent task execution.
+//!
+//! A client that retries `POST /task/execute` after a timeout must not end up
+//! with two different random values for one draw. Requests therefore carry an
+//! idempotency key, the `Idempotency-Key` header or, failing that, the
+//! client's `taskId`. The first request with a key is executed and its
+//! outcome kept in [`IDEMPOTENCY_KEYS`] for `server.idempotency_ttl`; every
+//! later request with that key is answered with the stored outcome, without
+//! generating anything.
+//!
+//! A key is bound to the request it first came with: reusing it for a
+//! different request is refused rather than answered with an outcome that
+//! was never asked for, and so is a retry while the first request is still
+//! running. Keys are scoped to the tenant.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Storage;
+use crate::tasks::{unix_millis, TaskOutcome};
+use crate::tenants;
+
+/// Collection of the outcomes served per idempotency key.
+pub const IDEMPOTENCY_KEYS: &str = "idempotency_keys";
+/// Longest accepted key.
+pub const MAX_KEY_LEN: usize = 255;
+
+/// Expired records are looked for at most this often.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Why a request cannot be served under its key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyError {
+    /// Empty, too long or not printable ASCII.
+    InvalidKey,
+    /// A request with the key is still being executed.
+    InProgress,
+    /// The key was first used for a different request.
+    Mismatch,
+    Storage(String),
+}
+
+impl fmt::Display for IdempotencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdempotencyError::InvalidKey => write!(
+                f,
+                "idempotency key must be 1 to {} printable ASCII characters",
+                MAX_KEY_LEN
+            ),
+            IdempotencyError::InProgress => {
+                write!(f, "a request with this idempotency key is in progress")
+            }
+            IdempotencyError::Mismatch => {
+                write!(
+                    f,
+                    "idempotency key was already used for a different request"
+                )
+            }
+            IdempotencyError::Storage(e) => write!(f, "idempotency store failed: {}", e),
+        }
+    }
+}
+
+/// What is stored per key.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    created_at: u64,
+    /// Hex SHA-256 of the request the key was first used for.
+    fingerprint: String,
+    outcome: TaskOutcome,
+}
+
+/// Result of [`IdempotencyCache::claim`].
+pub enum Claim<'a> {
+    /// The key has been served before; answer with this outcome.
+    Replay(Box<TaskOutcome>),
+    /// The key is new (or expired) and now reserved for the caller.
+    Fresh(Reservation<'a>),
+}
+
+/// The outcomes served per key, and the keys being served right now.
+pub struct IdempotencyCache {
+    storage: Arc<dyn Storage>,
+    ttl: Duration,
+    /// `(collection, key)` of the requests being executed.
+    in_progress: Mutex<HashSet<(String, String)>>,
+    /// Collections keys have been claimed in, i.e. that may need purging.
+    collections: Mutex<HashSet<String>>,
+    last_purge: Mutex<Instant>,
+}
+
+impl IdempotencyCache {
+    pub fn new(storage: Arc<dyn Storage>, ttl: Duration) -> Self {
+        IdempotencyCache {
+            storage,
+            ttl,
+            in_progress: Mutex::new(HashSet::new()),
+            collections: Mutex::new(HashSet::new()),
+            last_purge: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Looks up `key` of `tenant` for a request with digest `fingerprint`.
+    pub fn claim(
+        &self,
+        tenant: Option<&str>,
+        key: &str,
+        fingerprint: &[u8; 32],
+    ) -> Result<Claim<'_>, IdempotencyError> {
+        if key.is_empty()
+            || key.len() > MAX_KEY_LEN
+            || !key.bytes().all(|b| b.is_ascii_graphic() || b == b' ')
+        {
+            return Err(IdempotencyError::InvalidKey);
+        }
+        self.purge_if_due();
+        let collection = tenants::collection(IDEMPOTENCY_KEYS, tenant);
+        self.collections
+            .lock()
+            .expect("idempotency lock poisoned")
+            .insert(collection.clone());
+        let scoped = (collection.clone(), key.to_string());
+        let mut in_progress = self.in_progress.lock().expect("idempotency lock poisoned");
+        if in_progress.contains(&scoped) {
+            return Err(IdempotencyError::InProgress);
+        }
+        let stored = self
+            .storage
+            .get(&collection, key)
+            .map_err(IdempotencyError::Storage)?
+            .and_then(|value| serde_json::from_value::<Record>(value).ok())
+            .filter(|record| !self.expired(record));
+        if let Some(record) = stored {
+            if record.fingerprint != hex::encode(fingerprint) {
+                return Err(IdempotencyError::Mismatch);
+            }
+            return Ok(Claim::Replay(Box::new(record.outcome)));
+        }
+        in_progress.insert(scoped.clone());
+        Ok(Claim::Fresh(Reservation {
+            cache: self,
+            scoped,
+            fingerprint: hex::encode(fingerprint),
+        }))
+    }
+
+    /// Deletes the records older than the TTL in every collection claimed
+    /// in since startup; returns how many.
+    pub fn purge_expired(&self) -> Result<usize, String> {
+        let collections: Vec<String> = self
+            .collections
+            .lock()
+            .expect("idempotency lock poisoned")
+            .iter()
+            .cloned()
+            .collect();
+        let mut purged = 0;
+        for collection in collections {
+            let before = purged;
+            for (key, value) in self.storage.scan(&collection)? {
+                let expired = serde_json::from_value::<Record>(value)
+                    .map_or(true, |record| self.expired(&record));
+                if expired {
+                    self.storage.delete(&collection, &key)?;
+                    purged += 1;
+                }
+            }
+            if purged > before {
+                self.storage.compact(&collection)?;
+            }
+        }
+        Ok(purged)
+    }
+
+    fn expired(&self, record: &Record) -> bool {
+        unix_millis().saturating_sub(record.created_at) >= self.ttl.as_millis() as u64
+    }
+
+    fn purge_if_due(&self) {
+        {
+            let mut last = self.last_purge.lock().expect("idempotency lock poisoned");
+            if last.elapsed() < PURGE_INTERVAL {
+                return;
+            }
+            *last = Instant::now();
+        }
+        if let Err(e) = self.purge_expired() {
+            warn!("Failed to purge expired idempotency keys: {}", e);
+        }
+    }
+}
+
+/// A key reserved for one request; released when dropped. Call
+/// [`Reservation::complete`] once the request has succeeded, so a failed
+/// request can be retried under the same key.
+pub struct Reservation<'a> {
+    cache: &'a IdempotencyCache,
+    scoped: (String, String),
+    fingerprint: String,
+}
+
+impl Reservation<'_> {
+    /// Stores `outcome` as the answer to every later request with the key.
+    pub fn complete(self, outcome: &TaskOutcome) -> Result<(), String> {
+        let record = Record {
+            created_at: unix_millis(),
+            fingerprint: self.fingerprint.clone(),
+            outcome: outcome.clone(),
+        };
+        let value = serde_json::to_value(&record).map_err(|e| e.to_string())?;
+        let (collection, key) = &self.scoped;
+        self.cache.storage.put(collection, key, value)?;
+        self.cache.storage.flush()
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.cache
+            .in_progress
+            .lock()
+            .expect("idempotency lock poisoned")
+            .remove(&self.scoped);
+    }
+}
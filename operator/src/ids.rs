@@ -0,0 +1,115 @@
+// src/ids.rs
+
+//! Verifiably random identifiers (RFC 4122 version 4 UUIDs).
+//!
+//! A batch of UUIDs is generated as one block of performer entropy, the
+//! version and variant bits are set in place, and the resulting bytes are
+//! attested with kind [`UUID_V4_KIND`]. The attestation therefore covers the
+//! exact identifiers handed out, and [`verify_uuids`] recovers them.
+
+use ed25519_dalek::VerifyingKey;
+
+use crate::attester::{Attestation, AttestationPayload, RngAttester};
+use crate::performer::RngPerformer;
+
+/// Payload kind recorded for UUID batches.
+pub const UUID_V4_KIND: &str = "uuid-v4";
+
+/// Largest batch accepted by [`RngPerformer::generate_uuid_v4_batch`].
+pub const MAX_UUID_BATCH: usize = 10_000;
+
+const UUID_LEN: usize = 16;
+
+/// A batch of UUIDs together with the attestation covering it.
+#[derive(Debug, Clone)]
+pub struct AttestedUuids {
+    pub attestation: Attestation,
+}
+
+impl AttestedUuids {
+    /// Returns the UUIDs in canonical `8-4-4-4-12` hyphenated form.
+    pub fn uuids(&self) -> Vec<String> {
+        self.attestation
+            .payload
+            .random_number
+            .chunks(UUID_LEN)
+            .map(format_uuid)
+            .collect()
+    }
+}
+
+impl RngPerformer {
+    /// Generates a single attested version 4 UUID.
+    pub fn generate_uuid_v4(&self, attester: &RngAttester) -> Result<AttestedUuids, String> {
+        self.generate_uuid_v4_batch(attester, 1)
+    }
+
+    /// Generates `count` version 4 UUIDs covered by a single attestation.
+    ///
+    /// # Arguments
+    /// * `attester` - Signs the batch.
+    /// * `count` - Number of UUIDs, between 1 and `MAX_UUID_BATCH`.
+    ///
+    /// # Returns
+    /// A `Result` containing:
+    /// - `Ok(AttestedUuids)` with the batch and its attestation.
+    /// - `Err(String)` if `count` is out of range or generation fails.
+    pub fn generate_uuid_v4_batch(
+        &self,
+        attester: &RngAttester,
+        count: usize,
+    ) -> Result<AttestedUuids, String> {
+        if count == 0 || count > MAX_UUID_BATCH {
+            return Err(format!(
+                "UUID count must be between 1 and {}.",
+                MAX_UUID_BATCH
+            ));
+        }
+        let mut bytes = self.generate_random_number(count * UUID_LEN)?;
+        for uuid in bytes.chunks_mut(UUID_LEN) {
+            uuid[6] = (uuid[6] & 0x0f) | 0x40; // version 4
+            uuid[8] = (uuid[8] & 0x3f) | 0x80; // RFC 4122 variant
+        }
+        let attestation =
+            attester.attest_payload(AttestationPayload::new(bytes).with_kind(UUID_V4_KIND))?;
+        Ok(AttestedUuids { attestation })
+    }
+}
+
+/// Verifies an attested UUID batch and returns its identifiers.
+pub fn verify_uuids(
+    public_key: &VerifyingKey,
+    attestation: &Attestation,
+) -> Result<Vec<String>, String> {
+    let payload = &attestation.payload;
+    if payload.kind.as_deref() != Some(UUID_V4_KIND) {
+        return Err("Attestation is not a UUID batch".to_string());
+    }
+    if payload.random_number.is_empty() || !payload.random_number.len().is_multiple_of(UUID_LEN) {
+        return Err("UUID batch length is not a multiple of 16 bytes".to_string());
+    }
+    RngAttester::verify(public_key, attestation)?;
+
+    payload
+        .random_number
+        .chunks(UUID_LEN)
+        .map(|uuid| {
+            if uuid[6] >> 4 != 4 || uuid[8] >> 6 != 0b10 {
+                return Err(format!("{} is not an RFC 4122 v4 UUID", format_uuid(uuid)));
+            }
+            Ok(format_uuid(uuid))
+        })
+        .collect()
+}
+
+fn format_uuid(bytes: &[u8]) -> String {
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
@@ -0,0 +1,65 @@
+//! Rust backend for the Othentic RNG AVS operator.
+//!
+//! The binary in `main.rs` drives these modules; they are exposed as a library
+//! so the pieces (generation, attestation, outbound resilience) can be reused
+//! and wired together independently.
+
+pub mod abi;
+pub mod archive;
+pub mod attester;
+pub mod batch;
+pub mod beacon;
+pub mod card_deck;
+pub mod chain;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod config;
+pub mod conformance;
+pub mod dedup;
+pub mod definitions;
+pub mod devnet;
+pub mod distributions;
+pub mod drand;
+pub mod encoding;
+#[cfg(feature = "enclave")]
+pub mod enclave;
+pub mod entropy;
+pub mod epochs;
+pub mod export;
+pub mod fulfillment;
+pub mod heartbeat;
+pub mod idempotency;
+pub mod ids;
+pub mod logging;
+pub mod merkle;
+pub mod metrics;
+pub mod p2p;
+pub mod performer;
+pub mod pool;
+pub mod postprocess;
+pub mod primes;
+pub mod provenance;
+pub mod publish;
+pub mod pvss;
+pub mod queue;
+pub mod receipts;
+pub mod reputation;
+pub mod resilience;
+pub mod revocation;
+pub mod reverify;
+pub mod roles;
+pub mod server;
+pub mod shamir;
+pub mod signer;
+pub mod snapshot;
+pub mod status;
+pub mod storage;
+pub mod stream;
+pub mod tasks;
+pub mod telemetry;
+pub mod tenants;
+pub mod timelock;
+pub mod vdf;
+pub mod vrf;
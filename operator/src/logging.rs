@@ -0,0 +1,79 @@
+// src/logging.rs
+
+//! Minimal stderr logger for the `log` facade.
+//!
+//! The level is a global filter that can be changed at any time, which is what
+//! lets a config reload adjust verbosity without restarting the operator.
+//! [`use_json`] switches to one JSON object per line, for log collectors.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::{LevelFilter, Log, Metadata, Record};
+use serde_json::json;
+
+use crate::tasks::unix_millis;
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if JSON.load(Ordering::Relaxed) {
+            let line = json!({
+                "ts": unix_millis(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            eprintln!("{}", line);
+        } else {
+            eprintln!(
+                "[{}] {}: {}",
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+/// Parses a level name (`off`, `error`, `warn`, `info`, `debug`, `trace`).
+pub fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level
+        .parse::<LevelFilter>()
+        .map_err(|_| format!("Unknown log level '{}'", level))
+}
+
+/// Installs the logger with the given initial level.
+///
+/// Calling this more than once only updates the level.
+pub fn init(level: &str) -> Result<(), String> {
+    let filter = parse_level(level)?;
+    // A second `set_logger` fails harmlessly; the level is still applied below.
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(filter);
+    Ok(())
+}
+
+/// Writes every further log line as `{"ts","level","target","message"}`.
+pub fn use_json() {
+    JSON.store(true, Ordering::Relaxed);
+}
+
+/// Changes the active log level.
+pub fn set_level(level: &str) -> Result<(), String> {
+    log::set_max_level(parse_level(level)?);
+    Ok(())
+}
@@ -1,55 +1,972 @@
 
-    mod performer;
-    mod attester;
+    use std::fs::File;
+    use std::io::{self, BufWriter, Write};
+    use std::process;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Instant;
 
-    use performer::RngPerformer;
-    use attester::RngAttester;
+    use ed25519_dalek::VerifyingKey;
+    use log::{error, info, warn};
+    use serde_json::{json, Value};
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
 
-    use hex;
+    use operator::config::{self, ConfigHandle};
+    use operator::metrics::Metrics;
+    use operator::performer::RngPerformer;
+    use operator::pool::RandomnessPool;
+    use operator::postprocess::Pipeline;
+    use operator::primes::{self, PrimeTrail};
+    use operator::publish::EventPublisher;
+    use operator::archive::{self as archival, Archiver};
+    use operator::attester::{Attestation, AttestationPayload, RngAttester};
+    use operator::beacon::BeaconNode;
+    use operator::card_deck;
+    use operator::chain::ChainSubmitter;
+    #[cfg(feature = "chaos")]
+    use operator::chaos::{ChaosSubmitter, Faults};
+    use operator::dedup::FulfilledTasks;
+    use operator::definitions::DefinitionRegistry;
+    use operator::devnet::{Devnet, DevnetConfig};
+    use operator::drand::DrandClient;
+    #[cfg(feature = "enclave")]
+    use operator::enclave::{EnclaveKey, Platform};
+    use operator::entropy::EntropyMixer;
+    use operator::epochs::EpochKeys;
+    use operator::export::{self, Format};
+    use operator::fulfillment::FulfillmentTracker;
+    use operator::heartbeat::HeartbeatEmitter;
+    use operator::idempotency::IdempotencyCache;
+    use operator::provenance::Provenance;
+    use operator::reputation::Reputation;
+    use operator::resilience::Resilience;
+    use operator::reverify::Reverifier;
+    use operator::revocation::RevocationRegistry;
+    use operator::roles::{QuorumSubmitter, Role, Validator};
+    use operator::server::{self, Server};
+    use operator::signer::BatchSigner;
+    use operator::snapshot as snapshots;
+    use operator::status::{self, Classify, Failure, FailureClass, OutputMode};
+    use operator::storage::{FileStorage, MemoryStorage, Storage};
+    use operator::tasks::{LogSubmitter, Submitter, TaskOutcome, TaskRunner};
+    use operator::telemetry::Tracer;
+    use operator::tenants::Tenants;
 
-    fn main() -> Result<(), String> {
-        println!("Starting RNG Operator (Rust Backend)...");
+    const DEFAULT_CONFIG_PATH: &str = "config/config.yaml";
+
+    /// `operator [--output text|json] COMMAND ...`; see [`operator::status`]
+    /// for the exit codes and the JSON status object.
+    fn main() {
+        let mut args: Vec<String> = std::env::args().skip(1).collect();
+        let mode = match take_output_mode(&mut args) {
+            Ok(mode) => mode,
+            Err(failure) => finish(OutputMode::Text, "operator", Err(failure)),
+        };
+        if mode == OutputMode::Json {
+            operator::logging::use_json();
+        }
+        let command = args.first().cloned().unwrap_or_else(|| "demo".to_string());
+        let result = match command.as_str() {
+            "serve" => serve(&args[1..], mode),
+            "export" => export(&args[1..], mode),
+            "bench" => bench(&args[1..], mode),
+            "archive" => archive(&args[1..], mode),
+            "deck" => deck(&args[1..], mode),
+            "prime" => prime(&args[1..], mode),
+            "reverify" => reverify(&args[1..], mode),
+            "snapshot" => snapshot(&args[1..], mode),
+            "restore" => restore(&args[1..], mode),
+            "devnet" => devnet(&args[1..], mode),
+            _ => run_demo(mode),
+        };
+        finish(mode, &command, result)
+    }
+
+    /// Strips the global `--output MODE` given before the command.
+    fn take_output_mode(args: &mut Vec<String>) -> Result<OutputMode, Failure> {
+        if args.first().map(String::as_str) != Some("--output") {
+            return Ok(OutputMode::Text);
+        }
+        let value = args.get(1).cloned()
+            .ok_or_else(|| Failure::new(FailureClass::Usage, "--output needs a value"))?;
+        args.drain(..2);
+        OutputMode::parse(&value)
+    }
+
+    /// Reports how `command` ended and exits with its code. The JSON status
+    /// goes to stdout, or to stderr for commands that write data there.
+    fn finish(mode: OutputMode, command: &str, result: Result<Value, Failure>) -> ! {
+        match mode {
+            OutputMode::Json => {
+                let mut status = status::status_json(&result);
+                status["command"] = json!(command);
+                if matches!(command, "export" | "archive" | "deck" | "prime" | "reverify") {
+                    eprintln!("{}", status);
+                } else {
+                    println!("{}", status);
+                }
+            }
+            OutputMode::Text => {
+                if let Err(failure) = &result {
+                    eprintln!("Error: {}", failure);
+                }
+            }
+        }
+        process::exit(status::exit_code(&result))
+    }
+
+    /// Runs the operator as a long-lived service.
+    ///
+    /// `--dry-run` attests every task but only records what would have been
+    /// submitted, in the `dry_runs` collection.
+    ///
+    /// `SIGHUP` reloads the config; `SIGTERM`/`SIGINT` stop admitting tasks,
+    /// drain in-flight ones for up to `server.drain_timeout`, flush storage and exit,
+    /// with a `drain` failure if tasks had to be persisted for the next start.
+    fn serve(args: &[String], mode: OutputMode) -> Result<Value, Failure> {
+        let config_path = flag_value(args, "--config").unwrap_or(DEFAULT_CONFIG_PATH);
+        let config = Arc::new(ConfigHandle::load(config_path).classify(FailureClass::Config)?);
+        let settings = config.current();
+        operator::logging::init(&settings.logging.level).classify(FailureClass::Config)?;
+        let drain_timeout = config::parse_duration(&settings.server.drain_timeout).classify(FailureClass::Config)?;
+
+        let metrics = Arc::new(Metrics::new());
+        let storage: Arc<dyn Storage> = match &settings.storage.path {
+            Some(path) => Arc::new(FileStorage::open(path).classify(FailureClass::Storage)?),
+            None => Arc::new(MemoryStorage::new()),
+        };
+        let mut attester = attestation_key(&settings.signing).classify(FailureClass::Key)?
+            .with_nonce_store(Arc::clone(&storage)).classify(FailureClass::Storage)?;
+        if settings.signing.secp256k1 {
+            attester = attester.with_secp256k1_key(&settings.operator.private_key).classify(FailureClass::Key)?;
+            if let Some(address) = attester.secp256k1_address() {
+                info!("Dual-signing attestations as 0x{}", hex::encode(address));
+            }
+        }
+        if settings.signing.schnorr {
+            attester = attester.with_schnorr_key(&settings.operator.private_key).classify(FailureClass::Key)?;
+            if let Some(key) = attester.schnorr_public_key() {
+                info!("Schnorr-signing attestations as x-only key {}", hex::encode(key));
+            }
+        }
+        let chain = if settings.chain.enabled {
+            let chain = ChainSubmitter::new(
+                &settings,
+                Arc::clone(&storage),
+                Arc::new(Resilience::new(settings.resilience.to_config()?, Arc::clone(&metrics))),
+                Arc::clone(&metrics),
+            ).classify(FailureClass::Config)?;
+            info!("Submitting results to chain {} from 0x{}", settings.network.chain_id, hex::encode(chain.address()));
+            Some(Arc::new(chain))
+        } else {
+            None
+        };
+        let mut submitter: Arc<dyn Submitter> = match &chain {
+            Some(chain) => Arc::clone(chain) as Arc<dyn Submitter>,
+            None => Arc::new(LogSubmitter),
+        };
+        match settings.role.mode {
+            Role::Performer => {
+                submitter = Arc::new(QuorumSubmitter::from_config(
+                    &settings.role,
+                    Arc::new(Resilience::new(settings.resilience.to_config()?, Arc::clone(&metrics))),
+                    submitter,
+                    Arc::clone(&metrics),
+                ).classify(FailureClass::Config)?);
+                info!("Running as a performer; outcomes need {} of {} attester approval(s)", settings.role.quorum, settings.role.attesters.len());
+            }
+            Role::Attester => info!("Running as an attester; task endpoints are disabled"),
+            Role::Full => {}
+        }
+        #[cfg(feature = "chaos")]
+        let faults = Arc::new(Faults::new(Arc::clone(&metrics)));
+        #[cfg(feature = "chaos")]
+        {
+            submitter = Arc::new(ChaosSubmitter::new(submitter, Arc::clone(&faults)));
+            warn!("Fault injection is built in; POST /admin/faults switches faults on");
+        }
+        let performer = RngPerformer::new()
+            .with_length_policy(settings.lengths.policy().classify(FailureClass::Config)?)
+            .with_max_stream_length(settings.lengths.stream_max);
+        let mut runner = TaskRunner::new(
+            performer,
+            attester,
+            Arc::clone(&storage),
+            submitter,
+            Arc::clone(&metrics),
+            settings.queue.capacity,
+        ).with_entropy(Arc::new(EntropyMixer::new(Arc::clone(&config), Arc::clone(&metrics))));
+        if !settings.entropy.sources.is_empty() {
+            info!("Mixing entropy from {} (at least {} per value)", settings.entropy.sources.join(", "), settings.entropy.min_sources);
+        }
+        if settings.vdf.enabled {
+            runner = runner.with_vdf(settings.vdf.iterations);
+        }
+        let mut epochs = None;
+        if settings.epochs.enabled {
+            let keys = Arc::new(EpochKeys::open(&settings.epochs, Arc::clone(&storage)).classify(FailureClass::Key)?);
+            runner = runner.with_epochs(Arc::clone(&keys)).classify(FailureClass::Key)?;
+            info!("Signing with epoch keys certified by master key {}", hex::encode(keys.master_public_key().as_bytes()));
+            epochs = Some(keys);
+        }
+        if settings.vrf.enabled {
+            runner = runner.with_vrf();
+        }
+        runner = runner.with_metadata(Arc::clone(&config));
+        if args.iter().any(|a| a == "--dry-run") {
+            runner = runner.with_dry_run();
+            warn!("Dry run: outcomes are attested and recorded but never submitted");
+        }
+        let mut publisher = None;
+        if settings.publish.enabled {
+            let events = Arc::new(EventPublisher::start(
+                &settings.publish,
+                &settings.operator.address,
+                settings.network.chain_id,
+                Arc::new(Resilience::new(settings.resilience.to_config()?, Arc::clone(&metrics))),
+                Arc::clone(&metrics),
+            ).classify(FailureClass::Network)?);
+            runner = runner.with_publisher(Arc::clone(&events));
+            info!("Publishing events to {} on {}", settings.publish.topic, settings.publish.url);
+            publisher = Some(events);
+        }
+        let tracer = Arc::new(Tracer::from_config(
+            &settings.tracing,
+            Arc::new(Resilience::new(settings.resilience.to_config()?, Arc::clone(&metrics))),
+            Arc::clone(&metrics),
+        )?);
+        runner = runner.with_tracer(Arc::clone(&tracer));
+        if tracer.is_enabled() {
+            let exporting = Arc::clone(&tracer);
+            thread::spawn(move || exporting.run());
+            info!("Exporting traces to {}", settings.tracing.endpoint);
+        }
+        if let Some(validity) = &settings.signing.validity {
+            runner = runner.with_validity(config::parse_duration(validity)?);
+        }
+        let definitions = DefinitionRegistry::from_config(&settings.task_definitions)?;
+        if !definitions.is_empty() {
+            info!("Serving task definitions {}", definitions.ids().join(", "));
+        }
+        runner = runner.with_definitions(definitions);
+        #[cfg(feature = "chaos")]
+        {
+            runner = runner.with_faults(Arc::clone(&faults));
+        }
+        if settings.signing.abi {
+            runner = runner.with_abi();
+        }
+        let pipeline = Pipeline::parse(&settings.postprocess)?;
+        if !pipeline.is_empty() {
+            info!("Post-processing fresh entropy with {}", pipeline.ids().join(", "));
+            runner = runner.with_postprocess(pipeline);
+        }
+        if let Some(slo) = &settings.performance.latency_slo {
+            runner = runner.with_latency_slo(config::parse_duration(slo)?);
+        }
+        if settings.dedup.enabled {
+            let ttl = config::parse_duration(&settings.dedup.ttl)?;
+            let fulfilled = FulfilledTasks::new(Arc::clone(&storage), ttl, settings.dedup.on_duplicate);
+            runner = runner.with_dedup(Arc::new(fulfilled));
+            info!("Remembering fulfilled tasks for {:?} (on duplicate: {})", ttl, settings.dedup.on_duplicate.as_str());
+        }
+        if settings.timelock.enabled {
+            runner = runner.with_timelock(config::parse_duration(&settings.timelock.max_delay)?);
+        }
+        if settings.signing.batching.enabled {
+            let signer = BatchSigner::start(&settings.signing.batching, Arc::clone(&metrics))?;
+            runner = runner.with_batch_signer(Arc::new(signer));
+            info!("Batching signatures, up to {} per batch", settings.signing.batching.max_batch);
+        }
+        if settings.pool.enabled {
+            runner = runner.with_pool(Arc::new(RandomnessPool::new(&settings.pool, Arc::clone(&storage)).classify(FailureClass::Storage)?));
+        }
+        if settings.drand.enabled {
+            let resilience = Arc::new(Resilience::new(
+                settings.resilience.to_config()?,
+                Arc::clone(&metrics),
+            ));
+            runner = runner.with_drand(DrandClient::new(
+                &settings.drand.url,
+                &settings.drand.chain_hash,
+                resilience,
+            )?);
+        }
+        let runner = Arc::new(runner);
+        runner.start_workers(settings.queue.workers);
+        if let Some(chain) = chain {
+            let tracker = FulfillmentTracker::new(
+                &settings.chain,
+                chain,
+                Arc::clone(&runner),
+                Arc::clone(&storage),
+                Arc::clone(&metrics),
+            )?
+            .with_tracer(Arc::clone(&tracer));
+            thread::spawn(move || tracker.run());
+        }
+        let tenants = Arc::new(Tenants::from_config(&settings.tenants, Arc::clone(&metrics))?);
+        if tenants.is_enabled() {
+            info!("Serving {} tenant(s): {}", settings.tenants.len(), tenants.ids().join(", "));
+        }
+        if settings.archive.enabled {
+            let archiver = Archiver::from_config(
+                &settings.archive,
+                Arc::clone(&storage),
+                Arc::new(Resilience::new(settings.resilience.to_config()?, Arc::clone(&metrics))),
+                Arc::clone(&metrics),
+            )?.with_tenants(tenants.ids());
+            thread::spawn(move || archiver.run());
+            info!("Archiving attestations to bucket {} every {}", settings.archive.bucket, settings.archive.interval);
+        }
+        let mut reverifier = None;
+        if settings.reverify.enabled {
+            let mut checker = Reverifier::from_config(&settings.reverify, Arc::clone(&storage), Arc::clone(&metrics))
+                .classify(FailureClass::Config)?
+                .with_tenants(tenants.ids());
+            if settings.archive.enabled {
+                checker = checker.with_archiver(Archiver::from_config(
+                    &settings.archive,
+                    Arc::clone(&storage),
+                    Arc::new(Resilience::new(settings.resilience.to_config()?, Arc::clone(&metrics))),
+                    Arc::clone(&metrics),
+                )?);
+            }
+            let checker = Arc::new(checker);
+            let running = Arc::clone(&checker);
+            thread::spawn(move || running.run());
+            info!("Re-verifying stored attestations every {}", settings.reverify.interval);
+            reverifier = Some(checker);
+        }
+        if settings.pool.enabled {
+            let refilling = Arc::clone(&runner);
+            thread::spawn(move || refilling.run_pool());
+            info!("Keeping {} pre-generated {}-byte output(s) ready", settings.pool.size, settings.pool.length);
+        }
+        if settings.timelock.enabled {
+            let releasing = Arc::clone(&runner);
+            let interval = config::parse_duration(&settings.timelock.poll_interval)?;
+            thread::spawn(move || releasing.run_timelocks(interval));
+            info!("Releasing timelocked values, checking every {}", settings.timelock.poll_interval);
+        }
+
+        let mut signals = Signals::new([SIGHUP, SIGTERM, SIGINT])
+            .map_err(|e| format!("Failed to register signal handlers: {}", e))?;
+
+        let resumer = Arc::clone(&runner);
+        thread::spawn(move || match resumer.resume_pending() {
+            Ok(0) => {}
+            Ok(n) => info!("Resumed {} task(s) left over from the previous run", n),
+            Err(e) => warn!("Failed to resume pending tasks: {}", e),
+        });
+
+        let mut revocations = RevocationRegistry::new(Arc::clone(&storage));
+        if let Some(events) = &publisher {
+            revocations = revocations.with_publisher(Arc::clone(events));
+        }
+        let provenance = Arc::new(
+            Provenance::new(&settings.provenance, &settings.operator.address).classify(FailureClass::Config)?,
+        );
+        // Fails early when the configured TEE cannot produce a quote.
+        let statement = provenance.statement(&runner.attester()).classify(FailureClass::Config)?;
+        match &statement.tee {
+            Some(tee) => info!("Running binary {} with a {} quote", statement.binary_sha256, tee.platform),
+            None => info!("Running binary {}", statement.binary_sha256),
+        }
+        let mut server = Server::new(Arc::clone(&config), Arc::clone(&metrics), Arc::clone(&runner))
+            .with_tracer(Arc::clone(&tracer))
+            .with_tenants(tenants)
+            .with_revocations(Arc::new(revocations))
+            .with_provenance(Arc::clone(&provenance))
+            .with_storage(Arc::clone(&storage));
+        #[cfg(feature = "chaos")]
+        {
+            server = server.with_faults(Arc::clone(&faults));
+        }
+        if let Some(keys) = &epochs {
+            server = server.with_epochs(Arc::clone(keys));
+        }
+        if let Some(checker) = &reverifier {
+            server = server.with_reverifier(Arc::clone(checker));
+        }
+        if settings.role.mode.validates() {
+            let validator = Validator::from_config(&settings.role, Arc::clone(&metrics)).classify(FailureClass::Config)?;
+            server = server.with_validator(Arc::new(validator));
+        }
+        if let Some(ttl) = &settings.server.idempotency_ttl {
+            let ttl = config::parse_duration(ttl).classify(FailureClass::Config)?;
+            server = server.with_idempotency(Arc::new(IdempotencyCache::new(Arc::clone(&storage), ttl)));
+        }
+        let mut reputation = None;
+        if settings.reputation.enabled {
+            let scores = Arc::new(Reputation::from_config(&settings.reputation, Arc::clone(&metrics)).classify(FailureClass::Config)?);
+            server = server.with_reputation(Arc::clone(&scores));
+            info!("Scoring other operators (latency target {}, min score {})", settings.reputation.latency_target, settings.reputation.min_score);
+            reputation = Some(scores);
+        }
+        let mut beacon = None;
+        if settings.beacon.enabled {
+            let resilience = Arc::new(Resilience::new(
+                settings.resilience.to_config()?,
+                Arc::clone(&metrics),
+            ));
+            let mut node = BeaconNode::from_config(
+                &settings.beacon,
+                resilience,
+                Arc::clone(&storage),
+                Arc::clone(&metrics),
+            ).classify(FailureClass::Key)?;
+            if let Some(events) = &publisher {
+                node = node.with_publisher(Arc::clone(events));
+            }
+            if let Some(scores) = &reputation {
+                node = node.with_reputation(Arc::clone(scores));
+            }
+            let node = Arc::new(node);
+            server = server.with_beacon(Arc::clone(&node));
+            let looping = Arc::clone(&node);
+            thread::spawn(move || looping.run());
+            beacon = Some(node);
+        }
+        if settings.heartbeat.enabled {
+            let resilience = Arc::new(Resilience::new(
+                settings.resilience.to_config()?,
+                Arc::clone(&metrics),
+            ));
+            let mut emitter = HeartbeatEmitter::new(
+                &settings.heartbeat,
+                &settings.operator.address,
+                Arc::clone(&runner),
+                resilience,
+                metrics,
+            )?;
+            if let Some(node) = &beacon {
+                emitter = emitter.with_beacon(Arc::clone(node));
+            }
+            emitter = emitter.with_provenance(Arc::clone(&provenance));
+            let emitter = Arc::new(emitter);
+            server = server.with_heartbeat(Arc::clone(&emitter));
+            thread::spawn(move || emitter.run());
+        }
+        thread::spawn(move || {
+            if let Err(e) = server.run() {
+                error!("HTTP server stopped: {}", e);
+                finish(mode, "serve", Err(Failure::new(FailureClass::Network, e)));
+            }
+        });
+
+        for signal in signals.forever() {
+            if signal == SIGHUP {
+                // Failures are logged and the running config is kept.
+                let _ = server::reload_config(&config);
+                continue;
+            }
+            break;
+        }
+
+        info!("Shutdown requested; draining in-flight tasks (timeout {:?})", drain_timeout);
+        runner.begin_shutdown();
+        let summary = runner.drain(drain_timeout);
+        storage.flush().classify(FailureClass::Storage)?;
+        if let Err(e) = tracer.flush() {
+            warn!("Failed to export the last spans: {}", e);
+        }
+
+        info!(
+            "Shutdown complete in {:?}: {} task(s) finished during drain, {} persisted for resume",
+            summary.elapsed,
+            summary.finished,
+            summary.persisted.len()
+        );
+        for (task_id, stage) in &summary.persisted {
+            warn!("Task {} persisted at stage {:?}", task_id, stage);
+        }
+        if !summary.is_clean() {
+            return Err(Failure::new(FailureClass::Drain,
+                format!("{} task(s) persisted for resume", summary.persisted.len())));
+        }
+        Ok(json!({
+            "finished": summary.finished,
+            "elapsedMs": summary.elapsed.as_millis() as u64,
+        }))
+    }
+
+    /// The attester `serve` signs with: an enclave-held key when
+    /// `signing.enclave` is set, otherwise a key in process memory.
+    #[cfg(feature = "enclave")]
+    fn attestation_key(signing: &config::SigningConfig) -> Result<RngAttester, String> {
+        let Some(platform) = &signing.enclave else {
+            return RngAttester::new();
+        };
+        let key = EnclaveKey::generate(Platform::parse(platform)?)?;
+        info!("Attestation key generated in a {} enclave", platform);
+        Ok(RngAttester::with_backend(Arc::new(key)))
+    }
+
+    /// Signs with a key in process memory; config validation rejects
+    /// `signing.enclave` in builds without the `enclave` feature.
+    #[cfg(not(feature = "enclave"))]
+    fn attestation_key(_signing: &config::SigningConfig) -> Result<RngAttester, String> {
+        RngAttester::new()
+    }
+
+    /// Dumps stored attestations and task events for auditors.
+    ///
+    /// `export [--config PATH] [--tenant ID] [--from T] [--to T] [--format jsonl|csv] [--output FILE]`
+    /// where `T` is Unix milliseconds or a UTC date such as `2024-01-31T12:00:00Z`;
+    /// `--from` is inclusive, `--to` exclusive. Writes to stdout by default.
+    /// Without `--tenant` the default namespace is exported.
+    fn export(args: &[String], mode: OutputMode) -> Result<Value, Failure> {
+        let config_path = flag_value(args, "--config").unwrap_or(DEFAULT_CONFIG_PATH);
+        let settings = ConfigHandle::load(config_path).classify(FailureClass::Config)?.current();
+        let path = settings.storage.path.as_ref()
+            .ok_or_else(|| Failure::new(FailureClass::Config,
+                "storage.path is not set, so nothing has been persisted to export"))?;
+        let from = flag_value(args, "--from").map(export::parse_time).transpose().classify(FailureClass::Usage)?;
+        let to = flag_value(args, "--to").map(export::parse_time).transpose().classify(FailureClass::Usage)?;
+        let format = Format::parse(flag_value(args, "--format").unwrap_or("jsonl")).classify(FailureClass::Usage)?;
+
+        let storage = FileStorage::open(path).classify(FailureClass::Storage)?;
+        let tenant = flag_value(args, "--tenant");
+        let records = export::collect(&storage, tenant, from, to).classify(FailureClass::Storage)?;
+        let mut out = open_output(args)?;
+        export::write(&records, format, &mut out).classify(FailureClass::Storage)?;
+        if mode == OutputMode::Text {
+            eprintln!("Exported {} record(s)", records.len());
+        }
+        Ok(json!({ "records": records.len() }))
+    }
+
+    /// The `--output FILE` of a command, or stdout.
+    fn open_output(args: &[String]) -> Result<Box<dyn Write>, Failure> {
+        Ok(match flag_value(args, "--output") {
+            Some(file) => Box::new(BufWriter::new(File::create(file)
+                .map_err(|e| Failure::new(FailureClass::Storage, format!("Failed to create {}: {}", file, e)))?)),
+            None => Box::new(io::stdout().lock()),
+        })
+    }
+
+    /// Fetches archived attestations back from object storage.
+    ///
+    /// `archive [--config PATH] [--tenant ID] [--task ID] [--from T] [--to T] [--output FILE]`
+    /// prints the matching rows as `export` JSONL after checking each object
+    /// against its Merkle root; `T` is as for `export`.
+    fn archive(args: &[String], mode: OutputMode) -> Result<Value, Failure> {
+        let config_path = flag_value(args, "--config").unwrap_or(DEFAULT_CONFIG_PATH);
+        let settings = ConfigHandle::load(config_path).classify(FailureClass::Config)?.current();
+        let path = settings.storage.path.as_ref()
+            .ok_or_else(|| Failure::new(FailureClass::Config, "storage.path is not set, so there is no archive index"))?;
+        let task = flag_value(args, "--task");
+        let from = flag_value(args, "--from").map(export::parse_time).transpose().classify(FailureClass::Usage)?;
+        let to = flag_value(args, "--to").map(export::parse_time).transpose().classify(FailureClass::Usage)?;
+
+        let storage: Arc<dyn Storage> = Arc::new(FileStorage::open(path).classify(FailureClass::Storage)?);
+        let objects = archival::find(storage.as_ref(), flag_value(args, "--tenant"), task, from, to).classify(FailureClass::Storage)?;
+        let metrics = Arc::new(Metrics::new());
+        let resilience = Arc::new(Resilience::new(settings.resilience.to_config()?, Arc::clone(&metrics)));
+        let archiver = Archiver::from_config(&settings.archive, storage, resilience, metrics).classify(FailureClass::Config)?;
+        let mut out = open_output(args)?;
+        let mut count = 0;
+        for object in &objects {
+            for row in archiver.fetch(object).classify(FailureClass::Network)? {
+                let at = row["at"].as_u64().unwrap_or(0);
+                let wanted = task.is_none_or(|t| row["task_id"] == t)
+                    && from.is_none_or(|f| at >= f)
+                    && to.is_none_or(|t| at < t);
+                if wanted {
+                    writeln!(out, "{}", row)
+                        .map_err(|e| Failure::new(FailureClass::Storage, format!("Failed to write: {}", e)))?;
+                    count += 1;
+                }
+            }
+        }
+        out.flush().map_err(|e| Failure::new(FailureClass::Storage, format!("Failed to write: {}", e)))?;
+        if mode == OutputMode::Text {
+            eprintln!("Fetched {} record(s) from {} object(s)", count, objects.len());
+        }
+        Ok(json!({ "records": count, "objects": objects.len() }))
+    }
+
+    /// Re-verifies stored attestations, archive roots and revocations once.
+    ///
+    /// `reverify [--config PATH] [--public-key HEX]... [--output FILE]` writes
+    /// the [`operator::reverify::ReverifyReport`] as JSON and fails with a
+    /// `verification` error if it lists discrepancies. `--public-key` adds to
+    /// `reverify.trusted_keys`; archived objects of pruned attestations are
+    /// fetched when `archive` is enabled.
+    fn reverify(args: &[String], mode: OutputMode) -> Result<Value, Failure> {
+        let config_path = flag_value(args, "--config").unwrap_or(DEFAULT_CONFIG_PATH);
+        let settings = ConfigHandle::load(config_path).classify(FailureClass::Config)?.current();
+        let path = settings.storage.path.as_ref()
+            .ok_or_else(|| Failure::new(FailureClass::Config, "storage.path is not set, so nothing has been persisted to check"))?;
+
+        let storage: Arc<dyn Storage> = Arc::new(FileStorage::open(path).classify(FailureClass::Storage)?);
+        let metrics = Arc::new(Metrics::new());
+        let tenants: Vec<String> = settings.tenants.iter().map(|t| t.id.clone()).collect();
+        let mut checker = Reverifier::from_config(&settings.reverify, Arc::clone(&storage), Arc::clone(&metrics))
+            .classify(FailureClass::Config)?
+            .with_tenants(tenants);
+        for pair in args.windows(2).filter(|pair| pair[0] == "--public-key") {
+            let key: [u8; 32] = hex::decode(&pair[1]).ok()
+                .and_then(|key| key.try_into().ok())
+                .ok_or_else(|| Failure::new(FailureClass::Usage, format!("--public-key {} is not a 32-byte hex key", pair[1])))?;
+            let key = VerifyingKey::from_bytes(&key)
+                .map_err(|e| Failure::new(FailureClass::Usage, format!("--public-key {}: {}", pair[1], e)))?;
+            checker = checker.with_trusted_key(key);
+        }
+        if settings.archive.enabled {
+            let resilience = Arc::new(Resilience::new(settings.resilience.to_config()?, Arc::clone(&metrics)));
+            checker = checker.with_archiver(Archiver::from_config(&settings.archive, storage, resilience, metrics)
+                .classify(FailureClass::Config)?);
+        }
+        let report = checker.run_once().classify(FailureClass::Storage)?;
+
+        let mut out = open_output(args)?;
+        writeln!(out, "{}", json!(report))
+            .and_then(|()| out.flush())
+            .map_err(|e| Failure::new(FailureClass::Storage, format!("Failed to write: {}", e)))?;
+        if mode == OutputMode::Text {
+            eprintln!("Checked {} attestation(s), {} archived object(s) and {} revocation(s)",
+                report.attestations, report.archived_objects, report.revocations);
+        }
+        if !report.is_clean() {
+            return Err(Failure::new(FailureClass::Verification,
+                format!("{} discrepancy(ies) found", report.discrepancies.len())));
+        }
+        Ok(json!({
+            "attestations": report.attestations,
+            "archivedObjects": report.archived_objects,
+            "uncheckedObjects": report.unchecked_objects,
+            "revocations": report.revocations,
+        }))
+    }
+
+    /// Writes the operator's state and encrypted keys to one archive, for
+    /// moving it to another host; the operator must be stopped meanwhile.
+    ///
+    /// `snapshot [--config PATH] [--passphrase-file FILE] --output FILE`; the
+    /// passphrase is read from `RNG_SNAPSHOT_PASSPHRASE` without a file. See
+    /// [`operator::snapshot`].
+    fn snapshot(args: &[String], mode: OutputMode) -> Result<Value, Failure> {
+        let config_path = flag_value(args, "--config").unwrap_or(DEFAULT_CONFIG_PATH);
+        let settings = ConfigHandle::load(config_path).classify(FailureClass::Config)?.current();
+        let path = settings.storage.path.as_ref()
+            .ok_or_else(|| Failure::new(FailureClass::Config, "storage.path is not set, so there is no state to snapshot"))?;
+        let output = flag_value(args, "--output")
+            .ok_or_else(|| Failure::new(FailureClass::Usage, "snapshot needs --output FILE"))?;
+        let passphrase = snapshots::passphrase(flag_value(args, "--passphrase-file")).classify(FailureClass::Usage)?;
+
+        let storage = FileStorage::open(path).classify(FailureClass::Storage)?;
+        let file = File::create(output)
+            .map_err(|e| Failure::new(FailureClass::Storage, format!("Failed to create {}: {}", output, e)))?;
+        let manifest = snapshots::create(&storage, &settings, &passphrase, BufWriter::new(file)).classify(FailureClass::Storage)?;
+        if mode == OutputMode::Text {
+            println!("Wrote {}: {} collection(s), {} pending task(s), next counter {}, keys: {}",
+                output, manifest.digests.len(), manifest.pending_tasks, manifest.next_counter,
+                if manifest.keys.is_empty() { "none".to_string() } else { manifest.keys.join(", ") });
+        }
+        Ok(json!(manifest))
+    }
+
+    /// Restores a snapshot into the empty data directory and missing key
+    /// files of this config, after checking it.
+    ///
+    /// `restore [--config PATH] [--passphrase-file FILE] --input FILE`
+    fn restore(args: &[String], mode: OutputMode) -> Result<Value, Failure> {
+        let config_path = flag_value(args, "--config").unwrap_or(DEFAULT_CONFIG_PATH);
+        let settings = ConfigHandle::load(config_path).classify(FailureClass::Config)?.current();
+        let path = settings.storage.path.as_ref()
+            .ok_or_else(|| Failure::new(FailureClass::Config, "storage.path is not set, so there is nowhere to restore to"))?;
+        let input = flag_value(args, "--input")
+            .ok_or_else(|| Failure::new(FailureClass::Usage, "restore needs --input FILE"))?;
+        let passphrase = snapshots::passphrase(flag_value(args, "--passphrase-file")).classify(FailureClass::Usage)?;
+
+        let file = File::open(input)
+            .map_err(|e| Failure::new(FailureClass::Storage, format!("Failed to open {}: {}", input, e)))?;
+        let storage = FileStorage::open(path).classify(FailureClass::Storage)?;
+        let manifest = snapshots::restore(&storage, &settings, &passphrase, io::BufReader::new(file)).classify(FailureClass::Verification)?;
+        if mode == OutputMode::Text {
+            println!("Restored {} collection(s) into {}: {} pending task(s), next counter {}",
+                manifest.digests.len(), path, manifest.pending_tasks, manifest.next_counter);
+        }
+        Ok(json!(manifest))
+    }
+
+    /// Shuffles a deck with an attested random number, for a per-hand proof.
+    ///
+    /// `deck --attestation FILE --hand ID [--size N] [--output FILE]` reads a
+    /// `/task/execute` response and writes the [`card_deck::DeckProof`] of hand
+    /// `ID` of an `N`-card deck (default 52).
+    fn deck(args: &[String], mode: OutputMode) -> Result<Value, Failure> {
+        let file = flag_value(args, "--attestation")
+            .ok_or_else(|| Failure::new(FailureClass::Usage, "deck needs --attestation FILE"))?;
+        let hand = flag_value(args, "--hand")
+            .ok_or_else(|| Failure::new(FailureClass::Usage, "deck needs --hand ID"))?;
+        let size = match flag_value(args, "--size").map(str::parse::<u16>) {
+            None => card_deck::STANDARD_DECK,
+            Some(Ok(size)) if size >= 2 => size,
+            Some(_) => return Err(Failure::new(FailureClass::Usage, "--size needs a card count from 2 to 65535")),
+        };
+
+        let (outcome, attestation, public_key) = read_outcome(file)?;
+        let proof = card_deck::deal(&public_key, &attestation, hand, size).classify(FailureClass::Verification)?;
+
+        let mut out = open_output(args)?;
+        writeln!(out, "{}", json!(proof))
+            .and_then(|()| out.flush())
+            .map_err(|e| Failure::new(FailureClass::Storage, format!("Failed to write: {}", e)))?;
+        if mode == OutputMode::Text {
+            eprintln!("Shuffled {} cards for hand {} of task {}", size, hand, outcome.task_id);
+        }
+        Ok(json!({ "hand": hand, "deckSize": size, "attestation": proof.attestation }))
+    }
+
+    /// Derives a prime from an attested random number, for verifiable parameters.
+    ///
+    /// `prime --attestation FILE --bits N [--output FILE]` reads a
+    /// `/task/execute` response and writes the [`PrimeTrail`] of the `N`-bit
+    /// prime its random number yields.
+    fn prime(args: &[String], mode: OutputMode) -> Result<Value, Failure> {
+        let file = flag_value(args, "--attestation")
+            .ok_or_else(|| Failure::new(FailureClass::Usage, "prime needs --attestation FILE"))?;
+        let bits = flag_value(args, "--bits")
+            .ok_or_else(|| Failure::new(FailureClass::Usage, "prime needs --bits N"))?
+            .parse::<u64>().ok()
+            .filter(|bits| (primes::MIN_PRIME_BITS..=primes::MAX_PRIME_BITS).contains(bits))
+            .ok_or_else(|| Failure::new(FailureClass::Usage, format!(
+                "--bits needs a size from {} to {}", primes::MIN_PRIME_BITS, primes::MAX_PRIME_BITS)))?;
+        let (outcome, attestation, public_key) = read_outcome(file)?;
+        let trail = PrimeTrail::from_attestation(&public_key, &attestation, bits).classify(FailureClass::Verification)?;
+
+        let mut out = open_output(args)?;
+        writeln!(out, "{}", json!(trail))
+            .and_then(|()| out.flush())
+            .map_err(|e| Failure::new(FailureClass::Storage, format!("Failed to write: {}", e)))?;
+        if mode == OutputMode::Text {
+            eprintln!("Found a {}-bit prime for task {} after {} rejected candidate(s)", bits, outcome.task_id, trail.rejected);
+        }
+        Ok(json!({ "bits": bits, "rejected": trail.rejected }))
+    }
+
+    /// Reads a `/task/execute` response with its attestation and public key.
+    fn read_outcome(file: &str) -> Result<(TaskOutcome, Attestation, VerifyingKey), Failure> {
+        let raw = std::fs::read_to_string(file)
+            .map_err(|e| Failure::new(FailureClass::Usage, format!("Failed to read {}: {}", file, e)))?;
+        let outcome: TaskOutcome = serde_json::from_str(&raw)
+            .map_err(|e| Failure::new(FailureClass::Usage, format!("{} is not a task outcome: {}", file, e)))?;
+        let attestation = outcome.to_attestation().classify(FailureClass::Usage)?;
+        let public_key = hex::decode(&outcome.public_key).ok()
+            .and_then(|key| <[u8; 32]>::try_from(key).ok())
+            .and_then(|key| VerifyingKey::from_bytes(&key).ok())
+            .ok_or_else(|| Failure::new(FailureClass::Usage, format!("{} has an invalid public key", file)))?;
+        Ok((outcome, attestation, public_key))
+    }
+
+    /// Measures attestation throughput with the configured signing settings.
+    ///
+    /// `bench [--config PATH] [--count N] [--threads N]` attests `N` payloads
+    /// from `--threads` callers against the configured storage (or memory).
+    fn bench(args: &[String], mode: OutputMode) -> Result<Value, Failure> {
+        let config_path = flag_value(args, "--config").unwrap_or(DEFAULT_CONFIG_PATH);
+        let settings = ConfigHandle::load(config_path).classify(FailureClass::Config)?.current();
+        let count: usize = flag_value(args, "--count").unwrap_or("10000").parse()
+            .map_err(|e| Failure::new(FailureClass::Usage, format!("Invalid --count: {}", e)))?;
+        let threads: usize = flag_value(args, "--threads").unwrap_or("8").parse()
+            .map_err(|e| Failure::new(FailureClass::Usage, format!("Invalid --threads: {}", e)))?;
+        if threads == 0 {
+            return Err(Failure::new(FailureClass::Usage, "--threads must be at least 1"));
+        }
+
+        let storage: Arc<dyn Storage> = match &settings.storage.path {
+            Some(path) => Arc::new(FileStorage::open(path).classify(FailureClass::Storage)?),
+            None => Arc::new(MemoryStorage::new()),
+        };
+        let attester = Arc::new(RngAttester::new().classify(FailureClass::Key)?
+            .with_nonce_store(storage).classify(FailureClass::Storage)?);
+        let signer = match settings.signing.batching.enabled {
+            true => Some(Arc::new(BatchSigner::start(&settings.signing.batching, Arc::new(Metrics::new()))?)),
+            false => None,
+        };
+
+        let started = Instant::now();
+        let handles: Vec<_> = (0..threads).map(|i| {
+            let attester = Arc::clone(&attester);
+            let signer = signer.clone();
+            let share = count / threads + usize::from(i < count % threads);
+            thread::spawn(move || -> Result<u128, String> {
+                let mut worst = 0;
+                for _ in 0..share {
+                    let payload = AttestationPayload::new(vec![0u8; 32]);
+                    let begun = Instant::now();
+                    match &signer {
+                        Some(signer) => signer.sign(Arc::clone(&attester), payload)?,
+                        None => attester.attest_payload(payload)?,
+                    };
+                    worst = worst.max(begun.elapsed().as_micros());
+                }
+                Ok(worst)
+            })
+        }).collect();
+        let mut worst = 0;
+        for handle in handles {
+            let joined = handle.join().map_err(|_| "Benchmark thread panicked".to_string())?;
+            worst = worst.max(joined.classify(FailureClass::Key)?);
+        }
+        let elapsed = started.elapsed().as_secs_f64();
+        if mode == OutputMode::Text {
+            println!("{} attestations in {:.3}s from {} thread(s), batching {}",
+                count, elapsed, threads, if signer.is_some() { "on" } else { "off" });
+            println!("{:.0} attestations/s, worst latency {:.3}ms", count as f64 / elapsed, worst as f64 / 1000.0);
+        }
+        Ok(json!({
+            "attestations": count,
+            "threads": threads,
+            "batching": signer.is_some(),
+            "seconds": elapsed,
+            "perSecond": count as f64 / elapsed,
+            "worstLatencyMs": worst as f64 / 1000.0,
+        }))
+    }
+
+    /// Runs an in-process devnet of simulated operators; see
+    /// [`operator::devnet`].
+    ///
+    /// `devnet [--operators N] [--quorum Q] [--requests R] [--length BYTES] [--faulty K]
+    /// [--loss RATE] [--timeout DURATION] [--output FILE]` prints how each request went;
+    /// `--quorum` defaults to a majority of the nodes other than the performer.
+    /// `--output` writes the accepted outcomes as JSONL, for `rng-verify`. Fails when an
+    /// accepted outcome does not verify or a request of an honest performer was not accepted.
+    fn devnet(args: &[String], mode: OutputMode) -> Result<Value, Failure> {
+        let number = |flag: &str, default: usize| -> Result<usize, Failure> {
+            match flag_value(args, flag) {
+                Some(value) => value.parse()
+                    .map_err(|e| Failure::new(FailureClass::Usage, format!("Invalid {}: {}", flag, e))),
+                None => Ok(default),
+            }
+        };
+        let defaults = DevnetConfig::default();
+        let operators = number("--operators", defaults.operators)?;
+        let devnet_config = DevnetConfig {
+            operators,
+            quorum: number("--quorum", operators.saturating_sub(1) / 2 + 1)?,
+            requests: number("--requests", defaults.requests)?,
+            length: number("--length", defaults.length)?,
+            faulty: number("--faulty", defaults.faulty)?,
+            loss: match flag_value(args, "--loss") {
+                Some(value) => value.parse()
+                    .map_err(|e| Failure::new(FailureClass::Usage, format!("Invalid --loss: {}", e)))?,
+                None => defaults.loss,
+            },
+            timeout: match flag_value(args, "--timeout") {
+                Some(value) => config::parse_duration(value).classify(FailureClass::Usage)?,
+                None => defaults.timeout,
+            },
+        };
+        devnet_config.validate().classify(FailureClass::Usage)?;
+
+        let devnet = Devnet::start(devnet_config).classify(FailureClass::Key)?;
+        if mode == OutputMode::Text {
+            for operator in devnet.operators() {
+                println!("operator {} {}{}", operator.id, operator.public_key,
+                    if operator.faulty { " (faulty)" } else { "" });
+            }
+        }
+        let report = devnet.run();
+        if mode == OutputMode::Text {
+            for request in &report.requests {
+                let verdict = match (&request.error, request.accepted, &request.verification_error) {
+                    (Some(e), _, _) => format!("failed: {}", e),
+                    (None, true, None) => "accepted and verified".to_string(),
+                    (None, true, Some(e)) => format!("ACCEPTED BUT INVALID: {}", e),
+                    (None, false, _) => "not accepted".to_string(),
+                };
+                println!("{} by operator {}: {} ({} approval(s), quorum {}, {}ms)", request.task_id,
+                    request.performer, verdict, request.approvals.len(), report.quorum, request.latency_ms);
+                for rejection in &request.rejections {
+                    println!("    {}", rejection);
+                }
+            }
+            println!("{} of {} request(s) accepted, {} invalid accepted, {} stalled",
+                report.accepted(), report.requests.len(), report.invalid(), report.stalled());
+        }
+
+        if let Some(file) = flag_value(args, "--output") {
+            let mut out = open_output(args)?;
+            for outcome in report.requests.iter().filter_map(|r| r.outcome.as_ref()) {
+                writeln!(out, "{}", json!(outcome))
+                    .map_err(|e| Failure::new(FailureClass::Storage, format!("Failed to write {}: {}", file, e)))?;
+            }
+            out.flush().map_err(|e| Failure::new(FailureClass::Storage, format!("Failed to write {}: {}", file, e)))?;
+        }
+        if report.invalid() > 0 || report.stalled() > 0 {
+            return Err(Failure::new(FailureClass::Verification, format!(
+                "{} accepted outcome(s) failed verification and {} request(s) of honest operators were not accepted",
+                report.invalid(), report.stalled())));
+        }
+        Ok(json!(report))
+    }
+
+    fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+        args.iter()
+            .position(|a| a == flag)
+            .and_then(|i| args.get(i + 1))
+            .map(String::as_str)
+    }
+
+    fn run_demo(mode: OutputMode) -> Result<Value, Failure> {
+        // The narration is for people; JSON mode prints only the result.
+        macro_rules! say {
+            ($($arg:tt)*) => { if mode == OutputMode::Text { println!($($arg)*); } };
+        }
+        say!("Starting RNG Operator (Rust Backend)...");
 
      
         let rng_performer = RngPerformer::new();
-        println!("RNG Performer initialized.");
+        say!("RNG Performer initialized.");
 
  
         let rng_attester = RngAttester::new()
-            .map_err(|e| format!("Failed to initialize RNG Attester: {}", e))?;
-        println!("RNG Attester initialized and key pair generated.");
+            .map_err(|e| Failure::new(FailureClass::Key, format!("Failed to initialize RNG Attester: {}", e)))?;
+        say!("RNG Attester initialized and key pair generated.");
 
         let public_key = rng_attester.get_public_key();
-        println!("Attester's Public Key (hex): {}", hex::encode(public_key.to_bytes()));
+        say!("Attester's Public Key (hex): {}", hex::encode(public_key.to_bytes()));
 
    
         let random_number_length = 32; // Bytes
         let raw_random_number = rng_performer.generate_random_number(random_number_length)
-            .map_err(|e| format!("Failed to generate random number: {}", e))?;
-        println!("\nGenerated Raw Random Number (hex): {}", hex::encode(&raw_random_number));
+            .map_err(|e| Failure::new(FailureClass::Entropy, format!("Failed to generate random number: {}", e)))?;
+        say!("\nGenerated Raw Random Number (hex): {}", hex::encode(&raw_random_number));
 
       
         let (_original_random_number, salt, signature) = rng_attester.attest(&raw_random_number)
-            .map_err(|e| format!("Failed to attest to random number: {}", e))?;
+            .map_err(|e| Failure::new(FailureClass::Key, format!("Failed to attest to random number: {}", e)))?;
 
-        println!("Generated Salt (hex): {}", hex::encode(&salt));
-        println!("Generated Signature (hex): {}", hex::encode(signature.to_bytes()));
+        say!("Generated Salt (hex): {}", hex::encode(&salt));
+        say!("Generated Signature (hex): {}", hex::encode(signature.to_bytes()));
 
 
-        println!("\nAttempting to verify attestation...");
+        say!("\nAttempting to verify attestation...");
         match RngAttester::verify_attestation(public_key, &raw_random_number, &salt, &signature) {
             Ok(()) => {
-                println!("Verification Result: SUCCESS!");
-                println!("Attestation successfully verified! The random number and salt are authentic.");
+                say!("Verification Result: SUCCESS!");
+                say!("Attestation successfully verified! The random number and salt are authentic.");
             }
             Err(e) => {
-                println!("Verification Result: FAILED!");
-                println!("Attestation verification FAILED! {}", e);
-                return Err(e); 
+                say!("Verification Result: FAILED!");
+                say!("Attestation verification FAILED! {}", e);
+                return Err(Failure::new(FailureClass::Verification, e)); 
             }
         }
 
-        println!("\nRNG Operator finished successfully.");
-        Ok(()) 
+        say!("\nRNG Operator finished successfully.");
+        Ok(json!({
+            "publicKey": hex::encode(public_key.to_bytes()),
+            "randomNumber": hex::encode(&raw_random_number),
+            "salt": hex::encode(&salt),
+            "signature": hex::encode(signature.to_bytes()),
+            "verified": true,
+        }))
     }
     
\ No newline at end of file
@@ -0,0 +1,203 @@
+// src/merkle.rs
+
+//! Binary Merkle trees over SHA-256, as specified for Certificate
+//! Transparency (RFC 6962 §2.1): leaves are hashed as `SHA-256(0x00 || data)`,
+//! interior nodes as `SHA-256(0x01 || left || right)`, and a tree of `n`
+//! leaves splits at the largest power of two below `n`. The domain bytes keep
+//! a leaf from ever being passed off as an interior node. An inclusion proof
+//! is the audit path of §2.1.1.
+
+use sha2::{Digest, Sha256};
+
+/// Hash of one leaf.
+pub fn leaf_hash(data: &[u8]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update([0x00])
+        .chain_update(data)
+        .finalize()
+        .into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update([0x01])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+/// Root over already hashed leaves; the root of no leaves is `SHA-256("")`.
+pub fn root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    match leaves.len() {
+        0 => Sha256::digest([]).into(),
+        1 => leaves[0],
+        n => {
+            let split = split_point(n);
+            node_hash(&root(&leaves[..split]), &root(&leaves[split..]))
+        }
+    }
+}
+
+/// Audit path of leaf `index` (RFC 6962 §2.1.1), from the leaf up.
+pub fn inclusion_proof(leaves: &[[u8; 32]], index: usize) -> Vec<[u8; 32]> {
+    if leaves.len() <= 1 {
+        return Vec::new();
+    }
+    let split = split_point(leaves.len());
+    let (mut path, sibling) = if index < split {
+        (
+            inclusion_proof(&leaves[..split], index),
+            root(&leaves[split..]),
+        )
+    } else {
+        (
+            inclusion_proof(&leaves[split..], index - split),
+            root(&leaves[..split]),
+        )
+    };
+    path.push(sibling);
+    path
+}
+
+/// Whether `proof` places `leaf` at `index` of a `size`-leaf tree with
+/// `root`, as verified in RFC 9162 §2.1.3.2.
+pub fn verify_inclusion(
+    leaf: &[u8; 32],
+    index: u64,
+    size: u64,
+    proof: &[[u8; 32]],
+    root: &[u8; 32],
+) -> bool {
+    if index >= size {
+        return false;
+    }
+    let (mut fnode, mut snode) = (index, size - 1);
+    let mut hash = *leaf;
+    for sibling in proof {
+        if snode == 0 {
+            return false;
+        }
+        if fnode & 1 == 1 || fnode == snode {
+            hash = node_hash(sibling, &hash);
+            while fnode & 1 == 0 && fnode != 0 {
+                fnode >>= 1;
+                snode >>= 1;
+            }
+        } else {
+            hash = node_hash(&hash, sibling);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    snode == 0 && hash == *root
+}
+
+/// Largest power of two strictly below `n` (for `n >= 2`).
+fn split_point(n: usize) -> usize {
+    let mut k = 1;
+    while k * 2 < n {
+        k *= 2;
+    }
+    k
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The leaves and roots of the RFC 6962 reference test vectors.
+    const LEAVES: [&str; 8] = [
+        "",
+        "00",
+        "10",
+        "2021",
+        "3031",
+        "40414243",
+        "5051525354555657",
+        "606162636465666768696a6b6c6d6e6f",
+    ];
+    const ROOTS: [&str; 8] = [
+        "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+        "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+        "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+        "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+        "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+        "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+        "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+        "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+    ];
+
+    fn leaves() -> Vec<[u8; 32]> {
+        LEAVES
+            .iter()
+            .map(|leaf| leaf_hash(&hex::decode(leaf).unwrap()))
+            .collect()
+    }
+
+    fn path(proof: &[[u8; 32]]) -> Vec<String> {
+        proof.iter().map(hex::encode).collect()
+    }
+
+    #[test]
+    fn rfc6962_roots() {
+        assert_eq!(
+            hex::encode(root(&[])),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        let leaves = leaves();
+        for (size, expected) in (1..=8).zip(ROOTS) {
+            assert_eq!(
+                hex::encode(root(&leaves[..size])),
+                expected,
+                "size {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn rfc6962_audit_paths() {
+        let leaves = leaves();
+        assert_eq!(
+            path(&inclusion_proof(&leaves, 0)),
+            [
+                "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+            ]
+        );
+        assert_eq!(
+            path(&inclusion_proof(&leaves, 5)),
+            [
+                "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+                "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            ]
+        );
+        assert_eq!(path(&inclusion_proof(&leaves[..3], 2)), [ROOTS[1]]);
+        assert!(inclusion_proof(&leaves[..1], 0).is_empty());
+    }
+
+    #[test]
+    fn every_proof_verifies_only_where_it_belongs() {
+        let leaves = leaves();
+        for size in 1..=leaves.len() {
+            let tree = &leaves[..size];
+            let root = root(tree);
+            for (index, leaf) in tree.iter().enumerate() {
+                let proof = inclusion_proof(tree, index);
+                let (index, size) = (index as u64, size as u64);
+                assert!(verify_inclusion(leaf, index, size, &proof, &root));
+                assert!(!verify_inclusion(leaf, index, size, &proof, &[0; 32]));
+                assert!(!verify_inclusion(&[0; 32], index, size, &proof, &root));
+                assert!(!verify_inclusion(leaf, size, size, &proof, &root));
+                if size > 1 {
+                    let other = (index + 1) % size;
+                    assert!(!verify_inclusion(leaf, other, size, &proof, &root));
+                    assert!(!verify_inclusion(leaf, index, size, &proof[1..], &root));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+// src/metrics.rs
+
+//! A small in-process metrics registry.
+//!
+//! Counters, gauges and summaries are keyed by a metric name plus an ordered
+//! set of label pairs, and can be rendered in the Prometheus text exposition
+//! format. Summary quantiles are over the latest [`SUMMARY_WINDOW`] samples.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+
+/// Samples a summary keeps for its quantiles.
+pub const SUMMARY_WINDOW: usize = 1024;
+/// Quantiles rendered for every summary.
+const QUANTILES: [f64; 3] = [0.5, 0.9, 0.99];
+
+/// Identifies a single time series: metric name plus its label pairs.
+type SeriesKey = (String, Vec<(String, String)>);
+
+/// Recent samples of one summary, and the totals over all of them.
+#[derive(Default)]
+struct Summary {
+    recent: VecDeque<f64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Summary {
+    fn quantile(&self, q: f64) -> Option<f64> {
+        if self.recent.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f64> = self.recent.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let rank = (q * (sorted.len() - 1) as f64).round() as usize;
+        Some(sorted[rank])
+    }
+}
+
+/// `Metrics` holds every counter and gauge recorded by the operator.
+///
+/// It is cheap to share behind an `Arc` and all methods take `&self`.
+#[derive(Default)]
+pub struct Metrics {
+    counters: Mutex<BTreeMap<SeriesKey, u64>>,
+    gauges: Mutex<BTreeMap<SeriesKey, f64>>,
+    summaries: Mutex<BTreeMap<SeriesKey, Summary>>,
+}
+
+impl Metrics {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `value` to the counter identified by `name` and `labels`.
+    pub fn inc_counter(&self, name: &str, labels: &[(&str, &str)], value: u64) {
+        let mut counters = self.counters.lock().expect("metrics lock poisoned");
+        *counters.entry(series_key(name, labels)).or_insert(0) += value;
+    }
+
+    /// Sets the gauge identified by `name` and `labels` to `value`.
+    pub fn set_gauge(&self, name: &str, labels: &[(&str, &str)], value: f64) {
+        let mut gauges = self.gauges.lock().expect("metrics lock poisoned");
+        gauges.insert(series_key(name, labels), value);
+    }
+
+    /// Adds a `value` sample to the summary identified by `name` and `labels`.
+    pub fn observe(&self, name: &str, labels: &[(&str, &str)], value: f64) {
+        let mut summaries = self.summaries.lock().expect("metrics lock poisoned");
+        let summary = summaries.entry(series_key(name, labels)).or_default();
+        if summary.recent.len() == SUMMARY_WINDOW {
+            summary.recent.pop_front();
+        }
+        summary.recent.push_back(value);
+        summary.sum += value;
+        summary.count += 1;
+    }
+
+    /// Returns the current value of a counter, or `0` if it was never touched.
+    pub fn counter(&self, name: &str, labels: &[(&str, &str)]) -> u64 {
+        let counters = self.counters.lock().expect("metrics lock poisoned");
+        counters
+            .get(&series_key(name, labels))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns the current value of a gauge, if it has been set.
+    pub fn gauge(&self, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+        let gauges = self.gauges.lock().expect("metrics lock poisoned");
+        gauges.get(&series_key(name, labels)).copied()
+    }
+
+    /// Returns quantile `q` (0 to 1) of the recent samples of a summary, if
+    /// it has any.
+    pub fn quantile(&self, name: &str, labels: &[(&str, &str)], q: f64) -> Option<f64> {
+        let summaries = self.summaries.lock().expect("metrics lock poisoned");
+        summaries.get(&series_key(name, labels))?.quantile(q)
+    }
+
+    /// Renders all series in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        {
+            let counters = self.counters.lock().expect("metrics lock poisoned");
+            for ((name, labels), value) in counters.iter() {
+                out.push_str(&format!("{}{} {}\n", name, render_labels(labels), value));
+            }
+        }
+        {
+            let gauges = self.gauges.lock().expect("metrics lock poisoned");
+            for ((name, labels), value) in gauges.iter() {
+                out.push_str(&format!("{}{} {}\n", name, render_labels(labels), value));
+            }
+        }
+        {
+            let summaries = self.summaries.lock().expect("metrics lock poisoned");
+            for ((name, labels), summary) in summaries.iter() {
+                for q in QUANTILES {
+                    let Some(value) = summary.quantile(q) else {
+                        continue;
+                    };
+                    let mut labels = labels.clone();
+                    labels.push(("quantile".to_string(), q.to_string()));
+                    out.push_str(&format!("{}{} {}\n", name, render_labels(&labels), value));
+                }
+                let labels = render_labels(labels);
+                out.push_str(&format!("{}_sum{} {}\n", name, labels, summary.sum));
+                out.push_str(&format!("{}_count{} {}\n", name, labels, summary.count));
+            }
+        }
+        out
+    }
+}
+
+fn series_key(name: &str, labels: &[(&str, &str)]) -> SeriesKey {
+    let mut labels: Vec<(String, String)> = labels
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    labels.sort();
+    (name.to_string(), labels)
+}
+
+fn render_labels(labels: &[(String, String)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, v.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
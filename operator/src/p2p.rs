@@ -0,0 +1,212 @@
+// src/p2p.rs
+
+//! Authenticated peer-to-peer messaging between operators.
+//!
+//! Messages travel as signed [`Envelope`]s: the body is an opaque string
+//! (JSON in practice) and the signature is a Schnorr signature by the
+//! sender's PVSS key, so a relayed envelope can be checked by anyone who
+//! knows the committee. Outbound delivery goes through a [`Transport`];
+//! [`HttpTransport`] posts envelopes to `POST {peer}/p2p/message` through
+//! the shared [`Resilience`] layer. Inbound envelopes are handed to
+//! [`P2pNode::receive`] by the HTTP server and queued until a consumer picks
+//! them up with [`P2pNode::wait`].
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::pvss::{self, Bytes32, KeyPair, SchnorrSignature};
+use crate::resilience::{CallError, Resilience};
+
+/// A signed message from one operator to the others.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Envelope {
+    pub sender: u32,
+    pub body: String,
+    pub signature: SchnorrSignature,
+}
+
+impl Envelope {
+    /// Signs `body` as `sender`.
+    pub fn seal(sender: u32, key: &KeyPair, body: String) -> Self {
+        let signature = key.sign(&signed_bytes(sender, &body));
+        Envelope {
+            sender,
+            body,
+            signature,
+        }
+    }
+
+    /// Checks the signature against the sender's public key.
+    pub fn verify(&self, public_key: &Bytes32) -> Result<(), String> {
+        pvss::verify_signature(
+            public_key,
+            &signed_bytes(self.sender, &self.body),
+            &self.signature,
+        )
+        .map_err(|e| format!("Envelope from {}: {}", self.sender, e))
+    }
+}
+
+fn signed_bytes(sender: u32, body: &str) -> Vec<u8> {
+    let mut data = Vec::with_capacity(4 + body.len());
+    data.extend_from_slice(&sender.to_be_bytes());
+    data.extend_from_slice(body.as_bytes());
+    data
+}
+
+/// Another operator reachable over the network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Peer {
+    pub id: u32,
+    /// Base URL of the peer's HTTP server, e.g. `http://operator-2:4003`.
+    pub url: String,
+    pub public_key: Bytes32,
+}
+
+/// Delivers envelopes to a single peer.
+pub trait Transport: Send + Sync {
+    fn send(&self, peer: &Peer, envelope: &Envelope) -> Result<(), String>;
+}
+
+/// Sends envelopes as JSON over HTTP, with retries and per-peer breakers.
+pub struct HttpTransport {
+    agent: ureq::Agent,
+    resilience: Arc<Resilience>,
+}
+
+impl HttpTransport {
+    pub fn new(resilience: Arc<Resilience>) -> Self {
+        HttpTransport {
+            agent: ureq::AgentBuilder::new().build(),
+            resilience,
+        }
+    }
+}
+
+impl Transport for HttpTransport {
+    fn send(&self, peer: &Peer, envelope: &Envelope) -> Result<(), String> {
+        let url = format!("{}/p2p/message", peer.url.trim_end_matches('/'));
+        let body = serde_json::to_string(envelope)
+            .map_err(|e| format!("Failed to encode envelope: {}", e))?;
+        self.resilience
+            .call(&format!("peer-{}", peer.id), |remaining| {
+                match self
+                    .agent
+                    .post(&url)
+                    .timeout(remaining)
+                    .set("Content-Type", "application/json")
+                    .send_string(&body)
+                {
+                    Ok(_) => Ok(()),
+                    Err(ureq::Error::Status(code, _)) if code < 500 && code != 429 => {
+                        Err(CallError::Permanent(format!("HTTP {}", code)))
+                    }
+                    Err(e) => Err(CallError::Transient(e.to_string())),
+                }
+            })
+    }
+}
+
+/// This operator's endpoint in the peer-to-peer layer.
+pub struct P2pNode {
+    id: u32,
+    key: KeyPair,
+    peers: Vec<Peer>,
+    transport: Arc<dyn Transport>,
+    inbox: Mutex<Vec<Envelope>>,
+    arrived: Condvar,
+}
+
+impl P2pNode {
+    /// Creates a node for `id`; `peers` may include this node itself.
+    pub fn new(id: u32, key: KeyPair, peers: Vec<Peer>, transport: Arc<dyn Transport>) -> Self {
+        P2pNode {
+            id,
+            key,
+            peers,
+            transport,
+            inbox: Mutex::new(Vec::new()),
+            arrived: Condvar::new(),
+        }
+    }
+
+    /// Returns this node's id.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns this node's key pair.
+    pub fn key(&self) -> &KeyPair {
+        &self.key
+    }
+
+    /// Returns the public key registered for `id`.
+    pub fn public_key(&self, id: u32) -> Option<&Bytes32> {
+        self.peers
+            .iter()
+            .find(|p| p.id == id)
+            .map(|p| &p.public_key)
+    }
+
+    /// Signs `body` and sends it to every peer, including this node.
+    ///
+    /// Delivery to remote peers happens on background threads; failures are
+    /// logged once the resilience layer gives up.
+    pub fn broadcast(self: &Arc<Self>, body: String) {
+        let envelope = Envelope::seal(self.id, &self.key, body);
+        for peer in &self.peers {
+            if peer.id == self.id {
+                continue;
+            }
+            let node = Arc::clone(self);
+            let peer = peer.clone();
+            let envelope = envelope.clone();
+            thread::spawn(move || {
+                if let Err(e) = node.transport.send(&peer, &envelope) {
+                    warn!("Failed to deliver message to peer {}: {}", peer.id, e);
+                }
+            });
+        }
+        self.push(envelope);
+    }
+
+    /// Accepts an envelope from the network after checking its sender.
+    pub fn receive(&self, envelope: Envelope) -> Result<(), String> {
+        let key = self
+            .public_key(envelope.sender)
+            .ok_or_else(|| format!("Unknown peer {}", envelope.sender))?;
+        envelope.verify(key)?;
+        self.push(envelope);
+        Ok(())
+    }
+
+    fn push(&self, envelope: Envelope) {
+        self.inbox
+            .lock()
+            .expect("p2p inbox lock poisoned")
+            .push(envelope);
+        self.arrived.notify_all();
+    }
+
+    /// Waits until at least one envelope is queued or `deadline` passes, then
+    /// takes everything queued.
+    pub fn wait(&self, deadline: Instant) -> Vec<Envelope> {
+        let mut inbox = self.inbox.lock().expect("p2p inbox lock poisoned");
+        while inbox.is_empty() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::ZERO {
+                break;
+            }
+            inbox = self
+                .arrived
+                .wait_timeout(inbox, remaining)
+                .expect("p2p inbox lock poisoned")
+                .0;
+        }
+        std::mem::take(&mut *inbox)
+    }
+}
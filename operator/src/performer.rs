@@ -3,14 +3,72 @@
 //! This module provides the `RngPerformer` responsible for generating
 //! cryptographically secure random numbers.
 
+use num_bigint::BigUint;
 use rand::RngCore; // Only RngCore is needed here
 use rand::rngs::OsRng; // Operating system's cryptographically secure random number generator
+use sha2::{Digest, Sha256};
+
+use crate::attester::{AttestationPayload, RngAttester};
+use crate::primes::PrimeTrail;
+use crate::stream::{RandomStream, MAX_STREAM_CHUNKS};
+
+/// Domain separation tag for mixing client-contributed entropy.
+pub const CLIENT_MIX_DOMAIN: &[u8] = b"othentic-rng/client-mix/v1";
+
+/// Largest value the performer generates in one piece unless configured otherwise.
+pub const DEFAULT_MAX_LENGTH: usize = 64 * 1024;
+
+/// Largest stream the performer generates unless configured otherwise.
+pub const DEFAULT_MAX_STREAM_LENGTH: usize = 16 * 1024 * 1024;
+
+/// The range of lengths, in bytes, a caller may ask for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthPolicy {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl LengthPolicy {
+    /// A policy admitting `min..=max` bytes; `min` must be at least 1.
+    pub fn new(min: usize, max: usize) -> Result<Self, String> {
+        if min == 0 || min > max {
+            return Err(format!("Invalid length policy {}..={}.", min, max));
+        }
+        Ok(LengthPolicy { min, max })
+    }
+
+    /// Whether `length` is within the policy.
+    pub fn check(&self, length: usize) -> Result<(), String> {
+        if length < self.min || length > self.max {
+            return Err(format!(
+                "Length must be between {} and {} bytes, not {}.",
+                self.min, self.max, length
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether every length `other` admits is admitted here too.
+    pub fn contains(&self, other: &LengthPolicy) -> bool {
+        self.min <= other.min && other.max <= self.max
+    }
+}
+
+impl Default for LengthPolicy {
+    fn default() -> Self {
+        LengthPolicy {
+            min: 1,
+            max: DEFAULT_MAX_LENGTH,
+        }
+    }
+}
 
 /// `RngPerformer` is a struct that encapsulates the random number generation logic.
-/// It currently holds no state, but could be extended for configuration (e.g., specific RNG source).
+/// It holds the length policy requests are checked against, so a caller cannot
+/// make it allocate, hash and sign arbitrarily large buffers.
 pub struct RngPerformer {
-    // In a more complex scenario, this could hold configurations
-    // like a specific RNG instance or default random number size.
+    policy: LengthPolicy,
+    max_stream_length: usize,
 }
 
 impl RngPerformer {
@@ -19,7 +77,38 @@ impl RngPerformer {
     /// # Returns
     /// A new `RngPerformer` instance.
     pub fn new() -> Self {
-        RngPerformer {}
+        RngPerformer {
+            policy: LengthPolicy::default(),
+            max_stream_length: DEFAULT_MAX_STREAM_LENGTH,
+        }
+    }
+
+    /// Admits request lengths within `policy`; larger values are only
+    /// produced as streams.
+    pub fn with_length_policy(mut self, policy: LengthPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Caps the total length of a stream at `max` bytes.
+    pub fn with_max_stream_length(mut self, max: usize) -> Self {
+        self.max_stream_length = max;
+        self
+    }
+
+    /// The lengths a request may ask for.
+    pub fn length_policy(&self) -> LengthPolicy {
+        self.policy
+    }
+
+    /// The largest stream the performer generates.
+    pub fn max_stream_length(&self) -> usize {
+        self.max_stream_length
+    }
+
+    /// Checks a requested length against the policy.
+    pub fn check_length(&self, length: usize) -> Result<(), String> {
+        self.policy.check(length)
     }
 
     /// Generates a cryptographically secure random byte vector of the specified length.
@@ -33,11 +122,20 @@ impl RngPerformer {
     /// # Returns
     /// A `Result` containing:
     /// - `Ok(Vec<u8>)` if the random number was generated successfully.
-    /// - `Err(String)` if an error occurred during generation (e.g., `OsRng` failure).
+    /// - `Err(String)` if the length is zero or above the policy's maximum, or if an
+    ///   error occurred during generation (e.g., `OsRng` failure).
     pub fn generate_random_number(&self, length: usize) -> Result<Vec<u8>, String> {
         if length == 0 {
             return Err("Length must be a positive integer.".to_string());
         }
+        // Internal draws such as seeds may be shorter than the policy's
+        // minimum; only the maximum guards the allocation.
+        if length > self.policy.max {
+            return Err(format!(
+                "Length {} exceeds the maximum of {} bytes.",
+                length, self.policy.max
+            ));
+        }
 
         let mut random_bytes = vec![0u8; length]; // Create a vector of zeros of the desired length
         let mut rng = OsRng; // Initialize the OS random number generator
@@ -53,6 +151,134 @@ impl RngPerformer {
 
         Ok(random_bytes)
     }
+
+    /// Generates a uniformly random integer below `2^bits`.
+    ///
+    /// # Arguments
+    /// * `bits` - Size of the range; the result may have fewer significant bits.
+    ///
+    /// # Returns
+    /// A `Result` containing:
+    /// - `Ok(BigUint)` drawn from `OsRng`.
+    /// - `Err(String)` if `bits` is zero.
+    pub fn generate_biguint(&self, bits: u64) -> Result<BigUint, String> {
+        if bits == 0 {
+            return Err("Bit size must be a positive integer.".to_string());
+        }
+        let len = bits.div_ceil(8) as usize;
+        let bytes = self.generate_random_number(len)?;
+        Ok(BigUint::from_bytes_be(&bytes) >> (len as u64 * 8 - bits))
+    }
+
+    /// Generates a `bits`-bit probable prime from a fresh random seed.
+    ///
+    /// Candidates are derived from the seed and tested with Miller–Rabin until
+    /// one passes; the returned trail records the seed and how many were
+    /// rejected. Attest the seed (e.g. as the random number of an
+    /// attestation) and publish the trail, and verifiers can replay the search
+    /// with `PrimeTrail::verify` or `PrimeTrail::from_attestation`.
+    ///
+    /// # Arguments
+    /// * `bits` - Exact bit length of the prime; the top two bits are set.
+    ///
+    /// # Returns
+    /// A `Result` containing:
+    /// - `Ok(PrimeTrail)` with the seed, the rejected count and the prime.
+    /// - `Err(String)` if `bits` is outside the supported range.
+    pub fn generate_prime(&self, bits: u64) -> Result<PrimeTrail, String> {
+        let seed = self.generate_random_number(32)?;
+        PrimeTrail::search(&seed, bits)
+    }
+
+    /// Generates `total_len` random bytes as a stream of attested chunks.
+    ///
+    /// Chunks of `chunk_size` bytes (the final one may be shorter) are produced
+    /// lazily as the iterator is advanced, and each one is hash-chained to its
+    /// predecessor; see the `stream` module for the verification side.
+    ///
+    /// # Arguments
+    /// * `attester` - Signs every chunk.
+    /// * `total_len` - Total number of random bytes in the stream.
+    /// * `chunk_size` - Number of random bytes per chunk.
+    ///
+    /// # Returns
+    /// A `Result` containing:
+    /// - `Ok(RandomStream)` yielding `Result<Attestation, String>` per chunk.
+    /// - `Err(String)` if the sizes fail [`RngPerformer::check_stream`].
+    pub fn generate_random_stream<'a>(
+        &'a self,
+        attester: &'a RngAttester,
+        total_len: usize,
+        chunk_size: usize,
+    ) -> Result<RandomStream<'a>, String> {
+        self.check_stream(total_len, chunk_size)?;
+        let source = move |len: usize, link| {
+            let bytes = self.generate_random_number(len)?;
+            attester.attest_payload(AttestationPayload::new(bytes).with_chain(link))
+        };
+        RandomStream::new(Box::new(source), total_len, chunk_size)
+    }
+
+    /// Checks the sizes of a stream: at most the maximum stream length, in
+    /// chunks within the length policy, and at most `MAX_STREAM_CHUNKS` of
+    /// them, since every chunk is signed.
+    pub fn check_stream(&self, total_len: usize, chunk_size: usize) -> Result<(), String> {
+        if total_len == 0 {
+            return Err("Stream length must be a positive integer.".to_string());
+        }
+        if total_len > self.max_stream_length {
+            return Err(format!(
+                "Stream length {} exceeds the maximum of {} bytes.",
+                total_len, self.max_stream_length
+            ));
+        }
+        if chunk_size < self.policy.min.max(1) || chunk_size > self.policy.max {
+            return Err(format!(
+                "Chunk size {} is outside the allowed range of {} to {} bytes.",
+                chunk_size, self.policy.min.max(1), self.policy.max
+            ));
+        }
+        if total_len.div_ceil(chunk_size) > MAX_STREAM_CHUNKS {
+            return Err(format!(
+                "A stream of {} bytes needs chunks of at least {} bytes, at most {} chunks.",
+                total_len, total_len.div_ceil(MAX_STREAM_CHUNKS), MAX_STREAM_CHUNKS
+            ));
+        }
+        Ok(())
+    }
+
+    /// Mixes caller-supplied entropy into operator-generated bytes.
+    ///
+    /// The output has the same length as `operator_bytes` and is produced by
+    /// SHA-256 in counter mode over both inputs (each length-prefixed). As long
+    /// as either input is unpredictable, so is the output; in particular a
+    /// consumer that keeps its contribution secret until submission does not
+    /// have to trust the operator's entropy source.
+    ///
+    /// # Arguments
+    /// * `operator_bytes` - Bytes produced by `generate_random_number`.
+    /// * `client_entropy` - Opaque caller bytes (raw entropy or a commitment hash).
+    ///
+    /// # Returns
+    /// The mixed output, deterministic in both inputs so verifiers can recompute it.
+    pub fn mix_client_entropy(operator_bytes: &[u8], client_entropy: &[u8]) -> Vec<u8> {
+        let mut output = Vec::with_capacity(operator_bytes.len());
+        let mut counter: u32 = 0;
+        while output.len() < operator_bytes.len() {
+            let mut hasher = Sha256::new();
+            hasher.update(CLIENT_MIX_DOMAIN);
+            hasher.update(counter.to_be_bytes());
+            hasher.update((operator_bytes.len() as u64).to_be_bytes());
+            hasher.update(operator_bytes);
+            hasher.update((client_entropy.len() as u64).to_be_bytes());
+            hasher.update(client_entropy);
+            let block = hasher.finalize();
+            let take = (operator_bytes.len() - output.len()).min(block.len());
+            output.extend_from_slice(&block[..take]);
+            counter += 1;
+        }
+        output
+    }
 }
 
 // Default implementation for `RngPerformer` to allow `RngPerformer::default()`
@@ -61,3 +287,21 @@ impl Default for RngPerformer {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_chunk_count_is_capped() {
+        let performer = RngPerformer::new();
+        let max = performer.max_stream_length();
+        assert!(performer.check_stream(max, 1).is_err());
+        assert!(performer.check_stream(max, max / MAX_STREAM_CHUNKS - 1).is_err());
+        performer.check_stream(max, max / MAX_STREAM_CHUNKS).unwrap();
+        performer.check_stream(1, 1).unwrap();
+        assert!(performer.check_stream(0, 1).is_err());
+        assert!(performer.check_stream(max + 1, DEFAULT_MAX_LENGTH).is_err());
+        assert!(performer.check_stream(max, DEFAULT_MAX_LENGTH + 1).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+// src/pool.rs
+
+//! Pre-generated randomness.
+//!
+//! A [`RandomnessPool`] holds outputs that were generated and attested ahead
+//! of demand, each reserved for a numbered slot that is signed into its
+//! payload. Fulfilling a request from the pool only costs a lookup and a
+//! binding signature over `(task ID, slot, attestation digest)` under
+//! [`BINDING_CONTEXT`], which ties the pre-attested value to the one task it
+//! served. Slot numbers are persisted, so they never repeat across restarts;
+//! a pooled payload without a valid binding must be rejected by consumers.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde_json::json;
+
+use crate::attester::{Attestation, RngAttester};
+use crate::config::{self, PoolConfig};
+use crate::storage::Storage;
+
+/// Ed25519ph context binding signatures are made under.
+pub const BINDING_CONTEXT: &[u8] = b"othentic-rng/pool-binding/v1";
+
+/// Storage collection holding the next slot number.
+const POOL_STATE: &str = "pool_state";
+
+/// An attested output waiting for a request, with the attester that signed it.
+pub struct PooledOutput {
+    pub attester: Arc<RngAttester>,
+    pub attestation: Attestation,
+}
+
+impl PooledOutput {
+    /// Signs the binding of this output to `task_id`.
+    pub fn bind(&self, task_id: &str) -> Result<Signature, String> {
+        let message = binding_message(task_id, &self.attestation)?;
+        self.attester.sign_with_context(BINDING_CONTEXT, &message)
+    }
+}
+
+/// Outputs generated ahead of demand.
+pub struct RandomnessPool {
+    size: usize,
+    length: usize,
+    refill_interval: Duration,
+    storage: Arc<dyn Storage>,
+    next_slot: Mutex<u64>,
+    outputs: Mutex<VecDeque<PooledOutput>>,
+}
+
+impl RandomnessPool {
+    /// Creates an empty pool configured by the `pool` section, resuming slot
+    /// numbering from `storage`.
+    pub fn new(config: &PoolConfig, storage: Arc<dyn Storage>) -> Result<Self, String> {
+        let next_slot = match storage.get(POOL_STATE, "slot")? {
+            Some(state) => state
+                .get("next")
+                .and_then(|n| n.as_u64())
+                .ok_or("Corrupt pool state: missing next slot")?,
+            None => 0,
+        };
+        Ok(RandomnessPool {
+            size: config.size,
+            length: config.length,
+            refill_interval: config::parse_duration(&config.refill_interval)?,
+            storage,
+            next_slot: Mutex::new(next_slot),
+            outputs: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    /// Number of outputs the pool is topped up to.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Byte length of pooled outputs.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn refill_interval(&self) -> Duration {
+        self.refill_interval
+    }
+
+    /// Number of outputs ready to serve.
+    pub fn available(&self) -> usize {
+        self.outputs.lock().expect("pool lock poisoned").len()
+    }
+
+    /// Reserves the next slot number, persisting it before it is used.
+    pub fn reserve_slot(&self) -> Result<u64, String> {
+        let mut next = self.next_slot.lock().expect("pool lock poisoned");
+        let slot = *next;
+        self.storage
+            .put(POOL_STATE, "slot", json!({ "next": slot + 1 }))?;
+        self.storage.flush()?;
+        *next = slot + 1;
+        Ok(slot)
+    }
+
+    /// Adds an attested output.
+    pub fn push(&self, output: PooledOutput) {
+        self.outputs
+            .lock()
+            .expect("pool lock poisoned")
+            .push_back(output);
+    }
+
+    /// Takes the oldest output that is still valid at `now` (Unix ms),
+    /// discarding expired ones on the way.
+    pub fn take(&self, now: u64) -> Option<PooledOutput> {
+        let mut outputs = self.outputs.lock().expect("pool lock poisoned");
+        while let Some(output) = outputs.pop_front() {
+            match output.attestation.payload.validity {
+                Some(validity) if validity.expires_at <= now => continue,
+                _ => return Some(output),
+            }
+        }
+        None
+    }
+
+    /// Drops every pooled output, e.g. after the attestation key rotated.
+    pub fn clear(&self) -> usize {
+        let mut outputs = self.outputs.lock().expect("pool lock poisoned");
+        let dropped = outputs.len();
+        outputs.clear();
+        dropped
+    }
+}
+
+/// Checks that `signature` binds the pooled `attestation` to `task_id`.
+pub fn verify_binding(
+    public_key: &VerifyingKey,
+    task_id: &str,
+    attestation: &Attestation,
+    signature: &Signature,
+) -> Result<(), String> {
+    let message = binding_message(task_id, attestation)?;
+    RngAttester::verify_with_context(public_key, BINDING_CONTEXT, &message, signature)
+        .map_err(|e| format!("Invalid pool binding: {}", e))
+}
+
+/// Length-prefixed task ID, then the slot and the attestation digest.
+fn binding_message(task_id: &str, attestation: &Attestation) -> Result<Vec<u8>, String> {
+    let slot = attestation
+        .payload
+        .slot
+        .ok_or("Attestation was not reserved for a pool slot")?;
+    let mut message = Vec::with_capacity(task_id.len() + 44);
+    message.extend_from_slice(&(task_id.len() as u32).to_be_bytes());
+    message.extend_from_slice(task_id.as_bytes());
+    message.extend_from_slice(&slot.to_be_bytes());
+    message.extend_from_slice(&attestation.payload.digest());
+    Ok(message)
+}
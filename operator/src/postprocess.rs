@@ -0,0 +1,296 @@
+// src/postprocess.rs
+
+//! Post-processing of fresh entropy before it is attested.
+//!
+//! The `postprocess` setting lists stages by ID, in the order they apply to
+//! the output of the performer (or entropy mixer):
+//!
+//! - `whiten:sha256`, `whiten:keccak256`: counter-mode hash of the input,
+//!   the same length as the input.
+//! - `von-neumann`: von Neumann debiasing. Reads pairs of bits, emits the
+//!   first of each unequal pair and drops equal ones, so it consumes about
+//!   four input bytes per output byte of an unbiased source.
+//! - `truncate:K`, `K` from 1 to 7: keeps the `K` low bits of every input
+//!   byte and packs them, for sources whose high bits are weak.
+//! - `hkdf`, `hkdf:INFO`: HKDF-SHA256 (RFC 5869) expansion of a 32-byte
+//!   input, with `INFO` as the info string; at most 8160 bytes.
+//!
+//! A [`Pipeline`] pulls: the last stage asks the one before it for as many
+//! bytes as it needs, down to the source. Every attestation over
+//! post-processed bytes records the stage IDs, so a verifier knows what was
+//! applied. VRF outputs are not post-processed; each chunk of a
+//! `/task/stream` is.
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+
+/// Domain tag of the whitening hash input.
+const WHITEN_DOMAIN: &[u8] = b"othentic-rng/postprocess/whiten/v1";
+/// HKDF salt.
+const HKDF_SALT: &[u8] = b"othentic-rng/postprocess/hkdf/v1";
+/// Input keying material drawn per HKDF expansion.
+const HKDF_IKM_LENGTH: usize = 32;
+/// Longest HKDF-SHA256 output.
+pub const HKDF_MAX_LENGTH: usize = 255 * 32;
+/// Input bytes a von Neumann stage may consume per output byte before it
+/// gives up on the source.
+const VON_NEUMANN_BUDGET: usize = 64;
+
+/// Reads `n` bytes from the stage before, or from the source.
+pub type Upstream<'a> = dyn FnMut(usize) -> Result<Vec<u8>, String> + 'a;
+
+/// Hash a `whiten` stage applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhitenHash {
+    Sha256,
+    Keccak256,
+}
+
+/// One post-processing stage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stage {
+    Whiten(WhitenHash),
+    VonNeumann,
+    Truncate(u8),
+    Hkdf(String),
+}
+
+impl Stage {
+    /// Parses a stage ID; see the module documentation.
+    pub fn parse(id: &str) -> Result<Self, String> {
+        let (name, argument) = match id.split_once(':') {
+            Some((name, argument)) => (name, Some(argument)),
+            None => (id, None),
+        };
+        match (name, argument) {
+            ("whiten", Some("sha256")) => Ok(Stage::Whiten(WhitenHash::Sha256)),
+            ("whiten", Some("keccak256")) => Ok(Stage::Whiten(WhitenHash::Keccak256)),
+            ("whiten", _) => Err(format!(
+                "Stage {} must be whiten:sha256 or whiten:keccak256",
+                id
+            )),
+            ("von-neumann", None) => Ok(Stage::VonNeumann),
+            ("truncate", Some(bits)) => match bits.parse::<u8>() {
+                Ok(bits @ 1..=7) => Ok(Stage::Truncate(bits)),
+                _ => Err(format!("Stage {} must keep 1 to 7 bits", id)),
+            },
+            ("hkdf", info) => Ok(Stage::Hkdf(info.unwrap_or_default().to_string())),
+            _ => Err(format!("Unknown post-processing stage {}", id)),
+        }
+    }
+
+    /// The canonical ID, as recorded in attestations.
+    pub fn id(&self) -> String {
+        match self {
+            Stage::Whiten(WhitenHash::Sha256) => "whiten:sha256".to_string(),
+            Stage::Whiten(WhitenHash::Keccak256) => "whiten:keccak256".to_string(),
+            Stage::VonNeumann => "von-neumann".to_string(),
+            Stage::Truncate(bits) => format!("truncate:{}", bits),
+            Stage::Hkdf(info) if info.is_empty() => "hkdf".to_string(),
+            Stage::Hkdf(info) => format!("hkdf:{}", info),
+        }
+    }
+
+    /// Produces `length` bytes from `upstream`.
+    pub fn apply(&self, upstream: &mut Upstream, length: usize) -> Result<Vec<u8>, String> {
+        match self {
+            Stage::Whiten(hash) => Ok(whiten(*hash, &upstream(length)?, length)),
+            Stage::VonNeumann => von_neumann(upstream, length),
+            Stage::Truncate(bits) => {
+                let bits = *bits as usize;
+                let input = upstream((length * 8).div_ceil(bits))?;
+                Ok(pack_low_bits(&input, bits, length))
+            }
+            Stage::Hkdf(info) => {
+                if length > HKDF_MAX_LENGTH {
+                    return Err(format!(
+                        "HKDF expands to at most {} bytes, not {}",
+                        HKDF_MAX_LENGTH, length
+                    ));
+                }
+                let ikm = upstream(HKDF_IKM_LENGTH)?;
+                Ok(hkdf_sha256(&ikm, info.as_bytes(), length))
+            }
+        }
+    }
+}
+
+/// The configured stages, in order; empty passes the source through.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+}
+
+impl Pipeline {
+    /// Parses the `postprocess` setting.
+    pub fn parse(ids: &[String]) -> Result<Self, String> {
+        let stages = ids
+            .iter()
+            .map(|id| Stage::parse(id))
+            .collect::<Result<_, _>>()?;
+        Ok(Pipeline { stages })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Canonical IDs of the stages, in order.
+    pub fn ids(&self) -> Vec<String> {
+        self.stages.iter().map(Stage::id).collect()
+    }
+
+    /// Longest output the pipeline can produce, if it is bounded.
+    pub fn max_length(&self) -> Option<usize> {
+        self.stages
+            .iter()
+            .any(|stage| matches!(stage, Stage::Hkdf(_)))
+            .then_some(HKDF_MAX_LENGTH)
+    }
+
+    /// Produces `length` bytes, drawing from `source` as often as the stages
+    /// need.
+    pub fn apply(&self, source: &mut Upstream, length: usize) -> Result<Vec<u8>, String> {
+        pull(&self.stages, source, length)
+    }
+}
+
+fn pull(stages: &[Stage], source: &mut Upstream, length: usize) -> Result<Vec<u8>, String> {
+    match stages.split_last() {
+        Some((last, rest)) => last.apply(&mut |n| pull(rest, source, n), length),
+        None => {
+            let bytes = source(length)?;
+            if bytes.len() != length {
+                return Err(format!(
+                    "Entropy source returned {} bytes, not {}",
+                    bytes.len(),
+                    length
+                ));
+            }
+            Ok(bytes)
+        }
+    }
+}
+
+/// `H(domain || i || input)` for block counters `i`, truncated to `length`.
+fn whiten(hash: WhitenHash, input: &[u8], length: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(length + 32);
+    let mut counter: u32 = 0;
+    while output.len() < length {
+        match hash {
+            WhitenHash::Sha256 => output.extend_from_slice(&block::<Sha256>(counter, input)),
+            WhitenHash::Keccak256 => output.extend_from_slice(&block::<Keccak256>(counter, input)),
+        }
+        counter += 1;
+    }
+    output.truncate(length);
+    output
+}
+
+fn block<D: Digest>(counter: u32, input: &[u8]) -> Vec<u8> {
+    D::new()
+        .chain_update(WHITEN_DOMAIN)
+        .chain_update(counter.to_be_bytes())
+        .chain_update(input)
+        .finalize()
+        .to_vec()
+}
+
+fn von_neumann(upstream: &mut Upstream, length: usize) -> Result<Vec<u8>, String> {
+    let mut bits = BitWriter::new(length);
+    let mut consumed = 0;
+    while !bits.is_full() {
+        if consumed >= VON_NEUMANN_BUDGET * length.max(1) {
+            return Err(format!(
+                "von Neumann debiasing got {} of {} bytes from {} input bytes; the source looks stuck",
+                bits.written() / 8,
+                length,
+                consumed
+            ));
+        }
+        // An unbiased source yields about a bit per four input bits.
+        let wanted = 4 * (length - bits.written() / 8).max(1);
+        let input = upstream(wanted)?;
+        consumed += input.len();
+        for byte in input {
+            for pair in 0..4 {
+                let first = (byte >> (7 - 2 * pair)) & 1;
+                let second = (byte >> (6 - 2 * pair)) & 1;
+                if first != second && !bits.is_full() {
+                    bits.push(first);
+                }
+            }
+        }
+    }
+    Ok(bits.into_bytes())
+}
+
+/// The `bits` low bits of each byte of `input`, most significant first,
+/// packed into `length` bytes.
+fn pack_low_bits(input: &[u8], bits: usize, length: usize) -> Vec<u8> {
+    let mut writer = BitWriter::new(length);
+    for byte in input {
+        for bit in (0..bits).rev() {
+            if !writer.is_full() {
+                writer.push((byte >> bit) & 1);
+            }
+        }
+    }
+    writer.into_bytes()
+}
+
+/// HKDF-SHA256 extract-then-expand.
+fn hkdf_sha256(ikm: &[u8], info: &[u8], length: usize) -> Vec<u8> {
+    let prk = hmac_sha256(HKDF_SALT, &[ikm]);
+    let mut output = Vec::with_capacity(length + 32);
+    let mut previous = Vec::new();
+    let mut counter: u8 = 1;
+    while output.len() < length {
+        previous = hmac_sha256(&prk, &[&previous, info, &[counter]]).to_vec();
+        output.extend_from_slice(&previous);
+        counter = counter.wrapping_add(1);
+    }
+    output.truncate(length);
+    output
+}
+
+fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+/// Packs bits, most significant first, into a fixed number of bytes.
+struct BitWriter {
+    bytes: Vec<u8>,
+    written: usize,
+}
+
+impl BitWriter {
+    fn new(length: usize) -> Self {
+        BitWriter {
+            bytes: vec![0; length],
+            written: 0,
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.written == self.bytes.len() * 8
+    }
+
+    fn written(&self) -> usize {
+        self.written
+    }
+
+    fn push(&mut self, bit: u8) {
+        self.bytes[self.written / 8] |= bit << (7 - self.written % 8);
+        self.written += 1;
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
@@ -0,0 +1,189 @@
+// src/primes.rs
+
+//! Deterministic Miller–Rabin primality testing.
+//!
+//! Witnesses are derived from the candidate itself by hashing, so any party
+//! re-running the test on the same number reaches the same verdict. That is
+//! what protocols built on top (VDF challenges, attested prime generation)
+//! need for their results to be reproducible by verifiers.
+//!
+//! Prime generation works the same way: a [`PrimeTrail`] derives numbered
+//! candidates from a seed and keeps the first that passes, so anyone holding
+//! the seed, typically an attested random number, can replay every rejected
+//! candidate and confirm the prime was not picked by hand.
+
+use ed25519_dalek::VerifyingKey;
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::attester::{Attestation, RngAttester};
+
+/// Default number of Miller–Rabin rounds (error probability below 2^-128).
+pub const DEFAULT_ROUNDS: usize = 64;
+
+/// Domain tag for deriving prime candidates from a seed.
+pub const CANDIDATE_DOMAIN: &[u8] = b"othentic-rng/prime-candidate/v1";
+/// Smallest and largest prime sizes [`PrimeTrail::search`] accepts.
+pub const MIN_PRIME_BITS: u64 = 16;
+pub const MAX_PRIME_BITS: u64 = 8192;
+
+/// Small primes used for cheap trial division before Miller–Rabin.
+const SMALL_PRIMES: [u32; 25] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+/// Returns `true` if `n` is prime with overwhelming probability.
+pub fn is_probable_prime(n: &BigUint, rounds: usize) -> bool {
+    let two = BigUint::from(2u32);
+    if *n < two {
+        return false;
+    }
+    for p in SMALL_PRIMES {
+        let p = BigUint::from(p);
+        if *n == p {
+            return true;
+        }
+        if (n % &p).is_zero() {
+            return false;
+        }
+    }
+
+    // n - 1 = d * 2^s with d odd.
+    let n_minus_one = n - BigUint::one();
+    let s = n_minus_one
+        .trailing_zeros()
+        .expect("n - 1 is non-zero for n > 97");
+    let d = &n_minus_one >> s;
+
+    'witness: for round in 0..rounds {
+        let a = witness(n, round);
+        let mut x = a.modpow(&d, n);
+        if x.is_one() || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..s {
+            x = (&x * &x) % n;
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// Derives the `round`-th witness for `n`, uniformly in `[2, n - 2]`.
+fn witness(n: &BigUint, round: usize) -> BigUint {
+    let range = n - BigUint::from(3u32);
+    let mut material = Vec::new();
+    let mut counter: u32 = 0;
+    // Draw 64 bits more than needed so the reduction bias is negligible.
+    while material.len() * 8 < range.bits() as usize + 64 {
+        let mut hasher = Sha256::new();
+        hasher.update(b"othentic-rng/miller-rabin/v1");
+        hasher.update(n.to_bytes_be());
+        hasher.update((round as u64).to_be_bytes());
+        hasher.update(counter.to_be_bytes());
+        material.extend_from_slice(&hasher.finalize());
+        counter += 1;
+    }
+    BigUint::from_bytes_be(&material).mod_floor(&range) + BigUint::from(2u32)
+}
+
+/// A prime found from a seed, with the search that led to it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrimeTrail {
+    /// Hex seed the candidates were derived from.
+    pub seed: String,
+    pub bits: u64,
+    /// Candidates found composite before the prime.
+    pub rejected: u64,
+    /// Decimal value of the prime.
+    pub prime: String,
+}
+
+impl PrimeTrail {
+    /// Tests candidates derived from `seed` until one is prime.
+    pub fn search(seed: &[u8], bits: u64) -> Result<Self, String> {
+        if !(MIN_PRIME_BITS..=MAX_PRIME_BITS).contains(&bits) {
+            return Err(format!(
+                "Prime size must be between {} and {} bits.",
+                MIN_PRIME_BITS, MAX_PRIME_BITS
+            ));
+        }
+        let mut index = 0;
+        loop {
+            let candidate = candidate(seed, bits, index);
+            if is_probable_prime(&candidate, DEFAULT_ROUNDS) {
+                return Ok(PrimeTrail {
+                    seed: hex::encode(seed),
+                    bits,
+                    rejected: index,
+                    prime: candidate.to_str_radix(10),
+                });
+            }
+            index += 1;
+        }
+    }
+
+    /// Searches from the random number of `attestation`, after checking its
+    /// signature.
+    pub fn from_attestation(
+        public_key: &VerifyingKey,
+        attestation: &Attestation,
+        bits: u64,
+    ) -> Result<Self, String> {
+        RngAttester::verify(public_key, attestation)?;
+        Self::search(&attestation.payload.random_number, bits)
+    }
+
+    /// Replays the search: every rejected candidate must be composite and
+    /// the next one must be the recorded prime.
+    pub fn verify(&self) -> Result<BigUint, String> {
+        let seed = hex::decode(&self.seed).map_err(|e| format!("Invalid seed: {}", e))?;
+        let replayed = Self::search(&seed, self.bits)?;
+        if replayed.rejected != self.rejected || replayed.prime != self.prime {
+            return Err(format!(
+                "Seed yields the {}-bit prime after {} rejected candidate(s), not the recorded one",
+                self.bits, replayed.rejected
+            ));
+        }
+        self.prime()
+    }
+
+    /// The prime as a number.
+    pub fn prime(&self) -> Result<BigUint, String> {
+        BigUint::parse_bytes(self.prime.as_bytes(), 10)
+            .ok_or_else(|| format!("Invalid prime {}", self.prime))
+    }
+}
+
+/// The `index`-th candidate for a `bits`-bit prime from `seed`: odd, with the
+/// top two bits set, so the product of two has exactly `2 * bits` bits as RSA
+/// key generation expects.
+fn candidate(seed: &[u8], bits: u64, index: u64) -> BigUint {
+    let len = bits.div_ceil(8) as usize;
+    let mut material = Vec::with_capacity(len + 32);
+    let mut counter: u32 = 0;
+    while material.len() < len {
+        let mut hasher = Sha256::new();
+        hasher.update(CANDIDATE_DOMAIN);
+        hasher.update((seed.len() as u64).to_be_bytes());
+        hasher.update(seed);
+        hasher.update(bits.to_be_bytes());
+        hasher.update(index.to_be_bytes());
+        hasher.update(counter.to_be_bytes());
+        material.extend_from_slice(&hasher.finalize());
+        counter += 1;
+    }
+    material.truncate(len);
+    let mut candidate = BigUint::from_bytes_be(&material) >> (len as u64 * 8 - bits);
+    candidate.set_bit(bits - 1, true);
+    candidate.set_bit(bits - 2, true);
+    candidate.set_bit(0, true);
+    candidate
+}
@@ -0,0 +1,268 @@
+// src/provenance.rs
+
+//! Signed statements of what software an operator runs.
+//!
+//! At startup the operator hashes its own executable. A
+//! [`ProvenanceStatement`] names that hash, the version and target it was
+//! built for, and is signed with Ed25519ph under [`CONTEXT`] by the current
+//! attestation key, so consumers can prefer operators whose binary hash
+//! matches an audited, reproducible build. `GET /identity` serves a fresh
+//! statement, and heartbeats carry one to the beacon committee.
+//!
+//! A self-reported hash is only as good as the operator's honesty. On a
+//! confidential-computing machine `provenance.tee` adds a hardware quote
+//! whose report data is [`report_data`] of the statement: a digest of the
+//! binary hash, version and attestation key. Checking the quote with the
+//! vendor's verification service (Intel DCAP for SGX, AMD KDS for SEV-SNP)
+//! then shows that the key is held by the measured enclave or VM; this
+//! module only checks that the quote is bound to the statement. Quotes come
+//! from Gramine's `/dev/attestation` on SGX and from the kernel's
+//! configfs-tsm interface on SEV-SNP and TDX guests.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::attester::RngAttester;
+use crate::config::ProvenanceConfig;
+use crate::tasks::unix_millis;
+
+/// Ed25519ph context provenance statements are signed under.
+pub const CONTEXT: &[u8] = b"othentic-rng/provenance/v1";
+
+const REPORT_DATA_DOMAIN: &[u8] = b"othentic-rng/provenance/report-data/v1";
+const GRAMINE_ATTESTATION: &str = "/dev/attestation";
+const TSM_REPORTS: &str = "/sys/kernel/config/tsm/report";
+
+/// A hardware quote binding a statement to a trusted execution environment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeeQuote {
+    /// `"sgx"`, or the configfs-tsm provider (e.g. `"sev_guest"`).
+    pub platform: String,
+    /// Hex 64 bytes carried in the quote's report data field.
+    pub report_data: String,
+    /// Hex quote as produced by the platform.
+    pub quote: String,
+}
+
+/// An operator's signed account of the software it runs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProvenanceStatement {
+    /// The operator's on-chain address (`operator.address`).
+    pub operator: String,
+    pub version: String,
+    /// Hex SHA-256 of the running executable.
+    pub binary_sha256: String,
+    /// Architecture and OS the binary was built for, e.g. `"x86_64-linux"`.
+    pub target: String,
+    pub issued_at: u64,
+    pub public_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tee: Option<TeeQuote>,
+    pub signature: String,
+}
+
+impl ProvenanceStatement {
+    /// Checks the signature, and that a quote is bound to the statement;
+    /// returns the key that signed. The quote itself is not verified here.
+    pub fn verify(&self) -> Result<VerifyingKey, String> {
+        let key: [u8; 32] = hex::decode(&self.public_key)
+            .map_err(|e| format!("Invalid provenance public key: {}", e))?
+            .try_into()
+            .map_err(|_| "Provenance public key must be 32 bytes".to_string())?;
+        let key = VerifyingKey::from_bytes(&key)
+            .map_err(|e| format!("Invalid provenance public key: {}", e))?;
+        let signature: [u8; 64] = hex::decode(&self.signature)
+            .map_err(|e| format!("Invalid provenance signature: {}", e))?
+            .try_into()
+            .map_err(|_| "Provenance signature must be 64 bytes".to_string())?;
+        RngAttester::verify_with_context(
+            &key,
+            CONTEXT,
+            &self.signed_bytes(),
+            &Signature::from_bytes(&signature),
+        )?;
+        if let Some(tee) = &self.tee {
+            let expected = report_data(&self.version, &self.binary_sha256, &key);
+            if tee.report_data != hex::encode(expected) {
+                return Err("TEE quote is not bound to this statement".to_string());
+            }
+        }
+        Ok(key)
+    }
+
+    /// Length-prefixed encoding of every field but the signature.
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        let (platform, report, quote) = match &self.tee {
+            Some(tee) => (
+                tee.platform.as_str(),
+                tee.report_data.as_str(),
+                tee.quote.as_str(),
+            ),
+            None => ("", "", ""),
+        };
+        for field in [
+            self.operator.as_str(),
+            self.version.as_str(),
+            self.binary_sha256.as_str(),
+            self.target.as_str(),
+            self.public_key.as_str(),
+            platform,
+            report,
+            quote,
+        ] {
+            data.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            data.extend_from_slice(field.as_bytes());
+        }
+        data.extend_from_slice(&self.issued_at.to_be_bytes());
+        data
+    }
+}
+
+/// The report data a quote for a statement must carry: SHA-512 over the
+/// version, binary hash and attestation key.
+pub fn report_data(version: &str, binary_sha256: &str, public_key: &VerifyingKey) -> [u8; 64] {
+    let mut hasher = Sha512::new().chain_update(REPORT_DATA_DOMAIN);
+    for field in [version.as_bytes(), binary_sha256.as_bytes()] {
+        hasher.update((field.len() as u32).to_be_bytes());
+        hasher.update(field);
+    }
+    hasher.update(public_key.as_bytes());
+    hasher.finalize().into()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tee {
+    /// Gramine's `/dev/attestation` pseudo-files.
+    Sgx,
+    /// The kernel's configfs-tsm report interface.
+    Tsm,
+}
+
+/// Issues statements for the running binary.
+pub struct Provenance {
+    operator: String,
+    binary_sha256: String,
+    tee: Option<Tee>,
+    /// Quote of the latest attestation key; keys rotate, binaries do not.
+    quote: Mutex<Option<TeeQuote>>,
+}
+
+impl Provenance {
+    /// Hashes the running executable.
+    pub fn new(config: &ProvenanceConfig, operator: &str) -> Result<Self, String> {
+        let exe = std::env::current_exe()
+            .map_err(|e| format!("Failed to locate the operator binary: {}", e))?;
+        let binary = fs::read(&exe).map_err(|e| {
+            format!(
+                "Failed to read the operator binary {}: {}",
+                exe.display(),
+                e
+            )
+        })?;
+        let tee = match config.tee.as_deref() {
+            None => None,
+            Some("sgx") => Some(Tee::Sgx),
+            Some("tsm") => Some(Tee::Tsm),
+            Some(other) => return Err(format!("Unknown provenance.tee {}", other)),
+        };
+        Ok(Provenance {
+            operator: operator.to_string(),
+            binary_sha256: hex::encode(Sha256::digest(&binary)),
+            tee,
+            quote: Mutex::new(None),
+        })
+    }
+
+    pub fn binary_sha256(&self) -> &str {
+        &self.binary_sha256
+    }
+
+    /// Signs a statement with the attester's key, quoting it if a TEE is
+    /// configured.
+    pub fn statement(&self, attester: &RngAttester) -> Result<ProvenanceStatement, String> {
+        let key = attester.get_public_key();
+        let tee = match self.tee {
+            Some(_) => Some(self.quote_for(key)?),
+            None => None,
+        };
+        let mut statement = ProvenanceStatement {
+            operator: self.operator.clone(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            binary_sha256: self.binary_sha256.clone(),
+            target: format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS),
+            issued_at: unix_millis(),
+            public_key: hex::encode(key.as_bytes()),
+            tee,
+            signature: String::new(),
+        };
+        let signature = attester.sign_with_context(CONTEXT, &statement.signed_bytes())?;
+        statement.signature = hex::encode(signature.to_bytes());
+        Ok(statement)
+    }
+
+    /// The quote for `key`, fetched anew when the key changed.
+    fn quote_for(&self, key: &VerifyingKey) -> Result<TeeQuote, String> {
+        let data = report_data(env!("CARGO_PKG_VERSION"), &self.binary_sha256, key);
+        let mut cached = self.quote.lock().expect("provenance lock poisoned");
+        if let Some(quote) = cached.as_ref() {
+            if quote.report_data == hex::encode(data) {
+                return Ok(quote.clone());
+            }
+        }
+        let (platform, quote) = match self.tee {
+            Some(Tee::Sgx) => ("sgx".to_string(), sgx_quote(&data)?),
+            Some(Tee::Tsm) => tsm_quote(&data)?,
+            None => return Err("No TEE is configured".to_string()),
+        };
+        let quote = TeeQuote {
+            platform,
+            report_data: hex::encode(data),
+            quote: hex::encode(quote),
+        };
+        *cached = Some(quote.clone());
+        Ok(quote)
+    }
+}
+
+/// Asks Gramine for a DCAP quote carrying `data` as report data.
+pub(crate) fn sgx_quote(data: &[u8; 64]) -> Result<Vec<u8>, String> {
+    let dir = Path::new(GRAMINE_ATTESTATION);
+    fs::write(dir.join("user_report_data"), data)
+        .and_then(|()| fs::read(dir.join("quote")))
+        .map_err(|e| {
+            format!(
+                "Failed to obtain an SGX quote from {}: {}",
+                dir.display(),
+                e
+            )
+        })
+}
+
+/// Requests a report through configfs-tsm; returns the provider and quote.
+fn tsm_quote(data: &[u8; 64]) -> Result<(String, Vec<u8>), String> {
+    let dir = Path::new(TSM_REPORTS).join(format!("othentic-rng-{}", std::process::id()));
+    let fail = |e: std::io::Error| {
+        format!(
+            "Failed to obtain a TEE report from {}: {}",
+            dir.display(),
+            e
+        )
+    };
+    fs::create_dir(&dir).map_err(fail)?;
+    let report = fs::write(dir.join("inblob"), data).and_then(|()| {
+        let quote = fs::read(dir.join("outblob"))?;
+        let provider = fs::read_to_string(dir.join("provider"))?;
+        Ok((provider.trim().to_string(), quote))
+    });
+    // The report directory is a kernel object; removing it frees it.
+    let _ = fs::remove_dir(&dir);
+    report.map_err(fail)
+}
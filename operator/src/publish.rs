@@ -0,0 +1,307 @@
+// src/publish.rs
+
+//! Message-bus feed of completed attestations and beacon rounds.
+//!
+//! An [`EventPublisher`] wraps every event in a schema-versioned envelope and
+//! hands it to a background thread that delivers it to `publish.topic` on the
+//! configured bus:
+//!
+//! - `nats`: core NATS over TCP (`nats://[user:pass@|token@]host:4222`); each
+//!   event is a `PUB` on the subject `topic`, confirmed with a `PING`.
+//! - `kafka`: a Kafka REST proxy (`POST {url}/topics/{topic}` with the v2 JSON
+//!   embedded format), keyed by task ID or beacon round.
+//!
+//! Publishing never blocks the task pipeline: events are buffered up to
+//! `publish.capacity` and dropped (counted in `rng_events_dropped_total`)
+//! when the bus falls behind.
+//!
+//! Envelope (`schemaVersion` 1):
+//!
+//! ```json
+//! { "schema": "othentic-rng/attestation", "schemaVersion": 1,
+//!   "operator": "0x...", "chainId": 1337, "publishedAt": 1700000000000,
+//!   "key": "<task id>", "data": { ...TaskOutcome... } }
+//! ```
+//!
+//! Beacon rounds use the schema `othentic-rng/beacon-round` and carry the
+//! stored round record as `data`, keyed by the round number.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use log::warn;
+use serde_json::{json, Value};
+
+use crate::config::PublishConfig;
+use crate::metrics::Metrics;
+use crate::resilience::{CallError, Resilience};
+use crate::tasks::unix_millis;
+
+/// Version of the envelope layout; bumped on incompatible changes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+pub const ATTESTATION_SCHEMA: &str = "othentic-rng/attestation";
+pub const BEACON_ROUND_SCHEMA: &str = "othentic-rng/beacon-round";
+pub const REVOCATION_SCHEMA: &str = "othentic-rng/revocation";
+
+/// A message bus events can be delivered to.
+trait Bus: Send {
+    fn send(
+        &mut self,
+        topic: &str,
+        key: &str,
+        envelope: &Value,
+        timeout: Duration,
+    ) -> Result<(), CallError>;
+}
+
+struct Event {
+    key: String,
+    envelope: Value,
+}
+
+/// Publishes events to the configured bus from a background thread.
+pub struct EventPublisher {
+    operator: String,
+    chain_id: u64,
+    sender: Mutex<SyncSender<Event>>,
+    metrics: Arc<Metrics>,
+}
+
+impl EventPublisher {
+    /// Connects the bus named by `publish.backend` and starts delivering.
+    pub fn start(
+        config: &PublishConfig,
+        operator: &str,
+        chain_id: u64,
+        resilience: Arc<Resilience>,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self, String> {
+        let bus: Box<dyn Bus> = match config.backend.as_str() {
+            "nats" => Box::new(NatsBus::new(&config.url)?),
+            "kafka" => Box::new(KafkaRestBus::new(&config.url)),
+            other => return Err(format!("Unknown publish.backend '{}'", other)),
+        };
+        let (sender, receiver) = mpsc::sync_channel(config.capacity);
+        let topic = config.topic.clone();
+        let delivering = Arc::clone(&metrics);
+        thread::Builder::new()
+            .name("rng-publisher".to_string())
+            .spawn(move || deliver(receiver, bus, &topic, &resilience, &delivering))
+            .map_err(|e| format!("Failed to start publisher thread: {}", e))?;
+        Ok(EventPublisher {
+            operator: operator.to_string(),
+            chain_id,
+            sender: Mutex::new(sender),
+            metrics,
+        })
+    }
+
+    /// Queues an event with schema `schema`; `key` orders events on the bus
+    /// (Kafka partitioning) and identifies them to consumers.
+    pub fn publish(&self, schema: &str, key: &str, data: Value) {
+        let envelope = json!({
+            "schema": schema,
+            "schemaVersion": SCHEMA_VERSION,
+            "operator": self.operator,
+            "chainId": self.chain_id,
+            "publishedAt": unix_millis(),
+            "key": key,
+            "data": data,
+        });
+        let event = Event {
+            key: key.to_string(),
+            envelope,
+        };
+        let sent = self
+            .sender
+            .lock()
+            .expect("publisher lock poisoned")
+            .try_send(event);
+        match sent {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                warn!(
+                    "Dropping {} event {}: the bus is not keeping up",
+                    schema, key
+                );
+                self.metrics
+                    .inc_counter("rng_events_dropped_total", &[("schema", schema)], 1);
+            }
+        }
+    }
+}
+
+fn deliver(
+    receiver: Receiver<Event>,
+    mut bus: Box<dyn Bus>,
+    topic: &str,
+    resilience: &Resilience,
+    metrics: &Metrics,
+) {
+    for event in receiver {
+        let result = resilience.call("publish", |remaining| {
+            bus.send(topic, &event.key, &event.envelope, remaining)
+        });
+        let outcome = match result {
+            Ok(()) => "published",
+            Err(e) => {
+                warn!("Failed to publish event {}: {}", event.key, e);
+                "failed"
+            }
+        };
+        metrics.inc_counter("rng_events_total", &[("outcome", outcome)], 1);
+    }
+}
+
+/// Core NATS client speaking the text protocol over one TCP connection,
+/// reconnecting after any error.
+struct NatsBus {
+    address: String,
+    connect: String,
+    connection: Option<(BufReader<TcpStream>, TcpStream)>,
+}
+
+impl NatsBus {
+    fn new(url: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("nats://")
+            .ok_or_else(|| format!("NATS URL {} must start with nats://", url))?;
+        let (credentials, address) = match rest.rsplit_once('@') {
+            Some((credentials, address)) => (Some(credentials), address),
+            None => (None, rest),
+        };
+        let mut options = json!({
+            "verbose": false,
+            "pedantic": false,
+            "name": "othentic-rng-operator",
+            "lang": "rust",
+            "version": env!("CARGO_PKG_VERSION"),
+        });
+        match credentials.map(|c| c.split_once(':')) {
+            Some(Some((user, pass))) => {
+                options["user"] = json!(user);
+                options["pass"] = json!(pass);
+            }
+            Some(None) => options["auth_token"] = json!(credentials),
+            None => {}
+        }
+        let address = address.trim_end_matches('/');
+        Ok(NatsBus {
+            address: if address.contains(':') {
+                address.to_string()
+            } else {
+                format!("{}:4222", address)
+            },
+            connect: format!("CONNECT {}\r\n", options),
+            connection: None,
+        })
+    }
+
+    fn publish(&mut self, subject: &str, body: &str, timeout: Duration) -> Result<(), String> {
+        if self.connection.is_none() {
+            let stream = TcpStream::connect(&self.address)
+                .map_err(|e| format!("Failed to connect to {}: {}", self.address, e))?;
+            let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+            stream
+                .set_read_timeout(Some(timeout))
+                .map_err(|e| e.to_string())?;
+            let info = read_line(&mut reader)?;
+            if !info.starts_with("INFO") {
+                return Err(format!("Unexpected NATS greeting: {}", info));
+            }
+            let mut writer = stream;
+            writer
+                .write_all(self.connect.as_bytes())
+                .map_err(|e| e.to_string())?;
+            self.connection = Some((reader, writer));
+        }
+        let (reader, writer) = self.connection.as_mut().expect("connected above");
+        writer
+            .set_read_timeout(Some(timeout))
+            .map_err(|e| e.to_string())?;
+        let command = format!("PUB {} {}\r\n{}\r\nPING\r\n", subject, body.len(), body);
+        writer
+            .write_all(command.as_bytes())
+            .map_err(|e| e.to_string())?;
+        loop {
+            let line = read_line(reader)?;
+            match line.as_str() {
+                "PONG" => return Ok(()),
+                "PING" => writer.write_all(b"PONG\r\n").map_err(|e| e.to_string())?,
+                "+OK" => {}
+                _ if line.starts_with("INFO") => {}
+                _ => return Err(format!("NATS error: {}", line)),
+            }
+        }
+    }
+}
+
+impl Bus for NatsBus {
+    fn send(
+        &mut self,
+        topic: &str,
+        _key: &str,
+        envelope: &Value,
+        timeout: Duration,
+    ) -> Result<(), CallError> {
+        self.publish(topic, &envelope.to_string(), timeout)
+            .map_err(|e| {
+                self.connection = None;
+                CallError::Transient(e)
+            })
+    }
+}
+
+fn read_line(reader: &mut BufReader<TcpStream>) -> Result<String, String> {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) => Err("NATS server closed the connection".to_string()),
+        Ok(_) => Ok(line.trim_end().to_string()),
+        Err(e) => Err(format!("Failed to read from NATS: {}", e)),
+    }
+}
+
+/// Produces to Kafka through a Confluent-compatible REST proxy.
+struct KafkaRestBus {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl KafkaRestBus {
+    fn new(url: &str) -> Self {
+        KafkaRestBus {
+            url: url.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new().build(),
+        }
+    }
+}
+
+impl Bus for KafkaRestBus {
+    fn send(
+        &mut self,
+        topic: &str,
+        key: &str,
+        envelope: &Value,
+        timeout: Duration,
+    ) -> Result<(), CallError> {
+        let records = json!({ "records": [{ "key": key, "value": envelope }] });
+        match self
+            .agent
+            .post(&format!("{}/topics/{}", self.url, topic))
+            .timeout(timeout)
+            .set("Content-Type", "application/vnd.kafka.json.v2+json")
+            .send_string(&records.to_string())
+        {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(code, _)) if code < 500 && code != 429 => {
+                Err(CallError::Permanent(format!("HTTP {}", code)))
+            }
+            Err(e) => Err(CallError::Transient(e.to_string())),
+        }
+    }
+}
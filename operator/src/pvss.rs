@@ -0,0 +1,791 @@
+// src/pvss.rs
+
+//! Publicly verifiable secret sharing (SCRAPE, DDH variant) over Ristretto.
+//!
+//! A dealer shares a random secret `s` among the configured participants
+//! with threshold `t`: it publishes commitments `v_i = g^p(i)` to a random
+//! polynomial `p` of degree `t - 1` with `p(0) = s`, encrypted shares
+//! `ŝ_i = pk_i^p(i)` and a DLEQ proof per share. Anybody can check a dealing
+//! without any secret: the DLEQ proofs tie each encrypted share to its
+//! commitment, and a random codeword of the dual code proves the commitments
+//! lie on a polynomial of the right degree.
+//!
+//! Participant `i` decrypts its share to `h^p(i)` and proves the decryption
+//! with another DLEQ proof; any `t` decrypted shares recover `h^s` by Lagrange
+//! interpolation in the exponent. [`combine`] folds the recovered secrets of
+//! every qualified dealer into a beacon value and [`verify_beacon`] re-checks
+//! the whole transcript.
+//!
+//! Participant keys live on the second generator: `pk_i = h^sk_i`. The same
+//! keys sign peer-to-peer messages with Schnorr signatures ([`KeyPair::sign`]).
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::Identity;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256, Sha512};
+
+const SECOND_GENERATOR_DOMAIN: &[u8] = b"othentic-rng/pvss/h/v1";
+const DLEQ_DOMAIN: &[u8] = b"othentic-rng/pvss/dleq/v1";
+const DUAL_CODE_DOMAIN: &[u8] = b"othentic-rng/pvss/dual-code/v1";
+const DEALING_DOMAIN: &[u8] = b"othentic-rng/pvss/dealing/v1";
+const SIGNATURE_DOMAIN: &[u8] = b"othentic-rng/pvss/schnorr/v1";
+const BEACON_DOMAIN: &[u8] = b"othentic-rng/pvss/beacon/v1";
+
+/// A 32-byte value (compressed point, scalar or digest), hex-encoded in JSON.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bytes32(pub [u8; 32]);
+
+impl Bytes32 {
+    fn from_point(point: &RistrettoPoint) -> Self {
+        Bytes32(point.compress().to_bytes())
+    }
+
+    fn from_scalar(scalar: &Scalar) -> Self {
+        Bytes32(scalar.to_bytes())
+    }
+
+    fn point(&self) -> Result<RistrettoPoint, String> {
+        CompressedRistretto(self.0)
+            .decompress()
+            .ok_or_else(|| format!("{} is not a valid group element", self))
+    }
+
+    fn scalar(&self) -> Result<Scalar, String> {
+        Option::from(Scalar::from_canonical_bytes(self.0))
+            .ok_or_else(|| format!("{} is not a canonical scalar", self))
+    }
+
+    /// Parses 64 hex characters.
+    pub fn from_hex(value: &str) -> Result<Self, String> {
+        let bytes = hex::decode(value).map_err(|e| format!("Invalid hex '{}': {}", value, e))?;
+        let bytes: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| format!("Expected 32 bytes in '{}'", value))?;
+        Ok(Bytes32(bytes))
+    }
+}
+
+impl fmt::Display for Bytes32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl fmt::Debug for Bytes32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Bytes32({})", self)
+    }
+}
+
+impl Serialize for Bytes32 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes32 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Bytes32::from_hex(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The second generator `h`, derived by hashing so nobody knows `log_g h`.
+fn second_generator() -> RistrettoPoint {
+    let wide: [u8; 64] = Sha512::digest(SECOND_GENERATOR_DOMAIN).into();
+    RistrettoPoint::from_uniform_bytes(&wide)
+}
+
+/// A participant's PVSS key pair (`pk = h^sk`).
+pub struct KeyPair {
+    secret: Scalar,
+    public: RistrettoPoint,
+}
+
+impl KeyPair {
+    /// Generates a fresh key pair.
+    pub fn generate() -> Self {
+        Self::from_scalar(Scalar::random(&mut OsRng))
+    }
+
+    /// Restores a key pair from its 32-byte secret scalar.
+    pub fn from_secret(secret: &Bytes32) -> Result<Self, String> {
+        let scalar = secret.scalar()?;
+        if scalar == Scalar::ZERO {
+            return Err("PVSS secret key must not be zero".to_string());
+        }
+        Ok(Self::from_scalar(scalar))
+    }
+
+    fn from_scalar(secret: Scalar) -> Self {
+        KeyPair {
+            secret,
+            public: second_generator() * secret,
+        }
+    }
+
+    /// Returns the secret scalar, for persisting the key.
+    pub fn secret(&self) -> Bytes32 {
+        Bytes32::from_scalar(&self.secret)
+    }
+
+    /// Returns the public key.
+    pub fn public(&self) -> Bytes32 {
+        Bytes32::from_point(&self.public)
+    }
+
+    /// Signs `message` with a Schnorr signature over the second generator.
+    pub fn sign(&self, message: &[u8]) -> SchnorrSignature {
+        let nonce = Scalar::random(&mut OsRng);
+        let commitment = second_generator() * nonce;
+        let challenge = signature_challenge(&self.public, &commitment, message);
+        SchnorrSignature {
+            commitment: Bytes32::from_point(&commitment),
+            response: Bytes32::from_scalar(&(nonce + challenge * self.secret)),
+        }
+    }
+}
+
+/// Schnorr signature produced by [`KeyPair::sign`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchnorrSignature {
+    pub commitment: Bytes32,
+    pub response: Bytes32,
+}
+
+/// Checks a signature made by [`KeyPair::sign`].
+pub fn verify_signature(
+    public_key: &Bytes32,
+    message: &[u8],
+    signature: &SchnorrSignature,
+) -> Result<(), String> {
+    let public = public_key.point()?;
+    let commitment = signature.commitment.point()?;
+    let response = signature.response.scalar()?;
+    let challenge = signature_challenge(&public, &commitment, message);
+    if second_generator() * response != commitment + public * challenge {
+        return Err("Schnorr signature verification failed".to_string());
+    }
+    Ok(())
+}
+
+fn signature_challenge(
+    public: &RistrettoPoint,
+    commitment: &RistrettoPoint,
+    message: &[u8],
+) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(SIGNATURE_DOMAIN);
+    hasher.update(public.compress().as_bytes());
+    hasher.update(commitment.compress().as_bytes());
+    hasher.update(message);
+    Scalar::from_bytes_mod_order_wide(&hasher.finalize().into())
+}
+
+/// A member of the sharing committee.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Participant {
+    /// Non-zero evaluation point of the participant's share.
+    pub id: u32,
+    pub public_key: Bytes32,
+}
+
+/// Committee and threshold shared by every dealing of a round.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Params {
+    /// Number of decrypted shares needed to recover a dealer's secret.
+    pub threshold: usize,
+    /// Participants ordered by id.
+    pub participants: Vec<Participant>,
+}
+
+impl Params {
+    /// Validates and normalises the committee.
+    pub fn new(threshold: usize, mut participants: Vec<Participant>) -> Result<Self, String> {
+        participants.sort_by_key(|p| p.id);
+        let ids: BTreeSet<u32> = participants.iter().map(|p| p.id).collect();
+        if ids.len() != participants.len() || ids.contains(&0) {
+            return Err("Participant ids must be distinct and non-zero".to_string());
+        }
+        if threshold == 0 || threshold > participants.len() {
+            return Err(format!(
+                "Threshold must be between 1 and the participant count ({})",
+                participants.len()
+            ));
+        }
+        for participant in &participants {
+            participant.public_key.point()?;
+        }
+        Ok(Params {
+            threshold,
+            participants,
+        })
+    }
+
+    /// Returns the position of participant `id` in [`Params::participants`].
+    pub fn position(&self, id: u32) -> Option<usize> {
+        self.participants.iter().position(|p| p.id == id)
+    }
+
+    fn public_keys(&self) -> Result<Vec<RistrettoPoint>, String> {
+        self.participants
+            .iter()
+            .map(|p| p.public_key.point())
+            .collect()
+    }
+}
+
+/// Non-interactive proof that `log_g1 h1 == log_g2 h2`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DleqProof {
+    pub challenge: Bytes32,
+    pub response: Bytes32,
+}
+
+fn dleq_prove(
+    g1: &RistrettoPoint,
+    h1: &RistrettoPoint,
+    g2: &RistrettoPoint,
+    h2: &RistrettoPoint,
+    witness: &Scalar,
+) -> DleqProof {
+    let nonce = Scalar::random(&mut OsRng);
+    let a1 = g1 * nonce;
+    let a2 = g2 * nonce;
+    let challenge = dleq_challenge(&[g1, h1, g2, h2, &a1, &a2]);
+    DleqProof {
+        challenge: Bytes32::from_scalar(&challenge),
+        response: Bytes32::from_scalar(&(nonce - challenge * witness)),
+    }
+}
+
+fn dleq_verify(
+    g1: &RistrettoPoint,
+    h1: &RistrettoPoint,
+    g2: &RistrettoPoint,
+    h2: &RistrettoPoint,
+    proof: &DleqProof,
+) -> Result<(), String> {
+    let challenge = proof.challenge.scalar()?;
+    let response = proof.response.scalar()?;
+    let a1 = g1 * response + h1 * challenge;
+    let a2 = g2 * response + h2 * challenge;
+    if dleq_challenge(&[g1, h1, g2, h2, &a1, &a2]) != challenge {
+        return Err("DLEQ proof does not verify".to_string());
+    }
+    Ok(())
+}
+
+fn dleq_challenge(points: &[&RistrettoPoint]) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(DLEQ_DOMAIN);
+    for point in points {
+        hasher.update(point.compress().as_bytes());
+    }
+    Scalar::from_bytes_mod_order_wide(&hasher.finalize().into())
+}
+
+/// One dealer's contribution to a round.
+///
+/// `commitments`, `encrypted_shares` and `proofs` are indexed like
+/// [`Params::participants`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dealing {
+    pub round: u64,
+    pub dealer: u32,
+    pub commitments: Vec<Bytes32>,
+    pub encrypted_shares: Vec<Bytes32>,
+    pub proofs: Vec<DleqProof>,
+}
+
+impl Dealing {
+    /// Binding digest of the dealing, referenced by decrypted shares.
+    pub fn digest(&self) -> Bytes32 {
+        let mut hasher = Sha256::new();
+        hasher.update(DEALING_DOMAIN);
+        hasher.update(self.round.to_be_bytes());
+        hasher.update(self.dealer.to_be_bytes());
+        for (i, commitment) in self.commitments.iter().enumerate() {
+            hasher.update(commitment.0);
+            if let Some(share) = self.encrypted_shares.get(i) {
+                hasher.update(share.0);
+            }
+            if let Some(proof) = self.proofs.get(i) {
+                hasher.update(proof.challenge.0);
+                hasher.update(proof.response.0);
+            }
+        }
+        Bytes32(hasher.finalize().into())
+    }
+}
+
+/// Shares a fresh random secret among `params.participants` as `dealer`.
+pub fn deal(params: &Params, round: u64, dealer: u32) -> Result<Dealing, String> {
+    let keys = params.public_keys()?;
+    let coefficients: Vec<Scalar> = (0..params.threshold)
+        .map(|_| Scalar::random(&mut OsRng))
+        .collect();
+    let g = RISTRETTO_BASEPOINT_POINT;
+
+    let mut dealing = Dealing {
+        round,
+        dealer,
+        commitments: Vec::with_capacity(keys.len()),
+        encrypted_shares: Vec::with_capacity(keys.len()),
+        proofs: Vec::with_capacity(keys.len()),
+    };
+    for (participant, key) in params.participants.iter().zip(&keys) {
+        let x = Scalar::from(participant.id);
+        let share = coefficients
+            .iter()
+            .rev()
+            .fold(Scalar::ZERO, |acc, c| acc * x + c);
+        let commitment = g * share;
+        let encrypted = key * share;
+        dealing
+            .proofs
+            .push(dleq_prove(&g, &commitment, key, &encrypted, &share));
+        dealing.commitments.push(Bytes32::from_point(&commitment));
+        dealing
+            .encrypted_shares
+            .push(Bytes32::from_point(&encrypted));
+    }
+    Ok(dealing)
+}
+
+/// Publicly checks a dealing against the committee.
+pub fn verify_dealing(params: &Params, dealing: &Dealing) -> Result<(), String> {
+    let n = params.participants.len();
+    if params.position(dealing.dealer).is_none() {
+        return Err(format!("Dealer {} is not a participant", dealing.dealer));
+    }
+    if dealing.commitments.len() != n
+        || dealing.encrypted_shares.len() != n
+        || dealing.proofs.len() != n
+    {
+        return Err(format!(
+            "Dealing from {} does not carry one share per participant",
+            dealing.dealer
+        ));
+    }
+
+    let keys = params.public_keys()?;
+    let g = RISTRETTO_BASEPOINT_POINT;
+    let mut commitments = Vec::with_capacity(n);
+    for (i, key) in keys.iter().enumerate() {
+        let commitment = dealing.commitments[i].point()?;
+        let encrypted = dealing.encrypted_shares[i].point()?;
+        dleq_verify(&g, &commitment, key, &encrypted, &dealing.proofs[i]).map_err(|e| {
+            format!(
+                "Share for participant {} in dealing from {}: {}",
+                params.participants[i].id, dealing.dealer, e
+            )
+        })?;
+        commitments.push(commitment);
+    }
+
+    // Dual-code check: for f of degree at most n - t - 1 and
+    // w_i = prod_{j != i} 1 / (x_i - x_j), sum w_i f(x_i) v_i is the identity
+    // exactly when the commitments lie on a polynomial of degree below t.
+    if n > params.threshold {
+        let xs: Vec<Scalar> = params
+            .participants
+            .iter()
+            .map(|p| Scalar::from(p.id))
+            .collect();
+        let f = dual_code_polynomial(&dealing.digest(), n - params.threshold);
+        let check: RistrettoPoint = xs
+            .iter()
+            .enumerate()
+            .map(|(i, xi)| {
+                let weight = xs
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .fold(Scalar::ONE, |acc, (_, xj)| acc * (xi - xj))
+                    .invert();
+                let fx = f.iter().rev().fold(Scalar::ZERO, |acc, c| acc * xi + c);
+                commitments[i] * (weight * fx)
+            })
+            .sum();
+        if check != RistrettoPoint::identity() {
+            return Err(format!(
+                "Commitments in dealing from {} are not of degree below {}",
+                dealing.dealer, params.threshold
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Derives `len` pseudo-random coefficients from the dealing digest.
+fn dual_code_polynomial(digest: &Bytes32, len: usize) -> Vec<Scalar> {
+    (0..len as u64)
+        .map(|k| {
+            let mut hasher = Sha512::new();
+            hasher.update(DUAL_CODE_DOMAIN);
+            hasher.update(digest.0);
+            hasher.update(k.to_be_bytes());
+            Scalar::from_bytes_mod_order_wide(&hasher.finalize().into())
+        })
+        .collect()
+}
+
+/// A participant's decryption of its share in one dealing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecryptedShare {
+    pub dealer: u32,
+    pub participant: u32,
+    /// [`Dealing::digest`] of the dealing the share was decrypted from.
+    pub dealing: Bytes32,
+    /// `h^p(participant)`.
+    pub share: Bytes32,
+    pub proof: DleqProof,
+}
+
+/// Decrypts participant `id`'s share of `dealing` with `key`.
+pub fn decrypt_share(
+    params: &Params,
+    key: &KeyPair,
+    id: u32,
+    dealing: &Dealing,
+) -> Result<DecryptedShare, String> {
+    let index = params
+        .position(id)
+        .ok_or_else(|| format!("Participant {} is not in the committee", id))?;
+    let encrypted = dealing
+        .encrypted_shares
+        .get(index)
+        .ok_or_else(|| format!("Dealing from {} has no share for {}", dealing.dealer, id))?
+        .point()?;
+    let h = second_generator();
+    let share = encrypted * key.secret.invert();
+    Ok(DecryptedShare {
+        dealer: dealing.dealer,
+        participant: id,
+        dealing: dealing.digest(),
+        share: Bytes32::from_point(&share),
+        proof: dleq_prove(&h, &key.public, &share, &encrypted, &key.secret),
+    })
+}
+
+/// Checks that `share` is the correct decryption of its slot in `dealing`.
+pub fn verify_decryption(
+    params: &Params,
+    dealing: &Dealing,
+    share: &DecryptedShare,
+) -> Result<(), String> {
+    if share.dealer != dealing.dealer || share.dealing != dealing.digest() {
+        return Err(format!(
+            "Decrypted share from {} refers to a different dealing",
+            share.participant
+        ));
+    }
+    let index = params
+        .position(share.participant)
+        .ok_or_else(|| format!("Participant {} is not in the committee", share.participant))?;
+    let public = params.participants[index].public_key.point()?;
+    let encrypted = dealing
+        .encrypted_shares
+        .get(index)
+        .ok_or_else(|| format!("Dealing from {} is truncated", dealing.dealer))?
+        .point()?;
+    dleq_verify(
+        &second_generator(),
+        &public,
+        &share.share.point()?,
+        &encrypted,
+        &share.proof,
+    )
+    .map_err(|e| format!("Decryption by {}: {}", share.participant, e))
+}
+
+/// Recovers `h^s` from the first `threshold` of `shares` (already verified).
+fn recover_secret(params: &Params, shares: &[DecryptedShare]) -> Result<RistrettoPoint, String> {
+    let shares = &shares[..params.threshold];
+    let xs: Vec<Scalar> = shares.iter().map(|s| Scalar::from(s.participant)).collect();
+    let mut secret = RistrettoPoint::identity();
+    for (i, share) in shares.iter().enumerate() {
+        let lambda = xs
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .fold(Scalar::ONE, |acc, (_, xj)| acc * xj * (xj - xs[i]).invert());
+        secret += share.share.point()? * lambda;
+    }
+    Ok(secret)
+}
+
+/// Transcript proving how a round's beacon value was obtained.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BeaconProof {
+    pub round: u64,
+    /// The qualified dealings, ordered by dealer.
+    pub dealings: Vec<Dealing>,
+    /// `threshold` decrypted shares per qualified dealing.
+    pub decryptions: Vec<DecryptedShare>,
+    pub value: Bytes32,
+}
+
+/// Combines qualified dealings with their verified decryptions.
+///
+/// Each entry pairs a dealing with at least `threshold` decrypted shares from
+/// distinct participants. The beacon value hashes the round number and the
+/// sum of all recovered secrets, so it is uniform as long as one qualified
+/// dealer was honest.
+pub fn combine(
+    params: &Params,
+    round: u64,
+    mut qualified: Vec<(Dealing, Vec<DecryptedShare>)>,
+) -> Result<BeaconProof, String> {
+    if qualified.is_empty() {
+        return Err(format!("Round {} has no qualified dealings", round));
+    }
+    qualified.sort_by_key(|(dealing, _)| dealing.dealer);
+
+    let mut total = RistrettoPoint::identity();
+    let mut proof = BeaconProof {
+        round,
+        dealings: Vec::with_capacity(qualified.len()),
+        decryptions: Vec::new(),
+        value: Bytes32([0; 32]),
+    };
+    for (dealing, mut shares) in qualified {
+        if shares.len() < params.threshold {
+            return Err(format!(
+                "Dealing from {} has {} decrypted share(s), {} required",
+                dealing.dealer,
+                shares.len(),
+                params.threshold
+            ));
+        }
+        shares.sort_by_key(|s| s.participant);
+        shares.truncate(params.threshold);
+        total += recover_secret(params, &shares)?;
+        proof.dealings.push(dealing);
+        proof.decryptions.extend(shares);
+    }
+    proof.value = beacon_value(round, &total);
+    Ok(proof)
+}
+
+/// Re-verifies every dealing and decryption in `proof` and recomputes its
+/// value; at least `threshold` dealers must have contributed, so that one of
+/// them is honest whenever that many operators are.
+pub fn verify_beacon(params: &Params, proof: &BeaconProof) -> Result<Bytes32, String> {
+    if proof.dealings.len() < params.threshold {
+        return Err(format!(
+            "Beacon proof combines {} dealing(s), {} required",
+            proof.dealings.len(),
+            params.threshold
+        ));
+    }
+    let mut total = RistrettoPoint::identity();
+    let mut dealers = BTreeSet::new();
+    for dealing in &proof.dealings {
+        if dealing.round != proof.round {
+            return Err(format!(
+                "Dealing from {} is for another round",
+                dealing.dealer
+            ));
+        }
+        if !dealers.insert(dealing.dealer) {
+            return Err(format!("Dealer {} appears more than once", dealing.dealer));
+        }
+        verify_dealing(params, dealing)?;
+
+        let shares: Vec<DecryptedShare> = proof
+            .decryptions
+            .iter()
+            .filter(|s| s.dealer == dealing.dealer)
+            .cloned()
+            .collect();
+        let participants: BTreeSet<u32> = shares.iter().map(|s| s.participant).collect();
+        if shares.len() != params.threshold || participants.len() != shares.len() {
+            return Err(format!(
+                "Dealing from {} needs {} decryptions by distinct participants",
+                dealing.dealer, params.threshold
+            ));
+        }
+        for share in &shares {
+            verify_decryption(params, dealing, share)?;
+        }
+        total += recover_secret(params, &shares)?;
+    }
+    if proof.decryptions.len() != proof.dealings.len() * params.threshold {
+        return Err("Beacon proof carries decryptions for unknown dealings".to_string());
+    }
+
+    let value = beacon_value(proof.round, &total);
+    if value != proof.value {
+        return Err("Beacon value does not match the recovered secrets".to_string());
+    }
+    Ok(value)
+}
+
+fn beacon_value(round: u64, total: &RistrettoPoint) -> Bytes32 {
+    let mut hasher = Sha256::new();
+    hasher.update(BEACON_DOMAIN);
+    hasher.update(round.to_be_bytes());
+    hasher.update(total.compress().as_bytes());
+    Bytes32(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn committee(n: u32, threshold: usize) -> (Params, Vec<KeyPair>) {
+        let keys: Vec<KeyPair> = (0..n).map(|_| KeyPair::generate()).collect();
+        let participants = keys
+            .iter()
+            .zip(1..)
+            .map(|(key, id)| Participant {
+                id,
+                public_key: key.public(),
+            })
+            .collect();
+        (Params::new(threshold, participants).unwrap(), keys)
+    }
+
+    fn decryptions(params: &Params, keys: &[KeyPair], dealing: &Dealing) -> Vec<DecryptedShare> {
+        keys.iter()
+            .zip(1..)
+            .map(|(key, id)| decrypt_share(params, key, id, dealing).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn second_generator_is_fixed() {
+        // Every key and dealing depends on it; changing it breaks them all.
+        assert_eq!(
+            Bytes32::from_point(&second_generator()).to_string(),
+            "74293012c592c89e72c808b12201b3d76a9311e26a2eb7b588125b111893aa29"
+        );
+        assert_ne!(second_generator(), RISTRETTO_BASEPOINT_POINT);
+    }
+
+    #[test]
+    fn round_trip_to_a_verified_beacon() {
+        let (params, keys) = committee(5, 3);
+        let dealings: Vec<Dealing> = (1..=3).map(|d| deal(&params, 7, d).unwrap()).collect();
+        let mut qualified = Vec::new();
+        for dealing in &dealings {
+            verify_dealing(&params, dealing).unwrap();
+            let shares = decryptions(&params, &keys, dealing);
+            for share in &shares {
+                verify_decryption(&params, dealing, share).unwrap();
+            }
+            qualified.push((dealing.clone(), shares));
+        }
+        let proof = combine(&params, 7, qualified.clone()).unwrap();
+        assert_eq!(verify_beacon(&params, &proof).unwrap(), proof.value);
+
+        // Any threshold of decryptions recovers the same secrets.
+        let other: Vec<_> = qualified
+            .into_iter()
+            .map(|(dealing, shares)| (dealing, shares[2..].to_vec()))
+            .collect();
+        assert_eq!(combine(&params, 7, other).unwrap().value, proof.value);
+    }
+
+    #[test]
+    fn bad_dealings_are_rejected() {
+        let (params, _) = committee(5, 2);
+        let dealing = deal(&params, 1, 1).unwrap();
+
+        let mut swapped = dealing.clone();
+        swapped.encrypted_shares.swap(0, 1);
+        assert!(verify_dealing(&params, &swapped).is_err());
+
+        let mut truncated = dealing.clone();
+        truncated.proofs.pop();
+        assert!(verify_dealing(&params, &truncated).is_err());
+
+        let mut stranger = dealing;
+        stranger.dealer = 9;
+        assert!(verify_dealing(&params, &stranger).is_err());
+
+        // Valid proofs on a polynomial of too high a degree.
+        let wider = Params::new(3, params.participants.clone()).unwrap();
+        let high = deal(&wider, 1, 1).unwrap();
+        verify_dealing(&wider, &high).unwrap();
+        let error = verify_dealing(&params, &high).unwrap_err();
+        assert!(error.contains("degree"), "{}", error);
+    }
+
+    #[test]
+    fn bad_decryptions_and_transcripts_are_rejected() {
+        let (params, keys) = committee(4, 2);
+        let dealing = deal(&params, 3, 2).unwrap();
+        let mut shares = decryptions(&params, &keys, &dealing);
+        let other = deal(&params, 3, 3).unwrap();
+        let others = decryptions(&params, &keys, &other);
+
+        let mut forged = shares[0].clone();
+        forged.share = shares[1].share;
+        assert!(verify_decryption(&params, &dealing, &forged).is_err());
+        let wrong_key = decrypt_share(&params, &keys[1], 1, &dealing).unwrap();
+        assert!(verify_decryption(&params, &dealing, &wrong_key).is_err());
+
+        let proof = combine(
+            &params,
+            3,
+            vec![
+                (dealing.clone(), shares.clone()),
+                (other.clone(), others.clone()),
+            ],
+        )
+        .unwrap();
+        verify_beacon(&params, &proof).unwrap();
+        let alone = combine(&params, 3, vec![(other, others)]).unwrap();
+        assert!(verify_beacon(&params, &alone).is_err());
+        let mut tampered = proof.clone();
+        tampered.value.0[0] ^= 1;
+        assert!(verify_beacon(&params, &tampered).is_err());
+        let mut replayed = proof.clone();
+        replayed.round = 4;
+        assert!(verify_beacon(&params, &replayed).is_err());
+        let mut short = proof;
+        short.decryptions.pop();
+        assert!(verify_beacon(&params, &short).is_err());
+
+        shares.truncate(1);
+        assert!(combine(&params, 3, vec![(dealing, shares)]).is_err());
+        assert!(combine(&params, 3, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn schnorr_signatures() {
+        let key = KeyPair::generate();
+        let signature = key.sign(b"message");
+        verify_signature(&key.public(), b"message", &signature).unwrap();
+        assert!(verify_signature(&key.public(), b"other", &signature).is_err());
+        assert!(verify_signature(&KeyPair::generate().public(), b"message", &signature).is_err());
+
+        let restored = KeyPair::from_secret(&key.secret()).unwrap();
+        assert_eq!(restored.public(), key.public());
+        assert!(KeyPair::from_secret(&Bytes32([0; 32])).is_err());
+    }
+
+    #[test]
+    fn committees_are_validated() {
+        let key = KeyPair::generate().public();
+        let member = |id| Participant {
+            id,
+            public_key: key,
+        };
+        assert!(Params::new(1, vec![member(0)]).is_err());
+        assert!(Params::new(1, vec![member(1), member(1)]).is_err());
+        assert!(Params::new(0, vec![member(1)]).is_err());
+        assert!(Params::new(2, vec![member(1)]).is_err());
+        let params = Params::new(1, vec![member(2), member(1)]).unwrap();
+        assert_eq!(params.position(1), Some(0));
+    }
+}
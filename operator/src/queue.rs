@@ -0,0 +1,143 @@
+// src/queue.rs
+
+//! Bounded, blocking priority queue feeding the task workers.
+//!
+//! Items are served strictly by [`Priority`] class and first-in first-out
+//! within a class, so an on-chain fulfillment never waits behind a backlog of
+//! best-effort API calls.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Condvar, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// Scheduling class of a task. Higher classes are always dequeued first.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// Best-effort requests arriving over the public API.
+    #[default]
+    Api,
+    /// Requests backing an on-chain fulfillment, where lateness costs gas.
+    OnChain,
+}
+
+impl Priority {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Priority::Api => "api",
+            Priority::OnChain => "on_chain",
+        }
+    }
+}
+
+struct Entry<T> {
+    priority: Priority,
+    seq: u64,
+    item: T,
+}
+
+impl<T> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority && self.seq == other.seq
+    }
+}
+
+impl<T> Eq for Entry<T> {}
+
+impl<T> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Entry<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max-heap: higher priority first, then lower sequence.
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+struct Inner<T> {
+    heap: BinaryHeap<Entry<T>>,
+    next_seq: u64,
+}
+
+/// `TaskQueue` is a bounded multi-producer, multi-consumer priority queue.
+pub struct TaskQueue<T> {
+    capacity: usize,
+    inner: Mutex<Inner<T>>,
+    available: Condvar,
+}
+
+impl<T> TaskQueue<T> {
+    /// Creates a queue holding at most `capacity` items.
+    pub fn new(capacity: usize) -> Self {
+        TaskQueue {
+            capacity,
+            inner: Mutex::new(Inner {
+                heap: BinaryHeap::new(),
+                next_seq: 0,
+            }),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Enqueues `item`, handing it back if the queue is full.
+    pub fn push(&self, priority: Priority, item: T) -> Result<(), T> {
+        let mut inner = self.inner.lock().expect("queue lock poisoned");
+        if inner.heap.len() >= self.capacity {
+            return Err(item);
+        }
+        let seq = inner.next_seq;
+        inner.next_seq += 1;
+        inner.heap.push(Entry {
+            priority,
+            seq,
+            item,
+        });
+        self.available.notify_one();
+        Ok(())
+    }
+
+    /// Blocks until an item is available and returns the highest-priority one.
+    pub fn pop(&self) -> (Priority, T) {
+        let mut inner = self.inner.lock().expect("queue lock poisoned");
+        loop {
+            if let Some(entry) = inner.heap.pop() {
+                return (entry.priority, entry.item);
+            }
+            inner = self.available.wait(inner).expect("queue lock poisoned");
+        }
+    }
+
+    /// Removes and returns every queued item, highest priority first.
+    pub fn drain(&self) -> Vec<(Priority, T)> {
+        let mut inner = self.inner.lock().expect("queue lock poisoned");
+        let mut items = Vec::with_capacity(inner.heap.len());
+        while let Some(entry) = inner.heap.pop() {
+            items.push((entry.priority, entry.item));
+        }
+        items
+    }
+
+    /// Returns the number of queued items in `priority`.
+    pub fn depth(&self, priority: Priority) -> usize {
+        let inner = self.inner.lock().expect("queue lock poisoned");
+        inner.heap.iter().filter(|e| e.priority == priority).count()
+    }
+
+    /// Returns the total number of queued items.
+    pub fn len(&self) -> usize {
+        self.inner.lock().expect("queue lock poisoned").heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
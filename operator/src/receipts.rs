@@ -0,0 +1,188 @@
+// src/receipts.rs
+
+//! Signed receipts for sets of served attestations.
+//!
+//! A consumer that fetches many values at once (`POST /task/execute_batch`,
+//! `POST /attestations/export`) also gets a [`Receipt`]: how many values it
+//! was served, the Merkle root (see [`crate::merkle`]) over them and the time
+//! range they were served in, signed by the attestation key. Holding the
+//! receipt and the attestations, the consumer can later prove to an auditor
+//! exactly what the operator served; [`Receipt::check`] recomputes the count
+//! and the root.
+//!
+//! Every plain attestation is one value, and so is every item of a batch;
+//! leaves follow the order the values were served in:
+//!
+//! ```text
+//! leaf = SHA-256(0x00 || u32 len || task ID || digest)
+//! ```
+//!
+//! where `digest` is the attestation's payload digest, or for a batch item
+//! `SHA-256(ITEM_DOMAIN || root payload digest || item leaf)`. Receipts are
+//! signed with Ed25519ph under [`CONTEXT`], so one can never be replayed as
+//! an attestation signature. Only the running operator holds its key, so the
+//! `export` command does not issue receipts.
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::attester::RngAttester;
+use crate::encoding::Encoding;
+use crate::epochs::EpochCertificate;
+use crate::merkle;
+use crate::tasks::{unix_millis, TaskOutcome};
+
+/// Ed25519ph context receipts are signed under.
+pub const CONTEXT: &[u8] = b"othentic-rng/receipt/v1";
+
+const ITEM_DOMAIN: &[u8] = b"othentic-rng/receipt-item/v1";
+
+/// A signed statement of what a consumer was served.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Receipt {
+    /// Values served.
+    pub count: u64,
+    /// Hex Merkle root over the values, in order.
+    pub root: String,
+    /// Unix ms the first and the last value were served at.
+    pub from: u64,
+    pub to: u64,
+    /// Tenant the values were served to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// Unix ms the receipt was signed at.
+    pub issued_at: u64,
+    pub public_key: String,
+    pub signature: String,
+    /// Master certificate of the signing key, when the operator runs key
+    /// epochs; not covered by `signature`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch_certificate: Option<EpochCertificate>,
+}
+
+impl Receipt {
+    /// Signs a receipt for `served`, each outcome with the time it was
+    /// served at, in order.
+    pub fn issue(
+        attester: &RngAttester,
+        served: &[(u64, TaskOutcome)],
+        tenant: Option<&str>,
+    ) -> Result<Self, String> {
+        let outcomes: Vec<TaskOutcome> = served.iter().map(|(_, o)| o.clone()).collect();
+        let leaves = leaves(&outcomes)?;
+        let issued_at = unix_millis();
+        let mut receipt = Receipt {
+            count: leaves.len() as u64,
+            root: hex::encode(merkle::root(&leaves)),
+            from: served.iter().map(|(at, _)| *at).min().unwrap_or(issued_at),
+            to: served.iter().map(|(at, _)| *at).max().unwrap_or(issued_at),
+            tenant: tenant.map(str::to_string),
+            issued_at,
+            public_key: hex::encode(attester.get_public_key().as_bytes()),
+            signature: String::new(),
+            epoch_certificate: attester.epoch_certificate().cloned(),
+        };
+        let signature = attester.sign_with_context(CONTEXT, &receipt.signed_bytes())?;
+        receipt.signature = hex::encode(signature.to_bytes());
+        Ok(receipt)
+    }
+
+    /// Checks the signature, and the certificate of the signing key if any,
+    /// and returns the key that made it.
+    pub fn verify(&self) -> Result<VerifyingKey, String> {
+        let key: [u8; 32] = hex::decode(&self.public_key)
+            .map_err(|e| format!("Invalid receipt public key: {}", e))?
+            .try_into()
+            .map_err(|_| "Receipt public key must be 32 bytes".to_string())?;
+        let key = VerifyingKey::from_bytes(&key)
+            .map_err(|e| format!("Invalid receipt public key: {}", e))?;
+        let signature: [u8; 64] = hex::decode(&self.signature)
+            .map_err(|e| format!("Invalid receipt signature: {}", e))?
+            .try_into()
+            .map_err(|_| "Receipt signature must be 64 bytes".to_string())?;
+        RngAttester::verify_with_context(
+            &key,
+            CONTEXT,
+            &self.signed_bytes(),
+            &Signature::from_bytes(&signature),
+        )?;
+        if let Some(certificate) = &self.epoch_certificate {
+            if certificate.verify()?.1 != key {
+                return Err("Epoch certificate is for a different key".to_string());
+            }
+        }
+        Ok(key)
+    }
+
+    /// Checks the signature and that the receipt covers exactly `outcomes`,
+    /// in order; returns the key that signed.
+    pub fn check(&self, outcomes: &[TaskOutcome]) -> Result<VerifyingKey, String> {
+        let key = self.verify()?;
+        let leaves = leaves(outcomes)?;
+        if leaves.len() as u64 != self.count {
+            return Err(format!(
+                "receipt covers {} values, not the {} given",
+                self.count,
+                leaves.len()
+            ));
+        }
+        if hex::encode(merkle::root(&leaves)) != self.root {
+            return Err("receipt root does not match the values given".to_string());
+        }
+        Ok(key)
+    }
+
+    /// Length-prefixed encoding of every field but the signature and the
+    /// certificate.
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&self.count.to_be_bytes());
+        for field in [
+            self.root.as_bytes(),
+            self.tenant.as_deref().unwrap_or("").as_bytes(),
+        ] {
+            data.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            data.extend_from_slice(field);
+        }
+        data.push(u8::from(self.tenant.is_some()));
+        data.extend_from_slice(&self.from.to_be_bytes());
+        data.extend_from_slice(&self.to.to_be_bytes());
+        data.extend_from_slice(&self.issued_at.to_be_bytes());
+        data.extend_from_slice(self.public_key.as_bytes());
+        data
+    }
+}
+
+/// The receipt leaves of `outcomes`, one per value served.
+pub fn leaves(outcomes: &[TaskOutcome]) -> Result<Vec<[u8; 32]>, String> {
+    let mut leaves = Vec::new();
+    for outcome in outcomes {
+        let outcome = outcome.encoded(Encoding::Hex)?;
+        let digest = outcome.to_attestation()?.payload.digest();
+        match &outcome.batch {
+            Some(items) => {
+                for item in items {
+                    let item_digest: [u8; 32] = Sha256::new()
+                        .chain_update(ITEM_DOMAIN)
+                        .chain_update(digest)
+                        .chain_update(item.leaf()?)
+                        .finalize()
+                        .into();
+                    leaves.push(leaf(&item.task_id, &item_digest));
+                }
+            }
+            None => leaves.push(leaf(&outcome.task_id, &digest)),
+        }
+    }
+    Ok(leaves)
+}
+
+fn leaf(task_id: &str, digest: &[u8; 32]) -> [u8; 32] {
+    let mut data = Vec::with_capacity(4 + task_id.len() + 32);
+    data.extend_from_slice(&(task_id.len() as u32).to_be_bytes());
+    data.extend_from_slice(task_id.as_bytes());
+    data.extend_from_slice(digest);
+    merkle::leaf_hash(&data)
+}
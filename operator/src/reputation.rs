@@ -0,0 +1,484 @@
+// src/reputation.rs
+
+//! Reputation of other operators, from what they are seen to sign.
+//!
+//! An aggregating consumer learns about operators from the outcomes and
+//! heartbeats gossiped to it: `POST /reputation/attestations`,
+//! `POST /reputation/heartbeats`, outcomes sent to `/task/validate` and the
+//! heartbeats of the beacon committee. [`Reputation`] checks each signature
+//! and tracks, per operator key (the master key of epoch-certified
+//! attestations and heartbeats, otherwise the signing key):
+//!
+//! - the response rate: heartbeats received out of those the sequence
+//!   numbers show were sent, a new boot ID marking a restart; a heartbeat
+//!   no newer than the last one seen is ignored, so replays count for
+//!   nothing;
+//! - latency: from the signed timestamp of each counted heartbeat to its
+//!   arrival here; only the first copy of a heartbeat is counted, so
+//!   holding one back and relaying it late cannot inflate it;
+//! - equivocations: two validly signed outcomes with different values for
+//!   the same task, kept as evidence. Only outcomes whose signatures cover
+//!   the task count: a VRF output over the task key, or a pooled value with
+//!   its task binding. A task ID nothing signs can be put on any genuine
+//!   outcome, so two outcomes of another task prove nothing. ABI forms do
+//!   not count either: their secp256k1 address is not covered by the
+//!   operator's ed25519 signature, so anybody can re-sign one.
+//!
+//! The score is the response rate times `latency_target` over the median
+//! latency (capped at 1), and 0 after any equivocation. An operator scoring
+//! below `min_score` is reported as excluded. At most `max_operators` keys
+//! are tracked; outcomes and heartbeats of keys beyond that are refused.
+//! Scores live in memory and are served at `GET /reputation` and
+//! `GET /reputation/{key}`.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::{self, ReputationConfig};
+use crate::encoding::Encoding;
+use crate::heartbeat::Heartbeat;
+use crate::metrics::Metrics;
+use crate::reverify;
+use crate::tasks::{unix_millis, TaskOutcome};
+
+/// Equivocations kept as evidence per operator; later ones are only counted.
+const MAX_EVIDENCE: usize = 16;
+
+/// An outcome gossiped for scoring.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Observation {
+    pub outcome: TaskOutcome,
+}
+
+/// Two outcomes an operator signed for the same task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Equivocation {
+    pub task_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    /// Unix ms the second outcome was seen.
+    pub detected_at: u64,
+    pub first: TaskOutcome,
+    pub second: TaskOutcome,
+}
+
+/// The standing of one operator, as served by the API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatorScore {
+    /// Hex ed25519 key the operator is tracked under.
+    pub public_key: String,
+    /// On-chain address its heartbeats name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// From 0 to 1.
+    pub score: f64,
+    /// Whether consumers should leave the operator out.
+    pub excluded: bool,
+    /// Heartbeats received out of those sent; absent before the second one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_rate: Option<f64>,
+    pub heartbeats: u64,
+    pub missed_heartbeats: u64,
+    pub attestations: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_p50_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_p90_ms: Option<u64>,
+    pub equivocations: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub evidence: Vec<Equivocation>,
+    pub first_seen: u64,
+    pub last_seen: u64,
+}
+
+/// What is known about one operator.
+#[derive(Default)]
+struct Record {
+    address: Option<String>,
+    heartbeats: u64,
+    missed_heartbeats: u64,
+    /// Boot ID, sequence and timestamp of the latest heartbeat.
+    last_heartbeat: Option<(String, u64, u64)>,
+    attestations: u64,
+    latencies: VecDeque<u64>,
+    /// Recent task keys, oldest first, with the outcome each was fulfilled
+    /// with and the digest of its value.
+    recent: HashMap<String, ([u8; 32], TaskOutcome)>,
+    order: VecDeque<String>,
+    equivocations: u64,
+    evidence: Vec<Equivocation>,
+    first_seen: u64,
+    last_seen: u64,
+}
+
+impl Record {
+    fn seen(&mut self, now: u64) {
+        if self.first_seen == 0 {
+            self.first_seen = now;
+        }
+        self.last_seen = now;
+    }
+}
+
+/// Scores of the operators observed so far.
+pub struct Reputation {
+    latency_target: Duration,
+    min_score: f64,
+    window: usize,
+    max_operators: usize,
+    operators: Mutex<BTreeMap<String, Record>>,
+    metrics: Arc<Metrics>,
+}
+
+impl Reputation {
+    pub fn from_config(config: &ReputationConfig, metrics: Arc<Metrics>) -> Result<Self, String> {
+        Ok(Reputation {
+            latency_target: config::parse_duration(&config.latency_target)?,
+            min_score: config.min_score,
+            window: config.window,
+            max_operators: config.max_operators,
+            operators: Mutex::new(BTreeMap::new()),
+            metrics,
+        })
+    }
+
+    /// The record of operator `key`, unless `max_operators` are tracked
+    /// already.
+    fn record<'a>(
+        &self,
+        operators: &'a mut BTreeMap<String, Record>,
+        key: &str,
+    ) -> Result<&'a mut Record, String> {
+        if !operators.contains_key(key) && operators.len() >= self.max_operators {
+            return Err(format!(
+                "Already tracking {} operators; not adding {}",
+                self.max_operators, key
+            ));
+        }
+        Ok(operators.entry(key.to_string()).or_default())
+    }
+
+    /// Checks a gossiped heartbeat and counts it, with its latency; one no
+    /// newer than the last seen from the operator is ignored.
+    pub fn observe_heartbeat(&self, heartbeat: &Heartbeat) -> Result<OperatorScore, String> {
+        heartbeat.verify()?;
+        let key = heartbeat.operator_key();
+        let now = unix_millis();
+        let mut operators = self.operators.lock().expect("reputation lock poisoned");
+        let record = self.record(&mut operators, &key)?;
+        record.seen(now);
+        let counted = match &record.last_heartbeat {
+            Some((_, _, at)) if heartbeat.timestamp <= *at => false,
+            Some((boot, last, _)) if *boot == heartbeat.boot_id => {
+                if heartbeat.sequence <= *last {
+                    false
+                } else {
+                    record.missed_heartbeats += heartbeat.sequence - last - 1;
+                    true
+                }
+            }
+            // A new process numbers its heartbeats from the start again.
+            _ => true,
+        };
+        if counted {
+            record.address = Some(heartbeat.operator.clone());
+            record.heartbeats += 1;
+            record.last_heartbeat = Some((
+                heartbeat.boot_id.clone(),
+                heartbeat.sequence,
+                heartbeat.timestamp,
+            ));
+            record
+                .latencies
+                .push_back(now.saturating_sub(heartbeat.timestamp));
+            while record.latencies.len() > self.window {
+                record.latencies.pop_front();
+            }
+        }
+        let score = self.score(&key, record);
+        self.export(&score);
+        Ok(score)
+    }
+
+    /// Checks a gossiped outcome and, if its task is signed, looks for an
+    /// earlier outcome of the same task with a different value.
+    pub fn observe_attestation(&self, observation: &Observation) -> Result<OperatorScore, String> {
+        let outcome = observation.outcome.encoded(Encoding::Hex)?;
+        if outcome.timelock.is_some() {
+            return Err("A sealed outcome carries no value to score".to_string());
+        }
+        reverify::check_outcome(&outcome, &[])
+            .map_err(|(kind, detail)| format!("{}: {}", kind.as_str(), detail))?;
+        let key = match (&outcome.epoch, &outcome.epoch_certificate) {
+            (Some(_), Some(certificate)) => certificate.master_public_key.to_lowercase(),
+            _ => outcome.public_key.to_lowercase(),
+        };
+        let now = unix_millis();
+        let mut operators = self.operators.lock().expect("reputation lock poisoned");
+        let record = self.record(&mut operators, &key)?;
+        record.seen(now);
+        let Some(task) = signed_task(&outcome) else {
+            record.attestations += 1;
+            let score = self.score(&key, record);
+            self.export(&score);
+            return Ok(score);
+        };
+        let task = match &outcome.domain {
+            Some(domain) => format!("{}/{}", domain, task),
+            None => task,
+        };
+        let value: [u8; 32] = Sha256::digest(outcome.random_number.to_lowercase()).into();
+        match record.recent.get(&task) {
+            Some((seen, _)) if *seen == value => return Ok(self.score(&key, record)),
+            Some((_, first)) => {
+                let equivocation = Equivocation {
+                    task_id: outcome.task_id.clone(),
+                    domain: outcome.domain.clone(),
+                    detected_at: now,
+                    first: first.clone(),
+                    second: outcome.clone(),
+                };
+                record.equivocations += 1;
+                if record.evidence.len() < MAX_EVIDENCE {
+                    record.evidence.push(equivocation);
+                }
+                self.metrics
+                    .inc_counter("rng_equivocations_total", &[("operator", &key)], 1);
+            }
+            None => {
+                record.recent.insert(task.clone(), (value, outcome.clone()));
+                record.order.push_back(task);
+                while record.order.len() > self.window {
+                    if let Some(oldest) = record.order.pop_front() {
+                        record.recent.remove(&oldest);
+                    }
+                }
+            }
+        }
+        record.attestations += 1;
+        let score = self.score(&key, record);
+        self.export(&score);
+        Ok(score)
+    }
+
+    /// Every operator observed, by key.
+    pub fn scores(&self) -> Vec<OperatorScore> {
+        let operators = self.operators.lock().expect("reputation lock poisoned");
+        operators
+            .iter()
+            .map(|(key, record)| self.score(key, record))
+            .collect()
+    }
+
+    /// The operator with hex key `key`, if observed.
+    pub fn score_of(&self, key: &str) -> Option<OperatorScore> {
+        let key = key.to_lowercase();
+        let operators = self.operators.lock().expect("reputation lock poisoned");
+        operators.get(&key).map(|record| self.score(&key, record))
+    }
+
+    fn score(&self, key: &str, record: &Record) -> OperatorScore {
+        let sent = record.heartbeats + record.missed_heartbeats;
+        let response_rate = (record.heartbeats > 1).then(|| record.heartbeats as f64 / sent as f64);
+        let mut latencies: Vec<u64> = record.latencies.iter().copied().collect();
+        latencies.sort_unstable();
+        let percentile = |q: f64| -> Option<u64> {
+            let last = latencies.len().checked_sub(1)?;
+            Some(latencies[((last as f64) * q).round() as usize])
+        };
+        let latency_p50_ms = percentile(0.5);
+        let latency_factor = match latency_p50_ms {
+            Some(p50) if p50 > 0 => (self.latency_target.as_millis() as f64 / p50 as f64).min(1.0),
+            _ => 1.0,
+        };
+        let score = if record.equivocations > 0 {
+            0.0
+        } else {
+            response_rate.unwrap_or(1.0) * latency_factor
+        };
+        OperatorScore {
+            public_key: key.to_string(),
+            address: record.address.clone(),
+            score,
+            excluded: score < self.min_score,
+            response_rate,
+            heartbeats: record.heartbeats,
+            missed_heartbeats: record.missed_heartbeats,
+            attestations: record.attestations,
+            latency_p50_ms,
+            latency_p90_ms: percentile(0.9),
+            equivocations: record.equivocations,
+            evidence: record.evidence.clone(),
+            first_seen: record.first_seen,
+            last_seen: record.last_seen,
+        }
+    }
+
+    fn export(&self, score: &OperatorScore) {
+        self.metrics.set_gauge(
+            "rng_operator_score",
+            &[("operator", &score.public_key)],
+            score.score,
+        );
+    }
+}
+
+/// The task the signatures on `outcome` cover, if any: the VRF input the
+/// value was derived from, or the task a pooled value is bound to
+/// (checked by [`reverify::check_outcome`]).
+fn signed_task(outcome: &TaskOutcome) -> Option<String> {
+    if let Some(vrf) = &outcome.vrf {
+        let key = outcome.key();
+        if hex::decode(&vrf.input).ok()? == key.as_bytes() {
+            return Some(key);
+        }
+    }
+    outcome.slot.map(|_| outcome.task_id.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attester::RngAttester;
+    use crate::performer::RngPerformer;
+    use crate::queue::Priority;
+    use crate::storage::MemoryStorage;
+    use crate::tasks::{LogSubmitter, TaskRequest, TaskRunner};
+
+    fn reputation() -> Reputation {
+        Reputation::from_config(&ReputationConfig::default(), Arc::new(Metrics::new())).unwrap()
+    }
+
+    fn beat(attester: &RngAttester, boot: &str, sequence: u64, timestamp: u64) -> Heartbeat {
+        Heartbeat::sign(attester, "0xop", None, boot, sequence, timestamp).unwrap()
+    }
+
+    #[test]
+    fn heartbeats_count_gaps_and_restarts() {
+        let reputation = reputation();
+        let attester = RngAttester::new().unwrap();
+        reputation
+            .observe_heartbeat(&beat(&attester, "a", 0, 1_000))
+            .unwrap();
+        let score = reputation
+            .observe_heartbeat(&beat(&attester, "a", 3, 4_000))
+            .unwrap();
+        assert_eq!((score.heartbeats, score.missed_heartbeats), (2, 2));
+
+        let score = reputation
+            .observe_heartbeat(&beat(&attester, "b", 0, 5_000))
+            .unwrap();
+        assert_eq!((score.heartbeats, score.missed_heartbeats), (3, 2));
+        assert_eq!(
+            score.public_key,
+            hex::encode(attester.get_public_key().as_bytes())
+        );
+    }
+
+    fn executed(runner: &TaskRunner, task_id: &str) -> TaskOutcome {
+        runner
+            .execute(TaskRequest {
+                task_id: task_id.to_string(),
+                length: 32,
+                priority: Priority::default(),
+                deadline: None,
+                client_entropy: None,
+                trace: None,
+                dry_run: false,
+                tenant: None,
+                domain: None,
+                definition: None,
+                timelock: None,
+                batch: None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn relabelled_task_ids_are_not_equivocations() {
+        for vrf in [false, true] {
+            let reputation = reputation();
+            let mut runner = TaskRunner::new(
+                RngPerformer::new(),
+                RngAttester::new().unwrap(),
+                Arc::new(MemoryStorage::new()),
+                Arc::new(LogSubmitter),
+                Arc::new(Metrics::new()),
+                4,
+            );
+            if vrf {
+                runner = runner.with_vrf();
+            }
+            let runner = Arc::new(runner);
+            runner.start_workers(1);
+
+            let first = executed(&runner, "a");
+            let mut relabelled = executed(&runner, "b");
+            relabelled.task_id = "a".to_string();
+            reputation
+                .observe_attestation(&Observation { outcome: first })
+                .unwrap();
+            let score = reputation
+                .observe_attestation(&Observation {
+                    outcome: relabelled,
+                })
+                .unwrap();
+            assert_eq!((score.attestations, score.equivocations), (2, 0));
+        }
+    }
+
+    #[test]
+    fn operators_beyond_the_bound_are_refused() {
+        let config = ReputationConfig {
+            max_operators: 1,
+            ..ReputationConfig::default()
+        };
+        let reputation = Reputation::from_config(&config, Arc::new(Metrics::new())).unwrap();
+        let (first, second) = (RngAttester::new().unwrap(), RngAttester::new().unwrap());
+        reputation
+            .observe_heartbeat(&beat(&first, "a", 0, 1_000))
+            .unwrap();
+        assert!(reputation
+            .observe_heartbeat(&beat(&second, "a", 0, 1_000))
+            .is_err());
+        reputation
+            .observe_heartbeat(&beat(&first, "a", 1, 2_000))
+            .unwrap();
+        assert_eq!(reputation.scores().len(), 1);
+    }
+
+    #[test]
+    fn replayed_heartbeats_are_ignored() {
+        let reputation = reputation();
+        let attester = RngAttester::new().unwrap();
+        let old = beat(&attester, "a", 0, 1_000);
+        reputation.observe_heartbeat(&old).unwrap();
+        reputation
+            .observe_heartbeat(&beat(&attester, "a", 1, 2_000))
+            .unwrap();
+        reputation
+            .observe_heartbeat(&beat(&attester, "b", 0, 3_000))
+            .unwrap();
+
+        // Neither the old boot's heartbeats nor a repeat look like restarts.
+        for replayed in [
+            old,
+            beat(&attester, "a", 2, 2_500),
+            beat(&attester, "b", 0, 3_000),
+        ] {
+            let score = reputation.observe_heartbeat(&replayed).unwrap();
+            assert_eq!((score.heartbeats, score.missed_heartbeats), (3, 0));
+        }
+
+        let mut forged = beat(&attester, "c", 0, 9_000);
+        forged.boot_id = "d".to_string();
+        assert!(reputation.observe_heartbeat(&forged).is_err());
+    }
+}
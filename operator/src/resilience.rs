@@ -0,0 +1,322 @@
+// src/resilience.rs
+
+//! Shared resilience layer for outbound calls (aggregator, chain RPC, webhooks).
+//!
+//! Every outbound client routes its requests through [`Resilience::call`], which
+//! applies a jittered exponential retry policy, a per-endpoint circuit breaker
+//! and an overall timeout budget. Breaker state and call outcomes are exported
+//! through the shared [`Metrics`] registry.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::metrics::Metrics;
+
+/// Classifies a failed outbound attempt.
+///
+/// Only `Transient` failures are retried; `Permanent` failures (bad request,
+/// rejected payload, ...) are returned to the caller immediately.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallError {
+    Transient(String),
+    Permanent(String),
+}
+
+/// Exponential backoff with full jitter.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Backoff ceiling for the first retry; doubled on each further retry.
+    pub base_delay: Duration,
+    /// Upper bound on any single backoff.
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Returns the delay to wait before retry number `retry` (starting at 0).
+    ///
+    /// The delay is drawn uniformly from `[0, min(max_delay, base_delay * 2^retry)]`
+    /// so that many operators retrying the same endpoint don't synchronise.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u32.checked_shl(retry).unwrap_or(u32::MAX);
+        let ceiling = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        let ceiling_ms = ceiling.as_millis() as u64;
+        if ceiling_ms == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_millis(rand::thread_rng().gen_range(0..=ceiling_ms))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Thresholds controlling when a circuit breaker trips and recovers.
+#[derive(Debug, Clone)]
+pub struct BreakerConfig {
+    /// Consecutive failures after which the breaker opens.
+    pub failure_threshold: u32,
+    /// How long the breaker stays open before letting a probe call through.
+    pub open_for: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        BreakerConfig {
+            failure_threshold: 5,
+            open_for: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The externally visible state of a circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Calls flow normally.
+    Closed,
+    /// The cool-down elapsed; a single probe call is allowed through.
+    HalfOpen,
+    /// Calls are rejected without touching the endpoint.
+    Open,
+}
+
+impl BreakerState {
+    /// Numeric encoding used for the `rng_circuit_breaker_state` gauge.
+    pub fn as_gauge(self) -> f64 {
+        match self {
+            BreakerState::Closed => 0.0,
+            BreakerState::HalfOpen => 1.0,
+            BreakerState::Open => 2.0,
+        }
+    }
+}
+
+struct CircuitBreaker {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probing: bool,
+}
+
+impl CircuitBreaker {
+    fn new() -> Self {
+        CircuitBreaker {
+            consecutive_failures: 0,
+            opened_at: None,
+            probing: false,
+        }
+    }
+
+    fn state(&self, config: &BreakerConfig) -> BreakerState {
+        match self.opened_at {
+            None => BreakerState::Closed,
+            Some(opened) if opened.elapsed() >= config.open_for => BreakerState::HalfOpen,
+            Some(_) => BreakerState::Open,
+        }
+    }
+
+    /// Decides whether a call may proceed, claiming the probe slot when half-open.
+    fn try_acquire(&mut self, config: &BreakerConfig) -> bool {
+        match self.state(config) {
+            BreakerState::Closed => true,
+            BreakerState::Open => false,
+            BreakerState::HalfOpen if self.probing => false,
+            BreakerState::HalfOpen => {
+                self.probing = true;
+                true
+            }
+        }
+    }
+
+    fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+        self.probing = false;
+    }
+
+    fn record_failure(&mut self, config: &BreakerConfig) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.probing || self.consecutive_failures >= config.failure_threshold {
+            self.opened_at = Some(Instant::now());
+        }
+        self.probing = false;
+    }
+}
+
+/// Complete configuration of the resilience layer.
+#[derive(Debug, Clone)]
+pub struct ResilienceConfig {
+    pub retry: RetryPolicy,
+    pub breaker: BreakerConfig,
+    /// Wall-clock budget for a call including all retries and backoff.
+    pub timeout_budget: Duration,
+}
+
+impl Default for ResilienceConfig {
+    fn default() -> Self {
+        ResilienceConfig {
+            retry: RetryPolicy::default(),
+            breaker: BreakerConfig::default(),
+            timeout_budget: Duration::from_secs(15),
+        }
+    }
+}
+
+/// `Resilience` wraps outbound calls with retries, breakers and timeout budgets.
+///
+/// Breakers are created lazily, one per endpoint name, so a misbehaving
+/// webhook target cannot trip the breaker guarding the aggregator.
+pub struct Resilience {
+    config: ResilienceConfig,
+    breakers: Mutex<HashMap<String, CircuitBreaker>>,
+    metrics: Arc<Metrics>,
+}
+
+impl Resilience {
+    /// Creates a resilience layer reporting into `metrics`.
+    pub fn new(config: ResilienceConfig, metrics: Arc<Metrics>) -> Self {
+        Resilience {
+            config,
+            breakers: Mutex::new(HashMap::new()),
+            metrics,
+        }
+    }
+
+    /// Returns the configuration this layer was built with.
+    pub fn config(&self) -> &ResilienceConfig {
+        &self.config
+    }
+
+    /// Returns the current breaker state for `endpoint`.
+    pub fn breaker_state(&self, endpoint: &str) -> BreakerState {
+        let breakers = self.breakers.lock().expect("breaker lock poisoned");
+        breakers
+            .get(endpoint)
+            .map(|b| b.state(&self.config.breaker))
+            .unwrap_or(BreakerState::Closed)
+    }
+
+    /// Runs `op` against `endpoint` under the configured policy.
+    ///
+    /// `op` receives the time remaining in the overall budget and should use it
+    /// as the timeout for its own I/O. Transient failures are retried with
+    /// jittered backoff until attempts or budget run out.
+    ///
+    /// # Returns
+    /// - `Ok(T)` with the first successful result.
+    /// - `Err(String)` describing the last failure, a permanent failure, an
+    ///   exhausted budget, or an open circuit breaker.
+    pub fn call<T, F>(&self, endpoint: &str, mut op: F) -> Result<T, String>
+    where
+        F: FnMut(Duration) -> Result<T, CallError>,
+    {
+        let deadline = Instant::now() + self.config.timeout_budget;
+        let max_attempts = self.config.retry.max_attempts.max(1);
+        let mut last_error = String::from("no attempt made");
+
+        for attempt in 0..max_attempts {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                self.record_outcome(endpoint, "budget_exhausted");
+                return Err(format!(
+                    "{}: timeout budget exhausted after {} attempt(s): {}",
+                    endpoint, attempt, last_error
+                ));
+            }
+
+            if !self.acquire(endpoint) {
+                self.record_outcome(endpoint, "rejected");
+                return Err(format!("{}: circuit breaker open", endpoint));
+            }
+
+            match op(remaining) {
+                Ok(value) => {
+                    self.update_breaker(endpoint, true);
+                    self.record_outcome(endpoint, "success");
+                    return Ok(value);
+                }
+                Err(CallError::Permanent(e)) => {
+                    // The endpoint answered; a rejected payload says nothing
+                    // about its health, so the breaker is not charged.
+                    self.update_breaker(endpoint, true);
+                    self.record_outcome(endpoint, "permanent");
+                    return Err(format!("{}: {}", endpoint, e));
+                }
+                Err(CallError::Transient(e)) => {
+                    self.update_breaker(endpoint, false);
+                    self.record_outcome(endpoint, "transient");
+                    last_error = e;
+                }
+            }
+
+            if attempt + 1 < max_attempts {
+                let delay = self.config.retry.backoff(attempt);
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if delay >= remaining {
+                    break;
+                }
+                self.metrics.inc_counter(
+                    "rng_outbound_retries_total",
+                    &[("endpoint", endpoint)],
+                    1,
+                );
+                thread::sleep(delay);
+            }
+        }
+
+        Err(format!(
+            "{}: giving up after transient failures: {}",
+            endpoint, last_error
+        ))
+    }
+
+    fn acquire(&self, endpoint: &str) -> bool {
+        let mut breakers = self.breakers.lock().expect("breaker lock poisoned");
+        let breaker = breakers
+            .entry(endpoint.to_string())
+            .or_insert_with(CircuitBreaker::new);
+        let allowed = breaker.try_acquire(&self.config.breaker);
+        self.publish_state(endpoint, breaker.state(&self.config.breaker));
+        allowed
+    }
+
+    fn update_breaker(&self, endpoint: &str, healthy: bool) {
+        let mut breakers = self.breakers.lock().expect("breaker lock poisoned");
+        let breaker = breakers
+            .entry(endpoint.to_string())
+            .or_insert_with(CircuitBreaker::new);
+        if healthy {
+            breaker.record_success();
+        } else {
+            breaker.record_failure(&self.config.breaker);
+        }
+        self.publish_state(endpoint, breaker.state(&self.config.breaker));
+    }
+
+    fn publish_state(&self, endpoint: &str, state: BreakerState) {
+        self.metrics.set_gauge(
+            "rng_circuit_breaker_state",
+            &[("endpoint", endpoint)],
+            state.as_gauge(),
+        );
+    }
+
+    fn record_outcome(&self, endpoint: &str, outcome: &str) {
+        self.metrics.inc_counter(
+            "rng_outbound_calls_total",
+            &[("endpoint", endpoint), ("outcome", outcome)],
+            1,
+        );
+    }
+}
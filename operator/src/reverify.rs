@@ -0,0 +1,487 @@
+// src/reverify.rs
+
+//! Re-verification of what the operator has stored.
+//!
+//! Attestations stay in storage for a long time, and a flipped bit or an
+//! edited record would otherwise only surface when a consumer checks one. The
+//! [`Reverifier`] looks for such damage first: every `reverify.interval`, or
+//! on demand with `operator reverify`, it checks
+//!
+//! - every stored attestation of every namespace: its signature and
+//!   derivation steps, the certificate of an epoch key, the binding of a
+//!   pooled value and, with `reverify.trusted_keys` set, that it was signed by
+//!   one of those keys or by an epoch key one of them certified;
+//! - every archived object whose attestations are still held locally against
+//!   the Merkle root in the archive index and, with an archive configured,
+//!   the uploaded copy of every object whose attestations were pruned;
+//! - the revocation list, for consecutive serials and valid signatures.
+//!
+//! Findings are logged, counted in `rng_reverify_discrepancies_total` and kept
+//! as the latest [`ReverifyReport`], which `GET /admin/reverify` serves.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::archive::{ArchivedObject, Archiver, ARCHIVE_OBJECTS};
+use crate::attester::RngAttester;
+use crate::config::{self, ReverifyConfig};
+use crate::export::{self, ExportRecord};
+use crate::merkle;
+use crate::metrics::Metrics;
+use crate::pool;
+use crate::revocation::{Revocation, REVOCATIONS};
+use crate::storage::Storage;
+use crate::tasks::{unix_millis, TaskOutcome, ATTESTATIONS};
+use crate::tenants;
+
+/// What is wrong with a stored record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscrepancyKind {
+    /// A record that cannot be read back.
+    Corrupt,
+    /// An attestation whose signature or derivation does not verify.
+    Signature,
+    /// An epoch attestation lacking a valid certificate of its key.
+    Certificate,
+    /// A pooled value without a valid binding to its task.
+    Binding,
+    /// Signed by a key `reverify.trusted_keys` does not vouch for.
+    UntrustedKey,
+    /// Archived attestations that no longer match their Merkle root.
+    MerkleRoot,
+    /// Some, but not all, attestations of an archived object are gone.
+    Missing,
+    /// The uploaded copy of an object is unavailable or does not match.
+    Archive,
+    /// A gap in the revocation serials, or a statement that does not verify.
+    Revocation,
+}
+
+impl DiscrepancyKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DiscrepancyKind::Corrupt => "corrupt",
+            DiscrepancyKind::Signature => "signature",
+            DiscrepancyKind::Certificate => "certificate",
+            DiscrepancyKind::Binding => "binding",
+            DiscrepancyKind::UntrustedKey => "untrusted_key",
+            DiscrepancyKind::MerkleRoot => "merkle_root",
+            DiscrepancyKind::Missing => "missing",
+            DiscrepancyKind::Archive => "archive",
+            DiscrepancyKind::Revocation => "revocation",
+        }
+    }
+}
+
+/// One finding of a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Discrepancy {
+    pub kind: DiscrepancyKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// Task ID, archive object name or revocation serial.
+    pub subject: String,
+    pub detail: String,
+}
+
+/// The outcome of one run.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReverifyReport {
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub attestations: usize,
+    /// Archived objects checked against their root.
+    pub archived_objects: usize,
+    /// Archived objects whose attestations were pruned while no archive is
+    /// configured to fetch them from.
+    pub unchecked_objects: usize,
+    pub revocations: usize,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl ReverifyReport {
+    pub fn is_clean(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+
+    fn flag(&mut self, kind: DiscrepancyKind, tenant: Option<&str>, subject: &str, detail: String) {
+        self.discrepancies.push(Discrepancy {
+            kind,
+            tenant: tenant.map(str::to_string),
+            subject: subject.to_string(),
+            detail,
+        });
+    }
+}
+
+/// Checks stored attestations, archive roots and revocations.
+pub struct Reverifier {
+    storage: Arc<dyn Storage>,
+    interval: Duration,
+    trusted: Vec<VerifyingKey>,
+    tenants: Vec<String>,
+    archiver: Option<Archiver>,
+    metrics: Arc<Metrics>,
+    latest: Mutex<Option<ReverifyReport>>,
+}
+
+impl Reverifier {
+    /// Builds a reverifier from the `reverify` config section.
+    pub fn from_config(
+        config: &ReverifyConfig,
+        storage: Arc<dyn Storage>,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self, String> {
+        let trusted = config
+            .trusted_keys
+            .iter()
+            .map(|key| parse_key(key).map_err(|e| format!("reverify.trusted_keys: {}", e)))
+            .collect::<Result<_, _>>()?;
+        Ok(Reverifier {
+            storage,
+            interval: config::parse_duration(&config.interval)?,
+            trusted,
+            tenants: Vec::new(),
+            archiver: None,
+            metrics,
+            latest: Mutex::new(None),
+        })
+    }
+
+    /// Also checks the namespaces of `tenants`.
+    pub fn with_tenants(mut self, tenants: Vec<String>) -> Self {
+        self.tenants = tenants;
+        self
+    }
+
+    /// Fetches the objects of pruned attestations back from `archiver`.
+    pub fn with_archiver(mut self, archiver: Archiver) -> Self {
+        self.archiver = Some(archiver);
+        self
+    }
+
+    /// Also trusts `key`, e.g. one given on the command line.
+    pub fn with_trusted_key(mut self, key: VerifyingKey) -> Self {
+        self.trusted.push(key);
+        self
+    }
+
+    /// Re-verifies everything every interval, forever.
+    pub fn run(&self) {
+        loop {
+            thread::sleep(self.interval);
+            if let Err(e) = self.run_once() {
+                warn!("Re-verification failed: {}", e);
+                self.metrics
+                    .inc_counter("rng_reverify_runs_total", &[("outcome", "failed")], 1);
+            }
+        }
+    }
+
+    /// Re-verifies everything once. Storage errors end the run; everything
+    /// found wrong with the stored data is reported.
+    pub fn run_once(&self) -> Result<ReverifyReport, String> {
+        let mut report = ReverifyReport {
+            started_at: unix_millis(),
+            ..Default::default()
+        };
+        let namespaces = std::iter::once(None).chain(self.tenants.iter().map(|t| Some(t.as_str())));
+        for tenant in namespaces {
+            self.check_attestations(tenant, &mut report)?;
+        }
+        self.check_archive(&mut report)?;
+        self.check_revocations(&mut report)?;
+        report.finished_at = unix_millis();
+
+        for discrepancy in &report.discrepancies {
+            warn!(
+                "Re-verification: {} {}{}: {}",
+                discrepancy.kind.as_str(),
+                discrepancy
+                    .tenant
+                    .as_ref()
+                    .map(|t| format!("{}/", t))
+                    .unwrap_or_default(),
+                discrepancy.subject,
+                discrepancy.detail
+            );
+            self.metrics.inc_counter(
+                "rng_reverify_discrepancies_total",
+                &[("kind", discrepancy.kind.as_str())],
+                1,
+            );
+        }
+        info!(
+            "Re-verified {} attestation(s), {} archived object(s) and {} revocation(s): {} discrepancy(ies)",
+            report.attestations,
+            report.archived_objects,
+            report.revocations,
+            report.discrepancies.len()
+        );
+        let outcome = if report.is_clean() {
+            "clean"
+        } else {
+            "discrepancies"
+        };
+        self.metrics
+            .inc_counter("rng_reverify_runs_total", &[("outcome", outcome)], 1);
+        self.metrics.set_gauge(
+            "rng_reverify_last_run_timestamp_seconds",
+            &[],
+            report.finished_at as f64 / 1000.0,
+        );
+        *self.latest.lock().expect("reverify lock poisoned") = Some(report.clone());
+        Ok(report)
+    }
+
+    /// The report of the latest run, if any has finished.
+    pub fn latest(&self) -> Option<ReverifyReport> {
+        self.latest.lock().expect("reverify lock poisoned").clone()
+    }
+
+    fn check_attestations(
+        &self,
+        tenant: Option<&str>,
+        report: &mut ReverifyReport,
+    ) -> Result<(), String> {
+        for (key, stored) in self
+            .storage
+            .scan(&tenants::collection(ATTESTATIONS, tenant))?
+        {
+            report.attestations += 1;
+            let outcome: TaskOutcome = match serde_json::from_value(stored["outcome"].clone()) {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    report.flag(
+                        DiscrepancyKind::Corrupt,
+                        tenant,
+                        &key,
+                        format!("invalid outcome: {}", e),
+                    );
+                    continue;
+                }
+            };
+            if outcome.task_id != key {
+                report.flag(
+                    DiscrepancyKind::Corrupt,
+                    tenant,
+                    &key,
+                    format!("stored attestation is for task {}", outcome.task_id),
+                );
+            }
+            if let Err((kind, detail)) = check_outcome(&outcome, &self.trusted) {
+                report.flag(kind, tenant, &key, detail);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_archive(&self, report: &mut ReverifyReport) -> Result<(), String> {
+        // Attestation rows per namespace, by storage key, read when first needed.
+        let mut held: HashMap<Option<String>, HashMap<String, ExportRecord>> = HashMap::new();
+        for (name, value) in self.storage.scan(ARCHIVE_OBJECTS)? {
+            let object: ArchivedObject = match serde_json::from_value(value) {
+                Ok(object) => object,
+                Err(e) => {
+                    report.flag(
+                        DiscrepancyKind::Corrupt,
+                        None,
+                        &name,
+                        format!("invalid archive index entry: {}", e),
+                    );
+                    continue;
+                }
+            };
+            let tenant = object.tenant.as_deref();
+            if !held.contains_key(&object.tenant) {
+                let rows = export::collect(self.storage.as_ref(), tenant, None, None)?
+                    .into_iter()
+                    .filter(|r| r.record == "attestation")
+                    .map(|r| (r.key.clone(), r))
+                    .collect();
+                held.insert(object.tenant.clone(), rows);
+            }
+            let rows = &held[&object.tenant];
+            // Objects from before `keys` held no stream chunks, so their task
+            // IDs are their keys.
+            let keys = if object.keys.is_empty() {
+                &object.task_ids
+            } else {
+                &object.keys
+            };
+            let mut local: Vec<&ExportRecord> =
+                keys.iter().filter_map(|key| rows.get(key)).collect();
+
+            if local.len() == keys.len() {
+                // Rebuild the object's lines in the order they were uploaded.
+                local.sort_by(|a, b| (a.at, &a.key).cmp(&(b.at, &b.key)));
+                let leaves = local
+                    .iter()
+                    .map(|r| {
+                        serde_json::to_string(r).map(|line| merkle::leaf_hash(line.as_bytes()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| e.to_string())?;
+                let root = hex::encode(merkle::root(&leaves));
+                if root != object.merkle_root {
+                    report.flag(
+                        DiscrepancyKind::MerkleRoot,
+                        tenant,
+                        &name,
+                        format!(
+                            "stored attestations have root {}, the index records {}",
+                            root, object.merkle_root
+                        ),
+                    );
+                }
+                report.archived_objects += 1;
+            } else if local.is_empty() {
+                let Some(archiver) = &self.archiver else {
+                    report.unchecked_objects += 1;
+                    continue;
+                };
+                match archiver.fetch(&object) {
+                    Ok(rows) if rows.len() == object.count => {}
+                    Ok(rows) => report.flag(
+                        DiscrepancyKind::Archive,
+                        tenant,
+                        &name,
+                        format!(
+                            "object holds {} row(s), the index records {}",
+                            rows.len(),
+                            object.count
+                        ),
+                    ),
+                    Err(e) => report.flag(DiscrepancyKind::Archive, tenant, &name, e),
+                }
+                report.archived_objects += 1;
+            } else {
+                report.flag(
+                    DiscrepancyKind::Missing,
+                    tenant,
+                    &name,
+                    format!(
+                        "{} of {} archived attestation(s) are no longer stored",
+                        keys.len() - local.len(),
+                        keys.len()
+                    ),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn check_revocations(&self, report: &mut ReverifyReport) -> Result<(), String> {
+        let mut expected = 1;
+        for (key, value) in self.storage.scan(REVOCATIONS)? {
+            report.revocations += 1;
+            let revocation: Revocation = match serde_json::from_value(value) {
+                Ok(revocation) => revocation,
+                Err(e) => {
+                    report.flag(
+                        DiscrepancyKind::Corrupt,
+                        None,
+                        &key,
+                        format!("invalid revocation: {}", e),
+                    );
+                    continue;
+                }
+            };
+            let serial = revocation.serial.to_string();
+            if revocation.serial != expected {
+                report.flag(
+                    DiscrepancyKind::Revocation,
+                    None,
+                    &serial,
+                    format!("found serial {} where {} was expected", serial, expected),
+                );
+            }
+            expected = revocation.serial + 1;
+            if let Err(e) = revocation.verify() {
+                report.flag(DiscrepancyKind::Revocation, None, &serial, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks an attestation's signature and derivation steps, the certificate
+/// of an epoch key, the binding of a pooled value and, unless `trusted` is
+/// empty, that one of `trusted` signed it or certified its epoch key.
+pub fn check_outcome(
+    outcome: &TaskOutcome,
+    trusted: &[VerifyingKey],
+) -> Result<(), (DiscrepancyKind, String)> {
+    let corrupt = |e: String| (DiscrepancyKind::Corrupt, e);
+    let attestation = outcome.to_attestation().map_err(corrupt)?;
+    let public_key = parse_key(&outcome.public_key).map_err(corrupt)?;
+    RngAttester::verify(&public_key, &attestation).map_err(|e| (DiscrepancyKind::Signature, e))?;
+
+    let mut certified_by = None;
+    if let Some(epoch) = attestation.payload.epoch {
+        let invalid = |e: String| (DiscrepancyKind::Certificate, e);
+        let certificate = outcome.epoch_certificate.as_ref().ok_or_else(|| {
+            invalid(format!(
+                "epoch {} attestation carries no key certificate",
+                epoch
+            ))
+        })?;
+        let (master, key) = certificate.verify().map_err(invalid)?;
+        if key != public_key || certificate.epoch != epoch {
+            return Err(invalid(format!(
+                "certificate is for key {} in epoch {}, not the signing key in epoch {}",
+                certificate.public_key, certificate.epoch, epoch
+            )));
+        }
+        certified_by = Some(master);
+    }
+
+    if let Some(slot) = attestation.payload.slot {
+        let binding: [u8; 64] = outcome
+            .binding
+            .as_deref()
+            .and_then(|b| hex::decode(b).ok())
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| {
+                (
+                    DiscrepancyKind::Binding,
+                    format!("pooled value (slot {}) carries no valid binding", slot),
+                )
+            })?;
+        pool::verify_binding(
+            &public_key,
+            &outcome.task_id,
+            &attestation,
+            &Signature::from_bytes(&binding),
+        )
+        .map_err(|e| (DiscrepancyKind::Binding, e))?;
+    }
+
+    let trusted = trusted.is_empty()
+        || trusted.contains(&public_key)
+        || certified_by.is_some_and(|master| trusted.contains(&master));
+    if !trusted {
+        return Err((
+            DiscrepancyKind::UntrustedKey,
+            format!("signed by untrusted key {}", outcome.public_key),
+        ));
+    }
+    Ok(())
+}
+
+fn parse_key(value: &str) -> Result<VerifyingKey, String> {
+    let bytes: [u8; 32] = hex::decode(value)
+        .map_err(|e| format!("invalid public key {}: {}", value, e))?
+        .try_into()
+        .map_err(|_| format!("public key {} must be 32 bytes", value))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("invalid public key {}: {}", value, e))
+}
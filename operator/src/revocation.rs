@@ -0,0 +1,271 @@
+// src/revocation.rs
+
+//! Signed revocation of attestations and keys.
+//!
+//! When a value must no longer be relied on, e.g. after an entropy source
+//! turned out to be compromised, the operator issues a [`Revocation`]: for one
+//! attestation (by payload digest) or for every attestation of a key, from an
+//! optional point in time on. Statements are signed with Ed25519ph under
+//! [`CONTEXT`] by the current attestation key, kept in [`REVOCATIONS`],
+//! published with the other events and served as a revocation list by
+//! `GET /revocations`. Serials are consecutive, so a list with a gap is
+//! missing statements.
+//!
+//! Verifiers consult the list with [`find`]; `rng-verify --crl` does so for
+//! every attestation it checks.
+
+use std::sync::{Arc, Mutex};
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use crate::attester::RngAttester;
+use crate::publish::{self, EventPublisher};
+use crate::storage::Storage;
+use crate::tasks::{unix_millis, TaskOutcome, ATTESTATIONS};
+use crate::tenants;
+
+/// Ed25519ph context revocations are signed under.
+pub const CONTEXT: &[u8] = b"othentic-rng/revocation/v1";
+/// Collection holding every revocation issued, keyed by zero-padded serial.
+pub const REVOCATIONS: &str = "revocations";
+
+/// What a [`Revocation`] withdraws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationKind {
+    /// The attestation whose payload digest is the target.
+    Attestation,
+    /// Every attestation of the ed25519 public key that is the target.
+    Key,
+}
+
+impl RevocationKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            RevocationKind::Attestation => "attestation",
+            RevocationKind::Key => "key",
+        }
+    }
+}
+
+/// A signed statement that attestations must no longer be relied on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Revocation {
+    /// Position in the operator's revocation list, from 1.
+    pub serial: u64,
+    pub kind: RevocationKind,
+    /// Hex payload digest or public key, depending on `kind`.
+    pub target: String,
+    /// For key revocations, the Unix ms from which attestations are revoked;
+    /// earlier ones stay valid. Unset, all of them are.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<u64>,
+    pub reason: String,
+    pub revoked_at: u64,
+    /// Key that signed the statement.
+    pub public_key: String,
+    pub signature: String,
+}
+
+impl Revocation {
+    /// Checks the signature and returns the key that made it.
+    pub fn verify(&self) -> Result<VerifyingKey, String> {
+        let key: [u8; 32] = hex::decode(&self.public_key)
+            .map_err(|e| format!("Invalid revocation public key: {}", e))?
+            .try_into()
+            .map_err(|_| "Revocation public key must be 32 bytes".to_string())?;
+        let key = VerifyingKey::from_bytes(&key)
+            .map_err(|e| format!("Invalid revocation public key: {}", e))?;
+        let signature: [u8; 64] = hex::decode(&self.signature)
+            .map_err(|e| format!("Invalid revocation signature: {}", e))?
+            .try_into()
+            .map_err(|_| "Revocation signature must be 64 bytes".to_string())?;
+        RngAttester::verify_with_context(
+            &key,
+            CONTEXT,
+            &self.signed_bytes(),
+            &Signature::from_bytes(&signature),
+        )?;
+        Ok(key)
+    }
+
+    /// Whether the statement covers an attestation with payload digest
+    /// `digest`, signed by `public_key` at `signed_at`: the start of its
+    /// validity window, unknown for attestations without one.
+    pub fn covers(
+        &self,
+        digest: &[u8; 32],
+        public_key: &VerifyingKey,
+        signed_at: Option<u64>,
+    ) -> bool {
+        match self.kind {
+            RevocationKind::Attestation => self.target == hex::encode(digest),
+            RevocationKind::Key => {
+                self.target == hex::encode(public_key.as_bytes())
+                    && match (self.since, signed_at) {
+                        (Some(since), Some(at)) => at >= since,
+                        // Without a signing time it may be after `since`.
+                        _ => true,
+                    }
+            }
+        }
+    }
+
+    /// Length-prefixed encoding of every field but the signature.
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&self.serial.to_be_bytes());
+        for field in [
+            self.kind.as_str().as_bytes(),
+            self.target.as_bytes(),
+            self.reason.as_bytes(),
+        ] {
+            data.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            data.extend_from_slice(field);
+        }
+        match self.since {
+            Some(since) => {
+                data.push(1);
+                data.extend_from_slice(&since.to_be_bytes());
+            }
+            None => data.push(0),
+        }
+        data.extend_from_slice(&self.revoked_at.to_be_bytes());
+        data.extend_from_slice(self.public_key.as_bytes());
+        data
+    }
+}
+
+/// The body of `GET /revocations`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevocationList {
+    pub issued_at: u64,
+    /// Ordered by serial.
+    pub revocations: Vec<Revocation>,
+}
+
+/// The first statement of `revocations` covering the attestation, if any;
+/// see [`Revocation::covers`]. Signatures are not checked here.
+pub fn find<'a>(
+    revocations: &'a [Revocation],
+    digest: &[u8; 32],
+    public_key: &VerifyingKey,
+    signed_at: Option<u64>,
+) -> Option<&'a Revocation> {
+    revocations
+        .iter()
+        .find(|r| r.covers(digest, public_key, signed_at))
+}
+
+/// Issues and stores revocations.
+pub struct RevocationRegistry {
+    storage: Arc<dyn Storage>,
+    publisher: Option<Arc<EventPublisher>>,
+    /// Serialises serial assignment.
+    issuing: Mutex<()>,
+}
+
+impl RevocationRegistry {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        RevocationRegistry {
+            storage,
+            publisher: None,
+            issuing: Mutex::new(()),
+        }
+    }
+
+    /// Publishes every revocation with `publisher`.
+    pub fn with_publisher(mut self, publisher: Arc<EventPublisher>) -> Self {
+        self.publisher = Some(publisher);
+        self
+    }
+
+    /// Signs, stores and publishes a revocation of `target`.
+    pub fn revoke(
+        &self,
+        attester: &RngAttester,
+        kind: RevocationKind,
+        target: &str,
+        since: Option<u64>,
+        reason: &str,
+    ) -> Result<Revocation, String> {
+        let length = match kind {
+            RevocationKind::Attestation => "attestation digest",
+            RevocationKind::Key => "public key",
+        };
+        if hex::decode(target).map_or(true, |t| t.len() != 32) {
+            return Err(format!("The {} must be 32 hex-encoded bytes", length));
+        }
+        if since.is_some() && kind != RevocationKind::Key {
+            return Err("since applies to key revocations only".to_string());
+        }
+        if reason.trim().is_empty() {
+            return Err("A revocation needs a reason".to_string());
+        }
+        let _issuing = self.issuing.lock().expect("revocation lock poisoned");
+        let serial = self.storage.scan(REVOCATIONS)?.len() as u64 + 1;
+        let mut revocation = Revocation {
+            serial,
+            kind,
+            target: target.to_lowercase(),
+            since,
+            reason: reason.to_string(),
+            revoked_at: unix_millis(),
+            public_key: hex::encode(attester.get_public_key().as_bytes()),
+            signature: String::new(),
+        };
+        let signature = attester.sign_with_context(CONTEXT, &revocation.signed_bytes())?;
+        revocation.signature = hex::encode(signature.to_bytes());
+        let value = serde_json::to_value(&revocation).map_err(|e| e.to_string())?;
+        self.storage
+            .put(REVOCATIONS, &format!("{:012}", serial), value.clone())?;
+        self.storage.flush()?;
+        if let Some(publisher) = &self.publisher {
+            publisher.publish(publish::REVOCATION_SCHEMA, &serial.to_string(), value);
+        }
+        Ok(revocation)
+    }
+
+    /// Revokes the stored attestation of task `task_id` of `tenant`.
+    pub fn revoke_task(
+        &self,
+        attester: &RngAttester,
+        tenant: Option<&str>,
+        task_id: &str,
+        reason: &str,
+    ) -> Result<Revocation, String> {
+        let outcome: TaskOutcome = self
+            .storage
+            .get(&tenants::collection(ATTESTATIONS, tenant), task_id)?
+            .and_then(|r| serde_json::from_value(r["outcome"].clone()).ok())
+            .ok_or_else(|| format!("No attestation stored for task {}", task_id))?;
+        let digest = outcome.to_attestation()?.payload.digest();
+        self.revoke(
+            attester,
+            RevocationKind::Attestation,
+            &hex::encode(digest),
+            None,
+            reason,
+        )
+    }
+
+    /// Every revocation issued so far.
+    pub fn list(&self) -> Result<RevocationList, String> {
+        let revocations = self
+            .storage
+            .scan(REVOCATIONS)?
+            .into_iter()
+            .map(|(key, value)| {
+                serde_json::from_value(value)
+                    .map_err(|e| format!("Corrupt revocation {}: {}", key, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(RevocationList {
+            issued_at: unix_millis(),
+            revocations,
+        })
+    }
+}
@@ -0,0 +1,382 @@
+// src/roles.rs
+
+//! Role separation: performer-only and attester-only nodes.
+//!
+//! An Othentic AVS splits the work between a performer, which executes a task
+//! and signs its proof of task, and attesters, which check the performer's
+//! output before it counts. `role.mode` picks which of these a process is:
+//!
+//! - `full` (the default) generates, attests and submits values on its own,
+//!   and also validates others' outputs;
+//! - `performer` generates and signs values, posts every outcome to each
+//!   `role.attesters` node's `POST /task/validate` through the shared
+//!   [`Resilience`] layer, and only submits it once `role.quorum` of them
+//!   have approved it with a signed [`Verdict`];
+//! - `attester` does not generate values at all: it serves
+//!   `POST /task/validate`, checking an outcome like `rng-verify` does
+//!   (signature, derivation steps, epoch certificate, pool binding, validity
+//!   window and, for a batch, every inclusion proof) and, with
+//!   `role.trusted_performers` set, that one of them signed it.
+//!
+//! Verdicts are signed with Ed25519ph under [`CONTEXT`] by the attester's
+//! attestation key, and performers only count verdicts from the key pinned
+//! for each attester. Attestation keys are generated on start, so attesters
+//! that restart run `epochs` and are pinned by their master key, which
+//! certifies each epoch key in the verdict.
+
+use std::sync::Arc;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::attester::{Clock, RngAttester, SystemClock, DEFAULT_CLOCK_SKEW};
+use crate::batch;
+use crate::config::{AttesterConfig, RoleConfig};
+use crate::encoding::Encoding;
+use crate::epochs::EpochCertificate;
+use crate::metrics::Metrics;
+use crate::resilience::{CallError, Resilience};
+use crate::reverify;
+use crate::tasks::{unix_millis, Submitter, TaskOutcome};
+
+/// Ed25519ph context verdicts are signed under.
+pub const CONTEXT: &[u8] = b"othentic-rng/verdict/v1";
+
+/// What a process does in the AVS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Generates, attests and validates.
+    #[default]
+    Full,
+    /// Generates and signs values; attesters validate them.
+    Performer,
+    /// Validates performers' outputs and generates nothing.
+    Attester,
+}
+
+impl Role {
+    /// Whether the process serves the task endpoints.
+    pub fn generates(self) -> bool {
+        self != Role::Attester
+    }
+
+    /// Whether the process serves `POST /task/validate`.
+    pub fn validates(self) -> bool {
+        self != Role::Performer
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Full => "full",
+            Role::Performer => "performer",
+            Role::Attester => "attester",
+        }
+    }
+}
+
+/// An attester's signed judgement of one outcome.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Verdict {
+    pub task_id: String,
+    /// Hex payload digest of the attestation judged.
+    pub digest: String,
+    pub approved: bool,
+    /// Why the outcome was rejected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Unix time in milliseconds.
+    pub timestamp: u64,
+    pub public_key: String,
+    pub signature: String,
+    /// Master certificate of the signing key, when the attester runs key
+    /// epochs; not covered by `signature`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch_certificate: Option<EpochCertificate>,
+}
+
+impl Verdict {
+    /// Builds and signs a verdict with the attester's key.
+    pub fn sign(
+        attester: &RngAttester,
+        task_id: &str,
+        digest: &str,
+        reason: Option<String>,
+    ) -> Result<Self, String> {
+        let mut verdict = Verdict {
+            task_id: task_id.to_string(),
+            digest: digest.to_string(),
+            approved: reason.is_none(),
+            reason,
+            timestamp: unix_millis(),
+            public_key: hex::encode(attester.get_public_key().as_bytes()),
+            signature: String::new(),
+            epoch_certificate: attester.epoch_certificate().cloned(),
+        };
+        let signature = attester.sign_with_context(CONTEXT, &verdict.signed_bytes())?;
+        verdict.signature = hex::encode(signature.to_bytes());
+        Ok(verdict)
+    }
+
+    /// Checks the signature, and the certificate of the signing key if any,
+    /// and returns the key that made it.
+    pub fn verify(&self) -> Result<VerifyingKey, String> {
+        let key = parse_key(&self.public_key)?;
+        let signature: [u8; 64] = hex::decode(&self.signature)
+            .map_err(|e| format!("Invalid verdict signature: {}", e))?
+            .try_into()
+            .map_err(|_| "Verdict signature must be 64 bytes".to_string())?;
+        RngAttester::verify_with_context(
+            &key,
+            CONTEXT,
+            &self.signed_bytes(),
+            &Signature::from_bytes(&signature),
+        )?;
+        if let Some(certificate) = &self.epoch_certificate {
+            if certificate.verify()?.1 != key {
+                return Err("Epoch certificate is for a different key".to_string());
+            }
+        }
+        Ok(key)
+    }
+
+    /// Whether the verdict is valid and signed by `key` or by an epoch key
+    /// `key` certified.
+    pub fn is_from(&self, key: &VerifyingKey) -> Result<bool, String> {
+        let signer = self.verify()?;
+        let master = match &self.epoch_certificate {
+            Some(certificate) => Some(certificate.verify()?.0),
+            None => None,
+        };
+        Ok(signer == *key || master.as_ref() == Some(key))
+    }
+
+    /// Length-prefixed encoding of every field but the signature.
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        for field in [self.task_id.as_bytes(), self.digest.as_bytes()] {
+            data.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            data.extend_from_slice(field);
+        }
+        data.push(u8::from(self.approved));
+        let reason = self.reason.as_deref().unwrap_or("");
+        data.extend_from_slice(&(reason.len() as u32).to_be_bytes());
+        data.extend_from_slice(reason.as_bytes());
+        data.extend_from_slice(&self.timestamp.to_be_bytes());
+        data.extend_from_slice(self.public_key.as_bytes());
+        data
+    }
+}
+
+/// Judges performers' outcomes for `POST /task/validate`.
+pub struct Validator {
+    trusted: Vec<VerifyingKey>,
+    metrics: Arc<Metrics>,
+}
+
+impl Validator {
+    /// Builds a validator trusting the keys of `role.trusted_performers`.
+    pub fn from_config(config: &RoleConfig, metrics: Arc<Metrics>) -> Result<Self, String> {
+        let trusted = config
+            .trusted_performers
+            .iter()
+            .map(|key| parse_key(key).map_err(|e| format!("role.trusted_performers: {}", e)))
+            .collect::<Result<_, _>>()?;
+        Ok(Validator { trusted, metrics })
+    }
+
+    /// Checks `outcome` and returns the verdict, signed by `attester`.
+    pub fn validate(
+        &self,
+        attester: &RngAttester,
+        outcome: &TaskOutcome,
+    ) -> Result<Verdict, String> {
+        let (digest, reason) = match self.check(outcome) {
+            Ok(digest) => (digest, None),
+            Err((digest, reason)) => (digest, Some(reason)),
+        };
+        let label = if reason.is_none() {
+            "approved"
+        } else {
+            "rejected"
+        };
+        self.metrics
+            .inc_counter("rng_validations_total", &[("verdict", label)], 1);
+        Verdict::sign(attester, &outcome.task_id, &digest, reason)
+    }
+
+    /// The outcome's payload digest, with the reason it is rejected if so.
+    fn check(&self, outcome: &TaskOutcome) -> Result<String, (String, String)> {
+        let outcome = outcome
+            .encoded(Encoding::Hex)
+            .map_err(|e| (String::new(), e))?;
+        let attestation = outcome.to_attestation().map_err(|e| (String::new(), e))?;
+        let digest = hex::encode(attestation.payload.digest());
+        let reject = |reason: String| (digest.clone(), reason);
+        if outcome.timelock.is_some() {
+            return Err(reject("the outcome is still sealed".to_string()));
+        }
+        reverify::check_outcome(&outcome, &self.trusted)
+            .map_err(|(kind, detail)| reject(format!("{}: {}", kind.as_str(), detail)))?;
+        if let Some(validity) = &attestation.payload.validity {
+            validity
+                .check(SystemClock.now_millis(), DEFAULT_CLOCK_SKEW)
+                .map_err(reject)?;
+        }
+        if outcome.batch.is_some() {
+            batch::verify_outcome(&outcome).map_err(reject)?;
+        }
+        Ok(digest)
+    }
+}
+
+/// Submitter of a performer node: has each outcome validated by the
+/// configured attesters and hands it on once a quorum approved.
+pub struct QuorumSubmitter {
+    attesters: Vec<(String, VerifyingKey)>,
+    quorum: usize,
+    agent: ureq::Agent,
+    resilience: Arc<Resilience>,
+    inner: Arc<dyn Submitter>,
+    metrics: Arc<Metrics>,
+}
+
+impl QuorumSubmitter {
+    /// Sends outcomes to the `role.attesters` and, once approved, to `inner`.
+    pub fn from_config(
+        config: &RoleConfig,
+        resilience: Arc<Resilience>,
+        inner: Arc<dyn Submitter>,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self, String> {
+        let attesters = config
+            .attesters
+            .iter()
+            .map(|AttesterConfig { url, public_key }| {
+                let key = parse_key(public_key)
+                    .map_err(|e| format!("role.attesters ({}): {}", url, e))?;
+                Ok((url.trim_end_matches('/').to_string(), key))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(QuorumSubmitter {
+            attesters,
+            quorum: config.quorum,
+            agent: ureq::AgentBuilder::new().build(),
+            resilience,
+            inner,
+            metrics,
+        })
+    }
+
+    /// Asks the attester at `url` for its verdict on `outcome`.
+    fn ask(
+        &self,
+        url: &str,
+        key: &VerifyingKey,
+        body: &str,
+        outcome: &TaskOutcome,
+        digest: &str,
+    ) -> Result<Verdict, String> {
+        let endpoint = format!("{}/task/validate", url);
+        let verdict: Verdict = self.resilience.call(url, |remaining| {
+            match self
+                .agent
+                .post(&endpoint)
+                .timeout(remaining)
+                .set("Content-Type", "application/json")
+                .send_string(body)
+            {
+                Ok(response) => response
+                    .into_string()
+                    .map_err(|e| CallError::Transient(e.to_string()))
+                    .and_then(|body| {
+                        serde_json::from_str(&body)
+                            .map_err(|e| CallError::Permanent(format!("unreadable verdict: {}", e)))
+                    }),
+                Err(ureq::Error::Status(code, _)) if code < 500 && code != 429 => {
+                    Err(CallError::Permanent(format!("HTTP {}", code)))
+                }
+                Err(e) => Err(CallError::Transient(e.to_string())),
+            }
+        })?;
+        if !verdict.is_from(key)? {
+            return Err(format!(
+                "{}: verdict is not signed by the attester's pinned key",
+                url
+            ));
+        }
+        if verdict.task_id != outcome.task_id || verdict.digest != digest {
+            return Err(format!("{}: verdict is about a different attestation", url));
+        }
+        Ok(verdict)
+    }
+}
+
+impl Submitter for QuorumSubmitter {
+    fn submit(&self, outcome: &TaskOutcome) -> Result<(), String> {
+        let digest = hex::encode(outcome.to_attestation()?.payload.digest());
+        let body = serde_json::to_string(outcome)
+            .map_err(|e| format!("Failed to encode outcome: {}", e))?;
+        let mut approvals = 0;
+        let mut objections = Vec::new();
+        for (url, key) in &self.attesters {
+            match self.ask(url, key, &body, outcome, &digest) {
+                Ok(verdict) if verdict.approved => approvals += 1,
+                Ok(verdict) => objections.push(format!(
+                    "{} rejected it: {}",
+                    url,
+                    verdict.reason.unwrap_or_default()
+                )),
+                Err(e) => {
+                    warn!("No verdict on task {}: {}", outcome.task_id, e);
+                    objections.push(e);
+                }
+            }
+            if approvals >= self.quorum {
+                break;
+            }
+        }
+        if approvals < self.quorum {
+            self.metrics
+                .inc_counter("rng_quorum_total", &[("outcome", "rejected")], 1);
+            return Err(format!(
+                "{} of {} required attester(s) approved task {} ({})",
+                approvals,
+                self.quorum,
+                outcome.task_id,
+                objections.join("; ")
+            ));
+        }
+        self.metrics
+            .inc_counter("rng_quorum_total", &[("outcome", "approved")], 1);
+        info!(
+            "Task {} approved by {} attester(s)",
+            outcome.task_id, approvals
+        );
+        self.inner.submit(outcome)
+    }
+
+    fn chain_height(&self) -> Result<Option<u64>, String> {
+        self.inner.chain_height()
+    }
+
+    fn dry_run(&self, outcome: &TaskOutcome) -> Result<Value, String> {
+        let urls: Vec<&str> = self.attesters.iter().map(|(url, _)| url.as_str()).collect();
+        Ok(json!({
+            "attesters": urls,
+            "quorum": self.quorum,
+            "then": self.inner.dry_run(outcome)?,
+        }))
+    }
+}
+
+fn parse_key(value: &str) -> Result<VerifyingKey, String> {
+    let bytes: [u8; 32] = hex::decode(value)
+        .map_err(|e| format!("invalid public key {}: {}", value, e))?
+        .try_into()
+        .map_err(|_| format!("public key {} must be 32 bytes", value))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("invalid public key {}: {}", value, e))
+}
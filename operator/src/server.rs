@@ -0,0 +1,1641 @@
+// src/server.rs
+
+//! HTTP interface of the operator.
+//!
+//! Endpoints:
+//! - `POST /task/execute` generates and attests a random value; a retry with
+//!   the same idempotency key returns the original (see [`crate::idempotency`]).
+//!   Byte fields are hex unless the body or `server.encoding` picks another
+//!   encoding (see [`crate::encoding`]). With a `timelock` the value is sealed
+//!   and the response is a commitment to it (see [`crate::timelock`]).
+//!   `length` (default `lengths.default`) must be within `lengths.min` and
+//!   `lengths.max`, and within the range of the task definition named. With
+//!   `"timings": true` the response carries how long each pipeline step took.
+//! - `POST /task/stream` returns up to `lengths.stream_max` bytes as attested,
+//!   hash-chained chunks, one outcome per line, each generated only when the
+//!   client has read the one before (see [`crate::stream`]).
+//! - `POST /task/execute_batch` fulfils up to `server.max_batch` requests under
+//!   one Merkle-rooted attestation, each returned with its inclusion proof
+//!   (see [`crate::batch`]). Each request's `length` and `taskDefinitionId`
+//!   are checked as for `/task/execute`.
+//! - `POST /task/validate` checks another operator's outcome and answers a
+//!   signed verdict (see [`crate::roles`]). Nodes running as `role.mode:
+//!   attester` serve no task endpoints but this one.
+//! - `GET /task/definitions` lists the task definitions a request may name
+//!   with `taskDefinitionId` (see [`crate::definitions`]).
+//! - `POST /admin/reload` re-reads the config file and applies non-critical settings.
+//! - `POST /admin/pause` and `POST /admin/resume` stop and restart admitting tasks.
+//! - `POST /admin/rotate-key` replaces the attestation key.
+//! - `POST /admin/flush-queue` drops queued tasks that have not been attested.
+//! - `GET /admin/tasks` lists in-flight tasks and their stages.
+//! - `GET /admin/config` returns the running config with secrets masked.
+//! - `POST /admin/revoke` issues a signed revocation of an attestation or key.
+//! - `GET /admin/reverify` returns the latest re-verification report (see [`crate::reverify`]).
+//! - `GET /metrics` renders the metrics registry in Prometheus text format.
+//! - `GET /latency` returns task latency percentiles, per pipeline step and
+//!   end to end, and how many tasks met `performance.latency_slo`.
+//! - `POST /p2p/message` accepts a signed envelope from another operator.
+//! - `GET /beacon/latest` and `GET /beacon/rounds/{round}` return beacon output.
+//! - `GET /beacon/head` returns the signed head of the round hash chain, and
+//!   `GET /beacon/rounds/{round}/proof` a past round with the links up to it
+//!   (see [`crate::beacon::verify_round_chain`]).
+//! - `GET /heartbeat` returns the latest signed heartbeat of this operator.
+//! - `GET /heartbeat/peers` returns the latest heartbeat seen from each committee member.
+//! - `POST /reputation/attestations` and `POST /reputation/heartbeats` score
+//!   other operators by what they sign, and need `reputation.token`;
+//!   `GET /reputation` and `GET /reputation/{key}` return the scores (see
+//!   [`crate::reputation`]).
+//! - `GET /revocations` returns every revocation issued (see [`crate::revocation`]).
+//! - `GET /identity` returns a signed statement of the operator's build
+//!   (see [`crate::provenance`]).
+//! - `GET /timelock/{task}` returns the commitment of a sealed task, with its
+//!   key once released; with tenants, only the tenant whose token is
+//!   presented can look up its tasks.
+//! - `GET /epochs` returns the master key and every epoch certificate (see [`crate::epochs`]).
+//! - `GET /conformance/vectors` returns the verifier conformance suite, and
+//!   `POST /conformance/report` grades a verifier's verdicts on it (see [`crate::conformance`]).
+//! - `GET /conformance/abi` returns a fixed attestation in the layout of the
+//!   Solidity verifier, with its digest and signature (see [`crate::abi`]).
+//! - `POST /attestations/export` returns the attestations completed in a time
+//!   range, with a signed receipt for them (see [`crate::receipts`]); like
+//!   `/task/execute` it serves the namespace of the tenant whose token is
+//!   presented, and without tenants it needs the admin token. Batch
+//!   responses carry a receipt too.
+//! - `GET /admin/faults` and `POST /admin/faults` read and set the faults
+//!   injected in builds with the `chaos` feature (see `operator::chaos`).
+//!
+//! Admin endpoints require `Authorization: Bearer <admin.token>` and are
+//! disabled while no token is configured. With `tenants` configured,
+//! `/task/execute` requires the bearer token of a tenant and serves the task
+//! in its namespace (see [`crate::tenants`]). With tracing enabled every request
+//! gets a server span, continuing the caller's `traceparent` if present, and
+//! responses carry its trace ID in `X-Trace-Id`.
+
+use std::io::{Cursor, Read};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use log::{info, warn};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::abi;
+use crate::batch::BatchEntry;
+use crate::beacon::BeaconNode;
+#[cfg(feature = "chaos")]
+use crate::chaos::{FaultSettings, Faults};
+use crate::config::{ConfigHandle, RateLimitConfig};
+use crate::conformance::{self, Submission};
+use crate::encoding::Encoding;
+use crate::epochs::EpochKeys;
+use crate::export;
+use crate::heartbeat::{Heartbeat, HeartbeatEmitter};
+use crate::idempotency::{Claim, IdempotencyCache, IdempotencyError};
+use crate::metrics::Metrics;
+use crate::p2p::Envelope;
+use crate::provenance::Provenance;
+use crate::queue::Priority;
+use crate::receipts::Receipt;
+use crate::reputation::{Observation, Reputation};
+use crate::reverify::Reverifier;
+use crate::revocation::{RevocationKind, RevocationRegistry};
+use crate::roles::Validator;
+use crate::storage::Storage;
+use crate::tasks::{
+    unix_millis, StreamRequest, TaskError, TaskOutcome, TaskRequest, TaskRunner, TaskStream,
+};
+use crate::telemetry::{SpanContext, SpanKind, Tracer};
+use crate::tenants::{self, Admission, Tenant, Tenants};
+use crate::timelock::Release;
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+/// Token bucket guarding the public (non-admin) endpoints.
+///
+/// Limits are read from the live config on each request, so a reload takes
+/// effect immediately without resetting the bucket.
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new() -> Self {
+        TokenBucket {
+            tokens: f64::MAX,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn try_take(&mut self, limits: &RateLimitConfig) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+        let burst = f64::from(limits.burst.max(1));
+        self.tokens = (self.tokens + elapsed * limits.requests_per_second).min(burst);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Body of `POST /task/execute`. All fields are optional.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExecuteBody {
+    task_id: Option<String>,
+    length: Option<usize>,
+    /// `"api"` (default) or `"on_chain"`.
+    priority: Option<Priority>,
+    /// Unix time in milliseconds after which the task must not be fulfilled.
+    deadline: Option<u64>,
+    /// Caller entropy mixed into the output and attested, in `encoding`;
+    /// fingerprinted as the bytes it decodes to.
+    #[serde(skip_serializing)]
+    client_entropy: Option<String>,
+    /// Attest but do not submit; the response carries what would have been sent.
+    dry_run: Option<bool>,
+    /// Encoding of `clientEntropy` and of the response's byte fields
+    /// (default `server.encoding`). Presentation only, so a retry may change
+    /// it without conflicting with its idempotency key.
+    #[serde(skip_serializing)]
+    encoding: Option<Encoding>,
+    /// Include the per-step [`crate::tasks::TaskTimings`] in the response;
+    /// presentation only, like `encoding`.
+    #[serde(skip_serializing)]
+    timings: Option<bool>,
+    /// Task definition to serve the request under (see [`crate::definitions`]);
+    /// sets the default length and the range `length` may pick from.
+    task_definition_id: Option<String>,
+    /// Seal the value until this release (see [`crate::timelock`]); the
+    /// response is then the commitment.
+    timelock: Option<Release>,
+}
+
+/// Body of `POST /task/execute_batch`: the request contexts, and the
+/// settings they share.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchBody {
+    /// Task ID of the batch root; generated when unset.
+    batch_id: Option<String>,
+    requests: Vec<BatchRequestBody>,
+    priority: Option<Priority>,
+    deadline: Option<u64>,
+    dry_run: Option<bool>,
+    #[serde(skip_serializing)]
+    encoding: Option<Encoding>,
+    #[serde(skip_serializing)]
+    timings: Option<bool>,
+}
+
+/// One request context of a [`BatchBody`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchRequestBody {
+    task_id: Option<String>,
+    length: Option<usize>,
+    /// Checked as for `/task/execute`; the batch root is served under none.
+    task_definition_id: Option<String>,
+    #[serde(skip_serializing)]
+    client_entropy: Option<String>,
+}
+
+/// Body of `POST /attestations/export`: Unix milliseconds or UTC dates as
+/// for the `export` command; `from` is inclusive, `to` exclusive.
+#[derive(Debug, Deserialize)]
+struct ExportBody {
+    from: Option<Value>,
+    to: Option<Value>,
+}
+
+/// Body of `POST /task/stream`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamBody {
+    /// Task ID every chunk is returned under; generated when unset.
+    task_id: Option<String>,
+    length: usize,
+    /// Bytes per chunk (default `lengths.stream_chunk`).
+    chunk_size: Option<usize>,
+    /// Encoding of the chunks' byte fields (default `server.encoding`).
+    encoding: Option<Encoding>,
+}
+
+/// Response body of `POST /task/stream`: one outcome per line, each chunk
+/// generated and signed only once the client has read the line before.
+struct ChunkLines<'a> {
+    chunks: TaskStream<'a>,
+    metrics: &'a Metrics,
+    encoding: Encoding,
+    line: Vec<u8>,
+    offset: usize,
+    done: bool,
+    // Held until the stream has been sent, which frees the tenant's slot.
+    _admission: Option<Admission>,
+}
+
+impl ChunkLines<'_> {
+    /// The next line of the body, or `None` after the last chunk or an error.
+    fn next_line(&mut self) -> Option<Vec<u8>> {
+        if self.done {
+            return None;
+        }
+        let outcome = match self.chunks.next()? {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                self.done = true;
+                return Some(error_line(&e));
+            }
+        };
+        let length = outcome.random_number.len() as u64;
+        match outcome.encoded(self.encoding) {
+            Ok(outcome) => {
+                self.metrics
+                    .inc_counter("rng_stream_bytes_total", &[], length);
+                let mut line = serde_json::to_vec(&outcome).expect("outcome serializes");
+                line.push(b'\n');
+                Some(line)
+            }
+            Err(e) => {
+                self.done = true;
+                Some(error_line(&e))
+            }
+        }
+    }
+}
+
+impl Read for ChunkLines<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.offset == self.line.len() {
+            match self.next_line() {
+                Some(line) => {
+                    self.line = line;
+                    self.offset = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.line.len() - self.offset);
+        buf[..n].copy_from_slice(&self.line[self.offset..self.offset + n]);
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+fn error_line(error: &str) -> Vec<u8> {
+    let mut line = json!({ "error": error }).to_string().into_bytes();
+    line.push(b'\n');
+    line
+}
+
+/// Body of `POST /admin/revoke`: exactly one of `attestation`, `taskId` or
+/// `publicKey`, and a reason.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RevokeBody {
+    /// Hex payload digest of the attestation to revoke.
+    attestation: Option<String>,
+    /// Task whose stored attestation to revoke, of `tenant` if set.
+    task_id: Option<String>,
+    tenant: Option<String>,
+    /// Hex ed25519 key whose attestations to revoke, from `since` (Unix ms) on.
+    public_key: Option<String>,
+    since: Option<u64>,
+    reason: String,
+}
+
+/// `Server` owns the HTTP listener and routes requests to operator components.
+pub struct Server {
+    config: Arc<ConfigHandle>,
+    metrics: Arc<Metrics>,
+    runner: Arc<TaskRunner>,
+    beacon: Option<Arc<BeaconNode>>,
+    heartbeat: Option<Arc<HeartbeatEmitter>>,
+    tracer: Arc<Tracer>,
+    tenants: Option<Arc<Tenants>>,
+    revocations: Option<Arc<RevocationRegistry>>,
+    epochs: Option<Arc<EpochKeys>>,
+    idempotency: Option<Arc<IdempotencyCache>>,
+    provenance: Option<Arc<Provenance>>,
+    reverifier: Option<Arc<Reverifier>>,
+    validator: Option<Arc<Validator>>,
+    reputation: Option<Arc<Reputation>>,
+    storage: Option<Arc<dyn Storage>>,
+    #[cfg(feature = "chaos")]
+    faults: Option<Arc<Faults>>,
+    limiter: Mutex<TokenBucket>,
+}
+
+impl Server {
+    /// Creates a server bound to the shared config, metrics and task runner.
+    pub fn new(config: Arc<ConfigHandle>, metrics: Arc<Metrics>, runner: Arc<TaskRunner>) -> Self {
+        Server {
+            config,
+            metrics,
+            runner,
+            beacon: None,
+            heartbeat: None,
+            tracer: Arc::new(Tracer::disabled()),
+            tenants: None,
+            revocations: None,
+            epochs: None,
+            idempotency: None,
+            provenance: None,
+            reverifier: None,
+            validator: None,
+            reputation: None,
+            storage: None,
+            #[cfg(feature = "chaos")]
+            faults: None,
+            limiter: Mutex::new(TokenBucket::new()),
+        }
+    }
+
+    /// Serves the peer-to-peer and beacon endpoints backed by `beacon`.
+    pub fn with_beacon(mut self, beacon: Arc<BeaconNode>) -> Self {
+        self.beacon = Some(beacon);
+        self
+    }
+
+    /// Serves the latest heartbeat emitted by `heartbeat`.
+    pub fn with_heartbeat(mut self, heartbeat: Arc<HeartbeatEmitter>) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    /// Opens a span for every request with `tracer`.
+    pub fn with_tracer(mut self, tracer: Arc<Tracer>) -> Self {
+        self.tracer = tracer;
+        self
+    }
+
+    /// Serves tasks in the namespaces of `tenants`.
+    pub fn with_tenants(mut self, tenants: Arc<Tenants>) -> Self {
+        self.tenants = Some(tenants);
+        self
+    }
+
+    /// Serves the certificate chain of `epochs`.
+    pub fn with_epochs(mut self, epochs: Arc<EpochKeys>) -> Self {
+        self.epochs = Some(epochs);
+        self
+    }
+
+    /// Serves build statements from `provenance`.
+    pub fn with_provenance(mut self, provenance: Arc<Provenance>) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Answers repeated idempotency keys from `idempotency`.
+    pub fn with_idempotency(mut self, idempotency: Arc<IdempotencyCache>) -> Self {
+        self.idempotency = Some(idempotency);
+        self
+    }
+
+    /// Serves the latest report of `reverifier`.
+    pub fn with_reverifier(mut self, reverifier: Arc<Reverifier>) -> Self {
+        self.reverifier = Some(reverifier);
+        self
+    }
+
+    /// Judges other operators' outcomes with `validator`.
+    pub fn with_validator(mut self, validator: Arc<Validator>) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    /// Scores other operators with `reputation`, including those whose
+    /// outcomes are sent for validation.
+    pub fn with_reputation(mut self, reputation: Arc<Reputation>) -> Self {
+        self.reputation = Some(reputation);
+        self
+    }
+
+    /// Serves `/attestations/export` from `storage`.
+    pub fn with_storage(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Serves `/admin/faults` and serves stale beacon rounds while `faults`
+    /// says so.
+    #[cfg(feature = "chaos")]
+    pub fn with_faults(mut self, faults: Arc<Faults>) -> Self {
+        self.faults = Some(faults);
+        self
+    }
+
+    /// Issues and serves revocations with `revocations`.
+    pub fn with_revocations(mut self, revocations: Arc<RevocationRegistry>) -> Self {
+        self.revocations = Some(revocations);
+        self
+    }
+
+    /// Binds `server.listen` and serves requests on `server.workers` threads
+    /// until the listener fails.
+    pub fn run(&self) -> Result<(), String> {
+        let settings = self.config.current().server.clone();
+        let http = tiny_http::Server::http(&settings.listen)
+            .map_err(|e| format!("Failed to bind {}: {}", settings.listen, e))?;
+        info!(
+            "Listening on {} with {} worker(s)",
+            settings.listen, settings.workers
+        );
+
+        std::thread::scope(|scope| {
+            for _ in 0..settings.workers {
+                scope.spawn(|| {
+                    for request in http.incoming_requests() {
+                        self.handle(request);
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    fn handle(&self, mut request: Request) {
+        let method = request.method().clone();
+        let path = request.url().split('?').next().unwrap_or("").to_string();
+        let header = |name: &'static str| {
+            request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv(name))
+                .map(|h| h.value.as_str().to_string())
+        };
+        let authorization = header("Authorization");
+        let idempotency_key = header("Idempotency-Key");
+        let parent = header("traceparent").and_then(|v| SpanContext::from_traceparent(&v));
+        let mut span = self.tracer.start(
+            &format!("{} {}", method, route_template(&path)),
+            SpanKind::Server,
+            parent.as_ref(),
+        );
+        span.set_attribute("http.request.method", method.as_str());
+        span.set_attribute("url.path", &path);
+
+        let mut body = String::new();
+        // Outlives the stream's chunks, which are signed with it as they are sent.
+        let mut stream = None;
+        let max_body = self.config.current().server.max_body_bytes;
+        let read = request
+            .as_reader()
+            .take(max_body as u64 + 1)
+            .read_to_string(&mut body);
+        let mut response = match read {
+            Ok(n) if n > max_body => json_response(
+                413,
+                json!({ "error": format!("Request body exceeds {} bytes", max_body) }),
+            ),
+            Ok(_) if method == Method::Post && path == "/task/stream" => {
+                match self.open_stream(&body, authorization.as_deref()) {
+                    Ok((head, chunks)) => {
+                        stream = Some(chunks);
+                        head
+                    }
+                    Err(response) => response,
+                }
+            }
+            Ok(_) => self.route(
+                &method,
+                &path,
+                &body,
+                authorization.as_deref(),
+                idempotency_key.as_deref(),
+                &span.context,
+            ),
+            Err(e) => json_response(400, json!({ "error": format!("Unreadable body: {}", e) })),
+        };
+
+        let status = response.status_code().0;
+        span.set_int_attribute("http.response.status_code", i64::from(status));
+        if status >= 500 {
+            span.set_error(&format!("HTTP {}", status));
+        }
+        if self.tracer.is_enabled() {
+            response.add_header(
+                Header::from_bytes(&b"X-Trace-Id"[..], span.context.trace_id_hex().as_bytes())
+                    .expect("hex header is valid"),
+            );
+        }
+        self.tracer.end(span);
+
+        self.metrics.inc_counter(
+            "rng_http_requests_total",
+            &[
+                ("path", &path),
+                ("status", &response.status_code().0.to_string()),
+            ],
+            1,
+        );
+        let sent = match stream {
+            Some(chunks) => request.respond(response.with_data(chunks, None)),
+            None => request.respond(response),
+        };
+        if let Err(e) = sent {
+            warn!("Failed to send response for {}: {}", path, e);
+        }
+    }
+
+    fn route(
+        &self,
+        method: &Method,
+        path: &str,
+        body: &str,
+        authorization: Option<&str>,
+        idempotency_key: Option<&str>,
+        trace: &SpanContext,
+    ) -> HttpResponse {
+        if path.starts_with("/admin/") {
+            if let Err(response) = self.authorize(authorization) {
+                return response;
+            }
+        } else if let Err(response) = self.throttle() {
+            return response;
+        }
+        if matches!(path, "/task/execute" | "/task/execute_batch") {
+            if let Err(response) = self.refuse_generation() {
+                return response;
+            }
+        }
+
+        match (method, path) {
+            (Method::Post, "/task/execute") => {
+                self.execute(body, authorization, idempotency_key, trace)
+            }
+            (Method::Post, "/task/execute_batch") => {
+                self.execute_batch(body, authorization, idempotency_key, trace)
+            }
+            (Method::Post, "/task/validate") => self.validate(body),
+            (Method::Post, "/attestations/export") => self.export(body, authorization),
+            (Method::Post, "/admin/reload") => self.reload(),
+            (Method::Post, "/admin/pause") => {
+                self.runner.pause();
+                info!("Task admission paused through the admin API");
+                json_response(200, json!({ "paused": true }))
+            }
+            (Method::Post, "/admin/resume") => {
+                self.runner.resume();
+                info!("Task admission resumed through the admin API");
+                json_response(200, json!({ "paused": false }))
+            }
+            (Method::Post, "/admin/rotate-key") => self.rotate_key(),
+            (Method::Post, "/admin/flush-queue") => {
+                let flushed = self.runner.flush_queue();
+                info!(
+                    "Flushed {} queued task(s) through the admin API",
+                    flushed.len()
+                );
+                json_response(200, json!({ "flushed": flushed }))
+            }
+            (Method::Get, "/task/definitions") => {
+                json_response(200, json!(self.runner.definitions().list()))
+            }
+            (Method::Get, "/admin/tasks") => self.tasks(),
+            (Method::Get, "/admin/config") => {
+                json_response(200, json!(self.config.current().redacted()))
+            }
+            (Method::Post, "/admin/revoke") => self.revoke(body),
+            (Method::Get, "/admin/faults") => self.faults(None),
+            (Method::Post, "/admin/faults") => self.faults(Some(body)),
+            (Method::Get, "/admin/reverify") => match &self.reverifier {
+                Some(reverifier) => match reverifier.latest() {
+                    Some(report) => json_response(200, json!(report)),
+                    None => {
+                        json_response(404, json!({ "error": "No re-verification has run yet" }))
+                    }
+                },
+                None => json_response(404, json!({ "error": "Re-verification is not enabled" })),
+            },
+            (Method::Get, "/metrics") => text_response(200, self.metrics.render()),
+            (Method::Get, "/latency") => json_response(200, json!(self.runner.latency())),
+            (Method::Post, "/p2p/message") => self.p2p_message(body),
+            (Method::Get, "/beacon/latest") => self.beacon_round(None),
+            (Method::Get, "/beacon/head") => self.beacon_head(),
+            (Method::Get, _) if path.starts_with("/beacon/rounds/") => {
+                let rest = &path["/beacon/rounds/".len()..];
+                let (round, proof) = match rest.strip_suffix("/proof") {
+                    Some(round) => (round, true),
+                    None => (rest, false),
+                };
+                match round.parse() {
+                    Ok(round) if proof => self.beacon_chain(round),
+                    Ok(round) => self.beacon_round(Some(round)),
+                    Err(_) => json_response(400, json!({ "error": "Invalid round number" })),
+                }
+            }
+            (Method::Get, "/heartbeat") => self.heartbeat(),
+            (Method::Get, "/heartbeat/peers") => match &self.beacon {
+                Some(beacon) => json_response(200, json!(beacon.peer_heartbeats())),
+                None => json_response(404, json!({ "error": "Beacon is not enabled" })),
+            },
+            (Method::Post, "/reputation/attestations") => {
+                match self.authorize_gossip(authorization) {
+                    Ok(()) => self.observe_attestation(body),
+                    Err(response) => response,
+                }
+            }
+            (Method::Post, "/reputation/heartbeats") => {
+                match self.authorize_gossip(authorization) {
+                    Ok(()) => self.observe_heartbeat(body),
+                    Err(response) => response,
+                }
+            }
+            (Method::Get, "/reputation") => match &self.reputation {
+                Some(reputation) => json_response(200, json!(reputation.scores())),
+                None => json_response(404, json!({ "error": "Reputation is not enabled" })),
+            },
+            (Method::Get, _) if path.starts_with("/reputation/") => match &self.reputation {
+                Some(reputation) => match reputation.score_of(&path["/reputation/".len()..]) {
+                    Some(score) => json_response(200, json!(score)),
+                    None => json_response(404, json!({ "error": "No such operator observed" })),
+                },
+                None => json_response(404, json!({ "error": "Reputation is not enabled" })),
+            },
+            (Method::Get, "/revocations") => match &self.revocations {
+                Some(registry) => match registry.list() {
+                    Ok(list) => json_response(200, json!(list)),
+                    Err(e) => json_response(500, json!({ "error": e })),
+                },
+                None => json_response(404, json!({ "error": "Revocations are not enabled" })),
+            },
+            (Method::Get, "/identity") => match &self.provenance {
+                Some(provenance) => match provenance.statement(&self.runner.attester()) {
+                    Ok(statement) => json_response(200, json!(statement)),
+                    Err(e) => json_response(500, json!({ "error": e })),
+                },
+                None => json_response(404, json!({ "error": "Provenance is not enabled" })),
+            },
+            (Method::Get, "/epochs") => self.epochs(),
+            (Method::Get, _) if path.starts_with("/timelock/") => {
+                self.timelock(&path["/timelock/".len()..], authorization)
+            }
+            (Method::Get, "/conformance/abi") => json_response(200, json!(abi::fixture())),
+            (Method::Get, "/conformance/vectors") => {
+                json_response(200, json!(conformance::suite()))
+            }
+            (Method::Post, "/conformance/report") => conformance_report(body),
+            _ => json_response(404, json!({ "error": "Not found" })),
+        }
+    }
+
+    /// Checks the bearer token against `admin.token` from the live config.
+    fn authorize(&self, authorization: Option<&str>) -> Result<(), HttpResponse> {
+        let Some(token) = self.config.current().admin.token.clone() else {
+            return Err(json_response(
+                403,
+                json!({ "error": "Admin API is disabled" }),
+            ));
+        };
+        let presented = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or("");
+        if !tokens_match(presented, &token) {
+            return Err(json_response(
+                401,
+                json!({ "error": "Invalid admin token" }),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the `reputation.token` a gossiper presents.
+    fn authorize_gossip(&self, authorization: Option<&str>) -> Result<(), HttpResponse> {
+        let Some(token) = self.config.current().reputation.token.clone() else {
+            return Err(json_response(
+                403,
+                json!({ "error": "Reputation gossip is disabled; set reputation.token" }),
+            ));
+        };
+        let presented = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or("");
+        if !tokens_match(presented, &token) {
+            return Err(json_response(
+                401,
+                json!({ "error": "Invalid reputation token" }),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Answers 403 on a node that only attests.
+    fn refuse_generation(&self) -> Result<(), HttpResponse> {
+        if self.config.current().role.mode.generates() {
+            return Ok(());
+        }
+        Err(json_response(
+            403,
+            json!({ "error": "This node only attests; send tasks to a performer" }),
+        ))
+    }
+
+    fn validate(&self, body: &str) -> HttpResponse {
+        let Some(validator) = &self.validator else {
+            return json_response(404, json!({ "error": "This node does not validate" }));
+        };
+        let outcome: TaskOutcome = match serde_json::from_str(body) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                return json_response(400, json!({ "error": format!("Invalid outcome: {}", e) }))
+            }
+        };
+        if let Some(reputation) = &self.reputation {
+            let observation = Observation {
+                outcome: outcome.clone(),
+            };
+            // The verdict says what is wrong with an outcome that fails.
+            let _ = reputation.observe_attestation(&observation);
+        }
+        match validator.validate(&self.runner.attester(), &outcome) {
+            Ok(verdict) => json_response(200, json!(verdict)),
+            Err(e) => json_response(500, json!({ "error": e })),
+        }
+    }
+
+    fn observe_attestation(&self, body: &str) -> HttpResponse {
+        let Some(reputation) = &self.reputation else {
+            return json_response(404, json!({ "error": "Reputation is not enabled" }));
+        };
+        let observation: Observation = match serde_json::from_str(body) {
+            Ok(observation) => observation,
+            Err(e) => {
+                return json_response(
+                    400,
+                    json!({ "error": format!("Invalid observation: {}", e) }),
+                )
+            }
+        };
+        match reputation.observe_attestation(&observation) {
+            Ok(score) => json_response(200, json!(score)),
+            Err(e) => json_response(400, json!({ "error": e })),
+        }
+    }
+
+    fn observe_heartbeat(&self, body: &str) -> HttpResponse {
+        let Some(reputation) = &self.reputation else {
+            return json_response(404, json!({ "error": "Reputation is not enabled" }));
+        };
+        let heartbeat: Heartbeat = match serde_json::from_str(body) {
+            Ok(heartbeat) => heartbeat,
+            Err(e) => {
+                return json_response(400, json!({ "error": format!("Invalid heartbeat: {}", e) }))
+            }
+        };
+        match reputation.observe_heartbeat(&heartbeat) {
+            Ok(score) => json_response(200, json!(score)),
+            Err(e) => json_response(400, json!({ "error": e })),
+        }
+    }
+
+    /// Takes a token for a public endpoint, or answers 429.
+    fn throttle(&self) -> Result<(), HttpResponse> {
+        let limits = self.config.current().rate_limits.clone();
+        let allowed = self
+            .limiter
+            .lock()
+            .expect("rate limiter lock poisoned")
+            .try_take(&limits);
+        if !allowed {
+            return Err(json_response(
+                429,
+                json!({ "error": "Rate limit exceeded" }),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Admits a task of the tenant whose token is presented; `None` when
+    /// tasks are not served per tenant.
+    fn admit_tenant(&self, authorization: Option<&str>) -> Result<Option<Admission>, HttpResponse> {
+        let Some(tenants) = self.tenants.as_ref().filter(|t| t.is_enabled()) else {
+            return Ok(None);
+        };
+        let presented = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or("");
+        let Some(tenant) = tenants.authenticate(presented) else {
+            return Err(json_response(
+                401,
+                json!({ "error": "Invalid tenant token" }),
+            ));
+        };
+        match tenants.admit(tenant) {
+            Ok(admission) => Ok(Some(admission)),
+            Err(e) => Err(json_response(429, json!({ "error": e }))),
+        }
+    }
+
+    /// Looks up a sealed task in the namespace of the presented tenant.
+    fn timelock(&self, task_id: &str, authorization: Option<&str>) -> HttpResponse {
+        let admission = match self.admit_tenant(authorization) {
+            Ok(admission) => admission,
+            Err(response) => return response,
+        };
+        // A lookup generates nothing, so it costs no quota.
+        if let Some(admission) = &admission {
+            admission.refund();
+        }
+        let tenant = admission.as_ref().map(|a| a.tenant().id.as_str());
+        match self.runner.timelock(&tenants::task_key(tenant, task_id)) {
+            Ok(Some(status)) => json_response(200, json!(status)),
+            Ok(None) => json_response(404, json!({ "error": "No such timelocked task" })),
+            Err(e) => json_response(500, json!({ "error": e })),
+        }
+    }
+
+    fn rotate_key(&self) -> HttpResponse {
+        match self.runner.rotate_key() {
+            Ok((previous, current)) => {
+                info!(
+                    "Attestation key rotated from {} to {}",
+                    hex::encode(previous.as_bytes()),
+                    hex::encode(current.as_bytes())
+                );
+                json_response(
+                    200,
+                    json!({
+                        "previousPublicKey": hex::encode(previous.as_bytes()),
+                        "publicKey": hex::encode(current.as_bytes()),
+                    }),
+                )
+            }
+            Err(e) => json_response(500, json!({ "error": e })),
+        }
+    }
+
+    fn revoke(&self, body: &str) -> HttpResponse {
+        let Some(registry) = &self.revocations else {
+            return json_response(404, json!({ "error": "Revocations are not enabled" }));
+        };
+        let parsed: RevokeBody = match serde_json::from_str(body) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                return json_response(400, json!({ "error": format!("Invalid body: {}", e) }))
+            }
+        };
+        let attester = self.runner.attester();
+        let issued = match (&parsed.attestation, &parsed.task_id, &parsed.public_key) {
+            (Some(digest), None, None) if parsed.tenant.is_none() => registry.revoke(
+                &attester,
+                RevocationKind::Attestation,
+                digest,
+                parsed.since,
+                &parsed.reason,
+            ),
+            (None, Some(task_id), None) if parsed.since.is_none() => {
+                registry.revoke_task(&attester, parsed.tenant.as_deref(), task_id, &parsed.reason)
+            }
+            (None, None, Some(key)) if parsed.tenant.is_none() => registry.revoke(
+                &attester,
+                RevocationKind::Key,
+                key,
+                parsed.since,
+                &parsed.reason,
+            ),
+            _ => Err(
+                "Give exactly one of attestation, taskId (with tenant) or publicKey (with since)"
+                    .to_string(),
+            ),
+        };
+        match issued {
+            Ok(revocation) => {
+                info!(
+                    "Revocation #{} issued for {:?} {}: {}",
+                    revocation.serial, revocation.kind, revocation.target, revocation.reason
+                );
+                json_response(200, json!(revocation))
+            }
+            Err(e) => json_response(400, json!({ "error": e })),
+        }
+    }
+
+    fn epochs(&self) -> HttpResponse {
+        let Some(epochs) = &self.epochs else {
+            return json_response(404, json!({ "error": "Epoch keys are not enabled" }));
+        };
+        match epochs.certificates() {
+            Ok(certificates) => json_response(
+                200,
+                json!({
+                    "masterPublicKey": hex::encode(epochs.master_public_key().as_bytes()),
+                    "certificates": certificates,
+                }),
+            ),
+            Err(e) => json_response(500, json!({ "error": e })),
+        }
+    }
+
+    fn tasks(&self) -> HttpResponse {
+        let mut tasks = self.runner.in_flight();
+        tasks.sort_by(|a, b| a.0.cmp(&b.0));
+        let tasks: Vec<Value> = tasks
+            .into_iter()
+            .map(|(task_id, stage)| json!({ "taskId": task_id, "stage": stage }))
+            .collect();
+        json_response(
+            200,
+            json!({
+                "paused": self.runner.is_paused(),
+                "queued": self.runner.queued(),
+                "tasks": tasks,
+            }),
+        )
+    }
+
+    fn p2p_message(&self, body: &str) -> HttpResponse {
+        let Some(beacon) = &self.beacon else {
+            return json_response(404, json!({ "error": "Beacon is not enabled" }));
+        };
+        let envelope: Envelope = match serde_json::from_str(body) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                return json_response(400, json!({ "error": format!("Invalid envelope: {}", e) }))
+            }
+        };
+        match beacon.p2p().receive(envelope) {
+            Ok(()) => json_response(202, json!({ "accepted": true })),
+            Err(e) => json_response(403, json!({ "error": e })),
+        }
+    }
+
+    fn beacon_round(&self, round: Option<u64>) -> HttpResponse {
+        let Some(beacon) = &self.beacon else {
+            return json_response(404, json!({ "error": "Beacon is not enabled" }));
+        };
+        let record = match round {
+            Some(round) => beacon.round(round),
+            None => self.latest_round(beacon),
+        };
+        match record {
+            Ok(Some(record)) => json_response(200, record),
+            Ok(None) => json_response(404, json!({ "error": "No such beacon round" })),
+            Err(e) => json_response(500, json!({ "error": e })),
+        }
+    }
+
+    #[cfg(feature = "chaos")]
+    fn latest_round(&self, beacon: &BeaconNode) -> Result<Option<Value>, String> {
+        let stale = match (&self.faults, beacon.latest_round()?) {
+            (Some(faults), Some(latest)) => faults.stale_round(latest),
+            _ => None,
+        };
+        match stale {
+            Some(round) => beacon.round(round),
+            None => beacon.latest(),
+        }
+    }
+
+    #[cfg(not(feature = "chaos"))]
+    fn latest_round(&self, beacon: &BeaconNode) -> Result<Option<Value>, String> {
+        beacon.latest()
+    }
+
+    /// Returns the injected faults, after replacing them with `body` if given.
+    #[cfg(feature = "chaos")]
+    fn faults(&self, body: Option<&str>) -> HttpResponse {
+        let Some(faults) = &self.faults else {
+            return json_response(404, json!({ "error": "Fault injection is not enabled" }));
+        };
+        if let Some(body) = body {
+            let settings: FaultSettings = match serde_json::from_str(body) {
+                Ok(settings) => settings,
+                Err(e) => {
+                    return json_response(
+                        400,
+                        json!({ "error": format!("Invalid fault settings: {}", e) }),
+                    )
+                }
+            };
+            if let Err(e) = faults.set(settings) {
+                return json_response(400, json!({ "error": e }));
+            }
+        }
+        json_response(200, json!(faults.settings()))
+    }
+
+    #[cfg(not(feature = "chaos"))]
+    fn faults(&self, _body: Option<&str>) -> HttpResponse {
+        json_response(
+            404,
+            json!({ "error": "Fault injection is not built in; rebuild with --features chaos" }),
+        )
+    }
+
+    fn beacon_head(&self) -> HttpResponse {
+        let Some(beacon) = &self.beacon else {
+            return json_response(404, json!({ "error": "Beacon is not enabled" }));
+        };
+        match beacon.head() {
+            Ok(Some(head)) => json_response(200, json!(head)),
+            Ok(None) => json_response(404, json!({ "error": "No beacon round completed yet" })),
+            Err(e) => json_response(500, json!({ "error": e })),
+        }
+    }
+
+    fn beacon_chain(&self, round: u64) -> HttpResponse {
+        let Some(beacon) = &self.beacon else {
+            return json_response(404, json!({ "error": "Beacon is not enabled" }));
+        };
+        match beacon.round_chain(round) {
+            Ok(Some(chain)) => json_response(200, json!(chain)),
+            Ok(None) => json_response(404, json!({ "error": "No such beacon round" })),
+            Err(e) => json_response(500, json!({ "error": e })),
+        }
+    }
+
+    fn heartbeat(&self) -> HttpResponse {
+        let Some(emitter) = &self.heartbeat else {
+            return json_response(404, json!({ "error": "Heartbeats are not enabled" }));
+        };
+        match emitter.latest() {
+            Some(heartbeat) => json_response(200, json!(heartbeat)),
+            None => json_response(503, json!({ "error": "No heartbeat emitted yet" })),
+        }
+    }
+
+    fn execute(
+        &self,
+        body: &str,
+        authorization: Option<&str>,
+        idempotency_key: Option<&str>,
+        trace: &SpanContext,
+    ) -> HttpResponse {
+        let parsed: ExecuteBody = if body.trim().is_empty() {
+            ExecuteBody::default()
+        } else {
+            match serde_json::from_str(body) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    return json_response(400, json!({ "error": format!("Invalid body: {}", e) }))
+                }
+            }
+        };
+
+        let encoding = parsed
+            .encoding
+            .unwrap_or(self.config.current().server.encoding);
+        let client_entropy = match parsed.client_entropy.as_deref().map(|e| encoding.decode(e)) {
+            None => None,
+            Some(Ok(bytes)) => Some(bytes),
+            Some(Err(e)) => {
+                return json_response(
+                    400,
+                    json!({ "error": format!("Invalid clientEntropy: {}", e) }),
+                )
+            }
+        };
+
+        let length = match self.request_length(parsed.length, parsed.task_definition_id.as_deref())
+        {
+            Ok(length) => length,
+            Err(e) => return json_response(400, json!({ "error": e })),
+        };
+
+        if parsed.timelock.is_some_and(|r| r.block.is_some())
+            && !self.config.current().chain.enabled
+        {
+            return json_response(
+                400,
+                json!({ "error": "Block-height releases need chain submission enabled" }),
+            );
+        }
+
+        let fingerprint = fingerprint(&parsed, std::slice::from_ref(&client_entropy));
+        self.fulfil(
+            fingerprint,
+            parsed.task_id.as_deref(),
+            authorization,
+            idempotency_key,
+            Presentation {
+                encoding,
+                timings: parsed.timings.unwrap_or(false),
+                receipt: false,
+            },
+            |tenant| TaskRequest {
+                task_id: parsed.task_id.clone().unwrap_or_else(new_task_id),
+                length,
+                priority: parsed.priority.unwrap_or_default(),
+                deadline: parsed.deadline,
+                client_entropy,
+                trace: Some(*trace),
+                dry_run: parsed.dry_run.unwrap_or(false),
+                tenant: tenant.map(|t| t.id.clone()),
+                domain: tenant.map(|t| t.domain.clone()),
+                definition: parsed.task_definition_id.clone(),
+                timelock: parsed.timelock,
+                batch: None,
+            },
+        )
+    }
+
+    fn execute_batch(
+        &self,
+        body: &str,
+        authorization: Option<&str>,
+        idempotency_key: Option<&str>,
+        trace: &SpanContext,
+    ) -> HttpResponse {
+        let parsed: BatchBody = match serde_json::from_str(body) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                return json_response(400, json!({ "error": format!("Invalid body: {}", e) }))
+            }
+        };
+        let settings = self.config.current();
+        if parsed.requests.is_empty() || parsed.requests.len() > settings.server.max_batch {
+            return json_response(
+                400,
+                json!({ "error": format!("A batch takes 1 to {} requests", settings.server.max_batch) }),
+            );
+        }
+
+        let encoding = parsed.encoding.unwrap_or(settings.server.encoding);
+        let mut entries = Vec::with_capacity(parsed.requests.len());
+        let mut entropy = Vec::with_capacity(parsed.requests.len());
+        for (n, item) in parsed.requests.iter().enumerate() {
+            let client_entropy = match item.client_entropy.as_deref().map(|e| encoding.decode(e)) {
+                None => None,
+                Some(Ok(bytes)) => Some(bytes),
+                Some(Err(e)) => {
+                    return json_response(
+                        400,
+                        json!({ "error": format!("Invalid clientEntropy of request {}: {}", n, e) }),
+                    )
+                }
+            };
+            let length = match self.request_length(item.length, item.task_definition_id.as_deref())
+            {
+                Ok(length) => length,
+                Err(e) => {
+                    return json_response(400, json!({ "error": format!("Request {}: {}", n, e) }))
+                }
+            };
+            entries.push(BatchEntry {
+                task_id: item.task_id.clone().unwrap_or_else(new_task_id),
+                length,
+                client_entropy: client_entropy.as_ref().map(hex::encode),
+            });
+            entropy.push(client_entropy);
+        }
+
+        let fingerprint = fingerprint(&parsed, &entropy);
+        self.fulfil(
+            fingerprint,
+            parsed.batch_id.as_deref(),
+            authorization,
+            idempotency_key,
+            Presentation {
+                encoding,
+                timings: parsed.timings.unwrap_or(false),
+                receipt: true,
+            },
+            |tenant| TaskRequest {
+                task_id: parsed.batch_id.clone().unwrap_or_else(new_task_id),
+                length: 32,
+                priority: parsed.priority.unwrap_or_default(),
+                deadline: parsed.deadline,
+                client_entropy: None,
+                trace: Some(*trace),
+                dry_run: parsed.dry_run.unwrap_or(false),
+                tenant: tenant.map(|t| t.id.clone()),
+                domain: tenant.map(|t| t.domain.clone()),
+                definition: None,
+                timelock: None,
+                batch: Some(entries),
+            },
+        )
+    }
+
+    /// The length a request asks for, or the default of its task definition
+    /// (else `lengths.default`), once checked against both policies.
+    fn request_length(
+        &self,
+        length: Option<usize>,
+        definition: Option<&str>,
+    ) -> Result<usize, String> {
+        let length = match definition {
+            Some(id) => {
+                let definition = self
+                    .runner
+                    .definitions()
+                    .get(id)
+                    .ok_or_else(|| format!("Unknown task definition {}", id))?;
+                let length = length.unwrap_or(definition.length);
+                definition
+                    .policy()
+                    .check(length)
+                    .map_err(|e| format!("Task definition {}: {}", id, e))?;
+                length
+            }
+            None => length.unwrap_or(self.config.current().lengths.default),
+        };
+        self.check_length(length)?;
+        Ok(length)
+    }
+
+    /// Checks a task length against the performer's policy, pointing lengths
+    /// that may only be streamed to `/task/stream`.
+    fn check_length(&self, length: usize) -> Result<(), String> {
+        let performer = self.runner.performer();
+        performer.check_length(length).map_err(|e| {
+            if length > performer.length_policy().max && length <= performer.max_stream_length() {
+                format!("{} Request it from /task/stream instead.", e)
+            } else {
+                e
+            }
+        })
+    }
+
+    /// Parses a `/task/stream` request, admits its tenant and opens it on the
+    /// task runner. Returns the response head and the lazily generated body.
+    fn open_stream<'s>(
+        &'s self,
+        body: &str,
+        authorization: Option<&str>,
+    ) -> Result<(HttpResponse, ChunkLines<'s>), HttpResponse> {
+        self.throttle()?;
+        self.refuse_generation()?;
+        let parsed: StreamBody = serde_json::from_str(body)
+            .map_err(|e| json_response(400, json!({ "error": format!("Invalid body: {}", e) })))?;
+        let settings = self.config.current();
+        let chunk_size = parsed.chunk_size.unwrap_or(settings.lengths.stream_chunk);
+        let admission = self.admit_tenant(authorization)?;
+        let tenant = admission.as_ref().map(Admission::tenant);
+        let request = StreamRequest {
+            task_id: parsed.task_id.unwrap_or_else(new_task_id),
+            length: parsed.length,
+            chunk_size,
+            tenant: tenant.map(|t| t.id.clone()),
+            domain: tenant.map(|t| t.domain.clone()),
+        };
+        let chunks = match self.runner.open_stream(request) {
+            Ok(chunks) => chunks,
+            Err(e) => {
+                if let (TaskError::Rejected(_), Some(admission)) = (&e, &admission) {
+                    admission.refund();
+                }
+                let status = match e {
+                    TaskError::ShuttingDown | TaskError::Paused => 503,
+                    _ => 400,
+                };
+                return Err(json_response(status, json!({ "error": e.to_string() })));
+            }
+        };
+        let head = Response::from_data(Vec::new())
+            .with_status_code(200)
+            .with_header(content_type("application/x-ndjson"))
+            .with_header(
+                Header::from_bytes(
+                    &b"X-Stream-Chunks"[..],
+                    chunks.chunk_count().to_string().as_bytes(),
+                )
+                .expect("numeric header is valid"),
+            );
+        Ok((
+            head,
+            ChunkLines {
+                chunks,
+                metrics: &self.metrics,
+                encoding: parsed.encoding.unwrap_or(settings.server.encoding),
+                line: Vec::new(),
+                offset: 0,
+                done: false,
+                _admission: admission,
+            },
+        ))
+    }
+
+    /// Admits the tenant, answers a repeated idempotency key (or the task
+    /// ID `client_key`) from the cache, and otherwise runs the request built
+    /// for the tenant.
+    fn fulfil(
+        &self,
+        fingerprint: [u8; 32],
+        client_key: Option<&str>,
+        authorization: Option<&str>,
+        idempotency_key: Option<&str>,
+        presentation: Presentation,
+        request: impl FnOnce(Option<&Tenant>) -> TaskRequest,
+    ) -> HttpResponse {
+        // Held until the task is done, which frees the tenant's slot.
+        let admission = match self.admit_tenant(authorization) {
+            Ok(admission) => admission,
+            Err(response) => return response,
+        };
+        let tenant = admission.as_ref().map(Admission::tenant);
+
+        // Without a header the client's own task ID is the key.
+        let mut reservation = None;
+        let key = idempotency_key.or(client_key);
+        if let (Some(cache), Some(key)) = (&self.idempotency, key) {
+            match cache.claim(tenant.map(|t| t.id.as_str()), key, &fingerprint) {
+                Ok(Claim::Replay(outcome)) => {
+                    if let Some(admission) = &admission {
+                        admission.refund();
+                    }
+                    self.metrics
+                        .inc_counter("rng_idempotent_replays_total", &[], 1);
+                    let receipt = self.receipt(&outcome, presentation, tenant);
+                    return encoded_response(&outcome, presentation, receipt).with_header(
+                        Header::from_bytes(&b"Idempotent-Replayed"[..], &b"true"[..])
+                            .expect("static header is valid"),
+                    );
+                }
+                Ok(Claim::Fresh(claimed)) => reservation = Some(claimed),
+                Err(e) => {
+                    if let Some(admission) = &admission {
+                        admission.refund();
+                    }
+                    let status = match e {
+                        IdempotencyError::InvalidKey => 400,
+                        IdempotencyError::InProgress => 409,
+                        IdempotencyError::Mismatch => 422,
+                        IdempotencyError::Storage(_) => 500,
+                    };
+                    return json_response(status, json!({ "error": e.to_string() }));
+                }
+            }
+        }
+
+        match self.runner.execute(request(tenant)) {
+            Ok(outcome) => {
+                if let Some(reservation) = reservation {
+                    if let Err(e) = reservation.complete(&outcome) {
+                        warn!(
+                            "Failed to record the idempotency key of task {}: {}",
+                            outcome.task_id, e
+                        );
+                    }
+                }
+                let receipt = self.receipt(&outcome, presentation, tenant);
+                encoded_response(&outcome, presentation, receipt)
+            }
+            Err(e) => {
+                if let (TaskError::Rejected(_), Some(admission)) = (&e, &admission) {
+                    admission.refund();
+                }
+                let status = match e {
+                    TaskError::ShuttingDown | TaskError::Paused => 503,
+                    TaskError::Rejected(_) => 400,
+                    TaskError::Failed(_) => 500,
+                    TaskError::DeadlineExceeded { .. } => 504,
+                };
+                json_response(status, json!({ "error": e.to_string() }))
+            }
+        }
+    }
+
+    /// A receipt for `outcome` when `presentation` asks for one; a value
+    /// still sealed is not served yet.
+    fn receipt(
+        &self,
+        outcome: &TaskOutcome,
+        presentation: Presentation,
+        tenant: Option<&Tenant>,
+    ) -> Option<Receipt> {
+        if !presentation.receipt || outcome.timelock.is_some() {
+            return None;
+        }
+        let served = [(unix_millis(), outcome.clone())];
+        match Receipt::issue(
+            &self.runner.attester(),
+            &served,
+            tenant.map(|t| t.id.as_str()),
+        ) {
+            Ok(receipt) => Some(receipt),
+            Err(e) => {
+                warn!(
+                    "Failed to sign a receipt for task {}: {}",
+                    outcome.task_id, e
+                );
+                None
+            }
+        }
+    }
+
+    fn export(&self, body: &str, authorization: Option<&str>) -> HttpResponse {
+        let Some(storage) = &self.storage else {
+            return json_response(404, json!({ "error": "Nothing is persisted to export" }));
+        };
+        let parsed: ExportBody = match serde_json::from_str(body) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                return json_response(400, json!({ "error": format!("Invalid body: {}", e) }))
+            }
+        };
+        let (from, to) = match (
+            parsed.from.map(time_bound).transpose(),
+            parsed.to.map(time_bound).transpose(),
+        ) {
+            (Ok(from), Ok(to)) => (from, to),
+            (Err(e), _) | (_, Err(e)) => return json_response(400, json!({ "error": e })),
+        };
+        let tenant = match self.tenants.as_ref().filter(|t| t.is_enabled()) {
+            Some(tenants) => {
+                let presented = authorization
+                    .and_then(|value| value.strip_prefix("Bearer "))
+                    .unwrap_or("");
+                match tenants.authenticate(presented) {
+                    Some(tenant) => Some(tenant),
+                    None => return json_response(401, json!({ "error": "Invalid tenant token" })),
+                }
+            }
+            // Without tenants every attestation is one namespace: the operator's.
+            None => match self.authorize(authorization) {
+                Ok(()) => None,
+                Err(response) => return response,
+            },
+        };
+        let tenant_id = tenant.as_ref().map(|t| t.id.as_str());
+        let served = match export::collect_outcomes(storage.as_ref(), tenant_id, from, to) {
+            Ok(served) => served,
+            Err(e) => return json_response(500, json!({ "error": e })),
+        };
+        let receipt = match Receipt::issue(&self.runner.attester(), &served, tenant_id) {
+            Ok(receipt) => receipt,
+            Err(e) => return json_response(500, json!({ "error": e })),
+        };
+        let attestations: Vec<TaskOutcome> =
+            served.into_iter().map(|(_, outcome)| outcome).collect();
+        json_response(
+            200,
+            json!({ "attestations": attestations, "receipt": receipt }),
+        )
+    }
+
+    fn reload(&self) -> HttpResponse {
+        match reload_config(&self.config) {
+            Ok(body) => json_response(200, body),
+            Err(e) => json_response(500, json!({ "error": e })),
+        }
+    }
+}
+
+/// Reloads `config`, logs the outcome and returns it as JSON.
+///
+/// Shared by the `/admin/reload` endpoint and the `SIGHUP` handler.
+pub fn reload_config(config: &ConfigHandle) -> Result<Value, String> {
+    match config.reload() {
+        Ok(outcome) => {
+            info!(
+                "Config reloaded from {}: applied {:?}",
+                config.path().display(),
+                outcome.applied
+            );
+            if !outcome.requires_restart.is_empty() {
+                warn!(
+                    "Config sections {:?} changed but require a restart to take effect",
+                    outcome.requires_restart
+                );
+            }
+            Ok(json!({
+                "applied": outcome.applied,
+                "requires_restart": outcome.requires_restart,
+            }))
+        }
+        Err(e) => {
+            warn!("Config reload failed, keeping running config: {}", e);
+            Err(e)
+        }
+    }
+}
+
+/// Compares tokens without leaking how many leading bytes match.
+pub(crate) fn tokens_match(presented: &str, expected: &str) -> bool {
+    let presented = Sha256::digest(presented.as_bytes());
+    let expected = Sha256::digest(expected.as_bytes());
+    presented
+        .iter()
+        .zip(expected.iter())
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+/// How a response presents an outcome; a retry may change it without
+/// conflicting with its idempotency key.
+#[derive(Debug, Clone, Copy)]
+struct Presentation {
+    encoding: Encoding,
+    /// Keep the outcome's timings; they are dropped otherwise.
+    timings: bool,
+    /// Add a receipt for the values served.
+    receipt: bool,
+}
+
+/// `outcome` with its byte fields in `encoding`.
+fn encoded_response(
+    outcome: &TaskOutcome,
+    presentation: Presentation,
+    receipt: Option<Receipt>,
+) -> HttpResponse {
+    if let Some(commitment) = &outcome.timelock {
+        return json_response(202, json!(commitment));
+    }
+    match outcome.encoded(presentation.encoding) {
+        Ok(mut outcome) => {
+            if !presentation.timings {
+                outcome.timings = None;
+            }
+            let mut body = json!(outcome);
+            if let Some(receipt) = receipt {
+                body["receipt"] = json!(receipt);
+            }
+            json_response(200, body)
+        }
+        Err(e) => json_response(500, json!({ "error": e })),
+    }
+}
+
+/// Collapses path parameters so span names stay low-cardinality.
+fn route_template(path: &str) -> &str {
+    if path.starts_with("/beacon/rounds/") && path.ends_with("/proof") {
+        "/beacon/rounds/{round}/proof"
+    } else if path.starts_with("/beacon/rounds/") {
+        "/beacon/rounds/{round}"
+    } else if path.starts_with("/timelock/") {
+        "/timelock/{task}"
+    } else if path.starts_with("/reputation/")
+        && !matches!(path, "/reputation/attestations" | "/reputation/heartbeats")
+    {
+        "/reputation/{key}"
+    } else {
+        path
+    }
+}
+
+/// A time bound of an [`ExportBody`].
+fn time_bound(value: Value) -> Result<u64, String> {
+    match value {
+        Value::Number(n) => n.as_u64().ok_or_else(|| format!("Invalid time {}", n)),
+        Value::String(s) => export::parse_time(&s),
+        other => Err(format!("Invalid time {}", other)),
+    }
+}
+
+/// Digest a retry under the same idempotency key must match: the body, less
+/// presentation fields, and the caller entropy as bytes, whatever encoding it
+/// was sent in.
+fn fingerprint(body: &impl Serialize, entropy: &[Option<Vec<u8>>]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(serde_json::to_vec(body).expect("body serializes"));
+    for bytes in entropy {
+        match bytes {
+            Some(bytes) => {
+                hasher.update([1]);
+                hasher.update((bytes.len() as u64).to_be_bytes());
+                hasher.update(bytes);
+            }
+            None => hasher.update([0]),
+        }
+    }
+    hasher.finalize().into()
+}
+
+fn new_task_id() -> String {
+    let mut id = [0u8; 16];
+    rand::rngs::OsRng.fill_bytes(&mut id);
+    hex::encode(id)
+}
+
+fn conformance_report(body: &str) -> HttpResponse {
+    let submission: Submission = match serde_json::from_str(body) {
+        Ok(submission) => submission,
+        Err(e) => return json_response(400, json!({ "error": format!("Invalid body: {}", e) })),
+    };
+    match conformance::evaluate(&submission) {
+        Ok(report) => {
+            info!(
+                "Conformance report for {}: {}/{} vector(s) agree with the reference",
+                report.implementation, report.agreed, report.vectors
+            );
+            json_response(200, json!(report))
+        }
+        Err(e) => json_response(400, json!({ "error": e })),
+    }
+}
+
+fn json_response(status: u16, body: Value) -> HttpResponse {
+    Response::from_data(body.to_string().into_bytes())
+        .with_status_code(status)
+        .with_header(content_type("application/json"))
+}
+
+fn text_response(status: u16, body: String) -> HttpResponse {
+    Response::from_data(body.into_bytes())
+        .with_status_code(status)
+        .with_header(content_type("text/plain; version=0.0.4"))
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes(&b"Content-Type"[..], value.as_bytes()).expect("static header is valid")
+}
@@ -0,0 +1,382 @@
+// src/shamir.rs
+
+//! Shamir t-of-n secret sharing of generated random values.
+//!
+//! The secret is split byte-wise over GF(2^8): each byte is the constant term
+//! of a random polynomial of degree `threshold - 1`, and share `i` holds the
+//! polynomials evaluated at `x = i`. Every share is attested on its own with
+//! kind [`SHAMIR_SHARE_KIND`] and a [`ShareInfo`] record binding it to a hash
+//! commitment on the secret, so custodians can check their share in isolation
+//! and [`reconstruct`] can tell a correct recovery from a tampered one.
+//!
+//! The commitment also hashes a random 32-byte blinding value, shared along
+//! with the secret: each share holds the secret's bytes followed by those of
+//! the blinding value. Without it a short secret could be found by hashing
+//! every candidate against the commitment every share carries.
+//!
+//! [`RngPerformer::generate_secret_shares`] never returns the secret itself;
+//! it only exists in memory long enough to be split.
+
+use ed25519_dalek::VerifyingKey;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::attester::{Attestation, AttestationPayload, RngAttester, ShareInfo};
+use crate::performer::RngPerformer;
+
+/// Payload kind recorded for Shamir shares.
+pub const SHAMIR_SHARE_KIND: &str = "shamir-share";
+
+const COMMITMENT_DOMAIN: &[u8] = b"othentic-rng/shamir/commitment/v2";
+
+/// Length of the blinding value shared after the secret's bytes.
+const BLINDING_LEN: usize = 32;
+
+impl RngPerformer {
+    /// Generates a `len`-byte secret and splits it into attested shares.
+    ///
+    /// # Arguments
+    /// * `attester` - Signs every share.
+    /// * `len` - Length of the secret in bytes.
+    /// * `threshold` - Number of shares required to reconstruct the secret.
+    /// * `total` - Number of shares to produce (evaluation points are non-zero
+    ///   `u8` values, so at most 255).
+    ///
+    /// # Returns
+    /// A `Result` containing:
+    /// - `Ok(Vec<Attestation>)` with one attested share per custodian.
+    /// - `Err(String)` if the parameters are invalid or generation fails.
+    pub fn generate_secret_shares(
+        &self,
+        attester: &RngAttester,
+        len: usize,
+        threshold: u8,
+        total: u8,
+    ) -> Result<Vec<Attestation>, String> {
+        let mut secret = self.generate_random_number(len)?;
+        let shares = split(attester, &secret, threshold, total);
+        secret.fill(0);
+        shares
+    }
+}
+
+/// Splits `secret` into `total` attested shares, any `threshold` of which
+/// reconstruct it.
+pub fn split(
+    attester: &RngAttester,
+    secret: &[u8],
+    threshold: u8,
+    total: u8,
+) -> Result<Vec<Attestation>, String> {
+    if secret.is_empty() {
+        return Err("Secret must not be empty.".to_string());
+    }
+    if threshold == 0 || threshold > total {
+        return Err(format!(
+            "Threshold must be between 1 and the share count ({}).",
+            total
+        ));
+    }
+
+    let mut blinding = [0u8; BLINDING_LEN];
+    OsRng.fill_bytes(&mut blinding);
+    let commitment = commitment(secret, &blinding, threshold, total);
+    let mut shared = [secret, &blinding].concat();
+    blinding.fill(0);
+
+    // coefficients[k][j] is the degree-k coefficient for shared byte j.
+    let mut coefficients = vec![shared.clone()];
+    shared.fill(0);
+    for _ in 1..threshold {
+        let mut row = vec![0u8; secret.len() + BLINDING_LEN];
+        OsRng.fill_bytes(&mut row);
+        coefficients.push(row);
+    }
+
+    let shares = (1..=total)
+        .map(|index| {
+            let value = (0..secret.len() + BLINDING_LEN)
+                .map(|j| {
+                    // Horner's rule, highest degree first.
+                    coefficients
+                        .iter()
+                        .rev()
+                        .fold(0u8, |acc, row| gf_mul(acc, index) ^ row[j])
+                })
+                .collect();
+            let info = ShareInfo {
+                index,
+                threshold,
+                total,
+                commitment,
+            };
+            attester.attest_payload(
+                AttestationPayload::new(value)
+                    .with_kind(SHAMIR_SHARE_KIND)
+                    .with_share(info),
+            )
+        })
+        .collect();
+
+    for row in coefficients.iter_mut() {
+        row.fill(0);
+    }
+    shares
+}
+
+/// Verifies a single share and returns its share metadata.
+pub fn verify_share(
+    public_key: &VerifyingKey,
+    attestation: &Attestation,
+) -> Result<ShareInfo, String> {
+    let payload = &attestation.payload;
+    if payload.kind.as_deref() != Some(SHAMIR_SHARE_KIND) {
+        return Err("Attestation is not a Shamir share".to_string());
+    }
+    let info = payload
+        .share
+        .ok_or_else(|| "Shamir share is missing its share record".to_string())?;
+    if info.index == 0
+        || info.index > info.total
+        || info.threshold == 0
+        || info.threshold > info.total
+    {
+        return Err(format!(
+            "Share record {}/{} (threshold {}) is out of range",
+            info.index, info.total, info.threshold
+        ));
+    }
+    if payload.random_number.len() <= BLINDING_LEN {
+        return Err(format!(
+            "Shamir share must be longer than its {}-byte blinding value",
+            BLINDING_LEN
+        ));
+    }
+    RngAttester::verify(public_key, attestation)?;
+    Ok(info)
+}
+
+/// Verifies `shares` and reconstructs the secret they were split from.
+///
+/// All shares must be signed by `public_key`, belong to the same set and have
+/// distinct indices. At least `threshold` of them are required; extra shares
+/// are ignored. The recovered secret is checked against the set's commitment.
+pub fn reconstruct(public_key: &VerifyingKey, shares: &[Attestation]) -> Result<Vec<u8>, String> {
+    let first = shares
+        .first()
+        .ok_or_else(|| "No shares supplied".to_string())?;
+    let set = verify_share(public_key, first)?;
+    let len = first.payload.random_number.len();
+
+    let mut points: Vec<(u8, &[u8])> = Vec::with_capacity(shares.len());
+    for share in shares {
+        let info = verify_share(public_key, share)?;
+        if info.commitment != set.commitment
+            || info.threshold != set.threshold
+            || info.total != set.total
+            || share.payload.random_number.len() != len
+        {
+            return Err(format!(
+                "Share {} does not belong to the same set",
+                info.index
+            ));
+        }
+        if points.iter().any(|(x, _)| *x == info.index) {
+            return Err(format!("Share {} supplied more than once", info.index));
+        }
+        points.push((info.index, &share.payload.random_number));
+    }
+    if points.len() < set.threshold as usize {
+        return Err(format!(
+            "{} share(s) supplied, {} required",
+            points.len(),
+            set.threshold
+        ));
+    }
+    points.truncate(set.threshold as usize);
+
+    // Lagrange interpolation at x = 0; subtraction in GF(2^8) is XOR.
+    let mut secret = vec![0u8; len];
+    for (i, (xi, yi)) in points.iter().enumerate() {
+        let mut basis = 1u8;
+        for (k, (xk, _)) in points.iter().enumerate() {
+            if k != i {
+                basis = gf_mul(basis, gf_mul(*xk, gf_inv(xk ^ xi)));
+            }
+        }
+        for (byte, y) in secret.iter_mut().zip(yi.iter()) {
+            *byte ^= gf_mul(basis, *y);
+        }
+    }
+
+    let mut blinding = secret.split_off(len - BLINDING_LEN);
+    let matches = commitment(&secret, &blinding, set.threshold, set.total) == set.commitment;
+    blinding.fill(0);
+    if !matches {
+        secret.fill(0);
+        return Err("Reconstructed secret does not match the share commitment".to_string());
+    }
+    Ok(secret)
+}
+
+fn commitment(secret: &[u8], blinding: &[u8], threshold: u8, total: u8) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(COMMITMENT_DOMAIN);
+    hasher.update([threshold, total]);
+    hasher.update(blinding);
+    hasher.update((secret.len() as u64).to_be_bytes());
+    hasher.update(secret);
+    hasher.finalize().into()
+}
+
+/// Multiplication in GF(2^8) modulo the AES polynomial `x^8 + x^4 + x^3 + x + 1`.
+///
+/// Runs a fixed eight rounds regardless of the operands.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// Multiplicative inverse in GF(2^8), computed as `a^254`.
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1u8;
+    let mut base = a;
+    let mut exp = 254u8;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::Encoding;
+    use crate::tasks::TaskOutcome;
+
+    fn shares(secret: &[u8], threshold: u8, total: u8) -> (VerifyingKey, Vec<Attestation>) {
+        let attester = RngAttester::new().unwrap();
+        let shares = split(&attester, secret, threshold, total).unwrap();
+        (*attester.get_public_key(), shares)
+    }
+
+    #[test]
+    fn field_arithmetic() {
+        // FIPS 197, section 4.2.
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        assert_eq!(gf_mul(0x57, 0x13), 0xfe);
+        assert_eq!(gf_inv(0x53), 0xca);
+        for a in 1..=u8::MAX {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+            assert_eq!(gf_mul(a, 1), a);
+            assert_eq!(gf_mul(a, 0), 0);
+        }
+    }
+
+    #[test]
+    fn any_threshold_of_shares_reconstructs() {
+        let secret = b"a secret of thirty-two bytes....".to_vec();
+        let (key, shares) = shares(&secret, 3, 5);
+        assert_eq!(shares.len(), 5);
+        for share in &shares {
+            assert_ne!(share.payload.random_number, secret);
+        }
+        for picked in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let subset: Vec<_> = picked.iter().map(|&i| shares[i].clone()).collect();
+            assert_eq!(reconstruct(&key, &subset).unwrap(), secret);
+        }
+        assert_eq!(reconstruct(&key, &shares).unwrap(), secret);
+
+        // With a threshold of one, the share is the secret and its blinding.
+        let (key, single) = self::shares(&secret, 1, 1);
+        assert_eq!(single[0].payload.random_number[..32], secret[..]);
+        assert_eq!(single[0].payload.random_number.len(), 32 + BLINDING_LEN);
+        assert_eq!(reconstruct(&key, &single).unwrap(), secret);
+    }
+
+    #[test]
+    fn commitment_is_blinded() {
+        // A one-byte secret is not found by hashing all 256 candidates.
+        let (_, shares) = shares(&[42], 2, 3);
+        let committed = shares[0].payload.share.unwrap().commitment;
+        for candidate in 0..=u8::MAX {
+            let mut hasher = Sha256::new();
+            hasher.update(b"othentic-rng/shamir/commitment/v1");
+            hasher.update([2, 3]);
+            hasher.update(1u64.to_be_bytes());
+            hasher.update([candidate]);
+            assert_ne!(<[u8; 32]>::from(hasher.finalize()), committed);
+            assert_ne!(
+                commitment(&[candidate], &[0; BLINDING_LEN], 2, 3),
+                committed
+            );
+        }
+        let (_, again) = self::shares(&[42], 2, 3);
+        assert_ne!(again[0].payload.share.unwrap().commitment, committed);
+    }
+
+    #[test]
+    fn shares_round_trip_through_task_outcomes() {
+        let attester = RngAttester::new().unwrap();
+        let shares = split(&attester, b"round trip", 2, 3).unwrap();
+        let restored: Vec<Attestation> = shares
+            .iter()
+            .map(|share| {
+                let outcome = TaskOutcome::from_attestation("t", share, &attester);
+                let json = serde_json::to_string(&outcome).unwrap();
+                let outcome: TaskOutcome = serde_json::from_str(&json).unwrap();
+                outcome
+                    .encoded(Encoding::Base64)
+                    .unwrap()
+                    .to_attestation()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(restored[1].payload.share, shares[1].payload.share);
+        verify_share(attester.get_public_key(), &restored[1]).unwrap();
+        assert_eq!(
+            reconstruct(attester.get_public_key(), &restored[1..]).unwrap(),
+            b"round trip"
+        );
+    }
+
+    #[test]
+    fn bad_share_sets_are_refused() {
+        let (key, shares) = shares(&[7; 16], 3, 5);
+        assert!(reconstruct(&key, &shares[..2]).is_err());
+        assert!(reconstruct(&key, &[]).is_err());
+        let repeated = [shares[0].clone(), shares[0].clone(), shares[1].clone()];
+        assert!(reconstruct(&key, &repeated).is_err());
+
+        let (_, other) = self::shares(&[7; 16], 3, 5);
+        let mixed = [shares[0].clone(), shares[1].clone(), other[2].clone()];
+        assert!(reconstruct(&key, &mixed).is_err());
+
+        let (other_key, _) = self::shares(&[1], 1, 1);
+        assert!(reconstruct(&other_key, &shares[..3]).is_err());
+        assert!(verify_share(&other_key, &shares[0]).is_err());
+
+        let mut tampered = shares[..3].to_vec();
+        tampered[1].payload.random_number[0] ^= 1;
+        assert!(reconstruct(&key, &tampered).is_err());
+    }
+
+    #[test]
+    fn parameters_are_checked() {
+        let attester = RngAttester::new().unwrap();
+        assert!(split(&attester, &[], 1, 1).is_err());
+        assert!(split(&attester, &[1], 0, 3).is_err());
+        assert!(split(&attester, &[1], 4, 3).is_err());
+    }
+}
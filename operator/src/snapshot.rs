@@ -0,0 +1,502 @@
+// src/snapshot.rs
+
+//! Disaster-recovery snapshots of operator state.
+//!
+//! `operator snapshot` writes everything an operator needs to carry on from
+//! another host into one gzipped JSON archive: every stored collection (the
+//! attester's nonce counter and used salts, unfinished and sealed tasks, the
+//! beacon round chain, idempotency keys, archive and revocation state), and
+//! the key files named in the config (epoch master key and ratchet state,
+//! beacon PVSS key). The key files and the time-locked tasks, whose records
+//! hold the key and outcome of every value not yet released, are encrypted;
+//! the other collections are not. `operator restore` checks the archive and
+//! writes it into an empty data directory; the operator then resumes its
+//! pending fulfillments on start, and its nonce store keeps refusing every
+//! salt signed before the snapshot.
+//!
+//! Keys are derived from a passphrase (`RNG_SNAPSHOT_PASSPHRASE` or a
+//! `--passphrase-file`) with PBKDF2-HMAC-SHA256. The secrets are encrypted
+//! with an HMAC-SHA256 keystream, and an HMAC over the whole archive, secrets
+//! and [`Manifest`] included, is checked before anything is restored, as are
+//! the per-collection digests of the manifest once the data is written back.
+//!
+//! A snapshot holds the epoch ratchet state of the epoch it was taken in, so
+//! whoever obtains it and the passphrase can derive that epoch's key and
+//! every later one: a snapshot gives up forward secrecy for the epochs after
+//! it, and should be destroyed once it is no longer needed. A restore refuses
+//! a ratchet state older than the latest epoch certificate restored with it.
+//!
+//! The snapshot is only consistent if the operator is stopped while it is
+//! taken, and it must not be started again on the old host afterwards: two
+//! operators continuing from the same counter would reuse nonces.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::attester::{NONCE_STATE, USED_SALTS};
+use crate::beacon::BEACON_ROUNDS;
+use crate::config::Config;
+use crate::epochs::{self, EPOCH_CERTIFICATES};
+use crate::storage::Storage;
+use crate::tasks::{unix_millis, PENDING_TASKS};
+use crate::timelock::TIMELOCKS;
+
+/// Format tag of the archive.
+pub const FORMAT: &str = "othentic-rng-snapshot/v1";
+/// Environment variable the passphrase is read from.
+pub const PASSPHRASE_ENV: &str = "RNG_SNAPSHOT_PASSPHRASE";
+
+const PBKDF2_ITERATIONS: u32 = 210_000;
+/// Most iterations a restore runs, so an archive cannot stall it.
+const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// What a snapshot holds, for a look before restoring it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    /// Hex SHA-256 of each collection, as written by [`collection_digest`].
+    pub digests: BTreeMap<String, String>,
+    /// Next attestation counter value.
+    pub next_counter: u64,
+    pub used_salts: usize,
+    pub pending_tasks: usize,
+    pub sealed_tasks: usize,
+    /// Latest beacon round and its link hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beacon_head: Option<(u64, String)>,
+    /// Key files included, by role.
+    pub keys: Vec<String>,
+}
+
+/// The [`Secrets`], encrypted under the passphrase.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SealedKeys {
+    salt: String,
+    iterations: u32,
+    ciphertext: String,
+}
+
+/// What [`SealedKeys`] decrypts to.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Secrets {
+    /// Key file contents, by role.
+    files: BTreeMap<String, String>,
+    /// The time-locked task collections.
+    collections: BTreeMap<String, BTreeMap<String, Value>>,
+}
+
+/// The archive as written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Snapshot {
+    format: String,
+    created_at: u64,
+    manifest: Manifest,
+    collections: BTreeMap<String, BTreeMap<String, Value>>,
+    keys: SealedKeys,
+    /// Hex HMAC-SHA256 over the archive with this field empty.
+    mac: String,
+}
+
+/// Writes a snapshot of `storage` and the key files of `config` to `out`.
+pub fn create(
+    storage: &dyn Storage,
+    config: &Config,
+    passphrase: &str,
+    out: impl Write,
+) -> Result<Manifest, String> {
+    let mut collections = BTreeMap::new();
+    let mut secrets = Secrets::default();
+    for name in storage.collections()? {
+        let documents: BTreeMap<String, Value> = storage.scan(&name)?.into_iter().collect();
+        if is_secret(&name) {
+            secrets.collections.insert(name, documents);
+        } else {
+            collections.insert(name, documents);
+        }
+    }
+
+    for (role, path) in key_files(config) {
+        match fs::read_to_string(path) {
+            Ok(contents) => {
+                secrets.files.insert(role.to_string(), contents);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to read {} key {}: {}", role, path, e)),
+        }
+    }
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let (encryption, authentication) = derive_keys(passphrase, &salt, PBKDF2_ITERATIONS);
+    let plaintext = serde_json::to_vec(&secrets).map_err(|e| e.to_string())?;
+    let manifest = Manifest {
+        digests: collections
+            .iter()
+            .chain(&secrets.collections)
+            .map(|(name, documents)| (name.clone(), hex::encode(collection_digest(documents))))
+            .collect(),
+        next_counter: collections
+            .get(NONCE_STATE)
+            .and_then(|c| c.get("counter"))
+            .and_then(|state| state["next"].as_u64())
+            .unwrap_or(0),
+        used_salts: collections.get(USED_SALTS).map_or(0, BTreeMap::len),
+        pending_tasks: collections.get(PENDING_TASKS).map_or(0, BTreeMap::len),
+        sealed_tasks: secrets.collections.values().map(BTreeMap::len).sum(),
+        beacon_head: collections
+            .get(BEACON_ROUNDS)
+            .and_then(|rounds| rounds.values().next_back())
+            .and_then(|record| {
+                Some((
+                    record["round"].as_u64()?,
+                    record["hash"].as_str()?.to_string(),
+                ))
+            }),
+        keys: secrets.files.keys().cloned().collect(),
+    };
+    let mut snapshot = Snapshot {
+        format: FORMAT.to_string(),
+        created_at: unix_millis(),
+        manifest: manifest.clone(),
+        collections,
+        keys: SealedKeys {
+            salt: hex::encode(salt),
+            iterations: PBKDF2_ITERATIONS,
+            ciphertext: hex::encode(keystream(&encryption, &plaintext)),
+        },
+        mac: String::new(),
+    };
+    snapshot.mac = hex::encode(mac(&authentication, &snapshot)?.finalize().into_bytes());
+
+    let mut encoder = GzEncoder::new(out, Compression::default());
+    serde_json::to_writer(&mut encoder, &snapshot).map_err(|e| e.to_string())?;
+    encoder
+        .finish()
+        .and_then(|mut out| out.flush())
+        .map_err(|e| format!("Failed to write snapshot: {}", e))?;
+    Ok(manifest)
+}
+
+/// Checks the snapshot in `input` and restores it into `storage`, which must
+/// be empty, and the key files of `config`, which must not exist yet.
+pub fn restore(
+    storage: &dyn Storage,
+    config: &Config,
+    passphrase: &str,
+    input: impl Read,
+) -> Result<Manifest, String> {
+    let mut raw = Vec::new();
+    GzDecoder::new(input)
+        .read_to_end(&mut raw)
+        .map_err(|e| format!("Failed to read snapshot: {}", e))?;
+    let mut snapshot: Snapshot =
+        serde_json::from_slice(&raw).map_err(|e| format!("Invalid snapshot: {}", e))?;
+    if snapshot.format != FORMAT {
+        return Err(format!("Unsupported snapshot format {}", snapshot.format));
+    }
+    let iterations = snapshot.keys.iterations;
+    if !(PBKDF2_ITERATIONS..=MAX_PBKDF2_ITERATIONS).contains(&iterations) {
+        return Err(format!(
+            "Snapshot key derivation runs {} iterations, not {} to {}",
+            iterations, PBKDF2_ITERATIONS, MAX_PBKDF2_ITERATIONS
+        ));
+    }
+    let salt = hex::decode(&snapshot.keys.salt).map_err(|e| format!("Invalid salt: {}", e))?;
+    let (encryption, authentication) = derive_keys(passphrase, &salt, iterations);
+    let expected = hex::decode(std::mem::take(&mut snapshot.mac))
+        .map_err(|e| format!("Invalid MAC: {}", e))?;
+    mac(&authentication, &snapshot)?
+        .verify_slice(&expected)
+        .map_err(|_| "Snapshot does not authenticate: wrong passphrase or corrupted".to_string())?;
+    let ciphertext = hex::decode(&snapshot.keys.ciphertext)
+        .map_err(|e| format!("Invalid key ciphertext: {}", e))?;
+    let secrets: Secrets = serde_json::from_slice(&keystream(&encryption, &ciphertext))
+        .map_err(|e| format!("Snapshot secrets do not decrypt: {}", e))?;
+    let all = || snapshot.collections.iter().chain(&secrets.collections);
+    for (name, documents) in all() {
+        if snapshot.manifest.digests.get(name) != Some(&hex::encode(collection_digest(documents))) {
+            return Err(format!("Collection {} does not match the manifest", name));
+        }
+    }
+    if snapshot.manifest.digests.len() != all().count() {
+        return Err("Snapshot is missing collections listed in its manifest".to_string());
+    }
+    if let Some(state) = secrets.files.get("epoch_state") {
+        check_ratchet(state, snapshot.collections.get(EPOCH_CERTIFICATES))?;
+    }
+    let files = &secrets.files;
+
+    // Restoring over live state could roll its nonce counter back.
+    if let Some(name) = storage.collections()?.first() {
+        return Err(format!(
+            "The data directory is not empty (collection {}); restore into a fresh one",
+            name
+        ));
+    }
+    let targets = key_files(config);
+    for role in files.keys() {
+        let (_, path) = targets
+            .iter()
+            .find(|(r, _)| r == role)
+            .ok_or_else(|| format!("Snapshot holds an unknown {} key", role))?;
+        if Path::new(path).exists() {
+            return Err(format!("{} key {} already exists", role, path));
+        }
+    }
+
+    for (name, documents) in all() {
+        for (key, value) in documents {
+            storage.put(name, key, value.clone())?;
+        }
+    }
+    storage.flush()?;
+    for (role, contents) in files {
+        let (_, path) = targets
+            .iter()
+            .find(|(r, _)| r == role)
+            .expect("checked above");
+        epochs::write_private(Path::new(path), contents.as_bytes())
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+
+    for (name, digest) in &snapshot.manifest.digests {
+        let documents: BTreeMap<String, Value> = storage.scan(name)?.into_iter().collect();
+        if hex::encode(collection_digest(&documents)) != *digest {
+            return Err(format!("Collection {} did not restore intact", name));
+        }
+    }
+    Ok(snapshot.manifest)
+}
+
+/// Reads the passphrase from `file`, or from [`PASSPHRASE_ENV`].
+pub fn passphrase(file: Option<&str>) -> Result<String, String> {
+    let passphrase = match file {
+        Some(file) => fs::read_to_string(file)
+            .map_err(|e| format!("Failed to read {}: {}", file, e))?
+            .trim_end_matches(['\r', '\n'])
+            .to_string(),
+        None => std::env::var(PASSPHRASE_ENV).map_err(|_| {
+            format!(
+                "Set {} or pass --passphrase-file to encrypt the keys",
+                PASSPHRASE_ENV
+            )
+        })?,
+    };
+    if passphrase.is_empty() {
+        return Err("The snapshot passphrase must not be empty".to_string());
+    }
+    Ok(passphrase)
+}
+
+/// SHA-256 over the length-prefixed keys and JSON values of a collection.
+pub fn collection_digest(documents: &BTreeMap<String, Value>) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for (key, value) in documents {
+        let value = value.to_string();
+        for field in [key.as_bytes(), value.as_bytes()] {
+            hasher.update((field.len() as u64).to_be_bytes());
+            hasher.update(field);
+        }
+    }
+    hasher.finalize().into()
+}
+
+/// Whether collection `name` is encrypted: time-locked tasks, of any tenant.
+fn is_secret(name: &str) -> bool {
+    name == TIMELOCKS
+        || name
+            .strip_prefix(TIMELOCKS)
+            .is_some_and(|rest| rest.starts_with("__"))
+}
+
+/// Refuses a ratchet `state` behind the latest of `certificates`: resuming
+/// from it would re-derive keys of epochs that have ended and been erased.
+fn check_ratchet(
+    state: &str,
+    certificates: Option<&BTreeMap<String, Value>>,
+) -> Result<(), String> {
+    let epoch = serde_json::from_str::<Value>(state)
+        .ok()
+        .and_then(|state| state["epoch"].as_u64())
+        .ok_or_else(|| "Snapshot holds an invalid epoch state".to_string())?;
+    let latest = certificates
+        .into_iter()
+        .flat_map(BTreeMap::values)
+        .filter_map(|certificate| certificate["epoch"].as_u64())
+        .max();
+    match latest {
+        Some(latest) if latest > epoch => Err(format!(
+            "Snapshot epoch state is at epoch {}, behind its certificate for epoch {}",
+            epoch, latest
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Key files of `config`, by role.
+fn key_files(config: &Config) -> [(&'static str, &str); 3] {
+    [
+        ("epoch_master", config.epochs.master_key_file.as_str()),
+        ("epoch_state", config.epochs.state_file.as_str()),
+        ("beacon", config.beacon.key_file.as_str()),
+    ]
+}
+
+/// PBKDF2-HMAC-SHA256; returns the encryption and MAC keys.
+fn derive_keys(passphrase: &str, salt: &[u8], iterations: u32) -> ([u8; 32], [u8; 32]) {
+    let prf = HmacSha256::new_from_slice(passphrase.as_bytes()).expect("HMAC accepts any key");
+    let mut blocks = [[0u8; 32]; 2];
+    for (index, block) in blocks.iter_mut().enumerate() {
+        let mut u: [u8; 32] = prf
+            .clone()
+            .chain_update(salt)
+            .chain_update((index as u32 + 1).to_be_bytes())
+            .finalize()
+            .into_bytes()
+            .into();
+        *block = u;
+        for _ in 1..iterations {
+            u = prf.clone().chain_update(u).finalize().into_bytes().into();
+            for (b, x) in block.iter_mut().zip(u) {
+                *b ^= x;
+            }
+        }
+    }
+    (blocks[0], blocks[1])
+}
+
+/// XORs `data` with HMAC-SHA256 of a counter under `key`.
+fn keystream(key: &[u8; 32], data: &[u8]) -> Vec<u8> {
+    let prf = HmacSha256::new_from_slice(key).expect("HMAC accepts any key");
+    let mut output = Vec::with_capacity(data.len());
+    for (counter, chunk) in data.chunks(32).enumerate() {
+        let block = prf
+            .clone()
+            .chain_update((counter as u64).to_be_bytes())
+            .finalize()
+            .into_bytes();
+        output.extend(chunk.iter().zip(block.iter()).map(|(b, k)| b ^ k));
+    }
+    output
+}
+
+/// HMAC of `snapshot` as serialized, before finalizing.
+fn mac(key: &[u8; 32], snapshot: &Snapshot) -> Result<HmacSha256, String> {
+    let body = serde_json::to_vec(snapshot).map_err(|e| e.to_string())?;
+    Ok(HmacSha256::new_from_slice(key)
+        .expect("HMAC accepts any key")
+        .chain_update(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use serde_json::json;
+
+    #[cfg(unix)]
+    #[test]
+    fn restored_key_files_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("snapshot-modes-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut config = Config::from_file(Path::new("config/config.yaml")).unwrap();
+        config.epochs.master_key_file = dir.join("master.key").display().to_string();
+        config.epochs.state_file = dir.join("epoch.state").display().to_string();
+        config.beacon.key_file = dir.join("beacon.key").display().to_string();
+        for file in ["master.key", "beacon.key"] {
+            fs::write(dir.join(file), "00\n").unwrap();
+        }
+
+        let mut snapshot = Vec::new();
+        create(&MemoryStorage::new(), &config, "passphrase", &mut snapshot).unwrap();
+        for file in ["master.key", "beacon.key"] {
+            fs::remove_file(dir.join(file)).unwrap();
+        }
+        restore(
+            &MemoryStorage::new(),
+            &config,
+            "passphrase",
+            snapshot.as_slice(),
+        )
+        .unwrap();
+        for file in ["master.key", "beacon.key"] {
+            let mode = fs::metadata(dir.join(file)).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600, "{}", file);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pbkdf2_rfc7914_vectors() {
+        let split = |(encryption, authentication): ([u8; 32], [u8; 32])| {
+            hex::encode([encryption, authentication].concat())
+        };
+        assert_eq!(
+            split(derive_keys("passwd", b"salt", 1)),
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc\
+             49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"
+        );
+        assert_eq!(
+            split(derive_keys("Password", b"NaCl", 80_000)),
+            "4ddcd8f60b98be21830cee5ef22701f9641a4418d04c0414aeff08876b34ab56\
+             a1d425a1225833549adb841b51c9b3176a272bdebba1d078478f62b397f33c8d"
+        );
+    }
+
+    #[test]
+    fn only_time_locked_collections_are_secret() {
+        assert!(is_secret(TIMELOCKS));
+        assert!(is_secret(&format!("{}__acme", TIMELOCKS)));
+        assert!(!is_secret(&format!("{}_archive", TIMELOCKS)));
+        assert!(!is_secret(PENDING_TASKS));
+        assert!(!is_secret(NONCE_STATE));
+    }
+
+    #[test]
+    fn stale_ratchet_state_is_refused() {
+        let certificates: BTreeMap<String, Value> = (0..=3u64)
+            .map(|epoch| (format!("{:012}", epoch), json!({ "epoch": epoch })))
+            .collect();
+        let state = |epoch: u64| json!({ "epoch": epoch, "chainKey": "00" }).to_string();
+        check_ratchet(&state(3), Some(&certificates)).unwrap();
+        check_ratchet(&state(4), Some(&certificates)).unwrap();
+        check_ratchet(&state(0), None).unwrap();
+        assert!(check_ratchet(&state(2), Some(&certificates)).is_err());
+        assert!(check_ratchet("{}", None).is_err());
+    }
+
+    #[test]
+    fn secrets_round_trip_through_the_keystream() {
+        let (encryption, _) = derive_keys("passphrase", b"salt", 2);
+        let mut secrets = Secrets::default();
+        secrets
+            .files
+            .insert("beacon".to_string(), "key".to_string());
+        secrets.collections.insert(
+            TIMELOCKS.to_string(),
+            BTreeMap::from([("task".to_string(), json!({ "key": "00" }))]),
+        );
+        let plaintext = serde_json::to_vec(&secrets).unwrap();
+        let ciphertext = keystream(&encryption, &plaintext);
+        assert_ne!(ciphertext, plaintext);
+        assert_eq!(keystream(&encryption, &ciphertext), plaintext);
+    }
+}
@@ -0,0 +1,164 @@
+// src/status.rs
+
+//! Exit codes and machine-readable status for the `operator` and
+//! `rng-verify` binaries.
+//!
+//! Every failure is classified so that scripts can react to the exit code
+//! instead of parsing messages:
+//!
+//! | code | class          | meaning                                           |
+//! |------|----------------|---------------------------------------------------|
+//! | 0    |                | success                                           |
+//! | 1    | `verification` | an attestation failed verification                |
+//! | 2    | `usage`        | invalid arguments or input                        |
+//! | 3    | `config`       | the config file is missing or invalid             |
+//! | 4    | `key`          | a signing key could not be loaded or used         |
+//! | 5    | `entropy`      | no randomness could be generated                  |
+//! | 6    | `network`      | a listener, peer, chain node or bucket failed     |
+//! | 7    | `storage`      | local storage or an output file failed            |
+//! | 8    | `drain`        | shutdown left tasks persisted for the next start  |
+//! | 70   | `internal`     | anything else                                     |
+//!
+//! With `--output json` a binary ends by printing one status object, e.g.
+//! `{"status":"error","class":"network","exitCode":6,"message":"..."}` or
+//! `{"status":"ok","exitCode":0,...}` with the command's summary fields.
+
+use std::fmt;
+
+use serde_json::{json, Map, Value};
+
+/// What kind of failure ended a command; see the module docs for codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureClass {
+    Verification,
+    Usage,
+    Config,
+    Key,
+    Entropy,
+    Network,
+    Storage,
+    Drain,
+    Internal,
+}
+
+impl FailureClass {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            FailureClass::Verification => 1,
+            FailureClass::Usage => 2,
+            FailureClass::Config => 3,
+            FailureClass::Key => 4,
+            FailureClass::Entropy => 5,
+            FailureClass::Network => 6,
+            FailureClass::Storage => 7,
+            FailureClass::Drain => 8,
+            FailureClass::Internal => 70,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FailureClass::Verification => "verification",
+            FailureClass::Usage => "usage",
+            FailureClass::Config => "config",
+            FailureClass::Key => "key",
+            FailureClass::Entropy => "entropy",
+            FailureClass::Network => "network",
+            FailureClass::Storage => "storage",
+            FailureClass::Drain => "drain",
+            FailureClass::Internal => "internal",
+        }
+    }
+}
+
+/// A classified error.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub class: FailureClass,
+    pub message: String,
+}
+
+impl Failure {
+    pub fn new(class: FailureClass, message: impl Into<String>) -> Self {
+        Failure {
+            class,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} error: {}", self.class.as_str(), self.message)
+    }
+}
+
+/// Errors nobody classified are internal.
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Failure::new(FailureClass::Internal, message)
+    }
+}
+
+/// Classifies the `String` errors used throughout the crate.
+pub trait Classify<T> {
+    fn classify(self, class: FailureClass) -> Result<T, Failure>;
+}
+
+impl<T> Classify<T> for Result<T, String> {
+    fn classify(self, class: FailureClass) -> Result<T, Failure> {
+        self.map_err(|message| Failure::new(class, message))
+    }
+}
+
+/// How a binary reports its outcome.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    #[default]
+    Text,
+    Json,
+}
+
+impl OutputMode {
+    /// Parses the value of `--output`.
+    pub fn parse(value: &str) -> Result<Self, Failure> {
+        match value {
+            "text" => Ok(OutputMode::Text),
+            "json" => Ok(OutputMode::Json),
+            other => Err(Failure::new(
+                FailureClass::Usage,
+                format!("Unknown --output '{}': expected text or json", other),
+            )),
+        }
+    }
+}
+
+/// The status object for `result`; `summary` fields are merged into a
+/// successful one.
+pub fn status_json(result: &Result<Value, Failure>) -> Value {
+    match result {
+        Ok(summary) => {
+            let mut status = Map::new();
+            status.insert("status".to_string(), json!("ok"));
+            status.insert("exitCode".to_string(), json!(0));
+            if let Value::Object(fields) = summary {
+                status.extend(fields.clone());
+            }
+            Value::Object(status)
+        }
+        Err(failure) => json!({
+            "status": "error",
+            "class": failure.class.as_str(),
+            "exitCode": failure.class.exit_code(),
+            "message": failure.message,
+        }),
+    }
+}
+
+/// Exit code for `result`.
+pub fn exit_code(result: &Result<Value, Failure>) -> i32 {
+    match result {
+        Ok(_) => 0,
+        Err(failure) => failure.class.exit_code(),
+    }
+}
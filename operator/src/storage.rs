@@ -0,0 +1,344 @@
+// src/storage.rs
+
+//! Storage backends for operator state (task lifecycle, pending work, attestations).
+//!
+//! Data is organised as named collections of JSON documents addressed by a
+//! string key. [`MemoryStorage`] keeps everything in process memory;
+//! [`FileStorage`] additionally appends every change to a per-collection JSONL
+//! log under a data directory and replays those logs on open.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Common interface implemented by all storage backends.
+pub trait Storage: Send + Sync {
+    /// Inserts or replaces the document stored under `key`.
+    fn put(&self, collection: &str, key: &str, value: Value) -> Result<(), String>;
+
+    /// Returns the document stored under `key`, if any.
+    fn get(&self, collection: &str, key: &str) -> Result<Option<Value>, String>;
+
+    /// Removes the document stored under `key`; missing keys are not an error.
+    fn delete(&self, collection: &str, key: &str) -> Result<(), String>;
+
+    /// Returns every `(key, document)` pair in `collection`, ordered by key.
+    fn scan(&self, collection: &str) -> Result<Vec<(String, Value)>, String>;
+
+    /// Names of the collections holding any document, sorted.
+    fn collections(&self) -> Result<Vec<String>, String>;
+
+    /// Makes all previous writes durable.
+    fn flush(&self) -> Result<(), String>;
+
+    /// Reclaims space held by overwritten and deleted documents of
+    /// `collection`, where the backend keeps any.
+    fn compact(&self, _collection: &str) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+type Collections = HashMap<String, BTreeMap<String, Value>>;
+
+fn live_collections(collections: &Collections) -> Vec<String> {
+    let mut names: Vec<String> = collections
+        .iter()
+        .filter(|(_, documents)| !documents.is_empty())
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Volatile storage, used when no data directory is configured.
+#[derive(Default)]
+pub struct MemoryStorage {
+    collections: Mutex<Collections>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn put(&self, collection: &str, key: &str, value: Value) -> Result<(), String> {
+        let mut collections = self.collections.lock().expect("storage lock poisoned");
+        collections
+            .entry(collection.to_string())
+            .or_default()
+            .insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn get(&self, collection: &str, key: &str) -> Result<Option<Value>, String> {
+        let collections = self.collections.lock().expect("storage lock poisoned");
+        Ok(collections
+            .get(collection)
+            .and_then(|c| c.get(key))
+            .cloned())
+    }
+
+    fn delete(&self, collection: &str, key: &str) -> Result<(), String> {
+        let mut collections = self.collections.lock().expect("storage lock poisoned");
+        if let Some(c) = collections.get_mut(collection) {
+            c.remove(key);
+        }
+        Ok(())
+    }
+
+    fn scan(&self, collection: &str) -> Result<Vec<(String, Value)>, String> {
+        let collections = self.collections.lock().expect("storage lock poisoned");
+        Ok(collections
+            .get(collection)
+            .map(|c| c.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+            .unwrap_or_default())
+    }
+
+    fn collections(&self) -> Result<Vec<String>, String> {
+        let collections = self.collections.lock().expect("storage lock poisoned");
+        Ok(live_collections(&collections))
+    }
+
+    fn flush(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// One line of a collection log.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum LogEntry {
+    Put { key: String, value: Value },
+    Delete { key: String },
+}
+
+struct FileState {
+    collections: Collections,
+    writers: HashMap<String, BufWriter<File>>,
+}
+
+/// Durable storage backed by append-only JSONL logs, one file per collection.
+pub struct FileStorage {
+    dir: PathBuf,
+    state: Mutex<FileState>,
+}
+
+impl FileStorage {
+    /// Opens (creating if needed) the data directory and replays existing logs.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, String> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create data dir {}: {}", dir.display(), e))?;
+
+        let mut collections = Collections::new();
+        let entries = fs::read_dir(&dir)
+            .map_err(|e| format!("Failed to read data dir {}: {}", dir.display(), e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let name = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            collections.insert(name, replay(&path)?);
+        }
+
+        Ok(FileStorage {
+            dir,
+            state: Mutex::new(FileState {
+                collections,
+                writers: HashMap::new(),
+            }),
+        })
+    }
+
+    fn append(
+        &self,
+        state: &mut FileState,
+        collection: &str,
+        entry: &LogEntry,
+    ) -> Result<(), String> {
+        if !state.writers.contains_key(collection) {
+            let path = self.collection_path(collection)?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+            state
+                .writers
+                .insert(collection.to_string(), BufWriter::new(file));
+        }
+        let writer = state
+            .writers
+            .get_mut(collection)
+            .expect("writer inserted above");
+        let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+        // Hand each entry to the OS right away so a crash of the process alone
+        // loses nothing; `flush()` additionally syncs to disk.
+        writeln!(writer, "{}", line)
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("Failed to write collection '{}': {}", collection, e))
+    }
+
+    fn collection_path(&self, collection: &str) -> Result<PathBuf, String> {
+        let valid = !collection.is_empty()
+            && collection
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(format!("Invalid collection name '{}'", collection));
+        }
+        Ok(self.dir.join(format!("{}.jsonl", collection)))
+    }
+}
+
+fn replay(path: &Path) -> Result<BTreeMap<String, Value>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let lines: Vec<String> = BufReader::new(file)
+        .lines()
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let mut docs = BTreeMap::new();
+    for (n, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<LogEntry>(line) {
+            Ok(LogEntry::Put { key, value }) => {
+                docs.insert(key, value);
+            }
+            Ok(LogEntry::Delete { key }) => {
+                docs.remove(&key);
+            }
+            // A torn final line is what an interrupted write looks like; anything
+            // earlier in the file is real corruption.
+            Err(_) if n + 1 == lines.len() => break,
+            Err(e) => {
+                return Err(format!(
+                    "Corrupt entry at {}:{}: {}",
+                    path.display(),
+                    n + 1,
+                    e
+                ))
+            }
+        }
+    }
+    Ok(docs)
+}
+
+impl Storage for FileStorage {
+    fn put(&self, collection: &str, key: &str, value: Value) -> Result<(), String> {
+        let mut state = self.state.lock().expect("storage lock poisoned");
+        let entry = LogEntry::Put {
+            key: key.to_string(),
+            value: value.clone(),
+        };
+        self.append(&mut state, collection, &entry)?;
+        state
+            .collections
+            .entry(collection.to_string())
+            .or_default()
+            .insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn get(&self, collection: &str, key: &str) -> Result<Option<Value>, String> {
+        let state = self.state.lock().expect("storage lock poisoned");
+        Ok(state
+            .collections
+            .get(collection)
+            .and_then(|c| c.get(key))
+            .cloned())
+    }
+
+    fn delete(&self, collection: &str, key: &str) -> Result<(), String> {
+        let mut state = self.state.lock().expect("storage lock poisoned");
+        let present = state
+            .collections
+            .get(collection)
+            .is_some_and(|c| c.contains_key(key));
+        if !present {
+            return Ok(());
+        }
+        self.append(
+            &mut state,
+            collection,
+            &LogEntry::Delete {
+                key: key.to_string(),
+            },
+        )?;
+        if let Some(c) = state.collections.get_mut(collection) {
+            c.remove(key);
+        }
+        Ok(())
+    }
+
+    fn scan(&self, collection: &str) -> Result<Vec<(String, Value)>, String> {
+        let state = self.state.lock().expect("storage lock poisoned");
+        Ok(state
+            .collections
+            .get(collection)
+            .map(|c| c.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+            .unwrap_or_default())
+    }
+
+    fn collections(&self) -> Result<Vec<String>, String> {
+        let state = self.state.lock().expect("storage lock poisoned");
+        Ok(live_collections(&state.collections))
+    }
+
+    fn flush(&self) -> Result<(), String> {
+        let mut state = self.state.lock().expect("storage lock poisoned");
+        for (name, writer) in state.writers.iter_mut() {
+            writer
+                .flush()
+                .map_err(|e| format!("Failed to flush collection '{}': {}", name, e))?;
+            writer
+                .get_ref()
+                .sync_data()
+                .map_err(|e| format!("Failed to sync collection '{}': {}", name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Rewrites the collection log with one entry per live document, via a
+    /// synced temporary file renamed over the old log.
+    fn compact(&self, collection: &str) -> Result<(), String> {
+        let mut state = self.state.lock().expect("storage lock poisoned");
+        let path = self.collection_path(collection)?;
+        let temp = path.with_extension("jsonl.tmp");
+        let mut writer = BufWriter::new(
+            File::create(&temp)
+                .map_err(|e| format!("Failed to create {}: {}", temp.display(), e))?,
+        );
+        if let Some(docs) = state.collections.get(collection) {
+            for (key, value) in docs {
+                let entry = LogEntry::Put {
+                    key: key.clone(),
+                    value: value.clone(),
+                };
+                let line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+                writeln!(writer, "{}", line)
+                    .map_err(|e| format!("Failed to write {}: {}", temp.display(), e))?;
+            }
+        }
+        writer
+            .flush()
+            .and_then(|_| writer.get_ref().sync_all())
+            .map_err(|e| format!("Failed to sync {}: {}", temp.display(), e))?;
+        // The next append reopens the new file.
+        state.writers.remove(collection);
+        fs::rename(&temp, &path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+    }
+}
@@ -0,0 +1,185 @@
+// src/stream.rs
+
+//! Chunked generation of large amounts of attested randomness.
+//!
+//! Instead of one huge buffer and one signature, a stream yields fixed-size
+//! chunks that are each attested on their own. Every chunk's payload carries a
+//! [`ChainLink`] holding the payload digest of the chunk before it, so the
+//! signed chunks form a hash chain: a verifier can detect dropped, reordered
+//! or spliced chunks while only ever holding one chunk in memory.
+
+use ed25519_dalek::VerifyingKey;
+use sha2::{Digest, Sha256};
+
+use crate::attester::{Attestation, ChainLink, RngAttester};
+
+/// Most chunks one stream is split into, each costing a signature.
+pub const MAX_STREAM_CHUNKS: usize = 4096;
+
+/// Domain tag for the genesis link of a stream.
+pub const STREAM_GENESIS_DOMAIN: &[u8] = b"othentic-rng/stream/v1";
+
+/// Returns the `previous` value used by chunk 0 of a stream.
+pub fn genesis(total_len: u64, chunk_size: u64) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(STREAM_GENESIS_DOMAIN);
+    hasher.update(total_len.to_be_bytes());
+    hasher.update(chunk_size.to_be_bytes());
+    hasher.finalize().into()
+}
+
+/// Generates and signs the chunk of the given length at a chain link.
+pub type ChunkSource<'a> = dyn FnMut(usize, ChainLink) -> Result<Attestation, String> + 'a;
+
+/// Iterator over the attested chunks of a random stream.
+///
+/// Created by [`crate::performer::RngPerformer::generate_random_stream`] and
+/// [`crate::tasks::TaskRunner::open_stream`]. Each call to `next` generates
+/// and signs one chunk; the last chunk may be shorter.
+pub struct RandomStream<'a> {
+    source: Box<ChunkSource<'a>>,
+    total_len: u64,
+    chunk_size: u64,
+    produced: u64,
+    index: u64,
+    previous: [u8; 32],
+    failed: bool,
+}
+
+impl<'a> RandomStream<'a> {
+    pub(crate) fn new(
+        source: Box<ChunkSource<'a>>,
+        total_len: usize,
+        chunk_size: usize,
+    ) -> Result<Self, String> {
+        if total_len == 0 || chunk_size == 0 {
+            return Err("Stream length and chunk size must be positive integers.".to_string());
+        }
+        let (total_len, chunk_size) = (total_len as u64, chunk_size as u64);
+        Ok(RandomStream {
+            source,
+            total_len,
+            chunk_size,
+            produced: 0,
+            index: 0,
+            previous: genesis(total_len, chunk_size),
+            failed: false,
+        })
+    }
+
+    /// Number of chunks the stream yields in total.
+    pub fn chunk_count(&self) -> u64 {
+        self.total_len.div_ceil(self.chunk_size)
+    }
+}
+
+impl Iterator for RandomStream<'_> {
+    type Item = Result<Attestation, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.produced >= self.total_len {
+            return None;
+        }
+        let len = (self.total_len - self.produced).min(self.chunk_size);
+        let link = ChainLink {
+            index: self.index,
+            total_len: self.total_len,
+            previous: self.previous,
+        };
+
+        let result = (self.source)(len as usize, link);
+        match &result {
+            Ok(chunk) => {
+                self.previous = chunk.payload.digest();
+                self.produced += len;
+                self.index += 1;
+            }
+            Err(_) => self.failed = true,
+        }
+        Some(result)
+    }
+}
+
+/// Incremental verifier for a stream, fed one chunk at a time in order.
+pub struct StreamVerifier<'a> {
+    public_key: &'a VerifyingKey,
+    chunk_size: u64,
+    expected_index: u64,
+    previous: Option<[u8; 32]>,
+    total_len: Option<u64>,
+    received: u64,
+}
+
+impl<'a> StreamVerifier<'a> {
+    /// Starts verifying a stream produced with `chunk_size`-byte chunks.
+    pub fn new(public_key: &'a VerifyingKey, chunk_size: usize) -> Self {
+        StreamVerifier {
+            public_key,
+            chunk_size: chunk_size as u64,
+            expected_index: 0,
+            previous: None,
+            total_len: None,
+            received: 0,
+        }
+    }
+
+    /// Checks the signature and chain link of the next chunk.
+    pub fn push(&mut self, chunk: &Attestation) -> Result<(), String> {
+        let link = chunk
+            .payload
+            .chain
+            .ok_or_else(|| "Chunk carries no chain link".to_string())?;
+        if link.index != self.expected_index {
+            return Err(format!(
+                "Expected chunk {}, got chunk {}",
+                self.expected_index, link.index
+            ));
+        }
+        let total_len = *self.total_len.get_or_insert(link.total_len);
+        if link.total_len != total_len {
+            return Err(format!(
+                "Chunk {} claims a different stream length",
+                link.index
+            ));
+        }
+        let previous = self
+            .previous
+            .unwrap_or_else(|| genesis(total_len, self.chunk_size));
+        if link.previous != previous {
+            return Err(format!(
+                "Chunk {} is not linked to its predecessor",
+                link.index
+            ));
+        }
+
+        let len = chunk.payload.random_number.len() as u64;
+        let expected_len = (total_len - self.received).min(self.chunk_size);
+        if len != expected_len {
+            return Err(format!(
+                "Chunk {} has {} bytes, expected {}",
+                link.index, len, expected_len
+            ));
+        }
+
+        RngAttester::verify(self.public_key, chunk)?;
+        self.previous = Some(chunk.payload.digest());
+        self.received += len;
+        self.expected_index += 1;
+        Ok(())
+    }
+
+    /// Confirms that every chunk of the stream has been seen.
+    ///
+    /// # Returns
+    /// The total number of verified bytes.
+    pub fn finish(self) -> Result<u64, String> {
+        match self.total_len {
+            Some(total) if total == self.received => Ok(total),
+            Some(total) => Err(format!(
+                "Stream truncated: received {} of {} bytes",
+                self.received, total
+            )),
+            None => Err("Stream contained no chunks".to_string()),
+        }
+    }
+}
@@ -0,0 +1,2485 @@
+// src/tasks.rs
+
+//! Task execution pipeline: queue → generate → attest → submit.
+//!
+//! `TaskRunner` tracks every task that is currently in flight and mirrors its
+//! progress into storage, so a task interrupted by shutdown (or a crash) can be
+//! picked up again on the next start via [`TaskRunner::resume_pending`].
+//! Admitted tasks wait in a [`TaskQueue`] ordered by [`Priority`]; a task whose
+//! deadline has passed is dropped with [`TaskError::DeadlineExceeded`] instead
+//! of being fulfilled late.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::abi::{AbiAttestation, AbiOutcome};
+use crate::attester::{
+    self, Attestation, AttestationPayload, ChainLink, DrandRound, EnclaveQuote, HashAlg,
+    OperatorMetadata, RngAttester, ShareInfo, Validity,
+};
+use crate::batch::{self, BatchEntry, BatchItem, BATCH_ROOT_KIND};
+#[cfg(feature = "chaos")]
+use crate::chaos::Faults;
+use crate::config::ConfigHandle;
+use crate::dedup::{Duplicate, FulfilledTasks};
+use crate::definitions::{DefinitionRegistry, Derivation, TaskDefinition};
+use crate::drand::{DrandBeacon, DrandClient};
+use crate::encoding::Encoding;
+use crate::entropy::EntropyMixer;
+use crate::epochs::{EpochCertificate, EpochKeys};
+use crate::metrics::Metrics;
+use crate::performer::RngPerformer;
+use crate::pool::{PooledOutput, RandomnessPool};
+use crate::postprocess::Pipeline;
+use crate::publish::{self, EventPublisher};
+use crate::queue::{Priority, TaskQueue};
+use crate::signer::BatchSigner;
+use crate::storage::Storage;
+use crate::stream::RandomStream;
+use crate::telemetry::{Span, SpanContext, SpanKind, Tracer};
+use crate::tenants;
+use crate::timelock::{Commitment, Release, TimelockStatus, TIMELOCKS};
+use crate::vdf::VdfProof;
+use crate::vrf::VrfProof;
+
+/// Collection holding tasks that have been accepted but not yet completed.
+pub const PENDING_TASKS: &str = "pending_tasks";
+/// Collection holding the append-only task lifecycle log.
+pub const TASK_EVENTS: &str = "task_events";
+/// Collection holding the outcome of every completed task, keyed by task ID;
+/// chunk `N` of a stream is keyed `{task ID}#N`. Tenants have partitions of
+/// their own, as do [`TASK_EVENTS`] and
+/// [`DRY_RUNS`]; see [`tenants::collection`].
+pub const ATTESTATIONS: &str = "attestations";
+/// Collection holding what dry-run tasks would have submitted, keyed by task ID.
+pub const DRY_RUNS: &str = "dry_runs";
+
+/// Position of a task in the pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStage {
+    Queued,
+    Generating,
+    /// Running the generated seed through the VDF.
+    Delaying,
+    Attesting,
+    Submitting,
+    Completed,
+    Failed,
+    /// Dropped because its deadline passed before it could be fulfilled.
+    Expired,
+    /// The fulfillment transaction has enough confirmations to be final.
+    Confirmed,
+    /// A chain reorganization undid the fulfillment transaction.
+    Reorged,
+    /// The fulfillment transaction was mined but reverted.
+    Reverted,
+    /// Attested and sealed until its release; see [`crate::timelock`].
+    Sealed,
+}
+
+/// A request to produce one attested random value.
+#[derive(Debug, Clone)]
+pub struct TaskRequest {
+    pub task_id: String,
+    pub length: usize,
+    pub priority: Priority,
+    /// Unix time in milliseconds after which the result is worthless.
+    pub deadline: Option<u64>,
+    /// Caller entropy to mix into the output (see `RngPerformer::mix_client_entropy`).
+    pub client_entropy: Option<Vec<u8>>,
+    /// Span the task's own spans are recorded under.
+    pub trace: Option<SpanContext>,
+    /// Attest as usual but only record what would have been submitted.
+    pub dry_run: bool,
+    /// Tenant whose namespace the task ID belongs to.
+    pub tenant: Option<String>,
+    /// Domain tag signed into the attestation, set with `tenant`.
+    pub domain: Option<String>,
+    /// Task definition the request is served under; `length` must be in its range.
+    pub definition: Option<String>,
+    /// Seal the outcome until this release instead of returning it.
+    pub timelock: Option<Release>,
+    /// Request contexts fulfilled together under one Merkle-rooted
+    /// attestation (see [`crate::batch`]); `length` is then the root's.
+    pub batch: Option<Vec<BatchEntry>>,
+}
+
+/// A request to stream `length` bytes as attested chunks (see
+/// [`crate::stream`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamRequest {
+    pub task_id: String,
+    pub length: usize,
+    pub chunk_size: usize,
+    /// Tenant whose namespace the task ID belongs to.
+    pub tenant: Option<String>,
+    /// Domain tag signed into every chunk, set with `tenant`.
+    pub domain: Option<String>,
+}
+
+/// Upper bound on caller-supplied entropy, to keep payloads small.
+pub const MAX_CLIENT_ENTROPY: usize = 1024;
+
+/// The attested result of a task, hex-encoded as it is stored and served.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskOutcome {
+    pub task_id: String,
+    pub random_number: String,
+    pub salt: String,
+    pub signature: String,
+    pub public_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_entropy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator_entropy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vdf: Option<VdfOutcome>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drand: Option<DrandOutcome>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vrf: Option<VrfOutcome>,
+    /// `r || s || v` over the same digest, for `ecrecover`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secp256k1_signature: Option<String>,
+    /// Ethereum address of the secp256k1 signer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secp256k1_address: Option<String>,
+    /// BIP-340 Schnorr signature over the same digest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schnorr_signature: Option<String>,
+    /// x-only public key the Schnorr signature verifies against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schnorr_public_key: Option<String>,
+    /// Unix ms before which the value must not be consumed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<u64>,
+    /// Unix ms after which the value is stale.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// The attester's monotonic signature counter.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counter: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<MetadataOutcome>,
+    /// Pool slot the value was pre-generated for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot: Option<u64>,
+    /// Signature binding a pooled value to `task_id`; see [`crate::pool`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binding: Option<String>,
+    /// What would have been submitted, for dry-run tasks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dry_run: Option<Value>,
+    /// Tenant the task was served for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// The tenant's domain tag, covered by the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    /// Key epoch of the signer, covered by the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch: Option<u64>,
+    /// Master certificate of the epoch key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch_certificate: Option<EpochCertificate>,
+    /// Platform of the enclave holding the signing key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enclave_platform: Option<String>,
+    /// Hex quote of that enclave; its digest is covered by the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enclave_quote: Option<String>,
+    /// Task definition the value was made under, covered by the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_definition_id: Option<String>,
+    /// Digest the signatures are over, when not SHA-256.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<HashAlg>,
+    /// Post-processing stages the operator's entropy went through, in order,
+    /// covered by the signature.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub postprocess: Vec<String>,
+    /// Encoding of the byte fields when not hex; see [`TaskOutcome::encoded`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<Encoding>,
+    /// Commitment a time-locked outcome is sealed under; every other field
+    /// but the task and tenant is empty until its release.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timelock: Option<Commitment>,
+    /// How `randomNumber` is meant to be read, covered by the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// The requests of a batch, whose Merkle root is `randomNumber`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<Vec<BatchItem>>,
+    /// Position of the value in a stream; see [`crate::stream`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain: Option<ChainOutcome>,
+    /// The Shamir share set the value belongs to; see [`crate::shamir`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub share: Option<ShareOutcome>,
+    /// How long the pipeline took; not covered by the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<TaskTimings>,
+    /// The attestation in the layout of the Solidity verifier; see
+    /// [`crate::abi`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abi: Option<AbiOutcome>,
+    /// Span the outcome was submitted under, for the submitter to record;
+    /// kept in memory only.
+    #[serde(skip)]
+    pub trace: Option<SpanContext>,
+}
+
+/// Pipeline steps timed in [`TaskTimings`], in order.
+pub const STAGES: [&str; 6] = [
+    "queue_wait",
+    "entropy",
+    "delay",
+    "hashing",
+    "signing",
+    "submission",
+];
+
+/// How long each pipeline step of one task took, in milliseconds; the steps
+/// it skipped are absent. With batched signing, hashing happens on the
+/// signing thread and counts as signing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskTimings {
+    /// From admission until a worker picked the task up.
+    pub queue_wait_ms: f64,
+    /// Drawing entropy or evaluating the VRF, the drand fetch included.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entropy_ms: Option<f64>,
+    /// Evaluating the VDF.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hashing_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submission_ms: Option<f64>,
+    /// From admission until the outcome was ready.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_ms: Option<f64>,
+}
+
+impl TaskTimings {
+    /// The timed steps, named as in [`STAGES`].
+    pub fn stages(&self) -> Vec<(&'static str, f64)> {
+        let measured = [
+            Some(self.queue_wait_ms),
+            self.entropy_ms,
+            self.delay_ms,
+            self.hashing_ms,
+            self.signing_ms,
+            self.submission_ms,
+        ];
+        STAGES
+            .into_iter()
+            .zip(measured)
+            .filter_map(|(stage, ms)| Some((stage, ms?)))
+            .collect()
+    }
+
+    /// Adds the time since `started` to `step`.
+    fn add(step: &mut Option<f64>, started: Instant) {
+        *step = Some(step.unwrap_or(0.0) + millis_since(started));
+    }
+}
+
+/// Latency summary served at `GET /latency`; percentiles are over the most
+/// recent [`crate::metrics::SUMMARY_WINDOW`] tasks.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyReport {
+    /// End-to-end latency target, if one is set.
+    pub slo_ms: Option<f64>,
+    /// Tasks that finished within the target, and that did not.
+    pub met: u64,
+    pub missed: u64,
+    pub total: Percentiles,
+    /// Per step of [`STAGES`], for the steps timed so far.
+    pub stages: BTreeMap<&'static str, Percentiles>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Percentiles {
+    pub p50: Option<f64>,
+    pub p90: Option<f64>,
+    pub p99: Option<f64>,
+}
+
+/// Hex-encoded [`ChainLink`] of a stream chunk attached to a [`TaskOutcome`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainOutcome {
+    pub index: u64,
+    pub total_length: u64,
+    pub previous: String,
+}
+
+/// [`ShareInfo`] of a Shamir share attached to a [`TaskOutcome`], with the
+/// commitment hex-encoded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareOutcome {
+    pub index: u8,
+    pub threshold: u8,
+    pub total: u8,
+    pub commitment: String,
+}
+
+/// Hex-encoded VDF evaluation attached to a [`TaskOutcome`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VdfOutcome {
+    pub seed: String,
+    pub iterations: u64,
+    pub output: String,
+    pub proof: String,
+}
+
+/// Hex-encoded drand round attached to a [`TaskOutcome`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DrandOutcome {
+    pub chain_hash: String,
+    pub round: u64,
+    pub signature: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub previous_signature: String,
+    /// The operator's contribution before the round was mixed in.
+    pub local: String,
+}
+
+/// Hex-encoded VRF evaluation attached to a [`TaskOutcome`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VrfOutcome {
+    pub input: String,
+    pub proof: String,
+}
+
+/// Operator metadata attached to a [`TaskOutcome`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataOutcome {
+    pub version: String,
+    pub signing_scheme: String,
+    pub entropy_sources: Vec<String>,
+    pub config_hash: String,
+    pub chain_id: u64,
+}
+
+impl TaskOutcome {
+    pub(crate) fn from_attestation(
+        task_id: &str,
+        attestation: &Attestation,
+        attester: &RngAttester,
+    ) -> Self {
+        let payload = &attestation.payload;
+        TaskOutcome {
+            task_id: task_id.to_string(),
+            random_number: hex::encode(&payload.random_number),
+            salt: hex::encode(&payload.salt),
+            signature: hex::encode(attestation.signature.to_bytes()),
+            public_key: hex::encode(attester.get_public_key().as_bytes()),
+            client_entropy: payload.client_entropy.as_ref().map(hex::encode),
+            operator_entropy: payload.operator_entropy.as_ref().map(hex::encode),
+            vdf: payload.vdf.as_ref().map(|p| VdfOutcome {
+                seed: hex::encode(&p.seed),
+                iterations: p.iterations,
+                output: hex::encode(&p.output),
+                proof: hex::encode(&p.proof),
+            }),
+            drand: payload.drand.as_ref().map(|d| DrandOutcome {
+                chain_hash: hex::encode(d.chain_hash),
+                round: d.beacon.round,
+                signature: hex::encode(&d.beacon.signature),
+                previous_signature: hex::encode(&d.beacon.previous_signature),
+                local: hex::encode(&d.local),
+            }),
+            vrf: payload.vrf.as_ref().map(|p| VrfOutcome {
+                input: hex::encode(&p.input),
+                proof: hex::encode(&p.proof),
+            }),
+            secp256k1_signature: attestation.secp256k1_signature.map(hex::encode),
+            secp256k1_address: attester.secp256k1_address().map(hex::encode),
+            schnorr_signature: attestation.schnorr_signature.map(hex::encode),
+            schnorr_public_key: attester.schnorr_public_key().map(hex::encode),
+            not_before: payload.validity.map(|v| v.not_before),
+            expires_at: payload.validity.map(|v| v.expires_at),
+            counter: payload.counter,
+            metadata: payload.metadata.as_ref().map(|m| MetadataOutcome {
+                version: m.version.clone(),
+                signing_scheme: m.signing_scheme.clone(),
+                entropy_sources: m.entropy_sources.clone(),
+                config_hash: hex::encode(m.config_hash),
+                chain_id: m.chain_id,
+            }),
+            slot: payload.slot,
+            binding: None,
+            dry_run: None,
+            tenant: None,
+            domain: payload.domain.clone(),
+            epoch: payload.epoch,
+            epoch_certificate: attester.epoch_certificate().cloned(),
+            enclave_platform: attester.enclave_quote().map(|q| q.platform.clone()),
+            enclave_quote: attester.enclave_quote().map(|q| hex::encode(&q.quote)),
+            task_definition_id: payload.definition.clone(),
+            hash: payload.hash,
+            postprocess: payload.postprocess.clone(),
+            encoding: None,
+            timelock: None,
+            kind: payload.kind.clone(),
+            batch: None,
+            chain: payload.chain.map(|link| ChainOutcome {
+                index: link.index,
+                total_length: link.total_len,
+                previous: hex::encode(link.previous),
+            }),
+            share: payload.share.map(|share| ShareOutcome {
+                index: share.index,
+                threshold: share.threshold,
+                total: share.total,
+                commitment: hex::encode(share.commitment),
+            }),
+            timings: None,
+            abi: None,
+            trace: None,
+        }
+    }
+
+    /// Re-encodes the randomness, salt, entropy, proofs, share commitments,
+    /// signatures and keys in `encoding`. Ethereum addresses, IDs and the
+    /// epoch certificate stay as they are.
+    pub fn encoded(&self, encoding: Encoding) -> Result<TaskOutcome, String> {
+        let from = self.encoding.unwrap_or_default();
+        if from == encoding {
+            return Ok(self.clone());
+        }
+        let convert = |field: &str, value: &mut String| -> Result<(), String> {
+            *value = encoding.encode(&decode(from, field, value)?);
+            Ok(())
+        };
+        let optional = |field: &str, value: &mut Option<String>| match value {
+            Some(value) => convert(field, value),
+            None => Ok(()),
+        };
+        let mut outcome = self.clone();
+        convert("randomNumber", &mut outcome.random_number)?;
+        convert("salt", &mut outcome.salt)?;
+        convert("signature", &mut outcome.signature)?;
+        convert("publicKey", &mut outcome.public_key)?;
+        optional("clientEntropy", &mut outcome.client_entropy)?;
+        optional("operatorEntropy", &mut outcome.operator_entropy)?;
+        if let Some(v) = &mut outcome.vdf {
+            convert("vdf.seed", &mut v.seed)?;
+            convert("vdf.output", &mut v.output)?;
+            convert("vdf.proof", &mut v.proof)?;
+        }
+        if let Some(d) = &mut outcome.drand {
+            convert("drand.chainHash", &mut d.chain_hash)?;
+            convert("drand.signature", &mut d.signature)?;
+            if !d.previous_signature.is_empty() {
+                convert("drand.previousSignature", &mut d.previous_signature)?;
+            }
+            convert("drand.local", &mut d.local)?;
+        }
+        if let Some(v) = &mut outcome.vrf {
+            convert("vrf.input", &mut v.input)?;
+            convert("vrf.proof", &mut v.proof)?;
+        }
+        if let Some(c) = &mut outcome.chain {
+            convert("chain.previous", &mut c.previous)?;
+        }
+        if let Some(share) = &mut outcome.share {
+            convert("share.commitment", &mut share.commitment)?;
+        }
+        optional("secp256k1Signature", &mut outcome.secp256k1_signature)?;
+        optional("schnorrSignature", &mut outcome.schnorr_signature)?;
+        optional("schnorrPublicKey", &mut outcome.schnorr_public_key)?;
+        if let Some(m) = &mut outcome.metadata {
+            convert("metadata.configHash", &mut m.config_hash)?;
+        }
+        optional("binding", &mut outcome.binding)?;
+        optional("enclaveQuote", &mut outcome.enclave_quote)?;
+        for item in outcome.batch.iter_mut().flatten() {
+            convert("batch.randomNumber", &mut item.random_number)?;
+            optional("batch.operatorEntropy", &mut item.operator_entropy)?;
+            optional("batch.clientEntropy", &mut item.client_entropy)?;
+            for node in &mut item.proof {
+                convert("batch.proof", node)?;
+            }
+        }
+        outcome.encoding = (encoding != Encoding::Hex).then_some(encoding);
+        Ok(outcome)
+    }
+
+    /// The enclave quote the outcome carries, if any.
+    pub fn enclave_quote(&self) -> Result<Option<EnclaveQuote>, String> {
+        match (&self.enclave_platform, &self.enclave_quote) {
+            (Some(platform), Some(quote)) => Ok(Some(EnclaveQuote {
+                platform: platform.clone(),
+                quote: decode(self.encoding.unwrap_or_default(), "enclaveQuote", quote)?,
+            })),
+            (None, None) => Ok(None),
+            _ => Err("enclavePlatform and enclaveQuote must be given together".to_string()),
+        }
+    }
+
+    /// The [`tenants::task_key`] of the task.
+    pub fn key(&self) -> String {
+        tenants::task_key(self.tenant.as_deref(), &self.task_id)
+    }
+
+    /// Decodes the outcome back into the attestation it was built from.
+    pub fn to_attestation(&self) -> Result<Attestation, String> {
+        let encoding = self.encoding.unwrap_or_default();
+        let decode = |field: &str, value: &str| decode(encoding, field, value);
+        let signature: [u8; 64] = decode("signature", &self.signature)?
+            .try_into()
+            .map_err(|_| "signature must be 64 bytes".to_string())?;
+        let optional =
+            |name, value: &Option<String>| value.as_deref().map(|v| decode(name, v)).transpose();
+        let vdf = match &self.vdf {
+            Some(v) => Some(VdfProof {
+                seed: decode("vdf.seed", &v.seed)?,
+                iterations: v.iterations,
+                output: decode("vdf.output", &v.output)?,
+                proof: decode("vdf.proof", &v.proof)?,
+            }),
+            None => None,
+        };
+        let drand = match &self.drand {
+            Some(d) => Some(DrandRound {
+                chain_hash: decode("drand.chainHash", &d.chain_hash)?
+                    .try_into()
+                    .map_err(|_| "drand.chainHash must be 32 bytes".to_string())?,
+                beacon: DrandBeacon {
+                    round: d.round,
+                    signature: decode("drand.signature", &d.signature)?,
+                    // Unchained rounds have none, in every encoding.
+                    previous_signature: match d.previous_signature.as_str() {
+                        "" => Vec::new(),
+                        value => decode("drand.previousSignature", value)?,
+                    },
+                },
+                local: decode("drand.local", &d.local)?,
+            }),
+            None => None,
+        };
+        let vrf = match &self.vrf {
+            Some(v) => Some(VrfProof {
+                input: decode("vrf.input", &v.input)?,
+                proof: decode("vrf.proof", &v.proof)?,
+            }),
+            None => None,
+        };
+        let metadata = match &self.metadata {
+            Some(m) => Some(OperatorMetadata {
+                version: m.version.clone(),
+                signing_scheme: m.signing_scheme.clone(),
+                entropy_sources: m.entropy_sources.clone(),
+                config_hash: decode("metadata.configHash", &m.config_hash)?
+                    .try_into()
+                    .map_err(|_| "metadata.configHash must be 32 bytes".to_string())?,
+                chain_id: m.chain_id,
+            }),
+            None => None,
+        };
+        let chain = match &self.chain {
+            Some(c) => Some(ChainLink {
+                index: c.index,
+                total_len: c.total_length,
+                previous: decode("chain.previous", &c.previous)?
+                    .try_into()
+                    .map_err(|_| "chain.previous must be 32 bytes".to_string())?,
+            }),
+            None => None,
+        };
+        let share = match &self.share {
+            Some(s) => Some(ShareInfo {
+                index: s.index,
+                threshold: s.threshold,
+                total: s.total,
+                commitment: decode("share.commitment", &s.commitment)?
+                    .try_into()
+                    .map_err(|_| "share.commitment must be 32 bytes".to_string())?,
+            }),
+            None => None,
+        };
+        let validity = match (self.not_before, self.expires_at) {
+            (Some(not_before), Some(expires_at)) => Some(Validity {
+                not_before,
+                expires_at,
+            }),
+            (None, None) => None,
+            _ => return Err("notBefore and expiresAt must be given together".to_string()),
+        };
+        Ok(Attestation {
+            payload: AttestationPayload {
+                random_number: decode("randomNumber", &self.random_number)?,
+                salt: decode("salt", &self.salt)?,
+                client_entropy: optional("clientEntropy", &self.client_entropy)?,
+                operator_entropy: optional("operatorEntropy", &self.operator_entropy)?,
+                vdf,
+                drand,
+                vrf,
+                validity,
+                counter: self.counter,
+                metadata,
+                slot: self.slot,
+                domain: self.domain.clone(),
+                epoch: self.epoch,
+                enclave: self.enclave_quote()?.as_ref().map(EnclaveQuote::digest),
+                definition: self.task_definition_id.clone(),
+                hash: self.hash,
+                postprocess: self.postprocess.clone(),
+                kind: self.kind.clone(),
+                chain,
+                share,
+            },
+            signature: Signature::from_bytes(&signature),
+            secp256k1_signature: match &self.secp256k1_signature {
+                Some(s) => Some(
+                    decode("secp256k1Signature", s)?
+                        .try_into()
+                        .map_err(|_| "secp256k1Signature must be 65 bytes".to_string())?,
+                ),
+                None => None,
+            },
+            schnorr_signature: match &self.schnorr_signature {
+                Some(s) => Some(
+                    decode("schnorrSignature", s)?
+                        .try_into()
+                        .map_err(|_| "schnorrSignature must be 64 bytes".to_string())?,
+                ),
+                None => None,
+            },
+        })
+    }
+}
+
+fn decode(encoding: Encoding, field: &str, value: &str) -> Result<Vec<u8>, String> {
+    encoding
+        .decode(value)
+        .map_err(|e| format!("Invalid {} in {}: {}", encoding, field, e))
+}
+
+/// Why a task could not be executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskError {
+    /// The runner is draining and no longer admits new work.
+    ShuttingDown,
+    /// Serving was paused through the admin API.
+    Paused,
+    /// The request itself is unacceptable (bad parameters, duplicate ID, ...).
+    Rejected(String),
+    /// The pipeline failed while processing the task.
+    Failed(String),
+    /// The deadline (Unix ms) passed before the task could be fulfilled.
+    DeadlineExceeded { deadline: u64 },
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskError::ShuttingDown => write!(f, "operator is shutting down"),
+            TaskError::Paused => write!(f, "operator is paused"),
+            TaskError::Rejected(e) => write!(f, "task rejected: {}", e),
+            TaskError::Failed(e) => write!(f, "task failed: {}", e),
+            TaskError::DeadlineExceeded { deadline } => {
+                write!(f, "task deadline {} passed before fulfillment", deadline)
+            }
+        }
+    }
+}
+
+/// Delivers an attested outcome to the next hop (aggregator, chain, ...).
+pub trait Submitter: Send + Sync {
+    fn submit(&self, outcome: &TaskOutcome) -> Result<(), String>;
+
+    /// Current height of the chain outcomes are submitted to, if any; what
+    /// block-height [`Release`]s are checked against.
+    fn chain_height(&self) -> Result<Option<u64>, String> {
+        Ok(None)
+    }
+
+    /// Describes what [`Submitter::submit`] would send for `outcome`,
+    /// without sending anything.
+    fn dry_run(&self, outcome: &TaskOutcome) -> Result<Value, String> {
+        Ok(json!({ "outcome": outcome }))
+    }
+}
+
+/// Submitter used when no aggregator is configured: it only logs the outcome.
+pub struct LogSubmitter;
+
+impl Submitter for LogSubmitter {
+    fn submit(&self, outcome: &TaskOutcome) -> Result<(), String> {
+        info!(
+            "Task {} attested (no aggregator configured)",
+            outcome.task_id
+        );
+        Ok(())
+    }
+}
+
+/// The chunks of an admitted stream, generated from fresh entropy and
+/// attested one at a time as the consumer reads them. The stream is in flight
+/// until dropped; it stops early with an error once serving is paused or
+/// shutdown begins.
+pub struct TaskStream<'a> {
+    runner: &'a TaskRunner,
+    attester: Arc<RngAttester>,
+    chunks: RandomStream<'a>,
+    task_id: String,
+    tenant: Option<String>,
+    index: u64,
+    done: bool,
+}
+
+impl TaskStream<'_> {
+    /// Number of chunks the stream yields in total.
+    pub fn chunk_count(&self) -> u64 {
+        self.chunks.chunk_count()
+    }
+
+    fn key(&self) -> String {
+        tenants::task_key(self.tenant.as_deref(), &self.task_id)
+    }
+}
+
+impl Iterator for TaskStream<'_> {
+    type Item = Result<TaskOutcome, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        {
+            let state = self.runner.state.lock().expect("runner lock poisoned");
+            let stopped = if !state.accepting {
+                Some(TaskError::ShuttingDown)
+            } else if state.paused {
+                Some(TaskError::Paused)
+            } else {
+                None
+            };
+            if let Some(e) = stopped {
+                self.done = true;
+                return Some(Err(e.to_string()));
+            }
+        }
+        let chunk = match self.chunks.next()? {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
+        let mut outcome = TaskOutcome::from_attestation(&self.task_id, &chunk, &self.attester);
+        outcome.tenant = self.tenant.clone();
+        let record = json!({ "completed_at": unix_millis(), "outcome": outcome });
+        let collection = tenants::collection(ATTESTATIONS, self.tenant.as_deref());
+        let key = format!("{}#{}", self.task_id, self.index);
+        if let Err(e) = self.runner.storage.put(&collection, &key, record) {
+            warn!(
+                "Failed to archive chunk {} of stream {}: {}",
+                self.index,
+                self.key(),
+                e
+            );
+        }
+        self.index += 1;
+        Some(Ok(outcome))
+    }
+}
+
+impl Drop for TaskStream<'_> {
+    fn drop(&mut self) {
+        self.runner.release(&self.key());
+    }
+}
+
+/// Persisted form of an in-flight task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingTask {
+    task_id: String,
+    length: usize,
+    stage: TaskStage,
+    accepted_at: u64,
+    #[serde(default)]
+    priority: Priority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deadline: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    client_entropy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    outcome: Option<TaskOutcome>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    dry_run: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tenant: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    definition: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timelock: Option<Release>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    batch: Option<Vec<BatchEntry>>,
+    /// When the task was last queued; not persisted.
+    #[serde(skip)]
+    queued_at: Option<Instant>,
+    #[serde(skip)]
+    timings: TaskTimings,
+}
+
+/// A sealed task as stored in [`TIMELOCKS`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SealedTask {
+    commitment: Commitment,
+    /// Hex key of the ciphertext, revealed once `released_at` is set.
+    key: String,
+    /// The task as it resumes at its release, outcome included.
+    task: PendingTask,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    released_at: Option<u64>,
+}
+
+impl PendingTask {
+    fn key(&self) -> String {
+        tenants::task_key(self.tenant.as_deref(), &self.task_id)
+    }
+
+    /// The partition of `base` this task's records go to.
+    fn collection(&self, base: &str) -> String {
+        tenants::collection(base, self.tenant.as_deref())
+    }
+
+    /// Task key and length of every task this one batches.
+    fn batch_keys(&self) -> Vec<(String, usize)> {
+        self.batch
+            .iter()
+            .flatten()
+            .map(|e| {
+                (
+                    tenants::task_key(self.tenant.as_deref(), &e.task_id),
+                    e.length,
+                )
+            })
+            .collect()
+    }
+
+    fn past_deadline(&self) -> Option<u64> {
+        self.deadline.filter(|&d| unix_millis() > d)
+    }
+}
+
+type Reply = mpsc::Sender<Result<TaskOutcome, TaskError>>;
+
+/// A queued unit of work; `reply` is absent for tasks resumed from storage.
+struct Job {
+    task: PendingTask,
+    reply: Option<Reply>,
+    trace: Option<SpanContext>,
+}
+
+/// Result of [`TaskRunner::drain`].
+#[derive(Debug, Clone, Default)]
+pub struct DrainSummary {
+    /// Tasks that finished while the drain was in progress.
+    pub finished: usize,
+    /// Tasks still running at the deadline; their last stage is in storage.
+    pub persisted: Vec<(String, TaskStage)>,
+    pub elapsed: Duration,
+}
+
+impl DrainSummary {
+    pub fn is_clean(&self) -> bool {
+        self.persisted.is_empty()
+    }
+}
+
+struct RunnerState {
+    accepting: bool,
+    paused: bool,
+    in_flight: HashMap<String, TaskStage>,
+    finished: usize,
+}
+
+/// `TaskRunner` executes tasks and coordinates graceful shutdown.
+pub struct TaskRunner {
+    performer: RngPerformer,
+    entropy: Option<Arc<EntropyMixer>>,
+    attester: RwLock<Arc<RngAttester>>,
+    storage: Arc<dyn Storage>,
+    submitter: Arc<dyn Submitter>,
+    metrics: Arc<Metrics>,
+    queue: TaskQueue<Job>,
+    vdf_iterations: Option<u64>,
+    drand: Option<DrandClient>,
+    deterministic: bool,
+    validity: Option<Duration>,
+    definitions: DefinitionRegistry,
+    timelock_max_delay: Option<Duration>,
+    config: Option<Arc<ConfigHandle>>,
+    tracer: Arc<Tracer>,
+    signer: Option<Arc<BatchSigner>>,
+    pool: Option<Arc<RandomnessPool>>,
+    epochs: Option<Arc<EpochKeys>>,
+    publisher: Option<Arc<EventPublisher>>,
+    fulfilled: Option<Arc<FulfilledTasks>>,
+    latency_slo: Option<Duration>,
+    abi: bool,
+    postprocess: Pipeline,
+    #[cfg(feature = "chaos")]
+    faults: Option<Arc<Faults>>,
+    dry_run: bool,
+    state: Mutex<RunnerState>,
+    idle: Condvar,
+    event_seq: AtomicU64,
+}
+
+impl TaskRunner {
+    /// Creates a runner whose queue holds at most `queue_capacity` tasks.
+    ///
+    /// No task is processed until [`TaskRunner::start_workers`] is called.
+    pub fn new(
+        performer: RngPerformer,
+        attester: RngAttester,
+        storage: Arc<dyn Storage>,
+        submitter: Arc<dyn Submitter>,
+        metrics: Arc<Metrics>,
+        queue_capacity: usize,
+    ) -> Self {
+        TaskRunner {
+            performer,
+            entropy: None,
+            attester: RwLock::new(Arc::new(attester)),
+            storage,
+            submitter,
+            metrics,
+            queue: TaskQueue::new(queue_capacity),
+            vdf_iterations: None,
+            drand: None,
+            deterministic: false,
+            validity: None,
+            definitions: DefinitionRegistry::default(),
+            timelock_max_delay: None,
+            config: None,
+            tracer: Arc::new(Tracer::disabled()),
+            signer: None,
+            pool: None,
+            epochs: None,
+            publisher: None,
+            fulfilled: None,
+            latency_slo: None,
+            abi: false,
+            postprocess: Pipeline::default(),
+            #[cfg(feature = "chaos")]
+            faults: None,
+            dry_run: false,
+            state: Mutex::new(RunnerState {
+                accepting: true,
+                paused: false,
+                in_flight: HashMap::new(),
+                finished: 0,
+            }),
+            idle: Condvar::new(),
+            event_seq: AtomicU64::new(0),
+        }
+    }
+
+    /// Draws fresh entropy from the sources `mixer` races instead of the OS
+    /// RNG alone.
+    pub fn with_entropy(mut self, mixer: Arc<EntropyMixer>) -> Self {
+        self.entropy = Some(mixer);
+        self
+    }
+
+    /// Enables the VDF post-processing stage with `iterations` squarings.
+    pub fn with_vdf(mut self, iterations: u64) -> Self {
+        self.vdf_iterations = Some(iterations);
+        self
+    }
+
+    /// Mixes the latest verified round from `client` into every output.
+    pub fn with_drand(mut self, client: DrandClient) -> Self {
+        self.drand = Some(client);
+        self
+    }
+
+    /// Derives each output from a VRF over the task ID (its
+    /// [`tenants::task_key`]) instead of fresh entropy, so every fulfilment
+    /// of a task yields the same value.
+    pub fn with_vrf(mut self) -> Self {
+        self.deterministic = true;
+        self
+    }
+
+    /// Signs a validity window into every attestation: it may be consumed
+    /// from the moment it is signed until `ttl` later.
+    pub fn with_validity(mut self, ttl: Duration) -> Self {
+        self.validity = Some(ttl);
+        self
+    }
+
+    /// Serves requests that name one of `definitions` under its settings.
+    pub fn with_definitions(mut self, definitions: DefinitionRegistry) -> Self {
+        self.definitions = definitions;
+        self
+    }
+
+    /// Admits time-locked tasks released at most `max_delay` from now; run
+    /// the releases with [`TaskRunner::run_timelocks`].
+    pub fn with_timelock(mut self, max_delay: Duration) -> Self {
+        self.timelock_max_delay = Some(max_delay);
+        self
+    }
+
+    /// Embeds [`OperatorMetadata`] in every attestation, describing the
+    /// settings of `config` current at signing time.
+    pub fn with_metadata(mut self, config: Arc<ConfigHandle>) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Records a span per task and pipeline step with `tracer`.
+    pub fn with_tracer(mut self, tracer: Arc<Tracer>) -> Self {
+        self.tracer = tracer;
+        self
+    }
+
+    /// Signs through `signer`'s batching thread instead of on the worker.
+    pub fn with_batch_signer(mut self, signer: Arc<BatchSigner>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Publishes every completed attestation with `publisher`.
+    pub fn with_publisher(mut self, publisher: Arc<EventPublisher>) -> Self {
+        self.publisher = Some(publisher);
+        self
+    }
+
+    /// Remembers fulfilled tasks in `fulfilled` and answers requests for them
+    /// from it, across restarts; see [`crate::dedup`].
+    pub fn with_dedup(mut self, fulfilled: Arc<FulfilledTasks>) -> Self {
+        self.fulfilled = Some(fulfilled);
+        self
+    }
+
+    /// Attaches the ABI form of the attestation, signed with the secp256k1
+    /// key, to every outcome that carries a secp256k1 address.
+    pub fn with_abi(mut self) -> Self {
+        self.abi = true;
+        self
+    }
+
+    /// Passes fresh entropy through `pipeline` before it is attested; VRF
+    /// outputs are left as they are.
+    pub fn with_postprocess(mut self, pipeline: Pipeline) -> Self {
+        self.postprocess = pipeline;
+        self
+    }
+
+    /// Delays signing and corrupts salts while `faults` says so.
+    #[cfg(feature = "chaos")]
+    pub fn with_faults(mut self, faults: Arc<Faults>) -> Self {
+        self.faults = Some(faults);
+        self
+    }
+
+    /// Counts the tasks that took longer than `slo` from admission to
+    /// outcome, and logs each with its slowest step.
+    pub fn with_latency_slo(mut self, slo: Duration) -> Self {
+        self.latency_slo = Some(slo);
+        self
+    }
+
+    /// Treats every task as a dry run: outcomes are attested but only
+    /// recorded in [`DRY_RUNS`], never submitted or published.
+    pub fn with_dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Serves eligible tasks from `pool`; keep it filled with
+    /// [`TaskRunner::run_pool`].
+    pub fn with_pool(mut self, pool: Arc<RandomnessPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Signs with the key of the current epoch of `epochs`, switching keys
+    /// as epochs begin.
+    pub fn with_epochs(mut self, epochs: Arc<EpochKeys>) -> Result<Self, String> {
+        let attester = self.attester.get_mut().expect("attester lock poisoned");
+        let (key, certificate) = epochs.enter(epochs.epoch_at(unix_millis()))?;
+        *attester = Arc::new(attester.for_epoch(key, certificate));
+        self.epochs = Some(epochs);
+        Ok(self)
+    }
+
+    /// Spawns `count` worker threads that process queued tasks.
+    pub fn start_workers(self: &Arc<Self>, count: usize) {
+        for _ in 0..count {
+            let runner = Arc::clone(self);
+            thread::spawn(move || loop {
+                let (_, job) = runner.queue.pop();
+                runner.publish_queue_depth();
+                let task_id = job.task.key();
+                let result = runner.run(job.task, job.trace);
+                runner.release(&task_id);
+                if let Some(reply) = job.reply {
+                    // The requester may have gone away; the result is persisted anyway.
+                    let _ = reply.send(result);
+                }
+            });
+        }
+    }
+
+    /// Queues `request` and blocks until a worker has processed it.
+    pub fn execute(&self, request: TaskRequest) -> Result<TaskOutcome, TaskError> {
+        // A batch's length is its root's; its entries are checked below.
+        if request.batch.is_none() {
+            self.performer
+                .check_length(request.length)
+                .map_err(TaskError::Rejected)?;
+        }
+        if request.task_id.is_empty() || request.task_id.contains('/') {
+            // `/` separates the tenant in task keys.
+            return Err(TaskError::Rejected(
+                "task ID must be non-empty and must not contain '/'".to_string(),
+            ));
+        }
+        if let Some(id) = &request.definition {
+            let definition = self
+                .definitions
+                .get(id)
+                .ok_or_else(|| TaskError::Rejected(format!("unknown task definition {}", id)))?;
+            definition
+                .policy()
+                .check(request.length)
+                .map_err(|e| TaskError::Rejected(format!("task definition {}: {}", id, e)))?;
+        }
+        if let Some(release) = &request.timelock {
+            let max_delay = self.timelock_max_delay.ok_or_else(|| {
+                TaskError::Rejected("timelocked delivery is not enabled".to_string())
+            })?;
+            if release.at.is_none() && release.block.is_none() {
+                return Err(TaskError::Rejected(
+                    "a timelock needs a release time or block".to_string(),
+                ));
+            }
+            if let Some(at) = release.at {
+                let now = unix_millis();
+                if at <= now || at - now > max_delay.as_millis() as u64 {
+                    return Err(TaskError::Rejected(format!(
+                        "timelock release must be in the future, within the next {:?}",
+                        max_delay
+                    )));
+                }
+                if request.deadline.is_some_and(|d| d < at) {
+                    return Err(TaskError::Rejected(
+                        "deadline is before the timelock release".to_string(),
+                    ));
+                }
+            }
+        }
+        if let Some(entries) = &request.batch {
+            self.check_batch(&request, entries)
+                .map_err(TaskError::Rejected)?;
+        }
+        if let Some(deadline) = request.deadline.filter(|&d| unix_millis() > d) {
+            return Err(TaskError::DeadlineExceeded { deadline });
+        }
+        if request
+            .client_entropy
+            .as_ref()
+            .is_some_and(|e| e.is_empty() || e.len() > MAX_CLIENT_ENTROPY)
+        {
+            return Err(TaskError::Rejected(format!(
+                "client entropy must be 1..={} bytes",
+                MAX_CLIENT_ENTROPY
+            )));
+        }
+        let task = PendingTask {
+            task_id: request.task_id,
+            length: request.length,
+            stage: TaskStage::Queued,
+            accepted_at: unix_millis(),
+            priority: request.priority,
+            deadline: request.deadline,
+            client_entropy: request.client_entropy.as_ref().map(hex::encode),
+            outcome: None,
+            dry_run: request.dry_run,
+            tenant: request.tenant,
+            domain: request.domain,
+            definition: request.definition,
+            timelock: request.timelock,
+            batch: request.batch,
+            queued_at: None,
+            timings: TaskTimings::default(),
+        };
+
+        let (reply, result) = mpsc::channel();
+        self.enqueue(task, Some(reply), request.trace)?;
+        result
+            .recv()
+            .unwrap_or_else(|_| Err(TaskError::Failed("worker dropped the task".to_string())))
+    }
+
+    /// Admits a stream as one task in flight; its chunks are drawn like the
+    /// entropy of a task, post-processed, tagged with the tenant's domain and
+    /// given the operator's metadata and validity window before signing.
+    pub fn open_stream(&self, request: StreamRequest) -> Result<TaskStream<'_>, TaskError> {
+        if request.task_id.is_empty() || request.task_id.contains('/') {
+            return Err(TaskError::Rejected(
+                "task ID must be non-empty and must not contain '/'".to_string(),
+            ));
+        }
+        self.performer
+            .check_stream(request.length, request.chunk_size)
+            .map_err(TaskError::Rejected)?;
+        if let Some(max) = self.postprocess.max_length() {
+            if request.chunk_size > max {
+                return Err(TaskError::Rejected(format!(
+                    "chunks are at most {} bytes with this post-processing",
+                    max
+                )));
+            }
+        }
+        let key = tenants::task_key(request.tenant.as_deref(), &request.task_id);
+        self.admit(&key, TaskStage::Generating)?;
+
+        let attester = self.attester();
+        let signing = Arc::clone(&attester);
+        let domain = request.domain;
+        let source = move |length: usize, link| {
+            let (bytes, sources) = self.fresh_entropy(length)?;
+            let mut payload = AttestationPayload::new(bytes)
+                .with_chain(link)
+                .with_postprocess(self.postprocess.ids());
+            if let Some(domain) = &domain {
+                payload = payload.with_domain(domain);
+            }
+            let payload = self.finish_payload(payload, &signing, None, sources);
+            self.sign(&signing, payload, &mut TaskTimings::default())
+        };
+        let chunks = match RandomStream::new(Box::new(source), request.length, request.chunk_size) {
+            Ok(chunks) => chunks,
+            Err(e) => {
+                self.release(&key);
+                return Err(TaskError::Rejected(e));
+            }
+        };
+        Ok(TaskStream {
+            runner: self,
+            attester,
+            chunks,
+            task_id: request.task_id,
+            tenant: request.tenant,
+            index: 0,
+            done: false,
+        })
+    }
+
+    fn check_batch(&self, request: &TaskRequest, entries: &[BatchEntry]) -> Result<(), String> {
+        if entries.is_empty() {
+            return Err("a batch needs at least one request".to_string());
+        }
+        if request.client_entropy.is_some()
+            || request.definition.is_some()
+            || request.timelock.is_some()
+        {
+            return Err(
+                "a batch takes client entropy per request, and no task definition or timelock"
+                    .to_string(),
+            );
+        }
+        if self.deterministic || self.drand.is_some() || self.vdf_iterations.is_some() {
+            return Err(
+                "batches are only served from fresh entropy, without VRF, drand or VDF".to_string(),
+            );
+        }
+        let total = entries
+            .iter()
+            .fold(0usize, |total, e| total.saturating_add(e.length));
+        let max = self.performer.length_policy().max;
+        if total > max {
+            return Err(format!(
+                "a batch draws at most {} bytes in total, not {}",
+                max, total
+            ));
+        }
+        let mut seen = HashSet::new();
+        for entry in entries {
+            if entry.task_id.is_empty() || entry.task_id.contains('/') {
+                return Err(
+                    "batched task IDs must be non-empty and must not contain '/'".to_string(),
+                );
+            }
+            if !seen.insert(entry.task_id.as_str()) {
+                return Err(format!("batch lists task {} twice", entry.task_id));
+            }
+            self.performer
+                .check_length(entry.length)
+                .map_err(|e| format!("batched task {}: {}", entry.task_id, e))?;
+            if let Some(client) = &entry.client_entropy {
+                let length = hex::decode(client)
+                    .map_err(|e| format!("batched task {}: {}", entry.task_id, e))?
+                    .len();
+                if length == 0 || length > MAX_CLIENT_ENTROPY {
+                    return Err(format!(
+                        "batched task {}: client entropy must be 1..={} bytes",
+                        entry.task_id, MAX_CLIENT_ENTROPY
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-queues tasks left in storage by a previous process.
+    ///
+    /// Tasks that were already attested are only resubmitted, so the value a
+    /// consumer may have seen is never replaced by a fresh one.
+    pub fn resume_pending(&self) -> Result<usize, String> {
+        let leftovers = self.storage.scan(PENDING_TASKS)?;
+        let mut resumed = 0;
+        for (key, value) in leftovers {
+            let pending: PendingTask = match serde_json::from_value(value) {
+                Ok(p) => p,
+                Err(e) => {
+                    warn!("Skipping unreadable pending task {}: {}", key, e);
+                    continue;
+                }
+            };
+            let task_id = pending.key();
+            let stage = pending.stage;
+            match self.enqueue(pending, None, None) {
+                Ok(()) => {
+                    info!("Resuming task {} from stage {:?}", task_id, stage);
+                    resumed += 1;
+                }
+                Err(e) => warn!("Could not resume task {}: {}", task_id, e),
+            }
+        }
+        Ok(resumed)
+    }
+
+    /// Stops admitting new tasks; tasks already running continue.
+    pub fn begin_shutdown(&self) {
+        let mut state = self.state.lock().expect("runner lock poisoned");
+        state.accepting = false;
+    }
+
+    /// Stops admitting new tasks until [`TaskRunner::resume`]; queued and
+    /// running tasks continue.
+    pub fn pause(&self) {
+        self.state.lock().expect("runner lock poisoned").paused = true;
+    }
+
+    /// Admits new tasks again after [`TaskRunner::pause`].
+    pub fn resume(&self) {
+        self.state.lock().expect("runner lock poisoned").paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().expect("runner lock poisoned").paused
+    }
+
+    /// Returns the number of tasks waiting for a worker.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns the performer generating the values.
+    pub fn performer(&self) -> &RngPerformer {
+        &self.performer
+    }
+
+    /// Returns the task definitions requests may name.
+    pub fn definitions(&self) -> &DefinitionRegistry {
+        &self.definitions
+    }
+
+    /// Returns the attester currently signing new tasks.
+    pub fn attester(&self) -> Arc<RngAttester> {
+        if let Some(epochs) = &self.epochs {
+            self.follow_epoch(epochs);
+        }
+        Arc::clone(&self.attester.read().expect("attester lock poisoned"))
+    }
+
+    /// Moves to the key of the epoch now is in, if the attester lags behind.
+    fn follow_epoch(&self, epochs: &EpochKeys) {
+        let epoch = epochs.epoch_at(unix_millis());
+        let current =
+            |attester: &RngAttester| attester.epoch_certificate().map(|c| c.epoch) >= Some(epoch);
+        if current(&self.attester.read().expect("attester lock poisoned")) {
+            return;
+        }
+        let mut attester = self.attester.write().expect("attester lock poisoned");
+        if current(&attester) {
+            return;
+        }
+        match epochs.enter(epoch) {
+            Ok((key, certificate)) => {
+                *attester = Arc::new(attester.for_epoch(key, certificate));
+                if let Some(pool) = &self.pool {
+                    // Pooled values were signed with the previous epoch's key.
+                    pool.clear();
+                }
+            }
+            Err(e) => warn!("Failed to enter epoch {}: {}", epoch, e),
+        }
+    }
+
+    /// Returns the public key outputs are currently attested with.
+    pub fn public_key(&self) -> VerifyingKey {
+        *self.attester().get_public_key()
+    }
+
+    /// Replaces the ed25519 attestation key with a freshly generated one and
+    /// returns the previous and new public keys. A secp256k1 key is kept.
+    ///
+    /// Tasks already attesting finish with the old key, and pre-generated
+    /// outputs are discarded. With the VRF enabled, outputs for a given task
+    /// ID change along with the key.
+    pub fn rotate_key(&self) -> Result<(VerifyingKey, VerifyingKey), String> {
+        if self.epochs.is_some() {
+            return Err("Attestation keys follow epochs and cannot be rotated by hand".to_string());
+        }
+        let mut attester = self.attester.write().expect("attester lock poisoned");
+        let fresh = Arc::new(attester.rotated()?);
+        let next = *fresh.get_public_key();
+        let previous = *attester.get_public_key();
+        *attester = fresh;
+        if let Some(pool) = &self.pool {
+            // Pooled values were signed with the old key.
+            pool.clear();
+        }
+        Ok((previous, next))
+    }
+
+    /// Drops every queued task that has not been attested yet and returns
+    /// their IDs.
+    ///
+    /// Attested tasks stay queued: their value may already have been seen and
+    /// must be resubmitted rather than discarded.
+    pub fn flush_queue(&self) -> Vec<String> {
+        let mut flushed = Vec::new();
+        for (priority, job) in self.queue.drain() {
+            let job = if job.task.outcome.is_some() {
+                match self.queue.push(priority, job) {
+                    Ok(()) => continue,
+                    Err(job) => job,
+                }
+            } else {
+                job
+            };
+            let task_id = job.task.key();
+            if let Err(e) = self.storage.delete(PENDING_TASKS, &task_id) {
+                warn!("Failed to clear pending task {}: {}", task_id, e);
+            }
+            self.record_event(&task_id, TaskStage::Failed, Some("flushed by operator"));
+            self.metrics
+                .inc_counter("rng_tasks_total", &[("outcome", "flushed")], 1);
+            self.release(&task_id);
+            if let Some(reply) = job.reply {
+                let _ = reply.send(Err(TaskError::Failed(
+                    "task flushed by operator".to_string(),
+                )));
+            }
+            flushed.push(task_id);
+        }
+        self.publish_queue_depth();
+        flushed
+    }
+
+    /// Returns the IDs and stages of the tasks currently in flight.
+    pub fn in_flight(&self) -> Vec<(String, TaskStage)> {
+        let state = self.state.lock().expect("runner lock poisoned");
+        state
+            .in_flight
+            .iter()
+            .map(|(id, stage)| (id.clone(), *stage))
+            .collect()
+    }
+
+    /// Waits up to `timeout` for in-flight tasks to finish.
+    ///
+    /// Call [`TaskRunner::begin_shutdown`] first, otherwise new tasks can keep
+    /// the runner busy indefinitely.
+    pub fn drain(&self, timeout: Duration) -> DrainSummary {
+        let started = Instant::now();
+        let mut state = self.state.lock().expect("runner lock poisoned");
+        let finished_before = state.finished;
+        while !state.in_flight.is_empty() {
+            let remaining = timeout.saturating_sub(started.elapsed());
+            if remaining.is_zero() {
+                break;
+            }
+            state = self
+                .idle
+                .wait_timeout(state, remaining)
+                .expect("runner lock poisoned")
+                .0;
+        }
+        DrainSummary {
+            finished: state.finished - finished_before,
+            persisted: state
+                .in_flight
+                .iter()
+                .map(|(id, stage)| (id.clone(), *stage))
+                .collect(),
+            elapsed: started.elapsed(),
+        }
+    }
+
+    /// Registers `task` as in flight, persists it and places it on the queue.
+    fn enqueue(
+        &self,
+        mut task: PendingTask,
+        reply: Option<Reply>,
+        trace: Option<SpanContext>,
+    ) -> Result<(), TaskError> {
+        self.admit(&task.key(), TaskStage::Queued)?;
+
+        let task_id = task.key();
+        let priority = task.priority;
+        // Checked once in flight: a task fulfilled concurrently is recorded
+        // before it leaves the in-flight set.
+        if task.outcome.is_none() {
+            match self.check_fulfilled(&task) {
+                Ok(None) => {}
+                Ok(Some(outcome)) => {
+                    self.release(&task_id);
+                    if let Some(reply) = reply {
+                        let _ = reply.send(Ok(outcome));
+                    }
+                    return Ok(());
+                }
+                Err(e) => {
+                    self.release(&task_id);
+                    return Err(e);
+                }
+            }
+        }
+        if task.outcome.is_none() {
+            if let Err(e) = self.advance(&mut task, TaskStage::Queued) {
+                self.release(&task_id);
+                return Err(TaskError::Failed(e));
+            }
+        }
+        task.queued_at = Some(Instant::now());
+        let job = Job { task, reply, trace };
+        if self.queue.push(priority, job).is_err() {
+            // Nothing has been generated yet, so the task can simply be forgotten.
+            if let Err(e) = self.storage.delete(PENDING_TASKS, &task_id) {
+                warn!("Failed to clear pending task {}: {}", task_id, e);
+            }
+            self.release(&task_id);
+            return Err(TaskError::Rejected("task queue is full".to_string()));
+        }
+        self.publish_queue_depth();
+        Ok(())
+    }
+
+    /// The stored outcome if `task` was fulfilled before and is to be
+    /// replayed; an error if it is to be refused.
+    fn check_fulfilled(&self, task: &PendingTask) -> Result<Option<TaskOutcome>, TaskError> {
+        let Some(fulfilled) = &self.fulfilled else {
+            return Ok(None);
+        };
+        let entries: Vec<String> = task.batch_keys().into_iter().map(|(key, _)| key).collect();
+        let duplicate = fulfilled
+            .check(&task.key(), task.length, &entries)
+            .map_err(TaskError::Failed)?;
+        match duplicate {
+            None => Ok(None),
+            Some(Duplicate::Replay(outcome)) => {
+                info!(
+                    "Task {} was already fulfilled; replaying its outcome",
+                    task.key()
+                );
+                self.metrics
+                    .inc_counter("rng_duplicate_tasks_total", &[("action", "replayed")], 1);
+                Ok(Some(*outcome))
+            }
+            Some(Duplicate::Reject(reason)) => {
+                self.metrics
+                    .inc_counter("rng_duplicate_tasks_total", &[("action", "rejected")], 1);
+                Err(TaskError::Rejected(reason))
+            }
+        }
+    }
+
+    /// Records `task` as fulfilled with `outcome`; must precede deleting
+    /// its pending record, which is what a restart would resume from.
+    fn mark_fulfilled(&self, task: &PendingTask, outcome: &TaskOutcome) {
+        let Some(fulfilled) = &self.fulfilled else {
+            return;
+        };
+        if let Err(e) = fulfilled.record(&task.key(), outcome, task.length, &task.batch_keys()) {
+            warn!("Failed to record task {} as fulfilled: {}", task.key(), e);
+        }
+    }
+
+    fn publish_queue_depth(&self) {
+        for priority in [Priority::Api, Priority::OnChain] {
+            self.metrics.set_gauge(
+                "rng_queue_depth",
+                &[("priority", priority.as_str())],
+                self.queue.depth(priority) as f64,
+            );
+        }
+    }
+
+    /// Registers `key` as in flight at `stage`, unless serving is paused,
+    /// shutdown has begun or the task is in flight already.
+    fn admit(&self, key: &str, stage: TaskStage) -> Result<(), TaskError> {
+        let mut state = self.state.lock().expect("runner lock poisoned");
+        if !state.accepting {
+            return Err(TaskError::ShuttingDown);
+        }
+        if state.paused {
+            return Err(TaskError::Paused);
+        }
+        if state.in_flight.contains_key(key) {
+            return Err(TaskError::Rejected(format!(
+                "task {} is already in flight",
+                key
+            )));
+        }
+        state.in_flight.insert(key.to_string(), stage);
+        self.metrics
+            .set_gauge("rng_tasks_in_flight", &[], state.in_flight.len() as f64);
+        Ok(())
+    }
+
+    fn release(&self, task_id: &str) {
+        let mut state = self.state.lock().expect("runner lock poisoned");
+        state.in_flight.remove(task_id);
+        state.finished += 1;
+        self.metrics
+            .set_gauge("rng_tasks_in_flight", &[], state.in_flight.len() as f64);
+        self.idle.notify_all();
+    }
+
+    fn run(
+        &self,
+        mut task: PendingTask,
+        trace: Option<SpanContext>,
+    ) -> Result<TaskOutcome, TaskError> {
+        let queued_at = task.queued_at.unwrap_or_else(Instant::now);
+        task.timings.queue_wait_ms = millis_since(queued_at);
+        let mut span = self
+            .tracer
+            .start("rng.task", SpanKind::Internal, trace.as_ref());
+        span.set_attribute("rng.task_id", &task.task_id);
+        if let Some(tenant) = &task.tenant {
+            span.set_attribute("rng.tenant", tenant);
+        }
+        if let Some(definition) = &task.definition {
+            span.set_attribute("rng.task_definition", definition);
+        }
+        span.set_int_attribute("rng.length", task.length as i64);
+        span.set_attribute("rng.priority", task.priority.as_str());
+        let mut result = self.process(task, &span);
+        match &mut result {
+            Ok(outcome) => self.observe_timings(outcome, queued_at),
+            Err(e) => span.set_error(&e.to_string()),
+        }
+        self.tracer.end(span);
+        result
+    }
+
+    /// Completes the timings of `outcome` and records them in the stage
+    /// metrics and against the latency SLO.
+    fn observe_timings(&self, outcome: &mut TaskOutcome, queued_at: Instant) {
+        let Some(timings) = outcome.timings.as_mut() else {
+            return;
+        };
+        let total = millis_since(queued_at);
+        timings.total_ms = Some(total);
+        let stages = timings.stages();
+        for (stage, ms) in &stages {
+            self.metrics
+                .observe("rng_task_stage_ms", &[("stage", stage)], *ms);
+        }
+        self.metrics.observe("rng_task_latency_ms", &[], total);
+        let Some(slo) = self.latency_slo else {
+            return;
+        };
+        if total <= slo.as_secs_f64() * 1000.0 {
+            self.metrics
+                .inc_counter("rng_latency_slo_total", &[("outcome", "met")], 1);
+            return;
+        }
+        self.metrics
+            .inc_counter("rng_latency_slo_total", &[("outcome", "missed")], 1);
+        if let Some((stage, ms)) = stages.into_iter().max_by(|a, b| a.1.total_cmp(&b.1)) {
+            warn!(
+                "Task {} took {:.1}ms, over the {:?} latency SLO; slowest step: {} ({:.1}ms)",
+                outcome.task_id, total, slo, stage, ms
+            );
+        }
+    }
+
+    /// Percentiles of the task latencies and of each step, and how the
+    /// tasks fared against the SLO.
+    pub fn latency(&self) -> LatencyReport {
+        let percentiles = |name: &str, labels: &[(&str, &str)]| Percentiles {
+            p50: self.metrics.quantile(name, labels, 0.5),
+            p90: self.metrics.quantile(name, labels, 0.9),
+            p99: self.metrics.quantile(name, labels, 0.99),
+        };
+        LatencyReport {
+            slo_ms: self.latency_slo.map(|slo| slo.as_secs_f64() * 1000.0),
+            met: self
+                .metrics
+                .counter("rng_latency_slo_total", &[("outcome", "met")]),
+            missed: self
+                .metrics
+                .counter("rng_latency_slo_total", &[("outcome", "missed")]),
+            total: percentiles("rng_task_latency_ms", &[]),
+            stages: STAGES
+                .into_iter()
+                .map(|stage| (stage, percentiles("rng_task_stage_ms", &[("stage", stage)])))
+                .filter(|(_, p)| p.p50.is_some())
+                .collect(),
+        }
+    }
+
+    fn process(&self, mut task: PendingTask, span: &Span) -> Result<TaskOutcome, TaskError> {
+        if let Some(deadline) = task.past_deadline() {
+            return Err(self.expire(&task, deadline));
+        }
+
+        let mut outcome = match task.outcome.clone() {
+            Some(outcome) => outcome,
+            None => match self.generate_and_attest(&mut task, &span.context) {
+                Ok(mut outcome) => {
+                    self.inject_faults(&mut outcome);
+                    outcome
+                }
+                Err(e) => {
+                    self.fail(&task, &e);
+                    return Err(TaskError::Failed(e));
+                }
+            },
+        };
+
+        // Generation may have taken a while; re-check before spending gas.
+        if let Some(deadline) = task.past_deadline() {
+            return Err(self.expire(&task, deadline));
+        }
+
+        // A resumed task is timed afresh, from its new admission.
+        outcome.timings = Some(task.timings);
+        task.outcome = Some(outcome.clone());
+        if let Some(release) = task.timelock {
+            return self.seal(task, &outcome, release);
+        }
+        self.advance(&mut task, TaskStage::Submitting)
+            .map_err(TaskError::Failed)?;
+        if self.dry_run || task.dry_run {
+            return self.finish_dry_run(&task, outcome, span);
+        }
+        let started = Instant::now();
+        let mut submitting =
+            self.tracer
+                .start("rng.submit", SpanKind::Internal, Some(&span.context));
+        if self.tracer.is_enabled() {
+            outcome.trace = Some(submitting.context);
+        }
+        let submitted = self.submitter.submit(&outcome);
+        if let Err(e) = &submitted {
+            submitting.set_error(e);
+        }
+        self.tracer.end(submitting);
+        TaskTimings::add(&mut task.timings.submission_ms, started);
+        outcome.timings = Some(task.timings);
+        if let Err(e) = submitted {
+            // Keep the pending record: the attested value must be resubmitted,
+            // not regenerated, on the next attempt.
+            self.record_event(&task.key(), TaskStage::Failed, Some(&e));
+            self.metrics
+                .inc_counter("rng_tasks_total", &[("outcome", "submit_failed")], 1);
+            return Err(TaskError::Failed(e));
+        }
+
+        if let Some(publisher) = &self.publisher {
+            match serde_json::to_value(&outcome) {
+                Ok(data) => publisher.publish(publish::ATTESTATION_SCHEMA, &task.key(), data),
+                Err(e) => warn!("Failed to encode task {} for publishing: {}", task.key(), e),
+            }
+        }
+        self.mark_fulfilled(&task, &outcome);
+        let record = json!({ "completed_at": unix_millis(), "outcome": outcome });
+        if let Err(e) = self
+            .storage
+            .put(&task.collection(ATTESTATIONS), &task.task_id, record)
+        {
+            warn!(
+                "Failed to archive attestation for task {}: {}",
+                task.key(),
+                e
+            );
+        }
+        self.storage
+            .delete(PENDING_TASKS, &task.key())
+            .map_err(TaskError::Failed)?;
+        self.set_stage(&task.key(), TaskStage::Completed);
+        self.record_event(&task.key(), TaskStage::Completed, None);
+        self.metrics
+            .inc_counter("rng_tasks_total", &[("outcome", "completed")], 1);
+        Ok(outcome)
+    }
+
+    /// Stores `outcome` sealed until `release` in place of submitting it, and
+    /// answers with the commitment.
+    fn seal(
+        &self,
+        task: PendingTask,
+        outcome: &TaskOutcome,
+        release: Release,
+    ) -> Result<TaskOutcome, TaskError> {
+        let key = task.key();
+        // Resumed after a restart: the commitment may be out already.
+        let stored = self
+            .storage
+            .get(TIMELOCKS, &key)
+            .map_err(TaskError::Failed)?;
+        let commitment = match stored.map(serde_json::from_value::<SealedTask>) {
+            Some(Ok(sealed)) => sealed.commitment,
+            Some(Err(e)) => return Err(TaskError::Failed(e.to_string())),
+            None => {
+                let (commitment, secret) =
+                    Commitment::seal(outcome, release, unix_millis(), &self.attester())
+                        .map_err(TaskError::Failed)?;
+                let sealed = SealedTask {
+                    commitment: commitment.clone(),
+                    key: hex::encode(secret),
+                    task: PendingTask {
+                        stage: TaskStage::Sealed,
+                        ..task.clone()
+                    },
+                    released_at: None,
+                };
+                let value = serde_json::to_value(&sealed).map_err(|e| e.to_string());
+                value
+                    .and_then(|value| self.storage.put(TIMELOCKS, &key, value))
+                    .and_then(|()| self.storage.flush())
+                    .map_err(TaskError::Failed)?;
+                commitment
+            }
+        };
+        let sealed = TaskOutcome {
+            task_id: task.task_id.clone(),
+            tenant: task.tenant.clone(),
+            timelock: Some(commitment),
+            timings: outcome.timings,
+            ..Default::default()
+        };
+        self.mark_fulfilled(&task, &sealed);
+        self.storage
+            .delete(PENDING_TASKS, &key)
+            .map_err(TaskError::Failed)?;
+        self.set_stage(&key, TaskStage::Sealed);
+        self.record_event(&key, TaskStage::Sealed, None);
+        self.metrics
+            .inc_counter("rng_tasks_total", &[("outcome", "sealed")], 1);
+        info!("Task {} sealed until its release", key);
+        Ok(sealed)
+    }
+
+    /// The commitment of sealed task `task_key` ([`tenants::task_key`]), with
+    /// its key once released.
+    pub fn timelock(&self, task_key: &str) -> Result<Option<TimelockStatus>, String> {
+        let Some(value) = self.storage.get(TIMELOCKS, task_key)? else {
+            return Ok(None);
+        };
+        let sealed: SealedTask = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        Ok(Some(TimelockStatus {
+            commitment: sealed.commitment,
+            key: sealed.released_at.map(|_| sealed.key),
+            released_at: sealed.released_at,
+        }))
+    }
+
+    /// Reveals the key of every sealed task whose release has come and queues
+    /// its outcome for submission; returns how many were released.
+    pub fn release_timelocks(&self) -> Result<usize, String> {
+        let now = unix_millis();
+        let mut height = None;
+        let mut released = 0;
+        for (key, value) in self.storage.scan(TIMELOCKS)? {
+            let mut sealed: SealedTask = match serde_json::from_value(value) {
+                Ok(sealed) => sealed,
+                Err(e) => {
+                    warn!("Skipping unreadable timelock {}: {}", key, e);
+                    continue;
+                }
+            };
+            let release = sealed.commitment.release;
+            if sealed.released_at.is_some() {
+                continue;
+            }
+            if release.block.is_some() && height.is_none() {
+                height = Some(self.submitter.chain_height()?);
+            }
+            if !release.is_due(now, height.flatten()) {
+                continue;
+            }
+            sealed.released_at = Some(now);
+            let task = PendingTask {
+                stage: TaskStage::Submitting,
+                timelock: None,
+                ..sealed.task.clone()
+            };
+            let pending = serde_json::to_value(&task).map_err(|e| e.to_string())?;
+            self.storage.put(PENDING_TASKS, &key, pending)?;
+            let value = serde_json::to_value(&sealed).map_err(|e| e.to_string())?;
+            self.storage.put(TIMELOCKS, &key, value)?;
+            self.storage.flush()?;
+            info!("Released timelocked task {}", key);
+            self.metrics
+                .inc_counter("rng_timelocks_released_total", &[], 1);
+            released += 1;
+            // Submitted like a resumed task; on the next start if not now.
+            if let Err(e) = self.enqueue(task, None, None) {
+                warn!("Could not queue released task {}: {}", key, e);
+            }
+        }
+        Ok(released)
+    }
+
+    /// Releases due timelocks every `interval`, forever.
+    pub fn run_timelocks(&self, interval: Duration) {
+        loop {
+            if let Err(e) = self.release_timelocks() {
+                warn!("Failed to release timelocks: {}", e);
+            }
+            thread::sleep(interval);
+        }
+    }
+
+    /// Records what would have been submitted for `outcome` in place of
+    /// submitting it.
+    fn finish_dry_run(
+        &self,
+        task: &PendingTask,
+        mut outcome: TaskOutcome,
+        span: &Span,
+    ) -> Result<TaskOutcome, TaskError> {
+        let submission = self
+            .tracer
+            .in_span("rng.dry_run", &span.context, || {
+                self.submitter.dry_run(&outcome)
+            })
+            .map_err(|e| {
+                self.record_event(&task.key(), TaskStage::Failed, Some(&e));
+                self.metrics
+                    .inc_counter("rng_tasks_total", &[("outcome", "submit_failed")], 1);
+                TaskError::Failed(e)
+            })?;
+        info!("Task {} attested (dry run, not submitted)", task.key());
+        let record = json!({
+            "recorded_at": unix_millis(),
+            "outcome": outcome,
+            "submission": submission,
+        });
+        self.storage
+            .put(&task.collection(DRY_RUNS), &task.task_id, record)
+            .and_then(|()| self.storage.delete(PENDING_TASKS, &task.key()))
+            .map_err(TaskError::Failed)?;
+        self.set_stage(&task.key(), TaskStage::Completed);
+        self.record_event(&task.key(), TaskStage::Completed, None);
+        self.metrics
+            .inc_counter("rng_tasks_total", &[("outcome", "dry_run")], 1);
+        outcome.dry_run = Some(submission);
+        Ok(outcome)
+    }
+
+    fn generate_and_attest(
+        &self,
+        task: &mut PendingTask,
+        trace: &SpanContext,
+    ) -> Result<TaskOutcome, String> {
+        if let Some(entries) = task.batch.clone() {
+            return self.generate_batch(task, &entries, trace);
+        }
+        if let Some(outcome) = self.claim_pooled(task)? {
+            return Ok(outcome);
+        }
+        let definition = match &task.definition {
+            Some(id) => Some(
+                self.definitions
+                    .get(id)
+                    .ok_or_else(|| format!("Task definition {} is no longer configured", id))?,
+            ),
+            None => None,
+        };
+        let deterministic = match definition.as_ref().and_then(|d| d.derivation) {
+            Some(derivation) => derivation == Derivation::Vrf,
+            None => self.deterministic,
+        };
+        // One key for the whole task, even if it is rotated meanwhile.
+        let attester = self.attester();
+        self.advance(task, TaskStage::Generating)?;
+        let started = Instant::now();
+        let (mut payload, sources) = self.tracer.in_span("rng.generate", trace, || {
+            Ok(if deterministic {
+                (
+                    attester.vrf_payload(task.key().as_bytes(), task.length),
+                    vec!["vrf".to_string()],
+                )
+            } else {
+                let (random_number, sources) = self.fresh_entropy(task.length)?;
+                (
+                    AttestationPayload::new(random_number).with_postprocess(self.postprocess.ids()),
+                    sources,
+                )
+            })
+        })?;
+        TaskTimings::add(&mut task.timings.entropy_ms, started);
+
+        self.advance(task, TaskStage::Attesting)?;
+        if let Some(client) = &task.client_entropy {
+            let client =
+                hex::decode(client).map_err(|e| format!("Invalid client entropy: {}", e))?;
+            payload = payload.with_client_entropy(client);
+        }
+        if let Some(client) = &self.drand {
+            let started = Instant::now();
+            let beacon = self
+                .tracer
+                .in_span("drand.fetch", trace, || client.fetch(None))?;
+            TaskTimings::add(&mut task.timings.entropy_ms, started);
+            payload = payload.with_drand(client.chain_hash(), beacon);
+        }
+        if let Some(iterations) = self.vdf_iterations {
+            self.advance(task, TaskStage::Delaying)?;
+            let started = Instant::now();
+            payload = self
+                .tracer
+                .in_span("rng.vdf", trace, || payload.with_vdf(iterations))?;
+            TaskTimings::add(&mut task.timings.delay_ms, started);
+        }
+        if let Some(domain) = &task.domain {
+            payload = payload.with_domain(domain);
+        }
+        if let Some(definition) = &definition {
+            payload = payload
+                .with_definition(&definition.id)
+                .with_hash(definition.hash);
+        }
+        let mut payload = self.finish_payload(payload, &attester, definition.as_deref(), sources);
+        // A value released by time is of no use before its release.
+        if let (Some(at), Some(validity)) = (task.timelock.and_then(|r| r.at), payload.validity) {
+            let ttl = validity.expires_at - validity.not_before;
+            payload = payload.with_validity(at, at.saturating_add(ttl));
+        }
+        let timings = &mut task.timings;
+        let mut attestation = self
+            .tracer
+            .in_span("rng.sign", trace, || self.sign(&attester, payload, timings))?;
+
+        let signing = definition.as_ref().and_then(|d| d.signing);
+        if let Some(signing) = signing {
+            // The attester signs with every key it holds; drop the rest.
+            if !signing.secp256k1 {
+                attestation.secp256k1_signature = None;
+            }
+            if !signing.schnorr {
+                attestation.schnorr_signature = None;
+            }
+        }
+        let mut outcome = TaskOutcome::from_attestation(&task.task_id, &attestation, &attester);
+        if let Some(signing) = signing {
+            if !signing.secp256k1 {
+                outcome.secp256k1_address = None;
+            }
+            if !signing.schnorr {
+                outcome.schnorr_public_key = None;
+            }
+        }
+        outcome.tenant = task.tenant.clone();
+        let started = Instant::now();
+        self.attach_abi(&mut outcome, &attestation, &attester)?;
+        TaskTimings::add(&mut task.timings.signing_ms, started);
+        Ok(outcome)
+    }
+
+    /// Splits one draw of fresh entropy between `entries` and attests the
+    /// Merkle root over them; see [`crate::batch`].
+    fn generate_batch(
+        &self,
+        task: &mut PendingTask,
+        entries: &[BatchEntry],
+        trace: &SpanContext,
+    ) -> Result<TaskOutcome, String> {
+        let attester = self.attester();
+        self.advance(task, TaskStage::Generating)?;
+        let total = entries.iter().map(|e| e.length).sum();
+        let started = Instant::now();
+        let (entropy, mut sources) = self
+            .tracer
+            .in_span("rng.generate", trace, || self.fresh_entropy(total))?;
+        TaskTimings::add(&mut task.timings.entropy_ms, started);
+
+        self.advance(task, TaskStage::Attesting)?;
+        let mut rest = entropy.as_slice();
+        let mut items = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let (share, tail) = rest.split_at(entry.length);
+            rest = tail;
+            let item = BatchItem {
+                task_id: entry.task_id.clone(),
+                index: 0,
+                random_number: hex::encode(share),
+                operator_entropy: None,
+                client_entropy: None,
+                proof: Vec::new(),
+            };
+            items.push(match &entry.client_entropy {
+                Some(client) => {
+                    let client = hex::decode(client)
+                        .map_err(|e| format!("Invalid client entropy: {}", e))?;
+                    BatchItem {
+                        random_number: hex::encode(RngPerformer::mix_client_entropy(
+                            share, &client,
+                        )),
+                        operator_entropy: Some(item.random_number),
+                        client_entropy: Some(hex::encode(client)),
+                        ..item
+                    }
+                }
+                None => item,
+            });
+        }
+        let root = batch::commit(&mut items)?;
+        let mut payload = AttestationPayload::new(root.to_vec())
+            .with_kind(BATCH_ROOT_KIND)
+            .with_postprocess(self.postprocess.ids());
+        if let Some(domain) = &task.domain {
+            payload = payload.with_domain(domain);
+        }
+        if items.iter().any(|item| item.client_entropy.is_some()) {
+            sources.push("client".to_string());
+        }
+        let payload = self.finish_payload(payload, &attester, None, sources);
+        let timings = &mut task.timings;
+        let attestation = self
+            .tracer
+            .in_span("rng.sign", trace, || self.sign(&attester, payload, timings))?;
+        let mut outcome = TaskOutcome::from_attestation(&task.task_id, &attestation, &attester);
+        outcome.tenant = task.tenant.clone();
+        outcome.batch = Some(items);
+        let started = Instant::now();
+        self.attach_abi(&mut outcome, &attestation, &attester)?;
+        TaskTimings::add(&mut task.timings.signing_ms, started);
+        self.metrics
+            .inc_counter("rng_batch_items_total", &[], entries.len() as u64);
+        Ok(outcome)
+    }
+
+    /// Attaches the ABI form of `attestation` to `outcome` if enabled and
+    /// the outcome carries a secp256k1 address; see [`crate::abi`].
+    fn attach_abi(
+        &self,
+        outcome: &mut TaskOutcome,
+        attestation: &Attestation,
+        attester: &RngAttester,
+    ) -> Result<(), String> {
+        let operator = attester.secp256k1_address();
+        let (true, Some(operator), Some(_)) = (self.abi, operator, &outcome.secp256k1_address)
+        else {
+            return Ok(());
+        };
+        let tuple = AbiAttestation::from_payload(&outcome.task_id, &attestation.payload, operator)?;
+        let signature = attester
+            .sign_ethereum(&tuple.digest())?
+            .ok_or("no secp256k1 key to sign the ABI form with")?;
+        outcome.abi = Some(tuple.to_outcome(&signature));
+        Ok(())
+    }
+
+    /// Fresh operator entropy and the sources it was drawn from.
+    fn fresh_entropy(&self, length: usize) -> Result<(Vec<u8>, Vec<String>), String> {
+        let mut sources = vec!["os".to_string()];
+        let bytes = self.postprocess.apply(
+            &mut |n| match &self.entropy {
+                Some(mixer) => {
+                    let gathered = mixer.gather(n)?;
+                    sources = gathered.sources;
+                    Ok(gathered.bytes)
+                }
+                None => self.performer.generate_random_number(n),
+            },
+            length,
+        )?;
+        Ok((bytes, sources))
+    }
+
+    /// Adds the metadata and validity window every attestation carries,
+    /// following `definition` where it overrides the operator's settings.
+    /// `entropy_sources` names where the operator's own contribution came from.
+    fn finish_payload(
+        &self,
+        mut payload: AttestationPayload,
+        attester: &RngAttester,
+        definition: Option<&TaskDefinition>,
+        mut entropy_sources: Vec<String>,
+    ) -> AttestationPayload {
+        if let Some(config) = &self.config {
+            let config = config.current();
+            if payload.client_entropy.is_some() {
+                entropy_sources.push("client".to_string());
+            }
+            if payload.drand.is_some() {
+                entropy_sources.push("drand".to_string());
+            }
+            payload = payload.with_metadata(OperatorMetadata {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                signing_scheme: match definition.and_then(|d| d.signing) {
+                    Some(signing) => attester::signing_scheme(
+                        signing.secp256k1 && attester.secp256k1_address().is_some(),
+                        signing.schnorr && attester.schnorr_public_key().is_some(),
+                    ),
+                    None => attester.signing_scheme(),
+                }
+                .to_string(),
+                entropy_sources,
+                config_hash: config.digest(),
+                chain_id: config.network.chain_id,
+            });
+        }
+        if let Some(ttl) = definition.and_then(|d| d.expiry).or(self.validity) {
+            let now = unix_millis();
+            payload = payload.with_validity(now, now.saturating_add(ttl.as_millis() as u64));
+        }
+        payload
+    }
+
+    /// Signs `payload`, adding the time spent hashing and signing it to
+    /// `timings`.
+    fn sign(
+        &self,
+        attester: &Arc<RngAttester>,
+        payload: AttestationPayload,
+        timings: &mut TaskTimings,
+    ) -> Result<Attestation, String> {
+        #[cfg(feature = "chaos")]
+        if let Some(faults) = &self.faults {
+            faults.delay_signing();
+        }
+        let started = Instant::now();
+        let Some(signer) = &self.signer else {
+            // `RngAttester::attest_payload`, with the digest timed apart.
+            let payload = attester.prepare_payloads(vec![payload])?.remove(0);
+            TaskTimings::add(&mut timings.signing_ms, started);
+            let started = Instant::now();
+            let digest = payload.digest();
+            TaskTimings::add(&mut timings.hashing_ms, started);
+            let started = Instant::now();
+            let attestation = attester.sign_prepared(payload, &digest);
+            TaskTimings::add(&mut timings.signing_ms, started);
+            return attestation;
+        };
+        let attestation = signer.sign(Arc::clone(attester), payload);
+        TaskTimings::add(&mut timings.signing_ms, started);
+        attestation
+    }
+
+    /// Corrupts a freshly attested outcome when fault injection says so.
+    #[cfg(feature = "chaos")]
+    fn inject_faults(&self, outcome: &mut TaskOutcome) {
+        if let Some(faults) = &self.faults {
+            faults.corrupt_salt(outcome);
+        }
+    }
+
+    #[cfg(not(feature = "chaos"))]
+    fn inject_faults(&self, _outcome: &mut TaskOutcome) {}
+
+    /// Serves `task` from the pool when it asks for nothing a pre-generated
+    /// value can't provide.
+    fn claim_pooled(&self, task: &mut PendingTask) -> Result<Option<TaskOutcome>, String> {
+        let Some(pool) = &self.pool else {
+            return Ok(None);
+        };
+        if self.deterministic
+            || self.drand.is_some()
+            || self.vdf_iterations.is_some()
+            || task.client_entropy.is_some()
+            || task.domain.is_some()
+            || task.definition.is_some()
+            || task.timelock.is_some()
+            || task.batch.is_some()
+            || task.length != pool.length()
+        {
+            return Ok(None);
+        }
+        // Skip values signed before a key rotation that raced a refill.
+        let current = self.attester();
+        let output = loop {
+            match pool.take(unix_millis()) {
+                Some(output) if !Arc::ptr_eq(&output.attester, &current) => continue,
+                output => break output,
+            }
+        };
+        let Some(output) = output else {
+            self.metrics
+                .inc_counter("rng_pool_claims_total", &[("outcome", "miss")], 1);
+            return Ok(None);
+        };
+        self.metrics
+            .inc_counter("rng_pool_claims_total", &[("outcome", "hit")], 1);
+        self.metrics
+            .set_gauge("rng_pool_available", &[], pool.available() as f64);
+        self.advance(task, TaskStage::Attesting)?;
+        let binding = output.bind(&task.task_id)?;
+        let mut outcome =
+            TaskOutcome::from_attestation(&task.task_id, &output.attestation, &output.attester);
+        outcome.binding = Some(hex::encode(binding.to_bytes()));
+        self.attach_abi(&mut outcome, &output.attestation, &output.attester)?;
+        Ok(Some(outcome))
+    }
+
+    /// Generates and attests outputs until the pool is full; returns how many
+    /// were added.
+    pub fn refill_pool(&self) -> Result<usize, String> {
+        let Some(pool) = &self.pool else {
+            return Ok(0);
+        };
+        let mut added = 0;
+        while pool.available() < pool.size() {
+            let attester = self.attester();
+            let (random_number, sources) = self.fresh_entropy(pool.length())?;
+            let payload = AttestationPayload::new(random_number)
+                .with_slot(pool.reserve_slot()?)
+                .with_postprocess(self.postprocess.ids());
+            let payload = self.finish_payload(payload, &attester, None, sources);
+            let attestation = self.sign(&attester, payload, &mut TaskTimings::default())?;
+            pool.push(PooledOutput {
+                attester,
+                attestation,
+            });
+            added += 1;
+        }
+        self.metrics
+            .set_gauge("rng_pool_available", &[], pool.available() as f64);
+        Ok(added)
+    }
+
+    /// Tops the pool up every `pool.refill_interval`, forever.
+    pub fn run_pool(&self) {
+        let Some(pool) = &self.pool else {
+            return;
+        };
+        loop {
+            if let Err(e) = self.refill_pool() {
+                warn!("Failed to refill the randomness pool: {}", e);
+            }
+            thread::sleep(pool.refill_interval());
+        }
+    }
+
+    /// Moves `task` to `stage`, persisting it before any work for that stage starts.
+    fn advance(&self, task: &mut PendingTask, stage: TaskStage) -> Result<(), String> {
+        task.stage = stage;
+        let value = serde_json::to_value(&*task).map_err(|e| e.to_string())?;
+        self.storage.put(PENDING_TASKS, &task.key(), value)?;
+        self.set_stage(&task.key(), stage);
+        self.record_event(&task.key(), stage, None);
+        Ok(())
+    }
+
+    fn fail(&self, task: &PendingTask, error: &str) {
+        if let Err(e) = self.storage.delete(PENDING_TASKS, &task.key()) {
+            warn!("Failed to clear pending task {}: {}", task.key(), e);
+        }
+        self.set_stage(&task.key(), TaskStage::Failed);
+        self.record_event(&task.key(), TaskStage::Failed, Some(error));
+        self.metrics
+            .inc_counter("rng_tasks_total", &[("outcome", "failed")], 1);
+    }
+
+    /// Drops a task whose deadline passed and records a typed `expired` event.
+    fn expire(&self, task: &PendingTask, deadline: u64) -> TaskError {
+        if let Err(e) = self.storage.delete(PENDING_TASKS, &task.key()) {
+            warn!("Failed to clear pending task {}: {}", task.key(), e);
+        }
+        self.set_stage(&task.key(), TaskStage::Expired);
+        let error = TaskError::DeadlineExceeded { deadline };
+        self.record_event(&task.key(), TaskStage::Expired, Some(&error.to_string()));
+        self.metrics.inc_counter(
+            "rng_tasks_total",
+            &[("outcome", "expired"), ("priority", task.priority.as_str())],
+            1,
+        );
+        error
+    }
+
+    fn set_stage(&self, task_id: &str, stage: TaskStage) {
+        let mut state = self.state.lock().expect("runner lock poisoned");
+        if let Some(current) = state.in_flight.get_mut(task_id) {
+            *current = stage;
+        }
+    }
+
+    /// Appends to the lifecycle log of the task with [`tenants::task_key`]
+    /// `task_key`; also used for what happens to the task on-chain after it
+    /// completed.
+    pub fn record_event(&self, task_key: &str, stage: TaskStage, detail: Option<&str>) {
+        let (tenant, task_id) = tenants::split_key(task_key);
+        let at = unix_millis();
+        let seq = self.event_seq.fetch_add(1, Ordering::Relaxed);
+        let key = format!("{:013}-{:06}", at, seq % 1_000_000);
+        let event = json!({
+            "task_id": task_id,
+            "stage": stage,
+            "at": at,
+            "detail": detail,
+        });
+        let collection = tenants::collection(TASK_EVENTS, tenant);
+        if let Err(e) = self.storage.put(&collection, &key, event) {
+            warn!("Failed to record event for task {}: {}", task_key, e);
+        }
+    }
+}
+
+/// Milliseconds elapsed since `started`, with sub-millisecond precision.
+fn millis_since(started: Instant) -> f64 {
+    started.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Milliseconds since the Unix epoch.
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,320 @@
+// src/telemetry.rs
+
+//! Distributed tracing with OTLP export.
+//!
+//! A [`Tracer`] records spans for the lifetime of a request: the HTTP
+//! handler opens a server span (continuing the caller's W3C `traceparent`
+//! when one is sent), and the task pipeline adds child spans for generation,
+//! mixing, the VDF, signing and submission; the fulfillment tracker closes
+//! the trace with a span once the transaction is final. Finished spans are
+//! buffered and posted in batches as OTLP/HTTP JSON (`POST {endpoint}`,
+//! normally `http://collector:4318/v1/traces`), which Jaeger and Tempo accept
+//! directly. A disabled tracer hands out spans that are dropped on end.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde_json::{json, Value};
+
+use crate::config::{self, TracingConfig};
+use crate::metrics::Metrics;
+use crate::resilience::{CallError, Resilience};
+
+/// Finished spans kept while the collector is unreachable; older ones are
+/// dropped first.
+const MAX_BUFFERED_SPANS: usize = 4096;
+
+/// Identifies a span within a trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpanContext {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+}
+
+impl SpanContext {
+    /// Parses a W3C `traceparent` header (`00-<trace id>-<span id>-<flags>`).
+    pub fn from_traceparent(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let (version, trace, span) = (parts.next()?, parts.next()?, parts.next()?);
+        parts.next()?;
+        if version != "00" {
+            return None;
+        }
+        let trace_id: [u8; 16] = hex::decode(trace).ok()?.try_into().ok()?;
+        let span_id: [u8; 8] = hex::decode(span).ok()?.try_into().ok()?;
+        if trace_id == [0; 16] || span_id == [0; 8] {
+            return None;
+        }
+        Some(SpanContext { trace_id, span_id })
+    }
+
+    /// Formats the context as a sampled W3C `traceparent` header.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-01",
+            hex::encode(self.trace_id),
+            hex::encode(self.span_id)
+        )
+    }
+
+    pub fn trace_id_hex(&self) -> String {
+        hex::encode(self.trace_id)
+    }
+}
+
+/// Role of a span, as in the OTLP `SpanKind` enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    Internal = 1,
+    Server = 2,
+    Client = 3,
+}
+
+/// A span in progress; hand it back to [`Tracer::end`] when done.
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub context: SpanContext,
+    parent: Option<[u8; 8]>,
+    name: String,
+    kind: SpanKind,
+    start: u64,
+    attributes: Vec<(String, Value)>,
+    error: Option<String>,
+}
+
+impl Span {
+    /// Attaches a string attribute.
+    pub fn set_attribute(&mut self, key: &str, value: &str) {
+        self.attributes
+            .push((key.to_string(), json!({ "stringValue": value })));
+    }
+
+    /// Attaches an integer attribute.
+    pub fn set_int_attribute(&mut self, key: &str, value: i64) {
+        // OTLP JSON carries 64-bit integers as strings.
+        self.attributes
+            .push((key.to_string(), json!({ "intValue": value.to_string() })));
+    }
+
+    /// Marks the span as failed.
+    pub fn set_error(&mut self, message: &str) {
+        self.error = Some(message.to_string());
+    }
+
+    fn to_otlp(&self, end: u64) -> Value {
+        let attributes: Vec<Value> = self
+            .attributes
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect();
+        let status = match &self.error {
+            Some(message) => json!({ "code": 2, "message": message }),
+            None => json!({ "code": 1 }),
+        };
+        let mut span = json!({
+            "traceId": hex::encode(self.context.trace_id),
+            "spanId": hex::encode(self.context.span_id),
+            "name": self.name,
+            "kind": self.kind as u8,
+            "startTimeUnixNano": self.start.to_string(),
+            "endTimeUnixNano": end.to_string(),
+            "attributes": attributes,
+            "status": status,
+        });
+        if let Some(parent) = self.parent {
+            span["parentSpanId"] = json!(hex::encode(parent));
+        }
+        span
+    }
+}
+
+struct Exporter {
+    endpoint: String,
+    service_name: String,
+    interval: Duration,
+    agent: ureq::Agent,
+    resilience: Arc<Resilience>,
+}
+
+/// Creates spans and exports finished ones.
+pub struct Tracer {
+    exporter: Option<Exporter>,
+    metrics: Option<Arc<Metrics>>,
+    finished: Mutex<Vec<Value>>,
+}
+
+impl Tracer {
+    /// A tracer that records nothing.
+    pub fn disabled() -> Self {
+        Tracer {
+            exporter: None,
+            metrics: None,
+            finished: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Builds a tracer exporting to `tracing.endpoint`, or a disabled one
+    /// when tracing is off.
+    pub fn from_config(
+        config: &TracingConfig,
+        resilience: Arc<Resilience>,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self, String> {
+        if !config.enabled {
+            return Ok(Self::disabled());
+        }
+        Ok(Tracer {
+            exporter: Some(Exporter {
+                endpoint: config.endpoint.clone(),
+                service_name: config.service_name.clone(),
+                interval: config::parse_duration(&config.export_interval)?,
+                agent: ureq::AgentBuilder::new().build(),
+                resilience,
+            }),
+            metrics: Some(metrics),
+            finished: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.exporter.is_some()
+    }
+
+    /// Starts a span; without a parent it opens a new trace.
+    pub fn start(&self, name: &str, kind: SpanKind, parent: Option<&SpanContext>) -> Span {
+        let mut span_id = [0u8; 8];
+        OsRng.fill_bytes(&mut span_id);
+        let trace_id = match parent {
+            Some(parent) => parent.trace_id,
+            None => {
+                let mut id = [0u8; 16];
+                OsRng.fill_bytes(&mut id);
+                id
+            }
+        };
+        Span {
+            context: SpanContext { trace_id, span_id },
+            parent: parent.map(|p| p.span_id),
+            name: name.to_string(),
+            kind,
+            start: unix_nanos(),
+            attributes: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Finishes `span` and queues it for export.
+    pub fn end(&self, span: Span) {
+        if !self.is_enabled() {
+            return;
+        }
+        let otlp = span.to_otlp(unix_nanos());
+        let mut finished = self.finished.lock().expect("tracer lock poisoned");
+        if finished.len() >= MAX_BUFFERED_SPANS {
+            finished.remove(0);
+            if let Some(metrics) = &self.metrics {
+                metrics.inc_counter("rng_trace_spans_dropped_total", &[], 1);
+            }
+        }
+        finished.push(otlp);
+    }
+
+    /// Runs `f` inside an internal span named `name`, recording its error.
+    pub fn in_span<T>(
+        &self,
+        name: &str,
+        parent: &SpanContext,
+        f: impl FnOnce() -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut span = self.start(name, SpanKind::Internal, Some(parent));
+        let result = f();
+        if let Err(e) = &result {
+            span.set_error(e);
+        }
+        self.end(span);
+        result
+    }
+
+    /// Exports buffered spans every `tracing.export_interval`, forever.
+    pub fn run(&self) {
+        let Some(exporter) = &self.exporter else {
+            return;
+        };
+        loop {
+            thread::sleep(exporter.interval);
+            if let Err(e) = self.flush() {
+                warn!("Failed to export spans: {}", e);
+            }
+        }
+    }
+
+    /// Sends every buffered span to the collector. Spans are kept for the
+    /// next attempt if the export fails.
+    pub fn flush(&self) -> Result<(), String> {
+        let Some(exporter) = &self.exporter else {
+            return Ok(());
+        };
+        let spans = std::mem::take(&mut *self.finished.lock().expect("tracer lock poisoned"));
+        if spans.is_empty() {
+            return Ok(());
+        }
+        let count = spans.len();
+        let body = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [{
+                        "key": "service.name",
+                        "value": { "stringValue": exporter.service_name },
+                    }],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": "operator", "version": env!("CARGO_PKG_VERSION") },
+                    "spans": &spans,
+                }],
+            }],
+        });
+        let result = exporter.resilience.call("otlp", |remaining| {
+            match exporter
+                .agent
+                .post(&exporter.endpoint)
+                .timeout(remaining)
+                .set("Content-Type", "application/json")
+                .send_string(&body.to_string())
+            {
+                Ok(_) => Ok(()),
+                Err(ureq::Error::Status(code, _)) if code < 500 && code != 429 => {
+                    Err(CallError::Permanent(format!("HTTP {}", code)))
+                }
+                Err(e) => Err(CallError::Transient(e.to_string())),
+            }
+        });
+        match result {
+            Ok(()) => {
+                if let Some(metrics) = &self.metrics {
+                    metrics.inc_counter("rng_trace_spans_exported_total", &[], count as u64);
+                }
+                Ok(())
+            }
+            Err(e) => {
+                // Put the batch back in front of anything recorded meanwhile.
+                let mut finished = self.finished.lock().expect("tracer lock poisoned");
+                let newer = std::mem::replace(&mut *finished, spans);
+                finished.extend(newer);
+                let excess = finished.len().saturating_sub(MAX_BUFFERED_SPANS);
+                finished.drain(..excess);
+                Err(e)
+            }
+        }
+    }
+}
+
+fn unix_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,195 @@
+// src/tenants.rs
+
+//! Namespaces that let one operator serve several consumers (dApps).
+//!
+//! With `tenants` configured, every `POST /task/execute` must present the
+//! bearer token of one of them, and is served in that tenant's namespace:
+//!
+//! - task IDs are scoped to the tenant: two tenants may both request task
+//!   `"1"`, which the runner tracks as `a/1` and `b/1` (see [`task_key`]);
+//! - the tenant's domain tag is signed into every attestation made for it, so
+//!   a value attested for one tenant does not pass as another's;
+//! - attestations, dry runs and lifecycle events are kept in per-tenant
+//!   collections (see [`collection`]), exported and archived separately;
+//! - `max_in_flight` and `quota` per `quota_window` bound what a tenant can
+//!   ask of the operator. Quota usage is counted in memory and starts over
+//!   when the operator restarts.
+//!
+//! Without tenants, tasks are served in the default namespace as before.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::{self, TenantConfig};
+use crate::metrics::Metrics;
+
+/// Scopes `task_id` to `tenant`; the key tasks are tracked and persisted under.
+pub fn task_key(tenant: Option<&str>, task_id: &str) -> String {
+    match tenant {
+        Some(tenant) => format!("{}/{}", tenant, task_id),
+        None => task_id.to_string(),
+    }
+}
+
+/// Splits a [`task_key`] into its tenant and task ID.
+pub fn split_key(key: &str) -> (Option<&str>, &str) {
+    match key.split_once('/') {
+        Some((tenant, task_id)) => (Some(tenant), task_id),
+        None => (None, key),
+    }
+}
+
+/// Name of the partition of collection `base` holding `tenant`'s records,
+/// e.g. `attestations__dice-game`.
+pub fn collection(base: &str, tenant: Option<&str>) -> String {
+    match tenant {
+        Some(tenant) => format!("{}__{}", base, tenant),
+        None => base.to_string(),
+    }
+}
+
+/// A tenant as configured.
+#[derive(Debug, Clone)]
+pub struct Tenant {
+    pub id: String,
+    /// Signed into the tenant's attestations.
+    pub domain: String,
+    token: String,
+    max_in_flight: Option<usize>,
+    quota: Option<u64>,
+    quota_window: Duration,
+}
+
+#[derive(Default)]
+struct Usage {
+    in_flight: usize,
+    window_start: Option<Instant>,
+    admitted: u64,
+}
+
+/// The configured tenants and what each currently uses.
+pub struct Tenants {
+    tenants: Vec<Arc<Tenant>>,
+    usage: Arc<Mutex<HashMap<String, Usage>>>,
+    metrics: Arc<Metrics>,
+}
+
+/// Holds one in-flight slot of a tenant; released on drop.
+pub struct Admission {
+    tenant: Arc<Tenant>,
+    usage: Arc<Mutex<HashMap<String, Usage>>>,
+}
+
+impl Admission {
+    pub fn tenant(&self) -> &Tenant {
+        &self.tenant
+    }
+
+    /// Gives back the unit of quota, for a task that was turned down.
+    pub fn refund(&self) {
+        let mut usage = self.usage.lock().expect("tenant lock poisoned");
+        if let Some(usage) = usage.get_mut(&self.tenant.id) {
+            usage.admitted = usage.admitted.saturating_sub(1);
+        }
+    }
+}
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        let mut usage = self.usage.lock().expect("tenant lock poisoned");
+        if let Some(usage) = usage.get_mut(&self.tenant.id) {
+            usage.in_flight = usage.in_flight.saturating_sub(1);
+        }
+    }
+}
+
+impl Tenants {
+    /// Builds the tenants of the `tenants` config section.
+    pub fn from_config(configs: &[TenantConfig], metrics: Arc<Metrics>) -> Result<Self, String> {
+        let tenants = configs
+            .iter()
+            .map(|c| {
+                Ok(Arc::new(Tenant {
+                    id: c.id.clone(),
+                    domain: c.domain.clone().unwrap_or_else(|| c.id.clone()),
+                    token: c.token.clone(),
+                    max_in_flight: c.max_in_flight,
+                    quota: c.quota,
+                    quota_window: config::parse_duration(&c.quota_window)?,
+                }))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Tenants {
+            tenants,
+            usage: Arc::new(Mutex::new(HashMap::new())),
+            metrics,
+        })
+    }
+
+    /// Whether requests must authenticate as a tenant.
+    pub fn is_enabled(&self) -> bool {
+        !self.tenants.is_empty()
+    }
+
+    /// The tenant whose token is `presented`.
+    pub fn authenticate(&self, presented: &str) -> Option<Arc<Tenant>> {
+        // Compare against every token so timing does not reveal which matched.
+        let mut found = None;
+        for tenant in &self.tenants {
+            if crate::server::tokens_match(presented, &tenant.token) {
+                found = Some(Arc::clone(tenant));
+            }
+        }
+        found
+    }
+
+    /// Takes an in-flight slot and one unit of quota of `tenant`.
+    pub fn admit(&self, tenant: Arc<Tenant>) -> Result<Admission, String> {
+        let mut usage = self.usage.lock().expect("tenant lock poisoned");
+        let current = usage.entry(tenant.id.clone()).or_default();
+        let now = Instant::now();
+        if current
+            .window_start
+            .is_none_or(|start| now.duration_since(start) >= tenant.quota_window)
+        {
+            current.window_start = Some(now);
+            current.admitted = 0;
+        }
+        let refused = if tenant
+            .max_in_flight
+            .is_some_and(|max| current.in_flight >= max)
+        {
+            Some(("busy", "too many tasks in flight"))
+        } else if tenant.quota.is_some_and(|quota| current.admitted >= quota) {
+            Some(("quota_exceeded", "task quota exhausted for this window"))
+        } else {
+            None
+        };
+        if let Some((outcome, reason)) = refused {
+            self.count(&tenant.id, outcome);
+            return Err(format!("Tenant {}: {}", tenant.id, reason));
+        }
+        current.in_flight += 1;
+        current.admitted += 1;
+        self.count(&tenant.id, "admitted");
+        drop(usage);
+        Ok(Admission {
+            tenant,
+            usage: Arc::clone(&self.usage),
+        })
+    }
+
+    /// IDs of every tenant.
+    pub fn ids(&self) -> Vec<String> {
+        self.tenants.iter().map(|t| t.id.clone()).collect()
+    }
+
+    fn count(&self, tenant: &str, outcome: &str) {
+        self.metrics.inc_counter(
+            "rng_tenant_tasks_total",
+            &[("tenant", tenant), ("outcome", outcome)],
+            1,
+        );
+    }
+}
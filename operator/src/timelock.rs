@@ -0,0 +1,297 @@
+// src/timelock.rs
+
+//! Time-locked delivery: randomness fixed now, readable only later.
+//!
+//! A task requested with a [`Release`] is generated and attested as usual,
+//! but its outcome is not returned or submitted. The operator encrypts it
+//! under a fresh key and signs a [`Commitment`] to the ciphertext, the key's
+//! hash and the release condition (a Unix time, a block height of the
+//! submission chain, or both) under [`CONTEXT`]; the commitment is what the
+//! request returns. Once every release condition holds, the operator reveals
+//! the key at `GET /timelock/{task}` and submits the outcome as it would any
+//! other. Anyone holding the commitment can then decrypt the outcome with
+//! [`TimelockStatus::open`] and check that it is exactly the value committed
+//! to before the release, and verify it like any other attestation.
+//!
+//! This is an operator-scheduled release, not timelock encryption: the
+//! operator can read the value early, but it cannot change it, and withholding
+//! the key past the release is visible to every holder of the commitment.
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::attester::RngAttester;
+use crate::tasks::TaskOutcome;
+
+/// Ed25519ph context commitments are signed under.
+pub const CONTEXT: &[u8] = b"othentic-rng/timelock/v1";
+/// Collection holding sealed tasks, keyed by [`crate::tenants::task_key`].
+pub const TIMELOCKS: &str = "timelocks";
+
+const KEY_DOMAIN: &[u8] = b"othentic-rng/timelock-key/v1";
+const STREAM_DOMAIN: &[u8] = b"othentic-rng/timelock-stream/v1";
+
+/// When a sealed outcome is released: once every condition given holds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Release {
+    /// Unix time in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<u64>,
+    /// Height of the chain outcomes are submitted to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<u64>,
+}
+
+impl Release {
+    /// Whether the release has come at `now` (Unix ms) and chain height
+    /// `height`; a block condition is never met without a height.
+    pub fn is_due(&self, now: u64, height: Option<u64>) -> bool {
+        self.at.is_none_or(|at| now >= at)
+            && self
+                .block
+                .is_none_or(|block| height.is_some_and(|h| h >= block))
+    }
+
+    fn write_to(&self, data: &mut Vec<u8>) {
+        for condition in [self.at, self.block] {
+            match condition {
+                Some(value) => {
+                    data.push(1);
+                    data.extend_from_slice(&value.to_be_bytes());
+                }
+                None => data.push(0),
+            }
+        }
+    }
+}
+
+/// A signed commitment to a sealed outcome.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Commitment {
+    pub task_id: String,
+    pub release: Release,
+    /// Hex of the outcome's JSON, encrypted.
+    pub ciphertext: String,
+    /// Hex SHA-256 of the key, under a domain tag.
+    pub key_hash: String,
+    pub sealed_at: u64,
+    /// Key that signed the commitment.
+    pub public_key: String,
+    pub signature: String,
+}
+
+impl Commitment {
+    /// Encrypts `outcome` under a fresh key and commits to it; returns the
+    /// commitment and the key.
+    pub fn seal(
+        outcome: &TaskOutcome,
+        release: Release,
+        sealed_at: u64,
+        attester: &RngAttester,
+    ) -> Result<(Commitment, [u8; 32]), String> {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        let plaintext = serde_json::to_vec(outcome).map_err(|e| e.to_string())?;
+        let mut commitment = Commitment {
+            task_id: outcome.task_id.clone(),
+            release,
+            ciphertext: hex::encode(apply_keystream(&key, &plaintext)),
+            key_hash: hex::encode(key_hash(&key)),
+            sealed_at,
+            public_key: hex::encode(attester.get_public_key().as_bytes()),
+            signature: String::new(),
+        };
+        let signature = attester.sign_with_context(CONTEXT, &commitment.signed_bytes())?;
+        commitment.signature = hex::encode(signature.to_bytes());
+        Ok((commitment, key))
+    }
+
+    /// Checks the signature and returns the key that made it.
+    pub fn verify(&self) -> Result<VerifyingKey, String> {
+        let key: [u8; 32] = hex::decode(&self.public_key)
+            .map_err(|e| format!("Invalid commitment public key: {}", e))?
+            .try_into()
+            .map_err(|_| "Commitment public key must be 32 bytes".to_string())?;
+        let key = VerifyingKey::from_bytes(&key)
+            .map_err(|e| format!("Invalid commitment public key: {}", e))?;
+        let signature: [u8; 64] = hex::decode(&self.signature)
+            .map_err(|e| format!("Invalid commitment signature: {}", e))?
+            .try_into()
+            .map_err(|_| "Commitment signature must be 64 bytes".to_string())?;
+        RngAttester::verify_with_context(
+            &key,
+            CONTEXT,
+            &self.signed_bytes(),
+            &Signature::from_bytes(&signature),
+        )?;
+        Ok(key)
+    }
+
+    /// Decrypts the outcome with the hex `key`, which must be the one
+    /// committed to. The signature is not checked here.
+    pub fn open(&self, key: &str) -> Result<TaskOutcome, String> {
+        let key: [u8; 32] = hex::decode(key)
+            .map_err(|e| format!("Invalid timelock key: {}", e))?
+            .try_into()
+            .map_err(|_| "Timelock key must be 32 bytes".to_string())?;
+        if hex::encode(key_hash(&key)) != self.key_hash {
+            return Err("Timelock key does not match the commitment".to_string());
+        }
+        let ciphertext =
+            hex::decode(&self.ciphertext).map_err(|e| format!("Invalid ciphertext: {}", e))?;
+        let outcome: TaskOutcome = serde_json::from_slice(&apply_keystream(&key, &ciphertext))
+            .map_err(|e| format!("Sealed outcome does not decrypt: {}", e))?;
+        if outcome.task_id != self.task_id {
+            return Err(format!(
+                "Sealed outcome is for task {}, not {}",
+                outcome.task_id, self.task_id
+            ));
+        }
+        Ok(outcome)
+    }
+
+    /// Length-prefixed encoding of every field but the signature.
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        for field in [
+            self.task_id.as_bytes(),
+            self.ciphertext.as_bytes(),
+            self.key_hash.as_bytes(),
+        ] {
+            data.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            data.extend_from_slice(field);
+        }
+        self.release.write_to(&mut data);
+        data.extend_from_slice(&self.sealed_at.to_be_bytes());
+        data.extend_from_slice(self.public_key.as_bytes());
+        data
+    }
+}
+
+/// The body of `GET /timelock/{task}`: the commitment, and its key once
+/// released.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelockStatus {
+    pub commitment: Commitment,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub released_at: Option<u64>,
+}
+
+impl TimelockStatus {
+    /// Checks the commitment's signature and decrypts the outcome; fails
+    /// while the key is not released.
+    pub fn open(&self) -> Result<(TaskOutcome, VerifyingKey), String> {
+        let key = self.key.as_deref().ok_or_else(|| {
+            format!(
+                "Timelock of task {} is not released yet",
+                self.commitment.task_id
+            )
+        })?;
+        let public_key = self.commitment.verify()?;
+        Ok((self.commitment.open(key)?, public_key))
+    }
+}
+
+fn key_hash(key: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(KEY_DOMAIN)
+        .chain_update(key)
+        .finalize()
+        .into()
+}
+
+/// XORs `data` with SHA-256 in counter mode under `key`.
+fn apply_keystream(key: &[u8; 32], data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    for (counter, chunk) in data.chunks(32).enumerate() {
+        let block = Sha256::new()
+            .chain_update(STREAM_DOMAIN)
+            .chain_update(key)
+            .chain_update((counter as u64).to_be_bytes())
+            .finalize();
+        output.extend(chunk.iter().zip(block.iter()).map(|(b, k)| b ^ k));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attester::AttestationPayload;
+
+    fn sealed() -> (Commitment, [u8; 32], TaskOutcome, RngAttester) {
+        let attester = RngAttester::new().unwrap();
+        let attestation = attester
+            .attest_payload(AttestationPayload::new(vec![42; 32]))
+            .unwrap();
+        let outcome = TaskOutcome::from_attestation("task-1", &attestation, &attester);
+        let release = Release {
+            at: Some(2_000),
+            block: None,
+        };
+        let (commitment, key) = Commitment::seal(&outcome, release, 1_000, &attester).unwrap();
+        (commitment, key, outcome, attester)
+    }
+
+    #[test]
+    fn sealed_outcomes_open_with_their_key() {
+        let (commitment, key, outcome, attester) = sealed();
+        assert_eq!(&commitment.verify().unwrap(), attester.get_public_key());
+        let json = |outcome: &TaskOutcome| serde_json::to_value(outcome).unwrap();
+        let opened = commitment.open(&hex::encode(key)).unwrap();
+        assert_eq!(json(&opened), json(&outcome));
+        assert!(!commitment.ciphertext.contains(&hex::encode([42; 32])));
+
+        let mut status = TimelockStatus {
+            commitment,
+            key: None,
+            released_at: None,
+        };
+        assert!(status.open().is_err());
+        status.key = Some(hex::encode(key));
+        assert_eq!(json(&status.open().unwrap().0), json(&outcome));
+    }
+
+    #[test]
+    fn wrong_keys_and_tampering_are_refused() {
+        let (commitment, key, ..) = sealed();
+        assert!(commitment.open(&hex::encode([0u8; 32])).is_err());
+        assert!(commitment.open("00").is_err());
+
+        let mut retargeted = commitment.clone();
+        retargeted.task_id = "task-2".to_string();
+        assert!(retargeted.verify().is_err());
+        assert!(retargeted.open(&hex::encode(key)).is_err());
+
+        let mut advanced = commitment.clone();
+        advanced.release.at = Some(1_500);
+        assert!(advanced.verify().is_err());
+
+        let mut flipped = commitment;
+        let mut ciphertext = hex::decode(&flipped.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        flipped.ciphertext = hex::encode(ciphertext);
+        assert!(flipped.verify().is_err());
+    }
+
+    #[test]
+    fn releases_wait_for_every_condition() {
+        let both = Release {
+            at: Some(100),
+            block: Some(7),
+        };
+        assert!(!both.is_due(99, Some(7)));
+        assert!(!both.is_due(100, Some(6)));
+        assert!(!both.is_due(100, None));
+        assert!(both.is_due(100, Some(7)));
+        assert!(Release::default().is_due(0, None));
+    }
+}
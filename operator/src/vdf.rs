@@ -0,0 +1,257 @@
+// src/vdf.rs
+
+//! Wesolowski verifiable delay function over the RSA-2048 group.
+//!
+//! Evaluating the VDF on a seed takes `iterations` sequential modular
+//! squarings, which cannot be parallelised; checking the accompanying proof
+//! takes two small exponentiations. Running an attested seed through the VDF
+//! means nobody, the operator included, can learn the final output sooner
+//! than the delay allows, so grinding over candidate seeds is impractical.
+//!
+//! The group is `Z/NZ` for the RSA Factoring Challenge modulus RSA-2048, whose
+//! factorisation is not publicly known, taken modulo `±1`: `-1` is an
+//! element of known order, and since the challenge prime is odd, `(N-y, N-π)`
+//! would pass the check as well as `(y, π)`. Outputs and proofs are therefore
+//! published as the smaller of `v` and `N - v`, and [`verify`] rejects the
+//! other representative, so every seed has exactly one output.
+
+use std::sync::OnceLock;
+
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use sha2::{Digest, Sha256};
+
+use crate::primes;
+
+/// Decimal digits of the RSA-2048 challenge modulus.
+const RSA_2048: &str = "25195908475657893494027183240048398571429282126204032027777137836043662020707595556264018525880784406918290641249515082189298559149176184502808489120072844992687392807287776735971418347270261896375014971824691165077613379859095700097330459748808428401797429100642458691817195118746121515172654632282216869987549182422433637259085141865462043576798423387184774447920739934236584823824281198163815010674810451660377306056201619676256133844143603833904414952634432190114657544454178424020924616515723350778707749817125772467962926386356373289912154831438167899885040445364023527381951378636564391212010397122822120720357";
+
+const GROUP_DOMAIN: &[u8] = b"othentic-rng/vdf/group/v1";
+const PRIME_DOMAIN: &[u8] = b"othentic-rng/vdf/prime/v1";
+const OUTPUT_DOMAIN: &[u8] = b"othentic-rng/vdf/output/v1";
+
+/// Upper bound on iterations accepted by [`evaluate`] and [`verify`].
+pub const MAX_ITERATIONS: u64 = 1 << 32;
+
+fn modulus() -> &'static BigUint {
+    static MODULUS: OnceLock<BigUint> = OnceLock::new();
+    MODULUS.get_or_init(|| {
+        BigUint::parse_bytes(RSA_2048.as_bytes(), 10).expect("RSA-2048 constant is valid")
+    })
+}
+
+/// Result of a VDF evaluation: `output = ±x^(2^iterations)` and its proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VdfProof {
+    /// The value the VDF was evaluated on.
+    pub seed: Vec<u8>,
+    pub iterations: u64,
+    /// Big-endian group element `y`.
+    pub output: Vec<u8>,
+    /// Big-endian group element `π`.
+    pub proof: Vec<u8>,
+}
+
+impl VdfProof {
+    /// Derives `len` bytes of randomness from the VDF output.
+    pub fn randomness(&self, len: usize) -> Vec<u8> {
+        let mut out = Vec::with_capacity(len);
+        let mut counter: u32 = 0;
+        while out.len() < len {
+            let mut hasher = Sha256::new();
+            hasher.update(OUTPUT_DOMAIN);
+            hasher.update(counter.to_be_bytes());
+            hasher.update(&self.output);
+            let block = hasher.finalize();
+            let take = (len - out.len()).min(block.len());
+            out.extend_from_slice(&block[..take]);
+            counter += 1;
+        }
+        out
+    }
+}
+
+/// Evaluates the VDF on `seed` for `iterations` squarings and proves it.
+pub fn evaluate(seed: &[u8], iterations: u64) -> Result<VdfProof, String> {
+    check_iterations(iterations)?;
+    let n = modulus();
+    let x = hash_to_group(seed);
+
+    let mut y = x.clone();
+    for _ in 0..iterations {
+        y = (&y * &y) % n;
+    }
+    let y = canonical(y);
+
+    // π = x^floor(2^T / l), computed bit by bit via long division of 2^T by l.
+    let l = hash_to_prime(&x, &y, iterations);
+    let mut pi = BigUint::one();
+    let mut r = BigUint::one();
+    for _ in 0..iterations {
+        r <<= 1;
+        pi = (&pi * &pi) % n;
+        if r >= l {
+            r -= &l;
+            pi = (&pi * &x) % n;
+        }
+    }
+
+    Ok(VdfProof {
+        seed: seed.to_vec(),
+        iterations,
+        output: y.to_bytes_be(),
+        proof: canonical(pi).to_bytes_be(),
+    })
+}
+
+/// Checks that `proof` is a valid evaluation for its seed and iteration count.
+pub fn verify(proof: &VdfProof) -> Result<(), String> {
+    check_iterations(proof.iterations)?;
+    let n = modulus();
+    let y = BigUint::from_bytes_be(&proof.output);
+    let pi = BigUint::from_bytes_be(&proof.proof);
+    if y.is_zero() || pi.is_zero() || &y >= n || &pi >= n {
+        return Err("VDF output or proof is not a valid group element".to_string());
+    }
+    if canonical(y.clone()) != y || canonical(pi.clone()) != pi {
+        return Err("VDF output or proof is not in canonical form".to_string());
+    }
+
+    let x = hash_to_group(&proof.seed);
+    let l = hash_to_prime(&x, &y, proof.iterations);
+    let r = BigUint::from(2u32).modpow(&BigUint::from(proof.iterations), &l);
+    let lhs = (pi.modpow(&l, n) * x.modpow(&r, n)) % n;
+    if canonical(lhs) != y {
+        return Err("VDF proof does not verify".to_string());
+    }
+    Ok(())
+}
+
+fn check_iterations(iterations: u64) -> Result<(), String> {
+    if iterations == 0 || iterations > MAX_ITERATIONS {
+        return Err(format!(
+            "VDF iterations must be between 1 and {}",
+            MAX_ITERATIONS
+        ));
+    }
+    Ok(())
+}
+
+/// The representative of `{v, N - v}` published: the smaller one.
+fn canonical(v: BigUint) -> BigUint {
+    let negated = modulus() - &v;
+    if negated < v {
+        negated
+    } else {
+        v
+    }
+}
+
+/// Maps `seed` to an element of the group, never 0 or 1.
+fn hash_to_group(seed: &[u8]) -> BigUint {
+    let n = modulus();
+    let mut material = Vec::new();
+    let mut counter: u32 = 0;
+    while material.len() * 8 < n.bits() as usize + 128 {
+        let mut hasher = Sha256::new();
+        hasher.update(GROUP_DOMAIN);
+        hasher.update(counter.to_be_bytes());
+        hasher.update((seed.len() as u64).to_be_bytes());
+        hasher.update(seed);
+        material.extend_from_slice(&hasher.finalize());
+        counter += 1;
+    }
+    let x = BigUint::from_bytes_be(&material) % n;
+    if x <= BigUint::one() {
+        return BigUint::from(2u32);
+    }
+    x
+}
+
+/// Fiat–Shamir challenge: a 256-bit prime bound to `x`, `y` and `iterations`.
+fn hash_to_prime(x: &BigUint, y: &BigUint, iterations: u64) -> BigUint {
+    let mut counter: u64 = 0;
+    loop {
+        let mut hasher = Sha256::new();
+        hasher.update(PRIME_DOMAIN);
+        hasher.update(x.to_bytes_be());
+        hasher.update(y.to_bytes_be());
+        hasher.update(iterations.to_be_bytes());
+        hasher.update(counter.to_be_bytes());
+        let mut bytes: [u8; 32] = hasher.finalize().into();
+        bytes[0] |= 0x80;
+        bytes[31] |= 0x01;
+        let candidate = BigUint::from_bytes_be(&bytes);
+        if primes::is_probable_prime(&candidate, primes::DEFAULT_ROUNDS) {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ITERATIONS: u64 = 64;
+
+    #[test]
+    fn evaluation_verifies() {
+        let proof = evaluate(b"seed", ITERATIONS).unwrap();
+        verify(&proof).unwrap();
+        assert_eq!(proof, evaluate(b"seed", ITERATIONS).unwrap());
+        assert_eq!(proof.randomness(48).len(), 48);
+    }
+
+    #[test]
+    fn output_is_canonical() {
+        let proof = evaluate(b"canonical", ITERATIONS).unwrap();
+        let half = modulus() >> 1;
+        assert!(BigUint::from_bytes_be(&proof.output) <= half);
+        assert!(BigUint::from_bytes_be(&proof.proof) <= half);
+    }
+
+    #[test]
+    fn negated_pair_is_rejected() {
+        let proof = evaluate(b"negate", ITERATIONS).unwrap();
+        let n = modulus();
+        let x = hash_to_group(&proof.seed);
+        // The other representative of the output, with a proof under the
+        // challenge prime it hashes to.
+        let y = n - BigUint::from_bytes_be(&proof.output);
+        let l = hash_to_prime(&x, &y, ITERATIONS);
+        let two_t = BigUint::one() << ITERATIONS;
+        let mut pi = x.modpow(&(&two_t / &l), n);
+        let r = &two_t % &l;
+        if (pi.modpow(&l, n) * x.modpow(&r, n)) % n != y {
+            pi = n - pi;
+        }
+        // Without the canonical form this pair passes the group check.
+        assert_eq!((pi.modpow(&l, n) * x.modpow(&r, n)) % n, y);
+        let negated = VdfProof {
+            output: y.to_bytes_be(),
+            proof: pi.to_bytes_be(),
+            ..proof
+        };
+        assert!(verify(&negated).is_err());
+    }
+
+    #[test]
+    fn tampered_proofs_are_rejected() {
+        let proof = evaluate(b"tamper", ITERATIONS).unwrap();
+        let mut output = proof.clone();
+        output.output[0] ^= 0x01;
+        assert!(verify(&output).is_err());
+        let other_seed = VdfProof {
+            seed: b"other".to_vec(),
+            ..proof.clone()
+        };
+        assert!(verify(&other_seed).is_err());
+        let fewer = VdfProof {
+            iterations: ITERATIONS - 1,
+            ..proof
+        };
+        assert!(verify(&fewer).is_err());
+        assert!(evaluate(b"seed", 0).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+// src/vrf.rs
+
+//! ECVRF-EDWARDS25519-SHA512-TAI verifiable random function (RFC 9381).
+//!
+//! The VRF key is the operator's Ed25519 signing key, so the attestation
+//! public key doubles as the VRF public key. For a given key and input the
+//! output is unique: re-running a request, or fulfilling it from another node
+//! holding the same key, always yields the same randomness, and anyone with
+//! the public key can check the proof without learning anything about other
+//! inputs' outputs.
+
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use sha2::{Digest, Sha256, Sha512};
+
+/// Length of an encoded proof: `Gamma (32) || c (16) || s (32)`.
+pub const PROOF_LEN: usize = 80;
+
+const SUITE: u8 = 0x03;
+const OUTPUT_DOMAIN: &[u8] = b"othentic-rng/vrf/output/v1";
+
+/// A VRF evaluation on `input`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VrfProof {
+    pub input: Vec<u8>,
+    pub proof: Vec<u8>,
+}
+
+/// Evaluates the VRF on `input` with the Ed25519 secret key `secret`.
+///
+/// Returns the proof together with the 64-byte VRF output.
+pub fn prove(secret: &[u8; 32], input: &[u8]) -> (VrfProof, [u8; 64]) {
+    let expanded = Sha512::digest(secret);
+    let mut scalar_bytes = [0u8; 32];
+    scalar_bytes.copy_from_slice(&expanded[..32]);
+    scalar_bytes[0] &= 248;
+    scalar_bytes[31] &= 127;
+    scalar_bytes[31] |= 64;
+    let x = Scalar::from_bytes_mod_order(scalar_bytes);
+    let public = EdwardsPoint::mul_base(&x);
+
+    let h = encode_to_curve(&public, input);
+    let gamma = h * x;
+    let mut nonce = Sha512::new();
+    nonce.update(&expanded[32..]);
+    nonce.update(h.compress().as_bytes());
+    let k = Scalar::from_bytes_mod_order_wide(&nonce.finalize().into());
+    let c = challenge(&[&public, &h, &gamma, &EdwardsPoint::mul_base(&k), &(h * k)]);
+    let s = k + challenge_scalar(&c) * x;
+
+    let mut proof = Vec::with_capacity(PROOF_LEN);
+    proof.extend_from_slice(gamma.compress().as_bytes());
+    proof.extend_from_slice(&c);
+    proof.extend_from_slice(s.as_bytes());
+    (
+        VrfProof {
+            input: input.to_vec(),
+            proof,
+        },
+        proof_to_hash(&gamma),
+    )
+}
+
+/// Checks `proof` against the Ed25519 public key and returns the VRF output.
+pub fn verify(public_key: &[u8; 32], proof: &VrfProof) -> Result<[u8; 64], String> {
+    let public = decode_point(public_key).ok_or("Invalid VRF public key")?;
+    if public.is_small_order() {
+        return Err("Invalid VRF public key".to_string());
+    }
+    if proof.proof.len() != PROOF_LEN {
+        return Err(format!("VRF proof must be {} bytes", PROOF_LEN));
+    }
+    let gamma = decode_point(&proof.proof[..32]).ok_or("Invalid VRF proof point")?;
+    let c: [u8; 16] = proof.proof[32..48].try_into().expect("length checked");
+    let s_bytes: [u8; 32] = proof.proof[48..].try_into().expect("length checked");
+    let s = Option::<Scalar>::from(Scalar::from_canonical_bytes(s_bytes))
+        .ok_or("Invalid VRF proof scalar")?;
+
+    let h = encode_to_curve(&public, &proof.input);
+    let c_scalar = challenge_scalar(&c);
+    let u = EdwardsPoint::vartime_double_scalar_mul_basepoint(&-c_scalar, &public, &s);
+    let v = h * s - gamma * c_scalar;
+    if challenge(&[&public, &h, &gamma, &u, &v]) != c {
+        return Err("VRF proof verification failed".to_string());
+    }
+    Ok(proof_to_hash(&gamma))
+}
+
+/// Derives `len` bytes of randomness from a VRF output.
+pub fn randomness(output: &[u8; 64], len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len);
+    let mut counter: u32 = 0;
+    while out.len() < len {
+        let mut hasher = Sha256::new();
+        hasher.update(OUTPUT_DOMAIN);
+        hasher.update(counter.to_be_bytes());
+        hasher.update(output);
+        let block = hasher.finalize();
+        let take = (len - out.len()).min(block.len());
+        out.extend_from_slice(&block[..take]);
+        counter += 1;
+    }
+    out
+}
+
+/// Decodes a canonically encoded Edwards point.
+fn decode_point(bytes: &[u8]) -> Option<EdwardsPoint> {
+    let compressed = CompressedEdwardsY::from_slice(bytes).ok()?;
+    let point = compressed.decompress()?;
+    (point.compress() == compressed).then_some(point)
+}
+
+/// Try-and-increment hash to the curve (RFC 9381, section 5.4.1.1).
+fn encode_to_curve(public: &EdwardsPoint, input: &[u8]) -> EdwardsPoint {
+    let encoded = public.compress();
+    for counter in 0..=u8::MAX {
+        let mut hasher = Sha512::new();
+        hasher.update([SUITE, 0x01]);
+        hasher.update(encoded.as_bytes());
+        hasher.update(input);
+        hasher.update([counter, 0x00]);
+        let hash = hasher.finalize();
+        if let Some(point) = CompressedEdwardsY::from_slice(&hash[..32])
+            .ok()
+            .and_then(|c| c.decompress())
+        {
+            return point.mul_by_cofactor();
+        }
+    }
+    unreachable!("try-and-increment finds a point with overwhelming probability")
+}
+
+/// Challenge generation (RFC 9381, section 5.4.3), truncated to 16 bytes.
+fn challenge(points: &[&EdwardsPoint]) -> [u8; 16] {
+    let mut hasher = Sha512::new();
+    hasher.update([SUITE, 0x02]);
+    for point in points {
+        hasher.update(point.compress().as_bytes());
+    }
+    hasher.update([0x00]);
+    let hash = hasher.finalize();
+    hash[..16].try_into().expect("SHA-512 output is 64 bytes")
+}
+
+fn challenge_scalar(c: &[u8; 16]) -> Scalar {
+    let mut bytes = [0u8; 32];
+    bytes[..16].copy_from_slice(c);
+    Scalar::from_bytes_mod_order(bytes)
+}
+
+fn proof_to_hash(gamma: &EdwardsPoint) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    hasher.update([SUITE, 0x03]);
+    hasher.update(gamma.mul_by_cofactor().compress().as_bytes());
+    hasher.update([0x00]);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ECVRF-EDWARDS25519-SHA512-TAI examples 16 to 18 of RFC 9381,
+    /// appendix B.3: secret key, public key, alpha, pi and beta.
+    const VECTORS: [[&str; 5]; 3] = [
+        [
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            "",
+            "8657106690b5526245a92b003bb079ccd1a92130477671f6fc01ad16f26f723f26f8a57ccaed74ee1b190bed1f479d9727d2d0f9b005a6e456a35d4fb0daab1268a1b0db10836d9826a528ca76567805",
+            "90cf1df3b703cce59e2a35b925d411164068269d7b2d29f3301c03dd757876ff66b71dda49d2de59d03450451af026798e8f81cd2e333de5cdf4f3e140fdd8ae",
+        ],
+        [
+            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            "72",
+            "f3141cd382dc42909d19ec5110469e4feae18300e94f304590abdced48aed5933bf0864a62558b3ed7f2fea45c92a465301b3bbf5e3e54ddf2d935be3b67926da3ef39226bbc355bdc9850112c8f4b02",
+            "eb4440665d3891d668e7e0fcaf587f1b4bd7fbfe99d0eb2211ccec90496310eb5e33821bc613efb94db5e5b54c70a848a0bef4553a41befc57663b56373a5031",
+        ],
+        [
+            "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+            "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+            "af82",
+            "9bc0f79119cc5604bf02d23b4caede71393cedfbb191434dd016d30177ccbf8096bb474e53895c362d8628ee9f9ea3c0e52c7a5c691b6c18c9979866568add7a2d41b00b05081ed0f58ee5e31b3a970e",
+            "645427e5d00c62a23fb703732fa5d892940935942101e456ecca7bb217c61c452118fec1219202a0edcf038bb6373241578be7217ba85a2687f7a0310b2df19f",
+        ],
+    ];
+
+    fn key(hex_key: &str) -> [u8; 32] {
+        hex::decode(hex_key).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn rfc9381_vectors() {
+        for [secret, public, alpha, pi, beta] in VECTORS {
+            let (proof, output) = prove(&key(secret), &hex::decode(alpha).unwrap());
+            assert_eq!(hex::encode(&proof.proof), pi);
+            assert_eq!(hex::encode(output), beta);
+            assert_eq!(hex::encode(verify(&key(public), &proof).unwrap()), beta);
+        }
+    }
+
+    #[test]
+    fn forged_proofs_are_rejected() {
+        let [secret, public, ..] = VECTORS[2];
+        let (proof, _) = prove(&key(secret), b"input");
+        for byte in [0, 40, 70] {
+            let mut forged = proof.clone();
+            forged.proof[byte] ^= 1;
+            assert!(verify(&key(public), &forged).is_err(), "byte {}", byte);
+        }
+        let mut other_input = proof.clone();
+        other_input.input = b"other".to_vec();
+        assert!(verify(&key(public), &other_input).is_err());
+        assert!(verify(&key(VECTORS[0][1]), &proof).is_err());
+        let mut short = proof;
+        short.proof.pop();
+        assert!(verify(&key(public), &short).is_err());
+    }
+
+    #[test]
+    fn randomness_expands_the_output() {
+        let output = [5u8; 64];
+        let long = randomness(&output, 100);
+        assert_eq!(long.len(), 100);
+        assert_eq!(randomness(&output, 40), long[..40]);
+        assert_ne!(randomness(&[6u8; 64], 40), long[..40]);
+    }
+}